@@ -1,5 +1,40 @@
 extern crate lalrpop;
 
+use std::fs;
+use std::path::{Path, PathBuf};
+
 fn main() {
     lalrpop::process_root().unwrap();
+    write_bundled_sources();
+}
+
+/// Writes the table of library sources to embed into the binary, one entry per .monoteny file in the monoteny directory.
+fn write_bundled_sources() {
+    let root = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("monoteny");
+    let mut files = vec![];
+    collect_sources(&root, &mut files);
+    files.sort();
+
+    let mut table = String::from("&[\n");
+    for file in files {
+        let key = file.strip_prefix(&root).unwrap().with_extension("");
+        let key = key.components().map(|component| component.as_os_str().to_str().unwrap()).collect::<Vec<_>>().join("/");
+        table.push_str(&format!("    ({:?}, include_str!({:?})),\n", key, file.to_str().unwrap()));
+    }
+    table.push(']');
+
+    let out_path = Path::new(&std::env::var("OUT_DIR").unwrap()).join("bundled_sources.rs");
+    fs::write(out_path, table).unwrap();
+}
+
+fn collect_sources(directory: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_sources(&path, files);
+        }
+        else if path.extension().is_some_and(|extension| extension == "monoteny") {
+            files.push(path);
+        }
+    }
 }
@@ -0,0 +1,67 @@
+//! Prints the call graph of Monoteny files in DOT format, using only the public analysis API.
+//!
+//! cargo run --example call_graph -- test-code/hello_world.monoteny | dot -Tsvg > calls.svg
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use monoteny::analysis::AnalyzedProgram;
+use monoteny::error::print_errors;
+
+fn main() -> ExitCode {
+    let paths = std::env::args().skip(1).map(PathBuf::from).collect::<Vec<_>>();
+    if paths.is_empty() {
+        eprintln!("Usage: call_graph <PATH>...");
+        return ExitCode::FAILURE
+    }
+
+    match monoteny::analyze(&paths) {
+        Ok(program) => {
+            print!("{}", call_graph(&program));
+            ExitCode::SUCCESS
+        }
+        Err(errors) => {
+            print_errors(&errors);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Functions of the analyzed files are boxes, grouped by module; the functions they call from
+///  other modules are ellipses.
+fn call_graph(program: &AnalyzedProgram) -> String {
+    let mut dot = String::from("digraph calls {\n");
+    let mut declared = HashSet::new();
+    let mut edges = vec![];
+
+    for module in program.modules().filter(|module| module.is_entry()) {
+        dot.push_str(&format!("    subgraph \"cluster_{}\" {{\n        label=\"{}\";\n", module.name(), module.name()));
+        for function in module.functions() {
+            dot.push_str(&format!("        \"{}\" [label=\"{}\", shape=box];\n", function.id(), function.name()));
+            declared.insert(function.id());
+            for call in function.calls() {
+                edges.push((function.id(), call.callee()));
+            }
+        }
+        dot.push_str("    }\n");
+    }
+
+    let mut external = HashSet::new();
+    for (_, callee) in edges.iter() {
+        if !declared.contains(callee) && external.insert(*callee) {
+            let name = program.function(*callee).map_or("?", |function| function.name());
+            dot.push_str(&format!("    \"{}\" [label=\"{}\"];\n", callee, name));
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for (caller, callee) in edges {
+        if seen.insert((caller, callee)) {
+            dot.push_str(&format!("    \"{}\" -> \"{}\";\n", caller, callee));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
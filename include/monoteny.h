@@ -0,0 +1,64 @@
+// C API of the Monoteny interpreter. Build with:
+//   cargo build --lib --release --features ffi
+// This produces a shared and a static library in target/release.
+
+#ifndef MONOTENY_H
+#define MONOTENY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MonotenyRuntime MonotenyRuntime;
+
+// UTF-8 text with an explicit length. It is not null-terminated.
+typedef struct MonotenyString {
+    const uint8_t *ptr;
+    size_t len;
+} MonotenyString;
+
+typedef enum MonotenyTag {
+    MONOTENY_NONE = 0,
+    MONOTENY_INT = 1,
+    MONOTENY_FLOAT = 2,
+    MONOTENY_STRING = 3,
+    MONOTENY_ERROR = 4,
+} MonotenyTag;
+
+// Only the field matching the tag is set.
+// MONOTENY_INT holds every integer type, and MONOTENY_FLOAT both float types.
+// Other values are tagged MONOTENY_NONE.
+typedef struct MonotenyResult {
+    MonotenyTag tag;
+    int64_t int_value;
+    double float_value;
+    MonotenyString string;
+} MonotenyResult;
+
+typedef void (*MonotenyOutputCallback)(void *user_data, const uint8_t *text, size_t len);
+
+// Creates a runtime with the bundled libraries. Returns NULL if that fails.
+MonotenyRuntime *monoteny_runtime_new(void);
+
+// Frees a runtime created by monoteny_runtime_new. NULL is ignored.
+void monoteny_runtime_free(MonotenyRuntime *runtime);
+
+// Passes everything the evaluated code prints to the callback, along with user_data.
+// Without a callback (NULL), output goes to stdout.
+void monoteny_set_output_callback(MonotenyRuntime *runtime, MonotenyOutputCallback callback, void *user_data);
+
+// Runs the source as the body of main!, with common imported.
+// If the last statement is a number or a string, the result holds its value.
+// On failure, the result is tagged MONOTENY_ERROR, and its string and out_err (if not NULL)
+// hold the rendered errors. Strings in the result stay valid until the next evaluation on
+// the runtime, or until it is freed.
+MonotenyResult monoteny_eval(MonotenyRuntime *runtime, const uint8_t *source, size_t len, MonotenyString *out_err);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,306 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use uuid::Uuid;
+
+#[cfg(feature = "native")]
+use crate::error::RResult;
+use crate::error::RuntimeError;
+use crate::interpreter::runtime::Runtime;
+use crate::program::expression_tree::ExpressionOperation;
+use crate::program::functions::{FunctionHead, ParameterKey};
+use crate::program::global::{FunctionImplementation, FunctionLogic};
+use crate::program::module::Module;
+#[cfg(feature = "native")]
+use crate::program::module::module_name;
+use crate::program::traits::TraitConformanceRule;
+use crate::program::types::TypeProto;
+use crate::resolver::query::ExpressionIndex;
+#[cfg(feature = "native")]
+use crate::static_analysis::lint::CustomLint;
+
+/// A resolved program, for tools that inspect code without running or transpiling it.
+/// Everything is exposed through read-only views, so the resolver's state stays consistent.
+pub struct AnalyzedProgram {
+    runtime: Box<Runtime>,
+    /// The analyzed files' modules, in the order they were passed.
+    entries: Vec<Module>,
+}
+
+/// Resolves the files, using the prelude of the first file's project.
+/// Each file is loaded as a module named after its file stem.
+#[cfg(feature = "native")]
+pub fn analyze(paths: &[PathBuf]) -> RResult<AnalyzedProgram> {
+    analyze_with_lints(paths, vec![])
+}
+
+/// Like analyze, but also runs the lints on every function body, including those of the modules the files use.
+/// Their warnings are reported with each module's warnings, and their errors fail the analysis.
+#[cfg(feature = "native")]
+pub fn analyze_with_lints(paths: &[PathBuf], lints: Vec<CustomLint>) -> RResult<AnalyzedProgram> {
+    let mut runtime = Runtime::new()?;
+    runtime.lints = lints;
+    runtime.repository.add_bundled("common");
+    if let Some(path) = paths.first() {
+        runtime.load_project_for(path)?;
+    }
+
+    let mut entries = vec![];
+    for path in paths {
+        let stem = path.file_stem().map_or("main".into(), |stem| stem.to_string_lossy());
+        entries.push(*runtime.load_file_as_module(path, module_name(&stem))?);
+    }
+
+    Ok(AnalyzedProgram { runtime, entries })
+}
+
+impl AnalyzedProgram {
+    /// The analyzed files' modules, followed by the modules they use, sorted by name.
+    pub fn modules(&self) -> impl Iterator<Item=ModuleView<'_>> {
+        let entries = self.entries.iter().map(|module| (module, true));
+        let dependencies = self.runtime.source.module_by_name.values()
+            .sorted_by_key(|module| &module.name)
+            .map(|module| (module.as_ref(), false));
+
+        entries.chain(dependencies).map(|(module, is_entry)| ModuleView { program: self, module, is_entry })
+    }
+
+    /// Any function known to the program, including builtins without an implementation.
+    pub fn function(&self, id: Uuid) -> Option<FunctionView<'_>> {
+        self.runtime.source.fn_heads.get(&id).map(|head| FunctionView::new(self, head))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ModuleView<'a> {
+    program: &'a AnalyzedProgram,
+    module: &'a Module,
+    is_entry: bool,
+}
+
+impl<'a> ModuleView<'a> {
+    /// The module's name, like "common.debug".
+    pub fn name(&self) -> String {
+        self.module.name.join(".")
+    }
+
+    /// The file the module was loaded from, if it was loaded from a file.
+    pub fn path(&self) -> Option<&'a Path> {
+        self.program.runtime.module_paths.get(&self.module.name).map(PathBuf::as_path)
+    }
+
+    /// Whether the module is one of the analyzed files, rather than one they use.
+    pub fn is_entry(&self) -> bool {
+        self.is_entry
+    }
+
+    /// Warnings found while resolving the module, including those of built-in and custom lints.
+    pub fn warnings(&self) -> &'a [RuntimeError] {
+        &self.module.warnings
+    }
+
+    /// The functions implemented in the module's source.
+    pub fn functions(&self) -> impl Iterator<Item=FunctionView<'a>> + 'a {
+        let program = self.program;
+        self.module.implemented_functions.iter().map(move |head| FunctionView::new(program, head))
+    }
+
+    /// The trait conformances the module declares, sorted by trait name.
+    /// This includes the implicit conformance of each of its functions to Function.
+    pub fn conformances(&self) -> impl Iterator<Item=ConformanceView<'a>> + 'a {
+        let program = self.program;
+        self.module.trait_conformance.conformance_rules.iter()
+            .sorted_by_key(|(trait_, _)| &trait_.name)
+            .flat_map(|(_, rules)| rules.iter())
+            .map(move |rule| ConformanceView { program, rule })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FunctionView<'a> {
+    program: &'a AnalyzedProgram,
+    head: &'a FunctionHead,
+    implementation: Option<&'a FunctionImplementation>,
+}
+
+impl<'a> FunctionView<'a> {
+    fn new(program: &'a AnalyzedProgram, head: &'a Arc<FunctionHead>) -> FunctionView<'a> {
+        let implementation = match program.runtime.source.fn_logic.get(head) {
+            Some(FunctionLogic::Implementation(implementation)) => Some(implementation.as_ref()),
+            _ => None,
+        };
+        FunctionView { program, head, implementation }
+    }
+
+    /// Unique to the function, even among functions of the same name.
+    pub fn id(&self) -> Uuid {
+        self.head.function_id
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.program.runtime.source.fn_representations.get(self.head).map_or("<anonymous>", |representation| representation.name.as_str())
+    }
+
+    pub fn parameters(&self) -> impl Iterator<Item=ParameterView<'a>> + 'a {
+        self.head.interface.parameters.iter().map(|parameter| ParameterView {
+            label: match &parameter.external_key {
+                ParameterKey::Positional => None,
+                ParameterKey::Name(name) => Some(name.as_str()),
+            },
+            name: parameter.internal_name.as_str(),
+            type_: TypeView(Arc::clone(&parameter.type_)),
+        })
+    }
+
+    pub fn return_type(&self) -> TypeView {
+        TypeView(Arc::clone(&self.head.interface.return_type))
+    }
+
+    /// The module declaring the function, and the declaration's byte range in its source.
+    /// Builtins, and functions the resolver creates itself, have no declaration.
+    pub fn declaration(&self) -> Option<(ModuleView<'a>, Range<usize>)> {
+        let site = self.program.runtime.source.fn_declarations.get(self.head)?;
+        let module = self.program.modules().find(|module| module.module.name == site.module)?;
+        Some((module, site.range.clone()))
+    }
+
+    /// Whether the function has a body, as opposed to being a builtin or a trait requirement.
+    pub fn is_implemented(&self) -> bool {
+        self.implementation.is_some()
+    }
+
+    /// Whether the function is free of side effects.
+    pub fn is_pure(&self) -> bool {
+        !self.program.runtime.source.impure_functions.contains_key(self.head)
+    }
+
+    /// The calls in the function's body, in the order of its expression tree.
+    pub fn calls(&self) -> Vec<CallView> {
+        let Some(implementation) = self.implementation else {
+            return vec![]
+        };
+        let tree = &implementation.expression_tree;
+
+        tree.deep_children(tree.root).into_iter()
+            .filter_map(|expression_id| {
+                let callees = match tree.values.get(&expression_id)? {
+                    ExpressionOperation::FunctionCall(binding) => vec![binding.function.function_id],
+                    ExpressionOperation::PairwiseOperations { calls } => calls.iter().map(|binding| binding.function.function_id).collect_vec(),
+                    _ => return None,
+                };
+                let range = implementation.expression_ranges.get(&expression_id).cloned();
+                let type_ = implementation.type_forest.resolve_binding_alias(&expression_id).ok().map(TypeView);
+                Some(callees.into_iter().map(move |callee| CallView { callee, range: range.clone(), type_: type_.clone() }))
+            })
+            .flatten()
+            .collect_vec()
+    }
+
+    /// Where each statement of the function's body was written, in the module's source.
+    pub fn statement_ranges(&self) -> Vec<Range<usize>> {
+        self.implementation.map_or(vec![], |implementation| {
+            implementation.statement_ranges.values().cloned().sorted_by_key(|range| range.start).collect_vec()
+        })
+    }
+
+    /// The type of the innermost expression at the byte offset in the module's source.
+    pub fn type_at(&self, offset: usize) -> Option<TypeView> {
+        let implementation = self.implementation?;
+        let (_, expression) = ExpressionIndex::new(implementation).innermost_at(offset)?.clone();
+        implementation.type_forest.resolve_binding_alias(&expression).ok().map(TypeView)
+    }
+}
+
+pub struct ParameterView<'a> {
+    /// The label callers use for the argument, or None if it is positional.
+    pub label: Option<&'a str>,
+    /// The name of the parameter's variable inside the function.
+    pub name: &'a str,
+    pub type_: TypeView,
+}
+
+/// A call to a function, from inside another function's body.
+#[derive(Clone, Debug)]
+pub struct CallView {
+    callee: Uuid,
+    range: Option<Range<usize>>,
+    type_: Option<TypeView>,
+}
+
+impl CallView {
+    /// The called function, which can be looked up with AnalyzedProgram::function.
+    pub fn callee(&self) -> Uuid {
+        self.callee
+    }
+
+    /// Where the call was written, if it was written explicitly.
+    pub fn range(&self) -> Option<Range<usize>> {
+        self.range.clone()
+    }
+
+    /// The type the call evaluates to.
+    pub fn result_type(&self) -> Option<&TypeView> {
+        self.type_.as_ref()
+    }
+}
+
+/// A fully resolved type, formatted like in error messages.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TypeView(Arc<TypeProto>);
+
+impl TypeView {
+    /// The arguments of a generic type, like Int32 for List<Int32>.
+    pub fn arguments(&self) -> Vec<TypeView> {
+        self.0.arguments.iter().cloned().map(TypeView).collect_vec()
+    }
+}
+
+impl Display for TypeView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl Debug for TypeView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+/// A declaration that types conform to a trait, like Eq<Self: Color>.
+#[derive(Clone, Copy)]
+pub struct ConformanceView<'a> {
+    program: &'a AnalyzedProgram,
+    rule: &'a TraitConformanceRule,
+}
+
+impl<'a> ConformanceView<'a> {
+    pub fn trait_name(&self) -> &'a str {
+        self.rule.conformance.binding.trait_.name.as_str()
+    }
+
+    /// The types bound to the trait's generics, sorted by the generics' names.
+    pub fn arguments(&self) -> Vec<(&'a str, TypeView)> {
+        self.rule.conformance.binding.generic_to_type.iter()
+            .map(|(generic, type_)| (generic.name.as_str(), TypeView(Arc::clone(type_))))
+            .sorted_by_key(|(name, _)| *name)
+            .collect_vec()
+    }
+
+    /// For each of the trait's abstract functions, the function implementing it.
+    pub fn functions(&self) -> Vec<(FunctionView<'a>, FunctionView<'a>)> {
+        self.rule.conformance.function_mapping.iter()
+            .map(|(abstract_, implementation)| (FunctionView::new(self.program, abstract_), FunctionView::new(self.program, implementation)))
+            .sorted_by_key(|(abstract_, _)| abstract_.name())
+            .collect_vec()
+    }
+}
+
+impl Display for ConformanceView<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}<{}>", self.trait_name(), self.arguments().iter().map(|(name, type_)| format!("{}: {}", name, type_)).join(", "))
+    }
+}
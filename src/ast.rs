@@ -2,18 +2,20 @@ pub use array::{Array, ArrayArgument};
 pub use block::Block;
 pub use conformance::TraitConformanceDeclaration;
 pub use decorated::Decorated;
+pub use enum_::EnumDefinition;
 pub use expression::Expression;
-pub use function::{Function, FunctionInterface};
-pub use statement::Statement;
+pub use function::{Function, FunctionInterface, Requirement};
+pub use statement::{DestructuringTarget, Statement};
 pub use string::StringPart;
 pub use struct_::{Struct, StructArgument};
-pub use term::{IfThenElse, Term};
+pub use term::{IfThenElse, Match, MatchArm, Term};
 pub use trait_::TraitDefinition;
 
 mod array;
 mod block;
 mod struct_;
 mod trait_;
+mod enum_;
 mod conformance;
 mod statement;
 mod expression;
@@ -4,7 +4,7 @@ use crate::ast::Expression;
 use crate::util::fmt::write_separated_display;
 use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Array { pub arguments: Vec<Box<Positioned<ArrayArgument>>> }
 
 impl Array {
@@ -21,7 +21,7 @@ impl Display for Array {
     }
 }
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct ArrayArgument {
     pub key: Option<Expression>,
     pub value: Expression,
@@ -5,7 +5,7 @@ use crate::ast::Statement;
 use crate::util::fmt::write_separated_display;
 use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Block {
     pub statements: Vec<Box<Decorated<Positioned<Statement>>>>
 }
@@ -3,7 +3,7 @@ use std::fmt::{Display, Formatter};
 use crate::ast::Block;
 use crate::ast::expression::Expression;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct TraitConformanceDeclaration {
     pub declared_for: Expression,
     pub declared: Expression,
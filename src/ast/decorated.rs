@@ -3,7 +3,7 @@ use std::fmt::{Display, Formatter};
 use crate::ast::{Array, Expression};
 use crate::error::{RResult, RuntimeError, TryCollectMany};
 
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone, Hash)]
 pub struct Decorated<T> {
     pub decorations: Array,
     pub value: T,
@@ -25,7 +25,7 @@ impl<V> Decorated<V> {
     }
 
     pub fn decorations_as_vec(&self) -> RResult<Vec<&Expression>> {
-        return self.decorations.arguments.iter().map(|d| {
+        self.decorations.arguments.iter().map(|d| {
             if d.value.key.is_some() {
                 return Err(RuntimeError::error("Decorations cannot have keys.").to_array())
             }
@@ -42,7 +42,7 @@ impl<V> Decorated<V> {
             return Err(RuntimeError::error("Decorations are not supported in this context.").to_array())
         }
 
-        return Ok(())
+        Ok(())
     }
 }
 
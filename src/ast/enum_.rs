@@ -0,0 +1,17 @@
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+
+use crate::util::position::Positioned;
+
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct EnumDefinition {
+    pub name: String,
+    pub variants: Vec<Positioned<String>>,
+}
+
+impl Display for EnumDefinition {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "enum {} {{ {} }}", self.name, self.variants.iter().map(|variant| &variant.value).join(", "))
+    }
+}
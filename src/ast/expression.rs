@@ -1,15 +1,20 @@
 use std::fmt::{Display, Formatter};
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 
 use crate::ast::term::Term;
 use crate::error::{RResult, TryCollectMany};
 use crate::util::fmt::write_separated_display;
 use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Expression(Vec<Box<Positioned<Term>>>);
 
 impl Expression {
+    /// The range spanning all terms of the expression, if any.
+    pub fn position(&self) -> Option<Range<usize>> {
+        Some(self.first()?.position.start..self.last()?.position.end)
+    }
+
     pub fn no_errors(&self) -> RResult<()> {
         self.iter()
             .map(|t| match &t.value {
@@ -1,8 +1,11 @@
 use std::fmt::{Display, Error, Formatter};
 
+use itertools::Itertools;
+
 use crate::ast::expression::Expression;
+use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Function {
     pub interface: FunctionInterface,
     pub body: Option<Expression>,
@@ -15,14 +18,23 @@ impl Display for Function {
         if let Some(body) = &self.body {
             write!(fmt, " :: {}", body)?;
         }
-        return Ok(())
+        Ok(())
     }
 }
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct FunctionInterface {
     pub expression: Expression,
     pub return_type: Option<Expression>,
+    /// Requirements on the interface's generics, like `where #T is Ord`.
+    pub where_clause: Option<Positioned<Vec<Positioned<Requirement>>>>,
+}
+
+/// A requirement that a type conforms to a trait, like `#T is Ord`.
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct Requirement {
+    pub subject: Expression,
+    pub trait_: Expression,
 }
 
 impl Display for FunctionInterface {
@@ -33,6 +45,16 @@ impl Display for FunctionInterface {
             write!(fmt, " -> {}", return_type)?;
         }
 
+        if let Some(where_clause) = &self.where_clause {
+            write!(fmt, " where {}", where_clause.value.iter().join(", "))?;
+        }
+
         Ok(())
     }
 }
+
+impl Display for Requirement {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{} is {}", self.subject, self.trait_)
+    }
+}
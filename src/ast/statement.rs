@@ -1,12 +1,16 @@
 use std::fmt::{Display, Error, Formatter};
 
+use itertools::Itertools;
+
 use crate::ast::conformance::TraitConformanceDeclaration;
+use crate::ast::enum_::EnumDefinition;
 use crate::ast::expression::Expression;
 use crate::ast::function::Function;
 use crate::ast::trait_::TraitDefinition;
 use crate::program::allocation::Mutability;
+use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub enum Statement {
     VariableDeclaration {
         mutability: Mutability,
@@ -14,12 +18,16 @@ pub enum Statement {
         type_declaration: Option<Box<Expression>>,
         assignment: Option<Box<Expression>>
     },
-    VariableUpdate { target: Box<Expression>, new_value: Box<Expression> },
+    /// Declares a variable for each of the value's fields, like `let (q, r) = divmod(7, 2)`.
+    Destructuring { mutability: Mutability, targets: Vec<Positioned<DestructuringTarget>>, assignment: Box<Expression> },
+    /// With an operator, the target is updated to `target <operator> new_value`.
+    VariableUpdate { target: Box<Expression>, operator: Option<Box<Positioned<String>>>, new_value: Box<Expression> },
     Expression(Box<Expression>),
     Return(Option<Box<Expression>>),
     FunctionDeclaration(Box<Function>),
     Trait(Box<TraitDefinition>),
     Conformance(Box<TraitConformanceDeclaration>),
+    Enum(Box<EnumDefinition>),
 }
 
 impl Display for Statement {
@@ -36,8 +44,13 @@ impl Display for Statement {
                 }
                 Ok(())
             },
-            Statement::VariableUpdate { target, new_value } => {
-                write!(fmt, "upd {} = {}", target, new_value)
+            Statement::Destructuring { mutability, targets, assignment } => {
+                let targets = targets.iter().map(|target| target.value.to_string()).join(", ");
+                write!(fmt, "{} ({}) = {}", mutability.variable_declaration_keyword(), targets, assignment)
+            },
+            Statement::VariableUpdate { target, operator, new_value } => {
+                let operator = operator.as_ref().map(|operator| operator.value.as_str()).unwrap_or("");
+                write!(fmt, "upd {} {}= {}", target, operator, new_value)
             },
             Statement::Return(Some(expression)) => write!(fmt, "return {}", expression),
             Statement::Return(None) => write!(fmt, "return"),
@@ -45,6 +58,24 @@ impl Display for Statement {
             Statement::FunctionDeclaration(function) => write!(fmt, "{}", function),
             Statement::Trait(trait_) => write!(fmt, "{}", trait_),
             Statement::Conformance(conformance) => write!(fmt, "{}", conformance),
+            Statement::Enum(enum_) => write!(fmt, "{}", enum_),
+        }
+    }
+}
+
+/// A variable declared by destructuring, and the field it is assigned.
+/// Unless it is named, like `quotient: q`, the field is the variable's namesake.
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct DestructuringTarget {
+    pub field: String,
+    pub identifier: String,
+}
+
+impl Display for DestructuringTarget {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        match self.field == self.identifier {
+            true => write!(fmt, "{}", self.identifier),
+            false => write!(fmt, "{}: {}", self.field, self.identifier),
         }
     }
 }
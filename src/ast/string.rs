@@ -2,7 +2,7 @@ use std::fmt::{Display, Formatter};
 
 use crate::ast::Struct;
 
-#[derive(PartialEq, Eq, Clone)]
+#[derive(PartialEq, Eq, Clone, Hash)]
 pub enum StringPart {
     Literal(String),
     Object(Box<Struct>),
@@ -5,7 +5,7 @@ use crate::program::functions::ParameterKey;
 use crate::util::fmt::write_separated_display;
 use crate::util::position::Positioned;
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct Struct { pub arguments: Vec<Box<Positioned<StructArgument>>> }
 
 impl Struct {
@@ -22,7 +22,7 @@ impl Display for Struct {
     }
 }
 
-#[derive(Eq, PartialEq, Clone)]
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct StructArgument {
     pub key: ParameterKey,
     pub value: Expression,
@@ -1,4 +1,6 @@
 use std::fmt::{Display, Error, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
 
 use crate::ast::{Array, Block, Expression, StringPart, Struct};
 use crate::error::RuntimeError;
@@ -10,23 +12,30 @@ pub enum Term {
     Identifier(String),
     MacroIdentifier(String),
     Dot,
+    /// Tests the preceding value's type against the following type.
+    Is,
+    /// Narrows the preceding value to the following type, failing if it isn't one.
+    As,
     IntLiteral(String),
     RealLiteral(String),
+    CharLiteral(String),
     Struct(Box<Struct>),
     Array(Box<Array>),
     StringLiteral(Vec<Box<Positioned<StringPart>>>),
     Block(Box<Block>),
     IfThenElse(Box<IfThenElse>),
+    Match(Box<Match>),
 }
 
 impl Display for Term {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
         match self {
-            Term::Error(err) => write!(fmt, "ERR"),
+            Term::Error(_err) => write!(fmt, "ERR"),
             Term::Identifier(s) => write!(fmt, "{}", s),
             Term::MacroIdentifier(s) => write!(fmt, "{}!", s),
             Term::IntLiteral(s) => write!(fmt, "{}", s),
             Term::RealLiteral(s) => write!(fmt, "{}", s),
+            Term::CharLiteral(s) => write!(fmt, "c\"{}\"", s),
             Term::StringLiteral(parts) => {
                 write!(fmt, "\"")?;
                 for part in parts {
@@ -38,6 +47,8 @@ impl Display for Term {
             Term::Array(array) => write!(fmt, "{}", array),
             Term::Block(block) => write!(fmt, "{{\n{}}}", block),
             Term::Dot => write!(fmt, "."),
+            Term::Is => write!(fmt, "is"),
+            Term::As => write!(fmt, "as"),
             Term::IfThenElse(if_then_else) => {
                 write!(fmt, "if {} :: {}", if_then_else.condition, if_then_else.consequent)?;
                 if let Some(alternative) = &if_then_else.alternative {
@@ -45,14 +56,53 @@ impl Display for Term {
                 }
                 Ok(())
             }
+            Term::Match(match_) => {
+                write!(fmt, "match {} :: {{", match_.subject)?;
+                for arm in match_.arms.iter() {
+                    write!(fmt, " {} :: {},", arm.value.pattern.value, arm.value.value)?;
+                }
+                write!(fmt, " }}")
+            }
         }
     }
 }
 
-#[derive(Eq, PartialEq, Clone)]
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            // Errors only differ by what went wrong.
+            Term::Error(err) => err.title.hash(state),
+            Term::Identifier(s) | Term::MacroIdentifier(s) | Term::IntLiteral(s) | Term::RealLiteral(s) | Term::CharLiteral(s) => s.hash(state),
+            Term::Dot | Term::Is | Term::As => {},
+            Term::Struct(struct_) => struct_.hash(state),
+            Term::Array(array) => array.hash(state),
+            Term::StringLiteral(parts) => parts.hash(state),
+            Term::Block(block) => block.hash(state),
+            Term::IfThenElse(if_then_else) => if_then_else.hash(state),
+            Term::Match(match_) => match_.hash(state),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct IfThenElse {
     pub condition: Expression,
     pub consequent: Expression,
     pub alternative: Option<Expression>,
 }
 
+
+/// `match subject :: { Variant :: value, _ :: value }`, choosing the value of the arm naming the subject's enum variant.
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct Match {
+    pub subject: Expression,
+    pub arms: Vec<Positioned<MatchArm>>,
+}
+
+#[derive(Eq, PartialEq, Clone, Hash)]
+pub struct MatchArm {
+    /// The variant's name, or _ for the default arm.
+    pub pattern: Positioned<String>,
+    pub value: Expression,
+}
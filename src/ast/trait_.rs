@@ -1,15 +1,26 @@
 use std::fmt::{Display, Formatter};
 
-use crate::ast::Block;
+use itertools::Itertools;
 
-#[derive(Eq, PartialEq, Clone)]
+use crate::ast::{Block, Requirement};
+use crate::util::position::Positioned;
+
+#[derive(Eq, PartialEq, Clone, Hash)]
 pub struct TraitDefinition {
     pub name: String,
     pub block: Box<Block>,
+    /// Requirements on the generics of the fields, like `where #T is Ord`.
+    pub where_clause: Option<Positioned<Vec<Positioned<Requirement>>>>,
 }
 
 impl Display for TraitDefinition {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(fmt, "trait {} {{\n{}}}", self.name, self.block)
+        write!(fmt, "trait {} {{\n{}}}", self.name, self.block)?;
+
+        if let Some(where_clause) = &self.where_clause {
+            write!(fmt, " where {}", where_clause.value.iter().join(", "))?;
+        }
+
+        Ok(())
     }
 }
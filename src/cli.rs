@@ -1,10 +1,15 @@
-use clap::Command;
+use clap::{arg, ArgAction, ArgMatches, Command};
 use std::process::ExitCode;
-use crate::cli::logging::dump_failure;
+use crate::cli::logging::{dump_failure, init_logger};
+use crate::error::RResult;
+use crate::interpreter::runtime::Runtime;
 
 pub mod run;
 pub mod check;
 pub mod transpile;
+pub mod explain;
+pub mod explain_grammar;
+pub mod watch;
 pub mod logging;
 
 pub fn make_command() -> Command {
@@ -13,20 +18,38 @@ pub fn make_command() -> Command {
         .subcommand_required(true)
         .arg_required_else_help(true)
         .allow_external_subcommands(true)
+        .arg(arg!(<VERBOSE> "log more details; repeat for even more").required(false).action(ArgAction::Count).long("verbose").short('v').global(true))
+        .arg(arg!(<NOPRELUDE> "don't import the prelude declared in the project's monoteny.toml").required(false).action(ArgAction::SetTrue).long("no-prelude").global(true))
+        .arg(arg!(<NOEMBEDDEDSTD> "read core and common only from the monoteny directory, not from the binary").required(false).action(ArgAction::SetTrue).long("no-embedded-std").global(true))
         .subcommand(run::make_command())
         .subcommand(check::make_command())
         .subcommand(transpile::make_command())
+        .subcommand(explain::make_command())
+        .subcommand(explain_grammar::make_command())
+        .subcommand(watch::make_command())
 }
 
 pub fn run_command() -> ExitCode {
     let matches = make_command().get_matches();
+    init_logger(matches.get_count("VERBOSE"));
 
     let result = match matches.subcommand() {
         Some(("run", sub_matches)) => run::run(sub_matches),
         Some(("check", sub_matches)) => check::run(sub_matches),
         Some(("transpile", sub_matches)) => transpile::run(sub_matches),
+        Some(("explain", sub_matches)) => explain::run(sub_matches),
+        Some(("explain-grammar", sub_matches)) => explain_grammar::run(sub_matches),
+        Some(("watch", sub_matches)) => watch::run(sub_matches),
         _ => panic!("Unsupported action."),
     };
 
-    result.unwrap_or_else(|e| dump_failure(e))
+    result.unwrap_or_else(dump_failure)
+}
+
+/// A runtime with core and common. Their files in the monoteny directory override the embedded ones.
+pub fn make_runtime(args: &ArgMatches) -> RResult<Box<Runtime>> {
+    let embedded_std = !args.get_flag("NOEMBEDDEDSTD");
+    let mut runtime = Runtime::with_std(embedded_std)?;
+    runtime.repository.add_std("common", embedded_std);
+    Ok(runtime)
 }
@@ -1,18 +1,28 @@
+use std::fmt::Write;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
-use clap::{arg, ArgMatches, Command};
+use clap::{arg, ArgAction, ArgMatches, Command};
 use crate::cli::logging::{dump_named_failure, dump_start, dump_success};
 
-use crate::error::RResult;
+use annotate_snippets::Level;
+use display_with_options::with_options;
+use itertools::Itertools;
+
+use crate::error::{print_errors, RResult, RuntimeError};
+use crate::cli::make_runtime;
 use crate::interpreter::runtime::Runtime;
-use crate::program::module::module_name;
+use crate::program::global::FunctionLogic;
+use crate::program::module::{module_name, Module};
+use crate::util::{glob, json};
 
 pub fn make_command() -> Command {
     Command::new("check")
         .about("Parse files to check for validity.")
         .arg_required_else_help(true)
-        .arg(arg!(<PATH> ... "files to check").value_parser(clap::value_parser!(PathBuf)))
+        .arg(arg!(<PATH> ... "files to check; patterns like src/**/*.monoteny are expanded").value_parser(clap::value_parser!(PathBuf)))
+        .arg(arg!(<JSON> "output the results as json").required(false).action(ArgAction::SetTrue).long("json"))
+        .arg(arg!(<DUMPTREE> "print the resolved expression tree of functions with this name").required(false).long("dump-tree").conflicts_with("JSON"))
 }
 
 pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
@@ -20,27 +30,115 @@ pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
         .get_many::<PathBuf>("PATH")
         .into_iter()
         .flatten()
+        .flat_map(|pattern| glob::expand(pattern))
         .collect::<Vec<_>>();
+    let should_output_json = args.get_flag("JSON");
 
-    let start = dump_start(format!("check for {} file(s)", paths.len()).as_str());
+    let start = (!should_output_json).then(|| dump_start(format!("check for {} file(s)", paths.len()).as_str()));
 
-    let mut runtime = Runtime::new()?;
-    runtime.repository.add("common", PathBuf::from("monoteny"));
+    let mut runtime = make_runtime(args)?;
+    // The runtime is shared, so all files use the prelude of the first file's project.
+    if let Some(path) = paths.first().filter(|_| !args.get_flag("NOPRELUDE")) {
+        runtime.load_project_for(path)?;
+    }
 
-    let mut error_count = 0;
-    for path in paths {
-        match runtime.load_file_as_module(path, module_name("main")) {
-            Ok(_) => {},
-            Err(e) => {
-                dump_named_failure(format!("import({})", path.as_os_str().to_string_lossy()).as_str(), e);
-                error_count += 1;
-            },
+    let mut results = vec![];
+    // Each file is its own module, but the modules they use are loaded only once.
+    for path in paths.iter() {
+        // Warnings are reported with the errors, but don't fail the check.
+        let (errors, warnings) = match runtime.load_file_as_module(path, module_name("main")) {
+            Ok(module) => {
+                if let Some(name) = args.get_one::<String>("DUMPTREE") {
+                    print!("{}", dump_trees(&module, &runtime, name));
+                }
+                (vec![], module.warnings.clone())
+            }
+            Err(e) => (e, vec![]),
         };
+
+        if !should_output_json {
+            print_errors(&warnings);
+            if !errors.is_empty() {
+                dump_named_failure(format!("import({})", path.as_os_str().to_string_lossy()).as_str(), errors.clone());
+            }
+        }
+        results.push((path, errors.into_iter().chain(warnings).collect_vec()));
     }
 
-    if error_count == 0 {
-        dump_success(start);
+    let error_count = results.iter()
+        .filter(|(_, errors)| errors.iter().any(|e| e.level == Level::Error))
+        .count();
+
+    if should_output_json {
+        println!("{}", make_json_report(&results));
+    }
+    else {
+        println!("\nChecked {} file(s): {} failed", results.len(), error_count);
+        if error_count == 0 {
+            dump_success(start.unwrap());
+        }
     }
 
-    Ok(ExitCode::from(error_count))
+    Ok(ExitCode::from(u8::try_from(error_count).unwrap_or(u8::MAX)))
+}
+
+/// The expression trees of the module's functions with the name, each headed by the function.
+fn dump_trees(module: &Module, runtime: &Runtime, name: &str) -> String {
+    let mut output = String::new();
+    for head in module.implemented_functions.iter() {
+        if runtime.source.fn_representations[head].name != name {
+            continue
+        }
+        let Some(FunctionLogic::Implementation(implementation)) = runtime.source.fn_logic.get(head) else {
+            continue
+        };
+        writeln!(output, "{:?}", with_options(head.interface.as_ref(), &runtime.source.fn_representations[head])).unwrap();
+        output.push_str(&implementation.dump(&runtime.source));
+    }
+    output
+}
+
+fn make_json_report(results: &Vec<(&PathBuf, Vec<RuntimeError>)>) -> String {
+    let mut output = String::new();
+
+    output.push_str("{\"files\": [");
+    for (idx, (path, errors)) in results.iter().enumerate() {
+        if idx > 0 { output.push_str(", ") }
+
+        output.push_str("{\"path\": ");
+        json::write_string(&mut output, &path.to_string_lossy());
+        output.push_str(", \"errors\": [");
+        for (idx, error) in errors.iter().enumerate() {
+            if idx > 0 { output.push_str(", ") }
+            write_json_error(&mut output, error);
+        }
+        output.push_str("]}");
+    }
+    output.push_str("]}");
+
+    output
+}
+
+fn write_json_error(output: &mut String, error: &RuntimeError) {
+    output.push_str("{\"level\": ");
+    json::write_string(output, &format!("{:?}", error.level).to_lowercase());
+    output.push_str(", \"title\": ");
+    json::write_string(output, &error.title);
+    if let Some(code) = &error.code {
+        output.push_str(", \"code\": ");
+        json::write_string(output, &code.id());
+    }
+    if let Some(path) = &error.path {
+        output.push_str(", \"path\": ");
+        json::write_string(output, &path.to_string_lossy());
+    }
+    if let Some(range) = &error.range {
+        write!(output, ", \"range\": [{}, {}]", range.start, range.end).unwrap();
+    }
+    output.push_str(", \"notes\": [");
+    for (idx, note) in error.notes.iter().enumerate() {
+        if idx > 0 { output.push_str(", ") }
+        write_json_error(output, note);
+    }
+    output.push_str("]}");
 }
@@ -0,0 +1,29 @@
+use std::process::ExitCode;
+
+use clap::{arg, ArgMatches, Command};
+use itertools::Itertools;
+
+use crate::error::codes::ErrorCode;
+use crate::error::{RResult, RuntimeError};
+
+pub fn make_command() -> Command {
+    Command::new("explain")
+        .about("Print a detailed explanation of an error code, like E0101.")
+        .arg_required_else_help(true)
+        .arg(arg!(<CODE> "error code to explain"))
+}
+
+pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
+    let id = args.get_one::<String>("CODE").unwrap();
+
+    let Some(code) = ErrorCode::parse(id) else {
+        return Err(
+            RuntimeError::error(format!("Unknown error code: {}", id).as_str())
+                .with_note(RuntimeError::note(format!("Known codes are {}.", ErrorCode::ALL.iter().join(", ")).as_str()))
+                .to_array()
+        )
+    };
+
+    println!("{}\n\n{}", code, code.explanation());
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,74 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use clap::{arg, ArgMatches, Command};
+use itertools::Itertools;
+
+use crate::error::RResult;
+use crate::cli::make_runtime;
+use crate::interpreter::runtime::Runtime;
+use crate::program::functions::FunctionHead;
+use crate::program::module::{module_name, Module};
+
+pub fn make_command() -> Command {
+    Command::new("explain-grammar")
+        .about("Print the precedence order in effect for a file, with the functions each operator calls.")
+        .arg_required_else_help(true)
+        .arg(arg!(<PATH> "file to explain").value_parser(clap::value_parser!(PathBuf)))
+}
+
+pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
+    let path = args.get_one::<PathBuf>("PATH").unwrap();
+
+    let mut runtime = make_runtime(args)?;
+    if !args.get_flag("NOPRELUDE") {
+        runtime.load_project_for(path)?;
+    }
+
+    let module = runtime.load_file_as_module(path, module_name("main"))?;
+    print!("{}", explain(&module, &runtime));
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Lists the module's precedence groups in evaluation order, i.e. the tightest binding first.
+pub fn explain(module: &Module, runtime: &Runtime) -> String {
+    let grammar = &module.grammar;
+    let mut output = String::new();
+
+    writeln!(output, "Precedence order, evaluated first to last:").unwrap();
+    for (idx, (group, keywords)) in grammar.groups_and_keywords.iter().enumerate() {
+        writeln!(output, "\n{}. {} ({})", idx + 1, group.name, group.associativity).unwrap();
+
+        let keyword_width = keywords.keys().map(|keyword| keyword.len()).max().unwrap_or(0);
+        for (keyword, overloads) in keywords.iter().sorted_by_key(|(keyword, _)| *keyword) {
+            let names = overloads.iter().map(|function| qualified_name(function, module, runtime)).join(", ");
+            writeln!(output, "    {:width$}  {}", keyword, names, width = keyword_width).unwrap();
+        }
+    }
+
+    let keywords_in_multiple_groups = grammar.keywords_in_multiple_groups();
+    if !keywords_in_multiple_groups.is_empty() {
+        writeln!(output, "\nKeywords in multiple groups:").unwrap();
+        for (keyword, groups) in keywords_in_multiple_groups {
+            writeln!(output, "    {}  {}", keyword, groups.iter().map(|group| &group.name).join(", ")).unwrap();
+        }
+    }
+
+    output
+}
+
+/// The function's name, prefixed by the module declaring the pattern that calls it.
+fn qualified_name(function: &Arc<FunctionHead>, module: &Module, runtime: &Runtime) -> String {
+    let name = &runtime.source.fn_representations[function].name;
+    let declaring_module = [module].into_iter()
+        .chain(runtime.source.module_by_name.values().map(AsRef::as_ref))
+        .find(|module| module.patterns.iter().any(|pattern| &pattern.function == function));
+
+    match declaring_module {
+        Some(declaring_module) => format!("{}.{}", declaring_module.name.join("."), name),
+        None => name.clone(),
+    }
+}
@@ -2,8 +2,9 @@ use std::process::ExitCode;
 use std::time::Instant;
 
 use colored::Colorize;
+use log::{Level, LevelFilter, Log, Metadata, Record};
 
-use crate::error::{print_errors, RResult, RuntimeError};
+use crate::error::{print_errors, print_errors_in_source, RResult, RuntimeError};
 
 pub fn dump_start(name: &str) -> Instant {
     println!("{} {}", "Running".green().bold(), name);
@@ -29,7 +30,53 @@ pub fn dump_failure(err: Vec<RuntimeError>) -> ExitCode {
     ExitCode::FAILURE
 }
 
+/// Like dump_failure, but errors without a file are shown in the given source.
+pub fn dump_failure_in_source(err: Vec<RuntimeError>, source: &str, origin: &str) -> ExitCode {
+    print_errors_in_source(&err, source, origin);
+    println!("\n{}: {} error(s)", "Failure".red().bold(), err.len());
+    ExitCode::FAILURE
+}
+
 pub fn dump_success(start: Instant) -> ExitCode {
     println!("{} in {:.2}s", "Finished".green().bold(), start.elapsed().as_secs_f32());
     ExitCode::SUCCESS
 }
+
+/// Writes log events to stderr, so that they don't interfere with machine-readable output.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return
+        }
+
+        let level = match record.level() {
+            Level::Error => "error".red().bold(),
+            Level::Warn => "warning".yellow().bold(),
+            Level::Info => "info".blue().bold(),
+            Level::Debug => "debug".dimmed(),
+            Level::Trace => "trace".dimmed(),
+        };
+        eprintln!("{} [{}] {}", level, record.target(), record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// 0: Warnings only, 1: Debug, 2+: Trace.
+pub fn init_logger(verbosity: u8) {
+    // This fails only if a logger was already set.
+    _ = log::set_logger(&LOGGER);
+    log::set_max_level(match verbosity {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    });
+}
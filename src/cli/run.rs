@@ -1,29 +1,71 @@
 use std::path::PathBuf;
 use std::process::ExitCode;
 
-use clap::{arg, ArgMatches, Command};
+use clap::{arg, ArgAction, ArgMatches, Command};
+use itertools::Itertools;
 
-use crate::error::RResult;
+use crate::cli::logging::dump_failure_in_source;
+use crate::error::{RResult, RuntimeError};
 use crate::interpreter;
-use crate::interpreter::runtime::Runtime;
+use crate::interpreter::coverage::line_coverage;
+use crate::cli::make_runtime;
 use crate::program::module::module_name;
 
 pub fn make_command() -> Command {
     Command::new("run")
         .about("Run a file using the interpreter.")
         .arg_required_else_help(true)
-        .arg(arg!(<PATH> "file to run").value_parser(clap::value_parser!(PathBuf)))
+        .arg(arg!([PATH] "file to run").value_parser(clap::value_parser!(PathBuf)).required_unless_present("EVAL"))
+        .arg(arg!(<EVAL> "code to run as the body of main!; repeat to add more statements").required(false).action(ArgAction::Append).long("eval").short('e').conflicts_with("PATH"))
+        .arg(arg!(<STATS> "print instruction, call, stack and allocation counts after running; requires the stats feature").required(false).action(ArgAction::SetTrue).long("stats"))
+        .arg(arg!(<COVERAGE> "print which lines of the loaded files were executed").required(false).action(ArgAction::SetTrue).long("coverage").conflicts_with("EVAL"))
 }
 
 pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
-    let input_path = args.get_one::<PathBuf>("PATH").unwrap();
+    let print_stats = args.get_flag("STATS");
+    if print_stats && !cfg!(feature = "stats") {
+        return Err(RuntimeError::error("--stats requires monoteny to be built with the stats feature.").to_array());
+    }
+
+    let mut runtime = make_runtime(args)?;
 
-    let mut runtime = Runtime::new()?;
-    runtime.repository.add("common", PathBuf::from("monoteny"));
+    if let Some(statements) = args.get_many::<String>("EVAL") {
+        let code = statements.map(String::as_str).collect::<Vec<_>>().join("\n");
+        return Ok(match runtime.evaluate_string(&code, &mut std::io::stdout()) {
+            Ok(stats) => {
+                if print_stats {
+                    eprintln!("{}", stats);
+                }
+                ExitCode::SUCCESS
+            },
+            Err(errs) => dump_failure_in_source(errs, &code, "<eval>"),
+        });
+    }
 
+    let input_path = args.get_one::<PathBuf>("PATH").unwrap();
+
+    if !args.get_flag("NOPRELUDE") {
+        runtime.load_project_for(input_path)?;
+    }
     let module = runtime.load_file_as_module(input_path, module_name("main"))?;
 
-    interpreter::run::main(&module, &mut runtime)?;
+    if args.get_flag("COVERAGE") {
+        let coverage = interpreter::run::main_with_coverage(&module, &mut runtime, &mut std::io::stdout())?;
+        eprintln!();
+        for file in line_coverage(&coverage, &runtime, &module) {
+            eprintln!("{}: {:.1}% of {} lines", file.path.display(), file.percentage(), file.lines.len());
+            let uncovered = file.uncovered_lines();
+            if !uncovered.is_empty() {
+                eprintln!("    not executed: {}", uncovered.iter().join(", "));
+            }
+        }
+        return Ok(ExitCode::SUCCESS)
+    }
+
+    let stats = interpreter::run::main(&module, &mut runtime, &mut std::io::stdout())?;
+    if print_stats {
+        eprintln!("{}", stats);
+    }
 
     Ok(ExitCode::SUCCESS)
 }
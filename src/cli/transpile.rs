@@ -5,75 +5,122 @@ use std::process::ExitCode;
 use clap::{arg, ArgAction, ArgMatches, Command};
 use itertools::Itertools;
 
-use crate::error::RResult;
+use crate::error::{print_errors, RResult, RuntimeError};
+use crate::cli::make_runtime;
 use crate::interpreter::runtime::Runtime;
 use crate::program::module::{Module, module_name};
 use crate::{interpreter, transpiler};
-use crate::cli::logging::{dump_failure, dump_start, dump_success};
+use crate::cli::logging::{dump_failure, dump_named_failure, dump_start, dump_success};
 use crate::transpiler::LanguageContext;
 use crate::util::file_writer::write_file_safe;
+use crate::util::glob;
 
 pub fn make_command() -> Command {
     Command::new("transpile")
-        .about("Transpile a file into another language.")
+        .about("Transpile files into another language.")
         .arg_required_else_help(true)
-        .arg(arg!(<INPUT> "file to transpile").value_parser(clap::value_parser!(PathBuf)).long("input").short('i'))
-        .arg(arg!(<OUTPUT> "output file path").required(false).value_parser(clap::value_parser!(PathBuf)).long("output").short('o'))
+        .arg(arg!(<INPUT> ... "files to transpile; patterns like src/**/*.monoteny are expanded").value_parser(clap::value_parser!(PathBuf)).long("input").short('i'))
+        .arg(arg!(<OUTPUT> "output file path; only for a single input").required(false).value_parser(clap::value_parser!(PathBuf)).long("output").short('o'))
         .arg(arg!(<ALL> "use all available transpilers").required(false).action(ArgAction::SetTrue).long("all"))
+        .arg(arg!(<OPTIMIZE> "optimization level: 0 (none), 1 (inline trivial functions), 2 (also propagate constants and eliminate dead functions)").required(false).value_parser(clap::value_parser!(u8).range(0..=2)).default_value("2").long("optimize").short('O'))
         .arg(arg!(<NOREFACTOR> "don't use ANY refactoring").required(false).action(ArgAction::SetTrue).long("norefactor"))
         .arg(arg!(<NOFOLD> "don't use constant folding").required(false).action(ArgAction::SetTrue).long("nofold"))
         .arg(arg!(<NOINLINE> "don't use inlining").required(false).action(ArgAction::SetTrue).long("noinline"))
         .arg(arg!(<NOTRIMLOCALS> "don't trim unused locals code").required(false).action(ArgAction::SetTrue).long("notrimlocals"))
+        .arg(arg!(<EXPLAININLINING> "write a json report of which functions were inlined").required(false).action(ArgAction::SetTrue).long("explain-inlining"))
+        .arg(arg!(<MATCHINTERPRETERERRORS> "raise the interpreter's errors for list indices out of bounds").required(false).action(ArgAction::SetTrue).long("match-interpreter-errors"))
+        .arg(arg!(<LAYOUT> "onefile: write a single file; package: write one file per module, in a package named like the output").required(false).value_parser(["onefile", "package"]).default_value("onefile").long("layout"))
 }
 
 pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
-    let input_path = args.get_one::<PathBuf>("INPUT").unwrap();
-    let output_path_proto = match args.contains_id("OUTPUT") {
-        true => args.get_one::<PathBuf>("OUTPUT").unwrap().clone(),
-        false => input_path.with_extension(""),
-    };
-    let base_filename = output_path_proto.file_name().and_then(OsStr::to_str).unwrap();
-    let base_output_path = output_path_proto.parent().unwrap();
+    let input_paths = args
+        .get_many::<PathBuf>("INPUT")
+        .into_iter()
+        .flatten()
+        .flat_map(|pattern| glob::expand(pattern))
+        .collect_vec();
+    let explicit_output_path = args.get_one::<PathBuf>("OUTPUT");
+    if explicit_output_path.is_some() && input_paths.len() != 1 {
+        return Err(RuntimeError::error("--output can only be used with a single input file.").to_array());
+    }
 
     let can_refactor = !args.get_flag("NOREFACTOR");
-    let config = transpiler::Config {
-        should_constant_fold: can_refactor && !args.get_flag("NOFOLD"),
-        should_monomorphize: true, // TODO Cannot do without it for now
-        should_inline: can_refactor && !args.get_flag("NOINLINE"),
-        should_trim_locals: can_refactor && !args.get_flag("NOTRIMLOCALS"),
+    let optimization_level = match can_refactor {
+        true => *args.get_one::<u8>("OPTIMIZE").unwrap(),
+        false => 0,
+    };
+    let mut config = transpiler::Config::with_optimization_level(optimization_level);
+    config.should_constant_fold &= !args.get_flag("NOFOLD");
+    config.should_inline &= !args.get_flag("NOINLINE");
+    config.should_trim_locals &= !args.get_flag("NOTRIMLOCALS");
+    config.should_explain_inlining = args.get_flag("EXPLAININLINING");
+    config.should_match_interpreter_errors = args.get_flag("MATCHINTERPRETERERRORS");
+    config.layout = match args.get_one::<String>("LAYOUT").unwrap().as_str() {
+        "package" => transpiler::Layout::Package,
+        _ => transpiler::Layout::OneFile,
     };
     let should_output_all = args.get_flag("ALL");
 
-    let output_extensions: Vec<&str> = match should_output_all {
-        true => vec!["py"],
-        false => vec![output_path_proto.extension().and_then(OsStr::to_str).unwrap()]
-    };
+    let mut runtime = make_runtime(args)?;
+    // The runtime is shared, so all files use the prelude of the first file's project.
+    if let Some(path) = input_paths.first().filter(|_| !args.get_flag("NOPRELUDE")) {
+        runtime.load_project_for(path)?;
+    }
 
-    let mut runtime = Runtime::new()?;
-    runtime.repository.add("common", PathBuf::from("monoteny"));
+    let mut error_count = 0;
+    let mut failed_files = 0;
 
-    let module = runtime.load_file_as_module(input_path, module_name("main"))?;
+    // Each file is its own module, but the modules they use are loaded only once.
+    for input_path in input_paths.iter() {
+        let output_path_proto = match explicit_output_path {
+            Some(path) => path.clone(),
+            None => input_path.with_extension(""),
+        };
+        let base_filename = output_path_proto.file_stem().and_then(OsStr::to_str).unwrap();
+        let base_output_path = output_path_proto.parent().unwrap();
 
-    let mut error_count = 0;
+        let output_extensions: Vec<&str> = match should_output_all {
+            true => vec!["py"],
+            false => vec![output_path_proto.extension().and_then(OsStr::to_str).unwrap()]
+        };
 
-    for output_extension in output_extensions {
-        let start = dump_start(format!("{}:transpile! using {}", input_path.as_os_str().to_string_lossy(), output_extension).as_str());
-        match transpile_target(base_filename, base_output_path, &config, &mut runtime, &module, output_extension) {
-            Ok(paths) => {
-                for path in paths {
-                    println!("{}", path.to_str().unwrap());
-                }
-                dump_success(start);
-            }
+        let module = match runtime.load_file_as_module(input_path, module_name("main")) {
+            Ok(module) => module,
             Err(e) => {
-                dump_failure(e);
+                dump_named_failure(format!("import({})", input_path.as_os_str().to_string_lossy()).as_str(), e);
                 error_count += 1;
-            },
+                failed_files += 1;
+                println!();
+                continue
+            }
+        };
+        print_errors(&module.warnings);
+
+        let previous_error_count = error_count;
+        for output_extension in output_extensions {
+            let start = dump_start(format!("{}:transpile! using {}", input_path.as_os_str().to_string_lossy(), output_extension).as_str());
+            match transpile_target(base_filename, base_output_path, &config, &mut runtime, &module, output_extension) {
+                Ok(paths) => {
+                    for path in paths {
+                        println!("{}", path.to_str().unwrap());
+                    }
+                    dump_success(start);
+                }
+                Err(e) => {
+                    dump_failure(e);
+                    error_count += 1;
+                },
+            }
+            println!();
         }
-        println!();
+        failed_files += usize::from(error_count > previous_error_count);
+    }
+
+    if input_paths.len() > 1 {
+        println!("Transpiled {} file(s): {} failed", input_paths.len(), failed_files);
     }
-    
-    Ok(ExitCode::from(error_count))
+
+    Ok(ExitCode::from(u8::try_from(error_count).unwrap_or(u8::MAX)))
 }
 
 fn create_context(runtime: &Runtime, extension: &str) -> Box<dyn LanguageContext> {
@@ -83,10 +130,10 @@ fn create_context(runtime: &Runtime, extension: &str) -> Box<dyn LanguageContext
     }
 }
 
-fn transpile_target(base_filename: &str, base_output_path: &Path, config: &transpiler::Config, mut runtime: &mut Box<Runtime>, module: &Box<Module>, output_extension: &str) -> RResult<Vec<PathBuf>> {
-    let context = create_context(&runtime, output_extension);
-    let transpiler = interpreter::run::transpile(&module, runtime)?;
-    let file_map = transpiler::transpile(transpiler, runtime, context.as_ref(), config, base_filename)?;
+fn transpile_target(base_filename: &str, base_output_path: &Path, config: &transpiler::Config, runtime: &mut Box<Runtime>, module: &Module, output_extension: &str) -> RResult<Vec<PathBuf>> {
+    let context = create_context(runtime, output_extension);
+    let transpiler = interpreter::run::transpile(module, runtime, &mut std::io::stdout())?;
+    let file_map = transpiler::transpile(*transpiler, runtime, context.as_ref(), config, base_filename)?;
 
     let output_files = file_map.into_iter().map(|(filename, content)| {
         write_file_safe(base_output_path, &filename, &content)
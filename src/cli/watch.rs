@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{arg, ArgMatches, Command};
+use colored::Colorize;
+use itertools::Itertools;
+
+use crate::error::{print_errors, print_errors_in_source, RResult, RuntimeError};
+use crate::interpreter;
+use crate::cli::make_runtime;
+use crate::interpreter::runtime::Runtime;
+use crate::program::module::{module_name, Module};
+
+/// How often files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Changes are collected until the files have been quiet for this long, so that rapid saves run once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub fn make_command() -> Command {
+    Command::new("watch")
+        .about("Run a file using the interpreter, and again whenever it or a module it uses changes.")
+        .arg_required_else_help(true)
+        .arg(arg!(<PATH> "file to run").value_parser(clap::value_parser!(PathBuf)))
+}
+
+/// The last successfully loaded program, to resolve edits of the entry file incrementally.
+struct Loaded {
+    runtime: Box<Runtime>,
+    module: Box<Module>,
+}
+
+pub fn run(args: &ArgMatches) -> RResult<ExitCode> {
+    let input_path = args.get_one::<PathBuf>("PATH").unwrap();
+
+    let mut loaded = None;
+    let mut changed = vec![];
+    loop {
+        // Clear the screen, then show what we're running.
+        print!("\x1b[2J\x1b[H");
+        println!("{} {} {}", timestamp().dimmed(), "Running".green().bold(), input_path.display());
+
+        let start = Instant::now();
+        let mut paths = vec![input_path.clone()];
+        // Errors and panics are reported, but the watcher keeps going.
+        match catch_unwind(AssertUnwindSafe(|| run_once(input_path, args, &changed, &mut loaded, &mut paths))) {
+            Ok(Ok(())) => println!("\n{} in {:.2}s", "Finished".green().bold(), start.elapsed().as_secs_f32()),
+            Ok(Err(errors)) => {
+                loaded = None;
+                println!("\n{}: {}", "Failure".red().bold(), summarize(&errors));
+            }
+            Err(_) => {
+                // The runtime may be in any state after a panic.
+                loaded = None;
+                println!("\n{}: the interpreter panicked.", "Failure".red().bold());
+            }
+        }
+        println!("{}", "Waiting for changes...".dimmed());
+
+        changed = wait_for_changes(&paths);
+    }
+}
+
+/// Loads and runs the file, printing errors in place.
+/// If only the entry file changed since the last successful run, only its changed functions are resolved again.
+/// Paths of all loaded modules are added to paths, even if loading fails.
+fn run_once(input_path: &PathBuf, args: &ArgMatches, changed: &[PathBuf], loaded: &mut Option<Loaded>, paths: &mut Vec<PathBuf>) -> RResult<()> {
+    let Loaded { runtime, module } = match loaded.take() {
+        Some(Loaded { mut runtime, mut module }) if changed == [input_path.clone()] => {
+            paths.extend(runtime.module_paths.values().cloned());
+            let source = std::fs::read_to_string(input_path)
+                .map_err(|e| RuntimeError::error(format!("Error loading {:?}: {}", input_path, e).as_str()).to_array())?;
+            if let Err(errors) = runtime.update_module(&mut module, &source) {
+                // Errors in the entry file are reported without its path.
+                print_errors_in_source(&errors, &source, &input_path.to_string_lossy());
+                return Err(errors)
+            }
+            loaded.insert(Loaded { runtime, module })
+        }
+        _ => {
+            let mut runtime = make_runtime(args)?;
+            if !args.get_flag("NOPRELUDE") {
+                runtime.load_project_for(input_path)?;
+            }
+            let result = runtime.load_file_as_module(input_path, module_name("main"));
+            paths.extend(runtime.module_paths.values().cloned());
+            let module = result.inspect_err(|errors| print_errors(errors))?;
+            loaded.insert(Loaded { runtime, module })
+        }
+    };
+
+    print_errors(&module.warnings);
+    interpreter::run::main(module, runtime, &mut std::io::stdout())
+        .inspect_err(|errors| print_errors(errors))?;
+    Ok(())
+}
+
+/// Blocks until any of the files changes, and then until they stop changing.
+/// Returns the changed files.
+fn wait_for_changes(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let modification_times = || paths.iter()
+        .map(|path| (path.clone(), std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()))
+        .collect::<HashMap<_, _>>();
+
+    let initial = modification_times();
+    let mut latest = initial.clone();
+    let mut last_change = None;
+    loop {
+        sleep(POLL_INTERVAL);
+
+        let current = modification_times();
+        if current != latest {
+            latest = current;
+            last_change = Some(Instant::now());
+        }
+        else if last_change.is_some_and(|time| time.elapsed() >= DEBOUNCE) {
+            return paths.iter()
+                .filter(|path| initial[*path] != latest[*path])
+                .unique()
+                .cloned()
+                .collect_vec()
+        }
+    }
+}
+
+/// The number of errors, and the first one's title.
+fn summarize(errors: &[RuntimeError]) -> String {
+    match errors.first() {
+        Some(first) if errors.len() > 1 => format!("{} (and {} more error(s))", first.title, errors.len() - 1),
+        Some(first) => first.title.clone(),
+        None => "unknown error".to_string(),
+    }
+}
+
+/// The current UTC time of day, as HH:MM:SS.
+fn timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs()) % (24 * 60 * 60);
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
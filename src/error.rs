@@ -1,41 +1,82 @@
-use std::fmt::{Debug, Display};
-use std::fs;
+use std::borrow::Cow;
+use std::fmt::Debug;
 use std::ops::Range;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use annotate_snippets::{Annotation, Level, Message, Renderer, Snippet};
-use itertools::Itertools;
 
+use crate::error::codes::ErrorCode;
+
+pub mod codes;
+mod tests;
+
+/// Errors are kept small (104 bytes), because deep recursions in the resolver hold many of them.
+/// That's why the path and notes are boxed rather than growable.
 #[derive(Debug, Clone, PartialEq)]
 pub struct RuntimeError {
     pub level: Level,
     pub title: String,
-    pub path: Option<PathBuf>,
+    /// Identifies the kind of error, for errors common enough to be explained in detail.
+    pub code: Option<ErrorCode>,
+    pub path: Option<Box<Path>>,
     pub range: Option<Range<usize>>,
-    pub notes: Vec<Box<RuntimeError>>,
+    pub notes: Box<[RuntimeError]>,
 }
 
 pub type RResult<V> = Result<V, Vec<RuntimeError>>;
 
 impl RuntimeError {
     pub fn print(&self) {
+        println!("{}", self.render(&Renderer::styled()));
+    }
+
+    pub fn render(&self, renderer: &Renderer) -> String {
         match &self.path {
-            None => self.print_snippet(Snippet::source(&self.title)),
-            Some(path) => match fs::read_to_string(path) {
-                Ok(source) => self.print_snippet(
-                    Snippet::source(source.as_str())
-                        .origin(path.to_str().unwrap())
-                        .fold(true)
-                ),
-                Err(err) => self.print_snippet(Snippet::source(err.to_string().as_str())),
-            }
-        };
+            None => self.render_snippet(Snippet::source(&self.title), renderer),
+            Some(path) => self.render_from_file(path, renderer),
+        }
     }
 
-    /// This could be inline with print, but Snippet doesn't copy its string...
-    fn print_snippet(&self, mut snippet: Snippet) {
+    /// Prints the error, using the given source for errors that don't reference a file.
+    pub fn print_in_source(&self, source: &str, origin: &str) {
+        println!("{}", self.render_in_source(source, origin, &Renderer::styled()));
+    }
+
+    /// Renders the error, using the given source for errors that don't reference a file.
+    pub fn render_in_source(&self, source: &str, origin: &str, renderer: &Renderer) -> String {
+        match &self.path {
+            None if self.range.is_some() => self.render_snippet(
+                Snippet::source(&displayable_source(source))
+                    .origin(origin)
+                    .fold(true),
+                renderer
+            ),
+            _ => self.render(renderer),
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn render_from_file(&self, path: &Path, renderer: &Renderer) -> String {
+        match std::fs::read_to_string(path) {
+            Ok(source) => self.render_snippet(
+                Snippet::source(&displayable_source(&source))
+                    .origin(path.to_str().unwrap())
+                    .fold(true),
+                renderer
+            ),
+            Err(err) => self.render_snippet(Snippet::source(err.to_string().as_str()), renderer),
+        }
+    }
+
+    #[cfg(not(feature = "native"))]
+    fn render_from_file(&self, path: &Path, renderer: &Renderer) -> String {
+        self.render_snippet(Snippet::source("").origin(path.to_str().unwrap()), renderer)
+    }
+
+    /// This could be inline with render, but Snippet doesn't copy its string...
+    fn render_snippet(&self, mut snippet: Snippet, renderer: &Renderer) -> String {
         if let Some(range) = &self.range {
             snippet = snippet.annotation(
-                Level::Error.span(range.clone())
+                self.level.span(range.clone())
             );
         }
 
@@ -45,22 +86,29 @@ impl RuntimeError {
             annotation.add_to_snippet(&mut annotations, &mut footers);
         }
 
-        let msg = Level::Error.title(&self.title)
+        let id = self.code.map(|code| code.id());
+        let mut msg = self.level.title(&self.title);
+        if let Some(id) = &id {
+            msg = msg.id(id);
+        }
+        let msg = msg
             .snippet(snippet.annotations(annotations))
-            .footers(footers.into_iter());
+            .footers(footers);
 
-        let renderer = Renderer::styled();
-
-        println!("{}", renderer.render(msg));
+        // The rendered message borrows id. Temporaries of a tail expression are dropped after the locals,
+        //  so returning it directly would drop id while it's still borrowed.
+        let rendered = renderer.render(msg).to_string();
+        rendered
     }
 
     pub fn new(level: Level, title: &str) -> RuntimeError {
         RuntimeError {
             level,
             title: title.to_string(),
+            code: None,
             path: None,
             range: None,
-            notes: vec![],
+            notes: Box::new([]),
         }
     }
 
@@ -98,7 +146,7 @@ impl RuntimeError {
             footers.push(
                 self.level
                     .title(&self.title)
-                    .footers(our_footers.into_iter())
+                    .footers(our_footers)
             );
 
             return
@@ -124,17 +172,35 @@ impl RuntimeError {
             return self;
         }
 
-        self.path = Some(path);
+        self.path = Some(path.into_boxed_path());
         self
     }
 
-    pub fn with_note(mut self, note: RuntimeError) -> Self {
-        self.notes.push(Box::new(note));
+    /// Moves the ranges of the error and its notes to be relative to a substring of its source,
+    /// starting at offset and spanning len bytes. Ranges outside the substring are clamped into it.
+    /// Errors in other files are unaffected.
+    pub fn in_substring(mut self, offset: usize, len: usize) -> RuntimeError {
+        if self.path.is_some() {
+            return self;
+        }
+
+        let clamp = |position: usize| position.saturating_sub(offset).min(len);
+        self.range = self.range.map(|range| clamp(range.start)..clamp(range.end));
+        self.notes = self.notes.into_vec().into_iter().map(|note| note.in_substring(offset, len)).collect();
         self
     }
 
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_note(self, note: RuntimeError) -> Self {
+        self.with_notes([note].into_iter())
+    }
+
     pub fn with_notes(mut self, notes: impl Iterator<Item=RuntimeError>) -> Self {
-        self.notes.extend(notes.into_iter().map(Box::new).collect_vec());
+        self.notes = self.notes.into_vec().into_iter().chain(notes).collect();
         self
     }
 }
@@ -165,20 +231,36 @@ impl<V, I: Iterator<Item=RResult<V>>, R: FromIterator<V>> TryCollectMany<R> for
             }
         }
 
-        return match errors.is_empty() {
+        match errors.is_empty() {
             true => Ok(R::from_iter(values)),
             false => Err(errors),
         }
     }
 }
 
-pub fn print_errors(errors: &Vec<RuntimeError>) {
+/// Replaces the carriage returns of CRLF line endings with spaces, so excerpts don't show them.
+/// The source keeps its length, so byte ranges into the original source still apply.
+fn displayable_source(source: &str) -> Cow<'_, str> {
+    match source.contains("\r\n") {
+        true => Cow::Owned(source.replace("\r\n", " \n")),
+        false => Cow::Borrowed(source),
+    }
+}
+
+pub fn print_errors(errors: &[RuntimeError]) {
     for error in errors.iter() {
         error.print();
         println!("\n");
     }
 }
 
+pub fn print_errors_in_source(errors: &[RuntimeError], source: &str, origin: &str) {
+    for error in errors.iter() {
+        error.print_in_source(source, origin);
+        println!("\n");
+    }
+}
+
 impl Eq for RuntimeError {
 
 }
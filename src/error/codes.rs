@@ -0,0 +1,362 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+/// A stable identifier for a class of errors, so they can be looked up with `monoteny explain`.
+/// Codes starting with E are errors, codes starting with W are warnings.
+/// Lints supplied by tools are identified by their prefix and name instead, like acme::long_function.
+/// Codes are never reused for something else, even if the error they stood for is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    UnresolvedFunction,
+    AmbiguousFunction,
+    UnknownReference,
+    ImmutableToMutParameter,
+    LiteralOutOfRange,
+    NonBoolCondition,
+    UnassignedLocal,
+    MissingPrecedenceGroup,
+    MissingConformance,
+    ConflictingConformance,
+    MissingConformanceFunction,
+    DuplicateConformance,
+    IndexOutOfBounds,
+    PopFromEmptyList,
+    InvalidExponent,
+    InvalidJson,
+    UnexpectedJsonKind,
+    UnreachableStatement,
+    StaticTypeTest,
+    DuplicateConformanceWarning,
+    ShadowedPrimitive,
+    Deprecated,
+    /// Reported by a custom lint; see static_analysis::lint.
+    Lint(&'static LintCode),
+}
+
+/// The code of a custom lint's diagnostics, like acme::long_function.
+/// Lints declare it as a constant, so codes can refer to it without growing errors by the two names.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct LintCode {
+    /// Chosen by the tool supplying the lint, so its codes can't be confused with those of other tools.
+    pub prefix: &'static str,
+    pub name: &'static str,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 22] = [
+        ErrorCode::UnresolvedFunction,
+        ErrorCode::AmbiguousFunction,
+        ErrorCode::UnknownReference,
+        ErrorCode::ImmutableToMutParameter,
+        ErrorCode::LiteralOutOfRange,
+        ErrorCode::NonBoolCondition,
+        ErrorCode::UnassignedLocal,
+        ErrorCode::MissingPrecedenceGroup,
+        ErrorCode::MissingConformance,
+        ErrorCode::ConflictingConformance,
+        ErrorCode::MissingConformanceFunction,
+        ErrorCode::DuplicateConformance,
+        ErrorCode::IndexOutOfBounds,
+        ErrorCode::PopFromEmptyList,
+        ErrorCode::InvalidExponent,
+        ErrorCode::InvalidJson,
+        ErrorCode::UnexpectedJsonKind,
+        ErrorCode::UnreachableStatement,
+        ErrorCode::StaticTypeTest,
+        ErrorCode::DuplicateConformanceWarning,
+        ErrorCode::ShadowedPrimitive,
+        ErrorCode::Deprecated,
+    ];
+
+    pub fn id(&self) -> Cow<'static, str> {
+        Cow::Borrowed(match self {
+            ErrorCode::UnresolvedFunction => "E0101",
+            ErrorCode::AmbiguousFunction => "E0102",
+            ErrorCode::UnknownReference => "E0103",
+            ErrorCode::ImmutableToMutParameter => "E0104",
+            ErrorCode::LiteralOutOfRange => "E0105",
+            ErrorCode::NonBoolCondition => "E0106",
+            ErrorCode::UnassignedLocal => "E0107",
+            ErrorCode::MissingPrecedenceGroup => "E0108",
+            ErrorCode::MissingConformance => "E0201",
+            ErrorCode::ConflictingConformance => "E0202",
+            ErrorCode::MissingConformanceFunction => "E0203",
+            ErrorCode::DuplicateConformance => "E0204",
+            ErrorCode::IndexOutOfBounds => "E0401",
+            ErrorCode::PopFromEmptyList => "E0402",
+            ErrorCode::InvalidExponent => "E0403",
+            ErrorCode::InvalidJson => "E0404",
+            ErrorCode::UnexpectedJsonKind => "E0405",
+            ErrorCode::UnreachableStatement => "W0301",
+            ErrorCode::StaticTypeTest => "W0302",
+            ErrorCode::DuplicateConformanceWarning => "W0303",
+            ErrorCode::ShadowedPrimitive => "W0304",
+            ErrorCode::Deprecated => "W0305",
+            ErrorCode::Lint(code) => return Cow::Owned(format!("{}::{}", code.prefix, code.name)),
+        })
+    }
+
+    /// Finds the built-in code by its id, ignoring case.
+    pub fn parse(id: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL.into_iter().find(|code| code.id().eq_ignore_ascii_case(id))
+    }
+
+    /// A longer description of the error, with an example that causes it.
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            ErrorCode::UnresolvedFunction => "\
+No function matches the call's name and arguments.
+The function may not exist, may not be imported, or may expect different argument types or labels.
+
+Example:
+
+    def square(x 'Int32) -> Int32 :: x * x;
+    def main! :: { square(\"4\"); };
+
+Check the function's declaration, and pass arguments of the types and labels it declares.",
+            ErrorCode::AmbiguousFunction => "\
+More than one function matches the call, and none is more specific than the others.
+
+Example:
+
+    def describe(x '$Number) -> String :: \"number\";
+    def describe(x '$Number) -> String :: \"also a number\";
+    def main! :: { write_line(describe(1)); };
+
+Remove one of the functions, or give them different parameter labels or types.",
+            ErrorCode::UnknownReference => "\
+The name is not declared in the current scope.
+
+Example:
+
+    def main! :: { write_line(x); };
+
+Declare the variable before using it, or import the module that declares it.",
+            ErrorCode::ImmutableToMutParameter => "\
+A value that cannot be changed is passed to a parameter declared as mut.
+Functions may change their mut parameters, so only variables declared with var can be passed.
+
+Example:
+
+    def increment(mut x 'Int32) :: upd x = x + 1;
+    def main! :: {
+        let x = 1;
+        increment(x);
+    };
+
+Declare the variable with var instead of let.",
+            ErrorCode::LiteralOutOfRange => "\
+The number literal doesn't fit into the type it is used as.
+
+Example:
+
+    def main! :: { let x 'UInt8 = 300; };
+
+Use a larger type, or a smaller number.",
+            ErrorCode::NonBoolCondition => "\
+The condition of an if expression must be a Bool.
+Numbers and other values are not converted to Bool implicitly.
+
+Example:
+
+    def main! :: { if 1 :: write_line(\"one\"); };
+
+Compare the value explicitly, like `if x != 0`.",
+            ErrorCode::UnassignedLocal => "\
+A variable declared without a value is read before it is assigned one.
+Every path leading to the read must assign it, so an if without an else is not enough.
+
+Example:
+
+    def main! :: {
+        let x 'Int64;
+        if true :: upd x = 1;
+        write_line(\"\\(x)\");
+    };
+
+Assign the variable in both branches of the if, or give it a value when declaring it.",
+            ErrorCode::MissingPrecedenceGroup => "\
+An imported pattern belongs to a precedence group that isn't in the module's precedence order.
+Every precedence_order! declares new groups, even if they have the same names as existing ones.
+
+Example:
+
+    -- ops.monoteny, using the order of common.precedence:
+    ![pattern(lhs <> rhs, AdditionPrecedence)]
+    def concatenate(lhs 'String, rhs 'String) -> String :: \"\\(lhs)\\(rhs)\";
+
+    -- main.monoteny:
+    precedence_order!(LeftUnaryPrecedence: LeftUnary, AdditionPrecedence: Left);
+    use!(module!(\"ops\"));
+
+Remove the module's precedence_order! to use the order the pattern was declared for,
+or declare the pattern again for a group of the module's own order.",
+            ErrorCode::MissingConformance => "\
+A type is required to conform to a trait, but no declaration says it does.
+This is often caused by calling a generic function with a type the function can't handle.
+
+Example:
+
+    trait Named { def (self 'Self).name() -> String; };
+    def greet(x '$Named) -> String :: x.name();
+    def main! :: { write_line(greet(5)); };
+
+Declare the conformance, like `declare Int32 is Named :: { ... };`, or pass a different type.",
+            ErrorCode::ConflictingConformance => "\
+More than one declaration could satisfy a trait requirement, so it is unclear which one to use.
+
+Example:
+
+    declare $Number is Named :: { def (self 'Self).name() -> String :: \"number\"; };
+    declare Int32 is Named :: { def (self 'Self).name() -> String :: \"int\"; };
+
+Remove one of the declarations, or make their conditions exclusive.",
+            ErrorCode::MissingConformanceFunction => "\
+A conformance declaration doesn't implement one of the trait's abstract functions.
+
+Example:
+
+    trait Named { def (self 'Self).name() -> String; };
+    declare Int32 is Named :: {};
+
+Implement the function inside the declaration, with the interface the trait declares.",
+            ErrorCode::DuplicateConformance => "\
+The same type is declared to conform to the same trait more than once.
+This is usually only a warning (W0303), but strict!(deny_duplicate_conformances) makes it an error.
+
+Example:
+
+    strict!(deny_duplicate_conformances);
+    declare Int32 is Named :: { def (self 'Self).name() -> String :: \"int\"; };
+    declare Int32 is Named :: { def (self 'Self).name() -> String :: \"integer\"; };
+
+Remove all but one of the declarations.",
+            ErrorCode::IndexOutOfBounds => "\
+A list was accessed at an index it doesn't have, while the program was running.
+
+Example:
+
+    def main! :: {
+        let list = List.new();
+        list.push(1);
+        write_line(\"\\(list.get(2))\");
+    };
+
+Check the index against the list's len() before accessing it.",
+            ErrorCode::PopFromEmptyList => "\
+pop() was called on a list without elements, while the program was running.
+
+Example:
+
+    def main! :: {
+        let list = List.new();
+        list.push(1);
+        list.pop();
+        list.pop();
+    };
+
+Check that the list's len() is above 0 before popping.",
+            ErrorCode::InvalidExponent => "\
+An integer was raised to a negative power, or to a power larger than 4294967295, while the program was running.
+The result of a negative power would be a fraction, which integers can't hold.
+Other powers wrap around on overflow, like the other integer operations. 0 to the power of 0 is 1.
+
+Example:
+
+    def main! :: {
+        let exponent 'Int32 = -1;
+        write_line(\"\\(pow(2, exponent))\");
+    };
+
+Check that the exponent isn't negative, or convert the base to a Float64 first.",
+            ErrorCode::InvalidJson => "\
+parse_json was called with a string that isn't valid JSON, while the program was running.
+The error names the line and char at which parsing failed.
+
+Example:
+
+    def main! :: {
+        let json = parse_json(\"{\\\"a\\\": 1,}\");
+    };
+
+Check the string for trailing commas, missing quotes or unescaped control characters.",
+            ErrorCode::UnexpectedJsonKind => "\
+A JSON value was converted to a kind it isn't, like as_string() on a number, while the program was running.
+Missing values are JSON null, so looking up a key that doesn't exist fails when the null is converted.
+
+Example:
+
+    def main! :: {
+        let json = parse_json(\"{\\\"a\\\": 1}\");
+        write_line(json.get(\"a\").as_string());
+    };
+
+Check the value's kind() before converting it.",
+            ErrorCode::UnreachableStatement => "\
+The statement can never run, because a statement before it always returns or panics.
+The statement is removed from the program.
+
+Example:
+
+    def main! :: {
+        return;
+        write_line(\"Never printed.\");
+    };
+
+Remove the statement, or the statement that returns early.",
+            ErrorCode::StaticTypeTest => "\
+A type check or cast always has the same result, because the value's type is known at compile time.
+Type checks and casts are only useful on values of generic types.
+
+Example:
+
+    def main! :: {
+        let x 'Int32 = 5;
+        if x is Int32 :: write_line(\"Always true.\");
+    };
+
+Remove the check, or use its known result directly.",
+            ErrorCode::DuplicateConformanceWarning => "\
+The same type is declared to conform to the same trait more than once, possibly in different modules.
+Only one of the declarations is used; the warning names which.
+
+Example:
+
+    declare Int32 is Named :: { def (self 'Self).name() -> String :: \"int\"; };
+    declare Int32 is Named :: { def (self 'Self).name() -> String :: \"integer\"; };
+
+Remove all but one of the declarations. Use strict!(deny_duplicate_conformances) to make this an error (E0204).",
+            ErrorCode::ShadowedPrimitive => "\
+A trait is named like a builtin primitive, such as Int32 or Float64.
+Where the trait is visible, the name refers to it instead of the primitive, so literals and
+ operators of the primitive no longer work with values declared of that type.
+
+Example:
+
+    trait Float32 { let value 'Float64; };
+    def main! :: { let x 'Float32 = 1.5; };
+
+Rename the trait.",
+            ErrorCode::Deprecated => "\
+A function or trait declared deprecated is used. Its declaration names what to use instead.
+Functions warn where they are called, traits where they are used as a type or conformed to.
+
+Example:
+
+    ![deprecated(\"Use greeting instead.\")]
+    def old_greeting() -> String :: \"Hello\";
+    def main! :: { write_line(old_greeting()); };
+
+Follow the message of the deprecation. Use allow!(deprecated) to silence the warnings in a module.",
+            ErrorCode::Lint(_) => "\
+Reported by a lint that the tool analyzing the code supplied, rather than by Monoteny itself.
+See the tool's documentation for what the lint checks.",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
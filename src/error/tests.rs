@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::error::codes::{ErrorCode, LintCode};
+    use crate::error::RuntimeError;
+
+    #[test]
+    fn every_code_is_explained() {
+        let mut ids = HashSet::new();
+        for code in ErrorCode::ALL {
+            let id = code.id();
+            assert!(ids.insert(id.clone()), "{} is assigned twice.", id);
+            assert!(id.len() == 5 && (id.starts_with('E') || id.starts_with('W')) && id[1..].chars().all(|c| c.is_ascii_digit()), "{} is malformed.", id);
+            assert_eq!(ErrorCode::parse(&id.to_lowercase()), Some(code));
+
+            let explanation = code.explanation();
+            assert!(explanation.contains("\n\nExample:\n\n    "), "{} has no example.", id);
+        }
+
+        assert_eq!(ErrorCode::parse("E9999"), None);
+    }
+
+    #[test]
+    fn lint_codes() {
+        const LONG_FUNCTION: LintCode = LintCode { prefix: "acme", name: "long_function" };
+        let code = ErrorCode::Lint(&LONG_FUNCTION);
+        assert_eq!(code.id(), "acme::long_function");
+        // Codes are equal by their names, not by the constant they refer to.
+        assert_eq!(code, ErrorCode::Lint(&LintCode { prefix: "acme", name: "long_function" }));
+        assert_ne!(code, ErrorCode::Lint(&LintCode { prefix: "other", name: "long_function" }));
+
+        // Deep recursions in the resolver hold many errors, so lint codes mustn't grow them.
+        assert_eq!(std::mem::size_of::<RuntimeError>(), 104);
+    }
+}
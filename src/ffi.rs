@@ -0,0 +1,165 @@
+//! A C API to evaluate Monoteny code. See include/monoteny.h for the declarations.
+//! Nothing may unwind through these functions; panics are caught and reported as errors.
+
+use std::ffi::c_void;
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use annotate_snippets::Renderer;
+use itertools::Itertools;
+
+use crate::interpreter::run::CallbackWriter;
+use crate::interpreter::runtime::{EvaluatedValue, Runtime};
+
+pub type MonotenyOutputCallback = extern "C" fn(user_data: *mut c_void, text: *const u8, len: usize);
+
+/// UTF-8 text with an explicit length. It is not null-terminated.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct MonotenyString {
+    pub ptr: *const u8,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MonotenyTag {
+    None = 0,
+    Int = 1,
+    Float = 2,
+    String = 3,
+    Error = 4,
+}
+
+/// The result of an evaluation. Only the field matching the tag is set.
+/// Int holds every integer type, and Float both float types. Other values are tagged None.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct MonotenyResult {
+    pub tag: MonotenyTag,
+    pub int_value: i64,
+    pub float_value: f64,
+    pub string: MonotenyString,
+}
+
+pub struct MonotenyRuntime {
+    runtime: Box<Runtime>,
+    output: Option<(MonotenyOutputCallback, *mut c_void)>,
+    /// Strings handed out by the last evaluation, kept alive until the next one.
+    last_string: String,
+}
+
+impl MonotenyString {
+    fn empty() -> MonotenyString {
+        MonotenyString { ptr: std::ptr::null(), len: 0 }
+    }
+
+    fn of(string: &str) -> MonotenyString {
+        MonotenyString { ptr: string.as_ptr(), len: string.len() }
+    }
+}
+
+impl MonotenyResult {
+    fn none() -> MonotenyResult {
+        MonotenyResult { tag: MonotenyTag::None, int_value: 0, float_value: 0.0, string: MonotenyString::empty() }
+    }
+}
+
+/// Creates a runtime with the bundled libraries. Returns null if that fails.
+/// It must be freed with monoteny_runtime_free.
+#[no_mangle]
+pub extern "C" fn monoteny_runtime_new() -> *mut MonotenyRuntime {
+    let runtime = catch_unwind(|| {
+        let mut runtime = Runtime::new().ok()?;
+        runtime.repository.add_bundled("common");
+        Some(runtime)
+    });
+
+    match runtime {
+        Ok(Some(runtime)) => Box::into_raw(Box::new(MonotenyRuntime { runtime, output: None, last_string: String::new() })),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a runtime created by monoteny_runtime_new. Null is ignored.
+///
+/// # Safety
+/// The runtime must be null or come from monoteny_runtime_new, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn monoteny_runtime_free(runtime: *mut MonotenyRuntime) {
+    if !runtime.is_null() {
+        drop(Box::from_raw(runtime));
+    }
+}
+
+/// Passes everything the evaluated code prints to the callback, along with user_data.
+/// Without a callback, output goes to stdout.
+///
+/// # Safety
+/// The runtime must come from monoteny_runtime_new and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn monoteny_set_output_callback(runtime: *mut MonotenyRuntime, callback: Option<MonotenyOutputCallback>, user_data: *mut c_void) {
+    let runtime = &mut *runtime;
+    runtime.output = callback.map(|callback| (callback, user_data));
+}
+
+/// Runs the source as the body of main!, with common imported.
+/// If the last statement is a number or a string, the result holds its value.
+/// On failure, the result is tagged Error, and its string and out_err hold the rendered errors.
+/// Strings in the result stay valid until the next evaluation on the runtime, or until it is freed.
+///
+/// # Safety
+/// The runtime must come from monoteny_runtime_new and not be freed yet.
+/// The source must point to len readable bytes, and out_err must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn monoteny_eval(runtime: *mut MonotenyRuntime, source: *const u8, len: usize, out_err: *mut MonotenyString) -> MonotenyResult {
+    let runtime = &mut *runtime;
+    runtime.last_string.clear();
+    if !out_err.is_null() {
+        *out_err = MonotenyString::empty();
+    }
+
+    let result = match std::str::from_utf8(std::slice::from_raw_parts(source, len)) {
+        Err(err) => Err(format!("Source is not valid UTF-8: {}", err)),
+        Ok(source) => {
+            let output = runtime.output;
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                let mut out: Box<dyn Write> = match output {
+                    Some((callback, user_data)) => Box::new(CallbackWriter(move |text: &str| callback(user_data, text.as_ptr(), text.len()))),
+                    None => Box::new(std::io::stdout()),
+                };
+                runtime.runtime.evaluate_value(source, &mut out)
+            }));
+
+            match result {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(errors)) => Err(errors.iter().map(|error| error.render_in_source(source, "<eval>", &Renderer::plain())).join("\n\n")),
+                Err(panic) => Err(format!("Internal error: {}", panic_message(&panic))),
+            }
+        }
+    };
+
+    match result {
+        Ok(None) => MonotenyResult::none(),
+        Ok(Some(EvaluatedValue::Int(value))) => MonotenyResult { tag: MonotenyTag::Int, int_value: value, ..MonotenyResult::none() },
+        Ok(Some(EvaluatedValue::Float(value))) => MonotenyResult { tag: MonotenyTag::Float, float_value: value, ..MonotenyResult::none() },
+        Ok(Some(EvaluatedValue::String(value))) => {
+            runtime.last_string = value;
+            MonotenyResult { tag: MonotenyTag::String, string: MonotenyString::of(&runtime.last_string), ..MonotenyResult::none() }
+        }
+        Err(error) => {
+            runtime.last_string = error;
+            let string = MonotenyString::of(&runtime.last_string);
+            if !out_err.is_null() {
+                *out_err = string;
+            }
+            MonotenyResult { tag: MonotenyTag::Error, string, ..MonotenyResult::none() }
+        }
+    }
+}
+
+fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> &str {
+    panic.downcast_ref::<&str>().copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("panic")
+}
@@ -6,5 +6,10 @@ pub mod builtins;
 pub mod opcode;
 pub mod disassembler;
 pub mod data;
+pub mod strings;
+pub mod stats;
+pub mod coverage;
+pub mod escape;
+pub mod json;
 pub mod runtime;
 mod tests;
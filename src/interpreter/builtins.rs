@@ -1,36 +1,59 @@
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::Arc;
+use log::debug;
 use crate::error::RResult;
 
 use crate::interpreter::compiler::InlineFunction;
 use crate::interpreter::opcode::{OpCode, Primitive};
 use crate::interpreter::runtime::Runtime;
-use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, PrimitiveOperation};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, JsonOperation, ListOperation, PrimitiveOperation};
 use crate::program::module::module_name;
 use crate::program::primitives;
 
-pub fn load(runtime: &mut Runtime) -> RResult<()> {
+pub fn load(runtime: &mut Runtime, embedded_std: bool) -> RResult<()> {
     // -------------------------------------- ------ --------------------------------------
     // -------------------------------------- Monoteny files --------------------------------------
     // -------------------------------------- ------ --------------------------------------
 
-    runtime.repository.add("core", PathBuf::from("monoteny"));
+    runtime.repository.add_std("core", embedded_std);
     runtime.get_or_load_module(&module_name("core"))?;
 
     for function in runtime.source.module_by_name[&module_name("core.debug")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        runtime.function_inlines.insert(Rc::clone(function), match representation.name.as_str() {
+        runtime.function_inlines.insert(Arc::clone(function), match representation.name.as_str() {
             "_write_line" => inline_fn_push(OpCode::PRINT),
             "_exit_with_error" => inline_fn_push(OpCode::PANIC),
             _ => continue,
         });
     }
 
+    for function in runtime.source.module_by_name[&module_name("core.console")].explicit_functions(&runtime.source) {
+        let representation = &runtime.source.fn_representations[function];
+
+        runtime.function_inlines.insert(Arc::clone(function), match (representation.name.as_str(), runtime.source.fn_logic.get(function)) {
+            // The only argument is the metatype, which has no runtime representation.
+            ("call_as_function", Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::Constructor(struct_)))) => match struct_.trait_.name.as_str() {
+                // StdOut has no state; the VM writes to its own out.
+                "StdOut" => Arc::new(|compiler, _, _| {
+                    compiler.chunk.push_load(0);
+                    Ok(None)
+                }),
+                "StringConsole" => Arc::new(|compiler, _, _| {
+                    compiler.chunk.push(OpCode::BUFFER_NEW);
+                    Ok(None)
+                }),
+                _ => continue,
+            },
+            ("_push_line", _) => inline_fn_push(OpCode::BUFFER_PUSH_LINE),
+            ("contents", _) => inline_fn_push(OpCode::BUFFER_COPY),
+            _ => continue,
+        });
+    }
+
     for function in runtime.source.module_by_name[&module_name("core.transpilation")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        runtime.function_inlines.insert(Rc::clone(function), match representation.name.as_str() {
+        runtime.function_inlines.insert(Arc::clone(function), match representation.name.as_str() {
             "add" => inline_fn_push(OpCode::TRANSPILE_ADD),
             _ => continue,
         });
@@ -39,7 +62,7 @@ pub fn load(runtime: &mut Runtime) -> RResult<()> {
     for function in runtime.source.module_by_name[&module_name("core.bool")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        runtime.function_inlines.insert(Rc::clone(function), match representation.name.as_str() {
+        runtime.function_inlines.insert(Arc::clone(function), match representation.name.as_str() {
             "true" => inline_fn_push_with_u8(OpCode::LOAD8, true as u8),
             "false" => inline_fn_push_with_u8(OpCode::LOAD8, false as u8),
             _ => continue,
@@ -49,8 +72,14 @@ pub fn load(runtime: &mut Runtime) -> RResult<()> {
     for function in runtime.source.module_by_name[&module_name("core.strings")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        runtime.function_inlines.insert(Rc::clone(function), match representation.name.as_str() {
+        runtime.function_inlines.insert(Arc::clone(function), match representation.name.as_str() {
             "add" => inline_fn_push(OpCode::ADD_STRING),
+            "_is_equal_string" => inline_fn_push(OpCode::EQ_STRING),
+            "_is_not_equal_string" => inline_fn_push(OpCode::NEQ_STRING),
+            "_is_greater_string" => inline_fn_push(OpCode::GR_STRING),
+            "_is_greater_or_equal_string" => inline_fn_push(OpCode::GR_EQ_STRING),
+            "_is_lesser_string" => inline_fn_push(OpCode::LE_STRING),
+            "_is_lesser_or_equal_string" => inline_fn_push(OpCode::LE_EQ_STRING),
             _ => continue,
         });
     }
@@ -64,7 +93,7 @@ pub fn load(runtime: &mut Runtime) -> RResult<()> {
             continue;
         };
 
-        runtime.function_inlines.insert(Rc::clone(function), match descriptor {
+        runtime.function_inlines.insert(Arc::clone(function), match descriptor {
             FunctionLogicDescriptor::Stub => todo!(),
             FunctionLogicDescriptor::TraitProvider(_) => continue,
             FunctionLogicDescriptor::FunctionProvider(_) => continue,
@@ -74,9 +103,24 @@ pub fn load(runtime: &mut Runtime) -> RResult<()> {
             FunctionLogicDescriptor::Constructor(_) => todo!(),
             FunctionLogicDescriptor::GetMemberField(_, _) => todo!(),
             FunctionLogicDescriptor::SetMemberField(_, _) => todo!(),
+            FunctionLogicDescriptor::ListOperation(operation) => {
+                compile_list_operation(operation)
+            }
+            FunctionLogicDescriptor::JsonOperation(operation) => {
+                compile_json_operation(operation)
+            }
+            // Enums are declared in code; compile_descriptor compiles their variants when used.
+            FunctionLogicDescriptor::EnumVariant(_, _) => unreachable!("The builtins declare no enums."),
+            // Calls are substituted during monomorphization.
+            FunctionLogicDescriptor::Reflection(_) => continue,
+            // Boxes are made for each trait; compile_descriptor compiles them when used.
+            FunctionLogicDescriptor::DynBox(_) => continue,
+            FunctionLogicDescriptor::DynDispatch(_) => continue,
         });
     }
 
+    debug!("Registered {} intrinsics", runtime.function_inlines.len());
+
     Ok(())
 }
 
@@ -93,27 +137,41 @@ pub fn primitive_from_primitive(primitive: &primitives::Type) -> Primitive {
         primitives::Type::UInt(64) => Primitive::U64,
         primitives::Type::Float(32) => Primitive::F32,
         primitives::Type::Float(64) => Primitive::F64,
+        primitives::Type::Char => Primitive::CHAR,
         _ => todo!("Unsupported type: {:?}", primitive)
     }
 }
 
 pub fn inline_fn_push(opcode: OpCode) -> InlineFunction {
-    Rc::new(move |compiler, expression| {{
-        let arguments = &compiler.implementation.expression_tree.children[expression];
-        for arg in arguments { compiler.compile_expression(arg)? }
+    Arc::new(move |compiler, expression, step| {{
+        if let Some(argument) = compiler.implementation.expression_tree.children[expression].get(step) {
+            return Ok(Some(*argument))
+        }
 
         compiler.chunk.push(opcode);
-        Ok(())
+        Ok(None)
     }})
 }
 
 pub fn inline_fn_push_with_u8(opcode: OpCode, arg: u8) -> InlineFunction {
-    Rc::new(move |compiler, expression| {{
-        let arguments = &compiler.implementation.expression_tree.children[expression];
-        for arg in arguments { compiler.compile_expression(arg)? }
+    Arc::new(move |compiler, expression, step| {{
+        if let Some(argument) = compiler.implementation.expression_tree.children[expression].get(step) {
+            return Ok(Some(*argument))
+        }
 
         compiler.chunk.push_with_u8(opcode, arg);
-        Ok(())
+        Ok(None)
+    }})
+}
+
+pub fn inline_fn_push_with_u32(opcode: OpCode, arg: u32) -> InlineFunction {
+    Arc::new(move |compiler, expression, step| {{
+        if let Some(argument) = compiler.implementation.expression_tree.children[expression].get(step) {
+            return Ok(Some(*argument))
+        }
+
+        compiler.chunk.push_with_u32(opcode, arg);
+        Ok(None)
     }})
 }
 
@@ -121,42 +179,55 @@ pub fn compile_primitive_operation(operation: &PrimitiveOperation, type_: &primi
     let primitive = primitive_from_primitive(type_) as u8;
 
     match operation {
-        PrimitiveOperation::And => Rc::new(move |compiler, expression| {
-            let arguments = &compiler.implementation.expression_tree.children[&expression];
-
-            // lhs
-            compiler.compile_expression(&arguments[0])?;
-
-            compiler.chunk.push(OpCode::DUP64);
-            let jump_location_skip_rhs = compiler.chunk.code.len();
-            compiler.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
-
-            // rhs
-            compiler.compile_expression(&arguments[1])?;
-            compiler.chunk.push(OpCode::AND);
-
-            compiler.fix_jump_location_i32(jump_location_skip_rhs);
-
-            Ok(())
+        PrimitiveOperation::And => Arc::new(move |compiler, expression, step| {
+            let arguments = &compiler.implementation.expression_tree.children[expression];
+
+            match step {
+                // lhs
+                0 => Ok(Some(arguments[0])),
+                1 => {
+                    compiler.chunk.push(OpCode::DUP64);
+                    compiler.jumps.push(compiler.chunk.code.len());
+                    compiler.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+
+                    // rhs
+                    Ok(Some(arguments[1]))
+                }
+                _ => {
+                    compiler.chunk.push(OpCode::AND);
+
+                    let jump_location_skip_rhs = compiler.jumps.pop().unwrap();
+                    compiler.fix_jump_location_i32(jump_location_skip_rhs);
+
+                    Ok(None)
+                }
+            }
         }),
-        PrimitiveOperation::Or => Rc::new(move |compiler, expression| {
-            let arguments = &compiler.implementation.expression_tree.children[&expression];
-
-            // lhs
-            compiler.compile_expression(&arguments[0])?;
-            compiler.chunk.push(OpCode::DUP64);
-            compiler.chunk.push(OpCode::NOT);
-
-            let jump_location_skip_rhs = compiler.chunk.code.len();
-            compiler.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
-
-            // rhs
-            compiler.compile_expression(&arguments[1])?;
-            compiler.chunk.push(OpCode::OR);
-
-            compiler.fix_jump_location_i32(jump_location_skip_rhs);
-
-            Ok(())
+        PrimitiveOperation::Or => Arc::new(move |compiler, expression, step| {
+            let arguments = &compiler.implementation.expression_tree.children[expression];
+
+            match step {
+                // lhs
+                0 => Ok(Some(arguments[0])),
+                1 => {
+                    compiler.chunk.push(OpCode::DUP64);
+                    compiler.chunk.push(OpCode::NOT);
+
+                    compiler.jumps.push(compiler.chunk.code.len());
+                    compiler.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+
+                    // rhs
+                    Ok(Some(arguments[1]))
+                }
+                _ => {
+                    compiler.chunk.push(OpCode::OR);
+
+                    let jump_location_skip_rhs = compiler.jumps.pop().unwrap();
+                    compiler.fix_jump_location_i32(jump_location_skip_rhs);
+
+                    Ok(None)
+                }
+            }
         }),
         PrimitiveOperation::Not => inline_fn_push(OpCode::NOT),
         PrimitiveOperation::Negative => inline_fn_push_with_u8(OpCode::NEG, primitive),
@@ -175,6 +246,39 @@ pub fn compile_primitive_operation(operation: &PrimitiveOperation, type_: &primi
         PrimitiveOperation::LesserThanOrEqual => inline_fn_push_with_u8(OpCode::LE_EQ, primitive),
         PrimitiveOperation::ParseIntString => inline_fn_push_with_u8(OpCode::PARSE, primitive),
         PrimitiveOperation::ParseRealString => inline_fn_push_with_u8(OpCode::PARSE, primitive),
+        PrimitiveOperation::ParseCharString => inline_fn_push_with_u8(OpCode::PARSE, primitive),
+        PrimitiveOperation::CharFromCode => inline_fn_push(OpCode::CHAR_FROM_CODE),
+        // Chars are stored as their code already.
+        PrimitiveOperation::CharToCode => inline_fn_push(OpCode::NOOP),
         PrimitiveOperation::ToString => inline_fn_push_with_u8(OpCode::TO_STRING, primitive),
     }
 }
+
+pub fn compile_list_operation(operation: &ListOperation) -> InlineFunction {
+    match operation {
+        // The only argument is the metatype, which has no runtime representation.
+        ListOperation::New => Arc::new(move |compiler, _, _| {
+            compiler.chunk.push(OpCode::LIST_NEW);
+            Ok(None)
+        }),
+        ListOperation::Push => inline_fn_push(OpCode::LIST_PUSH),
+        ListOperation::Pop => inline_fn_push(OpCode::LIST_POP),
+        ListOperation::Length => inline_fn_push(OpCode::LIST_LEN),
+        ListOperation::Get => inline_fn_push(OpCode::LIST_GET),
+        ListOperation::Set => inline_fn_push(OpCode::LIST_SET),
+        ListOperation::FromChars => inline_fn_push(OpCode::STRING_CHARS),
+    }
+}
+
+pub fn compile_json_operation(operation: &JsonOperation) -> InlineFunction {
+    inline_fn_push(match operation {
+        JsonOperation::Parse => OpCode::JSON_PARSE,
+        JsonOperation::Get => OpCode::JSON_GET,
+        JsonOperation::At => OpCode::JSON_AT,
+        JsonOperation::Length => OpCode::JSON_LEN,
+        JsonOperation::Kind => OpCode::JSON_KIND,
+        JsonOperation::AsString => OpCode::JSON_AS_STRING,
+        JsonOperation::AsFloat => OpCode::JSON_AS_FLOAT,
+        JsonOperation::AsBool => OpCode::JSON_AS_BOOL,
+    })
+}
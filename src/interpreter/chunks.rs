@@ -1,11 +1,34 @@
+use std::ops::Range;
 use std::ptr::write_unaligned;
+use uuid::Uuid;
 use crate::interpreter::data::Value;
 use crate::interpreter::opcode::OpCode;
 
+#[derive(Clone)]
 pub struct Chunk {
     pub code: Vec<u8>,
     pub locals_count: u32,
+    /// Parameters are the first locals. When called, the arguments are moved to them.
+    pub parameters_count: u32,
+    /// Whether the value left on the stack is returned to the caller.
+    pub returns_value: bool,
     pub constants: Vec<Value>,
+    /// The function the chunk was compiled from, before it was monomorphized.
+    pub function: Option<Uuid>,
+    /// Source ranges of expressions in the function's file, by the offset their code starts at.
+    pub positions: Vec<(usize, Range<usize>)>,
+}
+
+// SAFETY: Chunks hold raw pointers only in their constants, and the compiler only puts strings of the
+//  StringInterner there. Interned strings are never written to or freed, so chunks may move between threads.
+unsafe impl Send for Chunk {}
+// SAFETY: Chunks aren't changed after compilation, and the strings their constants point to are immutable.
+unsafe impl Sync for Chunk {}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chunk {
@@ -13,7 +36,11 @@ impl Chunk {
         Chunk {
             code: vec![],
             locals_count: 0,
+            parameters_count: 0,
+            returns_value: false,
             constants: vec![],
+            function: None,
+            positions: vec![],
         }
     }
 
@@ -22,7 +49,11 @@ impl Chunk {
     }
 
     pub fn push_with_u8(&mut self, code: OpCode, arg: u8) {
-        unsafe { self.code.extend([code as u8, arg]) }
+        self.code.extend([code as u8, arg])
+    }
+
+    pub fn push_with_u8_u8(&mut self, code: OpCode, arg0: u8, arg1: u8) {
+        self.code.extend([code as u8, arg0, arg1])
     }
 
     pub fn push_with_u16(&mut self, code: OpCode, arg: u16) {
@@ -69,6 +100,37 @@ impl Chunk {
         }
     }
 
+    /// Loads the value with the narrowest operand it fits into.
+    /// The narrow loads zero the rest of the value.
+    pub fn push_load(&mut self, value: u64) {
+        if let Ok(value) = u8::try_from(value) {
+            self.push_with_u8(OpCode::LOAD8, value)
+        }
+        else if let Ok(value) = u16::try_from(value) {
+            self.push_with_u16(OpCode::LOAD16, value)
+        }
+        else if let Ok(value) = u32::try_from(value) {
+            self.push_with_u32(OpCode::LOAD32, value)
+        }
+        else {
+            self.push_with_u64(OpCode::LOAD64, value)
+        }
+    }
+
+    pub fn push_load_local(&mut self, slot: u32) {
+        match u8::try_from(slot) {
+            Ok(slot) => self.push_with_u8(OpCode::LOAD_LOCAL_8, slot),
+            Err(_) => self.push_with_u32(OpCode::LOAD_LOCAL, slot),
+        }
+    }
+
+    pub fn push_store_local(&mut self, slot: u32) {
+        match u8::try_from(slot) {
+            Ok(slot) => self.push_with_u8(OpCode::STORE_LOCAL_8, slot),
+            Err(_) => self.push_with_u32(OpCode::STORE_LOCAL, slot),
+        }
+    }
+
     pub fn modify_u32(&mut self, position: usize, arg: u32) {
         unsafe {
             write_unaligned(self.code.as_mut_ptr().add(position) as *mut u32, arg);
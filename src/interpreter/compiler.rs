@@ -1,32 +1,60 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::mem::transmute;
-use std::rc::Rc;
+use std::sync::Arc;
 use itertools::Itertools;
+use log::debug;
+use uuid::Uuid;
 use crate::error::{RuntimeError, RResult};
+use crate::interpreter::builtins::{compile_json_operation, compile_list_operation, compile_primitive_operation, inline_fn_push_with_u32};
 use crate::interpreter::chunks::Chunk;
-use crate::interpreter::data::{string_to_ptr, Value};
+use crate::interpreter::data::Value;
+use crate::interpreter::escape;
 use crate::interpreter::opcode::OpCode;
 use crate::interpreter::runtime::Runtime;
-use crate::program::allocation::ObjectReference;
+use crate::interpreter::strings::StringInterner;
+use crate::program::allocation::{Mutability, ObjectReference};
 use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
+use crate::program::function_object::FunctionRepresentation;
 use crate::program::functions::FunctionHead;
+use crate::program::traits::Trait;
+use crate::program::types::{TypeProto, TypeUnit};
+use crate::program::calls::FunctionBinding;
+use crate::source::StructInfo;
 use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor};
 use crate::refactor::Refactor;
 use crate::refactor::simplify::Simplify;
 use crate::transpiler;
 
-pub type InlineFunction = Rc<dyn Fn(&mut FunctionCompiler, &ExpressionID) -> RResult<()>>;
+/// Compiles a call to the function in steps, like `FunctionCompiler::compile_step`.
+/// Inlines are shared by bodies compiled on different threads.
+pub type InlineFunction = Arc<dyn Fn(&mut FunctionCompiler, &ExpressionID, usize) -> RResult<Option<ExpressionID>> + Send + Sync>;
+
+/// Below this many bodies per thread, starting the threads takes longer than compiling the bodies.
+const MIN_FUNCTIONS_PER_THREAD: usize = 16;
+
+/// Everything compiling a function body reads from the runtime.
+/// Bodies don't change it, so they can be compiled in any order and their chunks merged afterwards.
+pub struct CompileContext<'a> {
+    pub function_inlines: &'a HashMap<Arc<FunctionHead>, InlineFunction>,
+    pub strings: &'a StringInterner,
+    /// The logic of every function the bodies may call, after monomorphization.
+    pub fn_logic: &'a HashMap<Arc<FunctionHead>, FunctionLogic>,
+    pub allocated_structs: &'a HashMap<Arc<Trait>, u32>,
+}
 
 pub struct FunctionCompiler<'a> {
-    pub runtime: &'a Runtime,
+    pub context: &'a CompileContext<'a>,
     pub implementation: &'a FunctionImplementation,
     pub chunk: Chunk,
-    pub locals: HashMap<Rc<ObjectReference>, u32>,
+    pub locals: HashMap<Arc<ObjectReference>, u32>,
+    /// Locals whose struct doesn't escape, with the first of the consecutive locals holding its fields.
+    pub local_structs: HashMap<Arc<ObjectReference>, (u32, Arc<StructInfo>)>,
     pub constants: Vec<Value>,
+    /// Locations of jumps whose target is still being compiled.
+    pub jumps: Vec<usize>,
 }
 
-pub fn compile_deep(runtime: &mut Runtime, function: &Rc<FunctionHead>) -> RResult<Chunk> {
+pub fn compile_deep(runtime: &mut Runtime, function: &Arc<FunctionHead>) -> RResult<Chunk> {
     let FunctionLogic::Implementation(implementation) = runtime.source.fn_logic[function].clone() else {
         return Err(RuntimeError::error("main! function was somehow internal.").to_array());
     };
@@ -39,10 +67,23 @@ pub fn compile_deep(runtime: &mut Runtime, function: &Rc<FunctionHead>) -> RResu
     simplify.run();
 
     let needed_functions = refactor.gather_needed_functions();
+    // Chunks of monomorphized functions are attributed to the function they were copied from.
+    let origins: HashMap<Uuid, Uuid> = needed_functions.iter()
+        .map(|head| (head.function_id, refactor.original_function(head).function_id))
+        .collect();
     let fn_logic = refactor.fn_logic;
+    let fn_representations = refactor.fn_representations;
 
-    let mut errors = vec![];
+    // Global constants are evaluated by the VM on first use, and memoized.
+    //  Their loads must be known before any caller is compiled.
+    for function in needed_functions.iter() {
+        if matches!(fn_logic[function], FunctionLogic::Implementation(_)) && fn_representations[function].is_global_constant(function) {
+            compile_global_constant_load(function, &fn_representations[function], runtime);
+        }
+    }
 
+    // Descriptors are registered first, since bodies are compiled against them.
+    let mut implementations = vec![];
     for function in needed_functions {
         match &fn_logic[&function] {
             FunctionLogic::Descriptor(d) => {
@@ -52,21 +93,32 @@ pub fn compile_deep(runtime: &mut Runtime, function: &Rc<FunctionHead>) -> RResu
 
                 compile_descriptor(&function, d, runtime);
             }
-            FunctionLogic::Implementation(implementation) => {
-                match compile_function(runtime, implementation) {
-                    Ok(compiled) => drop(runtime.function_evaluators.insert(function.function_id, compiled)),
-                    Err(err) => errors.extend(err),
-                };
-            }
+            FunctionLogic::Implementation(implementation) => implementations.push(implementation.as_ref()),
         }
     }
 
-    let FunctionLogic::Implementation(implementation) = &fn_logic[function] else {
-        errors.push(RuntimeError::error("main! function was somehow internal after refactor."));
-        return Err(errors);
+    let FunctionLogic::Implementation(entry_implementation) = &fn_logic[function] else {
+        return Err(RuntimeError::error("main! function was somehow internal after refactor.").to_array());
+    };
+
+    let context = CompileContext {
+        function_inlines: &runtime.function_inlines,
+        strings: &runtime.strings,
+        fn_logic: &fn_logic,
+        allocated_structs: &runtime.allocated_structs,
     };
+    let compiled = compile_functions(&context, &implementations, &origins);
+    let entry = compile_function(&context, entry_implementation, Some(function.function_id));
+
+    let mut errors = vec![];
+    for (function_id, chunk) in compiled {
+        match chunk {
+            Ok(chunk) => drop(runtime.function_evaluators.insert(function_id, chunk)),
+            Err(err) => errors.extend(err),
+        }
+    }
 
-    match compile_function(runtime, implementation) {
+    match entry {
         Ok(compiled) => {
             if !errors.is_empty() { Err(errors) }
             else { Ok(compiled) }
@@ -78,133 +130,415 @@ pub fn compile_deep(runtime: &mut Runtime, function: &Rc<FunctionHead>) -> RResu
     }
 }
 
-fn compile_function(runtime: &mut Runtime, implementation: &FunctionImplementation) -> RResult<Chunk> {
+/// Compiles the function like compile_deep, unless it was compiled before.
+/// The chunk is kept in the runtime's function_evaluators until the function (or one it calls) is changed.
+pub fn compile_cached(runtime: &mut Runtime, function: &Arc<FunctionHead>) -> RResult<()> {
+    if !runtime.function_evaluators.contains_key(&function.function_id) {
+        let chunk = compile_deep(runtime, function)?;
+        runtime.function_evaluators.insert(function.function_id, chunk);
+    }
+    Ok(())
+}
+
+/// Compiles the bodies on worker threads, each taking an even share of them.
+/// Threads are only worth starting for larger programs, so small ones are compiled on this thread.
+fn compile_functions(context: &CompileContext, implementations: &[&FunctionImplementation], origins: &HashMap<Uuid, Uuid>) -> Vec<(Uuid, RResult<Chunk>)> {
+    let compile = |implementation: &&FunctionImplementation| {
+        (implementation.head.function_id, compile_function(context, implementation, origins.get(&implementation.head.function_id).copied()))
+    };
+
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get())
+        .min(implementations.len() / MIN_FUNCTIONS_PER_THREAD);
+    if threads <= 1 {
+        return implementations.iter().map(compile).collect_vec()
+    }
+
+    std::thread::scope(|scope| {
+        implementations.chunks(implementations.len().div_ceil(threads))
+            .map(|share| scope.spawn(move || share.iter().map(compile).collect_vec()))
+            .collect_vec().into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect_vec()
+    })
+}
+
+fn compile_function(context: &CompileContext, implementation: &FunctionImplementation, origin: Option<Uuid>) -> RResult<Chunk> {
     let mut compiler = FunctionCompiler {
-        runtime,
+        context,
         implementation,
         chunk: Chunk::new(),
         locals: HashMap::new(),
+        local_structs: HashMap::new(),
         constants: vec![],
+        jumps: vec![],
     };
 
+    // Callers move the arguments to the first locals.
+    for parameter in implementation.parameter_locals.iter() {
+        compiler.get_variable_slot(parameter);
+    }
+    for (local, struct_) in escape::find_local_structs(implementation, context.fn_logic) {
+        let first_slot = compiler.chunk.locals_count;
+        compiler.chunk.locals_count += u32::try_from(struct_.fields.len()).unwrap();
+        compiler.local_structs.insert(local, (first_slot, struct_));
+    }
+
     compiler.compile_expression(&implementation.expression_tree.root)?;
     // The root expression is implicitly returned.
     compiler.chunk.push(OpCode::RETURN);
 
-    compiler.chunk.locals_count = u32::try_from(compiler.locals.len()).unwrap();
+    compiler.chunk.parameters_count = u32::try_from(implementation.parameter_locals.len()).unwrap();
+    compiler.chunk.returns_value = !implementation.head.interface.return_type.unit.is_void();
     compiler.chunk.constants = compiler.constants;
+    compiler.chunk.function = origin;
 
-    // println!("{:?}", implementation.head);
-    // disassemble(&compiler.chunk);
-    // println!("\n");
+    debug!("Compiled {:?} ({} bytes, {} locals)", implementation.head, compiler.chunk.code.len(), compiler.chunk.locals_count);
 
     Ok(compiler.chunk)
 }
 
 impl FunctionCompiler<'_> {
     pub fn compile_expression(&mut self, expression: &ExpressionID) -> RResult<()> {
+        // Expressions may be nested arbitrarily deep, so we keep our own stack instead of recursing.
+        let mut stack = vec![(*expression, 0)];
+        while let Some((expression, step)) = stack.pop() {
+            if let Some(argument) = self.compile_step(&expression, step)? {
+                stack.push((expression, step + 1));
+                stack.push((argument, 0));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the part of the expression that comes before its argument at `step`,
+    ///  and returns the argument to compile next, or None if the expression is complete.
+    fn compile_step(&mut self, expression: &ExpressionID, step: usize) -> RResult<Option<ExpressionID>> {
         let operation = &self.implementation.expression_tree.values[expression];
+        let arguments = &self.implementation.expression_tree.children[expression];
+
+        if step == 0 {
+            if let Some(range) = self.implementation.expression_ranges.get(expression) {
+                self.chunk.positions.push((self.chunk.code.len(), range.clone()));
+            }
+        }
 
         match operation {
             ExpressionOperation::Block => {
-                let arguments = &self.implementation.expression_tree.children[expression];
-                for expr in arguments {
-                    self.compile_expression(expr)?;
-                    let type_ = &self.implementation.type_forest.resolve_binding_alias(expr)?;
-                    if !type_.unit.is_void() {
+                if let Some(previous) = step.checked_sub(1).map(|idx| &arguments[idx]) {
+                    // Unless the block is void, its last statement is its value.
+                    let keeps_value = !self.implementation.type_forest.resolve_binding_alias(expression)?.unit.is_void();
+                    let type_ = &self.implementation.type_forest.resolve_binding_alias(previous)?;
+                    if !(type_.unit.is_void() || keeps_value && step == arguments.len()) {
                         self.chunk.push(OpCode::POP64);
                     }
                 }
+
+                return Ok(arguments.get(step).cloned())
             },
             ExpressionOperation::GetLocal(local) => {
                 let slot = self.get_variable_slot(local);
-                self.chunk.push_with_u32(OpCode::LOAD_LOCAL, slot);
+                self.chunk.push_load_local(slot);
             },
             ExpressionOperation::SetLocal(local) => {
-                let arguments = &self.implementation.expression_tree.children[expression];
                 assert_eq!(arguments.len(), 1);
-                self.compile_expression(&arguments[0])?;
+                if let Some((first_slot, struct_)) = self.local_structs.get(local) {
+                    // The constructor's arguments are stored to the fields' locals, last field first.
+                    let [_, fields @ ..] = &self.implementation.expression_tree.children[&arguments[0]][..] else {
+                        panic!("Constructor call without a metatype.")
+                    };
+                    if let Some(field) = fields.get(step) {
+                        return Ok(Some(*field))
+                    }
+                    for idx in (0..struct_.fields.len()).rev() {
+                        self.chunk.push_store_local(first_slot + u32::try_from(idx).unwrap());
+                    }
+                    return Ok(None)
+                }
+
+                if step == 0 {
+                    return Ok(Some(arguments[0]))
+                }
+                if let Some(count) = self.copied_struct_fields(local, &arguments[0])? {
+                    self.chunk.push_with_u32(OpCode::COPY_STRUCT, count);
+                }
                 let slot = self.get_variable_slot(local);
-                self.chunk.push_with_u32(OpCode::STORE_LOCAL, slot);
+                self.chunk.push_store_local(slot);
+            },
+            ExpressionOperation::Return => {
+                if let Some(argument) = arguments.get(step) {
+                    return Ok(Some(*argument))
+                }
+                self.chunk.push(OpCode::RETURN);
             },
-            ExpressionOperation::Return => todo!(),
             ExpressionOperation::FunctionCall(function) => {
-                if let Some(inline_fn) = self.runtime.function_inlines.get(&function.function) {
-                    inline_fn(self, expression);
+                if let Some((slot, is_set)) = self.local_field_access(function, arguments) {
+                    match (is_set, step) {
+                        (false, _) => self.chunk.push_load_local(slot),
+                        (true, 0) => return Ok(Some(arguments[1])),
+                        (true, _) => self.chunk.push_store_local(slot),
+                    }
+                    return Ok(None)
                 }
-                else {
-                    todo!()
+
+                if let Some(inline_fn) = self.context.function_inlines.get(&function.function) {
+                    return inline_fn(self, expression, step)
                 }
+
+                // The callee's body is compiled alongside ours, and runs in its own VM.
+                if let Some(argument) = arguments.get(step) {
+                    return Ok(Some(*argument))
+                }
+                self.chunk.push_with_u128(OpCode::CALL, function.function.function_id.as_u128());
             },
             ExpressionOperation::PairwiseOperations { .. } => todo!(),
-            ExpressionOperation::ArrayLiteral => todo!(),
+            ExpressionOperation::ArrayLiteral => {
+                // Each element is pushed to a copy of the list, so the list stays on the stack.
+                match step {
+                    0 => self.chunk.push(OpCode::LIST_NEW),
+                    _ => self.chunk.push(OpCode::LIST_PUSH),
+                }
+                if let Some(element) = arguments.get(step) {
+                    self.chunk.push(OpCode::DUP64);
+                    return Ok(Some(*element))
+                }
+            },
             ExpressionOperation::StringLiteral(string) => {
-                unsafe {
-                    self.constants.push(Value { ptr: string_to_ptr(string) });
-                    self.chunk.push_with_u32(OpCode::LOAD_CONSTANT, u32::try_from(self.constants.len() - 1).unwrap());
+                self.constants.push(Value { ptr: self.context.strings.intern(string) });
+                self.chunk.push_with_u32(OpCode::LOAD_CONSTANT, u32::try_from(self.constants.len() - 1).unwrap());
+            },
+            ExpressionOperation::TypeCheck(trait_) => {
+                let [subject] = arguments[..] else {
+                    panic!("Type check got the wrong number of arguments.")
+                };
+                if step == 0 {
+                    return Ok(Some(subject))
+                }
+
+                let subject_type = self.implementation.type_forest.resolve_binding_alias(&subject)?;
+                if self.is_allocated_struct(&subject_type) {
+                    self.chunk.push_with_u128(OpCode::LOAD128, trait_.id.as_u128());
+                    self.chunk.push(OpCode::IS_TYPE);
+                }
+                else {
+                    // Other values carry no type, but after monomorphization, every type is exact.
+                    self.chunk.push(OpCode::POP64);
+                    self.chunk.push_with_u8(OpCode::LOAD8, subject_type.is_instance_of(trait_) as u8);
+                }
+            },
+            ExpressionOperation::TypeCast(trait_) => {
+                let [subject] = arguments[..] else {
+                    panic!("Type cast got the wrong number of arguments.")
+                };
+                if step == 0 {
+                    return Ok(Some(subject))
+                }
+
+                let subject_type = self.implementation.type_forest.resolve_binding_alias(&subject)?;
+                let message = format!("Cannot cast {:?} to {}.", subject_type, trait_.name);
+                if self.is_allocated_struct(&subject_type) {
+                    self.chunk.push(OpCode::DUP64);
+                    self.chunk.push_with_u128(OpCode::LOAD128, trait_.id.as_u128());
+                    self.chunk.push(OpCode::IS_TYPE);
+                    let jump_location_skip_error = self.chunk.code.len();
+                    self.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+                    let jump_location_skip_cast = self.chunk.code.len();
+                    self.chunk.push_with_u32(OpCode::JUMP, 0);
+                    self.fix_jump_location_i32(jump_location_skip_error);
+                    self.push_panic_with_message(&message);
+                    self.fix_jump_location_i32(jump_location_skip_cast);
+                }
+                else if !subject_type.is_instance_of(trait_) {
+                    self.push_panic_with_message(&message);
                 }
             },
             ExpressionOperation::IfThenElse => {
-                let arguments = &self.implementation.expression_tree.children[expression];
+                match step {
+                    // Condition
+                    0 => return Ok(Some(arguments[0])),
+                    // Consequent
+                    1 => {
+                        self.jumps.push(self.chunk.code.len());
+                        self.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+                        return Ok(Some(arguments[1]))
+                    }
+                    2 => {
+                        let jump_location_skip_consequent = self.jumps.pop().unwrap();
+                        let Some(alternative) = arguments.get(2) else {
+                            self.fix_jump_location_i32(jump_location_skip_consequent);
+                            return Ok(None)
+                        };
 
-                // Condition
-                self.compile_expression(&arguments[0])?;
+                        self.jumps.push(self.chunk.code.len());
+                        self.chunk.push_with_u32(OpCode::JUMP, 0);
+                        // The consequent is skipped to just after its jump over the alternative.
+                        self.fix_jump_location_i32(jump_location_skip_consequent);
 
-                let jump_location_skip_consequent = self.chunk.code.len();
-                self.chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+                        // Alternative
+                        return Ok(Some(*alternative))
+                    }
+                    _ => {
+                        let jump_location_skip_alternative = self.jumps.pop().unwrap();
+                        self.fix_jump_location_i32(jump_location_skip_alternative);
+                    }
+                }
+            },
+        }
 
-                // Consequent
-                self.compile_expression(&arguments[1])?;
-                self.fix_jump_location_i32(jump_location_skip_consequent);
+        Ok(None)
+    }
 
-                if let Some(alternative) = arguments.get(2) {
-                    let jump_location_skip_alternative = self.chunk.code.len();
-                    self.chunk.push_with_u32(OpCode::JUMP, 0);
+    /// Whether values of the type were allocated by ALLOC, and thus carry their trait's id.
+    fn is_allocated_struct(&self, type_: &TypeProto) -> bool {
+        matches!(&type_.unit, TypeUnit::Struct(trait_) if self.context.allocated_structs.contains_key(trait_))
+    }
 
-                    // Alternative
-                    self.compile_expression(alternative)?;
-                    self.fix_jump_location_i32(jump_location_skip_alternative);
-                }
-            },
+    /// If the value is a struct passed to a non-mut parameter and assigned to a mutable variable,
+    ///  the number of fields to copy, so that changing the variable doesn't change the caller's struct.
+    fn copied_struct_fields(&self, variable: &ObjectReference, value: &ExpressionID) -> RResult<Option<u32>> {
+        let ExpressionOperation::GetLocal(local) = &self.implementation.expression_tree.values[value] else {
+            return Ok(None)
+        };
+        if variable.mutability != Mutability::Mutable || local.mutability != Mutability::Immutable || !self.implementation.parameter_locals.contains(local) {
+            return Ok(None)
         }
+        let TypeUnit::Struct(trait_) = &self.implementation.type_forest.resolve_type(&variable.type_)?.unit else {
+            return Ok(None)
+        };
+        Ok(self.context.allocated_structs.get(trait_).copied())
+    }
 
-        Ok(())
+    fn push_panic_with_message(&mut self, message: &str) {
+        self.constants.push(Value { ptr: self.context.strings.intern(message) });
+        self.chunk.push_with_u32(OpCode::LOAD_CONSTANT, u32::try_from(self.constants.len() - 1).unwrap());
+        self.chunk.push(OpCode::PANIC_WITH_MESSAGE);
     }
 
     pub fn fix_jump_location_i32(&mut self, jump_location: usize) {
         // +5 because opcode and argument were popped
         let distance_skip_consequence = self.chunk.code.len() - (jump_location + 5);
-        unsafe {
-            self.chunk.modify_u32(jump_location + 1, transmute(i32::try_from(distance_skip_consequence).unwrap()));
-        }
+        self.chunk.modify_u32(jump_location + 1, i32::cast_unsigned(i32::try_from(distance_skip_consequence).unwrap()));
     }
 
-    pub fn get_variable_slot(&mut self, object: &Rc<ObjectReference>) -> u32 {
-        let count = self.locals.len();
-
-        match self.locals.entry(Rc::clone(object)) {
+    pub fn get_variable_slot(&mut self, object: &Arc<ObjectReference>) -> u32 {
+        match self.locals.entry(Arc::clone(object)) {
             Entry::Occupied(o) => *o.get(),
             Entry::Vacant(v) => {
-                *v.insert(u32::try_from(count).unwrap())
+                self.chunk.locals_count += 1;
+                *v.insert(self.chunk.locals_count - 1)
             }
         }
     }
+
+    /// If the call gets or sets a field of a struct stored in locals, the field's local,
+    ///  and whether it's set.
+    fn local_field_access(&self, function: &FunctionBinding, arguments: &[ExpressionID]) -> Option<(u32, bool)> {
+        let (struct_, field, is_set) = match self.context.fn_logic.get(&function.function)? {
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::GetMemberField(struct_, field)) => (struct_, field, false),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::SetMemberField(struct_, field)) => (struct_, field, true),
+            _ => return None,
+        };
+        let ExpressionOperation::GetLocal(local) = &self.implementation.expression_tree.values[&arguments[0]] else {
+            return None
+        };
+        let (first_slot, _) = self.local_structs.get(local)?;
+        let field_idx = struct_.fields.iter().position(|f| f == field).unwrap();
+        Some((first_slot + u32::try_from(field_idx).unwrap(), is_set))
+    }
+}
+
+fn compile_global_constant_load(function: &Arc<FunctionHead>, representation: &FunctionRepresentation, runtime: &mut Runtime) {
+    let uuid = function.function_id;
+    runtime.global_constants.insert(uuid, representation.name.clone());
+    runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, _, _| {
+        compiler.chunk.push_with_u128(OpCode::LOAD_GLOBAL, uuid.as_u128());
+        Ok(None)
+    }));
 }
 
-pub fn compile_descriptor(function: &Rc<FunctionHead>, descriptor: &FunctionLogicDescriptor, runtime: &mut Runtime) {
+pub fn compile_descriptor(function: &Arc<FunctionHead>, descriptor: &FunctionLogicDescriptor, runtime: &mut Runtime) {
     match descriptor {
         FunctionLogicDescriptor::Stub => todo!("{:?}", function),
-        FunctionLogicDescriptor::TraitProvider(_) => todo!(),
+        FunctionLogicDescriptor::TraitProvider(trait_) => {
+            let uuid = trait_.id;
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, _, _| {
+                compiler.chunk.push_with_u128(OpCode::LOAD128, uuid.as_u128());
+                Ok(None)
+            }));
+        }
         FunctionLogicDescriptor::FunctionProvider(f) => {
             let uuid = f.function_id;
-            runtime.function_inlines.insert(Rc::clone(function), Rc::new(move |compiler, expression| {
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, _, _| {
+                compiler.chunk.push_with_u128(OpCode::LOAD128, uuid.as_u128());
+                Ok(None)
+            }));
+        }
+        FunctionLogicDescriptor::PrimitiveOperation { operation, type_ } => {
+            runtime.function_inlines.insert(Arc::clone(function), compile_primitive_operation(operation, type_));
+        }
+        FunctionLogicDescriptor::Constructor(struct_) => {
+            let count = u32::try_from(struct_.fields.len()).unwrap();
+            let uuid = struct_.trait_.id;
+            runtime.allocated_structs.insert(Arc::clone(&struct_.trait_), count);
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, expression, step| {
+                // The metatype has no value to compile.
+                if let Some(field) = compiler.implementation.expression_tree.children[expression].get(step + 1) {
+                    return Ok(Some(*field))
+                }
+                compiler.chunk.push_with_u128(OpCode::LOAD128, uuid.as_u128());
+                compiler.chunk.push_with_u32(OpCode::ALLOC, count);
+                Ok(None)
+            }));
+        }
+        FunctionLogicDescriptor::GetMemberField(struct_, field) => {
+            let field_idx = struct_.fields.iter().position(|f| f == field).unwrap();
+            runtime.function_inlines.insert(Arc::clone(function), inline_fn_push_with_u32(OpCode::GET_MEMBER, u32::try_from(field_idx).unwrap()));
+        }
+        FunctionLogicDescriptor::SetMemberField(struct_, field) => {
+            let field_idx = struct_.fields.iter().position(|f| f == field).unwrap();
+            runtime.function_inlines.insert(Arc::clone(function), inline_fn_push_with_u32(OpCode::SET_MEMBER, u32::try_from(field_idx).unwrap()));
+        }
+        FunctionLogicDescriptor::ListOperation(operation) => {
+            runtime.function_inlines.insert(Arc::clone(function), compile_list_operation(operation));
+        }
+        FunctionLogicDescriptor::JsonOperation(operation) => {
+            runtime.function_inlines.insert(Arc::clone(function), compile_json_operation(operation));
+        }
+        FunctionLogicDescriptor::EnumVariant(_, idx) => {
+            // Variants are represented by their index. The metatype argument has no value to compile.
+            let idx = u8::try_from(*idx).unwrap();
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, _, _| {
+                compiler.chunk.push_with_u8(OpCode::LOAD8, idx);
+                Ok(None)
+            }));
+        }
+        FunctionLogicDescriptor::Reflection(_) => unreachable!("Reflection calls are substituted during monomorphization."),
+        FunctionLogicDescriptor::DynBox(vtable) => {
+            let ids = vtable.iter().map(|function| function.function_id.as_u128()).collect_vec();
+            let count = u32::try_from(1 + 2 * ids.len()).unwrap();
+            let uuid = runtime.traits.as_ref().unwrap().Dyn.id;
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, expression, step| {
+                if let Some(argument) = compiler.implementation.expression_tree.children[expression].get(step) {
+                    return Ok(Some(*argument))
+                }
+                for id in ids.iter() {
+                    compiler.chunk.push_with_u128(OpCode::LOAD128, *id);
+                }
                 compiler.chunk.push_with_u128(OpCode::LOAD128, uuid.as_u128());
-                Ok(())
+                compiler.chunk.push_with_u32(OpCode::ALLOC, count);
+                Ok(None)
+            }));
+        }
+        FunctionLogicDescriptor::DynDispatch(idx) => {
+            let idx = u8::try_from(*idx).unwrap();
+            let count = u8::try_from(function.interface.parameters.len()).unwrap();
+            runtime.function_inlines.insert(Arc::clone(function), Arc::new(move |compiler, expression, step| {
+                if let Some(argument) = compiler.implementation.expression_tree.children[expression].get(step) {
+                    return Ok(Some(*argument))
+                }
+                compiler.chunk.push_with_u8_u8(OpCode::CALL_VTABLE, idx, count);
+                Ok(None)
             }));
         }
-        FunctionLogicDescriptor::PrimitiveOperation { .. } => todo!("{:?}", descriptor),
-        FunctionLogicDescriptor::Constructor(_) => todo!(),
-        FunctionLogicDescriptor::GetMemberField(_, _) => todo!(),
-        FunctionLogicDescriptor::SetMemberField(_, _) => todo!(),
     }
 }
@@ -0,0 +1,76 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use itertools::Itertools;
+#[cfg(feature = "native")]
+use uuid::Uuid;
+
+use crate::interpreter::chunks::Chunk;
+#[cfg(feature = "native")]
+use crate::interpreter::runtime::Runtime;
+#[cfg(feature = "native")]
+use crate::program::module::Module;
+
+/// Flags for each offset of the chunks a VM ran, marking the instructions it executed.
+pub type Coverage = HashMap<*const Chunk, Vec<bool>>;
+
+/// Which lines of a file ran.
+pub struct FileCoverage {
+    pub path: PathBuf,
+    /// Lines with compiled code, starting at 1, and whether any of it ran.
+    pub lines: BTreeMap<usize, bool>,
+}
+
+impl FileCoverage {
+    pub fn covered_count(&self) -> usize {
+        self.lines.values().filter(|covered| **covered).count()
+    }
+
+    pub fn uncovered_lines(&self) -> Vec<usize> {
+        self.lines.iter()
+            .filter(|(_, covered)| !**covered)
+            .map(|(line, _)| *line)
+            .collect_vec()
+    }
+
+    pub fn percentage(&self) -> f32 {
+        match self.lines.len() {
+            0 => 100.0,
+            count => self.covered_count() as f32 * 100.0 / count as f32,
+        }
+    }
+}
+
+/// Maps the executed offsets of the runtime's chunks to lines of the files they were compiled from.
+/// Only functions compiled to chunks count, so code that was inlined or folded away is left out.
+#[cfg(feature = "native")]
+pub fn line_coverage(coverage: &Coverage, runtime: &Runtime, entry: &Module) -> Vec<FileCoverage> {
+    let function_paths: HashMap<Uuid, &PathBuf> = runtime.source.module_by_name.values()
+        .map(|module| module.as_ref())
+        .chain([entry])
+        .filter_map(|module| Some((module, runtime.module_paths.get(&module.name)?)))
+        .flat_map(|(module, path)| module.implemented_functions.iter().map(move |head| (head.function_id, path)))
+        .collect();
+
+    let mut files: BTreeMap<&PathBuf, FileCoverage> = BTreeMap::new();
+    let mut line_starts: HashMap<&PathBuf, Vec<usize>> = HashMap::new();
+    for chunk in runtime.function_evaluators.values() {
+        let Some(path) = chunk.function.and_then(|function| function_paths.get(&function)) else {
+            continue
+        };
+        let line_starts = line_starts.entry(path).or_insert_with(|| {
+            let source = std::fs::read_to_string(path).unwrap_or_default();
+            [0].into_iter().chain(source.match_indices('\n').map(|(idx, _)| idx + 1)).collect_vec()
+        });
+        let file = files.entry(path).or_insert_with(|| FileCoverage { path: (*path).clone(), lines: Default::default() });
+        let executed = coverage.get(&(chunk as *const Chunk));
+
+        for (offset, range) in chunk.positions.iter() {
+            let line = line_starts.partition_point(|start| *start <= range.start);
+            let ran = executed.and_then(|executed| executed.get(*offset)).is_some_and(|ran| *ran);
+            *file.lines.entry(line).or_insert(false) |= ran;
+        }
+    }
+
+    files.into_values().collect_vec()
+}
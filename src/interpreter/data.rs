@@ -1,7 +1,7 @@
 use std::alloc::{alloc, Layout};
-use std::intrinsics::transmute;
+use std::mem::transmute;
 use std::ptr::write_unaligned;
-use crate::program::types::TypeProto;
+use crate::interpreter::stats;
 
 #[derive(Copy, Clone)]
 pub union Value {
@@ -19,14 +19,20 @@ pub union Value {
     pub ptr: *mut (),
 }
 
+/// Struct values start with the two halves of their trait's id, followed by their fields.
+pub const STRUCT_HEADER_SIZE: usize = 2;
+
 impl Value {
     pub fn alloc() -> Value {
-        Value { u8: 0 }
+        Value { u64: 0 }
     }
 }
 
-pub unsafe fn string_to_ptr(string: &String) -> *mut () {
-    let data = alloc(Layout::new::<String>());
-    write_unaligned(data as *mut String, string.clone());
-    transmute(data)
+pub fn string_to_ptr(string: &str) -> *mut () {
+    stats::record_allocation(size_of::<String>() + string.len());
+    unsafe {
+        let data = alloc(Layout::new::<String>());
+        write_unaligned(data as *mut String, string.to_string());
+        transmute(data)
+    }
 }
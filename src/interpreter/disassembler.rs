@@ -4,18 +4,22 @@ use crate::interpreter::chunks::Chunk;
 use crate::interpreter::opcode::{OpCode, Primitive};
 
 pub fn disassemble(chunk: &Chunk) {
-    unsafe {
-        let mut idx = 0;
+    let mut idx = 0;
 
-        while idx < chunk.code.len() {
-            print!("{:04}\t", idx);
-            idx += disassemble_one(transmute(&chunk.code[idx]));
-            print!("\n");
-        }
+    while idx < chunk.code.len() {
+        print!("{:04}\t", idx);
+        // The chunk's code is a sequence of complete instructions.
+        idx += unsafe { disassemble_one(&chunk.code[idx]) };
+        println!();
     }
 }
 
-pub fn disassemble_one(ip: *const u8) -> usize {
+/// Prints the instruction, and returns its length in bytes.
+///
+/// # Safety
+///
+/// `ip` must point to the start of a complete instruction.
+pub unsafe fn disassemble_one(ip: *const u8) -> usize {
     unsafe {
         let code = transmute::<u8, OpCode>(*ip);
         // TODO Somehow, {:<20?} doesn't pad correctly.
@@ -26,36 +30,47 @@ pub fn disassemble_one(ip: *const u8) -> usize {
             OpCode::EQ | OpCode::NEQ | OpCode::GR | OpCode::GR_EQ  | OpCode::LE  | OpCode::LE_EQ |
             OpCode::MOD | OpCode::EXP | OpCode::LOG | OpCode::PARSE | OpCode::TO_STRING => {
                 print!("\t{:?}", transmute::<u8, Primitive>(*ip.add(1)));
-                return 1 + 1;
+                1 + 1
             },
-            OpCode::LOAD8 => {
+            OpCode::LOAD8 | OpCode::LOAD_LOCAL_8 | OpCode::STORE_LOCAL_8 => {
                 print!("\t{:?}", *ip.add(1));
-                return 1 + 1;
+                1 + 1
+            }
+            OpCode::CALL_VTABLE => {
+                print!("\t{:?}\t{:?}", *ip.add(1), *ip.add(2));
+                1 + 2
             }
             OpCode::LOAD16 => {
                 print!("\t{:?}", read_unaligned(ip.add(1) as *mut u16));
-                return 1 + 2;
+                1 + 2
             }
-            OpCode::LOAD32 | OpCode::LOAD_LOCAL | OpCode::STORE_LOCAL | OpCode::LOAD_CONSTANT => {
+            OpCode::LOAD32 | OpCode::LOAD_LOCAL | OpCode::STORE_LOCAL | OpCode::LOAD_CONSTANT |
+            OpCode::ALLOC | OpCode::COPY_STRUCT | OpCode::GET_MEMBER | OpCode::SET_MEMBER => {
                 print!("\t{:?}", read_unaligned(ip.add(1) as *mut u32));
-                return 1 + 4;
+                1 + 4
             }
             OpCode::LOAD64 => {
                 print!("\t{:?}", read_unaligned(ip.add(1) as *mut u64));
-                return 1 + 8;
+                1 + 8
             }
-            OpCode::LOAD128 => {
+            OpCode::LOAD128 | OpCode::LOAD_GLOBAL | OpCode::CALL => {
                 print!("\t{:?}", read_unaligned(ip.add(1) as *mut u128));
-                return 1 + 16;
+                1 + 16
             }
             OpCode::JUMP | OpCode::JUMP_IF_FALSE => {
                 print!("\t{:?}", read_unaligned(ip.add(1) as *mut i32));
-                return 1 + 4;
+                1 + 4
             }
-            OpCode::NOOP | OpCode::PANIC | OpCode::RETURN | OpCode::TRANSPILE_ADD | OpCode::AND |
+            OpCode::NOOP | OpCode::PANIC | OpCode::PANIC_WITH_MESSAGE | OpCode::IS_TYPE | OpCode::RETURN | OpCode::TRANSPILE_ADD | OpCode::AND |
             OpCode::OR | OpCode::POP64 | OpCode::POP128 | OpCode::PRINT | OpCode::NOT |
-            OpCode::ADD_STRING | OpCode::DUP64 => {
-                return 1;
+            OpCode::ADD_STRING | OpCode::EQ_STRING | OpCode::NEQ_STRING | OpCode::GR_STRING | OpCode::GR_EQ_STRING |
+            OpCode::LE_STRING | OpCode::LE_EQ_STRING | OpCode::DUP64 | OpCode::LIST_NEW | OpCode::LIST_PUSH |
+            OpCode::LIST_POP | OpCode::LIST_LEN | OpCode::LIST_GET | OpCode::LIST_SET |
+            OpCode::CHAR_FROM_CODE | OpCode::STRING_CHARS | OpCode::BUFFER_NEW |
+            OpCode::BUFFER_PUSH_LINE | OpCode::BUFFER_COPY | OpCode::JSON_PARSE | OpCode::JSON_GET |
+            OpCode::JSON_AT | OpCode::JSON_LEN | OpCode::JSON_KIND | OpCode::JSON_AS_STRING |
+            OpCode::JSON_AS_FLOAT | OpCode::JSON_AS_BOOL => {
+                1
             },
         }
     }
@@ -0,0 +1,47 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::program::allocation::ObjectReference;
+use crate::program::expression_tree::ExpressionOperation;
+use crate::program::functions::FunctionHead;
+use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor};
+use crate::source::StructInfo;
+
+/// Finds the locals holding structs that never escape the function, so their fields can be stored in
+///  locals instead of on the heap.
+/// A local qualifies if it is only ever assigned new structs, and only read to get or set their fields.
+/// Anything else, including parameters, might share the struct with someone else, so it stays on the heap.
+pub fn find_local_structs(implementation: &FunctionImplementation, fn_logic: &HashMap<Arc<FunctionHead>, FunctionLogic>) -> HashMap<Arc<ObjectReference>, Arc<StructInfo>> {
+    let tree = &implementation.expression_tree;
+    let descriptor = |expression| match tree.values.get(expression)? {
+        ExpressionOperation::FunctionCall(binding) => match fn_logic.get(&binding.function)? {
+            FunctionLogic::Descriptor(descriptor) => Some(descriptor),
+            FunctionLogic::Implementation(_) => None,
+        },
+        _ => None,
+    };
+
+    let mut structs = HashMap::new();
+    let mut escaping: HashSet<_> = implementation.parameter_locals.iter().cloned().collect();
+    for (expression, operation) in tree.values.iter() {
+        match operation {
+            ExpressionOperation::SetLocal(local) => match descriptor(&tree.children[expression][0]) {
+                Some(FunctionLogicDescriptor::Constructor(struct_)) => _ = structs.insert(Arc::clone(local), Arc::clone(struct_)),
+                _ => _ = escaping.insert(Arc::clone(local)),
+            },
+            ExpressionOperation::GetLocal(local) => {
+                let is_field_access = tree.parents.get(expression).is_some_and(|parent| {
+                    matches!(descriptor(parent), Some(FunctionLogicDescriptor::GetMemberField(_, _) | FunctionLogicDescriptor::SetMemberField(_, _)))
+                        && tree.children[parent][0] == *expression
+                });
+                if !is_field_access {
+                    escaping.insert(Arc::clone(local));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    structs.retain(|local, _| !escaping.contains(local));
+    structs
+}
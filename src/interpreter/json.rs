@@ -0,0 +1,263 @@
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::error::codes::ErrorCode;
+use crate::error::{RResult, RuntimeError};
+
+/// A parsed JSON value, as referenced by Json values in the VM.
+/// Values are never mutated after parsing, so the VM hands out pointers into them.
+#[derive(Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Entries in order of their first appearance. Duplicate keys keep the last value, like in python.
+    Object(Vec<(String, Json)>),
+}
+
+/// Returned by lookups that find nothing.
+pub static NULL: Json = Json::Null;
+
+impl Json {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "bool",
+            Json::Number(_) => "number",
+            Json::String(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+
+    /// Negative indices are out of bounds; they don't count from the end.
+    pub fn at(&self, index: i64) -> &Json {
+        match self {
+            Json::Array(elements) => usize::try_from(index).ok().and_then(|index| elements.get(index)).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        match self {
+            Json::Array(elements) => elements.len(),
+            Json::Object(entries) => entries.len(),
+            _ => 0,
+        }
+    }
+
+    /// The error for converting the value to a kind it isn't.
+    pub fn unexpected_kind(&self, expected: &str) -> Vec<RuntimeError> {
+        RuntimeError::error(format!("Expected a JSON {}, but got {}.", expected, self.kind()).as_str())
+            .with_code(ErrorCode::UnexpectedJsonKind)
+            .to_array()
+    }
+}
+
+/// Parses the string as a single JSON value, surrounded by optional whitespace.
+/// Errors are described like python's json module does, so both backends fail the same way.
+pub fn parse(string: &str) -> RResult<Json> {
+    let mut parser = Parser { string, chars: string.char_indices().peekable() };
+    parser.skip_whitespace();
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.chars.peek() {
+        Some(&(idx, _)) => Err(parser.error("Extra data", idx)),
+        None => Ok(value),
+    }
+}
+
+struct Parser<'a> {
+    string: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn position(&mut self) -> usize {
+        self.chars.peek().map(|(idx, _)| *idx).unwrap_or(self.string.len())
+    }
+
+    /// Positions are reported by line and char, both counted from 1.
+    fn error(&self, message: &str, idx: usize) -> Vec<RuntimeError> {
+        let before = &self.string[..idx];
+        let line = before.matches('\n').count() + 1;
+        let char = before.rsplit('\n').next().unwrap().chars().count() + 1;
+        RuntimeError::error(format!("Invalid JSON at line {}, char {}: {}.", line, char, message).as_str())
+            .with_code(ErrorCode::InvalidJson)
+            .to_array()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, ch)| matches!(ch, ' ' | '\t' | '\n' | '\r')).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char, message: &str) -> RResult<()> {
+        let position = self.position();
+        match self.chars.next_if(|(_, ch)| *ch == expected) {
+            Some(_) => Ok(()),
+            None => Err(self.error(message, position)),
+        }
+    }
+
+    fn parse_value(&mut self) -> RResult<Json> {
+        let start = self.position();
+        match self.chars.peek().map(|(_, ch)| *ch) {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some(_) => {
+                for (literal, value) in [("true", Json::Bool(true)), ("false", Json::Bool(false)), ("null", Json::Null)] {
+                    if self.string[start..].starts_with(literal) {
+                        self.chars.nth(literal.len() - 1);
+                        return Ok(value)
+                    }
+                }
+                Err(self.error("Expecting value", start))
+            }
+            None => Err(self.error("Expecting value", start)),
+        }
+    }
+
+    fn parse_object(&mut self) -> RResult<Json> {
+        self.chars.next();
+        let mut entries: Vec<(String, Json)> = vec![];
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == '}').is_some() {
+            return Ok(Json::Object(entries))
+        }
+
+        loop {
+            let position = self.position();
+            if self.chars.peek().map(|(_, ch)| *ch) != Some('"') {
+                return Err(self.error("Expecting property name enclosed in double quotes", position))
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':', "Expecting ':' delimiter")?;
+            self.skip_whitespace();
+            let value = self.parse_value()?;
+            match entries.iter_mut().find(|(k, _)| *k == key) {
+                Some(entry) => entry.1 = value,
+                None => entries.push((key, value)),
+            }
+
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, ch)| *ch == '}').is_some() {
+                return Ok(Json::Object(entries))
+            }
+            self.expect(',', "Expecting ',' delimiter")?;
+            self.skip_whitespace();
+        }
+    }
+
+    fn parse_array(&mut self) -> RResult<Json> {
+        self.chars.next();
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == ']').is_some() {
+            return Ok(Json::Array(elements))
+        }
+
+        loop {
+            elements.push(self.parse_value()?);
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, ch)| *ch == ']').is_some() {
+                return Ok(Json::Array(elements))
+            }
+            self.expect(',', "Expecting ',' delimiter")?;
+            self.skip_whitespace();
+        }
+    }
+
+    fn parse_string(&mut self) -> RResult<String> {
+        let (start, _) = self.chars.next().unwrap();
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error("Unterminated string starting at", start)),
+                Some((_, '"')) => return Ok(string),
+                Some((idx, ch)) if (ch as u32) < 0x20 => return Err(self.error("Invalid control character at", idx)),
+                Some((idx, '\\')) => {
+                    let escaped = match self.chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '/')) => '/',
+                        Some((_, 'b')) => '\u{8}',
+                        Some((_, 'f')) => '\u{c}',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'u')) => self.parse_unicode_escape(idx)?,
+                        Some(_) => return Err(self.error("Invalid \\escape", idx)),
+                        None => return Err(self.error("Unterminated string starting at", start)),
+                    };
+                    string.push(escaped);
+                }
+                Some((_, ch)) => string.push(ch),
+            }
+        }
+    }
+
+    /// Parses the 4 hex digits after \u, and the low surrogate that must follow a high surrogate.
+    fn parse_unicode_escape(&mut self, start: usize) -> RResult<char> {
+        let Some(code) = self.parse_hex() else {
+            return Err(self.error("Invalid \\uXXXX escape", start))
+        };
+        if !(0xD800..0xDC00).contains(&code) {
+            return char::from_u32(code).ok_or_else(|| self.error("Lone surrogate in \\uXXXX escape", start))
+        }
+
+        let low_start = self.position();
+        if !self.string[low_start..].starts_with("\\u") {
+            return Err(self.error("Lone surrogate in \\uXXXX escape", start))
+        }
+        self.chars.nth(1);
+        match self.parse_hex() {
+            Some(low @ 0xDC00..0xE000) => Ok(char::from_u32(0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)).unwrap()),
+            Some(_) => Err(self.error("Lone surrogate in \\uXXXX escape", start)),
+            None => Err(self.error("Invalid \\uXXXX escape", low_start)),
+        }
+    }
+
+    fn parse_hex(&mut self) -> Option<u32> {
+        (0..4).try_fold(0, |code, _| Some(code * 16 + self.chars.next()?.1.to_digit(16)?))
+    }
+
+    /// -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+    /// Like in python, a fraction or exponent without digits isn't part of the number.
+    fn parse_number(&mut self) -> RResult<Json> {
+        let start = self.position();
+        let rest = &self.string[start..];
+        let digits = |string: &str| string.chars().take_while(char::is_ascii_digit).count();
+
+        let mut length = usize::from(rest.starts_with('-'));
+        match digits(&rest[length..]) {
+            0 => return Err(self.error("Expecting value", start)),
+            _ if rest[length..].starts_with('0') => length += 1,
+            count => length += count,
+        }
+        if rest[length..].starts_with('.') && digits(&rest[length + 1..]) > 0 {
+            length += 1 + digits(&rest[length + 1..]);
+        }
+        if rest[length..].starts_with(['e', 'E']) {
+            let sign = usize::from(rest[length + 1..].starts_with(['+', '-']));
+            let count = digits(&rest[length + 1 + sign..]);
+            if count > 0 {
+                length += 1 + sign + count;
+            }
+        }
+
+        self.chars.nth(length - 1);
+        Ok(Json::Number(rest[..length].parse().unwrap()))
+    }
+}
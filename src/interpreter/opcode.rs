@@ -4,6 +4,8 @@
 pub enum OpCode {
     NOOP,
     PANIC,
+    // Like PANIC, titled by the String on top of the stack.
+    PANIC_WITH_MESSAGE,
     RETURN,
     // TODO Replace with function call?
     TRANSPILE_ADD,
@@ -16,7 +18,12 @@ pub enum OpCode {
     LOAD128,
     LOAD_LOCAL,
     STORE_LOCAL,
+    // Like LOAD_LOCAL and STORE_LOCAL, for the first 256 locals.
+    LOAD_LOCAL_8,
+    STORE_LOCAL_8,
     LOAD_CONSTANT,
+    LOAD_GLOBAL,
+    CALL,
     DUP64,
     POP64,
     POP128,
@@ -43,6 +50,48 @@ pub enum OpCode {
     TO_STRING,
     // TODO This can probably be done in-code some time (?)
     ADD_STRING,
+    // Strings are compared by their contents, not by pointer.
+    EQ_STRING,
+    NEQ_STRING,
+    // Strings are ordered by their chars' code points.
+    GR_STRING,
+    GR_EQ_STRING,
+    LE_STRING,
+    LE_EQ_STRING,
+    LIST_NEW,
+    LIST_PUSH,
+    LIST_POP,
+    LIST_LEN,
+    LIST_GET,
+    LIST_SET,
+    CHAR_FROM_CODE,
+    STRING_CHARS,
+    // Json values point into the tree parsed by JSON_PARSE, which is never changed.
+    JSON_PARSE,
+    JSON_GET,
+    JSON_AT,
+    JSON_LEN,
+    JSON_KIND,
+    JSON_AS_STRING,
+    JSON_AS_FLOAT,
+    JSON_AS_BOOL,
+    // A String that lines are appended to, backing StringConsole.
+    BUFFER_NEW,
+    BUFFER_PUSH_LINE,
+    BUFFER_COPY,
+    // Structs are arrays of their fields' values, in order of declaration, after their trait's id.
+    //  ALLOC pops the id (like LOAD128 pushes it) and then the fields.
+    ALLOC,
+    // Replaces the struct on top of the stack with a copy. The argument is its number of fields.
+    COPY_STRUCT,
+    GET_MEMBER,
+    SET_MEMBER,
+    // Pops a trait id and a struct, and pushes whether the struct was allocated with the id.
+    IS_TYPE,
+    // Dyn values are structs of the boxed value, followed by the ids of their vtable's functions.
+    //  CALL_VTABLE reads the function's index and the number of arguments, the first of which is the Dyn.
+    //  The function is called with the boxed value in its place.
+    CALL_VTABLE,
 }
 
 #[repr(u8)]
@@ -59,4 +108,5 @@ pub enum Primitive {
     U64,
     F32,
     F64,
+    CHAR,
 }
@@ -1,32 +1,73 @@
-use std::rc::Rc;
-use itertools::Itertools;
+use std::io::Write;
+use std::sync::Arc;
 use uuid::Uuid;
 use crate::error::{RuntimeError, RResult};
-use crate::interpreter::compiler::compile_deep;
+use crate::interpreter::compiler::{compile_cached, compile_deep};
+use crate::interpreter::coverage::Coverage;
 use crate::interpreter::runtime::Runtime;
+use crate::interpreter::stats::Stats;
 use crate::interpreter::vm::VM;
 use crate::program::functions::FunctionHead;
 use crate::program::global::FunctionLogic;
 use crate::program::module::Module;
 use crate::transpiler::{TranspiledArtifact, Transpiler};
 
-pub fn main(module: &Module, runtime: &mut Runtime) -> RResult<()> {
-    let entry_function = get_main_function(&module)?
+/// Runs the module's main! function, returning the VM's stats.
+pub fn main(module: &Module, runtime: &mut Runtime, out: &mut dyn Write) -> RResult<Stats> {
+    let entry_function = get_main_function(module)?
         .ok_or(RuntimeError::error("No main! function declared.").to_array())?;
 
     // TODO Should gather all used functions and compile them
-    let compiled = compile_deep(runtime, entry_function)?;
+    compile_cached(runtime, entry_function)?;
+    let compiled = &runtime.function_evaluators[&entry_function.function_id];
 
-    let mut out = std::io::stdout();
-    let mut vm = VM::new(&compiled, &mut out);
-    unsafe {
-        vm.run()?;
-    }
+    let mut vm = VM::new(compiled, out);
+    vm.load_runtime(runtime);
+    vm.run()?;
 
+    Ok(vm.stats)
+}
+
+/// Runs the module's main! function like main, recording which instructions were executed.
+pub fn main_with_coverage(module: &Module, runtime: &mut Runtime, out: &mut dyn Write) -> RResult<Coverage> {
+    let entry_function = get_main_function(module)?
+        .ok_or(RuntimeError::error("No main! function declared.").to_array())?;
+
+    compile_cached(runtime, entry_function)?;
+    let compiled = &runtime.function_evaluators[&entry_function.function_id];
+
+    let mut vm = VM::new(compiled, out);
+    vm.load_runtime(runtime);
+    vm.coverage = Some(Default::default());
+    vm.run()?;
+
+    Ok(vm.coverage.take().unwrap())
+}
+
+/// Runs the code as the body of an implicit main! function, using only the bundled libraries.
+/// Anything the program prints is passed to out.
+pub fn compile_and_run(source: &str, out: impl FnMut(&str)) -> RResult<()> {
+    let mut runtime = Runtime::new()?;
+    runtime.repository.add_bundled("common");
+    runtime.evaluate_string(source, &mut CallbackWriter(out))?;
     Ok(())
 }
 
-pub fn get_main_function(module: &Module) -> RResult<Option<&Rc<FunctionHead>>> {
+/// Forwards everything written to a callback, for targets without stdout.
+pub struct CallbackWriter<F: FnMut(&str)>(pub F);
+
+impl<F: FnMut(&str)> Write for CallbackWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        (self.0)(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn get_main_function(module: &Module) -> RResult<Option<&Arc<FunctionHead>>> {
     let entry_function = match &module.main_functions[..] {
         [] => return Ok(None),
         [f] => f,
@@ -42,29 +83,28 @@ pub fn get_main_function(module: &Module) -> RResult<Option<&Rc<FunctionHead>>>
 }
 
 // The function is written like this
-pub fn transpile(module: &Module, runtime: &mut Runtime) -> RResult<Box<Transpiler>> {
+pub fn transpile(module: &Module, runtime: &mut Runtime, out: &mut dyn Write) -> RResult<Box<Transpiler>> {
     let entry_function = get_transpile_function(module)?;
     assert!(entry_function.interface.return_type.unit.is_void(), "transpile! function has a return value.");
 
     // Set the transpiler object.
     let compiled = compile_deep(runtime, entry_function)?;
 
-    let mut out = std::io::stdout();
-    let mut vm = VM::new(&compiled, &mut out);
-    unsafe {
-        vm.run()?;
-    }
+    let mut vm = VM::new(&compiled, out);
+    vm.load_runtime(runtime);
+    vm.run()?;
 
     let exported_artifacts = gather_functions_logic(runtime, &vm.transpile_functions);
 
     Ok(Box::new(Transpiler {
         // TODO This should be one of the exported artifacts
-        main_function: get_main_function(module)?.map(Rc::clone),
+        main_function: get_main_function(module)?.map(Arc::clone),
         exported_artifacts,
+        included_modules: module.included_modules.clone(),
     }))
 }
 
-fn get_transpile_function(module: &Module) -> RResult<&Rc<FunctionHead>> {
+fn get_transpile_function(module: &Module) -> RResult<&Arc<FunctionHead>> {
     match &module.transpile_functions[..] {
         [] => Err(RuntimeError::error("No transpile! function declared.").to_array()),
         [f] => Ok(f),
@@ -72,7 +112,7 @@ fn get_transpile_function(module: &Module) -> RResult<&Rc<FunctionHead>> {
     }
 }
 
-pub fn gather_functions_logic(runtime: &Runtime, transpile_functions: &Vec<Uuid>) -> Vec<TranspiledArtifact> {
+pub fn gather_functions_logic(runtime: &Runtime, transpile_functions: &[Uuid]) -> Vec<TranspiledArtifact> {
     transpile_functions.iter().map(|uuid| {
         let function_head = &runtime.source.fn_heads[uuid];
         match &runtime.source.fn_logic[function_head] {
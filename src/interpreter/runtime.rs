@@ -1,65 +1,142 @@
 use std::collections::HashMap;
+use std::io::Write;
+#[cfg(feature = "native")]
+use std::path::Path;
 use std::path::PathBuf;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 use itertools::Itertools;
+#[cfg(feature = "native")]
+use log::debug;
 use uuid::Uuid;
 
-use crate::{ast, parser, program, resolver};
+use crate::{ast, interpreter, parser, program, resolver};
 use crate::error::{RResult, RuntimeError};
 use crate::interpreter::builtins;
 use crate::interpreter::chunks::Chunk;
-use crate::interpreter::compiler::InlineFunction;
+use crate::interpreter::compiler::{compile_cached, InlineFunction};
+use crate::interpreter::stats::Stats;
+use crate::interpreter::strings::StringInterner;
+use crate::interpreter::vm::VM;
+use crate::program::expression_tree::ExpressionOperation;
 use crate::program::functions::FunctionHead;
+use crate::program::global::FunctionLogic;
 use crate::program::module::{Module, module_name, ModuleName};
+use crate::program::primitives;
 use crate::program::traits::Trait;
-use crate::repository::Repository;
+use crate::program::types::TypeUnit;
+#[cfg(feature = "native")]
+use crate::repository::Project;
+use crate::repository::{ModuleSource, Repository};
 use crate::resolver::{imports, referencible, scopes};
 use crate::source::Source;
+use crate::static_analysis::lint::CustomLint;
 
+/// Modules imported implicitly by code passed to evaluate_string.
+/// Files don't get these; they are expected to use! what they need.
+pub const EVAL_PRELUDE: [&str; 1] = ["common"];
+
+/// A value returned by evaluate_value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvaluatedValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+/// Imports the EVAL_PRELUDE, followed by the header of the function the code is the body of.
+fn eval_prefix(header: &str) -> String {
+    EVAL_PRELUDE.iter()
+        .map(|name| format!("use!(module!(\"{}\"));\n", name))
+        .join("") + header
+}
+
+/// Positions errors of code wrapped with eval_prefix relative to the code.
+fn in_code(errors: Vec<RuntimeError>, prefix: &str, code: &str) -> Vec<RuntimeError> {
+    errors.into_iter().map(|e| e.in_substring(prefix.len(), code.len())).collect_vec()
+}
+
+/// The runtime constructed first by new_cached, which later calls clone.
+static CONSTRUCTED_RUNTIME: Mutex<Option<Box<Runtime>>> = Mutex::new(None);
+
+#[derive(Clone)]
+#[allow(non_snake_case)]
 pub struct Runtime {
-    #[allow(non_snake_case)]
-    pub Metatype: Rc<Trait>,
-    pub primitives: Option<HashMap<program::primitives::Type, Rc<Trait>>>,
+    pub Metatype: Arc<Trait>,
+    pub primitives: Option<HashMap<program::primitives::Type, Arc<Trait>>>,
     pub traits: Option<program::builtins::traits::Traits>,
 
     // These are optimized for running and may not reflect the source code itself.
     // They are also only loaded on demand.
     pub function_evaluators: HashMap<Uuid, Chunk>,
-    // TODO We'll need these only in the future when we compile functions to constants.
-    // pub global_assignments: HashMap<Uuid, Value>,
-    pub function_inlines: HashMap<Rc<FunctionHead>, InlineFunction>,
+    // Names of functions compiled as global constants; the VM evaluates them lazily.
+    pub global_constants: HashMap<Uuid, String>,
+    pub function_inlines: HashMap<Arc<FunctionHead>, InlineFunction>,
+    // Structs whose values are allocated with their trait's id, so they can be type checked at runtime.
+    pub allocated_structs: HashMap<Arc<Trait>, u32>,
+    // Strings shared by compiled chunks and the VM.
+    pub strings: Arc<StringInterner>,
 
     // These remain unchanged after resolution.
     pub source: Source,
     pub repository: Box<Repository>,
+    /// The files modules were loaded from, e.g. to watch them for changes.
+    pub module_paths: HashMap<ModuleName, PathBuf>,
+    /// Modules imported into every module loaded, like core.
+    pub prelude: Vec<ModuleName>,
+    /// Run on each function body resolved, after the built-in lints.
+    pub lints: Vec<CustomLint>,
 }
 
 impl Runtime {
-    #[allow(non_snake_case)]
     pub fn new() -> RResult<Box<Runtime>> {
-        let mut Metatype = Trait::new_with_self("Type");
-        let Metatype = Rc::new(Metatype);
+        Runtime::with_std(true)
+    }
+
+    /// Like new, but without embedded_std, core is read only from the monoteny directory
+    ///  instead of falling back to the sources embedded into the binary.
+    #[allow(non_snake_case)]
+    pub fn with_std(embedded_std: bool) -> RResult<Box<Runtime>> {
+        let Metatype = Trait::new_with_self("Type");
+        let Metatype = Arc::new(Metatype);
 
         let mut runtime = Box::new(Runtime {
-            Metatype: Rc::clone(&Metatype),
+            Metatype: Arc::clone(&Metatype),
             primitives: None,
             traits: None,
             function_evaluators: Default::default(),
+            global_constants: Default::default(),
             function_inlines: Default::default(),
+            allocated_structs: Default::default(),
+            strings: Arc::new(StringInterner::new()),
             source: Source::new(),
             repository: Repository::new(),
+            module_paths: Default::default(),
+            prelude: vec![],
+            lints: vec![],
         });
 
         let mut builtins_module = program::builtins::create_builtins(&mut runtime);
         referencible::add_trait(&mut runtime, &mut builtins_module, None, &Metatype).unwrap();
 
         runtime.source.module_by_name.insert(builtins_module.name.clone(), builtins_module);
-        builtins::load(&mut runtime)?;
+        builtins::load(&mut runtime, embedded_std)?;
 
         Ok(runtime)
     }
 
+    /// Like new, but clones the runtime constructed first in this process instead of loading builtins again.
+    /// Traits and functions are immutable after creation, so clones share them. The maps referencing them
+    ///  are copied, so loading modules into one runtime doesn't affect others.
+    pub fn new_cached() -> RResult<Box<Runtime>> {
+        // Threads calling this while the first runtime is constructed wait for it.
+        let mut constructed = CONSTRUCTED_RUNTIME.lock().unwrap();
+        if constructed.is_none() {
+            *constructed = Some(Runtime::new()?);
+        }
+        Ok(constructed.clone().unwrap())
+    }
+
     pub fn get_or_load_module(&mut self, name: &ModuleName) -> RResult<&Module> {
         // FIXME this should be if let Some( ... but the compiler bugs out
         if self.source.module_by_name.contains_key(name) {
@@ -68,21 +145,51 @@ impl Runtime {
         }
 
         // Gotta load the module first.
-        let path = self.repository.resolve_module_path(name)?;
-        let module = self.load_file_as_module(&path, name.clone())?;
+        let module = match self.repository.resolve_module(name)? {
+            #[cfg(feature = "native")]
+            ModuleSource::File(path) => self.load_file_as_module(&path, name.clone())?,
+            #[cfg(not(feature = "native"))]
+            ModuleSource::File(path) => return Err(RuntimeError::error(format!("Cannot load {:?} without file system access.", path).as_str()).to_array()),
+            ModuleSource::Text(source) => self.load_text_as_module(&source, name.clone())?,
+        };
         self.source.module_by_name.insert(name.clone(), module);
         Ok(&self.source.module_by_name[name])
     }
 
+    /// Loads the modules, and imports them into every module loaded afterwards.
+    /// The prelude modules, and the modules they use, are loaded without a prelude.
+    pub fn set_prelude(&mut self, prelude: Vec<ModuleName>) -> RResult<()> {
+        self.prelude = vec![];
+        for name in prelude.iter() {
+            self.get_or_load_module(name)?;
+        }
+        self.prelude = prelude;
+        Ok(())
+    }
+
+    /// Uses the prelude of the project the file belongs to, if it belongs to one.
+    #[cfg(feature = "native")]
+    pub fn load_project_for(&mut self, path: &Path) -> RResult<()> {
+        let Some(project) = Project::find(path)? else {
+            return Ok(())
+        };
+        self.set_prelude(project.prelude)
+    }
+
+    #[cfg(feature = "native")]
     pub fn load_file_as_module(&mut self, path: &PathBuf, name: ModuleName) -> RResult<Box<Module>> {
-        let content = std::fs::read_to_string(&path)
+        debug!("Loading module {} from {:?}", name.join("."), path);
+        self.module_paths.insert(name.clone(), path.clone());
+        let content = std::fs::read_to_string(path)
             .map_err(|e| RuntimeError::error(format!("Error loading {:?}: {}", path, e).as_str()).to_array())?;
-        self.load_text_as_module(&content, name)
+        let mut module = self.load_text_as_module(&content, name)
             .map_err(|errs| {
                 errs.into_iter().map(|e| {
                     e.in_file(path.clone())
                 }).collect_vec()
-            })
+            })?;
+        module.warnings = module.warnings.drain(..).map(|w| w.in_file(path.clone())).collect_vec();
+        Ok(module)
     }
 
     pub fn load_text_as_module(&mut self, source: &str, name: ModuleName) -> RResult<Box<Module>> {
@@ -93,7 +200,95 @@ impl Runtime {
         self.load_ast_as_module(&ast, name)
     }
 
+    /// Runs the code as the body of an implicit main! function, with the EVAL_PRELUDE imported.
+    /// Errors are positioned relative to the code.
+    pub fn evaluate_string(&mut self, code: &str, out: &mut dyn Write) -> RResult<Stats> {
+        let prefix = eval_prefix("def main! :: {\n");
+        let module = self.load_code_as_module(&prefix, code)?;
+        interpreter::run::main(&module, self, out).map_err(|errs| in_code(errs, &prefix, code))
+    }
+
+    /// Runs the code like evaluate_string.
+    /// If its last statement is a number or a string, the code is run as a function returning it instead,
+    ///  and its value is returned.
+    pub fn evaluate_value(&mut self, code: &str, out: &mut dyn Write) -> RResult<Option<EvaluatedValue>> {
+        let prefix = eval_prefix("def main! :: {\n");
+        let module = self.load_code_as_module(&prefix, code)?;
+        let main = interpreter::run::get_main_function(&module)?.unwrap();
+        let Some(value_type) = self.last_statement_type(main) else {
+            interpreter::run::main(&module, self, out).map_err(|errs| in_code(errs, &prefix, code))?;
+            return Ok(None)
+        };
+
+        let prefix = eval_prefix(&format!("def _evaluate() -> {} :: {{\n", value_type.name));
+        let module = self.load_code_as_module(&prefix, code)?;
+        let function = module.implemented_functions.iter()
+            .find(|head| self.source.fn_representations[*head].name == "_evaluate")
+            .unwrap();
+        compile_cached(self, function).map_err(|errs| in_code(errs, &prefix, code))?;
+
+        let mut vm = VM::new(&self.function_evaluators[&function.function_id], out);
+        vm.load_runtime(self);
+        vm.run().map_err(|errs| in_code(errs, &prefix, code))?;
+        let value = vm.stack[0];
+
+        let primitive = self.primitives.as_ref().unwrap().iter()
+            .find(|(_, trait_)| Arc::ptr_eq(trait_, &value_type))
+            .map(|(primitive, _)| *primitive);
+        Ok(Some(unsafe {
+            match primitive {
+                Some(primitives::Type::Int(8)) => EvaluatedValue::Int(value.i8 as i64),
+                Some(primitives::Type::Int(16)) => EvaluatedValue::Int(value.i16 as i64),
+                Some(primitives::Type::Int(32)) => EvaluatedValue::Int(value.i32 as i64),
+                Some(primitives::Type::Int(_)) => EvaluatedValue::Int(value.i64),
+                Some(primitives::Type::UInt(8)) => EvaluatedValue::Int(value.u8 as i64),
+                Some(primitives::Type::UInt(16)) => EvaluatedValue::Int(value.u16 as i64),
+                Some(primitives::Type::UInt(32)) => EvaluatedValue::Int(value.u32 as i64),
+                // Values above i64::MAX wrap around.
+                Some(primitives::Type::UInt(_)) => EvaluatedValue::Int(value.u64 as i64),
+                Some(primitives::Type::Float(32)) => EvaluatedValue::Float(value.f32 as f64),
+                Some(primitives::Type::Float(_)) => EvaluatedValue::Float(value.f64),
+                _ => EvaluatedValue::String((*(value.ptr as *const String)).clone()),
+            }
+        }))
+    }
+
+    fn load_code_as_module(&mut self, prefix: &str, code: &str) -> RResult<Box<Module>> {
+        let source = format!("{}{}\n}};\n", prefix, code);
+        self.load_text_as_module(&source, module_name("main"))
+            .map_err(|errs| in_code(errs, prefix, code))
+    }
+
+    /// The type of the function's last statement, if it is a number or a string.
+    fn last_statement_type(&self, function: &Arc<FunctionHead>) -> Option<Arc<Trait>> {
+        let FunctionLogic::Implementation(implementation) = self.source.fn_logic.get(function)? else {
+            return None
+        };
+        let tree = &implementation.expression_tree;
+        let last = match tree.values[&tree.root] {
+            ExpressionOperation::Block => *tree.children[&tree.root].last()?,
+            _ => tree.root,
+        };
+        let type_ = implementation.type_forest.resolve_binding_alias(&last).ok()?;
+        let TypeUnit::Struct(trait_) = &type_.unit else {
+            return None
+        };
+
+        let is_number = self.primitives.as_ref()?.iter()
+            .any(|(primitive, primitive_trait)| primitive.is_number() && Arc::ptr_eq(primitive_trait, trait_));
+        (is_number || Arc::ptr_eq(trait_, &self.traits.as_ref()?.String)).then(|| Arc::clone(trait_))
+    }
+
     pub fn load_ast_as_module(&mut self, syntax: &ast::Block, name: ModuleName) -> RResult<Box<Module>> {
+        let scope = self.base_scope()?;
+
+        let mut module = Box::new(Module::new(name));
+        resolver::resolve_file(syntax, &scope, self, &mut module)?;
+        Ok(module)
+    }
+
+    /// The scope every module is resolved in: builtins, core and the prelude.
+    pub fn base_scope<'a>(&mut self) -> RResult<scopes::Scope<'a>> {
         let mut scope = scopes::Scope::new();
 
         let builtins_name = module_name("builtins");
@@ -106,8 +301,41 @@ impl Runtime {
             imports::deep(self, core_name, &mut scope)?;
         }
 
-        let mut module = Box::new(Module::new(name));
-        resolver::resolve_file(syntax, &scope, self, &mut module)?;
-        Ok(module)
+        for prelude_name in self.prelude.clone() {
+            imports::deep(self, prelude_name, &mut scope)?;
+        }
+
+        Ok(scope)
     }
+
+    /// Applies an edit of the module's source, for tools that keep a runtime alive while the code changes.
+    /// If only function bodies changed, just those are resolved again, and the cached chunks of them
+    ///  (or of their callers) are compiled again. Everything else is kept, including the chunks of other functions.
+    /// Otherwise, the module is resolved from scratch.
+    pub fn update_module(&mut self, module: &mut Box<Module>, source: &str) -> RResult<ModuleUpdate> {
+        let (ast, _) = parser::parse_program(source)?;
+        let Some(heads) = resolver::incremental::update_bodies(&ast, self, module)? else {
+            *module = self.load_ast_as_module(&ast, module.name.clone())?;
+            return Ok(ModuleUpdate::Module)
+        };
+
+        // Chunks compiled from the old bodies are compiled again, including callers that may have inlined them.
+        let stale = resolver::incremental::with_callers(&heads, self).into_iter()
+            .filter(|head| self.function_evaluators.remove(&head.function_id).is_some())
+            .collect_vec();
+        for head in stale {
+            compile_cached(self, &head)?;
+        }
+
+        Ok(ModuleUpdate::Bodies(heads))
+    }
+}
+
+/// What Runtime::update_module resolved again.
+#[derive(Debug, PartialEq)]
+pub enum ModuleUpdate {
+    /// Only the bodies of these functions changed.
+    Bodies(Vec<Arc<FunctionHead>>),
+    /// Declarations changed, so the whole module was resolved again.
+    Module,
 }
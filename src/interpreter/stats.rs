@@ -0,0 +1,64 @@
+use std::fmt::{Display, Formatter};
+
+/// Counters of a VM run, for performance investigations.
+/// They are only collected with the `stats` feature; otherwise, they stay zero and cost nothing.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub instructions: u64,
+    /// Calls of compiled functions, made by CALL instructions.
+    pub calls: u64,
+    /// In stack slots.
+    pub peak_stack_depth: usize,
+    /// Heap allocations by list and string intrinsics.
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+}
+
+#[cfg(feature = "stats")]
+thread_local! {
+    /// Allocations are counted where they happen, which isn't always inside the VM.
+    static ALLOCATIONS: std::cell::Cell<(u64, u64)> = const { std::cell::Cell::new((0, 0)) };
+}
+
+#[inline(always)]
+#[cfg_attr(not(feature = "stats"), allow(unused_variables))]
+pub fn record_allocation(bytes: usize) {
+    #[cfg(feature = "stats")]
+    ALLOCATIONS.with(|allocations| {
+        let (count, total) = allocations.get();
+        allocations.set((count + 1, total + bytes as u64));
+    });
+}
+
+/// The number of allocations and bytes allocated on this thread so far.
+pub fn allocations() -> (u64, u64) {
+    #[cfg(feature = "stats")]
+    return ALLOCATIONS.with(|allocations| allocations.get());
+    #[cfg(not(feature = "stats"))]
+    return (0, 0);
+}
+
+impl Stats {
+    pub fn add_allocations_since(&mut self, before: (u64, u64)) {
+        let (count, total) = allocations();
+        self.allocations += count - before.0;
+        self.allocated_bytes += total - before.1;
+    }
+
+    /// Adds the instructions of another VM that ran within this one.
+    /// Its allocations are already counted by ours.
+    pub fn add_nested(&mut self, other: &Stats) {
+        self.instructions += other.instructions;
+        self.calls += other.calls;
+        self.peak_stack_depth = self.peak_stack_depth.max(other.peak_stack_depth);
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Instructions: {}", self.instructions)?;
+        writeln!(f, "Calls: {}", self.calls)?;
+        writeln!(f, "Peak stack depth: {}", self.peak_stack_depth)?;
+        write!(f, "Allocations: {} ({} bytes)", self.allocations, self.allocated_bytes)
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::fmt::LowerExp;
+use std::sync::Mutex;
+
+use crate::interpreter::data::string_to_ptr;
+
+/// Integers in this range get a shared string when converted with to_string.
+const SMALL_INTEGERS: std::ops::Range<i128> = -128..1024;
+
+/// Shares string allocations between chunks and VM runs.
+/// The VM never frees strings, so the pointers handed out stay valid for the whole process.
+pub struct StringInterner {
+    strings: Mutex<HashMap<String, *mut ()>>,
+    small_integers: Mutex<Vec<*mut ()>>,
+}
+
+// SAFETY: The maps only hold pointers to interned strings, which are never written to or freed,
+//  so the interner may move between threads.
+unsafe impl Send for StringInterner {}
+// SAFETY: The maps are only accessed through their Mutexes, and the strings behind the pointers are immutable.
+unsafe impl Sync for StringInterner {}
+
+impl Default for StringInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringInterner {
+    pub fn new() -> StringInterner {
+        StringInterner {
+            strings: Default::default(),
+            small_integers: Default::default(),
+        }
+    }
+
+    /// Returns the shared allocation for the string, allocating it on first use.
+    pub fn intern(&self, string: &str) -> *mut () {
+        let mut strings = self.strings.lock().unwrap();
+        if let Some(ptr) = strings.get(string) {
+            return *ptr;
+        }
+
+        let ptr = string_to_ptr(string);
+        strings.insert(string.to_string(), ptr);
+        ptr
+    }
+
+    /// Converts the integer to a string, sharing allocations for small integers.
+    pub fn integer_to_string(&self, value: i128) -> *mut () {
+        if !SMALL_INTEGERS.contains(&value) {
+            return string_to_ptr(&value.to_string());
+        }
+
+        let mut small_integers = self.small_integers.lock().unwrap();
+        if small_integers.is_empty() {
+            small_integers.extend(SMALL_INTEGERS.map(|i| string_to_ptr(&i.to_string())));
+        }
+        small_integers[usize::try_from(value - SMALL_INTEGERS.start).unwrap()]
+    }
+}
+
+/// Formats the real like python's repr does: with the shortest digits that read back as the same value,
+/// a fraction even for integral values, and in scientific notation below 1e-4 and from 1e16 on.
+pub fn real_to_string(value: impl LowerExp) -> String {
+    let scientific = format!("{:e}", value);
+    let Some((mantissa, exponent)) = scientific.split_once('e') else {
+        // NaN and the infinities.
+        return scientific.to_lowercase()
+    };
+
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    let exponent: i32 = exponent.parse().unwrap();
+
+    // How many of the digits are before the decimal point.
+    match exponent + 1 {
+        ..=-4 | 17.. => {
+            let (first, rest) = digits.split_at(1);
+            let fraction = if rest.is_empty() { String::new() } else { format!(".{}", rest) };
+            format!("{}{}{}e{}{:02}", sign, first, fraction, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+        }
+        point @ ..=0 => format!("{}0.{}{}", sign, "0".repeat(point.unsigned_abs() as usize), digits),
+        point => {
+            let point = point as usize;
+            match digits.len().checked_sub(point) {
+                Some(1..) => format!("{}{}.{}", sign, &digits[..point], &digits[point..]),
+                _ => format!("{}{}{}.0", sign, digits, "0".repeat(point - digits.len())),
+            }
+        }
+    }
+}
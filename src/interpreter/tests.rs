@@ -2,21 +2,34 @@
 mod tests {
     use std::path::PathBuf;
     use std::ptr::read_unaligned;
+    use std::sync::Arc;
+    use annotate_snippets::Renderer;
+    use itertools::Itertools;
 
+    use crate::error::codes::ErrorCode;
     use crate::error::RResult;
     use crate::interpreter;
     use crate::interpreter::chunks::Chunk;
-    use crate::interpreter::compiler::compile_deep;
+    use crate::interpreter::compiler::{compile_cached, compile_deep};
+    use crate::interpreter::coverage::line_coverage;
+    use crate::interpreter::data::Value;
+    use crate::interpreter::escape::find_local_structs;
+    use crate::interpreter::json;
+    use crate::interpreter::json::Json;
     use crate::interpreter::opcode::{OpCode, Primitive};
-    use crate::interpreter::runtime::Runtime;
+    use crate::interpreter::runtime::{ModuleUpdate, Runtime};
+    use crate::interpreter::strings::StringInterner;
     use crate::interpreter::vm::VM;
     use crate::program::module::{Module, module_name};
+    use crate::repository::{Loader, ModuleSource, Project, Repository, BUNDLED_SOURCES};
     use crate::transpiler::LanguageContext;
+    use crate::program::global::{FunctionLogic, FunctionLogicDescriptor};
+    use crate::transpiler;
 
     /// This tests the transpiler, interpreter and function calls.
     #[test]
     fn run_expression() -> RResult<()> {
-        let mut runtime = Runtime::new()?;
+        let mut runtime = Runtime::new_cached()?;
         runtime.repository.add("common", PathBuf::from("monoteny"));
 
         let mut chunk = Chunk::new();
@@ -41,8 +54,84 @@ mod tests {
         Ok(())
     }
 
+    /// Values and local slots are pushed with the narrowest operand that fits, falling back to wide ones.
+    #[test]
+    fn narrow_operands() -> RResult<()> {
+        let mut chunk = Chunk::new();
+        chunk.locals_count = 300;
+        // Narrow loads must not keep the bytes a previous value left in the slot.
+        chunk.push_with_u64(OpCode::LOAD64, u64::MAX);
+        chunk.push(OpCode::POP64);
+        for (value, slot) in [(1, 0), (300, 3), (70_000, 255), (1 << 40, 299)] {
+            chunk.push_load(value);
+            chunk.push_store_local(slot);
+        }
+        for slot in [0, 3, 255, 299] {
+            chunk.push_load_local(slot);
+        }
+        chunk.push(OpCode::RETURN);
+        // LOAD8, LOAD16, LOAD32, LOAD64 and STORE_LOCAL_8 thrice, STORE_LOCAL once, and likewise for the loads.
+        assert_eq!(chunk.code.len(), 9 + 1 + (2 + 3 + 5 + 9) + (2 * 3 + 5) * 2 + 1);
+
+        let mut out: Vec<u8> = vec![];
+        let mut vm = VM::new(&chunk, &mut out);
+        vm.run()?;
+
+        unsafe {
+            assert_eq!([vm.stack[0].u64, vm.stack[8].u64, vm.stack[16].u64, vm.stack[24].u64], [1, 300, 70_000, 1 << 40]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats() -> RResult<()> {
+        let mut chunk = Chunk::new();
+        chunk.push(OpCode::LIST_NEW);
+        chunk.push(OpCode::LIST_NEW);
+        chunk.push(OpCode::RETURN);
+
+        let mut out: Vec<u8> = vec![];
+        let mut vm = VM::new(&chunk, &mut out);
+        vm.run()?;
+
+        if cfg!(feature = "stats") {
+            assert_eq!(vm.stats.instructions, 3);
+            assert_eq!(vm.stats.peak_stack_depth, 2);
+            assert_eq!(vm.stats.allocations, 2);
+            assert_eq!(vm.stats.allocated_bytes, 2 * size_of::<Vec<Value>>() as u64);
+        }
+        else {
+            // Without the feature, nothing is counted.
+            assert_eq!(vm.stats.instructions, 0);
+            assert_eq!(vm.stats.allocations, 0);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_stats() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/differential/list_sum.monoteny"), module_name("main"))?;
+        let mut out: Vec<u8> = vec![];
+        let stats = interpreter::run::main(&module, &mut runtime, &mut out)?;
+
+        if cfg!(feature = "stats") {
+            // fill and sum_range recurse once per element, and once per split.
+            assert!(stats.calls > 20_000, "{}", stats.calls);
+            assert!(stats.to_string().contains(&format!("Calls: {}", stats.calls)));
+        }
+        else {
+            assert_eq!(stats.calls, 0);
+        }
+
+        Ok(())
+    }
+
     fn test_runs(path: &str) -> RResult<String> {
-        let mut runtime = Runtime::new()?;
+        let mut runtime = Runtime::new_cached()?;
         runtime.repository.add("common", PathBuf::from("monoteny"));
 
         let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
@@ -54,9 +143,8 @@ mod tests {
 
         let mut out: Vec<u8> = vec![];
         let mut vm = VM::new(&compiled, &mut out);
-        unsafe {
-            vm.run()?;
-        }
+        vm.load_runtime(&runtime);
+        vm.run()?;
 
         Ok(std::str::from_utf8(&out).unwrap().to_string())
     }
@@ -78,6 +166,14 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn trailing_commas() -> RResult<()> {
+        let out = test_runs("test-code/grammar/trailing_commas.monoteny")?;
+        assert_eq!(out, "square has size 4\nrectangle has size 6\n");
+
+        Ok(())
+    }
+
     #[test]
     fn eq0() -> RResult<()> {
         test_runs("test-code/requirements/eq0.monoteny")?;
@@ -96,6 +192,25 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn ord_bool() {
+        let errors = test_runs("test-code/requirements/ord_bool.monoteny").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let failure = &errors[0].notes[0].notes[0];
+        assert!(failure.title.starts_with("No compatible declaration for trait conformance requirement: Ord<"));
+        assert!(failure.title.ends_with(": Bool>"));
+        assert_eq!(failure.code, Some(ErrorCode::MissingConformance));
+    }
+
+    /// Generic functions that aren't inlined are called, once per monomorphization.
+    #[test]
+    fn generic_clamp() -> RResult<()> {
+        let out = test_runs("test-code/differential/clamp.monoteny")?;
+        assert_eq!(out, "100 0 0.5\n");
+
+        Ok(())
+    }
+
     #[test]
     fn string_interpolation() -> RResult<()> {
         let out = test_runs("test-code/grammar/string_interpolation.monoteny")?;
@@ -104,6 +219,110 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn literal_ranges() -> RResult<()> {
+        let out = test_runs("test-code/literals/in_range.monoteny")?;
+        assert_eq!(out, "-128 127\n-32768 32767\n-2147483648 2147483647\n-9223372036854775808 9223372036854775807\n0 255\n0 65535\n0 4294967295\n0 18446744073709551615\n-128 5 -1.5\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_literal() -> RResult<()> {
+        let out = test_runs("test-code/literals/decimal.monoteny")?;
+        assert_eq!(out, "Price: 3.5, Count: 2.0\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn builtin_keywords() -> RResult<()> {
+        let out = test_runs("test-code/signatures/builtin_keywords.monoteny")?;
+        assert_eq!(out, "false\nfalse\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn literal_overflow() {
+        let errors = test_runs("test-code/literals/int_overflow.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Literal 300 is out of range for Int8.");
+        assert_eq!(errors[0].range, Some(132..135));
+        assert_eq!(errors[0].code, Some(ErrorCode::LiteralOutOfRange));
+
+        // The minus belongs to the literal.
+        let errors = test_runs("test-code/literals/negative_overflow.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Literal -129 is out of range for Int8.");
+        assert_eq!(errors[0].range, Some(141..145));
+
+        let errors = test_runs("test-code/literals/real_overflow.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Literal 1000000000000000000000000000000000000000.0 is out of range for Float32.");
+    }
+
+    #[test]
+    fn compound_assignment() -> RResult<()> {
+        let out = test_runs("test-code/mutability/compound_assignment.monoteny")?;
+        assert_eq!(out, "8\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn deferred_assignment() -> RResult<()> {
+        let out = test_runs("test-code/mutability/deferred_assignment.monoteny")?;
+        assert_eq!(out, "negative zero positive\n0 4\n");
+
+        let errors = test_runs("test-code/mutability/deferred_missing_else.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "`value` is read before it is assigned on every path.");
+        assert_eq!(errors[0].code, Some(ErrorCode::UnassignedLocal));
+        assert_eq!(errors[0].range, Some(194..199));
+
+        let errors = test_runs("test-code/mutability/deferred_read_before.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "`value` is read before it is assigned.");
+        assert_eq!(errors[0].range, Some(142..147));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reflection() -> RResult<()> {
+        let out = test_runs("test-code/reflection/debug_print.monoteny")?;
+        assert_eq!(out, "Int32: 5\nString: hello\nPoint: (1.0, 2.0)\nPoint has 2 fields, the first is x.\n");
+
+        let errors = test_runs("test-code/reflection/unknown_type.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "The type passed to type_name must be known at compile time, but could not be inferred.");
+        assert_eq!(errors[0].range, Some(56..77));
+
+        let errors = test_runs("test-code/reflection/fields_of_primitive.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "fields_of needs a struct, but Int32 has no fields.");
+        assert_eq!(errors[0].range, Some(58..74));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed_interpolation() -> RResult<()> {
+        let out = test_runs("test-code/grammar/shadowed_interpolation.monoteny")?;
+        assert_eq!(out, "Format: 3, Add: added\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolate_primitives() -> RResult<()> {
+        let out = test_runs("test-code/grammar/interpolate_primitives.monoteny")?;
+        assert_eq!(out, "Flag: true, Small: -5\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolate_unformattable() {
+        let errors = test_runs("test-code/grammar/interpolate_unformattable.monoteny").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].title, "Cannot format type Point in string interpolation.");
+    }
+
     #[test]
     fn if_then_else() -> RResult<()> {
         let out = test_runs("test-code/control_flow/if_then_else.monoteny")?;
@@ -112,6 +331,26 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn coverage() -> RResult<()> {
+        let path = PathBuf::from("test-code/control_flow/coverage.monoteny");
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&path, module_name("main"))?;
+
+        let mut out: Vec<u8> = vec![];
+        let coverage = interpreter::run::main_with_coverage(&module, &mut runtime, &mut out)?;
+        assert_eq!(String::from_utf8(out).unwrap(), "small\n");
+
+        let files = line_coverage(&coverage, &runtime, &module);
+        let file = files.iter().find(|file| file.path == path).unwrap();
+        // Only the branch that was not taken is missing.
+        assert_eq!(file.uncovered_lines(), vec![5]);
+        assert_eq!(file.lines.get(&8), Some(&true));
+
+        Ok(())
+    }
+
     #[test]
     fn and_or() -> RResult<()> {
         let out = test_runs("test-code/control_flow/and_or.monoteny")?;
@@ -119,4 +358,860 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn shared_expressions() -> RResult<()> {
+        let out = test_runs("test-code/refactor/shared_expressions.monoteny")?;
+        assert_eq!(out, "20\n20\n25\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn guarded_division() -> RResult<()> {
+        // The division by zero is guarded, so it must not be hoisted.
+        let out = test_runs("test-code/refactor/guarded_division.monoteny")?;
+        assert_eq!(out, "Dividing by 0.\nDividing by 4.\n25\n25\n13\n13\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_constants() -> RResult<()> {
+        let out = test_runs("test-code/globals/lazy_constants.monoteny")?;
+        assert_eq!(out, "Start.\nInitializing greeting.\nHello\nHello\n");
+
+        Ok(())
+    }
+
+    /// Blocks as values, and returns from within nested blocks.
+    #[test]
+    fn blocks() -> RResult<()> {
+        let out = test_runs("test-code/control_flow/blocks.monoteny")?;
+        assert_eq!(out, "Block value.\nNested block.\n");
+
+        Ok(())
+    }
+
+    /// Blocks passed as arguments leave their value for the call.
+    #[test]
+    fn block_arguments() -> RResult<()> {
+        let out = test_runs("test-code/control_flow/block_arguments.monoteny")?;
+        assert_eq!(out, "9\n4\nLarge when scaled.\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowing() -> RResult<()> {
+        let out = test_runs("test-code/control_flow/shadowing.monoteny")?;
+        assert_eq!(out, "2 shadowed shadowed!\ninner 11\nouter shadowed\n1 111\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_blocks() -> RResult<()> {
+        let out = test_runs("test-code/control_flow/empty_blocks.monoteny")?;
+        assert_eq!(out, "effect\neffect\nelse\ndone\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn constant_cycle() {
+        let Err(errors) = test_runs("test-code/globals/constant_cycle.monoteny") else {
+            panic!("Cyclic constants should fail.");
+        };
+        let title = &errors[0].title;
+        assert!(title.contains("'ping'") && title.contains("'pong'"), "{}", title);
+    }
+
+    #[test]
+    fn int_condition() {
+        let Err(errors) = test_runs("test-code/control_flow/int_condition.monoteny") else {
+            panic!("Int conditions should fail.");
+        };
+        assert_eq!(errors[0].title, "if condition must be Bool, found Int64.");
+        assert_eq!(errors[0].range, Some(151..156));
+        assert_eq!(errors[0].notes[0].title, "Use an explicit comparison to get a Bool, e.g. `!= 0`.");
+    }
+
+    #[test]
+    fn list() -> RResult<()> {
+        let out = test_runs("test-code/collections/list.monoteny")?;
+        assert_eq!(out, "3 5 3 2\n");
+
+        Ok(())
+    }
+
+    /// Has enough bodies to compile them on worker threads, if there are cores for them.
+    #[test]
+    fn parallel_compile() -> RResult<()> {
+        let out = test_runs("test-code/performance/parallel_compile.monoteny")?;
+        assert_eq!(out, "364\n");
+
+        Ok(())
+    }
+
+    /// Lists can be passed to functions, which recurse to fill and sum them.
+    #[test]
+    fn list_sum() -> RResult<()> {
+        let out = test_runs("test-code/differential/list_sum.monoteny")?;
+        assert_eq!(out, "10000 49995000\n1 a\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn struct_fields() -> RResult<()> {
+        let out = test_runs("test-code/traits/fields.monoteny")?;
+        assert_eq!(out, "Noir (Cat) was: 180.0cm\nAnd is now: 25.0\n");
+
+        Ok(())
+    }
+
+    /// Like in python, a struct passed to a non-mut parameter is copied when assigned to a var.
+    #[test]
+    fn mut_parameter() -> RResult<()> {
+        let out = test_runs("test-code/mutability/mut_parameter.monoteny")?;
+        assert_eq!(out, "Noir is 35.0cm\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn generic_struct_arguments() -> RResult<()> {
+        let out = test_runs("test-code/traits/generic_struct_arguments.monoteny")?;
+        assert_eq!(out, "2\nOne\n1 / One\nOne / 1\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn where_clause() -> RResult<()> {
+        let out = test_runs("test-code/signatures/where_clause.monoteny")?;
+        assert_eq!(out, "5 2.5 0\n");
+
+        let path = "test-code/signatures/where_clause_unsatisfied.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+        let errors = test_runs(path).unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::UnresolvedFunction));
+        let note = errors[0].notes.last().unwrap();
+        assert_eq!(note.title, "Required by this where clause.");
+        assert_eq!(&source[note.range.clone().unwrap()], "#T is Ord");
+
+        Ok(())
+    }
+
+    #[test]
+    fn struct_where_clause() -> RResult<()> {
+        let out = test_runs("test-code/traits/struct_where_clause.monoteny")?;
+        assert_eq!(out, "true false\ntrue true\n");
+
+        let path = "test-code/traits/struct_where_clause_unsatisfied.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+        let errors = test_runs(path).unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::UnresolvedFunction));
+        let note = errors[0].notes.last().unwrap();
+        assert_eq!(note.title, "Required by this where clause.");
+        assert_eq!(&source[note.range.clone().unwrap()], "#T is Ord");
+
+        Ok(())
+    }
+
+    /// Declaration order doesn't matter, even for conformances and types.
+    #[test]
+    fn forward_references() -> RResult<()> {
+        let out = test_runs("test-code/traits/forward_references.monoteny")?;
+        assert_eq!(out, "Hello, Ada! Meet Rex.\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn local_structs() -> RResult<()> {
+        let path = "test-code/performance/local_structs.monoteny";
+        let out = test_runs(path)?;
+        assert_eq!(out, "650 8\n");
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+        let local_structs = |name: &str| {
+            let head = module.implemented_functions.iter().find(|head| runtime.source.fn_representations[*head].name == name).unwrap();
+            let implementation = runtime.source.fn_logic[head].as_implementation().unwrap();
+            find_local_structs(implementation, &runtime.source.fn_logic).keys()
+                .map(|local| implementation.locals_names[local].clone())
+                .collect_vec()
+        };
+        assert_eq!(local_structs("sum_points"), vec!["point"]);
+        // Returned, passed as an argument, assigned from a call, or shared with another local.
+        assert!(local_structs("make_point").is_empty());
+        assert!(local_structs("length").is_empty());
+        assert!(local_structs("escaping").is_empty());
+
+        if cfg!(feature = "stats") {
+            // Iterations don't allocate, so their number doesn't change the count.
+            let source = std::fs::read_to_string(path).unwrap();
+            let allocations = |iterations: usize| {
+                let mut runtime = Runtime::new_cached()?;
+                runtime.repository.add("common", PathBuf::from("monoteny"));
+                let module = runtime.load_text_as_module(&source.replace("sum_points(20, 0)", &format!("sum_points({}, 0)", iterations)), module_name("main"))?;
+                let mut out: Vec<u8> = vec![];
+                RResult::Ok(interpreter::run::main(&module, &mut runtime, &mut out)?.allocations)
+            };
+            assert_eq!(allocations(5)?, allocations(25)?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_console() -> RResult<()> {
+        let out = test_runs("test-code/differential/console.monoteny")?;
+        assert_eq!(out, "Nothing was written yet.\nHello, Cat!\nGoodbye, Cat!\nCaptured:\nHello, Noir!\nGoodbye, Noir!\n\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> RResult<()> {
+        let out = test_runs("test-code/differential/enums.monoteny")?;
+        assert_eq!(out, "warm\ncool\ngreen is not blue\ngreen\nno longer red\n");
+
+        Ok(())
+    }
+
+    /// Anonymous structs of the same fields share their struct, but not their type if their fields' types differ.
+    #[test]
+    fn anonymous_structs() -> RResult<()> {
+        let out = test_runs("test-code/differential/anonymous_structs.monoteny")?;
+        assert_eq!(out, "17 / 5 = 3 remainder 2\n42 = 4 * 10 + 2\n1 / 4 = 0.25\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn destructuring() -> RResult<()> {
+        let out = test_runs("test-code/differential/destructuring.monoteny")?;
+        assert_eq!(out, "7 = 3 * 2 + 1\n17 % 5 + 1 = 3\n(3, 4)\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_destructuring() {
+        let errors = test_runs("test-code/types/repeated_destructuring.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Variable q is declared twice.");
+    }
+
+    #[test]
+    fn unnamed_fields() {
+        let errors = test_runs("test-code/types/unnamed_fields.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Anonymous struct literals must name their fields, like (q: 1, r: 2).");
+    }
+
+    /// Boxed values of different types share a list, and calls on them dispatch to their own implementation.
+    #[test]
+    fn dyn_box() -> RResult<()> {
+        let out = test_runs("test-code/differential/dyn_box.monoteny")?;
+        assert_eq!(out, "1\n2.5\nthree\n(4, 5)\nBark Meow\nBark Bark Bark, Meow x2\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn unboxable() {
+        let errors = test_runs("test-code/traits/unboxable.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Shape cannot be boxed.");
+    }
+
+    /// Strings built at runtime are equal to literals of the same contents.
+    #[test]
+    fn string_equality() -> RResult<()> {
+        let out = test_runs("test-code/differential/string_equality.monoteny")?;
+        assert_eq!(out, "found missing\nsame\n");
+
+        Ok(())
+    }
+
+    /// Strings are ordered by their code points, so min and max work for them like for primitives.
+    #[test]
+    fn min_max() -> RResult<()> {
+        let out = test_runs("test-code/differential/min_max.monoteny")?;
+        assert_eq!(out, "3 5 10 0.0\napple pear c\ntrue false true\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_to_string() -> RResult<()> {
+        let out = test_runs("test-code/differential/list_to_string.monoteny")?;
+        assert_eq!(out, "[1, 2, 3]\nnested: [[a, b], []]\n");
+
+        Ok(())
+    }
+
+    /// Reals are written like python writes them, so both backends print the same.
+    #[test]
+    fn real_formatting() -> RResult<()> {
+        let out = test_runs("test-code/differential/real_formatting.monoteny")?;
+        assert_eq!(out, "1.0 0.30000000000000004 -0.0\n1e+16 1234567890123456.0 1.2345678901234568e+17\n0.0001 1e-05 1.2345e-05\n1.0 0.3 16777216.0 1e-05\n");
+
+        Ok(())
+    }
+
+    /// Structs derive ToJson from their fields when declaring the conformance without functions.
+    #[test]
+    fn json() -> RResult<()> {
+        let out = test_runs("test-code/differential/json.monoteny")?;
+        assert_eq!(out, r#""plain"
+"say \"hi\""
+"back\\slash"
+"line\nbreak\ttab\rreturn"
+"bell \u0007 unit \u001f"
+"café € 🙂"
+""
+"\""
+-12 200 false
+{"x": 1, "y": -2}
+{"text": "a \"b\"", "position": {"x": 0, "y": 3}, "visible": true}
+"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_parse() -> RResult<()> {
+        let out = test_runs("test-code/differential/json_parse.monoteny")?;
+        assert_eq!(out, "object of 3\nstring say \"hi\"\n\ntext survived the round trip\nx survived the round trip\ny survived the round trip\ntrue\nnull of 0\narray of 4\npositive number\nstring café 🙂\nobject of 1\narray of 1\nfalse\narray of 0\nnull of 0\nnull of 0\n");
+
+        let errors = test_runs("test-code/json/invalid.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Invalid JSON at line 3, char 7: Expecting ':' delimiter.");
+        assert_eq!(errors[0].code, Some(ErrorCode::InvalidJson));
+
+        let errors = test_runs("test-code/json/unexpected_kind.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Expected a JSON string, but got number.");
+        assert_eq!(errors[0].code, Some(ErrorCode::UnexpectedJsonKind));
+
+        Ok(())
+    }
+
+    /// Json operations compile from their descriptors too, if they weren't registered with the builtins.
+    #[test]
+    fn json_descriptors() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let is_json_operation = |logic: Option<&FunctionLogic>| matches!(logic, Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::JsonOperation(_))));
+        let count = runtime.function_inlines.len();
+        runtime.function_inlines.retain(|head, _| !is_json_operation(runtime.source.fn_logic.get(head)));
+        assert!(runtime.function_inlines.len() < count);
+
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/differential/json_parse.monoteny"), module_name("main"))?;
+        let mut out: Vec<u8> = vec![];
+        interpreter::run::main(&module, &mut runtime, &mut out)?;
+        assert!(String::from_utf8(out).unwrap().starts_with("object of 3\nstring say \"hi\"\n"));
+
+        Ok(())
+    }
+
+    /// Errors are positioned like python's json module positions them.
+    #[test]
+    fn json_parse_errors() {
+        for (string, title) in [
+            ("", "Invalid JSON at line 1, char 1: Expecting value."),
+            ("[1, 2,]", "Invalid JSON at line 1, char 7: Expecting value."),
+            ("{\"a\": 1,}", "Invalid JSON at line 1, char 9: Expecting property name enclosed in double quotes."),
+            ("[1 2]", "Invalid JSON at line 1, char 4: Expecting ',' delimiter."),
+            ("\"abc", "Invalid JSON at line 1, char 1: Unterminated string starting at."),
+            ("\"a\tb\"", "Invalid JSON at line 1, char 3: Invalid control character at."),
+            ("\"\\x\"", "Invalid JSON at line 1, char 2: Invalid \\escape."),
+            ("[\"é\"] 1", "Invalid JSON at line 1, char 7: Extra data."),
+            ("01", "Invalid JSON at line 1, char 2: Extra data."),
+            ("\n -", "Invalid JSON at line 2, char 2: Expecting value."),
+        ] {
+            assert_eq!(json::parse(string).unwrap_err()[0].title, title, "{:?}", string);
+        }
+
+        assert_eq!(json::parse("-0.5e1").unwrap(), Json::Number(-5.0));
+        assert_eq!(json::parse("\"\\ud83d\\ude42\\/\"").unwrap(), Json::String("🙂/".to_string()));
+    }
+
+    #[test]
+    fn char() -> RResult<()> {
+        let out = test_runs("test-code/primitives/char.monoteny")?;
+        assert_eq!(out, "é 233 true true\n5\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn not() -> RResult<()> {
+        let out = test_runs("test-code/primitives/not.monoteny")?;
+        assert_eq!(out, "false true true false true\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn char_from_surrogate() {
+        let mut chunk = Chunk::new();
+        chunk.push_with_u32(OpCode::LOAD32, 0xD800);
+        chunk.push(OpCode::CHAR_FROM_CODE);
+        chunk.push(OpCode::RETURN);
+
+        let mut out: Vec<u8> = vec![];
+        let mut vm = VM::new(&chunk, &mut out);
+        assert!(vm.run().is_err());
+    }
+
+    fn jump_back(chunk: &mut Chunk, opcode: OpCode, target: usize) {
+        // +5 because opcode and argument were popped
+        let distance = target as i64 - (chunk.code.len() + 5) as i64;
+        chunk.push_with_u32(opcode, i32::try_from(distance).unwrap() as u32);
+    }
+
+    fn fix_jump(chunk: &mut Chunk, jump_location: usize) {
+        let distance = chunk.code.len() - (jump_location + 5);
+        chunk.modify_u32(jump_location + 1, u32::try_from(distance).unwrap());
+    }
+
+    /// Concatenates with empty strings and converts small integers in a loop; none of it should allocate.
+    #[test]
+    fn string_fast_paths() -> RResult<()> {
+        let strings = Arc::new(StringInterner::new());
+        let (string, i) = (0, 1);
+
+        let mut chunk = Chunk::new();
+        chunk.locals_count = 2;
+        chunk.constants = vec![Value { ptr: strings.intern("Hello") }, Value { ptr: strings.intern("") }];
+        let (hello, empty) = (0, 1);
+
+        chunk.push_with_u32(OpCode::LOAD_CONSTANT, hello);
+        chunk.push_with_u32(OpCode::STORE_LOCAL, string);
+        chunk.push_with_u64(OpCode::LOAD64, 0);
+        chunk.push_with_u32(OpCode::STORE_LOCAL, i);
+
+        // while i < 100000 { string = ("" + string) + ""; (i % 100).to_string(); i += 1 }
+        let loop_start = chunk.code.len();
+        chunk.push_with_u32(OpCode::LOAD_LOCAL, i);
+        chunk.push_with_u64(OpCode::LOAD64, 100000);
+        chunk.push_with_u8(OpCode::LE, Primitive::I64 as u8);
+        let loop_end = chunk.code.len();
+        chunk.push_with_u32(OpCode::JUMP_IF_FALSE, 0);
+        chunk.push_with_u32(OpCode::LOAD_CONSTANT, empty);
+        chunk.push_with_u32(OpCode::LOAD_LOCAL, string);
+        chunk.push(OpCode::ADD_STRING);
+        chunk.push_with_u32(OpCode::LOAD_CONSTANT, empty);
+        chunk.push(OpCode::ADD_STRING);
+        chunk.push_with_u32(OpCode::STORE_LOCAL, string);
+        chunk.push_with_u32(OpCode::LOAD_LOCAL, i);
+        chunk.push_with_u64(OpCode::LOAD64, 100);
+        chunk.push_with_u8(OpCode::MOD, Primitive::I64 as u8);
+        chunk.push_with_u8(OpCode::TO_STRING, Primitive::I64 as u8);
+        chunk.push(OpCode::POP64);
+        chunk.push_with_u32(OpCode::LOAD_LOCAL, i);
+        chunk.push_with_u64(OpCode::LOAD64, 1);
+        chunk.push_with_u8(OpCode::ADD, Primitive::I64 as u8);
+        chunk.push_with_u32(OpCode::STORE_LOCAL, i);
+        jump_back(&mut chunk, OpCode::JUMP, loop_start);
+        fix_jump(&mut chunk, loop_end);
+
+        chunk.push_with_u32(OpCode::LOAD_LOCAL, string);
+        chunk.push_with_u64(OpCode::LOAD64, 42);
+        chunk.push_with_u8(OpCode::TO_STRING, Primitive::I64 as u8);
+        chunk.push(OpCode::RETURN);
+
+        let mut out: Vec<u8> = vec![];
+        let mut vm = VM::new(&chunk, &mut out);
+        vm.strings = Arc::clone(&strings);
+        vm.run()?;
+
+        unsafe {
+            assert_eq!(vm.stack[0].ptr, strings.intern("Hello"));
+            assert_eq!(vm.stack[8].ptr, strings.integer_to_string(42));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_out_of_bounds() {
+        let mut chunk = Chunk::new();
+        chunk.push(OpCode::LIST_NEW);
+        chunk.push_with_u64(OpCode::LOAD64, 0);
+        chunk.push(OpCode::LIST_GET);
+        chunk.push(OpCode::RETURN);
+
+        let mut out: Vec<u8> = vec![];
+        let mut vm = VM::new(&chunk, &mut out);
+        assert_eq!(vm.run().unwrap_err()[0].title, "index 0 out of bounds for length 0");
+    }
+
+    #[test]
+    fn list_negative_index() {
+        let errors = test_runs("test-code/collections/list_negative_index.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "index -1 out of bounds for length 1");
+        assert_eq!(errors[0].code, Some(ErrorCode::IndexOutOfBounds));
+    }
+
+    #[test]
+    fn int_pow() -> RResult<()> {
+        let out = test_runs("test-code/primitives/int_pow.monoteny")?;
+        assert_eq!(out, "1024\n1\n-128\n9\n0.5\n");
+
+        let errors = test_runs("test-code/primitives/int_pow_negative.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "integer pow with negative exponent");
+        assert_eq!(errors[0].code, Some(ErrorCode::InvalidExponent));
+
+        Ok(())
+    }
+
+    /// Runs code against the bundled common library, as the wasm build does.
+    #[test]
+    fn compile_and_run() -> RResult<()> {
+        let mut out = String::new();
+        interpreter::run::compile_and_run("let a 'Int32 = 3;\nwrite_line(\"Hello \\(a)\");", |s| out.push_str(s))?;
+        assert_eq!(out, "Hello 3\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable() -> RResult<()> {
+        let path = "test-code/control_flow/unreachable.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+
+        let unreachable = module.warnings.iter()
+            .map(|warning| &source[warning.range.clone().unwrap()])
+            .sorted()
+            .collect_vec();
+        assert_eq!(unreachable, vec![
+            "write_line(\"Unreachable after if.\");",
+            "write_line(\"Unreachable after return.\");",
+        ]);
+
+        // Dead code is dropped before compilation.
+        let function_named = |name: &str| module.exposed_functions.iter()
+            .find(|function| runtime.source.fn_representations[*function].name == name)
+            .map(Arc::clone)
+            .unwrap();
+        let (answer, answer_without_dead_code) = (function_named("answer"), function_named("answer_without_dead_code"));
+        assert_eq!(
+            compile_deep(&mut runtime, &answer)?.code.len(),
+            compile_deep(&mut runtime, &answer_without_dead_code)?.code.len(),
+        );
+
+        assert_eq!(test_runs(path)?, "Reachable.\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn static_type_test() -> RResult<()> {
+        let path = "test-code/types/static_type_test.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+
+        // Every test is known statically, because the value isn't generic.
+        let warnings = module.warnings.iter()
+            .map(|warning| (warning.title.as_str(), &source[warning.range.clone().unwrap()]))
+            .sorted()
+            .collect_vec();
+        assert_eq!(warnings, vec![
+            ("Cast always succeeds.", "number as Int32"),
+            ("Type check is always false.", "number is Bool"),
+            ("Type check is always true.", "number is Int32"),
+        ]);
+
+        assert_eq!(test_runs(path)?, "true false 5\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed_primitive() -> RResult<()> {
+        let path = "test-code/types/shadowed_primitive.monoteny";
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+
+        let [warning] = &module.warnings[..] else {
+            panic!("Expected one warning, got {:?}", module.warnings)
+        };
+        assert_eq!(warning.title, "Trait Float32 shadows the builtin primitive of the same name.");
+        assert_eq!(warning.code, Some(ErrorCode::ShadowedPrimitive));
+        assert_eq!(test_runs(path)?, "1.5\n");
+
+        let errors = test_runs("test-code/types/shadowed_primitive_literal.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Cannot initialize `x` of type Float32 with this value.");
+        assert_eq!(errors[0].notes.last().unwrap().title, "Float32 is a trait declared in source, not the builtin primitive Float32.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_cast() {
+        let errors = test_runs("test-code/types/failed_cast.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Cannot cast Int32 to Bool.");
+    }
+
+    #[test]
+    fn type_test() -> RResult<()> {
+        assert_eq!(test_runs("test-code/types/type_test.monoteny")?, "Int32: 5\nOther: true\ntrue\n8\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn struct_cast() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/types/struct_cast.monoteny"), module_name("main"))?;
+
+        // The struct's type is checked at runtime, so the first cast succeeds before the second fails.
+        let mut out: Vec<u8> = vec![];
+        let errors = interpreter::run::main(&module, &mut runtime, &mut out).unwrap_err();
+        assert_eq!(std::str::from_utf8(&out).unwrap(), "3\n");
+        assert_eq!(errors[0].title, "Cannot cast Bool to Point.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn declaration_conflict() {
+        let errors = test_runs("test-code/types/declaration_conflict.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Cannot initialize `flag` of type String with a value of type Bool.");
+        assert_eq!(errors[0].range, Some(113..137));
+        assert_eq!(errors[0].notes[0].title, "`flag` is declared as String here.");
+        assert_eq!(errors[0].notes[0].range, Some(123..129));
+        assert_eq!(errors[0].notes[1].range, Some(132..136));
+
+        let errors = test_runs("test-code/types/declaration_literal_conflict.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Cannot initialize `count` of type String with this value.");
+        assert!(errors[0].notes[1].title.contains("ConstructableByIntLiteral"));
+    }
+
+    #[test]
+    fn ascription() -> RResult<()> {
+        assert_eq!(test_runs("test-code/types/ascription.monoteny")?, "9\n1.0\n");
+
+        let errors = test_runs("test-code/types/ascription_conflict.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Value cannot be ascribed Int64.");
+        assert_eq!(errors[0].range, Some(178..183));
+        assert_eq!(errors[0].notes[0].title, "Cannot merge types: Float32 and Int64");
+
+        Ok(())
+    }
+
+    #[test]
+    fn one_liners() -> RResult<()> {
+        assert_eq!(test_runs("test-code/refactor/one_liners.monoteny")?, "40\n");
+
+        Ok(())
+    }
+
+    /// Loads the deep_addition fixture, chaining its addition so that the expression is depth calls deep.
+    fn load_deep_addition(depth: usize) -> RResult<(Box<Runtime>, Box<Module>)> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let source = std::fs::read_to_string("test-code/refactor/deep_addition.monoteny").unwrap();
+        let source = source.replace("one + one", &vec!["one"; depth + 1].join(" + "));
+        let module = runtime.load_text_as_module(&source, module_name("main"))?;
+
+        Ok((runtime, module))
+    }
+
+    /// Expressions are resolved, compiled and transpiled without recursion, so that deep trees don't overflow the stack.
+    #[test]
+    fn deep_expression() -> RResult<()> {
+        let depth = 1000;
+
+        let (mut runtime, module) = load_deep_addition(depth)?;
+        let mut out: Vec<u8> = vec![];
+        interpreter::run::main(&module, &mut runtime, &mut out)?;
+        assert_eq!(std::str::from_utf8(&out).unwrap(), format!("{}\n", depth + 1));
+
+        let (mut runtime, module) = load_deep_addition(depth)?;
+        let context = transpiler::python::Context::new(&runtime);
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
+        assert_eq!(file_map["main.py"].matches(" + one").count(), depth);
+        // Python rejects more than 200 nested parentheses.
+        assert!(file_map["main.py"].contains(&format!("str({})", vec!["one"; depth + 1].join(" + "))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn impure_pure() {
+        let errors = test_runs("test-code/refactor/impure_pure.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "`loud_sum` is declared pure, but calls `loud_cube`, which calls `write_line`, which calls `write_line`, which calls `_write_line`.");
+        assert_eq!(errors[0].range, Some(188..192));
+    }
+
+    #[test]
+    fn project_prelude() -> RResult<()> {
+        let run = |path: &str, use_project: bool| -> RResult<String> {
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+            if use_project {
+                runtime.load_project_for(&PathBuf::from(path))?;
+            }
+
+            let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+            let mut out: Vec<u8> = vec![];
+            interpreter::run::main(&module, &mut runtime, &mut out)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+
+        // The project file is found from nested directories.
+        assert_eq!(run("test-code/project/nested/deeper/implicit.monoteny", true)?, "Hello from the prelude!\n");
+        assert_eq!(run("test-code/project/nested/explicit.monoteny", true)?, "Hello again!\n");
+        assert_eq!(run("test-code/project/nested/explicit.monoteny", false)?, "Hello again!\n");
+
+        let errors = run("test-code/project/nested/deeper/implicit.monoteny", false).unwrap_err();
+        assert_eq!(errors[0].title, "Cannot find 'write_line' in this scope");
+
+        Ok(())
+    }
+
+    #[test]
+    fn project_file() {
+        let project = Project::parse("[prelude]\nimports = [\"common\", \"core.debug\",] # Comment\n").unwrap();
+        assert_eq!(project.prelude, vec![module_name("common"), module_name("core.debug")]);
+
+        let errors = Project::parse("[prelude]\nimport = [\"common\"]").unwrap_err();
+        assert_eq!(errors[0].title, "Unknown key `import` in [prelude] on line 2.");
+
+        assert!(Project::parse("[prelude]\nimports = \"common\"").is_err());
+        assert!(Project::parse("[prelude]\nimports = [\n\"common\"\n").is_err());
+    }
+
+    #[test]
+    fn module_file_paths() -> RResult<()> {
+        let mut repository = Repository::new();
+        repository.add("common", PathBuf::from("monoteny"));
+
+        let ModuleSource::File(path) = repository.resolve_module(&module_name("common.math"))? else {
+            panic!("common.math was not resolved to a file.")
+        };
+        assert_eq!(path, PathBuf::from("monoteny").join("common").join("math.monoteny"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn embedded_std() -> RResult<()> {
+        // Files in the monoteny directory override the embedded sources.
+        let mut repository = Repository::new();
+        repository.add_std("core", true);
+        assert!(matches!(repository.resolve_module(&module_name("core.strings"))?, ModuleSource::File(_)));
+
+        // Without the directory, the embedded sources are used.
+        let Loader::Layered(_, sources) = repository.entries["core"].clone() else {
+            panic!("core is not layered.")
+        };
+        repository.entries.insert("core".to_string(), Loader::Layered(PathBuf::from("nonexistent"), sources));
+        let ModuleSource::Text(text) = repository.resolve_module(&module_name("core.strings"))? else {
+            panic!("core.strings was not resolved to its embedded source.")
+        };
+        assert_eq!(text, BUNDLED_SOURCES.iter().find(|(path, _)| *path == "core/strings").unwrap().1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        // Built from explicit bytes, so checkouts that convert line endings don't change the test.
+        let lf = "use!(module!(\"common\"));\n\ndef main! :: {\n    write_line(x);\n};\n".to_string();
+        let crlf = lf.replace('\n', "\r\n");
+
+        let render = |source: &str| {
+            let mut runtime = Runtime::new_cached().unwrap();
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+            let Err(errors) = runtime.load_text_as_module(source, module_name("main")) else {
+                panic!("x was found.")
+            };
+            assert_eq!(&source[errors[0].range.clone().unwrap()], "x");
+            errors[0].render_in_source(source, "<source>", &Renderer::plain())
+        };
+
+        let (lf_rendered, crlf_rendered) = (render(&lf), render(&crlf));
+        assert!(lf_rendered.contains("<source>:4:16"));
+        assert!(crlf_rendered.contains("<source>:4:16"));
+        assert!(!crlf_rendered.contains('\r'));
+    }
+
+    #[test]
+    fn cached_runtime() -> RResult<()> {
+        let fresh = Runtime::new()?;
+        let mut runtime = Runtime::new_cached()?;
+
+        // Runtimes cached on other threads are clones of the same one.
+        let other_thread = std::thread::spawn(|| Runtime::new_cached().map(|runtime| Arc::clone(&runtime.Metatype))).join().unwrap()?;
+        assert!(Arc::ptr_eq(&other_thread, &runtime.Metatype));
+        assert!(!Arc::ptr_eq(&fresh.Metatype, &runtime.Metatype));
+
+        // Loading modules into a clone leaves the other runtimes as they were.
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.get_or_load_module(&module_name("common"))?;
+        let other = Runtime::new_cached()?;
+        assert!(!other.repository.entries.contains_key("common"));
+        assert!(!other.source.module_by_name.contains_key(&module_name("common")));
+        assert_eq!(other.source.fn_heads.len(), fresh.source.fn_heads.len());
+        assert!(runtime.source.fn_heads.len() > fresh.source.fn_heads.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_function_body() -> RResult<()> {
+        let source = std::fs::read_to_string("test-code/incremental/edit.monoteny").unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let mut module = runtime.load_text_as_module(&source, module_name("main"))?;
+
+        let run = |runtime: &mut Runtime, module: &Module| -> RResult<String> {
+            let mut out: Vec<u8> = vec![];
+            interpreter::run::main(module, runtime, &mut out)?;
+            Ok(String::from_utf8(out).unwrap())
+        };
+        assert_eq!(run(&mut runtime, &module)?, "9\n8\n");
+
+        let function_named = |runtime: &Runtime, module: &Module, name: &str| module.exposed_functions.iter()
+            .find(|function| runtime.source.fn_representations[*function].name == name)
+            .map(Arc::clone)
+            .unwrap();
+        let (square, cube) = (function_named(&runtime, &module, "print_square"), function_named(&runtime, &module, "print_cube"));
+        compile_cached(&mut runtime, &square)?;
+        compile_cached(&mut runtime, &cube)?;
+        let square_code = runtime.function_evaluators[&square.function_id].code.as_ptr();
+        let cube_code = runtime.function_evaluators[&cube.function_id].code.as_ptr();
+
+        // Only cube is resolved and compiled again; square keeps its chunk.
+        let update = runtime.update_module(&mut module, &source.replace("x * x * x", "x * x * x + 1"))?;
+        assert_eq!(update, ModuleUpdate::Bodies(vec![Arc::clone(&cube)]));
+        assert_eq!(runtime.function_evaluators[&square.function_id].code.as_ptr(), square_code);
+        assert_ne!(runtime.function_evaluators[&cube.function_id].code.as_ptr(), cube_code);
+        assert_eq!(run(&mut runtime, &module)?, "9\n9\n");
+        assert_eq!(runtime.function_evaluators[&square.function_id].code.as_ptr(), square_code);
+
+        // Interface changes resolve the whole module again.
+        let update = runtime.update_module(&mut module, &source.replace("def print_cube(x 'Int32)", "def print_cube(x 'Int64)").replace("x * x * x", "x * x * x + 1"))?;
+        assert_eq!(update, ModuleUpdate::Module);
+        assert!(!module.exposed_functions.contains(&cube));
+        assert_eq!(run(&mut runtime, &module)?, "9\n9\n");
+
+        Ok(())
+    }
 }
@@ -1,12 +1,23 @@
+use std::collections::HashMap;
 use std::mem::transmute;
+use std::sync::Arc;
 use monoteny_macro::{bin_expr, pop_ip, pop_sp, un_expr};
-use std::ptr::{read_unaligned, write_unaligned};
+use std::ptr::{null_mut, read_unaligned};
+use itertools::Itertools;
 use uuid::Uuid;
 use std::ops::Neg;
+use crate::error::codes::ErrorCode;
 use crate::error::{RuntimeError, RResult};
 use crate::interpreter::chunks::Chunk;
-use crate::interpreter::data::{string_to_ptr, Value};
+use crate::interpreter::coverage::Coverage;
+use crate::interpreter::data::{string_to_ptr, Value, STRUCT_HEADER_SIZE};
+use crate::interpreter::json;
+use crate::interpreter::json::Json;
 use crate::interpreter::opcode::{OpCode, Primitive};
+use crate::interpreter::runtime::Runtime;
+use crate::interpreter::stats;
+use crate::interpreter::stats::Stats;
+use crate::interpreter::strings::{real_to_string, StringInterner};
 
 pub struct VM<'a, 'b> {
     pub pipe_out: &'b mut dyn std::io::Write,
@@ -14,13 +25,55 @@ pub struct VM<'a, 'b> {
     pub stack: Vec<Value>,
     pub locals: Vec<Value>,
     pub transpile_functions: Vec<Uuid>,
+    /// Global constants, evaluated on their first load.
+    pub globals: HashMap<Uuid, Global<'a>>,
+    /// Compiled function bodies, by function id.
+    pub functions: HashMap<Uuid, &'a Chunk>,
+    /// The global constant this VM is evaluating, if any.
+    pub evaluating_global: Option<Uuid>,
+    pub strings: Arc<StringInterner>,
+    /// Collected by run, with the `stats` feature.
+    pub stats: Stats,
+    /// If set, run marks the offsets of every instruction it executes.
+    pub coverage: Option<Coverage>,
 }
 
-pub unsafe fn to_str_ptr<A: ToString>(a: A) -> *mut () {
+pub struct Global<'a> {
+    pub name: String,
+    pub chunk: &'a Chunk,
+    pub state: GlobalState,
+}
+
+pub enum GlobalState {
+    Uninitialized,
+    Initializing,
+    Initialized(Value),
+}
+
+pub fn to_str_ptr<A: ToString>(a: A) -> *mut () {
     let string = a.to_string();
     string_to_ptr(&string)
 }
 
+/// Negative indices are out of bounds; they don't count from the end.
+pub fn get_list_slot(list: &mut [Value], index: i64) -> RResult<&mut Value> {
+    let length = list.len();
+    usize::try_from(index).ok()
+        .and_then(|index| list.get_mut(index))
+        .ok_or_else(|| RuntimeError::error(format!("index {} out of bounds for length {}", index, length).as_str()).with_code(ErrorCode::IndexOutOfBounds).to_array())
+}
+
+/// Integer powers wrap around on overflow, but their exponents must fit into u32.
+/// Negative exponents would result in a fraction, so they are rejected too.
+pub fn int_exponent(exponent: impl Into<i128>) -> RResult<u32> {
+    let exponent = exponent.into();
+    if exponent < 0 {
+        return Err(RuntimeError::error("integer pow with negative exponent").with_code(ErrorCode::InvalidExponent).to_array())
+    }
+    u32::try_from(exponent)
+        .map_err(|_| RuntimeError::error(format!("integer pow with exponent {} above {}", exponent, u32::MAX).as_str()).with_code(ErrorCode::InvalidExponent).to_array())
+}
+
 impl<'a, 'b> VM<'a, 'b> {
     pub fn new(chunk: &'a Chunk, pipe_out: &'b mut dyn std::io::Write) -> VM<'a, 'b> {
         VM {
@@ -29,36 +82,159 @@ impl<'a, 'b> VM<'a, 'b> {
             stack: vec![Value::alloc(); 1024],
             locals: vec![Value::alloc(); usize::try_from(chunk.locals_count).unwrap()],
             transpile_functions: vec![],
+            globals: Default::default(),
+            functions: Default::default(),
+            evaluating_global: None,
+            strings: Arc::new(StringInterner::new()),
+            stats: Stats::default(),
+            coverage: None,
+        }
+    }
+
+    /// Makes the functions, global constants and strings of the runtime available to the VM.
+    pub fn load_runtime(&mut self, runtime: &'a Runtime) {
+        self.strings = Arc::clone(&runtime.strings);
+        self.functions = runtime.function_evaluators.iter().map(|(id, chunk)| (*id, chunk)).collect();
+        for (id, name) in runtime.global_constants.iter() {
+            let chunk = &runtime.function_evaluators[id];
+            self.globals.insert(*id, Global { name: name.clone(), chunk, state: GlobalState::Uninitialized });
+        }
+    }
+
+    pub fn load_global(&mut self, id: Uuid) -> RResult<Value> {
+        let Some(global) = self.globals.get_mut(&id) else {
+            return Err(RuntimeError::error(format!("Unknown global constant: {}", id).as_str()).to_array());
+        };
+
+        match global.state {
+            GlobalState::Initialized(value) => return Ok(value),
+            GlobalState::Initializing => {
+                let referencing = self.evaluating_global.as_ref()
+                    .and_then(|id| self.globals.get(id))
+                    .map(|global| global.name.as_str())
+                    .unwrap_or("<unknown>");
+                return Err(RuntimeError::error(format!("Cycle in initialization of global constant '{}', referenced from '{}'.", self.globals[&id].name, referencing).as_str()).to_array());
+            }
+            GlobalState::Uninitialized => {}
+        }
+
+        global.state = GlobalState::Initializing;
+        let chunk = global.chunk;
+
+        // Evaluate the constant in its own VM, sharing the globals with it.
+        let mut vm = VM::new(chunk, &mut *self.pipe_out);
+        vm.globals = std::mem::take(&mut self.globals);
+        vm.functions = std::mem::take(&mut self.functions);
+        vm.evaluating_global = Some(id);
+        vm.strings = Arc::clone(&self.strings);
+        vm.coverage = self.coverage.take();
+        let result = vm.run();
+        let value = vm.stack[0];
+        self.globals = std::mem::take(&mut vm.globals);
+        self.functions = std::mem::take(&mut vm.functions);
+        self.coverage = vm.coverage.take();
+        self.transpile_functions.extend(vm.transpile_functions);
+        self.stats.add_nested(&vm.stats);
+        result?;
+
+        self.globals.get_mut(&id).unwrap().state = GlobalState::Initialized(value);
+        Ok(value)
+    }
+
+    /// Runs the function in its own VM, like a global constant.
+    /// The arguments are popped from the stack, and the return value, if any, is pushed.
+    ///
+    /// # Safety
+    ///
+    /// `sp` must point just past the function's arguments, with room for its return value.
+    pub unsafe fn call(&mut self, id: Uuid, mut sp: *mut Value) -> RResult<*mut Value> {
+        let Some(chunk) = self.functions.get(&id).copied() else {
+            return Err(RuntimeError::error(format!("Unknown function: {}", id).as_str()).to_array());
+        };
+
+        let mut vm = VM::new(chunk, &mut *self.pipe_out);
+        // The last argument is on top.
+        for idx in (0..usize::try_from(chunk.parameters_count).unwrap()).rev() {
+            sp = sp.offset(-8);
+            vm.locals[idx] = *sp;
+        }
+        vm.globals = std::mem::take(&mut self.globals);
+        vm.functions = std::mem::take(&mut self.functions);
+        vm.evaluating_global = self.evaluating_global;
+        vm.strings = Arc::clone(&self.strings);
+        vm.coverage = self.coverage.take();
+        let result = vm.run();
+        self.globals = std::mem::take(&mut vm.globals);
+        self.functions = std::mem::take(&mut vm.functions);
+        self.coverage = vm.coverage.take();
+        self.transpile_functions.extend(vm.transpile_functions);
+        self.stats.add_nested(&vm.stats);
+        result?;
+
+        if chunk.returns_value {
+            *sp = vm.stack[0];
+            sp = sp.add(8);
         }
+        Ok(sp)
     }
 
     pub fn run(&mut self) -> RResult<()> {
+        let allocations_before = stats::allocations();
+        let result = self.run_instructions();
+        self.stats.add_allocations_since(allocations_before);
+        result
+    }
+
+    fn run_instructions(&mut self) -> RResult<()> {
         unsafe {
-            let mut ip: *const u8 = transmute(&self.chunk.code[0]);
+            let code_start: *const u8 = &self.chunk.code[0];
+            let mut ip: *const u8 = code_start;
             let mut sp: *mut Value = &mut self.stack[0] as *mut Value;
+            // Flags for each offset of the chunk, or null if coverage isn't recorded.
+            //  Nested VMs add their own chunks, which doesn't move the flags of ours.
+            let executed: *mut bool = match &mut self.coverage {
+                Some(coverage) => coverage.entry(self.chunk as *const Chunk)
+                    .or_insert_with(|| vec![false; self.chunk.code.len()])
+                    .as_mut_ptr(),
+                None => null_mut(),
+            };
+            #[cfg(feature = "stats")]
+            let stack_start: *const Value = sp;
 
             loop {
-                // println!("sp: {:?}; ip: {:?}", sp, ip);
-                // disassemble_one(ip);
-                // print!("\n");
+                if !executed.is_null() {
+                    *executed.offset(ip.offset_from(code_start)) = true;
+                }
 
                 let code = transmute::<u8, OpCode>(*ip);
                 ip = ip.add(1);
 
+                #[cfg(feature = "stats")]
+                {
+                    self.stats.instructions += 1;
+                    // Every stack slot spans 8 values.
+                    self.stats.peak_stack_depth = self.stats.peak_stack_depth.max(sp.offset_from(stack_start) as usize / 8);
+                }
+
                 match code {
                     OpCode::NOOP => {},
                     OpCode::PANIC => return Err(RuntimeError::error("panic").to_array()),
+                    OpCode::PANIC_WITH_MESSAGE => {
+                        let message = &*(pop_sp!().ptr as *const String);
+                        return Err(RuntimeError::error(message).to_array())
+                    },
                     OpCode::RETURN => return Ok(()),
+                    // Narrow loads zero the rest of the value, so they can load any unsigned value that fits.
                     OpCode::LOAD8 => {
-                        (*sp).u8 = pop_ip!(u8);
+                        (*sp).u64 = u64::from(pop_ip!(u8));
                         sp = sp.add(8);
                     },
                     OpCode::LOAD16 => {
-                        (*sp).u16 = pop_ip!(u16);
+                        (*sp).u64 = u64::from(pop_ip!(u16));
                         sp = sp.add(8);
                     },
                     OpCode::LOAD32 => {
-                        (*sp).u32 = pop_ip!(u32);
+                        (*sp).u64 = u64::from(pop_ip!(u32));
                         sp = sp.add(8);
                     },
                     OpCode::LOAD64 => {
@@ -84,11 +260,34 @@ impl<'a, 'b> VM<'a, 'b> {
                         sp = sp.offset(-8);
                         self.locals[usize::try_from(local_idx).unwrap()] = *sp;
                     }
+                    OpCode::LOAD_LOCAL_8 => {
+                        let local_idx: u8 = pop_ip!(u8);
+                        *sp = self.locals[usize::from(local_idx)];
+                        sp = sp.add(8);
+                    }
+                    OpCode::STORE_LOCAL_8 => {
+                        let local_idx: u8 = pop_ip!(u8);
+                        sp = sp.offset(-8);
+                        self.locals[usize::from(local_idx)] = *sp;
+                    }
                     OpCode::LOAD_CONSTANT => {
                         let constant_idx: u32 = pop_ip!(u32);
                         *sp = self.chunk.constants[usize::try_from(constant_idx).unwrap()];
                         sp = sp.add(8);
                     }
+                    OpCode::LOAD_GLOBAL => {
+                        let id = Uuid::from_u128(pop_ip!(u128));
+                        *sp = self.load_global(id)?;
+                        sp = sp.add(8);
+                    }
+                    OpCode::CALL => {
+                        let id = Uuid::from_u128(pop_ip!(u128));
+                        #[cfg(feature = "stats")]
+                        {
+                            self.stats.calls += 1;
+                        }
+                        sp = self.call(id, sp)?;
+                    }
                     OpCode::DUP64 => {
                         *sp = *sp.offset(-8);
                         sp = sp.offset(8);
@@ -196,6 +395,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs==rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs==rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs==rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs==rhs),
                         }
                     },
                     OpCode::NEQ => {
@@ -213,6 +413,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs!=rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs!=rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs!=rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs!=rhs),
                         }
                     },
                     OpCode::GR => {
@@ -229,6 +430,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs>rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs>rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs>rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs>rhs),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
                         }
                     },
@@ -246,6 +448,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs>=rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs>=rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs>=rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs>=rhs),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
                         }
                     },
@@ -263,6 +466,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs<rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs<rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs<rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs<rhs),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
                         }
                     },
@@ -280,20 +484,23 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => bin_expr!(i64, bool, lhs<=rhs),
                             Primitive::F32 => bin_expr!(f32, bool, lhs<=rhs),
                             Primitive::F64 => bin_expr!(f64, bool, lhs<=rhs),
+                            Primitive::CHAR => bin_expr!(u32, bool, lhs<=rhs),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
                         }
                     },
                     OpCode::TRANSPILE_ADD => {
+                        // Functions are passed as their id, like LOAD128 pushes it.
                         let lsb = pop_sp!().u64;
                         let msb = pop_sp!().u64;
-                        let transpiler = pop_sp!();
+                        // The Transpiler has no fields; the functions are collected by the VM instead.
+                        _ = pop_sp!();
 
-                        let uuid = Uuid::from_u64_pair(msb, lsb);
-                        self.transpile_functions.push(uuid);
+                        self.transpile_functions.push(Uuid::from_u64_pair(msb, lsb));
                     }
                     OpCode::PRINT => {
-                        // TODO Shouldn't need to copy it
-                        let string: String = read_unaligned(pop_sp!().ptr as *mut String);
+                        // Strings may be referenced more than once (e.g. by locals), so we only borrow them.
+                        // TODO They are never freed for now.
+                        let string = &*(pop_sp!().ptr as *const String);
                         writeln!(self.pipe_out, "{}", string)
                             .map_err(|e| RuntimeError::error(&e.to_string()).to_array())?;
                     }
@@ -337,12 +544,12 @@ impl<'a, 'b> VM<'a, 'b> {
                         match arg {
                             Primitive::U8 => bin_expr!(u8, u8, lhs.wrapping_pow(rhs.into())),
                             Primitive::U16 => bin_expr!(u16, u16, lhs.wrapping_pow(rhs.into())),
-                            Primitive::U32 => bin_expr!(u32, u32, lhs.wrapping_pow(rhs.try_into().unwrap())),
-                            Primitive::U64 => bin_expr!(u64, u64, lhs.wrapping_pow(rhs.try_into().unwrap())),
-                            Primitive::I8 => bin_expr!(i8, i8, lhs.wrapping_pow(rhs.try_into().unwrap())),
-                            Primitive::I16 => bin_expr!(i16, i16, lhs.wrapping_pow(rhs.try_into().unwrap())),
-                            Primitive::I32 => bin_expr!(i32, i32, lhs.wrapping_pow(rhs.try_into().unwrap())),
-                            Primitive::I64 => bin_expr!(i64, i64, lhs.wrapping_pow(rhs.try_into().unwrap())),
+                            Primitive::U32 => bin_expr!(u32, u32, lhs.wrapping_pow(rhs)),
+                            Primitive::U64 => bin_expr!(u64, u64, lhs.wrapping_pow(int_exponent(rhs)?)),
+                            Primitive::I8 => bin_expr!(i8, i8, lhs.wrapping_pow(int_exponent(rhs)?)),
+                            Primitive::I16 => bin_expr!(i16, i16, lhs.wrapping_pow(int_exponent(rhs)?)),
+                            Primitive::I32 => bin_expr!(i32, i32, lhs.wrapping_pow(int_exponent(rhs)?)),
+                            Primitive::I64 => bin_expr!(i64, i64, lhs.wrapping_pow(int_exponent(rhs)?)),
                             Primitive::F32 => bin_expr!(f32, f32, lhs.powf(rhs)),
                             Primitive::F64 => bin_expr!(f64, f64, lhs.powf(rhs)),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
@@ -361,8 +568,7 @@ impl<'a, 'b> VM<'a, 'b> {
                         let arg: Primitive = transmute(pop_ip!(u8));
 
                         let sp_last = sp.offset(-8);
-                        // TODO Shouldn't need to copy
-                        let string = read_unaligned((*sp_last).ptr as *mut String);
+                        let string = &*((*sp_last).ptr as *const String);
 
                         match arg {
                             Primitive::U8 => (*sp_last).u8 = string.parse().unwrap(),
@@ -375,6 +581,7 @@ impl<'a, 'b> VM<'a, 'b> {
                             Primitive::I64 => (*sp_last).i64 = string.parse().unwrap(),
                             Primitive::F32 => (*sp_last).f32 = string.parse().unwrap(),
                             Primitive::F64 => (*sp_last).f64 = string.parse().unwrap(),
+                            Primitive::CHAR => (*sp_last).u32 = u32::from(string.chars().exactly_one().ok().unwrap()),
                             _ => return Err(RuntimeError::error("Unexpected primitive.").to_array()),
                         }
                     }
@@ -382,28 +589,229 @@ impl<'a, 'b> VM<'a, 'b> {
                         let arg: Primitive = transmute(pop_ip!(u8));
 
                         match arg {
-                            Primitive::U8 => un_expr!(u8, ptr, to_str_ptr(val)),
-                            Primitive::U16 => un_expr!(u16, ptr, to_str_ptr(val)),
-                            Primitive::U32 => un_expr!(u32, ptr, to_str_ptr(val)),
-                            Primitive::U64 => un_expr!(u64, ptr, to_str_ptr(val)),
-                            Primitive::I8 => un_expr!(i8, ptr, to_str_ptr(val)),
-                            Primitive::I16 => un_expr!(i16, ptr, to_str_ptr(val)),
-                            Primitive::I32 => un_expr!(i32, ptr, to_str_ptr(val)),
-                            Primitive::I64 => un_expr!(i64, ptr, to_str_ptr(val)),
-                            Primitive::F32 => un_expr!(f32, ptr, to_str_ptr(val)),
-                            Primitive::F64 => un_expr!(f64, ptr, to_str_ptr(val)),
+                            Primitive::U8 => un_expr!(u8, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::U16 => un_expr!(u16, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::U32 => un_expr!(u32, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::U64 => un_expr!(u64, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::I8 => un_expr!(i8, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::I16 => un_expr!(i16, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::I32 => un_expr!(i32, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::I64 => un_expr!(i64, ptr, self.strings.integer_to_string(i128::from(val))),
+                            Primitive::F32 => un_expr!(f32, ptr, to_str_ptr(real_to_string(val))),
+                            Primitive::F64 => un_expr!(f64, ptr, to_str_ptr(real_to_string(val))),
                             Primitive::BOOL => un_expr!(bool, ptr, to_str_ptr(val)),
+                            Primitive::CHAR => un_expr!(u32, ptr, to_str_ptr(char::from_u32(val).unwrap())),
                         }
                     }
                     OpCode::ADD_STRING => {
-                        // TODO Shouldn't need to copy
-                        let rhs = read_unaligned(pop_sp!().ptr as *mut String);
+                        let rhs_ptr = pop_sp!().ptr;
+                        let rhs = &*(rhs_ptr as *const String);
+
+                        let sp_last = sp.offset(-8);
+                        let lhs = &*((*sp_last).ptr as *const String);
+
+                        // Strings are immutable, so if either side is empty, we can re-use the other.
+                        if lhs.is_empty() {
+                            (*sp_last).ptr = rhs_ptr;
+                        }
+                        else if !rhs.is_empty() {
+                            (*sp_last).ptr = to_str_ptr(lhs.to_string() + rhs);
+                        }
+                    }
+                    OpCode::EQ_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) == *rhs;
+                    }
+                    OpCode::NEQ_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) != *rhs;
+                    }
+                    OpCode::GR_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) > *rhs;
+                    }
+                    OpCode::GR_EQ_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) >= *rhs;
+                    }
+                    OpCode::LE_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) < *rhs;
+                    }
+                    OpCode::LE_EQ_STRING => {
+                        let rhs = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = *((*sp_last).ptr as *const String) <= *rhs;
+                    }
+                    OpCode::LIST_NEW => {
+                        stats::record_allocation(size_of::<Vec<Value>>());
+                        (*sp).ptr = Box::into_raw(Box::new(Vec::<Value>::new())) as *mut ();
+                        sp = sp.add(8);
+                    }
+                    OpCode::LIST_PUSH => {
+                        let value = pop_sp!();
+                        let list = pop_sp!().ptr as *mut Vec<Value>;
+                        (*list).push(value);
+                    }
+                    OpCode::LIST_POP => {
+                        let sp_last = sp.offset(-8);
+                        let list = (*sp_last).ptr as *mut Vec<Value>;
 
-                        // TODO Shouldn't need to copy
+                        *sp_last = (*list).pop()
+                            .ok_or_else(|| RuntimeError::error("Cannot pop from an empty list.").with_code(ErrorCode::PopFromEmptyList).to_array())?;
+                    }
+                    OpCode::LIST_LEN => {
                         let sp_last = sp.offset(-8);
-                        let lhs = read_unaligned((*sp_last).ptr as *mut String);
+                        let list = (*sp_last).ptr as *mut Vec<Value>;
 
-                        (*sp_last).ptr = to_str_ptr(lhs.to_string() + &rhs);
+                        (*sp_last).i64 = (*list).len() as i64;
+                    }
+                    OpCode::LIST_GET => {
+                        let index = pop_sp!().i64;
+                        let sp_last = sp.offset(-8);
+                        let list = (*sp_last).ptr as *mut Vec<Value>;
+
+                        *sp_last = *get_list_slot(&mut *list, index)?;
+                    }
+                    OpCode::LIST_SET => {
+                        let value = pop_sp!();
+                        let index = pop_sp!().i64;
+                        let list = pop_sp!().ptr as *mut Vec<Value>;
+
+                        *get_list_slot(&mut *list, index)? = value;
+                    }
+                    OpCode::CHAR_FROM_CODE => {
+                        let code = (*sp.offset(-8)).u32;
+                        if char::from_u32(code).is_none() {
+                            return Err(RuntimeError::error(format!("Not a valid character code: {}.", code).as_str()).to_array());
+                        }
+                    }
+                    OpCode::STRING_CHARS => {
+                        let sp_last = sp.offset(-8);
+                        let string = &*((*sp_last).ptr as *const String);
+
+                        let chars = string.chars().map(|char| Value { u32: u32::from(char) }).collect::<Vec<_>>();
+                        stats::record_allocation(size_of::<Vec<Value>>() + chars.capacity() * size_of::<Value>());
+                        (*sp_last).ptr = Box::into_raw(Box::new(chars)) as *mut ();
+                    }
+                    OpCode::JSON_PARSE => {
+                        let sp_last = sp.offset(-8);
+                        let json = json::parse(&*((*sp_last).ptr as *const String))?;
+                        stats::record_allocation(size_of::<Json>());
+                        (*sp_last).ptr = Box::into_raw(Box::new(json)) as *mut ();
+                    }
+                    OpCode::JSON_GET => {
+                        let key = &*(pop_sp!().ptr as *const String);
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).ptr = (*((*sp_last).ptr as *const Json)).get(key) as *const Json as *mut ();
+                    }
+                    OpCode::JSON_AT => {
+                        let index = pop_sp!().i64;
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).ptr = (*((*sp_last).ptr as *const Json)).at(index) as *const Json as *mut ();
+                    }
+                    OpCode::JSON_LEN => {
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).i64 = (*((*sp_last).ptr as *const Json)).count() as i64;
+                    }
+                    OpCode::JSON_KIND => {
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).ptr = string_to_ptr((*((*sp_last).ptr as *const Json)).kind());
+                    }
+                    OpCode::JSON_AS_STRING => {
+                        let sp_last = sp.offset(-8);
+                        // Strings are immutable, so the json's own string can be shared.
+                        (*sp_last).ptr = match &*((*sp_last).ptr as *const Json) {
+                            Json::String(string) => string as *const String as *mut (),
+                            json => return Err(json.unexpected_kind("string")),
+                        };
+                    }
+                    OpCode::JSON_AS_FLOAT => {
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).f64 = match &*((*sp_last).ptr as *const Json) {
+                            Json::Number(number) => *number,
+                            json => return Err(json.unexpected_kind("number")),
+                        };
+                    }
+                    OpCode::JSON_AS_BOOL => {
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).bool = match &*((*sp_last).ptr as *const Json) {
+                            Json::Bool(bool) => *bool,
+                            json => return Err(json.unexpected_kind("bool")),
+                        };
+                    }
+                    OpCode::BUFFER_NEW => {
+                        (*sp).ptr = string_to_ptr("");
+                        sp = sp.add(8);
+                    }
+                    OpCode::BUFFER_PUSH_LINE => {
+                        let string = &*(pop_sp!().ptr as *const String);
+                        let buffer = pop_sp!().ptr as *mut String;
+                        (*buffer).push_str(string);
+                        (*buffer).push('\n');
+                    }
+                    OpCode::BUFFER_COPY => {
+                        // Strings are shared, so the buffer must not be handed out while it can still change.
+                        let sp_last = sp.offset(-8);
+                        (*sp_last).ptr = string_to_ptr(&*((*sp_last).ptr as *const String));
+                    }
+                    OpCode::ALLOC => {
+                        let count = usize::try_from(pop_ip!(u32)).unwrap();
+                        stats::record_allocation((STRUCT_HEADER_SIZE + count) * size_of::<Value>());
+                        let lsb = pop_sp!();
+                        let msb = pop_sp!();
+                        // The fields were pushed in order, so the last one is on top.
+                        sp = sp.offset(-8 * count as isize);
+                        let object = [msb, lsb].into_iter()
+                            .chain((0..count).map(|idx| *sp.add(8 * idx)))
+                            .collect::<Box<[Value]>>();
+                        (*sp).ptr = Box::into_raw(object) as *mut ();
+                        sp = sp.add(8);
+                    }
+                    OpCode::COPY_STRUCT => {
+                        let count = usize::try_from(pop_ip!(u32)).unwrap();
+                        stats::record_allocation((STRUCT_HEADER_SIZE + count) * size_of::<Value>());
+                        let sp_last = sp.offset(-8);
+                        let object = std::slice::from_raw_parts((*sp_last).ptr as *const Value, STRUCT_HEADER_SIZE + count);
+                        (*sp_last).ptr = Box::into_raw(object.to_vec().into_boxed_slice()) as *mut ();
+                    }
+                    OpCode::GET_MEMBER => {
+                        let field_idx = usize::try_from(pop_ip!(u32)).unwrap();
+                        let sp_last = sp.offset(-8);
+                        *sp_last = *((*sp_last).ptr as *mut Value).add(STRUCT_HEADER_SIZE + field_idx);
+                    }
+                    OpCode::SET_MEMBER => {
+                        let field_idx = usize::try_from(pop_ip!(u32)).unwrap();
+                        let value = pop_sp!();
+                        let object = pop_sp!().ptr as *mut Value;
+                        *object.add(STRUCT_HEADER_SIZE + field_idx) = value;
+                    }
+                    OpCode::IS_TYPE => {
+                        let lsb = pop_sp!().u64;
+                        let msb = pop_sp!().u64;
+                        let object = pop_sp!().ptr as *mut Value;
+                        (*sp).u64 = u64::from((*object).u64 == msb && (*object.add(1)).u64 == lsb);
+                        sp = sp.add(8);
+                    }
+                    OpCode::CALL_VTABLE => {
+                        let function_idx = usize::from(pop_ip!(u8));
+                        let arguments_count = isize::from(pop_ip!(u8));
+                        let receiver = sp.offset(-8 * arguments_count);
+                        let object = (*receiver).ptr as *mut Value;
+                        // The vtable follows the boxed value, with two values per function id.
+                        let entry = object.add(STRUCT_HEADER_SIZE + 1 + 2 * function_idx);
+                        let id = Uuid::from_u64_pair((*entry).u64, (*entry.add(1)).u64);
+                        *receiver = *object.add(STRUCT_HEADER_SIZE);
+                        #[cfg(feature = "stats")]
+                        {
+                            self.stats.calls += 1;
+                        }
+                        sp = self.call(id, sp)?;
                     }
                 }
             }
@@ -0,0 +1,32 @@
+// Nested generic types are spelled out throughout, rather than named by aliases,
+//  and syntax tree nodes are boxed wherever they are stored.
+// Test files (tests.rs) wrap their tests in a #[cfg(test)] mod tests.
+#![allow(clippy::type_complexity, clippy::vec_box, clippy::module_inception)]
+
+extern crate core;
+#[macro_use]
+extern crate lalrpop_util;
+
+// The parser is generated, so its code can't follow the lints.
+lalrpop_mod!(#[allow(clippy::all)] pub monoteny_grammar);
+pub mod interpreter;
+pub mod resolver;
+pub mod parser;
+pub mod program;
+pub mod transpiler;
+pub mod util;
+pub mod error;
+pub mod repository;
+pub mod refactor;
+pub mod source;
+#[cfg(feature = "native")]
+pub mod cli;
+pub mod static_analysis;
+pub mod ast;
+pub mod analysis;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+pub use interpreter::run::compile_and_run;
+#[cfg(feature = "native")]
+pub use analysis::{analyze, analyze_with_lints};
@@ -1,4 +1,3 @@
-use itertools::Itertools;
 use lalrpop_util::ErrorRecovery;
 
 use crate::{ast, monoteny_grammar};
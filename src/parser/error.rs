@@ -22,13 +22,13 @@ pub fn map_parse_error(e: &ParseError<usize, Token, Error>) -> RuntimeError {
         ParseError::UnrecognizedEof { location, expected } => {
             add_expected_note(RuntimeError::error("File ended unexpectedly.").in_range(*location..*location), expected)
         }
-        ParseError::UnrecognizedToken { token: (start, token, end), expected } => {
+        ParseError::UnrecognizedToken { token: (start, _token, end), expected } => {
             add_expected_note(RuntimeError::error("Unexpected token.").in_range(*start..*end), expected)
         }
-        ParseError::ExtraToken { token: (start, token, end) } => {
+        ParseError::ExtraToken { token: (start, _token, end) } => {
             RuntimeError::error("Extraneous token.").in_range(*start..*end)
         }
-        ParseError::User { error } => {
+        ParseError::User { error: _ } => {
             panic!()
         }
     }
@@ -45,8 +45,8 @@ fn unquote(value: &str) -> &str {
     chars.as_str()
 }
 
-fn add_expected_note(error: RuntimeError, expected: &Vec<String>) -> RuntimeError {
-    match &expected[..] {
+fn add_expected_note(error: RuntimeError, expected: &[String]) -> RuntimeError {
+    match expected {
         [] => error,
         [one] => error.with_note(RuntimeError::note(format!("Expected: {}", unquote(one)).as_str())),
         expected => error.with_note(RuntimeError::note(format!("Expected one of: {}", expected.iter().map(|s| unquote(s)).join(" ")).as_str())),
@@ -3,11 +3,11 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::Range;
 
-use itertools::Itertools;
 
 pub use token::{Token, Value};
 
 use crate::ast;
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 use crate::parser::grammar::{Grammar, OperatorAssociativity};
 use crate::util::position::Positioned;
@@ -25,7 +25,12 @@ pub fn parse_to_tokens<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(synt
         match &ast_token.value {
             ast::Term::Error(err) => Err(err.clone().to_array())?,
             ast::Term::Identifier(identifier) => {
-                if grammar.keywords.contains(identifier) {
+                if let Some((trait_name, struct_, end)) = dyn_box_call(identifier, &syntax[i..]) {
+                    i += 4;
+                    let position = ast_token.position.start..end;
+                    tokens.push(Token::Value(Box::new(Positioned { position, value: Value::DynBox(trait_name, struct_) })));
+                }
+                else if grammar.keywords.contains(identifier) {
                     tokens.push(Token::Keyword(ast_token.with_value(identifier)));
                 }
                 else {
@@ -48,7 +53,28 @@ pub fn parse_to_tokens<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(synt
                 };
 
                 i += 1;
-                tokens.push(Token::Value(Box::new(next_token.with_value(Value::MemberAccess(target, member)))));
+                let position = target.position.start..next_token.position.end;
+                tokens.push(Token::Value(Box::new(Positioned { position, value: Value::MemberAccess(target, member) })));
+            }
+            ast::Term::Is | ast::Term::As => {
+                // Like member access, this binds tighter than any operator.
+                let Some(Token::Value(target)) = tokens.pop() else {
+                    return Err(RuntimeError::error(format!("'{}' requires a preceding value.", ast_token.value).as_str()).in_range(ast_token.position.clone()).to_array())
+                };
+
+                let Some(Positioned { value: ast::Term::Identifier(type_name), position }) = syntax.get(i).map(|term| term.as_ref()) else {
+                    return Err(RuntimeError::error(format!("'{}' requires a following type name.", ast_token.value).as_str()).in_range(ast_token.position.clone()).to_array())
+                };
+
+                i += 1;
+                let position = target.position.start..position.end;
+                tokens.push(Token::Value(Box::new(Positioned {
+                    position,
+                    value: match &ast_token.value {
+                        ast::Term::Is => Value::TypeCheck(target, type_name),
+                        _ => Value::TypeCast(target, type_name),
+                    },
+                })));
             }
             ast::Term::IntLiteral(string) => {
                 tokens.push(Token::Value(Box::new(ast_token.with_value(Value::IntLiteral(string)))));
@@ -56,6 +82,9 @@ pub fn parse_to_tokens<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(synt
             ast::Term::RealLiteral(string) => {
                 tokens.push(Token::Value(Box::new(ast_token.with_value(Value::RealLiteral(string)))));
             }
+            ast::Term::CharLiteral(string) => {
+                tokens.push(Token::Value(Box::new(ast_token.with_value(Value::CharLiteral(string)))));
+            }
             ast::Term::StringLiteral(parts) => {
                 tokens.push(Token::Value(Box::new(ast_token.with_value(Value::StringLiteral(parts)))));
             }
@@ -94,13 +123,28 @@ pub fn parse_to_tokens<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(synt
             ast::Term::IfThenElse(if_then_else) => {
                 tokens.push(Token::Value(Box::new(ast_token.with_value(Value::IfThenElse(if_then_else)))));
             }
+            ast::Term::Match(match_) => {
+                tokens.push(Token::Value(Box::new(ast_token.with_value(Value::Match(match_)))));
+            }
         }
     }
 
     Ok(tokens)
 }
 
-pub fn parse_unary<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(mut tokens: Vec<Token<'a, Function>>, functions: Option<&'a HashMap<String, Function>>) -> RResult<(Vec<Box<Positioned<Value<'a, Function>>>>, Vec<Positioned<&'a str>>)> {
+/// If the identifier starts `box<Trait>(value)`, the trait's name, the call's struct and where it ends.
+fn dyn_box_call<'a>(identifier: &str, following: &'a [Box<Positioned<ast::Term>>]) -> Option<(&'a String, &'a ast::Struct, usize)> {
+    let [open, trait_name, close, call, ..] = following else {
+        return None
+    };
+    match (identifier, &open.value, &trait_name.value, &close.value, &call.value) {
+        ("box", ast::Term::Identifier(open), ast::Term::Identifier(trait_name), ast::Term::Identifier(close), ast::Term::Struct(struct_))
+            if open == "<" && close == ">" => Some((trait_name, struct_, call.position.end)),
+        _ => None,
+    }
+}
+
+pub fn parse_unary<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(mut tokens: Vec<Token<'a, Function>>, functions: Option<&'a HashMap<String, Vec<Function>>>) -> RResult<(Vec<Box<Positioned<Value<'a, Function>>>>, Vec<Positioned<&'a str>>)> {
     let mut values: Vec<Box<Positioned<Value<Function>>>> = vec![];
     let mut keywords: Vec<Positioned<&'a str>> = vec![];
 
@@ -136,12 +180,24 @@ pub fn parse_unary<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(mut toke
             }
 
             // Unary operator, because left of operator is an operator
+            let Some(overloads) = functions.get(keyword.value.as_str()) else {
+                return Err(
+                    RuntimeError::error(format!("'{}' is not a unary operator; expected a value before it.", keyword.value).as_str())
+                        .in_range(keyword.position.clone())
+                        .to_array()
+                )
+            };
             let argument = values.remove(0);
-            values.insert(0, Box::new(keyword.with_value(Value::Operation(functions[keyword.value.as_str()].clone(), vec![argument]))));
+            if keyword.value == "-" && matches!(argument.value, Value::IntLiteral(_) | Value::RealLiteral(_)) {
+                let position = keyword.position.start..argument.position.end;
+                values.insert(0, Box::new(Positioned { position, value: Value::NegativeLiteral(argument) }));
+                continue
+            }
+            values.insert(0, Box::new(keyword.with_value(Value::Operation(overloads, vec![argument]))));
         }
     }
 
-    return Ok((values, keywords))
+    Ok((values, keywords))
 }
 
 pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Box<Positioned<ast::Term>>], grammar: &'a Grammar<Function>) -> RResult<Box<Positioned<Value<'a, Function>>>> {
@@ -152,10 +208,10 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
     //  struct, or it will call the previous object as function (if any).
     // Some terms also do something to the next term. Mostly, a . will interpret the next term as a
     //  member reference rather than a global.
-    let mut tokens = parse_to_tokens(syntax, grammar)?;
+    let tokens = parse_to_tokens(syntax, grammar)?;
 
-    let left_unary_operators = grammar.groups_and_keywords.iter().next().map(|(group, ops)| {
-        if let Some((group, left_unary_operators)) = &grammar.groups_and_keywords.iter().next() {
+    let left_unary_operators = grammar.groups_and_keywords.iter().next().map(|(_group, ops)| {
+        if let Some((group, _left_unary_operators)) = &grammar.groups_and_keywords.iter().next() {
             if group.associativity != OperatorAssociativity::LeftUnary {
                 todo!("Left Unary operators must be first for now.");
             }
@@ -172,17 +228,18 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
     }
 
     // Resolve binary operators. At this point, we have only expressions interspersed with operators.
-    let join_binary_at = |arguments: &mut Vec<Box<Positioned<Value<Function>>>>, function: &Function, range: &Range<usize>, i: usize| -> RResult<()> {
+    let join_binary_at = |arguments: &mut Vec<Box<Positioned<Value<'a, Function>>>>, overloads: &'a [Function], range: &Range<usize>, i: usize| -> RResult<()> {
         let lhs = arguments.remove(i);
         let rhs = arguments.remove(i);
 
-        Ok(arguments.insert(
+        arguments.insert(
             i,
             Box::new(Positioned {
                 position: range.clone(),
-                value: Value::Operation(function.clone(), vec![lhs, rhs]),
+                value: Value::Operation(overloads, vec![lhs, rhs]),
             })
-        ))
+        );
+        Ok(())
     };
 
     for (group, group_operators) in grammar.groups_and_keywords.iter() {
@@ -191,9 +248,9 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
                 // Iterate left to right
                 let mut i = 0;
                 while i < keywords.len() {
-                    if let Some(function_head) = group_operators.get(keywords[i].value) {
+                    if let Some(overloads) = group_operators.get(keywords[i].value) {
                         let keyword = keywords.remove(i);
-                        join_binary_at(&mut values, function_head, &keyword.position, i)?;
+                        join_binary_at(&mut values, overloads, &keyword.position, i)?;
                     }
                     else {
                         i += 1;  // Skip
@@ -205,9 +262,9 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
                 let mut i = keywords.len();
                 while i > 0 {
                     i -= 1;
-                    if let Some(alias) = group_operators.get(keywords[i].value) {
-                        keywords.remove(i);
-                        join_binary_at(&mut values, alias, &keywords[i].position, i)?;
+                    if let Some(overloads) = group_operators.get(keywords[i].value) {
+                        let keyword = keywords.remove(i);
+                        join_binary_at(&mut values, overloads, &keyword.position, i)?;
                     }
                 }
             }
@@ -215,13 +272,13 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
                 // Iteration direction doesn't matter here.
                 let mut i = 0;
                 while i < keywords.len() {
-                    if let Some(alias) = group_operators.get(keywords[i].value) {
+                    if let Some(overloads) = group_operators.get(keywords[i].value) {
                         if i + 1 < group_operators.len() && group_operators.contains_key(keywords[i + 1].value) {
                             panic!("Cannot parse two neighboring {} operators because no associativity is defined.", keywords[i]);
                         }
 
-                        keywords.remove(i);
-                        join_binary_at(&mut values, alias, &keywords[i].position, i)?;
+                        let keyword = keywords.remove(i);
+                        join_binary_at(&mut values, overloads, &keyword.position, i)?;
                     }
 
                     i += 1;
@@ -261,7 +318,7 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
                     }
 
                     // Let's wrap this up.
-                    values.insert(i, todo!("Resolve group_operators to functions"));
+                    todo!("Resolve group_operators to functions");
                 }
             }
             // Unary operators are already resolved at this stage.
@@ -269,17 +326,23 @@ pub fn parse<'a, Function: Clone + PartialEq + Eq + Hash + Debug>(syntax: &'a[Bo
             OperatorAssociativity::RightUnary => todo!(),
         }
 
-        if keywords.len() == 0 {
+        if keywords.is_empty() {
             // We can return early
             assert_eq!(values.len(), 1);
             return Ok(values.pop().unwrap())
         }
     }
 
-    if keywords.len() > 0 {
-        panic!("Unrecognized binary operator pattern(s); did you forget an import? Offending Operators: {:?}", &keywords);
+    // Imports make sure every keyword's group is in the order, but the grammar may have been built otherwise.
+    if let Some(keyword) = keywords.first() {
+        return Err(
+            RuntimeError::error(format!("'{}' is not an operator of any precedence group in the precedence order.", keyword.value).as_str())
+                .with_code(ErrorCode::MissingPrecedenceGroup)
+                .in_range(keyword.position.clone())
+                .to_array()
+        )
     }
 
     assert_eq!(values.len(), 1);
-    return Ok(values.pop().unwrap())
+    Ok(values.pop().unwrap())
 }
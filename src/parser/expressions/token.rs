@@ -2,19 +2,28 @@ use crate::ast;
 use crate::util::position::Positioned;
 
 pub enum Value<'a, Function> {
-    Operation(Function, Vec<Box<Positioned<Self>>>),
+    /// A call to any of the overloads bound to the operator's keyword.
+    Operation(&'a [Function], Vec<Box<Positioned<Self>>>),
     Identifier(&'a String),
     MacroIdentifier(&'a String),
     RealLiteral(&'a String),
     IntLiteral(&'a String),
+    /// A number literal with a leading minus, so that values like -128 'Int8 are in range.
+    NegativeLiteral(Box<Positioned<Self>>),
+    CharLiteral(&'a String),
     StringLiteral(&'a Vec<Box<Positioned<ast::StringPart>>>),
     StructLiteral(&'a ast::Struct),
     ArrayLiteral(&'a ast::Array),
     Block(&'a ast::Block),
     MemberAccess(Box<Positioned<Self>>, &'a String),
+    TypeCheck(Box<Positioned<Self>>, &'a String),
+    TypeCast(Box<Positioned<Self>>, &'a String),
+    /// `box<Trait>(value)`, boxing the value as Dyn<Trait>.
+    DynBox(&'a String, &'a ast::Struct),
     FunctionCall(Box<Positioned<Self>>, &'a ast::Struct),
     Subscript(Box<Positioned<Self>>, &'a ast::Array),
     IfThenElse(&'a ast::IfThenElse),
+    Match(&'a ast::Match),
 }
 
 pub enum Token<'a, Function> {
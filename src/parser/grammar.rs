@@ -1,13 +1,14 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use linked_hash_map::LinkedHashMap;
 use strum::{Display, EnumIter};
 use uuid::Uuid;
 
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Display, EnumIter)]
@@ -30,7 +31,7 @@ pub struct PrecedenceGroup {
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Pattern<Function: Clone + PartialEq + Eq + Hash + Debug> {
     pub id: Uuid,
-    pub precedence_group: Rc<PrecedenceGroup>,
+    pub precedence_group: Arc<PrecedenceGroup>,
 
     pub parts: Vec<Box<PatternPart>>,
     pub function: Function,
@@ -44,9 +45,17 @@ pub enum PatternPart {
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct Grammar<Function: Clone + PartialEq + Eq + Hash + Debug> {
-    pub patterns: HashSet<Rc<Pattern<Function>>>,
+    pub patterns: HashSet<Arc<Pattern<Function>>>,
     pub keywords: HashSet<String>,
-    pub groups_and_keywords: LinkedHashMap<Rc<PrecedenceGroup>, HashMap<String, Function>>,
+    /// For each keyword, the functions of all patterns binding it in the group, in the order they were added.
+    /// Operations call them like an overloaded function, so e.g. + can be declared for more than one type.
+    pub groups_and_keywords: LinkedHashMap<Arc<PrecedenceGroup>, HashMap<String, Vec<Function>>>,
+}
+
+impl<Function: Clone + PartialEq + Eq + Hash + Debug> Default for Grammar<Function> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
@@ -58,7 +67,7 @@ impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
         }
     }
 
-    pub fn set_precedence_order(&mut self, precedence: Vec<Rc<PrecedenceGroup>>) {
+    pub fn set_precedence_order(&mut self, precedence: Vec<Arc<PrecedenceGroup>>) {
         self.groups_and_keywords = precedence.into_iter()
             .map(|p| (p, HashMap::new()))
             .collect();
@@ -66,9 +75,42 @@ impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
         self.keywords = HashSet::new();
     }
 
-    pub fn add_pattern(&mut self, pattern: Rc<Pattern<Function>>) -> RResult<Vec<String>> {
+    /// Finds a registered pattern of the same form that binds the same keywords in another precedence group.
+    /// Patterns in the same group don't conflict; their functions are overloads of each other.
+    pub fn find_conflicting_pattern(&self, pattern: &Pattern<Function>) -> Option<&Arc<Pattern<Function>>> {
+        let keywords = pattern.keywords().collect_vec();
+        self.patterns.iter().find(|other| {
+            other.precedence_group != pattern.precedence_group
+                && other.parts.len() == pattern.parts.len()
+                && other.keywords().collect_vec() == keywords
+        })
+    }
+
+    /// Keywords bound in more than one precedence group, with their groups in evaluation order.
+    /// Which function such a keyword calls depends on its position, e.g. - as negation or subtraction.
+    pub fn keywords_in_multiple_groups(&self) -> Vec<(&String, Vec<&Arc<PrecedenceGroup>>)> {
+        self.keywords.iter()
+            .sorted()
+            .map(|keyword| (keyword, self.groups_and_keywords.iter()
+                .filter(|(_, keywords)| keywords.contains_key(keyword))
+                .map(|(group, _)| group)
+                .collect_vec()
+            ))
+            .filter(|(_, groups)| groups.len() > 1)
+            .collect()
+    }
+
+    pub fn add_pattern(&mut self, pattern: Arc<Pattern<Function>>) -> RResult<Vec<String>> {
+        if let Some(conflict) = self.find_conflicting_pattern(&pattern) {
+            return Err(RuntimeError::error(format!("Pattern '{}' conflicts with an existing pattern in {}.", conflict.keywords().join(" "), conflict.precedence_group.name).as_str()).to_array());
+        }
+
         let Some(keyword_map) = self.groups_and_keywords.get_mut(&pattern.precedence_group) else {
-            panic!("Cannot find precedence group {:?} in: {:?}", pattern.precedence_group, self.groups_and_keywords);
+            return Err(
+                RuntimeError::error(format!("Pattern '{}' belongs to precedence group {}, which is not in the precedence order.", pattern.keywords().join(" "), pattern.precedence_group.name).as_str())
+                    .with_code(ErrorCode::MissingPrecedenceGroup)
+                    .to_array()
+            );
         };
 
         let keywords = match &pattern.parts.iter().map(|x| x.as_ref()).collect_vec()[..] {
@@ -80,15 +122,15 @@ impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
                 if pattern.precedence_group.associativity != OperatorAssociativity::LeftUnary {
                     return Err(RuntimeError::error("Unary pattern must use LeftUnary precedence.").to_array())
                 }
-                keyword_map.insert(keyword.clone(), pattern.function.clone());
+                add_overload(keyword_map.entry(keyword.clone()).or_default(), &pattern.function);
                 self.keywords.insert(keyword.clone());
                 vec![keyword.clone()]
             },
             [
                 PatternPart::Parameter { .. },
-                PatternPart::Keyword(keyword),
+                PatternPart::Keyword(_keyword),
             ] => {
-                todo!("Right unary operators aren't supported yet.")
+                return Err(RuntimeError::error("Right unary operators aren't supported yet.").to_array())
             },
             [
                 PatternPart::Parameter { .. },
@@ -99,7 +141,7 @@ impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
                     return Err(RuntimeError::error("Binary pattern must not use LeftUnary precedence.").to_array())
                 }
 
-                keyword_map.insert(keyword.clone(), pattern.function.clone());
+                add_overload(keyword_map.entry(keyword.clone()).or_default(), &pattern.function);
                 self.keywords.insert(keyword.clone());
                 vec![keyword.clone()]
             }
@@ -111,6 +153,22 @@ impl<Function: Clone + PartialEq + Eq + Hash + Debug> Grammar<Function> where  {
     }
 }
 
+/// Importing the same pattern twice, e.g. through two modules including it, doesn't add another overload.
+fn add_overload<Function: Clone + PartialEq>(overloads: &mut Vec<Function>, function: &Function) {
+    if !overloads.contains(function) {
+        overloads.push(function.clone());
+    }
+}
+
+impl<Function: Clone + PartialEq + Eq + Hash + Debug> Pattern<Function> {
+    pub fn keywords(&self) -> impl Iterator<Item=&String> {
+        self.parts.iter().filter_map(|part| match part.as_ref() {
+            PatternPart::Keyword(keyword) => Some(keyword),
+            PatternPart::Parameter(_) => None,
+        })
+    }
+}
+
 impl PrecedenceGroup {
     pub fn new(name: &str, associativity: OperatorAssociativity) -> PrecedenceGroup {
         PrecedenceGroup {
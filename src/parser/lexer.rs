@@ -1,4 +1,3 @@
-use std::fmt::{Display, Formatter};
 use std::iter::Peekable;
 use std::str::CharIndices;
 
@@ -26,6 +25,27 @@ pub struct Lexer<'i> {
     /// e.g. when we find " in a string, we need to emit the current string part,
     /// as well as the " token itself.
     next_planned: Option<<Self as Iterator>::Item>,
+
+    /// Whether the last token was 'def'. Reserved keywords following it are lexed as identifiers,
+    ///  so that declaring them as functions can be reported by the resolver.
+    after_def: bool,
+
+    /// Within a type following ' or ->, the number of its open angle brackets.
+    /// Commas between them are lexed as operators, so that types like `Pair<Int32, String>` stay one expression.
+    type_argument_depth: Option<usize>,
+}
+
+/// Words lexed as symbols, which cannot be used as identifiers.
+pub fn is_reserved_keyword(word: &str) -> bool {
+    match word.len() {
+        7 => matches!(word, "declare"),
+        6 => matches!(word, "return"),
+        5 => matches!(word, "trait" | "where" | "match"),
+        4 => matches!(word, "else" | "enum"),
+        3 => matches!(word, "let" | "var" | "upd" | "def"),
+        2 => matches!(word, "is" | "as" | "if"),
+        _ => false,
+    }
 }
 
 impl<'i> Lexer<'i> {
@@ -35,6 +55,8 @@ impl<'i> Lexer<'i> {
             input: source.char_indices().peekable(),
             string_context: vec![],
             next_planned: None,
+            after_def: false,
+            type_argument_depth: None,
         }
     }
 }
@@ -63,7 +85,18 @@ impl<'i> Iterator for Lexer<'i> {
             return self.scan_string_part();
         }
 
-        return self.scan_normal_token();
+        let token = self.scan_normal_token();
+        self.after_def = matches!(token, Some(Ok((_, Token::Symbol("def"), _))));
+        self.type_argument_depth = match &token {
+            Some(Ok((_, Token::Symbol("'" | "->"), _))) => Some(0),
+            Some(Ok((_, Token::Identifier(_), _))) => self.type_argument_depth,
+            Some(Ok((_, Token::OperatorIdentifier(","), _))) => self.type_argument_depth,
+            Some(Ok((_, Token::OperatorIdentifier(brackets), _))) if brackets.chars().all(|ch| ch == '<' || ch == '>') => {
+                self.type_argument_depth.map(|depth| (depth + brackets.matches('<').count()).saturating_sub(brackets.matches('>').count()))
+            }
+            _ => None,
+        };
+        token
     }
 }
 
@@ -83,11 +116,14 @@ impl<'i> Lexer<'i> {
                     self.string_context.push(0);
                     return self.make_token_from(start, Token::Symbol);
                 }
+                ',' if self.type_argument_depth.is_some_and(|depth| depth > 0) => {
+                    return self.make_token_from(start, Token::OperatorIdentifier)
+                }
                 '{' | '}' | '(' | ')' | '[' | ']' | ':' | '@' | '\'' | ',' | ';' => {
-                    if let Some((_, ':')) = self.input.peek() {
-                        // Consume :
+                    if let (':', Some((_, ':'))) = (ch, self.input.peek()) {
+                        // Consume the second : of ::
                         self.input.next();
-                        self.make_token_from(start, Token::Symbol);
+                        return self.make_token_from(start, Token::Symbol);
                     }
 
                     // If it's ( or ), we need to modify the current string context.
@@ -100,7 +136,7 @@ impl<'i> Lexer<'i> {
                     return self.make_token_from(start, Token::Symbol)
                 }
                 '0'..='9' => {
-                    self.input.by_ref().peeking_take_while(|(_, ch)| matches!(ch, '0'..='9')).count() + 1;
+                    self.input.by_ref().peeking_take_while(|(_, ch)| ch.is_ascii_digit()).count();
 
                     let Some((dot_start, '.')) = self.input.peek().cloned() else {
                         return self.make_token_from(start, Token::IntLiteral);
@@ -109,7 +145,7 @@ impl<'i> Lexer<'i> {
                     // Skip .
                     self.input.next();
 
-                    if self.input.peeking_take_while(|(_, ch)| matches!(ch, '0'..='9')).count() > 0 {
+                    if self.input.peeking_take_while(|(_, ch)| ch.is_ascii_digit()).count() > 0 {
                         // We found at least one digit! Skip all digits.
                         return self.make_token_from(start, Token::RealLiteral)
                     } else {
@@ -127,18 +163,17 @@ impl<'i> Lexer<'i> {
                         return macro_token;
                     };
 
+                    if len == 1 && ch == 'c' {
+                        if let Some((_, '"')) = self.input.peek() {
+                            self.input.next();  // Skip "
+                            return self.scan_char_literal(start);
+                        }
+                    }
+
                     let end = peek_pos(&mut self.input, self.source);
                     let slice = unsafe { self.source.get_unchecked(start..end) };
 
-                    if match len {
-                        7 => matches!(slice, "declare"),
-                        6 => matches!(slice, "return"),
-                        5 => matches!(slice, "trait"),
-                        4 => matches!(slice, "else"),
-                        3 => matches!(slice, "let" | "var" | "upd" | "def"),
-                        2 => matches!(slice, "is" | "if"),
-                        _ => false,
-                    } {
+                    if !self.after_def && is_reserved_keyword(slice) {
                         return Some(Ok((start, Token::Symbol(slice), end)));
                     };
 
@@ -158,7 +193,7 @@ impl<'i> Lexer<'i> {
                     let slice = unsafe { self.source.get_unchecked(start..end) };
 
                     if match len {
-                        2 => matches!(slice, "->"),
+                        2 => matches!(slice, "->" | "+=" | "-=" | "*=" | "/="),
                         1 => matches!(ch, '=' | '.' | '!'),
                         _ => false,
                     } {
@@ -180,12 +215,8 @@ impl<'i> Lexer<'i> {
         let mut builder = String::new();
 
         // Advance until " or \(
-        loop {
-            let Some((pos, ch)) = self.input.next() else {
-                // Unterminated string.
-                // Handled in lalrpop, due to missing "
-                break;
-            };
+        // Unterminated strings end early; they're handled in lalrpop, due to the missing ".
+        while let Some((pos, ch)) = self.input.next() {
 
             match ch {
                 '"' => {
@@ -211,12 +242,10 @@ impl<'i> Lexer<'i> {
                             string_builder_preemptive_end_chars = 2;
                             break;
                         }
-                        '\\' | '"' => builder.push(ch),
-                        '0' => builder.push('\0'),
-                        'n' => builder.push('\n'),
-                        't' => builder.push('\t'),
-                        'r' => builder.push('\r'),
-                        _ => return Some(Err(Error(format!("Invalid escape sequence in string literal: {}", ch))))
+                        _ => match unescape(ch) {
+                            Some(ch) => builder.push(ch),
+                            None => return Some(Err(Error(format!("Invalid escape sequence in string literal: {}", ch)))),
+                        }
                     }
                 }
                 // Normal character.
@@ -236,7 +265,40 @@ impl<'i> Lexer<'i> {
 
         // If we have something planned, emit that now.
         // Otherwise, it's eof.
-        return self.next_planned.take();
+        self.next_planned.take()
+    }
+
+    fn scan_char_literal(&mut self, start: usize) -> Option<<Self as Iterator>::Item> {
+        // We are in a char literal, after c".
+        // Unlike string literals, it cannot be interpolated.
+        let mut builder = String::new();
+
+        loop {
+            let Some((_, ch)) = self.input.next() else {
+                return Some(Err(Error("Unterminated char literal.".to_string())));
+            };
+
+            match ch {
+                '"' => break,
+                '\\' => {
+                    let Some((_, ch)) = self.input.next() else {
+                        return Some(Err(Error("Unterminated char literal.".to_string())));
+                    };
+
+                    match unescape(ch) {
+                        Some(ch) => builder.push(ch),
+                        None => return Some(Err(Error(format!("Invalid escape sequence in char literal: {}", ch)))),
+                    }
+                }
+                _ => builder.push(ch),
+            }
+        }
+
+        if builder.chars().count() != 1 {
+            return Some(Err(Error(format!("Char literals must contain exactly one character, but found \"{}\".", builder))));
+        }
+
+        Some(Ok((start, Token::CharLiteral(builder), peek_pos(&mut self.input, self.source))))
     }
 
     fn make_token_from_to(&mut self, start: usize, token: fn(&'i str) -> Token<'i>, end: usize) -> Option<<Self as Iterator>::Item> {
@@ -250,6 +312,17 @@ impl<'i> Lexer<'i> {
     }
 }
 
+fn unescape(ch: char) -> Option<char> {
+    match ch {
+        '\\' | '"' => Some(ch),
+        '0' => Some('\0'),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        _ => None,
+    }
+}
+
 #[inline]
 fn peek_pos(input: &mut Peekable<CharIndices>, full_str: &str) -> usize {
     match input.peek() {
@@ -7,6 +7,7 @@ pub enum Token<'a> {
     MacroIdentifier(&'a str),
     OperatorIdentifier(&'a str),
     StringLiteral(String),
+    CharLiteral(String),
     IntLiteral(&'a str),
     RealLiteral(&'a str),
     Symbol(&'a str),
@@ -22,6 +23,7 @@ impl<'i> Display for Token<'i> {
             Token::RealLiteral(s) => write!(f, "{}", s),
             Token::Symbol(s) => write!(f, "{}", s),
             Token::StringLiteral(s) => write!(f, "{}", s),
+            Token::CharLiteral(s) => write!(f, "c\"{}\"", s),
         }
     }
 }
@@ -1,12 +1,30 @@
 #[cfg(test)]
 mod tests {
     use std::fs;
+    use std::sync::Arc;
 
     use itertools::Itertools;
+    use uuid::Uuid;
 
+    use crate::error::codes::ErrorCode;
     use crate::error::RResult;
     use crate::parser;
+    use crate::parser::expressions;
     use crate::parser::ast::*;
+    use crate::parser::grammar::{Grammar, OperatorAssociativity, Pattern, PatternPart, PrecedenceGroup};
+
+    fn pattern(group: &Arc<PrecedenceGroup>, parts: Vec<PatternPart>, function: &str) -> Arc<Pattern<String>> {
+        Arc::new(Pattern {
+            id: Uuid::new_v4(),
+            precedence_group: Arc::clone(group),
+            parts: parts.into_iter().map(Box::new).collect(),
+            function: function.to_string(),
+        })
+    }
+
+    fn binary(keyword: &str) -> Vec<PatternPart> {
+        vec![PatternPart::Parameter(0), PatternPart::Keyword(keyword.to_string()), PatternPart::Parameter(1)]
+    }
 
     #[test]
     fn hello_world() -> RResult<()> {
@@ -52,4 +70,143 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn trailing_commas() -> RResult<()> {
+        let file_contents = fs::read_to_string("test-code/grammar/trailing_commas.monoteny").unwrap();
+        let (parsed, errors) = parser::parse_program(file_contents.as_str())?;
+        assert!(errors.is_empty());
+
+        let Statement::FunctionDeclaration(main) = &parsed.statements[3].value.value else {
+            panic!();
+        };
+        let Term::Block(body) = &main.body.as_ref().unwrap()[0].value else {
+            panic!();
+        };
+        let Statement::Expression(call) = &body.statements[1].value.value else {
+            panic!();
+        };
+        let Term::Struct(arguments) = &call[1].value else {
+            panic!();
+        };
+
+        // Each argument spans just its own text, without the separators and newlines around it.
+        let argument_texts = arguments.arguments.iter().map(|argument| &file_contents[argument.position.clone()]).collect_vec();
+        assert_eq!(argument_texts, vec!["name: \"rectangle\"", "size: area(\n            width: 2,\n            height: 3,\n        )"]);
+        // Displaying normalizes the trailing commas away.
+        assert_eq!(call.to_string(), "describe (name: \"rectangle\", size: area (width: 2, height: 3))");
+
+        let parse_main = |body: &str| parser::parse_program(&format!("def main! :: {{\n    {}\n}};", body)).map(|(parsed, errors)| (parsed.statements.len(), errors.len()));
+        assert_eq!(parse_main("f();")?, (1, 0));
+        assert_eq!(parse_main("f(\n    [\n        1,\n        [2, 3,],\n    ],\n    (\n        a: 4,\n    ),\n);")?, (1, 0));
+        // A separator needs an argument before it.
+        assert!(parse_main("f(,);").map_or(true, |(_, errors)| errors > 0));
+        assert!(parse_main("f[,];").map_or(true, |(_, errors)| errors > 0));
+
+        Ok(())
+    }
+
+    #[test]
+    fn type_argument_commas() -> RResult<()> {
+        let (parsed, errors) = parser::parse_program("def f(pair 'Pair<Int32, List<String>>, count 'Int32) -> Pair<Int32, Int32> :: g(a < b, c > d);")?;
+        assert!(errors.is_empty());
+
+        let Statement::FunctionDeclaration(function) = &parsed.statements[0].value.value else {
+            panic!();
+        };
+        let Term::Struct(parameters) = &function.interface.expression[1].value else {
+            panic!();
+        };
+        // Commas within a type's angle brackets separate its arguments, not the parameters.
+        assert_eq!(parameters.arguments.len(), 2);
+        assert_eq!(parameters.arguments[0].value.type_declaration.as_ref().unwrap().to_string(), "Pair < Int32 , List < String >>");
+        assert_eq!(function.interface.return_type.as_ref().unwrap().to_string(), "Pair < Int32 , Int32 >");
+        // Elsewhere, they separate arguments.
+        let Term::Struct(arguments) = &function.body.as_ref().unwrap()[1].value else {
+            panic!();
+        };
+        assert_eq!(arguments.arguments.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn grammar_merge_conflicts() -> RResult<()> {
+        let unary = Arc::new(PrecedenceGroup::new("LeftUnaryPrecedence", OperatorAssociativity::LeftUnary));
+        let addition = Arc::new(PrecedenceGroup::new("AdditionPrecedence", OperatorAssociativity::Left));
+        let multiplication = Arc::new(PrecedenceGroup::new("MultiplicationPrecedence", OperatorAssociativity::Left));
+        let mut grammar = Grammar::<String>::new();
+        grammar.set_precedence_order(vec![Arc::clone(&unary), Arc::clone(&addition), Arc::clone(&multiplication)]);
+
+        // Importing the same pattern twice, e.g. through two modules including it, is fine.
+        let add = pattern(&addition, binary("+"), "add");
+        grammar.add_pattern(Arc::clone(&add))?;
+        grammar.add_pattern(add)?;
+
+        // Binding the same keywords to another function in the same group adds an overload.
+        grammar.add_pattern(pattern(&addition, binary("+"), "concatenate"))?;
+        assert_eq!(grammar.groups_and_keywords[&addition]["+"], vec!["add", "concatenate"]);
+
+        // The same keyword may be bound in another group, though, as long as the form differs.
+        grammar.add_pattern(pattern(&unary, vec![PatternPart::Keyword("+".to_string()), PatternPart::Parameter(0)], "positive"))?;
+        let in_multiple_groups = grammar.keywords_in_multiple_groups();
+        assert_eq!(in_multiple_groups.len(), 1);
+        assert_eq!(in_multiple_groups[0].0, "+");
+        assert_eq!(in_multiple_groups[0].1.iter().map(|group| &group.name).collect_vec(), vec!["LeftUnaryPrecedence", "AdditionPrecedence"]);
+
+        // Binding the same form in another group is ambiguous to parse.
+        let errors = grammar.add_pattern(pattern(&multiplication, binary("+"), "multiply")).unwrap_err();
+        assert_eq!(errors[0].title, "Pattern '+' conflicts with an existing pattern in AdditionPrecedence.");
+        assert!(grammar.groups_and_keywords[&multiplication].is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn grammar_precedence_change() -> RResult<()> {
+        let addition = Arc::new(PrecedenceGroup::new("AdditionPrecedence", OperatorAssociativity::Left));
+        let mut grammar = Grammar::<String>::new();
+        grammar.set_precedence_order(vec![Arc::clone(&addition)]);
+        grammar.add_pattern(pattern(&addition, binary("+"), "add"))?;
+
+        // A new precedence order drops all patterns; they were bound to the old groups.
+        let other_addition = Arc::new(PrecedenceGroup::new("AdditionPrecedence", OperatorAssociativity::Left));
+        grammar.set_precedence_order(vec![Arc::clone(&other_addition)]);
+        assert!(grammar.keywords.is_empty());
+        assert!(grammar.groups_and_keywords[&other_addition].is_empty());
+
+        // So the keyword is free to be bound again.
+        grammar.add_pattern(pattern(&other_addition, binary("+"), "concatenate"))?;
+        assert_eq!(grammar.groups_and_keywords[&other_addition]["+"], vec!["concatenate"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn grammar_missing_group() -> RResult<()> {
+        let unary = Arc::new(PrecedenceGroup::new("LeftUnaryPrecedence", OperatorAssociativity::LeftUnary));
+        let addition = Arc::new(PrecedenceGroup::new("AdditionPrecedence", OperatorAssociativity::Left));
+        let mut grammar = Grammar::<String>::new();
+        grammar.set_precedence_order(vec![unary, Arc::clone(&addition)]);
+
+        // Groups are told apart by identity, not by name.
+        let other_addition = Arc::new(PrecedenceGroup::new("AdditionPrecedence", OperatorAssociativity::Left));
+        let errors = grammar.add_pattern(pattern(&other_addition, binary("+"), "add")).unwrap_err();
+        assert_eq!(errors[0].title, "Pattern '+' belongs to precedence group AdditionPrecedence, which is not in the precedence order.");
+        assert_eq!(errors[0].code, Some(ErrorCode::MissingPrecedenceGroup));
+
+        // Keywords without a group are reported where they are used, rather than panicking.
+        grammar.keywords.insert("+".to_string());
+        let (parsed, _) = parser::parse_program("def f :: a + b;")?;
+        let Statement::FunctionDeclaration(function) = &parsed.statements[0].value.value else {
+            panic!();
+        };
+        let Err(errors) = expressions::parse(function.body.as_ref().unwrap(), &grammar) else {
+            panic!();
+        };
+        assert_eq!(errors[0].title, "'+' is not an operator of any precedence group in the precedence order.");
+        assert_eq!(errors[0].range, Some(11..12));
+
+        Ok(())
+    }
 }
@@ -1,12 +1,12 @@
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use uuid::Uuid;
 
 use crate::program::types::TypeProto;
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Mutability {
     Immutable,
     Mutable,
@@ -24,13 +24,13 @@ impl Mutability {
 #[derive(Clone, Eq)]
 pub struct ObjectReference {
     pub id: Uuid,
-    pub type_: Rc<TypeProto>,
+    pub type_: Arc<TypeProto>,
     pub mutability: Mutability,
 }
 
 impl ObjectReference {
-    pub fn new_immutable(type_: Rc<TypeProto>) -> Rc<ObjectReference> {
-        Rc::new(ObjectReference {
+    pub fn new_immutable(type_: Arc<TypeProto>) -> Arc<ObjectReference> {
+        Arc::new(ObjectReference {
             id: Uuid::new_v4(),
             type_,
             mutability: Mutability::Immutable
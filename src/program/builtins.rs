@@ -1,8 +1,14 @@
+use itertools::Itertools;
+
 use crate::interpreter::runtime::Runtime;
 use crate::program::module::{Module, module_name};
+use crate::resolver::boxes;
 
 pub mod primitives;
 pub mod traits;
+pub mod lists;
+pub mod json;
+pub mod reflection;
 
 pub fn create_builtins(runtime: &mut Runtime) -> Box<Module> {
     let mut module = Box::new(Module::new(module_name("builtins")));
@@ -10,5 +16,14 @@ pub fn create_builtins(runtime: &mut Runtime) -> Box<Module> {
     runtime.primitives = Some(primitives::create_traits(runtime, &mut module));
     runtime.traits = Some(traits::create(runtime, &mut module));
     primitives::create_functions(runtime, &mut module);
+    lists::create_functions(runtime, &mut module);
+    json::create_functions(runtime, &mut module);
+    reflection::create_functions(runtime, &mut module);
+
+    // Dyn is declared with the other traits, so boxable builtin traits can only conform to it now.
+    let traits = runtime.source.trait_references.values().cloned().unique().collect_vec();
+    for trait_ in traits {
+        boxes::add_dyn_conformance(runtime, &mut module, None, &trait_).unwrap();
+    }
     module
 }
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::interpreter::runtime::Runtime;
+use crate::program::builtins::traits::FunctionPointer;
+use crate::program::functions::FunctionInterface;
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, JsonOperation};
+use crate::program::module::Module;
+use crate::program::primitives;
+use crate::program::types::TypeProto;
+use crate::resolver::referencible;
+
+#[allow(non_snake_case)]
+pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
+    let traits = runtime.traits.as_ref().unwrap();
+    let primitives = runtime.primitives.as_ref().unwrap();
+    let Json = TypeProto::unit_struct(&traits.Json);
+    let String = TypeProto::unit_struct(&traits.String);
+    let Int64 = TypeProto::unit_struct(&primitives[&primitives::Type::Int(64)]);
+    let Float64 = TypeProto::unit_struct(&primitives[&primitives::Type::Float(64)]);
+    let Bool = TypeProto::unit_struct(&primitives[&primitives::Type::Bool]);
+
+    let parse = FunctionPointer::new_global_function("parse_json", FunctionInterface::new_simple(
+        [("string", String.clone())].into_iter(), Json.clone()
+    ));
+
+    let member = |name: &str, parameters: Vec<(&str, Arc<TypeProto>)>, return_type: &Arc<TypeProto>| {
+        FunctionPointer::new_member_function(name, FunctionInterface::new_member(Json.clone(), parameters.into_iter(), return_type.clone()))
+    };
+
+    for (function, operation) in [
+        (parse, JsonOperation::Parse),
+        (member("get", vec![("key", String.clone())], &Json), JsonOperation::Get),
+        (member("at", vec![("index", Int64.clone())], &Json), JsonOperation::At),
+        (member("len", vec![], &Int64), JsonOperation::Length),
+        (member("kind", vec![], &String), JsonOperation::Kind),
+        (member("as_string", vec![], &String), JsonOperation::AsString),
+        (member("as_float", vec![], &Float64), JsonOperation::AsFloat),
+        (member("as_bool", vec![], &Bool), JsonOperation::AsBool),
+    ] {
+        referencible::add_function(runtime, module, None, Arc::clone(&function.target), function.representation.clone()).unwrap();
+        runtime.source.fn_logic.insert(
+            Arc::clone(&function.target),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::JsonOperation(operation))
+        );
+    }
+}
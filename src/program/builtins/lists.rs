@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::interpreter::runtime::Runtime;
+use crate::program::allocation::Mutability;
+use crate::program::builtins::traits::FunctionPointer;
+use crate::program::functions::{FunctionInterface, Parameter, ParameterKey};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, ListOperation};
+use crate::program::module::Module;
+use crate::program::primitives;
+use crate::program::traits::Trait;
+use crate::program::types::TypeProto;
+use crate::resolver::referencible;
+
+#[allow(non_snake_case)]
+pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
+    let List = Arc::clone(&runtime.traits.as_ref().unwrap().List);
+    let index_type = TypeProto::unit_struct(&runtime.primitives.as_ref().unwrap()[&primitives::Type::Int(64)]);
+
+    // Each function gets its own element generic, so that calls can be resolved independently.
+    let mut add_function = |name: &str, operation: ListOperation, make_interface: &dyn Fn(Arc<TypeProto>, Arc<TypeProto>) -> (Vec<Arc<TypeProto>>, Arc<TypeProto>), runtime: &mut Runtime| {
+        let Element = Arc::new(Trait::new_flat("Element"));
+        let element_type = TypeProto::unit_struct(&Element);
+        let list_type = TypeProto::one_arg(&List, element_type.clone());
+
+        let (parameter_types, return_type) = make_interface(list_type, element_type);
+        let function = FunctionPointer::new_member_function(name, Arc::new(FunctionInterface {
+            parameters: parameter_types.into_iter().enumerate().map(|(idx, type_)| Parameter {
+                external_key: ParameterKey::Positional,
+                internal_name: if idx == 0 { "self".to_string() } else { format!("p{}", idx - 1) },
+                type_,
+                mutability: Mutability::Immutable,
+            }).collect(),
+            return_type,
+            requirements: Default::default(),
+            generics: HashMap::from([("Element".to_string(), Element)]),
+        }));
+
+        referencible::add_function(runtime, module, None, Arc::clone(&function.target), function.representation.clone()).unwrap();
+        runtime.source.fn_logic.insert(
+            Arc::clone(&function.target),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::ListOperation(operation))
+        );
+    };
+
+    // List.new() is called on the metatype, like a constructor would be.
+    let list_metatype = TypeProto::one_arg(&runtime.Metatype, TypeProto::unit_struct(&List));
+    add_function("new", ListOperation::New, &|list, _| (vec![list_metatype.clone()], list), runtime);
+    add_function("push", ListOperation::Push, &|list, element| (vec![list, element], TypeProto::void()), runtime);
+    add_function("pop", ListOperation::Pop, &|list, element| (vec![list], element), runtime);
+    add_function("len", ListOperation::Length, &|list, _| (vec![list], index_type.clone()), runtime);
+    add_function("get", ListOperation::Get, &|list, element| (vec![list, index_type.clone()], element), runtime);
+    add_function("set", ListOperation::Set, &|list, element| (vec![list, index_type.clone(), element], TypeProto::void()), runtime);
+
+    // List<#T> conforms to ToString in common.lists, since it is written like any other Monoteny code.
+
+    // String.chars() is not generic, so it doesn't need its own element trait.
+    let char_type = TypeProto::unit_struct(&runtime.primitives.as_ref().unwrap()[&primitives::Type::Char]);
+    let chars = FunctionPointer::new_member_function("chars", FunctionInterface::new_member(
+        TypeProto::unit_struct(&runtime.traits.as_ref().unwrap().String),
+        [].into_iter(),
+        TypeProto::one_arg(&List, char_type),
+    ));
+    referencible::add_function(runtime, module, None, Arc::clone(&chars.target), chars.representation.clone()).unwrap();
+    runtime.source.fn_logic.insert(
+        Arc::clone(&chars.target),
+        FunctionLogic::Descriptor(FunctionLogicDescriptor::ListOperation(ListOperation::FromChars))
+    );
+}
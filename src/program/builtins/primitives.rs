@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::interpreter::runtime::Runtime;
 use crate::resolver::referencible;
@@ -12,8 +12,8 @@ use crate::program::primitives;
 use crate::program::traits::{Trait, TraitConformanceRule};
 use crate::program::types::{TypeProto, TypeUnit};
 
-pub fn create_traits(runtime: &mut Runtime, module: &mut Module) -> HashMap<primitives::Type, Rc<Trait>> {
-    let mut traits: HashMap<primitives::Type, Rc<Trait>> = Default::default();
+pub fn create_traits(runtime: &mut Runtime, module: &mut Module) -> HashMap<primitives::Type, Arc<Trait>> {
+    let mut traits: HashMap<primitives::Type, Arc<Trait>> = Default::default();
 
     for primitive_type in [
         primitives::Type::Bool,
@@ -27,8 +27,9 @@ pub fn create_traits(runtime: &mut Runtime, module: &mut Module) -> HashMap<prim
         primitives::Type::UInt(64),
         primitives::Type::Float(32),
         primitives::Type::Float(64),
+        primitives::Type::Char,
     ] {
-        let trait_ = Rc::new(Trait::new_with_self(&primitive_type.identifier_string()));
+        let trait_ = Arc::new(Trait::new_with_self(&primitive_type.identifier_string()));
         referencible::add_trait(runtime, module, None, &trait_).unwrap();
         traits.insert(primitive_type, trait_);
     }
@@ -44,15 +45,15 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
     let primitive_traits = runtime.primitives.as_ref().unwrap().clone();
     let bool_type = TypeProto::unit_struct(&primitive_traits[&primitives::Type::Bool]);
 
-    let mut add_function = |function: &Rc<FunctionPointer>, primitive_type: primitives::Type, operation: PrimitiveOperation, module: &mut Module, runtime: &mut Runtime| {
-        referencible::add_function(runtime, module, None, Rc::clone(&function.target), function.representation.clone()).unwrap();
+    let add_function = |function: &Arc<FunctionPointer>, primitive_type: primitives::Type, operation: PrimitiveOperation, module: &mut Module, runtime: &mut Runtime| {
+        referencible::add_function(runtime, module, None, Arc::clone(&function.target), function.representation.clone()).unwrap();
         runtime.source.fn_logic.insert(
-            Rc::clone(&function.target),
+            Arc::clone(&function.target),
             FunctionLogic::Descriptor(FunctionLogicDescriptor::PrimitiveOperation { type_: primitive_type, operation })
         );
     };
 
-    for (primitive_type, trait_) in primitive_traits.iter() {
+    for (primitive_type, _trait_) in primitive_traits.iter() {
         let type_ = TypeProto::unit_struct(&primitive_traits[primitive_type]);
         let primitive_type = *primitive_type;
 
@@ -78,7 +79,24 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
             ]
         ));
 
-        if !primitive_type.is_number() {
+        if primitive_type == primitives::Type::Char {
+            add_function(&traits.parse_char_literal_function, primitive_type, PrimitiveOperation::ParseCharString, module, runtime);
+
+            let code_type = TypeProto::unit_struct(&primitive_traits[&primitives::Type::UInt(32)]);
+            let to_char = FunctionPointer::new_global_function(
+                "to_char",
+                FunctionInterface::new_simple([("code", code_type.clone())].into_iter(), type_.clone())
+            );
+            add_function(&to_char, primitive_type, PrimitiveOperation::CharFromCode, module, runtime);
+            let code = FunctionPointer::new_global_function(
+                "code",
+                FunctionInterface::new_simple([("char", type_.clone())].into_iter(), code_type)
+            );
+            add_function(&code, primitive_type, PrimitiveOperation::CharToCode, module, runtime);
+        }
+        else if !primitive_type.is_number() {
+            // Bool is not ordered, so requiring Ord of it fails during resolution.
+            //  Accordingly, the VM's comparison opcodes reject it.
             continue;
         }
 
@@ -99,6 +117,10 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
             ]
         ));
 
+        if !primitive_type.is_number() {
+            continue;
+        }
+
         // Number
         let number_functions = traits::make_number_functions(&type_);
         add_function(&number_functions.add, primitive_type, PrimitiveOperation::Add, module, runtime);
@@ -110,7 +132,7 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
 
         let _parse_int_literal = FunctionPointer::new_global_function(
             "parse_int_literal",
-            FunctionInterface::new_operator(1, &TypeProto::unit_struct(&traits.String), &type_)
+            FunctionInterface::new_operator(&["literal"], &TypeProto::unit_struct(&traits.String), &type_)
         );
         add_function(&_parse_int_literal, primitive_type, PrimitiveOperation::ParseIntString, module, runtime);
         module.trait_conformance.add_conformance_rule(TraitConformanceRule::manual(
@@ -133,9 +155,14 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
         ));
 
         if primitive_type.is_int() {
+            let int_functions = traits::make_int_functions(&type_);
+            add_function(&int_functions.pow, primitive_type, PrimitiveOperation::Exp, module, runtime);
+
             module.trait_conformance.add_conformance_rule(TraitConformanceRule::manual(
                 traits.Int.create_generic_binding(vec![("Self", type_.clone())]),
-                vec![]
+                vec![
+                    (&traits.Int_functions.pow.target, &int_functions.pow.target),
+                ]
             ));
 
             if !primitive_type.is_signed_number() {
@@ -156,7 +183,7 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
 
         let _parse_real_literal = FunctionPointer::new_global_function(
             "parse_real_literal",
-            FunctionInterface::new_operator(1, &TypeProto::unit(TypeUnit::Struct(Rc::clone(&traits.String))), &type_)
+            FunctionInterface::new_operator(&["literal"], &TypeProto::unit(TypeUnit::Struct(Arc::clone(&traits.String))), &type_)
         );
         add_function(&_parse_real_literal, primitive_type, PrimitiveOperation::ParseRealString, module, runtime);
         module.trait_conformance.add_conformance_rule(TraitConformanceRule::manual(
@@ -177,19 +204,19 @@ pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
 
     let and_op = FunctionPointer::new_global_function(
         "and_f",
-        FunctionInterface::new_operator(2, &bool_type, &bool_type)
+        FunctionInterface::new_operator(&["lhs", "rhs"], &bool_type, &bool_type)
     );
     add_function(&and_op, primitives::Type::Bool, PrimitiveOperation::And, module, runtime);
 
     let or__op = FunctionPointer::new_global_function(
         "or_f",
-        FunctionInterface::new_operator(2, &bool_type, &bool_type)
+        FunctionInterface::new_operator(&["lhs", "rhs"], &bool_type, &bool_type)
     );
     add_function(&or__op, primitives::Type::Bool, PrimitiveOperation::Or, module, runtime);
 
     let not_op = FunctionPointer::new_global_function(
         "not_f",
-        FunctionInterface::new_operator(1, &bool_type, &bool_type)
+        FunctionInterface::new_operator(&["arg"], &bool_type, &bool_type)
     );
     add_function(&not_op, primitives::Type::Bool, PrimitiveOperation::Not, module, runtime);
 }
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::interpreter::runtime::Runtime;
+use crate::program::allocation::Mutability;
+use crate::program::builtins::traits::FunctionPointer;
+use crate::program::functions::{FunctionInterface, Parameter, ParameterKey};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, Reflection};
+use crate::program::module::Module;
+use crate::program::traits::Trait;
+use crate::program::types::TypeProto;
+use crate::resolver::referencible;
+
+#[allow(non_snake_case)]
+pub fn create_functions(runtime: &mut Runtime, module: &mut Module) {
+    let String = TypeProto::unit_struct(&runtime.traits.as_ref().unwrap().String);
+    let List = Arc::clone(&runtime.traits.as_ref().unwrap().List);
+
+    let mut add_function = |name: &str, reflection: Reflection, make_parameter: &dyn Fn(Arc<TypeProto>) -> Arc<TypeProto>, return_type: Arc<TypeProto>, runtime: &mut Runtime| {
+        let Subject = Arc::new(Trait::new_flat("Subject"));
+
+        let function = FunctionPointer::new_global_function(name, Arc::new(FunctionInterface {
+            parameters: vec![Parameter {
+                external_key: ParameterKey::Positional,
+                internal_name: "subject".to_string(),
+                type_: make_parameter(TypeProto::unit_struct(&Subject)),
+                mutability: Mutability::Immutable,
+            }],
+            return_type,
+            requirements: Default::default(),
+            generics: HashMap::from([("Subject".to_string(), Subject)]),
+        }));
+
+        referencible::add_function(runtime, module, None, Arc::clone(&function.target), function.representation.clone()).unwrap();
+        runtime.source.fn_logic.insert(
+            Arc::clone(&function.target),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::Reflection(reflection))
+        );
+    };
+
+    add_function("type_name", Reflection::TypeName, &|subject| subject, String.clone(), runtime);
+    // Structs are passed by their metatype, e.g. fields_of(Point).
+    let metatype = Arc::clone(&runtime.Metatype);
+    add_function("fields_of", Reflection::FieldsOf, &|subject| TypeProto::one_arg(&metatype, subject), TypeProto::one_arg(&List, String.clone()), runtime);
+}
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::interpreter::runtime::Runtime;
 use crate::resolver::referencible;
@@ -10,13 +10,13 @@ use crate::program::traits::Trait;
 use crate::program::types::TypeProto;
 
 pub struct FunctionPointer {
-    pub target: Rc<FunctionHead>,
+    pub target: Arc<FunctionHead>,
     pub representation: FunctionRepresentation,
 }
 
 impl FunctionPointer {
-    pub fn new_global_function(name: &str, interface: Rc<FunctionInterface>) -> Rc<FunctionPointer> {
-        Rc::new(FunctionPointer {
+    pub fn new_global_function(name: &str, interface: Arc<FunctionInterface>) -> Arc<FunctionPointer> {
+        Arc::new(FunctionPointer {
             target: FunctionHead::new_static(interface),
             representation: FunctionRepresentation {
                 name: name.to_string(),
@@ -26,8 +26,8 @@ impl FunctionPointer {
         })
     }
 
-    pub fn new_member_function(name: &str, interface: Rc<FunctionInterface>) -> Rc<FunctionPointer> {
-        Rc::new(FunctionPointer {
+    pub fn new_member_function(name: &str, interface: Arc<FunctionInterface>) -> Arc<FunctionPointer> {
+        Arc::new(FunctionPointer {
             target: FunctionHead::new_static(interface),
             representation: FunctionRepresentation {
                 name: name.to_string(),
@@ -37,8 +37,8 @@ impl FunctionPointer {
         })
     }
 
-    pub fn new_global_implicit(name: &str, interface: Rc<FunctionInterface>) -> Rc<FunctionPointer> {
-        Rc::new(FunctionPointer {
+    pub fn new_global_implicit(name: &str, interface: Arc<FunctionInterface>) -> Arc<FunctionPointer> {
+        Arc::new(FunctionPointer {
             target: FunctionHead::new_static(interface),
             representation: FunctionRepresentation {
                 name: name.to_string(),
@@ -49,9 +49,9 @@ impl FunctionPointer {
     }
 }
 
-pub fn insert_functions<'a, I>(module: &mut Trait, functions: I) where I: Iterator<Item=&'a Rc<FunctionPointer>> {
+pub fn insert_functions<'a, I>(module: &mut Trait, functions: I) where I: Iterator<Item=&'a Arc<FunctionPointer>> {
     for ptr in functions {
-        module.insert_function(Rc::clone(&ptr.target), ptr.representation.clone())
+        module.insert_function(Arc::clone(&ptr.target), ptr.representation.clone())
     }
 }
 
@@ -60,155 +60,183 @@ pub fn insert_functions<'a, I>(module: &mut Trait, functions: I) where I: Iterat
 pub struct Traits {
     /// Supertype of all function objects.
     /// No requirements yet (will require call_as_function to return self!).
-    pub Function: Rc<Trait>,
+    pub Function: Arc<Trait>,
 
-    pub Eq: Rc<Trait>,
+    pub Eq: Arc<Trait>,
     pub Eq_functions: EqFunctions,
 
-    pub Ord: Rc<Trait>,
+    pub Ord: Arc<Trait>,
     pub Ord_functions: OrdFunctions,
 
-    pub String: Rc<Trait>,
-    pub ToString: Rc<Trait>,
-    pub to_string_function: Rc<FunctionPointer>,
+    pub String: Arc<Trait>,
+    pub ToString: Arc<Trait>,
+    pub to_string_function: Arc<FunctionPointer>,
 
-    pub ConstructableByIntLiteral: Rc<Trait>,
-    pub parse_int_literal_function: Rc<FunctionPointer>,
+    pub ConstructableByIntLiteral: Arc<Trait>,
+    pub parse_int_literal_function: Arc<FunctionPointer>,
 
-    pub ConstructableByRealLiteral: Rc<Trait>,
-    pub parse_real_literal_function: Rc<FunctionPointer>,
+    pub ConstructableByRealLiteral: Arc<Trait>,
+    pub parse_real_literal_function: Arc<FunctionPointer>,
 
-    pub Number: Rc<Trait>,
+    /// Char literals always denote a Char, so they are parsed without a trait.
+    pub parse_char_literal_function: Arc<FunctionPointer>,
+
+    pub Number: Arc<Trait>,
     pub Number_functions: NumberFunctions,
 
-    pub Real: Rc<Trait>,
+    pub Real: Arc<Trait>,
     pub Real_functions: RealFunctions,
 
-    pub Int: Rc<Trait>,
-    pub Natural: Rc<Trait>,
+    pub Int: Arc<Trait>,
+    pub Int_functions: IntFunctions,
+    pub Natural: Arc<Trait>,
+
+    /// Growable, homogeneous collection. Its single type argument is the element type.
+    pub List: Arc<Trait>,
+    /// A parsed JSON value: an object, array, string, number, bool or null.
+    pub Json: Arc<Trait>,
+    /// A boxed value of any type conforming to the trait that is its single type argument.
+    /// Calls to the trait's functions are dispatched to the boxed value's implementation.
+    pub Dyn: Arc<Trait>,
 }
 
 #[derive(Clone)]
 pub struct EqFunctions {
-    pub equal_to: Rc<FunctionPointer>,
-    pub not_equal_to: Rc<FunctionPointer>,
+    pub equal_to: Arc<FunctionPointer>,
+    pub not_equal_to: Arc<FunctionPointer>,
 }
 
-pub fn make_eq_functions(type_: &Rc<TypeProto>, bool_type: &Rc<TypeProto>) -> EqFunctions {
+pub fn make_eq_functions(type_: &Arc<TypeProto>, bool_type: &Arc<TypeProto>) -> EqFunctions {
     EqFunctions {
         equal_to: FunctionPointer::new_global_function(
             "is_equal",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
         not_equal_to: FunctionPointer::new_global_function(
             "is_not_equal",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
     }
 }
 
 #[derive(Clone)]
 pub struct OrdFunctions {
-    pub greater_than: Rc<FunctionPointer>,
-    pub greater_than_or_equal_to: Rc<FunctionPointer>,
-    pub lesser_than: Rc<FunctionPointer>,
-    pub lesser_than_or_equal_to: Rc<FunctionPointer>,
+    pub greater_than: Arc<FunctionPointer>,
+    pub greater_than_or_equal_to: Arc<FunctionPointer>,
+    pub lesser_than: Arc<FunctionPointer>,
+    pub lesser_than_or_equal_to: Arc<FunctionPointer>,
 }
 
-pub fn make_ord_functions(type_: &Rc<TypeProto>, bool_type: &Rc<TypeProto>) -> OrdFunctions {
+pub fn make_ord_functions(type_: &Arc<TypeProto>, bool_type: &Arc<TypeProto>) -> OrdFunctions {
     OrdFunctions {
         greater_than: FunctionPointer::new_global_function(
             "is_greater",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
         greater_than_or_equal_to: FunctionPointer::new_global_function(
             "is_greater_or_equal",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
         lesser_than: FunctionPointer::new_global_function(
             "is_lesser",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
         lesser_than_or_equal_to: FunctionPointer::new_global_function(
             "is_lesser_or_equal",
-            FunctionInterface::new_operator(2, type_, bool_type)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, bool_type)
         ),
     }
 }
 
 #[derive(Clone)]
 pub struct NumberFunctions {
-    pub add: Rc<FunctionPointer>,
-    pub subtract: Rc<FunctionPointer>,
-    pub multiply: Rc<FunctionPointer>,
-    pub divide: Rc<FunctionPointer>,
+    pub add: Arc<FunctionPointer>,
+    pub subtract: Arc<FunctionPointer>,
+    pub multiply: Arc<FunctionPointer>,
+    pub divide: Arc<FunctionPointer>,
 
-    pub modulo: Rc<FunctionPointer>,
+    pub modulo: Arc<FunctionPointer>,
 
     /// You may argue that unsigned numbers should not need to support negative.
     /// However, all unsigned numbers have rollover. That means that e.g. -1 = MAX, and
     ///  it's generally a perfectly valid operation.
-    pub negative: Rc<FunctionPointer>,
+    pub negative: Arc<FunctionPointer>,
 }
 
-pub fn make_number_functions(type_: &Rc<TypeProto>) -> NumberFunctions {
+pub fn make_number_functions(type_: &Arc<TypeProto>) -> NumberFunctions {
     NumberFunctions {
         add: FunctionPointer::new_global_function(
             "add",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
         subtract: FunctionPointer::new_global_function(
             "subtract",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
         multiply: FunctionPointer::new_global_function(
             "multiply",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
         divide: FunctionPointer::new_global_function(
             "divide",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
 
         negative: FunctionPointer::new_global_function(
             "negative",
-            FunctionInterface::new_operator(1, type_, type_)
+            FunctionInterface::new_operator(&["arg"], type_, type_)
         ),
 
         modulo: FunctionPointer::new_global_function(
             "modulo",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
     }
 }
 
 #[derive(Clone)]
 pub struct RealFunctions {
-    pub pow: Rc<FunctionPointer>,
-    pub log: Rc<FunctionPointer>,
+    pub pow: Arc<FunctionPointer>,
+    pub log: Arc<FunctionPointer>,
 }
 
-pub fn make_real_functions(type_: &Rc<TypeProto>) -> RealFunctions {
+pub fn make_real_functions(type_: &Arc<TypeProto>) -> RealFunctions {
     RealFunctions {
         pow: FunctionPointer::new_global_function(
             "pow",
-            FunctionInterface::new_operator(2, type_, type_)
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
         log: FunctionPointer::new_global_function(
             "log",
-            FunctionInterface::new_operator(1, type_, type_)
+            FunctionInterface::new_operator(&["arg"], type_, type_)
+        ),
+    }
+}
+
+#[derive(Clone)]
+pub struct IntFunctions {
+    /// Wraps around on overflow, like the other integer operations.
+    /// Negative exponents fail at runtime, because the result would be a fraction.
+    pub pow: Arc<FunctionPointer>,
+}
+
+pub fn make_int_functions(type_: &Arc<TypeProto>) -> IntFunctions {
+    IntFunctions {
+        pow: FunctionPointer::new_global_function(
+            "pow",
+            FunctionInterface::new_operator(&["lhs", "rhs"], type_, type_)
         ),
     }
 }
 
 #[allow(non_snake_case)]
-pub fn make_to_string_function(type_: &Trait, String: &Rc<Trait>) -> Rc<FunctionPointer> {
+pub fn make_to_string_function(type_: &Trait, String: &Arc<Trait>) -> Arc<FunctionPointer> {
     FunctionPointer::new_member_function(
         "to_string",
         FunctionInterface::new_member(
             type_.create_generic_type("Self"),
             [].into_iter(),
-            TypeProto::unit_struct(&String)
+            TypeProto::unit_struct(String)
         )
     )
 }
@@ -217,9 +245,10 @@ pub fn make_to_string_function(type_: &Trait, String: &Rc<Trait>) -> Rc<Function
 pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
     let primitive_traits = runtime.primitives.as_ref().unwrap();
     let bool_type = TypeProto::unit_struct(&primitive_traits[&primitives::Type::Bool]);
+    let char_type = TypeProto::unit_struct(&primitive_traits[&primitives::Type::Char]);
 
-    let mut Function = Trait::new_with_self("Function");
-    let Function = Rc::new(Function);
+    let Function = Trait::new_with_self("Function");
+    let Function = Arc::new(Function);
     referencible::add_trait(runtime, module, None, &Function).unwrap();
 
     let mut Eq = Trait::new_with_self("Eq");
@@ -228,7 +257,7 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
         &eq_functions.equal_to,
         &eq_functions.not_equal_to,
     ].into_iter());
-    let Eq = Rc::new(Eq);
+    let Eq = Arc::new(Eq);
     referencible::add_trait(runtime, module, None, &Eq).unwrap();
 
     let mut Ord = Trait::new_with_self("Ord");
@@ -240,7 +269,7 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
         &ord_functions.lesser_than_or_equal_to,
     ].into_iter());
     Ord.add_simple_parent_requirement(&Eq);
-    let Ord = Rc::new(Ord);
+    let Ord = Arc::new(Ord);
     referencible::add_trait(runtime, module, None, &Ord).unwrap();
 
     let mut Number = Trait::new_with_self("Number");
@@ -254,11 +283,11 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
         &number_functions.modulo,
     ].into_iter());
     Number.add_simple_parent_requirement(&Ord);
-    let Number = Rc::new(Number);
+    let Number = Arc::new(Number);
     referencible::add_trait(runtime, module, None, &Number).unwrap();
 
-    let mut String = Trait::new_with_self("String");
-    let String = Rc::new(String);
+    let String = Trait::new_with_self("String");
+    let String = Arc::new(String);
     referencible::add_trait(runtime, module, None, &String).unwrap();
 
     // TODO String is not ToString. We could declare it on the struct, but that seems counterintuitive, no?
@@ -268,21 +297,21 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
     insert_functions(&mut ToString, [
         &to_string_function
     ].into_iter());
-    let ToString = Rc::new(ToString);
+    let ToString = Arc::new(ToString);
     referencible::add_trait(runtime, module, None, &ToString).unwrap();
 
     let mut ConstructableByIntLiteral = Trait::new_with_self("ConstructableByIntLiteral");
     let parse_int_literal_function = FunctionPointer::new_global_function(
         "parse_int_literal",
         FunctionInterface::new_simple(
-            [TypeProto::unit_struct(&String)].into_iter(),
+            [("literal", TypeProto::unit_struct(&String))].into_iter(),
             ConstructableByIntLiteral.create_generic_type("Self"),
         )
     );
     insert_functions(&mut ConstructableByIntLiteral, [
         &parse_int_literal_function
     ].into_iter());
-    let ConstructableByIntLiteral = Rc::new(ConstructableByIntLiteral);
+    let ConstructableByIntLiteral = Arc::new(ConstructableByIntLiteral);
     referencible::add_trait(runtime, module, None, &ConstructableByIntLiteral).unwrap();
 
 
@@ -290,17 +319,25 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
     let parse_real_literal_function = FunctionPointer::new_global_function(
         "parse_real_literal",
         FunctionInterface::new_simple(
-            [TypeProto::unit_struct(&String)].into_iter(),
+            [("literal", TypeProto::unit_struct(&String))].into_iter(),
             ConstructableByRealLiteral.create_generic_type("Self")
         ),
     );
     insert_functions(&mut ConstructableByRealLiteral, [
         &parse_real_literal_function
     ].into_iter());
-    let ConstructableByRealLiteral = Rc::new(ConstructableByRealLiteral);
+    let ConstructableByRealLiteral = Arc::new(ConstructableByRealLiteral);
     referencible::add_trait(runtime, module, None, &ConstructableByRealLiteral).unwrap();
 
 
+    let parse_char_literal_function = FunctionPointer::new_global_function(
+        "parse_char_literal",
+        FunctionInterface::new_simple(
+            [("literal", TypeProto::unit_struct(&String))].into_iter(),
+            char_type,
+        ),
+    );
+
     let mut Real = Trait::new_with_self("Real");
     let float_functions = make_real_functions(&Real.create_generic_type("Self"));
     insert_functions(&mut Real, [
@@ -310,20 +347,33 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
     Real.add_simple_parent_requirement(&Number);
     Real.add_simple_parent_requirement(&ConstructableByRealLiteral);
     Real.add_simple_parent_requirement(&ConstructableByIntLiteral);
-    let Real = Rc::new(Real);
+    let Real = Arc::new(Real);
     referencible::add_trait(runtime, module, None, &Real).unwrap();
 
     let mut Int = Trait::new_with_self("Int");
+    let int_functions = make_int_functions(&Int.create_generic_type("Self"));
+    insert_functions(&mut Int, [
+        &int_functions.pow,
+    ].into_iter());
     Int.add_simple_parent_requirement(&Number);
     Int.add_simple_parent_requirement(&ConstructableByIntLiteral);
-    let Int = Rc::new(Int);
+    let Int = Arc::new(Int);
     referencible::add_trait(runtime, module, None, &Int).unwrap();
 
     let mut Natural = Trait::new_with_self("Natural");
     Natural.add_simple_parent_requirement(&Int);
-    let Natural = Rc::new(Natural);
+    let Natural = Arc::new(Natural);
     referencible::add_trait(runtime, module, None, &Natural).unwrap();
 
+    let List = Arc::new(Trait::new_with_self("List"));
+    referencible::add_trait(runtime, module, None, &List).unwrap();
+
+    let Json = Arc::new(Trait::new_with_self("Json"));
+    referencible::add_trait(runtime, module, None, &Json).unwrap();
+
+    let Dyn = Arc::new(Trait::new_with_self("Dyn"));
+    referencible::add_trait(runtime, module, None, &Dyn).unwrap();
+
     Traits {
         Function,
 
@@ -341,6 +391,7 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
         parse_int_literal_function,
         ConstructableByRealLiteral,
         parse_real_literal_function,
+        parse_char_literal_function,
 
         Number,
         Number_functions: number_functions,
@@ -349,6 +400,11 @@ pub fn create(runtime: &mut Runtime, module: &mut Module) -> Traits {
         Real_functions: float_functions,
 
         Int,
+        Int_functions: int_functions,
         Natural,
+
+        List,
+        Json,
+        Dyn,
     }
 }
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::program::functions::FunctionHead;
 use crate::program::generics::TypeForest;
@@ -6,32 +6,32 @@ use crate::program::traits::{RequirementsFulfillment, TraitConformance, TraitCon
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct FunctionBinding {
-    pub function: Rc<FunctionHead>,
-    pub requirements_fulfillment: Rc<RequirementsFulfillment>,
+    pub function: Arc<FunctionHead>,
+    pub requirements_fulfillment: Arc<RequirementsFulfillment>,
 }
 
 impl FunctionBinding {
-    pub fn pure(function: Rc<FunctionHead>) -> Rc<FunctionBinding> {
-        Rc::new(FunctionBinding {
+    pub fn pure(function: Arc<FunctionHead>) -> Arc<FunctionBinding> {
+        Arc::new(FunctionBinding {
             function,
             requirements_fulfillment: RequirementsFulfillment::empty(),
         })
     }
 }
 
-pub fn resolve_binding(binding: &FunctionBinding, type_forest: &TypeForest) -> Rc<FunctionBinding> {
-    Rc::new(FunctionBinding {
-        function: Rc::clone(&binding.function),
+pub fn resolve_binding(binding: &FunctionBinding, type_forest: &TypeForest) -> Arc<FunctionBinding> {
+    Arc::new(FunctionBinding {
+        function: Arc::clone(&binding.function),
         requirements_fulfillment: resolve_fulfillment(&binding.requirements_fulfillment, type_forest),
     })
 }
 
-pub fn resolve_fulfillment(fulfillment: &RequirementsFulfillment, type_forest: &TypeForest) -> Rc<RequirementsFulfillment> {
-    Rc::new(RequirementsFulfillment {
+pub fn resolve_fulfillment(fulfillment: &RequirementsFulfillment, type_forest: &TypeForest) -> Arc<RequirementsFulfillment> {
+    Arc::new(RequirementsFulfillment {
         conformance: fulfillment.conformance.iter().map(|(b, f)| {
             let binding = b.mapping_types(&|t| type_forest.resolve_type(t).unwrap());
-            (Rc::clone(&binding), Rc::new(TraitConformanceWithTail {
-                conformance: Rc::new(TraitConformance {
+            (Arc::clone(&binding), Arc::new(TraitConformanceWithTail {
+                conformance: Arc::new(TraitConformance {
                     binding,
                     function_mapping: f.conformance.function_mapping.clone(),
                 }),
@@ -39,7 +39,7 @@ pub fn resolve_fulfillment(fulfillment: &RequirementsFulfillment, type_forest: &
             }))
         }).collect(),
         generic_mapping: fulfillment.generic_mapping.iter()
-            .map(|(generic, type_)| (Rc::clone(generic), type_forest.resolve_type(type_).unwrap()))
+            .map(|(generic, type_)| (Arc::clone(generic), type_forest.resolve_type(type_).unwrap()))
             .collect(),
     })
 }
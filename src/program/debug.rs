@@ -2,13 +2,13 @@ use std::fmt::{Display, Formatter};
 use display_with_options::with_options;
 
 use itertools::Itertools;
-use uuid::Uuid;
 
+use crate::program::allocation::Mutability;
 use crate::program::expression_tree::ExpressionID;
 use crate::program::function_object::FunctionRepresentation;
 use crate::program::functions::{FunctionInterface, Parameter, ParameterKey};
 use crate::program::generics::TypeForest;
-use crate::program::types::{TypeProto, TypeUnit};
+use crate::program::types::TypeProto;
 
 pub struct MockFunctionInterface<'a> {
     pub representation: FunctionRepresentation,
@@ -27,8 +27,10 @@ impl<'a> Display for MockFunctionInterface<'a> {
                     ParameterKey::Name(n) => n.clone(),
                 },
                 type_: self.types.prototype_binding_alias(expression_id),
+                mutability: Mutability::Immutable,
             }).collect_vec(),
-            return_type: TypeProto::unit(TypeUnit::Generic(Uuid::new_v4())),
+            // The call doesn't know what it returns; void is displayed as no return type.
+            return_type: TypeProto::void(),
             requirements: Default::default(),
             generics: Default::default(),
         };
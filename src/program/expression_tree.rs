@@ -1,9 +1,10 @@
 use std::fmt::Debug;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::program::allocation::ObjectReference;
 use crate::program::calls::FunctionBinding;
 use crate::program::generics::GenericAlias;
+use crate::program::traits::Trait;
 use crate::util::graphs::node_tree::NodeTree;
 
 pub type ExpressionID = GenericAlias;
@@ -25,20 +26,25 @@ pub enum ExpressionOperation {
     IfThenElse,
 
     // TODO We can remove these operations if we just add a getter and setter for every global.
-    GetLocal(Rc<ObjectReference>),
-    SetLocal(Rc<ObjectReference>),
+    GetLocal(Arc<ObjectReference>),
+    SetLocal(Arc<ObjectReference>),
 
     // 0 arguments if no return type is set, otherwise 1
     Return,
 
-    FunctionCall(Rc<FunctionBinding>),
-    PairwiseOperations { calls: Vec<Rc<FunctionBinding>> },
+    FunctionCall(Arc<FunctionBinding>),
+    PairwiseOperations { calls: Vec<Arc<FunctionBinding>> },
 
     // TODO This is required because it has a variable number of arguments (its elements).
     //  This is not supported in functions otherwise, and we'd have to make an exception.
     //  Which might be fair in the future, but for now it's not a pressing concern.
     ArrayLiteral,
     StringLiteral(String),
+
+    /// Whether the value (1 argument) is of the trait's type.
+    TypeCheck(Arc<Trait>),
+    /// The value (1 argument) as the trait's type, failing if it is not.
+    TypeCast(Arc<Trait>),
 }
 
 pub type ExpressionTree = NodeTree<ExpressionID, ExpressionOperation>;
@@ -1,5 +1,6 @@
-use std::collections::HashSet;
-use std::rc::Rc;
+use std::sync::Arc;
+
+use linked_hash_set::LinkedHashSet;
 
 use crate::error::RResult;
 use crate::program::functions::FunctionHead;
@@ -27,7 +28,8 @@ pub struct FunctionRepresentation {
 /// Reference to a multiplicity of functions, usually resolved when attempting to call
 #[derive(Clone, PartialEq, Eq)]
 pub struct FunctionOverload {
-    pub functions: HashSet<Rc<FunctionHead>>,
+    /// In order of declaration, so that candidates are reported stably.
+    pub functions: LinkedHashSet<Arc<FunctionHead>>,
     // Note: If representation is NOT an implicit, the functions within are getters.
     pub representation: FunctionRepresentation,
 }
@@ -40,21 +42,30 @@ impl FunctionRepresentation {
             call_explicity: explicity,
         }
     }
+
+    /// Implicit global functions without parameters (e.g. `def pi -> $Real :: ...`) are constants.
+    /// They are evaluated once, on first use, even if they are impure.
+    pub fn is_global_constant(&self, function: &FunctionHead) -> bool {
+        function.interface.parameters.is_empty()
+            && !function.interface.return_type.unit.is_void()
+            && self.target_type == FunctionTargetType::Global
+            && self.call_explicity == FunctionCallExplicity::Implicit
+    }
 }
 
 impl FunctionOverload {
-    pub fn from(function: &Rc<FunctionHead>, representation: FunctionRepresentation) -> Rc<FunctionOverload> {
-        Rc::new(FunctionOverload {
-            functions: HashSet::from([Rc::clone(function)]),
+    pub fn from(function: &Arc<FunctionHead>, representation: FunctionRepresentation) -> Arc<FunctionOverload> {
+        Arc::new(FunctionOverload {
+            functions: LinkedHashSet::from_iter([Arc::clone(function)]),
             representation,
         })
     }
 
-    pub fn adding_function(&self, function: &Rc<FunctionHead>) -> RResult<Rc<FunctionOverload>> {
-        Ok(Rc::new(FunctionOverload {
+    pub fn adding_function(&self, function: &Arc<FunctionHead>) -> RResult<Arc<FunctionOverload>> {
+        Ok(Arc::new(FunctionOverload {
             functions: self.functions.iter()
                 .chain([function])
-                .map(Rc::clone)
+                .map(Arc::clone)
                 .collect(),
             representation: self.representation.clone(),
         }))
@@ -1,11 +1,12 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
-use display_with_options::{DebugWithOptions, DisplayWithOptions, with_options};
+use std::sync::Arc;
+use display_with_options::{DebugWithOptions, with_options};
 
 use uuid::Uuid;
 
+use crate::program::allocation::Mutability;
 use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
 use crate::program::traits::{Trait, TraitBinding};
 use crate::program::types::TypeProto;
@@ -22,7 +23,7 @@ pub enum FunctionType {
     /// A normal function.
     Static,
     /// Not a real function; instead, it refers to a function of some requirement.
-    Polymorphic { assumed_requirement: Rc<TraitBinding>, abstract_function: Rc<FunctionHead> },
+    Polymorphic { assumed_requirement: Arc<TraitBinding>, abstract_function: Arc<FunctionHead> },
 }
 
 /// The 'head' of a function. It is identifiable by its ID and has an interface.
@@ -31,7 +32,7 @@ pub enum FunctionType {
 pub struct FunctionHead {
     pub function_id: Uuid,
     pub function_type: FunctionType,
-    pub interface: Rc<FunctionInterface>,
+    pub interface: Arc<FunctionInterface>,
 }
 
 /// A parameter as visible from the outside.
@@ -41,7 +42,9 @@ pub struct FunctionHead {
 pub struct Parameter {
     pub external_key: ParameterKey,
     pub internal_name: String,
-    pub type_: Rc<TypeProto>,
+    pub type_: Arc<TypeProto>,
+    /// Mutable parameters may be modified by the function, and only accept mutable arguments.
+    pub mutability: Mutability,
 }
 
 /// Machine interface of the function. Everything needed to call it.
@@ -50,48 +53,62 @@ pub struct FunctionInterface {
     /// Parameters to the function
     pub parameters: Vec<Parameter>,
     /// Type of what the function returns
-    pub return_type: Rc<TypeProto>,
+    pub return_type: Arc<TypeProto>,
 
     /// Requirements for parameters and the return type.
-    pub requirements: HashSet<Rc<TraitBinding>>,
+    pub requirements: HashSet<Arc<TraitBinding>>,
     /// All internally used generics. These are not guaranteed to not exist elsewhere,
     /// but for the purposes of this interface, they are to be regarded as generics.
-    pub generics: HashMap<String, Rc<Trait>>,
+    pub generics: HashMap<String, Arc<Trait>>,
 }
 
 impl FunctionInterface {
-    pub fn new_provider<'a>(return_type: &Rc<TypeProto>, requirements: Vec<&Rc<TraitBinding>>) -> Rc<FunctionInterface> {
-        Rc::new(FunctionInterface {
+    /// Whether both interfaces are called the same way.
+    /// Internal parameter names are up to each implementation, so they don't need to match.
+    pub fn is_callable_as(&self, other: &FunctionInterface) -> bool {
+        self.return_type == other.return_type
+            && self.requirements == other.requirements
+            && self.generics == other.generics
+            && self.parameters.len() == other.parameters.len()
+            && self.parameters.iter().zip(other.parameters.iter())
+                .all(|(lhs, rhs)| lhs.external_key == rhs.external_key && lhs.type_ == rhs.type_ && lhs.mutability == rhs.mutability)
+    }
+
+    pub fn new_provider(return_type: &Arc<TypeProto>, requirements: Vec<&Arc<TraitBinding>>) -> Arc<FunctionInterface> {
+        Arc::new(FunctionInterface {
             parameters: vec![],
             return_type: return_type.clone(),
-            requirements: requirements.into_iter().map(Rc::clone).collect(),
+            requirements: requirements.into_iter().map(Arc::clone).collect(),
             generics: Default::default(),
         })
     }
 
-    pub fn new_consumer<'a>(parameter_type: &Rc<TypeProto>, requirements: Vec<&Rc<TraitBinding>>) -> Rc<FunctionInterface> {
-        Rc::new(FunctionInterface {
+    pub fn new_consumer(parameter_type: &Arc<TypeProto>, requirements: Vec<&Arc<TraitBinding>>) -> Arc<FunctionInterface> {
+        Arc::new(FunctionInterface {
             parameters: vec![Parameter {
                 external_key: ParameterKey::Positional,
                 internal_name: "arg".to_string(),
                 type_: parameter_type.clone(),
+                mutability: Mutability::Immutable,
             }],
             return_type: TypeProto::void(),
-            requirements: requirements.into_iter().map(Rc::clone).collect(),
+            requirements: requirements.into_iter().map(Arc::clone).collect(),
             generics: Default::default(),
         })
     }
 
-    pub fn new_operator<'a>(count: usize, parameter_type: &Rc<TypeProto>, return_type: &Rc<TypeProto>) -> Rc<FunctionInterface> {
-        let parameters: Vec<Parameter> = (0..count)
-            .map(|x| { Parameter {
+    /// An operator function, taking positional parameters of the same type.
+    pub fn new_operator(parameter_names: &[&str], parameter_type: &Arc<TypeProto>, return_type: &Arc<TypeProto>) -> Arc<FunctionInterface> {
+        let parameters: Vec<Parameter> = parameter_names.iter()
+            .map(|name| { Parameter {
                 external_key: ParameterKey::Positional,
-                internal_name: format!("p{}", x),
+                internal_name: name.to_string(),
                 type_: parameter_type.clone(),
+                mutability: Mutability::Immutable,
             }
         }).collect();
 
-        Rc::new(FunctionInterface {
+        Arc::new(FunctionInterface {
             parameters,
             return_type: return_type.clone(),
             requirements: Default::default(),
@@ -99,17 +116,17 @@ impl FunctionInterface {
         })
     }
 
-    pub fn new_simple<'a, I>(parameter_types: I, return_type: Rc<TypeProto>) -> Rc<FunctionInterface> where I: Iterator<Item=Rc<TypeProto>> {
-        let parameters: Vec<Parameter> = parameter_types
-            .enumerate()
-            .map(|(i, x)| Parameter {
+    pub fn new_simple<'a, I>(parameters: I, return_type: Arc<TypeProto>) -> Arc<FunctionInterface> where I: Iterator<Item=(&'a str, Arc<TypeProto>)> {
+        let parameters: Vec<Parameter> = parameters
+            .map(|(name, x)| Parameter {
                 external_key: ParameterKey::Positional,
-                internal_name: format!("p{}", i),
+                internal_name: name.to_string(),
                 type_: x.clone(),
+                mutability: Mutability::Immutable,
             })
             .collect();
 
-        Rc::new(FunctionInterface {
+        Arc::new(FunctionInterface {
             parameters,
             return_type: return_type.clone(),
             requirements: Default::default(),
@@ -117,21 +134,22 @@ impl FunctionInterface {
         })
     }
 
-    pub fn new_member<'a, I>(self_type: Rc<TypeProto>, parameter_types: I, return_type: Rc<TypeProto>) -> Rc<FunctionInterface> where I: Iterator<Item=Rc<TypeProto>> {
+    pub fn new_member<'a, I>(self_type: Arc<TypeProto>, parameters: I, return_type: Arc<TypeProto>) -> Arc<FunctionInterface> where I: Iterator<Item=(&'a str, Arc<TypeProto>)> {
         let parameters: Vec<Parameter> = [Parameter {
                 external_key: ParameterKey::Positional,
                 internal_name: "self".to_string(),
                 type_: self_type,
-            }].into_iter().chain(parameter_types
-            .enumerate()
-            .map(|(i, x)| Parameter {
+                mutability: Mutability::Immutable,
+            }].into_iter().chain(parameters
+            .map(|(name, x)| Parameter {
                 external_key: ParameterKey::Positional,
-                internal_name: format!("p{}", i),
+                internal_name: name.to_string(),
                 type_: x.clone(),
+                mutability: Mutability::Immutable,
             }))
             .collect();
 
-        Rc::new(FunctionInterface {
+        Arc::new(FunctionInterface {
             parameters,
             return_type: return_type.clone(),
             requirements: Default::default(),
@@ -141,12 +159,12 @@ impl FunctionInterface {
 }
 
 impl FunctionHead {
-    pub fn new_static(interface: Rc<FunctionInterface>) -> Rc<FunctionHead> {
+    pub fn new_static(interface: Arc<FunctionInterface>) -> Arc<FunctionHead> {
         Self::new(interface, FunctionType::Static)
     }
 
-    pub fn new(interface: Rc<FunctionInterface>, function_type: FunctionType) -> Rc<FunctionHead> {
-        Rc::new(FunctionHead {
+    pub fn new(interface: Arc<FunctionInterface>, function_type: FunctionType) -> Arc<FunctionHead> {
+        Arc::new(FunctionHead {
             function_id: Uuid::new_v4(),
             interface,
             function_type
@@ -162,11 +180,12 @@ impl FunctionHead {
 }
 
 impl Parameter {
-    pub fn mapping_type(&self,  map: &dyn Fn(&Rc<TypeProto>) -> Rc<TypeProto>) -> Parameter {
+    pub fn mapping_type(&self,  map: &dyn Fn(&Arc<TypeProto>) -> Arc<TypeProto>) -> Parameter {
         Parameter {
             external_key: self.external_key.clone(),
             internal_name: self.internal_name.clone(),
             type_: map(&self.type_),
+            mutability: self.mutability,
         }
     }
 }
@@ -202,15 +221,19 @@ impl Display for ParameterKey {
 
 impl Debug for Parameter {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut_keyword = match self.mutability {
+            Mutability::Immutable => "",
+            Mutability::Mutable => "mut ",
+        };
         match &self.external_key {
             ParameterKey::Positional => {
-                write!(f, "{} '{:?}", self.internal_name, self.type_)
+                write!(f, "{}{} '{:?}", mut_keyword, self.internal_name, self.type_)
             }
             ParameterKey::Name(n) => {
                 if n != &self.internal_name {
-                    write!(f, "{}: {} '{:?}", n, self.internal_name, self.type_)
+                    write!(f, "{}: {}{} '{:?}", n, mut_keyword, self.internal_name, self.type_)
                 } else {
-                    write!(f, "{}: '{:?}", n, self.type_)
+                    write!(f, "{}: {}'{:?}", n, mut_keyword, self.type_)
                 }
             }
         }
@@ -231,8 +254,9 @@ impl DebugWithOptions<FunctionRepresentation> for FunctionInterface {
     fn fmt(&self, fmt: &mut Formatter<'_>, representation: &FunctionRepresentation) -> std::fmt::Result {
         let mut head = 0;
 
-        if representation.target_type == FunctionTargetType::Member {
-            write!(fmt, "({:?}).", self.parameters.get(head).unwrap())?;
+        // Members without parameters are shown by their plain name.
+        if let (FunctionTargetType::Member, Some(target)) = (&representation.target_type, self.parameters.first()) {
+            write!(fmt, "({:?}).", target)?;
             head += 1;
         }
 
@@ -1,13 +1,13 @@
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::{Itertools, zip_eq};
 use uuid::Uuid;
 
 use crate::error::{RResult, RuntimeError, TryCollectMany};
 use crate::program::traits::Trait;
-use crate::program::types::{TypeProto, TypeUnit};
+use crate::program::types::{MAX_TYPE_DEPTH, too_deeply_nested, TypeProto, TypeUnit};
 
 pub type GenericIdentity = Uuid;
 pub type GenericAlias = Uuid;
@@ -19,9 +19,24 @@ pub struct TypeForest {
     /// should have pointed to that generics' identity instead.
     pub identity_to_type: HashMap<GenericIdentity, TypeUnit>,
     pub identity_to_arguments: HashMap<GenericIdentity, Vec<GenericIdentity>>,
+    /// Identities that may use the key as an argument, so that relinking doesn't have to search all arguments.
+    /// Entries are never removed, so users check the parent's arguments.
+    identity_to_parents: HashMap<GenericIdentity, HashSet<GenericIdentity>>,
 
     pub alias_to_identity: HashMap<GenericAlias, GenericIdentity>,
     pub identity_to_alias: HashMap<GenericIdentity, HashSet<GenericAlias>>,
+
+    /// Identities whose binding changed since the last call to take_changed_identities.
+    /// This includes types whose arguments changed.
+    /// Identities are tracked instead of their aliases, because one identity can collect the aliases
+    ///  of a whole chain of calls.
+    changed_identities: HashSet<GenericIdentity>,
+}
+
+impl Default for TypeForest {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TypeForest {
@@ -29,8 +44,10 @@ impl TypeForest {
         TypeForest {
             identity_to_type: HashMap::new(),
             identity_to_arguments: HashMap::new(),
+            identity_to_parents: HashMap::new(),
             alias_to_identity: HashMap::new(),
             identity_to_alias: HashMap::new(),
+            changed_identities: HashSet::new(),
         }
     }
 
@@ -54,17 +71,29 @@ impl TypeForest {
         self.identity_to_type.get(identity)
     }
 
-    pub fn resolve_type(&self, type_: &TypeProto) -> RResult<Rc<TypeProto>> {
+    pub fn resolve_type(&self, type_: &TypeProto) -> RResult<Arc<TypeProto>> {
+        self.resolve_type_at_depth(type_, 0)
+    }
+
+    fn resolve_type_at_depth(&self, type_: &TypeProto, depth: usize) -> RResult<Arc<TypeProto>> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(too_deeply_nested())
+        }
+
         match &type_.unit {
-            TypeUnit::Generic(alias) => self.resolve_binding_alias(alias).map(|x| x.clone()),
-            _ => Ok(Rc::new(TypeProto {
+            TypeUnit::Generic(alias) => self.resolve_binding_alias_at_depth(alias, depth),
+            _ => Ok(Arc::new(TypeProto {
                 unit: type_.unit.clone(),
-                arguments: type_.arguments.iter().map(|x| self.resolve_type(x)).try_collect_many()?
+                arguments: type_.arguments.iter().map(|x| self.resolve_type_at_depth(x, depth + 1)).try_collect_many()?
             }))
         }
     }
 
-    pub fn resolve_binding_alias(&self, alias: &GenericAlias) -> RResult<Rc<TypeProto>> {
+    pub fn resolve_binding_alias(&self, alias: &GenericAlias) -> RResult<Arc<TypeProto>> {
+        self.resolve_binding_alias_at_depth(alias, 0)
+    }
+
+    fn resolve_binding_alias_at_depth(&self, alias: &GenericAlias, depth: usize) -> RResult<Arc<TypeProto>> {
         let Some(identity) = self.alias_to_identity.get(alias) else {
             return Err(RuntimeError::error(format!("Unknown generic: {}", alias).as_str()).to_array())
         };
@@ -73,45 +102,69 @@ impl TypeForest {
             return Ok(TypeProto::unit(TypeUnit::Generic(*alias)))
         };
 
-        return Ok(Rc::new(TypeProto {
+        Ok(Arc::new(TypeProto {
             unit: binding.clone(),
-            arguments: self.identity_to_arguments.get(&identity).unwrap().iter()
-                .map(|x| self.resolve_binding_alias(x))
+            arguments: self.identity_to_arguments.get(identity).unwrap().iter()
+                .map(|x| self.resolve_identity(x, depth + 1))
                 .try_collect_many()?
         }))
     }
 
-    pub fn prototype_binding_alias(&self, alias: &GenericAlias) -> Rc<TypeProto> {
+    pub fn prototype_binding_alias(&self, alias: &GenericAlias) -> Arc<TypeProto> {
         let Some(identity) = self.alias_to_identity.get(alias) else {
             return TypeProto::unit(TypeUnit::Generic(*alias));
         };
 
+        match self.identity_to_type.get(identity) {
+            None => TypeProto::unit(TypeUnit::Generic(*alias)),
+            Some(_) => self.resolve_identity(identity, 0).unwrap(),
+        }
+    }
+
+    /// Arguments are stored as identities, not aliases. Unbound identities are represented
+    ///  by any of their aliases.
+    fn resolve_identity(&self, identity: &GenericIdentity, depth: usize) -> RResult<Arc<TypeProto>> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(too_deeply_nested())
+        }
+
         let Some(binding) = self.identity_to_type.get(identity) else {
-            return TypeProto::unit(TypeUnit::Generic(*alias));
+            let Some(alias) = self.identity_to_alias.get(identity).and_then(|aliases| aliases.iter().next()) else {
+                return Err(RuntimeError::error(format!("Unknown generic identity: {}", identity).as_str()).to_array())
+            };
+            return Ok(TypeProto::unit(TypeUnit::Generic(*alias)))
         };
 
-        return Rc::new(TypeProto {
+        Ok(Arc::new(TypeProto {
             unit: binding.clone(),
-            arguments: self.identity_to_arguments.get(&identity).unwrap().iter()
-                .map(|x| self.prototype_binding_alias(x))
-                .collect()
-        })
+            arguments: self.identity_to_arguments.get(identity).unwrap().iter()
+                .map(|x| self.resolve_identity(x, depth + 1))
+                .try_collect_many()?
+        }))
+    }
+
+    pub fn take_changed_identities(&mut self) -> HashSet<GenericIdentity> {
+        std::mem::take(&mut self.changed_identities)
+    }
+
+    pub fn identity_of(&self, alias: &GenericAlias) -> Option<GenericIdentity> {
+        self.alias_to_identity.get(alias).copied()
     }
 
-    pub fn merge_all(&mut self, types: &Vec<GenericAlias>) -> RResult<GenericAlias> {
+    pub fn merge_all(&mut self, types: &[GenericAlias]) -> RResult<GenericAlias> {
         if types.is_empty() {
             // No elements, so we can be whatever we want to be!
             let id = Uuid::new_v4();
-            self.register(id.clone());
+            self.register(id);
             return Ok(id)
         }
 
-        let reference = types[0].clone();
+        let reference = types[0];
         for other in types.iter().skip(1) {
-            self.merge_identities(reference.clone(), other.clone())?;
+            self.merge_identities(reference, *other, 0)?;
         }
 
-        return Ok(reference)
+        Ok(reference)
     }
 
     pub fn rebind(&mut self, generic: GenericAlias, t: &TypeProto) -> RResult<()> {
@@ -123,11 +176,11 @@ impl TypeForest {
         self.bind_identity(*identity, t)
     }
 
-    pub fn rebind_structs_as_generic(&mut self, structs: &HashMap<Rc<Trait>, Rc<TypeProto>>) -> RResult<()>{
-        let map: HashMap<_, _> = structs.into_iter().map(|(struct_, type_)| {
+    pub fn rebind_structs_as_generic(&mut self, structs: &HashMap<Arc<Trait>, Arc<TypeProto>>) -> RResult<()>{
+        let map: HashMap<_, _> = structs.iter().map(|(struct_, type_)| {
             let identity = self._register(struct_.id);
             self.bind_identity(identity, type_)?;
-            Ok::<(&Rc<Trait>, Uuid), Vec<RuntimeError>>((struct_, identity))
+            Ok::<(&Arc<Trait>, Uuid), Vec<RuntimeError>>((struct_, identity))
         }).try_collect_many()?;
 
         let mut replace_map = HashMap::new();
@@ -149,20 +202,23 @@ impl TypeForest {
 
     fn _register(&mut self, alias: GenericAlias) -> GenericIdentity {
         if let Some(existing) = self.alias_to_identity.get(&alias) {
-            return existing.clone()
+            return *existing
         }
 
         let new = Uuid::new_v4();
-        self.alias_to_identity.insert(alias, new.clone());
-        self.identity_to_alias.insert(new.clone(), HashSet::from([alias.clone()]));
-        return new
+        self.alias_to_identity.insert(alias, new);
+        self.identity_to_alias.insert(new, HashSet::from([alias]));
+        new
     }
 
     fn bind_identity(&mut self, identity: GenericIdentity, t: &TypeProto) -> RResult<()> {
+        // insert_new_identity recurses through the type's arguments.
+        t.check_depth()?;
+
         // TODO This could be done faster by not creating a new ID,
         //  but for now this approach saves us boilerplate / duplicate code.
         let new_id = self.insert_new_identity(t);
-        self.merge_identities(identity, new_id)?;
+        self.merge_identities(identity, new_id, 0)?;
         Ok(())
     }
 
@@ -170,17 +226,20 @@ impl TypeForest {
         match &t.unit {
             TypeUnit::Generic(alias) => {
                 // If the generic already has an identity, return that. Otherwise, make one.
-                self._register(alias.clone())
+                self._register(*alias)
             },
             _ => {
                 let identity = GenericIdentity::new_v4();
-                self.identity_to_type.insert(identity.clone(), t.unit.clone());
-                self.identity_to_alias.insert(identity.clone(), HashSet::new());
+                self.identity_to_type.insert(identity, t.unit.clone());
+                self.identity_to_alias.insert(identity, HashSet::new());
 
                 let arguments = t.arguments.iter()
                     .map(|arg| self.insert_new_identity(arg))
-                    .collect();
+                    .collect_vec();
 
+                for argument in arguments.iter() {
+                    self.identity_to_parents.entry(*argument).or_default().insert(identity);
+                }
                 self.identity_to_arguments.insert(identity, arguments);
 
                 identity
@@ -210,17 +269,33 @@ impl TypeForest {
             }
         }
 
-        return true;
+        true
     }
 
-    fn merge_identities(&mut self, lhs: GenericIdentity, rhs: GenericIdentity) -> RResult<GenericIdentity> {
+    fn merge_identities(&mut self, lhs: GenericIdentity, rhs: GenericIdentity, depth: usize) -> RResult<GenericIdentity> {
         if lhs == rhs {
             return Ok(lhs)
         }
+        if depth > MAX_TYPE_DEPTH {
+            return Err(too_deeply_nested())
+        }
+        // Relinking moves all of rhs' aliases, so the identity with fewer aliases is merged into the other.
+        //  Otherwise, merging into a long chain of calls would move the whole chain every time.
+        let (lhs, rhs) = match self.identity_to_alias[&rhs].len() > self.identity_to_alias[&lhs].len() {
+            true => (rhs, lhs),
+            false => (lhs, rhs),
+        };
+
+        // Binding a type that is already bound merges in a new identity without aliases, which changes nothing.
+        //  Marking it anyway would wake ambiguities that re-bind their types on every attempt, forever.
+        let is_change = !self.identity_to_alias[&rhs].is_empty()
+            || (!self.identity_to_type.contains_key(&lhs) && self.identity_to_type.contains_key(&rhs));
 
-        // TODO We default to "into lhs" out of convenience, but it may be faster to use rhs sometimes
         // Merge rhs aliases / arguments into lhs
         self.relink_identity(rhs, lhs);
+        if is_change {
+            self.mark_changed(lhs);
+        }
 
         // Merge types
         let rhs_type = self.identity_to_type.remove(&rhs);
@@ -237,13 +312,16 @@ impl TypeForest {
                     self.identity_to_arguments.get(&lhs).unwrap().clone(),
                     self.identity_to_arguments.remove(&rhs).unwrap()
                 ) {
-                    self.merge_identities(arg, r_arg)?;
+                    self.merge_identities(arg, r_arg, depth + 1)?;
                 }
             }
             (Entry::Vacant(lhs_entry), Some(rhs_type)) => {
                 // No left entry; we can just move right into left.
                 lhs_entry.insert(rhs_type);
                 let rhs_args = self.identity_to_arguments.remove(&rhs).unwrap();
+                for argument in rhs_args.iter() {
+                    self.identity_to_parents.entry(*argument).or_default().insert(lhs);
+                }
                 self.identity_to_arguments.insert(lhs, rhs_args);
             }
             (_, None) => {}  // Nothing to merge, right is empty.
@@ -252,17 +330,37 @@ impl TypeForest {
         Ok(lhs)
     }
 
+    /// Marks the identity as changed, and any identity using it as an argument.
+    fn mark_changed(&mut self, identity: GenericIdentity) {
+        let mut marked = HashSet::from([identity]);
+        let mut pending = vec![identity];
+        while let Some(identity) = pending.pop() {
+            self.changed_identities.insert(identity);
+
+            for parent in self.identity_to_parents.get(&identity).into_iter().flatten() {
+                let is_parent = self.identity_to_arguments.get(parent).is_some_and(|args| args.contains(&identity));
+                if is_parent && marked.insert(*parent) {
+                    pending.push(*parent);
+                }
+            }
+        }
+    }
+
     fn relink_identity(&mut self, source: GenericIdentity, target: GenericIdentity) {
-        // TODO This is pretty naive; maybe we also want a reverse map here too?
-        for args in self.identity_to_arguments.values_mut() {
-            if args.contains(&source) {
-                *args = args.iter().map(|x| if *x == source { target } else { *x } ).collect();
+        let parents = self.identity_to_parents.remove(&source).unwrap_or_default();
+        for parent in parents.iter() {
+            if let Some(args) = self.identity_to_arguments.get_mut(parent) {
+                args.iter_mut().filter(|x| **x == source).for_each(|x| *x = target);
             }
         }
+        self.identity_to_parents.entry(target).or_default().extend(parents);
+        if self.changed_identities.remove(&source) {
+            self.changed_identities.insert(target);
+        }
 
         let source_aliases = self.identity_to_alias.remove(&source).unwrap();
         for alias in source_aliases.iter() {
-            self.alias_to_identity.insert(alias.clone(), target);
+            self.alias_to_identity.insert(*alias, target);
         }
         self.identity_to_alias.get_mut(&target).unwrap().extend(source_aliases);
     }
@@ -1,14 +1,19 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::fmt::Write;
+use std::ops::Range;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use linked_hash_map::LinkedHashMap;
 
 use crate::error::{RResult, RuntimeError};
 use crate::program::allocation::ObjectReference;
-use crate::program::expression_tree::ExpressionTree;
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation, ExpressionTree};
 use crate::program::functions::FunctionHead;
 use crate::program::generics::TypeForest;
 use crate::program::primitives;
 use crate::program::traits::{RequirementsAssumption, Trait};
-use crate::source::StructInfo;
+use crate::source::{EnumInfo, Source, StructInfo};
 
 #[derive(Clone)]
 pub enum FunctionLogic {
@@ -18,15 +23,65 @@ pub enum FunctionLogic {
 
 #[derive(Clone)]
 pub struct FunctionImplementation {
-    pub head: Rc<FunctionHead>,
+    pub head: Arc<FunctionHead>,
 
     pub requirements_assumption: Box<RequirementsAssumption>,
 
     pub expression_tree: Box<ExpressionTree>,
     pub type_forest: Box<TypeForest>,
 
-    pub parameter_locals: Vec<Rc<ObjectReference>>,
-    pub locals_names: HashMap<Rc<ObjectReference>, String>,
+    pub parameter_locals: Vec<Arc<ObjectReference>>,
+    /// In order of declaration, so that generated names are stable.
+    pub locals_names: LinkedHashMap<Arc<ObjectReference>, String>,
+    /// Where each statement of a block was declared, for static analysis.
+    pub statement_ranges: HashMap<ExpressionID, Range<usize>>,
+    /// Where each expression was written in the source, if it was written explicitly.
+    pub expression_ranges: HashMap<ExpressionID, Range<usize>>,
+    /// How often the resolver attempted to resolve ambiguities in the body.
+    pub ambiguity_attempts: usize,
+    /// Warnings found while resolving the body, like calls to deprecated functions.
+    pub warnings: Vec<RuntimeError>,
+}
+
+impl FunctionImplementation {
+    /// Renders the expression tree with one line per expression, indented by depth, for debugging.
+    /// Each line names the operation, then the expression's type, e.g. `FunctionCall common.math._add 'Int32`.
+    /// Called functions are qualified by the module declaring them, if known.
+    pub fn dump(&self, source: &Source) -> String {
+        let mut output = String::new();
+        self.dump_expression(&mut output, source, self.expression_tree.root, 0);
+        output
+    }
+
+    fn dump_expression(&self, output: &mut String, source: &Source, expression: ExpressionID, depth: usize) {
+        let function_name = |function: &Arc<FunctionHead>| source.qualified_name(function);
+        let local_name = |object: &Arc<ObjectReference>| self.locals_names.get(object).cloned().unwrap_or_else(|| "?".to_string());
+
+        let operation = match &self.expression_tree.values[&expression] {
+            ExpressionOperation::Block => "Block".to_string(),
+            ExpressionOperation::IfThenElse => "IfThenElse".to_string(),
+            ExpressionOperation::GetLocal(object) => format!("GetLocal {}", local_name(object)),
+            ExpressionOperation::SetLocal(object) => format!("SetLocal {}", local_name(object)),
+            ExpressionOperation::Return => "Return".to_string(),
+            ExpressionOperation::FunctionCall(binding) => format!("FunctionCall {}", function_name(&binding.function)),
+            ExpressionOperation::PairwiseOperations { calls } => {
+                format!("PairwiseOperations {}", calls.iter().map(|call| function_name(&call.function)).join(", "))
+            }
+            ExpressionOperation::ArrayLiteral => "ArrayLiteral".to_string(),
+            ExpressionOperation::StringLiteral(string) => format!("StringLiteral {:?}", string),
+            ExpressionOperation::TypeCheck(trait_) => format!("TypeCheck {}", trait_.name),
+            ExpressionOperation::TypeCast(trait_) => format!("TypeCast {}", trait_.name),
+        };
+        let type_ = match self.type_forest.resolve_binding_alias(&expression) {
+            Ok(type_) => format!("{:?}", type_),
+            Err(_) => "?".to_string(),
+        };
+        writeln!(output, "{}{} '{}", "  ".repeat(depth), operation, type_).unwrap();
+
+        for child in self.expression_tree.children[&expression].iter() {
+            self.dump_expression(output, source, *child, depth + 1);
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -34,12 +89,23 @@ pub enum FunctionLogicDescriptor {
     /// This function was not described by the implementer and is expected not to be called,
     ///  or to be injected by a transpiler.
     Stub,
-    TraitProvider(Rc<Trait>),
-    FunctionProvider(Rc<FunctionHead>),
+    TraitProvider(Arc<Trait>),
+    FunctionProvider(Arc<FunctionHead>),
     PrimitiveOperation { operation: PrimitiveOperation, type_: primitives::Type },
-    Constructor(Rc<StructInfo>),
-    GetMemberField(Rc<StructInfo>, Rc<ObjectReference>),
-    SetMemberField(Rc<StructInfo>, Rc<ObjectReference>),
+    Constructor(Arc<StructInfo>),
+    GetMemberField(Arc<StructInfo>, Arc<ObjectReference>),
+    SetMemberField(Arc<StructInfo>, Arc<ObjectReference>),
+    ListOperation(ListOperation),
+    JsonOperation(JsonOperation),
+    /// Returns the enum's variant at the index, from its metatype.
+    EnumVariant(Arc<EnumInfo>, usize),
+    /// Describes a type. Calls are replaced by the description during monomorphization,
+    ///  when the type is known.
+    Reflection(Reflection),
+    /// Boxes its argument as a Dyn, along with the functions that implement the trait for it.
+    DynBox(Vec<Arc<FunctionHead>>),
+    /// Calls the function at the index of the boxed value's vtable.
+    DynDispatch(usize),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -55,7 +121,56 @@ pub enum PrimitiveOperation {
     GreaterThanOrEqual, LesserThanOrEqual,
     ParseIntString,
     ParseRealString,
+    ParseCharString,
     ToString,
+    /// Fails for codes that aren't unicode scalar values.
+    CharFromCode,
+    CharToCode,
+}
+
+impl PrimitiveOperation {
+    /// Whether the operation can fail at runtime, e.g. by dividing by zero or parsing an invalid string.
+    pub fn can_fail(&self) -> bool {
+        matches!(self, PrimitiveOperation::Divide | PrimitiveOperation::Modulo | PrimitiveOperation::Exp
+            | PrimitiveOperation::ParseIntString | PrimitiveOperation::ParseRealString | PrimitiveOperation::ParseCharString
+            | PrimitiveOperation::CharFromCode)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ListOperation {
+    New,
+    Push, Pop,
+    Length,
+    Get, Set,
+    /// Creates a list of the chars of a string.
+    FromChars,
+}
+
+/// Operations on parsed JSON values.
+/// Missing values are represented by JSON null, so lookups never fail.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum JsonOperation {
+    /// Fails if the string isn't valid JSON.
+    Parse,
+    /// The object's value for a key, or null.
+    Get,
+    /// The array's element at an index, or null.
+    At,
+    /// The number of elements of an array or object, or 0.
+    Length,
+    /// The name of the value's kind, e.g. "object" or "null".
+    Kind,
+    /// Fail if the value is of another kind.
+    AsString, AsFloat, AsBool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reflection {
+    /// The name of the argument's type, as a String.
+    TypeName,
+    /// The names of the fields of the struct, from its metatype, as a List of Strings.
+    FieldsOf,
 }
 
 impl FunctionLogic {
@@ -80,3 +195,27 @@ impl FunctionLogic {
         }
     }
 }
+
+impl FunctionLogicDescriptor {
+    /// Whether calls to the function are free of side effects.
+    /// Pure calls may still fail (see `PrimitiveOperation::can_fail`), so they can't always be moved.
+    /// Stubs are assumed to be intrinsics with side effects, such as I/O.
+    /// Objects have identity and may be mutated, so constructing them or accessing their contents is not pure.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            FunctionLogicDescriptor::TraitProvider(_) => true,
+            FunctionLogicDescriptor::FunctionProvider(_) => true,
+            FunctionLogicDescriptor::PrimitiveOperation { .. } => true,
+            FunctionLogicDescriptor::Stub => false,
+            FunctionLogicDescriptor::Constructor(_) => false,
+            FunctionLogicDescriptor::GetMemberField(_, _) => false,
+            FunctionLogicDescriptor::SetMemberField(_, _) => false,
+            FunctionLogicDescriptor::ListOperation(_) => false,
+            FunctionLogicDescriptor::JsonOperation(_) => false,
+            FunctionLogicDescriptor::EnumVariant(_, _) => true,
+            FunctionLogicDescriptor::Reflection(_) => true,
+            FunctionLogicDescriptor::DynBox(_) => false,
+            FunctionLogicDescriptor::DynDispatch(_) => false,
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashSet;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use uuid::Uuid;
-use crate::parser::grammar::{Pattern, PrecedenceGroup};
+use crate::error::RuntimeError;
+use crate::parser::grammar::{Grammar, Pattern, PrecedenceGroup};
 
 use crate::program::functions::FunctionHead;
 use crate::program::traits::TraitGraph;
@@ -15,26 +16,93 @@ pub fn module_name(name: &str) -> ModuleName {
     name.split(".").map(ToString::to_string).collect_vec()
 }
 
+#[derive(Clone)]
 pub struct Module {
     pub id: Uuid,
     pub name: ModuleName,
 
-    /// For each trait, its metatype getter function.
-    pub included_modules: Vec<Vec<String>>,
+    /// Modules re-exported to importers through include!, with the namespace
+    ///  they are re-exported under, if any.
+    pub included_modules: Vec<(ModuleName, Option<String>)>,
+    /// Modules imported through use! or include!, in order.
+    pub imported_modules: Vec<ModuleName>,
 
-    pub precedence_order: Option<Vec<Rc<PrecedenceGroup>>>,
-    pub patterns: HashSet<Rc<Pattern<Rc<FunctionHead>>>>,
+    pub precedence_order: Option<Vec<Arc<PrecedenceGroup>>>,
+    pub patterns: HashSet<Arc<Pattern<Arc<FunctionHead>>>>,
+    /// The grammar in effect for the module's code, including imported patterns.
+    pub grammar: Grammar<Arc<FunctionHead>>,
     pub trait_conformance: Box<TraitGraph>,
 
     /// Functions that are directly referencible.
     /// Usually, these are just getters for traits, function objects etc.
-    pub exposed_functions: HashSet<Rc<FunctionHead>>,
+    pub exposed_functions: HashSet<Arc<FunctionHead>>,
 
     /// These come from decorators.
     /// Collecting all decorated functions allows us to fail late - the rest of the code is still
     ///  valid even if multiple main! functions are declared! We just cannot run them as 'main'.
-    pub main_functions: Vec<Rc<FunctionHead>>,
-    pub transpile_functions: Vec<Rc<FunctionHead>>,
+    pub main_functions: Vec<Arc<FunctionHead>>,
+    pub transpile_functions: Vec<Arc<FunctionHead>>,
+    /// Functions whose bodies were resolved from this module's source.
+    pub implemented_functions: Vec<Arc<FunctionHead>>,
+
+    /// The module's global statements, in order, to tell which changed when the source is edited.
+    pub declarations: Vec<Declaration>,
+
+    /// Problems found while resolving that don't prevent the module from loading.
+    pub warnings: Vec<RuntimeError>,
+
+    /// Declared through strict! and allow!. Applies only to this module's own code, not to its importers.
+    pub settings: ModuleSettings,
+}
+
+/// Structural hashes of a global statement, ignoring positions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Declaration {
+    /// The whole statement, except the body of function declarations.
+    pub interface_hash: u64,
+    /// For function declarations with a body, the function and the hash of its body.
+    pub body: Option<(Arc<FunctionHead>, u64)>,
+}
+
+/// Opt-in checks, each denying code that is otherwise accepted.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ModuleSettings {
+    /// Number literals whose type nothing decides are an error, rather than an Int64 or Float64.
+    pub no_implicit_number_defaults: bool,
+    /// Declaring a local that hides another local of the same name is an error.
+    pub deny_shadowing: bool,
+    /// Discarding a non-void value in the middle of a block is an error.
+    pub deny_unused_results: bool,
+    /// Declaring (or importing) a trait conformance that is already declared is an error.
+    pub deny_duplicate_conformances: bool,
+    /// Using deprecated functions and traits doesn't warn. Declared through allow!.
+    pub allow_deprecated: bool,
+}
+
+impl ModuleSettings {
+    pub const FLAGS: [&'static str; 4] = ["no_implicit_number_defaults", "deny_shadowing", "deny_unused_results", "deny_duplicate_conformances"];
+    pub const ALLOWED_WARNINGS: [&'static str; 1] = ["deprecated"];
+
+    /// Enables the flag, returning false if it is not known.
+    pub fn enable(&mut self, flag: &str) -> bool {
+        match flag {
+            "no_implicit_number_defaults" => self.no_implicit_number_defaults = true,
+            "deny_shadowing" => self.deny_shadowing = true,
+            "deny_unused_results" => self.deny_unused_results = true,
+            "deny_duplicate_conformances" => self.deny_duplicate_conformances = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Silences the warning, returning false if it is not known.
+    pub fn allow(&mut self, warning: &str) -> bool {
+        match warning {
+            "deprecated" => self.allow_deprecated = true,
+            _ => return false,
+        }
+        true
+    }
 }
 
 impl Module {
@@ -43,18 +111,24 @@ impl Module {
             id: Default::default(),
             name,
             included_modules: vec![],
+            imported_modules: vec![],
             precedence_order: None,
             patterns: Default::default(),
+            grammar: Grammar::new(),
             trait_conformance: Box::new(TraitGraph::new()),
             exposed_functions: Default::default(),
             main_functions: vec![],
             transpile_functions: vec![],
+            implemented_functions: vec![],
+            declarations: vec![],
+            warnings: vec![],
+            settings: Default::default(),
         }
     }
 }
 
 impl Module {
-    pub fn explicit_functions<'a>(&'a self, source: &'a Source) -> Vec<&'a Rc<FunctionHead>> {
+    pub fn explicit_functions<'a>(&'a self, _source: &'a Source) -> Vec<&'a Arc<FunctionHead>> {
         self.exposed_functions.iter().collect_vec()
     }
 }
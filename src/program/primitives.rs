@@ -6,36 +6,52 @@ pub enum Type {
     Int(usize),
     UInt(usize),
     Float(usize),
+    /// A unicode scalar value.
+    Char,
 }
 
 impl Type {
     pub fn is_number(&self) -> bool {
-        match self {
-            Type::Bool => false,
-            _ => true,
-        }
+        !matches!(self, Type::Bool | Type::Char)
     }
 
     pub fn is_float(&self) -> bool {
-        match self {
-            Type::Float(_) => true,
-            _ => false,
-        }
+        matches!(self, Type::Float(_))
     }
 
     pub fn is_int(&self) -> bool {
+        !matches!(self, Type::Bool | Type::Char | Type::Float(_))
+    }
+
+    pub fn is_signed_number(&self) -> bool {
+        !matches!(self, Type::Bool | Type::Char | Type::UInt(_))
+    }
+
+    /// Whether the literal, as written in code, denotes a value of this type.
+    /// Ints must be in range, and reals must not overflow to infinity.
+    pub fn can_represent_literal(&self, literal: &str) -> bool {
         match self {
-            Type::Bool => false,
-            Type::Float(_) => false,
-            _ => true,
+            Type::Bool | Type::Char => false,
+            Type::Int(bits) => literal.parse::<i128>().is_ok_and(|value| {
+                *bits >= 128 || (-(1 << (bits - 1))..(1 << (bits - 1))).contains(&value)
+            }),
+            Type::UInt(bits) => literal.parse::<u128>().is_ok_and(|value| {
+                *bits >= 128 || value < (1 << bits)
+            }),
+            Type::Float(32) => literal.parse::<f32>().is_ok_and(f32::is_finite),
+            Type::Float(_) => literal.parse::<f64>().is_ok_and(f64::is_finite),
         }
     }
 
-    pub fn is_signed_number(&self) -> bool {
+    /// A description of the values this type can hold, for error messages.
+    pub fn range_description(&self) -> String {
         match self {
-            Type::Bool => false,
-            Type::UInt(_) => false,
-            _ => true,
+            Type::Bool => "true or false".to_string(),
+            Type::Int(bits) => format!("{} to {}", -(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+            Type::UInt(bits) => format!("0 to {}", (1u128 << bits) - 1),
+            Type::Float(32) => format!("{:e} to {:e}", f32::MIN, f32::MAX),
+            Type::Float(_) => format!("{:e} to {:e}", f64::MIN, f64::MAX),
+            Type::Char => "U+0000 to U+10FFFF, excluding surrogates".to_string(),
         }
     }
 
@@ -45,6 +61,7 @@ impl Type {
             Type::Int(bits) => format!("Int{}", bits),
             Type::UInt(bits) => format!("UInt{}", bits),
             Type::Float(bits) => format!("Float{}", bits),
+            Type::Char => "Char".to_string(),
         }
     }
 }
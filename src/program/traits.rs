@@ -1,7 +1,4 @@
-use std::fmt::Debug;
-use std::hash::{Hash, Hasher};
 
-use itertools::Itertools;
 
 pub use crate::program::traits::binding::TraitBinding;
 pub use crate::program::traits::conformance::{TraitConformance, TraitConformanceWithTail, RequirementsAssumption, RequirementsFulfillment};
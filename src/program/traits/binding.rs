@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 
@@ -14,24 +14,24 @@ use crate::util::fmt::write_keyval;
 #[derive(Clone, Eq, PartialEq)]
 pub struct TraitBinding {
     /// The trait that is bound.
-    pub trait_: Rc<Trait>,
+    pub trait_: Arc<Trait>,
 
     /// A mapping from each of the trait's generics to some type.
-    pub generic_to_type: HashMap<Rc<Trait>, Rc<TypeProto>>,
+    pub generic_to_type: HashMap<Arc<Trait>, Arc<TypeProto>>,
 }
 
 impl TraitBinding {
-    pub fn mapping_types(&self, map: &dyn Fn(&Rc<TypeProto>) -> Rc<TypeProto>) -> Rc<TraitBinding> {
-        Rc::new(TraitBinding {
-            trait_: Rc::clone(&self.trait_),
-            generic_to_type: self.generic_to_type.iter().map(|(generic, type_) | (Rc::clone(generic), map(type_))).collect()
+    pub fn mapping_types(&self, map: &dyn Fn(&Arc<TypeProto>) -> Arc<TypeProto>) -> Arc<TraitBinding> {
+        Arc::new(TraitBinding {
+            trait_: Arc::clone(&self.trait_),
+            generic_to_type: self.generic_to_type.iter().map(|(generic, type_) | (Arc::clone(generic), map(type_))).collect()
         })
     }
 
-    pub fn try_mapping_types<B>(&self, map: &dyn Fn(&Rc<TypeProto>) -> Result<Rc<TypeProto>, B>) -> Result<Rc<TraitBinding>, B> {
-        Ok(Rc::new(TraitBinding {
-            trait_: Rc::clone(&self.trait_),
-            generic_to_type: self.generic_to_type.iter().map(|(generic, type_) | Ok((Rc::clone(generic), map(type_)?))).try_collect()?
+    pub fn try_mapping_types<B>(&self, map: &dyn Fn(&Arc<TypeProto>) -> Result<Arc<TypeProto>, B>) -> Result<Arc<TraitBinding>, B> {
+        Ok(Arc::new(TraitBinding {
+            trait_: Arc::clone(&self.trait_),
+            generic_to_type: self.generic_to_type.iter().map(|(generic, type_) | Ok((Arc::clone(generic), map(type_)?))).try_collect()?
         }))
     }
 
@@ -44,7 +44,7 @@ impl Hash for TraitBinding {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.trait_.hash(state);
 
-        for keyval in self.generic_to_type.iter().sorted_by_key(|(trait_, type_)| trait_.id) {
+        for keyval in self.generic_to_type.iter().sorted_by_key(|(trait_, _type_)| trait_.id) {
             keyval.hash(state);
         }
     }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 
@@ -13,17 +13,17 @@ use crate::util::hash;
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct TraitConformance {
     /// The binding that is being fulfilled.
-    pub binding: Rc<TraitBinding>,
+    pub binding: Arc<TraitBinding>,
     /// Mapping of: abstract function of the trait => the function that implements it.
     /// The functions have the same interfaces as the requirement (trait_.abstract_functions),
     ///  except with the generics replaced (binding.generic_to_type).
-    pub function_mapping: HashMap<Rc<FunctionHead>, Rc<FunctionHead>>,
+    pub function_mapping: HashMap<Arc<FunctionHead>, Arc<FunctionHead>>,
 }
 
 #[derive(Clone, Eq, Hash, PartialEq, Debug)]
 pub struct TraitConformanceWithTail {
     /// The actual conformance.
-    pub conformance: Rc<TraitConformance>,
+    pub conformance: Arc<TraitConformance>,
     /// How the conformance was achieved (through dynamic rules).
     /// While the dynamic function call itself does not need this information, the dynamic dispatch (/monomorphization)
     ///  later needs to know more because the conformance's functions might call the tail's functions.
@@ -34,31 +34,31 @@ pub struct TraitConformanceWithTail {
     ///  declared only on Cats.
     /// So when we use this conformance, we must also bring along the tail
     /// which must be pre-resolved w.r.t. the conformance's requirements itself.
-    pub tail: Rc<RequirementsFulfillment>,
+    pub tail: Arc<RequirementsFulfillment>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct RequirementsAssumption {
-    pub conformance: HashMap<Rc<TraitBinding>, Rc<TraitConformance>>,
+    pub conformance: HashMap<Arc<TraitBinding>, Arc<TraitConformance>>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub struct RequirementsFulfillment {
     // Requirement: (tail, conformance)
-    pub conformance: HashMap<Rc<TraitBinding>, Rc<TraitConformanceWithTail>>,
-    pub generic_mapping: HashMap<Rc<Trait>, Rc<TypeProto>>,
+    pub conformance: HashMap<Arc<TraitBinding>, Arc<TraitConformanceWithTail>>,
+    pub generic_mapping: HashMap<Arc<Trait>, Arc<TypeProto>>,
 }
 
 
 impl TraitConformance {
-    pub fn new(binding: Rc<TraitBinding>, function_mapping: HashMap<Rc<FunctionHead>, Rc<FunctionHead>>,) -> Rc<TraitConformance> {
-        Rc::new(TraitConformance {
+    pub fn new(binding: Arc<TraitBinding>, function_mapping: HashMap<Arc<FunctionHead>, Arc<FunctionHead>>,) -> Arc<TraitConformance> {
+        Arc::new(TraitConformance {
             binding,
             function_mapping,
         })
     }
 
-    pub fn pure(binding: Rc<TraitBinding>) -> Rc<TraitConformance> {
+    pub fn pure(binding: Arc<TraitBinding>) -> Arc<TraitConformance> {
         if !binding.trait_.abstract_functions.is_empty() {
             panic!()
         }
@@ -70,7 +70,7 @@ impl TraitConformance {
 impl Hash for TraitConformance {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.binding.hash(state);
-        for keyval in self.function_mapping.iter().sorted_by_key(|(src, dst)| src.function_id) {
+        for keyval in self.function_mapping.iter().sorted_by_key(|(src, _dst)| src.function_id) {
             keyval.hash(state)
         }
     }
@@ -78,12 +78,12 @@ impl Hash for TraitConformance {
 
 impl Hash for RequirementsFulfillment {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        for (binding, conformance) in self.conformance.iter().sorted_by_key(|(binding, mapping)| hash::one(binding, DefaultHasher::new())) {
+        for (binding, conformance) in self.conformance.iter().sorted_by_key(|(binding, _mapping)| hash::one(binding, DefaultHasher::new())) {
             binding.hash(state);
             conformance.hash(state);
         }
 
-        for (id, type_) in self.generic_mapping.iter().sorted_by_key(|(trait_, type_)| trait_.id) {
+        for (id, type_) in self.generic_mapping.iter().sorted_by_key(|(trait_, _type_)| trait_.id) {
             id.hash(state);
             type_.hash(state);
         }
@@ -92,8 +92,8 @@ impl Hash for RequirementsFulfillment {
 
 
 impl RequirementsFulfillment {
-    pub fn empty() -> Rc<RequirementsFulfillment> {
-        Rc::new(RequirementsFulfillment {
+    pub fn empty() -> Arc<RequirementsFulfillment> {
+        Arc::new(RequirementsFulfillment {
             conformance: Default::default(),
             generic_mapping: Default::default(),
         })
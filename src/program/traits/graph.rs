@@ -1,13 +1,14 @@
 use std::collections::{HashMap, HashSet};
-use std::collections::hash_map::Entry;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use uuid::Uuid;
 
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 use crate::program::functions::{FunctionHead, FunctionInterface, FunctionType};
 use crate::program::generics::TypeForest;
+use crate::program::module::ModuleName;
 use crate::program::traits::{RequirementsFulfillment, Trait, TraitBinding, TraitConformance, TraitConformanceWithTail};
 use crate::program::types::{TypeProto, TypeUnit};
 use crate::resolver::ambiguous::AmbiguityResult;
@@ -22,13 +23,16 @@ use crate::resolver::ambiguous::AmbiguityResult;
 pub struct TraitConformanceRule {
     /// Generics declared for this conformance, by name (via its declaration).
     /// Used in requirements and the conformance itself (collect_generics on those would yield the same GenericAliases).
-    pub generics: HashMap<String, Rc<Trait>>,
+    pub generics: HashMap<String, Arc<Trait>>,
 
     /// To use this conformance, these other conformances are required.
-    pub requirements: HashSet<Rc<TraitBinding>>,
+    pub requirements: HashSet<Arc<TraitBinding>>,
 
     /// The conformance (w.r.t. generics) defined by this rule.
-    pub conformance: Rc<TraitConformance>,
+    pub conformance: Arc<TraitConformance>,
+
+    /// The module whose source declares the rule, or None for builtin rules.
+    pub module: Option<ModuleName>,
 }
 
 /// A sum of knowledge about trait conformance.
@@ -38,12 +42,27 @@ pub struct TraitConformanceRule {
 pub struct TraitGraph {
     /// All known conformances.
     /// For each conformance, we also know its tail, aka how it was achieved.
-    pub conformance_cache: HashMap<Rc<TraitBinding>, Option<Rc<TraitConformanceWithTail>>>,
+    pub conformance_cache: HashMap<Arc<TraitBinding>, Option<Arc<TraitConformanceWithTail>>>,
 
     /// A list of conformance declarations that allow for dynamic conformance.
     /// All these use generics in the conformance, which are provided by the requirements.
     /// To use the conformance, these generics should be replaced by the matching bindings.
-    pub conformance_rules: HashMap<Rc<Trait>, Vec<Rc<TraitConformanceRule>>>,
+    pub conformance_rules: HashMap<Arc<Trait>, Vec<Arc<TraitConformanceRule>>>,
+
+    /// The same rules, by their normalized binding, to quickly find duplicates.
+    /// Traits like Function have a rule for every function, so comparing each pair would be slow.
+    rules_by_binding: HashMap<Arc<TraitBinding>, Vec<Arc<TraitConformanceRule>>>,
+
+    /// For modules that were imported through include!, the chain of modules leading to them,
+    ///  starting with the imported module and ending with the included one.
+    /// Used to explain where rules come from in error messages.
+    pub include_chains: HashMap<ModuleName, Vec<ModuleName>>,
+}
+
+impl Default for TraitGraph {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TraitGraph {
@@ -51,6 +70,8 @@ impl TraitGraph {
         TraitGraph {
             conformance_cache: Default::default(),
             conformance_rules: Default::default(),
+            rules_by_binding: Default::default(),
+            include_chains: Default::default(),
         }
     }
 
@@ -58,30 +79,57 @@ impl TraitGraph {
         self.conformance_cache = HashMap::new();
     }
 
-    pub fn add_graph(&mut self, graph: &TraitGraph) {
+    /// Adds all rules of the graph. Rules that duplicate a rule already known are skipped,
+    ///  so that rules added first take precedence.
+    /// Returns each skipped rule, along with the rule it duplicates.
+    pub fn add_graph(&mut self, graph: &TraitGraph) -> Vec<(Arc<TraitConformanceRule>, Arc<TraitConformanceRule>)> {
         self.conformance_cache.clear();
-        for (trait_, rules) in graph.conformance_rules.iter() {
-            match self.conformance_rules.entry(Rc::clone(trait_)) {
-                Entry::Occupied(o) => _ = o.into_mut().extend(rules.clone()),
-                Entry::Vacant(v) => _ = v.insert(rules.clone()),
-            }
+        // The first chain a module was reached by is the one its rules were added by.
+        for (module, chain) in graph.include_chains.iter() {
+            self.include_chains.entry(module.clone()).or_insert_with(|| chain.clone());
         }
-    }
 
-    pub fn add_conformance_rule(&mut self, rule: Rc<TraitConformanceRule>) {
-        match self.conformance_rules.entry(Rc::clone(&rule.conformance.binding.trait_)) {
-            Entry::Occupied(e) => {
-                e.into_mut().push(rule);
+        let mut duplicates = vec![];
+        for rule in graph.conformance_rules.values().flatten() {
+            let normalized = rule.normalized();
+            let candidates = self.rules_by_binding.entry(Arc::clone(&normalized.0)).or_default();
+            // The same module may be imported more than once.
+            if candidates.iter().any(|existing| Arc::ptr_eq(existing, rule)) {
+                continue
             }
-            Entry::Vacant(e) => {
-                e.insert(vec![rule]);
+
+            match candidates.iter().find(|existing| existing.normalized() == normalized) {
+                Some(existing) => duplicates.push((Arc::clone(rule), Arc::clone(existing))),
+                None => {
+                    candidates.push(Arc::clone(rule));
+                    self.conformance_rules.entry(Arc::clone(&rule.conformance.binding.trait_)).or_default().push(Arc::clone(rule));
+                }
             }
+        }
+        duplicates
+    }
+
+    /// Adds the rule. If it duplicates a rule already known, it replaces it, and the replaced rule is returned.
+    pub fn add_conformance_rule(&mut self, rule: Arc<TraitConformanceRule>) -> Option<Arc<TraitConformanceRule>> {
+        let normalized = rule.normalized();
+        let candidates = self.rules_by_binding.entry(Arc::clone(&normalized.0)).or_default();
+        let rules = self.conformance_rules.entry(Arc::clone(&rule.conformance.binding.trait_)).or_default();
+
+        let Some(existing) = candidates.iter_mut().find(|existing| existing.normalized() == normalized) else {
+            candidates.push(Arc::clone(&rule));
+            rules.push(rule);
+            return None
         };
+
+        self.conformance_cache.clear();
+        let replaced = std::mem::replace(existing, Arc::clone(&rule));
+        *rules.iter_mut().find(|existing| Arc::ptr_eq(existing, &replaced)).unwrap() = rule;
+        Some(replaced)
     }
 
     // TODO This should not return an ambiguity result. The caller should make sure to resolve types, and we should just do our jobs.
     //  Any layers deeper cannot yield ::Ambiguous anyway, if all bindings are properly filled.
-    pub fn satisfy_requirement(&mut self, requirement: &Rc<TraitBinding>, mapping: &TypeForest) -> RResult<AmbiguityResult<Rc<TraitConformanceWithTail>>> {
+    pub fn satisfy_requirement(&mut self, requirement: &Arc<TraitBinding>, mapping: &TypeForest) -> RResult<AmbiguityResult<Arc<TraitConformanceWithTail>>> {
         // TODO What if requirement is e.g. Float<Float>? Is Float declared on itself?
 
         // We resolve this binding because it might contain generics.
@@ -94,7 +142,7 @@ impl TraitGraph {
             // In cache
             return match state {
                 None => Err(
-                    RuntimeError::error(format!("No compatible declaration for trait conformance requirement: {:?}", resolved_binding).as_str()).to_array()
+                    RuntimeError::error(format!("No compatible declaration for trait conformance requirement: {:?}", resolved_binding).as_str()).with_code(ErrorCode::MissingConformance).to_array()
                 ),
                 Some(declaration) => Ok(AmbiguityResult::Ok(declaration.clone())),
             };
@@ -102,27 +150,30 @@ impl TraitGraph {
 
         let Some(relevant_declarations) = self.conformance_rules.get(&resolved_binding.trait_) else {
             return Err(
-                RuntimeError::error(format!("No declarations found for trait: {:?}", resolved_binding.trait_).as_str()).to_array()
+                RuntimeError::error(format!("No declarations found for trait: {:?}", resolved_binding.trait_).as_str()).with_code(ErrorCode::MissingConformance).to_array()
             );
         };
 
         let mut compatible_conformances = vec![];
+        let mut compatible_rules = vec![];
         let mut bind_errors = vec![];
         let mut requirements_errors = vec![];
 
         // Recalculate
         // TODO clone is a bit much, but we need it to be memory safe
-        let cloned_declarations: Vec<Rc<TraitConformanceRule>> = relevant_declarations.clone();
+        let cloned_declarations: Vec<Arc<TraitConformanceRule>> = relevant_declarations.clone();
         'rule: for rule in cloned_declarations.iter() {
             // For a rule to be compatible, its binding must be compatible with the binding from the arguments.
             //  So we create a new TypeForest where we can bind them together.
-            let mut rule_mapping = mapping.clone();
+            //  The binding has no generics left, so it doesn't need the caller's types. Copying them for every
+            //  rule would make resolving a body quadratic in its size.
+            let mut rule_mapping = TypeForest::new();
 
             // A rule may also use generics. Those need to be rebindable, and we need to be able to figure out
             //  how they've been bound in the end. To do that, we'll just map them to generics and query those
             //  generics later on.
             let rule_generics_map = rule.generics.values()
-                .map(|generic| (Rc::clone(generic), TypeProto::unit(TypeUnit::Generic(Uuid::new_v4()))))
+                .map(|generic| (Arc::clone(generic), TypeProto::unit(TypeUnit::Generic(Uuid::new_v4()))))
                 .collect();
 
             // Bind together the rule and argument.
@@ -165,9 +216,10 @@ impl TraitGraph {
                         // TODO Do we need to map the functions?
                         rule.conformance.function_mapping.clone(),
                     );
+                    compatible_rules.push(Arc::clone(rule));
                     compatible_conformances.push(
-                        Rc::new(TraitConformanceWithTail {
-                            tail: Rc::new(RequirementsFulfillment {
+                        Arc::new(TraitConformanceWithTail {
+                            tail: Arc::new(RequirementsFulfillment {
                                 conformance: fulfilled_requirements,
                                 generic_mapping,
                             }),
@@ -180,9 +232,10 @@ impl TraitGraph {
 
         match compatible_conformances.as_slice() {
             [] => {
-                let error = RuntimeError::error(format!("No compatible declaration for trait conformance requirement: {:?}", resolved_binding).as_str());
+                let error = RuntimeError::error(format!("No compatible declaration for trait conformance requirement: {:?}", resolved_binding).as_str())
+                    .with_code(ErrorCode::MissingConformance);
 
-                self.conformance_cache.insert(Rc::clone(&resolved_binding), None);
+                self.conformance_cache.insert(Arc::clone(&resolved_binding), None);
                 if !requirements_errors.is_empty() {
                     Err(
                         error.with_note(
@@ -201,27 +254,49 @@ impl TraitGraph {
                 }
             }
             [declaration] => {
-                self.conformance_cache.insert(resolved_binding, Some(Rc::clone(declaration)));
-                Ok(AmbiguityResult::Ok(Rc::clone(declaration)))
+                self.conformance_cache.insert(resolved_binding, Some(Arc::clone(declaration)));
+                Ok(AmbiguityResult::Ok(Arc::clone(declaration)))
             }
             _ => {
                 Err(
-                    RuntimeError::error(format!("Conflicting declarations for trait conformance requirement: {:?}", resolved_binding).as_str()).with_note(
-                        RuntimeError::info(format!("{} matching rule(s).", cloned_declarations.len()).as_str())
-                            .with_notes(cloned_declarations.iter().map(|c| RuntimeError::info(format!("{:?}", c).as_str())))
+                    RuntimeError::error(format!("Conflicting declarations for trait conformance requirement: {:?}", resolved_binding).as_str()).with_code(ErrorCode::ConflictingConformance).with_note(
+                        RuntimeError::info(format!("{} matching rule(s).", compatible_rules.len()).as_str())
+                            .with_notes(compatible_rules.iter().map(|rule| self.describe_rule(rule)))
                     ).to_array()
                 )
             }
         }
     }
 
-    pub fn test_requirements(&mut self, requirements: &HashSet<Rc<TraitBinding>>, generics_map: &HashMap<Rc<Trait>, Rc<TypeProto>>, mapping: &TypeForest) -> RResult<AmbiguityResult<HashMap<Rc<TraitBinding>, Rc<TraitConformanceWithTail>>>> {
+    /// Names the rule's conformance and the module declaring it, and how that module was included, if it was.
+    fn describe_rule(&self, rule: &TraitConformanceRule) -> RuntimeError {
+        // Like the declaration, e.g. `$Number is Named`.
+        let binding = &rule.conformance.binding;
+        let declaration = format!(
+            "{} is {}",
+            binding.generic_to_type.iter().sorted_by_key(|(generic, _)| &generic.name).map(|(_, type_)| format!("{:?}", type_)).join(", "),
+            binding.trait_.name
+        );
+        let Some(module) = &rule.module else {
+            return RuntimeError::info(format!("{} (builtin)", declaration).as_str())
+        };
+
+        let description = RuntimeError::info(format!("{}, declared in {}", declaration, module.join(".")).as_str());
+        match self.include_chains.get(module) {
+            Some(chain) => description.with_note(RuntimeError::note(
+                format!("Included through {}.", chain.iter().map(|module| module.join(".")).join(" -> ")).as_str()
+            )),
+            None => description,
+        }
+    }
+
+    pub fn test_requirements(&mut self, requirements: &HashSet<Arc<TraitBinding>>, generics_map: &HashMap<Arc<Trait>, Arc<TypeProto>>, mapping: &TypeForest) -> RResult<AmbiguityResult<HashMap<Arc<TraitBinding>, Arc<TraitConformanceWithTail>>>> {
         let mut conformance = HashMap::new();
 
         for requirement in self.gather_deep_requirements(requirements.iter().cloned()) {
             let mapped_requirement = requirement.mapping_types(&|t| t.replacing_structs(generics_map));
 
-            match self.satisfy_requirement(&mapped_requirement, &mapping)? {
+            match self.satisfy_requirement(&mapped_requirement, mapping)? {
                 AmbiguityResult::Ok(trait_conformance) => {
                     conformance.insert(requirement.clone(), trait_conformance);
                 }
@@ -236,13 +311,13 @@ impl TraitGraph {
 
     /// This function takes in some 'explicit' requirements,
     ///  and returns a vector of all requirements these imply, explicit or implicit.
-    pub fn gather_deep_requirements<C>(&self, bindings: C) -> Vec<Rc<TraitBinding>> where C: Iterator<Item=Rc<TraitBinding>> {
+    pub fn gather_deep_requirements<C>(&self, bindings: C) -> Vec<Arc<TraitBinding>> where C: Iterator<Item=Arc<TraitBinding>> {
         let mut all = HashSet::new();
         let mut ordered = vec![];
         let mut rest = bindings.collect_vec();
         while let Some(binding) = rest.pop() {
-            if all.insert(Rc::clone(&binding)) {
-                ordered.push(Rc::clone(&binding));
+            if all.insert(Arc::clone(&binding)) {
+                ordered.push(Arc::clone(&binding));
                 rest.extend(
                     binding.trait_.requirements.iter()
                         .map(|x| x.mapping_types(&|type_| type_.replacing_structs(&binding.generic_to_type))))
@@ -256,7 +331,7 @@ impl TraitGraph {
     ///  for all requirements implied by those implicit requirements.
     /// To do this, it invents function stubs that will later have to be replaced by the actual
     ///  functions that the caller provides.
-    pub fn assume_granted(&self, bindings: impl Iterator<Item=Rc<TraitBinding>>) -> Vec<Rc<TraitConformance>> {
+    pub fn assume_granted(&self, bindings: impl Iterator<Item=Arc<TraitBinding>>) -> Vec<Arc<TraitConformance>> {
         let deep_requirements = self.gather_deep_requirements(bindings);
         let mut resolutions = vec![];
 
@@ -265,7 +340,7 @@ impl TraitGraph {
 
             for abstract_fun in requirement.trait_.abstract_functions.keys() {
                 let mapped_head = FunctionHead::new(
-                    Rc::new(FunctionInterface {
+                    Arc::new(FunctionInterface {
                         parameters: abstract_fun.interface.parameters.iter().map(|x| {
                             x.mapping_type(&|type_| type_.replacing_structs(&requirement.generic_to_type))
                         }).collect(),
@@ -277,18 +352,18 @@ impl TraitGraph {
                         generics: abstract_fun.interface.generics.clone(),
                     }),
                     FunctionType::Polymorphic {
-                        assumed_requirement: Rc::clone(&requirement),
-                        abstract_function: Rc::clone(abstract_fun)
+                        assumed_requirement: Arc::clone(requirement),
+                        abstract_function: Arc::clone(abstract_fun)
                     }
                 );
                 binding_resolution.insert(
-                    Rc::clone(&abstract_fun),
+                    Arc::clone(abstract_fun),
                     mapped_head
                 );
             }
 
             resolutions.push(
-                TraitConformance::new(Rc::clone(requirement), binding_resolution)
+                TraitConformance::new(Arc::clone(requirement), binding_resolution)
             );
         }
 
@@ -297,23 +372,57 @@ impl TraitGraph {
 }
 
 impl TraitConformanceRule {
+    /// The rule's binding and requirements, with its generics replaced by placeholders in order of appearance.
+    /// Rules that normalize the same are duplicates: Every binding that matches one matches the other,
+    ///  so only one of them can be used.
+    fn normalized(&self) -> (Arc<TraitBinding>, HashSet<Arc<TraitBinding>>) {
+        if self.generics.is_empty() {
+            return (Arc::clone(&self.conformance.binding), self.requirements.clone())
+        }
+
+        let mut order = vec![];
+        let mut todo = self.conformance.binding.generic_to_type.iter()
+            .sorted_by_key(|(generic, _)| generic.id)
+            .map(|(_, type_)| type_)
+            .rev()
+            .collect_vec();
+        while let Some(type_) = todo.pop() {
+            if let TypeUnit::Struct(struct_) = &type_.unit {
+                if self.generics.values().contains(struct_) && !order.contains(struct_) {
+                    order.push(Arc::clone(struct_));
+                }
+            }
+            todo.extend(type_.arguments.iter().rev());
+        }
+        // Generics used only in requirements don't appear in the binding.
+        order.extend(self.generics.iter().sorted_by_key(|(name, _)| *name).map(|(_, generic)| generic).filter(|generic| !order.contains(generic)).cloned().collect_vec());
+
+        let placeholders = order.into_iter().enumerate()
+            .map(|(idx, generic)| (generic, TypeProto::unit(TypeUnit::Generic(Uuid::from_u128(idx as u128)))))
+            .collect();
+        let normalize = |binding: &Arc<TraitBinding>| binding.mapping_types(&|type_| type_.replacing_structs(&placeholders));
+
+        (normalize(&self.conformance.binding), self.requirements.iter().map(normalize).collect())
+    }
+
     // Create a conformance rule that doesn't have generics or requirements.
-    pub fn direct(conformance: Rc<TraitConformance>) -> Rc<TraitConformanceRule> {
-        Rc::new(TraitConformanceRule {
+    pub fn direct(conformance: Arc<TraitConformance>) -> Arc<TraitConformanceRule> {
+        Arc::new(TraitConformanceRule {
             generics: Default::default(),
             requirements: Default::default(),
-            conformance
+            conformance,
+            module: None,
         })
     }
 
-    pub fn manual(binding: Rc<TraitBinding>, function_bindings: Vec<(&Rc<FunctionHead>, &Rc<FunctionHead>)>) -> Rc<TraitConformanceRule> {
+    pub fn manual(binding: Arc<TraitBinding>, function_bindings: Vec<(&Arc<FunctionHead>, &Arc<FunctionHead>)>) -> Arc<TraitConformanceRule> {
         Self::direct(
             TraitConformance::new(
                 binding,
                 HashMap::from_iter(
                     function_bindings.into_iter().map(
                         |(x, y)|
-                            (Rc::clone(x), Rc::clone(y)))
+                            (Arc::clone(x), Arc::clone(y)))
                 )
             )
         )
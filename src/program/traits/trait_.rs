@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::hash::{Hash, Hasher};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use uuid::Uuid;
 
@@ -19,14 +19,16 @@ pub struct Trait {
 
     // Generics declared for this trait, by name (via its declaration).
     // Used in abstract functions and requirements (collect_generics on those would yield the same GenericAliases).
-    pub generics: HashMap<String, Rc<Trait>>,
+    pub generics: HashMap<String, Arc<Trait>>,
 
     // To conform to this trait, these other conformances are required.
-    pub requirements: HashSet<Rc<TraitBinding>>,
+    pub requirements: HashSet<Arc<TraitBinding>>,
+    // To construct the trait as a struct, these requirements on its generics must be satisfied (via its where clause).
+    pub struct_requirements: HashSet<Arc<TraitBinding>>,
 
     // Functions required by this trait specifically (not its requirements).
     // The head of each function to its pointer (how it is defined).
-    pub abstract_functions: HashMap<Rc<FunctionHead>, FunctionRepresentation>,
+    pub abstract_functions: HashMap<Arc<FunctionHead>, FunctionRepresentation>,
     pub field_hints: Vec<FieldHint>,
 }
 
@@ -36,9 +38,9 @@ pub struct Trait {
 #[derive(Clone)]
 pub struct FieldHint {
     pub name: String,
-    pub type_: Rc<TypeProto>,
-    pub setter: Option<Rc<FunctionHead>>,
-    pub getter: Option<Rc<FunctionHead>>,
+    pub type_: Arc<TypeProto>,
+    pub setter: Option<Arc<FunctionHead>>,
+    pub getter: Option<Arc<FunctionHead>>,
 }
 
 impl Trait {
@@ -48,6 +50,7 @@ impl Trait {
             name: name.to_string(),
             generics: Default::default(),
             requirements: Default::default(),
+            struct_requirements: Default::default(),
             abstract_functions: Default::default(),
             field_hints: Default::default(),
         }
@@ -57,32 +60,33 @@ impl Trait {
         Trait {
             id: Uuid::new_v4(),
             name: name.to_string(),
-            generics: HashMap::from([("Self".to_string(), Rc::new(Trait::new_flat("Self")))]),
+            generics: HashMap::from([("Self".to_string(), Arc::new(Trait::new_flat("Self")))]),
             requirements: Default::default(),
+            struct_requirements: Default::default(),
             abstract_functions: Default::default(),
             field_hints: Default::default(),
         }
     }
 
-    pub fn create_generic_type(self: &Trait, generic_name: &str) -> Rc<TypeProto> {
+    pub fn create_generic_type(self: &Trait, generic_name: &str) -> Arc<TypeProto> {
         TypeProto::unit_struct(&self.generics[generic_name])
     }
 
-    pub fn create_generic_binding(self: &Rc<Trait>, generic_to_type: Vec<(&str, Rc<TypeProto>)>) -> Rc<TraitBinding> {
-        Rc::new(TraitBinding {
-            trait_: Rc::clone(self),
+    pub fn create_generic_binding(self: &Arc<Trait>, generic_to_type: Vec<(&str, Arc<TypeProto>)>) -> Arc<TraitBinding> {
+        Arc::new(TraitBinding {
+            trait_: Arc::clone(self),
             generic_to_type: HashMap::from_iter(
                 generic_to_type.into_iter()
-                    .map(|(generic_name, type_)| (Rc::clone(&self.generics[generic_name]), type_))
+                    .map(|(generic_name, type_)| (Arc::clone(&self.generics[generic_name]), type_))
             ),
         })
     }
 
-    pub fn insert_function(&mut self, function: Rc<FunctionHead>, representation: FunctionRepresentation) {
+    pub fn insert_function(&mut self, function: Arc<FunctionHead>, representation: FunctionRepresentation) {
         self.abstract_functions.insert(function, representation);
     }
 
-    pub fn add_simple_parent_requirement(&mut self, parent_trait: &Rc<Trait>) {
+    pub fn add_simple_parent_requirement(&mut self, parent_trait: &Arc<Trait>) {
         self.requirements.insert(
             parent_trait.create_generic_binding(vec![("Self", self.create_generic_type("Self"))])
         );
@@ -1,19 +1,28 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use uuid::Uuid;
 
+use crate::error::{RResult, RuntimeError};
 use crate::program::generics::GenericAlias;
 use crate::program::traits::Trait;
 use crate::util::fmt::write_separated_debug;
 
+/// Types nested deeper than this are rejected by type inference.
+/// Walking them recursively could overflow the stack, and no hand-written type comes close.
+pub const MAX_TYPE_DEPTH: usize = 1000;
+
+pub fn too_deeply_nested() -> Vec<RuntimeError> {
+    RuntimeError::error(format!("Type too deeply nested; at most {} levels are supported.", MAX_TYPE_DEPTH).as_str()).to_array()
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct TypeProto {
     pub unit: TypeUnit,
-    pub arguments: Vec<Rc<TypeProto>>
+    pub arguments: Vec<Arc<TypeProto>>
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -27,7 +36,7 @@ pub enum TypeUnit {
     /// some type that isn't bound yet. This is fully unique and should not be created statically or imported.
     Generic(GenericAlias),
     /// Bound to an instance of a trait. The arguments are the generic bindings.
-    Struct(Rc<Trait>),
+    Struct(Arc<Trait>),
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -59,84 +68,154 @@ impl Debug for TypeUnit {
 }
 
 impl TypeProto {
-    pub fn void() -> Rc<TypeProto> {
+    pub fn void() -> Arc<TypeProto> {
         TypeProto::unit(TypeUnit::Void)
     }
 
-    pub fn unit(unit: TypeUnit) -> Rc<TypeProto> {
-        Rc::new(TypeProto { unit, arguments: vec![] })
+    pub fn unit(unit: TypeUnit) -> Arc<TypeProto> {
+        Arc::new(TypeProto { unit, arguments: vec![] })
     }
 
-    pub fn one_arg(trait_: &Rc<Trait>, subtype: Rc<TypeProto>) -> Rc<TypeProto> {
-        Rc::new(TypeProto {
-            unit: TypeUnit::Struct(Rc::clone(trait_)),
+    pub fn one_arg(trait_: &Arc<Trait>, subtype: Arc<TypeProto>) -> Arc<TypeProto> {
+        Arc::new(TypeProto {
+            unit: TypeUnit::Struct(Arc::clone(trait_)),
             arguments: vec![subtype]
         })
     }
 
-    pub fn unit_struct(trait_: &Rc<Trait>) -> Rc<TypeProto> {
-        TypeProto::unit(TypeUnit::Struct(Rc::clone(trait_)))
+    pub fn unit_struct(trait_: &Arc<Trait>) -> Arc<TypeProto> {
+        TypeProto::unit(TypeUnit::Struct(Arc::clone(trait_)))
     }
 
-    pub fn replacing_generics(self: &Rc<TypeProto>, map: &HashMap<Uuid, Rc<TypeProto>>) -> Rc<TypeProto> {
-        match &self.unit {
-            TypeUnit::Generic(id) => map.get(id)
-                .cloned()
-                .unwrap_or_else(|| self.clone()),
-            _ => Rc::new(TypeProto {
-                unit: self.unit.clone(),
-                arguments: self.arguments.iter().map(|x| x.replacing_generics(map)).collect()
-            }),
-        }
+    pub fn replacing_generics(self: &Arc<TypeProto>, map: &HashMap<Uuid, Arc<TypeProto>>) -> Arc<TypeProto> {
+        self.map_bottom_up(|type_| match &type_.unit {
+            TypeUnit::Generic(id) => Some(map.get(id).cloned().unwrap_or_else(|| type_.clone())),
+            _ => None,
+        })
     }
 
-    pub fn replacing_structs(self: &Rc<TypeProto>, map: &HashMap<Rc<Trait>, Rc<TypeProto>>) -> Rc<TypeProto> {
-        match &self.unit {
-            TypeUnit::Struct(struct_) => map.get(struct_)
-                .cloned()
-                .unwrap_or_else(|| self.clone()),
-            _ => Rc::new(TypeProto {
-                unit: self.unit.clone(),
-                arguments: self.arguments.iter().map(|x| x.replacing_structs(map)).collect()
-            }),
+    pub fn replacing_structs(self: &Arc<TypeProto>, map: &HashMap<Arc<Trait>, Arc<TypeProto>>) -> Arc<TypeProto> {
+        self.map_bottom_up(|type_| match &type_.unit {
+            TypeUnit::Struct(struct_) => map.get(struct_).cloned(),
+            _ => None,
+        })
+    }
+
+    /// Rebuilds the type, replacing every part for which replace returns Some.
+    /// Parts that are replaced are not descended into.
+    /// Types can be nested arbitrarily deep, so this walks them with an explicit stack instead of recursing.
+    fn map_bottom_up(self: &Arc<TypeProto>, replace: impl Fn(&Arc<TypeProto>) -> Option<Arc<TypeProto>>) -> Arc<TypeProto> {
+        // Types whose arguments are being mapped, with the arguments mapped so far.
+        let mut parents: Vec<(&Arc<TypeProto>, Vec<Arc<TypeProto>>)> = vec![];
+        let mut next = self;
+        loop {
+            let mut mapped = match replace(next) {
+                Some(replacement) => replacement,
+                None if next.arguments.is_empty() => TypeProto::unit(next.unit.clone()),
+                None => {
+                    parents.push((next, Vec::with_capacity(next.arguments.len())));
+                    next = &next.arguments[0];
+                    continue
+                }
+            };
+
+            // Ascend until a parent has arguments left to map.
+            loop {
+                let Some((parent, arguments)) = parents.last_mut() else {
+                    return mapped
+                };
+                arguments.push(mapped);
+                if arguments.len() < parent.arguments.len() {
+                    next = &parent.arguments[arguments.len()];
+                    break
+                }
+
+                let (parent, arguments) = parents.pop().unwrap();
+                mapped = Arc::new(TypeProto { unit: parent.unit.clone(), arguments });
+            }
         }
     }
 
-    pub fn collect_generics<'a, C>(collection: C) -> HashSet<Uuid> where C: Iterator<Item=&'a Rc<TypeProto>> {
-        let mut anys = HashSet::new();
-        let mut todo = collection.collect_vec();
+    /// Whether values of this type are instances of the trait.
+    /// Until values can be dynamic, a value's type is exact, so this is known statically.
+    pub fn is_instance_of(&self, trait_: &Arc<Trait>) -> bool {
+        self.unit == TypeUnit::Struct(Arc::clone(trait_))
+    }
+
+    /// Whether the type or any of its arguments is the given struct.
+    pub fn contains_struct(&self, trait_: &Arc<Trait>) -> bool {
+        let mut todo = vec![self];
 
         while let Some(next) = todo.pop() {
             match &next.unit {
-                TypeUnit::Generic(id) => { anys.insert(*id); },
+                TypeUnit::Struct(struct_) if struct_ == trait_ => return true,
                 _ => {}
             };
+            todo.extend(next.arguments.iter().map(AsRef::as_ref));
+        }
+
+        false
+    }
+
+    /// The number of types nested in each other at the deepest point, e.g. 2 for List<Int32>.
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut todo = vec![(self, 1)];
+
+        while let Some((next, next_depth)) = todo.pop() {
+            depth = depth.max(next_depth);
+            todo.extend(next.arguments.iter().map(|argument| (argument.as_ref(), next_depth + 1)));
+        }
+
+        depth
+    }
+
+    /// Fails for types nested deeper than MAX_TYPE_DEPTH, which can't be inferred without risking a stack overflow.
+    pub fn check_depth(&self) -> RResult<()> {
+        match self.depth() > MAX_TYPE_DEPTH {
+            true => Err(too_deeply_nested()),
+            false => Ok(()),
+        }
+    }
+
+    pub fn collect_generics<'a, C>(collection: C) -> HashSet<Uuid> where C: Iterator<Item=&'a Arc<TypeProto>> {
+        let mut anys = HashSet::new();
+        let mut todo = collection.collect_vec();
+
+        while let Some(next) = todo.pop() {
+            if let TypeUnit::Generic(id) = &next.unit { anys.insert(*id); };
             todo.extend(&next.arguments);
         }
 
         anys
     }
 
-    pub fn contains_generics<'a, C>(collection: C) -> bool where C: Iterator<Item=&'a Rc<TypeProto>> {
+    pub fn contains_generics<'a, C>(collection: C) -> bool where C: Iterator<Item=&'a Arc<TypeProto>> {
         let mut todo = collection.collect_vec();
 
         while let Some(next) = todo.pop() {
-            match &next.unit {
-                TypeUnit::Generic(_) => { return true },
-                _ => {}
-            };
+            if let TypeUnit::Generic(_) = &next.unit { return true };
             todo.extend(&next.arguments);
         }
 
-        return false
+        false
+    }
+}
+
+impl Drop for TypeProto {
+    fn drop(&mut self) {
+        // Dropping the arguments recursively could overflow the stack for deeply nested types.
+        let mut arguments = std::mem::take(&mut self.arguments);
+        while let Some(argument) = arguments.pop() {
+            if let Ok(mut argument) = Arc::try_unwrap(argument) {
+                arguments.append(&mut argument.arguments);
+            }
+        }
     }
 }
 
 impl TypeUnit {
     pub fn is_void(&self) -> bool {
-        match self {
-            TypeUnit::Void => true,
-            _ => false
-        }
+        matches!(self, TypeUnit::Void)
     }
 }
@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::Entry;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use linked_hash_set::LinkedHashSet;
@@ -12,25 +12,34 @@ use crate::program::functions::{FunctionHead, FunctionType};
 use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor};
 use crate::refactor::call_graph::CallGraph;
 use crate::refactor::inline::{inline_calls, InlineHint, try_inline};
-use crate::refactor::monomorphize::monomorphize_implementation;
+use crate::refactor::monomorphize::{monomorphize_head, monomorphize_implementation, substitute_reflection};
 
 pub mod simplify;
 pub mod monomorphize;
 pub mod inline;
 pub mod locals;
+pub mod constants;
 pub mod analyze;
 pub mod call_graph;
+pub mod report;
 
 pub struct Refactor<'a> {
     pub runtime: &'a mut Runtime,
 
-    pub explicit_functions: Vec<Rc<FunctionHead>>,
-    pub invented_functions: HashSet<Rc<FunctionHead>>,
-
-    pub fn_representations: HashMap<Rc<FunctionHead>, FunctionRepresentation>,
-    pub fn_logic: HashMap<Rc<FunctionHead>, FunctionLogic>,
-    pub fn_inline_hints: HashMap<Rc<FunctionHead>, InlineHint>,
-    pub fn_optimizations: HashMap<Rc<FunctionBinding>, Rc<FunctionHead>>,
+    pub explicit_functions: Vec<Arc<FunctionHead>>,
+    pub invented_functions: HashSet<Arc<FunctionHead>>,
+    /// For each invented function, the function it was made from.
+    pub fn_origins: HashMap<Arc<FunctionHead>, Arc<FunctionHead>>,
+
+    pub fn_representations: HashMap<Arc<FunctionHead>, FunctionRepresentation>,
+    pub fn_logic: HashMap<Arc<FunctionHead>, FunctionLogic>,
+    pub fn_inline_hints: HashMap<Arc<FunctionHead>, InlineHint>,
+    /// For each function with an inline hint, the callers that were rewritten when it was applied.
+    pub fn_inlined_callers: HashMap<Arc<FunctionHead>, HashSet<Arc<FunctionHead>>>,
+    pub fn_optimizations: HashMap<Arc<FunctionBinding>, Arc<FunctionHead>>,
+    /// Functions in the vtable of a box. Like explicit functions, they are referenced by their head,
+    ///  so they must keep it.
+    pub vtable_functions: HashSet<Arc<FunctionHead>>,
 
     pub call_graph: CallGraph,
 }
@@ -41,24 +50,29 @@ impl<'a> Refactor<'a> {
             runtime,
             explicit_functions: vec![],
             invented_functions: HashSet::new(),
+            fn_origins: Default::default(),
             fn_representations: Default::default(),
             fn_logic: Default::default(),
             fn_inline_hints: Default::default(),
+            fn_inlined_callers: Default::default(),
             fn_optimizations: Default::default(),
+            vtable_functions: Default::default(),
             call_graph: CallGraph::new(),
         }
     }
 
-    pub fn add(&mut self, mut implementation: Box<FunctionImplementation>, representation: FunctionRepresentation) {
-        self.explicit_functions.push(Rc::clone(&implementation.head));
+    pub fn add(&mut self, implementation: Box<FunctionImplementation>, representation: FunctionRepresentation) {
+        self.explicit_functions.push(Arc::clone(&implementation.head));
         self._add(implementation, representation)
     }
 
     fn _add(&mut self, mut implementation: Box<FunctionImplementation>, representation: FunctionRepresentation) {
-        let head = Rc::clone(&implementation.head);
+        let head = Arc::clone(&implementation.head);
+        // Functions added explicitly aren't monomorphized, but their types are known already.
+        substitute_reflection(&mut implementation, &self.runtime.source);
 
-        self.fn_logic.insert(Rc::clone(&head), FunctionLogic::Implementation(implementation));
-        self.fn_representations.insert(Rc::clone(&head), representation);
+        self.fn_logic.insert(Arc::clone(&head), FunctionLogic::Implementation(implementation));
+        self.fn_representations.insert(Arc::clone(&head), representation);
         self.update_callees(&head);
 
         // New function; it may call functions that were already inlined!
@@ -67,15 +81,21 @@ impl<'a> Refactor<'a> {
 
     /// Place or replace a function with a stub.
     ///  This can be useful to 'mark' the function as the compiler intending to inject it itself.
-    pub fn replace_with_stub(&mut self, head: &Rc<FunctionHead>) {
+    pub fn replace_with_stub(&mut self, head: &Arc<FunctionHead>) {
         self.call_graph.change_callees(head, LinkedHashSet::new());
-        self.fn_logic.insert(Rc::clone(head), FunctionLogic::Descriptor(FunctionLogicDescriptor::Stub));
+        self.fn_logic.insert(Arc::clone(head), FunctionLogic::Descriptor(FunctionLogicDescriptor::Stub));
     }
 
-    pub fn update_callees(&mut self, head: &Rc<FunctionHead>) {
+    pub fn update_callees(&mut self, head: &Arc<FunctionHead>) {
         match &self.fn_logic[head] {
             FunctionLogic::Implementation(i) => {
-                self.call_graph.change_callees(head, analyze::gather_callees(i))
+                let callees = self.with_vtable_calls(analyze::gather_callees(i));
+                self.call_graph.change_callees(head, callees)
+            },
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::DynBox(vtable)) => {
+                // Boxes call their vtable's functions, though not directly.
+                let callees = vtable.iter().map(|function| FunctionBinding::pure(Arc::clone(function))).collect();
+                self.call_graph.change_callees(head, callees)
             },
             FunctionLogic::Descriptor(_) => {
                 // For now, descriptors are disallowed from calling monoteny functions anyway
@@ -84,40 +104,64 @@ impl<'a> Refactor<'a> {
         }
     }
 
-    pub fn try_inline(&mut self, head: &Rc<FunctionHead>) -> Result<HashSet<Rc<FunctionHead>>, ()> {
-        if self.explicit_functions.contains(head) {
-            return Err(())
+    /// Boxing a value makes a vtable of the functions it needs, with the box call's fulfillment.
+    /// They are listed before the box, so they are monomorphized before it is.
+    fn with_vtable_calls(&self, callees: LinkedHashSet<Arc<FunctionBinding>>) -> LinkedHashSet<Arc<FunctionBinding>> {
+        let mut result = LinkedHashSet::new();
+        for callee in callees {
+            let logic = self.fn_logic.get(&callee.function).or_else(|| self.runtime.source.fn_logic.get(&callee.function));
+            if let Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::DynBox(vtable))) = logic {
+                result.extend(vtable.iter().map(|function| Arc::new(FunctionBinding {
+                    function: Arc::clone(function),
+                    requirements_fulfillment: Arc::clone(&callee.requirements_fulfillment),
+                })));
+            }
+            result.insert(callee);
+        }
+        result
+    }
+
+    pub fn try_inline(&mut self, head: &Arc<FunctionHead>, substitute_expressions: bool) -> Option<HashSet<Arc<FunctionHead>>> {
+        if self.explicit_functions.contains(head) || self.vtable_functions.contains(head) {
+            return None
         }
 
-        let Entry::Occupied(o) = self.fn_logic.entry(Rc::clone(head)) else {
+        let Entry::Occupied(o) = self.fn_logic.entry(Arc::clone(head)) else {
             panic!("(Internal Error) Tried to inline an unknown function: {:?}", head);
         };
 
         let FunctionLogic::Implementation(imp) = o.get() else {
-            return Err(())
+            return None
         };
 
-        let Some(inline) = try_inline(imp) else {
-            return Err(())
-        };
+        let inline = try_inline(imp, substitute_expressions)?;
 
         o.remove();
-        self.fn_inline_hints.insert(Rc::clone(head), inline);
+        self.fn_inline_hints.insert(Arc::clone(head), inline);
+
+        Some(self.inline_calls_to(head))
+    }
 
-        return Ok(self.inline_calls_to(head))
+    /// The source function the function was made from, following invented functions back to it.
+    pub fn original_function<'b>(&'b self, mut head: &'b Arc<FunctionHead>) -> &'b Arc<FunctionHead> {
+        while let Some(origin) = self.fn_origins.get(head) {
+            head = origin;
+        }
+        head
     }
 
-    pub fn inline_calls_to(&mut self, head: &Rc<FunctionHead>) -> HashSet<Rc<FunctionHead>> {
+    pub fn inline_calls_to(&mut self, head: &Arc<FunctionHead>) -> HashSet<Arc<FunctionHead>> {
         let affected: HashSet<_> = self.call_graph.get_callers(head).cloned().collect();
         for caller in affected.iter() {
             self.inline_calls_from(caller);
         }
         self.call_graph.remove(head);
+        self.fn_inlined_callers.entry(Arc::clone(head)).or_default().extend(affected.iter().cloned());
 
         affected
     }
 
-    pub fn inline_calls_from(&mut self, head: &Rc<FunctionHead>) {
+    pub fn inline_calls_from(&mut self, head: &Arc<FunctionHead>) {
         match self.fn_logic.get_mut(head).unwrap() {
             FunctionLogic::Implementation(imp) => {
                 inline_calls(imp, &self.fn_optimizations, &self.fn_inline_hints);
@@ -128,28 +172,46 @@ impl<'a> Refactor<'a> {
         }
     }
 
-    pub fn try_monomorphize(&mut self, binding: &Rc<FunctionBinding>) -> Option<Rc<FunctionHead>> {
+    pub fn try_monomorphize(&mut self, binding: &Arc<FunctionBinding>) -> Option<Arc<FunctionHead>> {
         if self.fn_optimizations.contains_key(binding) {
             return None  // We already have an optimization; we need not monomorphize.
         }
+        if self.invented_functions.contains(&binding.function) {
+            // We made this function ourselves; it's already monomorphic.
+            //  Monomorphizing it again would loop forever on recursive calls.
+            return None
+        }
+        if self.explicit_functions.contains(&binding.function) && binding.requirements_fulfillment.is_empty() {
+            // Explicit functions are kept as they are, so calls to them need no copy if
+            //  they aren't generic.
+            return None
+        }
 
         let Some(logic) = self.fn_logic.get(&binding.function).or_else(|| self.runtime.source.fn_logic.get(&binding.function)) else {
             panic!("Cannot find logic for function {:?}", binding.function);
         };
 
-        let FunctionLogic::Implementation(implementation) = logic else {
-            return None
+        let implementation = match logic {
+            FunctionLogic::Implementation(implementation) => implementation,
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::DynBox(vtable)) if !binding.requirements_fulfillment.is_empty() => {
+                let vtable = vtable.clone();
+                return Some(self.monomorphize_box(binding, &vtable))
+            }
+            FunctionLogic::Descriptor(_) => return None,
         };
 
         let mut new_implementation = implementation.clone();
         monomorphize_implementation(&mut new_implementation, binding);
-        let mono_head = Rc::clone(&new_implementation.head);
+        substitute_reflection(&mut new_implementation, &self.runtime.source);
+        let mono_head = Arc::clone(&new_implementation.head);
 
-        self.fn_optimizations.insert(Rc::clone(binding), Rc::clone(&mono_head));
+        self.fn_optimizations.insert(Arc::clone(binding), Arc::clone(&mono_head));
+        self.invented_functions.insert(Arc::clone(&mono_head));
+        self.fn_origins.insert(Arc::clone(&mono_head), Arc::clone(&binding.function));
 
-        self.fn_logic.insert(Rc::clone(&mono_head), FunctionLogic::Implementation(new_implementation));
+        self.fn_logic.insert(Arc::clone(&mono_head), FunctionLogic::Implementation(new_implementation));
         let representation = self.fn_representations.get(&binding.function).or_else(|| self.runtime.source.fn_representations.get(&binding.function)).unwrap().clone();
-        self.fn_representations.insert(Rc::clone(&mono_head), representation);
+        self.fn_representations.insert(Arc::clone(&mono_head), representation);
 
         // Set the initial callees (none if it's a stub)
         self.update_callees(&mono_head);
@@ -165,8 +227,37 @@ impl<'a> Refactor<'a> {
         Some(mono_head)
     }
 
+    /// Boxes the value with the monomorphized functions of its vtable.
+    /// The vtable's functions were monomorphized already, because they are listed before the box in the callees.
+    fn monomorphize_box(&mut self, binding: &Arc<FunctionBinding>, vtable: &[Arc<FunctionHead>]) -> Arc<FunctionHead> {
+        let vtable = vtable.iter().map(|function| {
+            let function_binding = FunctionBinding {
+                function: Arc::clone(function),
+                requirements_fulfillment: Arc::clone(&binding.requirements_fulfillment),
+            };
+            Arc::clone(&self.fn_optimizations[&function_binding])
+        }).collect_vec();
+        self.vtable_functions.extend(vtable.iter().cloned());
+
+        let mono_head = monomorphize_head(binding);
+        self.fn_optimizations.insert(Arc::clone(binding), Arc::clone(&mono_head));
+        self.invented_functions.insert(Arc::clone(&mono_head));
+        self.fn_origins.insert(Arc::clone(&mono_head), Arc::clone(&binding.function));
+
+        self.fn_logic.insert(Arc::clone(&mono_head), FunctionLogic::Descriptor(FunctionLogicDescriptor::DynBox(vtable)));
+        let representation = self.fn_representations.get(&binding.function).or_else(|| self.runtime.source.fn_representations.get(&binding.function)).unwrap().clone();
+        self.fn_representations.insert(Arc::clone(&mono_head), representation);
+        self.update_callees(&mono_head);
+
+        for caller in self.call_graph.get_binding_callers(binding).cloned().collect_vec() {
+            self.inline_calls_from(&caller);
+        }
+
+        mono_head
+    }
+
     /// Map an implementation. If the implementation's head is changed, the mapper must provide an inline hint.
-    pub fn swizzle_implementation(&mut self, function: &Rc<FunctionHead>, map: impl Fn(&mut FunctionImplementation) -> Option<Vec<usize>>) -> HashSet<Rc<FunctionHead>> {
+    pub fn swizzle_implementation(&mut self, function: &Arc<FunctionHead>, map: impl Fn(&mut FunctionImplementation) -> Option<Vec<usize>>) -> HashSet<Arc<FunctionHead>> {
         assert!(function.function_type == FunctionType::Static);
 
         let Some(FunctionLogic::Implementation(mut implementation)) = self.fn_logic.remove(function) else {
@@ -176,18 +267,19 @@ impl<'a> Refactor<'a> {
         if let Some(swizzle) = map(&mut implementation) {
             // The mapper changed the interface / function ID!
             assert_ne!(function, &implementation.head);
-            let new_head = Rc::clone(&implementation.head);
+            let new_head = Arc::clone(&implementation.head);
 
-            self.invented_functions.insert(Rc::clone(&new_head));
-            self.fn_inline_hints.insert(Rc::clone(function), InlineHint::ReplaceCall(Rc::clone(&implementation.head), swizzle));
-            self.fn_logic.insert(Rc::clone(&new_head), FunctionLogic::Implementation(implementation));
-            self.fn_representations.insert(Rc::clone(&new_head), self.fn_representations[function].clone());
+            self.invented_functions.insert(Arc::clone(&new_head));
+            self.fn_origins.insert(Arc::clone(&new_head), Arc::clone(function));
+            self.fn_inline_hints.insert(Arc::clone(function), InlineHint::ReplaceCall(Arc::clone(&implementation.head), swizzle));
+            self.fn_logic.insert(Arc::clone(&new_head), FunctionLogic::Implementation(implementation));
+            self.fn_representations.insert(Arc::clone(&new_head), self.fn_representations[function].clone());
 
             // Find the initial callees.
             self.update_callees(&new_head);
 
             // Gather everyone that's dirty! That includes the new function!
-            let changed_functions = self.inline_calls_to(function).into_iter().chain([new_head].into_iter()).collect();
+            let changed_functions = self.inline_calls_to(function).into_iter().chain([new_head]).collect();
 
             // Remove the last function from the call graph. After apply_inline has been called,
             // we don't need this anymore, since we're inlined.
@@ -199,19 +291,35 @@ impl<'a> Refactor<'a> {
             // The function kept its interface!
             assert_eq!(function, &implementation.head);
 
-            self.fn_logic.insert(Rc::clone(function), FunctionLogic::Implementation(implementation));
+            self.fn_logic.insert(Arc::clone(function), FunctionLogic::Implementation(implementation));
             self.update_callees(function);
             // We changed the function; it is dirty!
-            return HashSet::from([Rc::clone(function)])
+            HashSet::from([Arc::clone(function)])
         }
     }
 
-    pub fn gather_needed_functions(&mut self) -> LinkedHashSet<Rc<FunctionHead>> {
+    pub fn gather_needed_functions(&mut self) -> LinkedHashSet<Arc<FunctionHead>> {
         let callees = self.call_graph.deep_callees(self.explicit_functions.iter());
+        self.gather_logic(callees)
+    }
+
+    /// Like gather_needed_functions, but also includes implementations that are not called by any explicit function.
+    pub fn gather_all_functions(&mut self) -> LinkedHashSet<Arc<FunctionHead>> {
+        let implementations = self.fn_logic.iter()
+            .filter(|(head, logic)| matches!(logic, FunctionLogic::Implementation(_)) && !self.explicit_functions.contains(head))
+            .map(|(head, _)| Arc::clone(head))
+            .collect_vec();
+
+        let mut functions = LinkedHashSet::from_iter(implementations.iter().cloned());
+        functions.extend(self.call_graph.deep_callees(self.explicit_functions.iter().chain(implementations.iter())));
+        self.gather_logic(functions)
+    }
+
+    fn gather_logic(&mut self, callees: LinkedHashSet<Arc<FunctionHead>>) -> LinkedHashSet<Arc<FunctionHead>> {
         for callee in callees.iter() {
             if !self.fn_logic.contains_key(callee) {
-                self.fn_logic.insert(Rc::clone(callee), self.runtime.source.fn_logic[callee].clone());
-                self.fn_representations.insert(Rc::clone(callee), self.runtime.source.fn_representations[callee].clone());
+                self.fn_logic.insert(Arc::clone(callee), self.runtime.source.fn_logic[callee].clone());
+                self.fn_representations.insert(Arc::clone(callee), self.runtime.source.fn_representations[callee].clone());
             }
         }
         callees
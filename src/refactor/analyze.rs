@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use linked_hash_set::LinkedHashSet;
 
@@ -6,7 +6,7 @@ use crate::program::calls::{FunctionBinding, resolve_binding};
 use crate::program::expression_tree::ExpressionOperation;
 use crate::program::global::FunctionImplementation;
 
-pub fn gather_callees(implementation: &FunctionImplementation) -> LinkedHashSet<Rc<FunctionBinding>> {
+pub fn gather_callees(implementation: &FunctionImplementation) -> LinkedHashSet<Arc<FunctionBinding>> {
     let mut callees = LinkedHashSet::new();
 
     // TODO Generic function calls would break this logic
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use linked_hash_map::LinkedHashMap;
+use uuid::Uuid;
+
+use crate::program::allocation::{Mutability, ObjectReference};
+use crate::program::calls::FunctionBinding;
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation, ExpressionTree};
+use crate::program::functions::FunctionHead;
+use crate::program::global::FunctionImplementation;
+use crate::program::types::TypeProto;
+
+/// Finds locals that are set to a constant value exactly once, and read exactly once.
+///  Maps them to their (setter, getter) expressions.
+/// A value is constant if it consists only of literals and calls that can be moved.
+pub fn find_propagatable_locals(implementation: &FunctionImplementation, can_move: impl Fn(&Arc<FunctionHead>, &[ExpressionID]) -> bool) -> HashMap<Arc<ObjectReference>, (ExpressionID, ExpressionID)> {
+    let mut setters: HashMap<&Arc<ObjectReference>, Vec<ExpressionID>> = HashMap::new();
+    let mut getters: HashMap<&Arc<ObjectReference>, Vec<ExpressionID>> = HashMap::new();
+
+    for (expression_id, operation) in implementation.expression_tree.values.iter() {
+        match operation {
+            ExpressionOperation::SetLocal(local) => setters.entry(local).or_default().push(*expression_id),
+            ExpressionOperation::GetLocal(local) => getters.entry(local).or_default().push(*expression_id),
+            _ => {},
+        }
+    }
+
+    setters.into_iter()
+        .filter(|(local, _)| !implementation.parameter_locals.contains(local))
+        .filter_map(|(local, setters)| {
+            let setter = setters.into_iter().exactly_one().ok()?;
+            let getter = getters.remove(local)?.into_iter().exactly_one().ok()?;
+            let value = implementation.expression_tree.children[&setter][0];
+
+            is_constant(implementation, &value, &can_move).then(|| (Arc::clone(local), (setter, getter)))
+        })
+        .collect()
+}
+
+fn is_constant(implementation: &FunctionImplementation, expression_id: &ExpressionID, can_move: &impl Fn(&Arc<FunctionHead>, &[ExpressionID]) -> bool) -> bool {
+    implementation.expression_tree.deep_children(*expression_id).iter()
+        .all(|expression_id| match &implementation.expression_tree.values[expression_id] {
+            ExpressionOperation::StringLiteral(_) => true,
+            ExpressionOperation::FunctionCall(f) => can_move(&f.function, &implementation.expression_tree.children[expression_id]),
+            _ => false,
+        })
+}
+
+/// Moves the values of the given locals to where they are read, removing the assignments.
+pub fn propagate_locals(implementation: &mut FunctionImplementation, locals: &HashMap<Arc<ObjectReference>, (ExpressionID, ExpressionID)>) {
+    let expression_forest = &mut implementation.expression_tree;
+
+    for (setter, getter) in locals.values() {
+        let value = expression_forest.children.get_mut(setter).unwrap().remove(0);
+
+        // The getter takes the value's place; its type is the same.
+        let operation = expression_forest.values.remove(&value).unwrap();
+        let arguments = expression_forest.children.remove(&value).unwrap();
+        expression_forest.parents.remove(&value);
+        for arg in arguments.iter() {
+            *expression_forest.parents.get_mut(arg).unwrap() = *getter;
+        }
+        *expression_forest.values.get_mut(getter).unwrap() = operation;
+        *expression_forest.children.get_mut(getter).unwrap() = arguments;
+
+        expression_forest.truncate_up_and_down(vec![*setter], |op| op == &ExpressionOperation::Block);
+    }
+
+    for local in locals.keys() {
+        implementation.locals_names.remove(local);
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum InternKey<'a> {
+    StringLiteral(&'a str),
+    Parameter(&'a Arc<ObjectReference>),
+    FunctionCall(&'a Arc<FunctionBinding>, Vec<usize>),
+}
+
+/// Finds identical constant subexpressions that occur more than once, and would be smaller if shared.
+///  Returns groups of expressions that can be replaced by a single shared local.
+/// Only literals, immutable parameters and calls that can be moved are considered.
+/// Groups are only shared within the innermost block that contains all of their expressions.
+pub fn find_shared_expressions(implementation: &FunctionImplementation, can_move: impl Fn(&Arc<FunctionHead>, &[ExpressionID]) -> bool) -> Vec<Vec<ExpressionID>> {
+    let expression_tree = &implementation.expression_tree;
+    let mut interner = HashMap::new();
+    let mut classes = HashMap::new();
+    intern(implementation, &expression_tree.root, &can_move, &mut interner, &mut classes);
+
+    // (size, occurrences) per class, in order of first occurrence.
+    let mut occurrences: LinkedHashMap<usize, (usize, Vec<ExpressionID>)> = LinkedHashMap::new();
+    for expression_id in expression_tree.deep_children(expression_tree.root) {
+        if let Some((class, size)) = classes.get(&expression_id) {
+            occurrences.entry(*class).or_insert_with(|| (*size, vec![])).1.push(expression_id);
+        }
+    }
+
+    occurrences.into_iter().map(|(_, group)| group)
+        // Each group is replaced by one definition (value + SetLocal) and a GetLocal per occurrence.
+        .filter(|(size, expressions)| expressions.len() * size > size + 1 + expressions.len())
+        // There must be a block to declare the shared local in.
+        .filter(|(_, expressions)| enclosing_block(expression_tree, expressions).is_some())
+        // Larger expressions first, so that contained duplicates are already shared.
+        .sorted_by_key(|(size, _)| usize::MAX - size)
+        .map(|(_, expressions)| expressions)
+        .collect()
+}
+
+fn intern<'a>(implementation: &'a FunctionImplementation, root: &ExpressionID, can_move: &impl Fn(&Arc<FunctionHead>, &[ExpressionID]) -> bool, interner: &mut HashMap<(InternKey<'a>, Arc<TypeProto>), usize>, classes: &mut HashMap<ExpressionID, (usize, usize)>) {
+    // Children come after their parents in breadth-first order, so reversed, they're interned first.
+    //  Trees can be deep, so this is better than recursing.
+    for expression_id in implementation.expression_tree.deep_children(*root).iter().rev() {
+        if let Some(class) = intern_expression(implementation, expression_id, can_move, interner, classes) {
+            classes.insert(*expression_id, class);
+        }
+    }
+}
+
+fn intern_expression<'a>(implementation: &'a FunctionImplementation, expression_id: &ExpressionID, can_move: &impl Fn(&Arc<FunctionHead>, &[ExpressionID]) -> bool, interner: &mut HashMap<(InternKey<'a>, Arc<TypeProto>), usize>, classes: &HashMap<ExpressionID, (usize, usize)>) -> Option<(usize, usize)> {
+    let expression_tree = &implementation.expression_tree;
+    let children = expression_tree.children[expression_id].iter()
+        .map(|child| classes.get(child).cloned())
+        .collect_vec();
+
+    let (key, size) = match &expression_tree.values[expression_id] {
+        ExpressionOperation::StringLiteral(string) => (InternKey::StringLiteral(string), 1),
+        ExpressionOperation::GetLocal(local) if local.mutability == Mutability::Immutable && implementation.parameter_locals.contains(local) => {
+            (InternKey::Parameter(local), 1)
+        }
+        ExpressionOperation::FunctionCall(binding) if can_move(&binding.function, &expression_tree.children[expression_id]) => {
+            let children: Vec<(usize, usize)> = children.into_iter().collect::<Option<_>>()?;
+            let size = 1 + children.iter().map(|(_, size)| size).sum::<usize>();
+            (InternKey::FunctionCall(binding, children.into_iter().map(|(class, _)| class).collect()), size)
+        }
+        _ => return None,
+    };
+
+    let type_ = implementation.type_forest.resolve_binding_alias(expression_id).ok()?;
+    let class_count = interner.len();
+    let class = *interner.entry((key, type_)).or_insert(class_count);
+    Some((class, size))
+}
+
+/// The innermost block that contains all expressions, and the index of its first statement that contains one.
+/// Blocks outside a conditional's branch are not considered for expressions within it.
+fn enclosing_block(expression_tree: &ExpressionTree, expressions: &[ExpressionID]) -> Option<(ExpressionID, usize)> {
+    let mut ancestor = expressions[0];
+    while let Some(parent) = expression_tree.parents.get(&ancestor) {
+        ancestor = *parent;
+        if expression_tree.values[&ancestor] != ExpressionOperation::Block {
+            continue
+        }
+
+        let statements = &expression_tree.children[&ancestor];
+        let indices: Option<Vec<usize>> = expressions.iter()
+            .map(|expression_id| {
+                let statement = statement_in(expression_tree, ancestor, *expression_id)?;
+                statements.iter().position(|s| *s == statement)
+            })
+            .collect();
+        if let Some(indices) = indices {
+            return indices.into_iter().min().map(|index| (ancestor, index))
+        }
+    }
+
+    None
+}
+
+/// The statement of the block that contains the expression, if any.
+/// Expressions in a branch of a conditional within the statement are not always evaluated, so they have none.
+fn statement_in(expression_tree: &ExpressionTree, block: ExpressionID, mut expression_id: ExpressionID) -> Option<ExpressionID> {
+    loop {
+        let parent = *expression_tree.parents.get(&expression_id)?;
+        if parent == block {
+            return Some(expression_id)
+        }
+        if expression_tree.values[&parent] == ExpressionOperation::IfThenElse && expression_tree.children[&parent][0] != expression_id {
+            return None
+        }
+        expression_id = parent;
+    }
+}
+
+/// Replaces each group of identical expressions by a single local.
+/// The local is declared in the innermost block containing the group, before the first statement that uses it.
+pub fn share_expressions(implementation: &mut FunctionImplementation, groups: &[Vec<ExpressionID>]) {
+    for group in groups.iter() {
+        let expression_tree = &mut implementation.expression_tree;
+        let live_expressions = group.iter().filter(|e| expression_tree.values.contains_key(e)).cloned().collect_vec();
+        if live_expressions.len() < 2 {
+            // The duplicates were contained in a larger shared expression.
+            continue
+        }
+        let Some((block, index)) = enclosing_block(expression_tree, &live_expressions) else {
+            continue
+        };
+
+        let type_ = implementation.type_forest.resolve_binding_alias(&live_expressions[0]).unwrap();
+        let local = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: Arc::clone(&type_), mutability: Mutability::Immutable });
+
+        // Move the first occurrence to be the local's value.
+        let value = Uuid::new_v4();
+        let setter = Uuid::new_v4();
+        let first = live_expressions[0];
+        let operation = expression_tree.values.remove(&first).unwrap();
+        let arguments = expression_tree.children.remove(&first).unwrap();
+        for arg in arguments.iter() {
+            *expression_tree.parents.get_mut(arg).unwrap() = value;
+        }
+        expression_tree.values.insert(value, operation);
+        expression_tree.children.insert(value, arguments);
+        expression_tree.parents.insert(value, setter);
+
+        expression_tree.values.insert(setter, ExpressionOperation::SetLocal(Arc::clone(&local)));
+        expression_tree.children.insert(setter, vec![value]);
+        expression_tree.parents.insert(setter, block);
+        expression_tree.children.get_mut(&block).unwrap().insert(index, setter);
+
+        implementation.type_forest.bind(value, &type_).unwrap();
+        implementation.type_forest.bind(setter, &TypeProto::void()).unwrap();
+
+        // Replace all occurrences with the local.
+        for expression_id in live_expressions {
+            if let Some(arguments) = expression_tree.children.insert(expression_id, vec![]) {
+                expression_tree.truncate_down(arguments);
+            }
+            expression_tree.values.insert(expression_id, ExpressionOperation::GetLocal(Arc::clone(&local)));
+        }
+
+        implementation.locals_names.insert(local, "shared".to_string());
+    }
+}
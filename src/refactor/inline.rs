@@ -1,37 +1,61 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
+use uuid::Uuid;
 
 use crate::program::calls::{FunctionBinding, resolve_binding};
 use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
 use crate::program::functions::FunctionHead;
 use crate::program::global::FunctionImplementation;
 use crate::program::traits::RequirementsFulfillment;
+use crate::program::types::TypeProto;
 
 #[derive(Clone, Debug)]
 pub enum InlineHint {
-    ReplaceCall(Rc<FunctionHead>, Vec<usize>),
+    ReplaceCall(Arc<FunctionHead>, Vec<usize>),
     YieldParameter(usize),
+    /// The call is replaced by a copy of the function's expression.
+    SubstituteExpression(Arc<InlineExpression>),
     NoOp,
 }
 
-pub fn try_inline(implementation: &FunctionImplementation) -> Option<InlineHint> {
+/// A function's single expression, ready to be copied into its callers.
+#[derive(Debug)]
+pub struct InlineExpression {
+    pub root: ExpressionID,
+    pub values: HashMap<ExpressionID, ExpressionOperation>,
+    pub children: HashMap<ExpressionID, Vec<ExpressionID>>,
+    pub types: HashMap<ExpressionID, Arc<TypeProto>>,
+    /// For each parameter, the expression reading it. Each is replaced by the call's argument.
+    pub parameters: Vec<ExpressionID>,
+}
+
+/// Finds out how calls to the implementation can be inlined, if at all.
+/// If substitute_expressions is set, single expression functions are inlined too, not just trivial calls.
+pub fn try_inline(implementation: &FunctionImplementation, substitute_expressions: bool) -> Option<InlineHint> {
     if !implementation.requirements_assumption.conformance.is_empty() {
         // TODO We can probably inline that too, but it would require extracting the abstract function
         //  from the caller. This is non trivial and not needed for now.
         return None;
     }
 
-    match (&implementation.expression_tree.values[&implementation.expression_tree.root], &implementation.expression_tree.children[&implementation.expression_tree.root].as_slice()) {
-        (ExpressionOperation::Block, []) => Some(InlineHint::NoOp),
+    let body = match (&implementation.expression_tree.values[&implementation.expression_tree.root], &implementation.expression_tree.children[&implementation.expression_tree.root].as_slice()) {
+        (ExpressionOperation::Block, []) => return Some(InlineHint::NoOp),
         // While this might result in a return where one wasn't expected,
         // any receiver that can handle a void return won't do anything with the return value.
         // Basically the receiver is guaranteed to be a block or a no-return function. So it's fine.
-        (ExpressionOperation::Block, [arg]) => get_trivial_expression_call_target(arg, implementation),
-        (ExpressionOperation::Return, [arg]) => get_trivial_expression_call_target(arg, implementation),
-        _ => get_trivial_expression_call_target(&implementation.expression_tree.root, implementation),
-    }
+        (ExpressionOperation::Block, [arg]) => arg,
+        (ExpressionOperation::Return, [arg]) => arg,
+        _ => &implementation.expression_tree.root,
+    };
+
+    get_trivial_expression_call_target(body, implementation).or_else(|| {
+        match substitute_expressions {
+            true => get_substitutable_expression(body, implementation).map(|expression| InlineHint::SubstituteExpression(Arc::new(expression))),
+            false => None,
+        }
+    })
 }
 
 pub fn get_trivial_expression_call_target(expression_id: &ExpressionID, implementation: &FunctionImplementation) -> Option<InlineHint> {
@@ -42,13 +66,10 @@ pub fn get_trivial_expression_call_target(expression_id: &ExpressionID, implemen
     match &implementation.expression_tree.values[expression_id] {
         ExpressionOperation::FunctionCall(f) => {
             let replace_args: Vec<_> = implementation.expression_tree.children[expression_id].iter().map(|arg| {
-                match &implementation.expression_tree.values[arg] {
-                    ExpressionOperation::GetLocal(v) => {
-                        if let Some(idx) = implementation.parameter_locals.iter().position(|ref_| ref_ == v) {
-                            return Some(idx)
-                        }
+                if let ExpressionOperation::GetLocal(v) = &implementation.expression_tree.values[arg] {
+                    if let Some(idx) = implementation.parameter_locals.iter().position(|ref_| ref_ == v) {
+                        return Some(idx)
                     }
-                    _ => { },
                 }
 
                 None
@@ -65,7 +86,7 @@ pub fn get_trivial_expression_call_target(expression_id: &ExpressionID, implemen
                 return None
             }
 
-            return Some(InlineHint::ReplaceCall(Rc::clone(&f.function), replace_args))
+            return Some(InlineHint::ReplaceCall(Arc::clone(&f.function), replace_args))
         },
         ExpressionOperation::GetLocal(v) => {
             if let Some(idx) = implementation.parameter_locals.iter().position(|ref_| ref_ == v) {
@@ -78,14 +99,76 @@ pub fn get_trivial_expression_call_target(expression_id: &ExpressionID, implemen
     None
 }
 
+/// Returns the expression if it can be copied into callers as-is.
+/// This is the case if it consists only of calls and literals, and reads every parameter exactly once,
+///  in order. That way, every argument is evaluated exactly once, and in the same order as before.
+pub fn get_substitutable_expression(expression_id: &ExpressionID, implementation: &FunctionImplementation) -> Option<InlineExpression> {
+    if implementation.parameter_locals.is_empty() {
+        // Constants are evaluated just once, and their names make the code more readable.
+        return None
+    }
+
+    let tree = &implementation.expression_tree;
+
+    let mut expression = InlineExpression {
+        root: *expression_id,
+        values: HashMap::new(),
+        children: HashMap::new(),
+        types: HashMap::new(),
+        parameters: vec![],
+    };
+    let mut parameter_idxs = vec![];
+
+    // Depth first, so parameters are found in the order their arguments are evaluated.
+    let mut next = vec![*expression_id];
+    while let Some(current) = next.pop() {
+        let operation = &tree.values[&current];
+        match operation {
+            ExpressionOperation::FunctionCall(f) => {
+                // The function makes no assumptions, so the calls' requirements are fulfilled
+                //  in any caller, too.
+                if f.function == implementation.head {
+                    return None
+                }
+            }
+            ExpressionOperation::GetLocal(v) => {
+                parameter_idxs.push(implementation.parameter_locals.iter().position(|ref_| ref_ == v)?);
+                expression.parameters.push(current);
+            }
+            ExpressionOperation::StringLiteral(_) => {},
+            _ => return None,
+        }
+
+        let type_ = implementation.type_forest.resolve_binding_alias(&current).ok()?;
+        if TypeProto::contains_generics([&type_].into_iter()) {
+            return None
+        }
+
+        expression.values.insert(current, operation.clone());
+        expression.children.insert(current, tree.children[&current].clone());
+        expression.types.insert(current, type_);
+        next.extend(tree.children[&current].iter().rev());
+    }
+
+    if parameter_idxs != (0..implementation.parameter_locals.len()).collect_vec() {
+        return None
+    }
+
+    Some(expression)
+}
+
 pub fn inline_calls(
     implementation: &mut Box<FunctionImplementation>,
-    optimizations: &HashMap<Rc<FunctionBinding>, Rc<FunctionHead>>,
-    hints: &HashMap<Rc<FunctionHead>, InlineHint>,
+    optimizations: &HashMap<Arc<FunctionBinding>, Arc<FunctionHead>>,
+    hints: &HashMap<Arc<FunctionHead>, InlineHint>,
 ) {
     let expression_forest = &mut implementation.expression_tree;
 
-    'expression: for expression_id in expression_forest.deep_children(expression_forest.root) {
+    // Substituted expressions add to the tree, so they are queued to be inlined as well.
+    let mut next = expression_forest.deep_children(expression_forest.root);
+    next.reverse();
+
+    'expression: while let Some(expression_id) = next.pop() {
         // Essentially, we run through the expression tree. When we change an operation,
         //  we run through it again because there may be more mappings.
         'inline: loop {
@@ -100,8 +183,8 @@ pub fn inline_calls(
                 ExpressionOperation::FunctionCall(f) => {
                     if let Some(optimized_head) = optimizations.get(&resolve_binding(f, &implementation.type_forest)) {
                         let operation = expression_forest.values.get_mut(&expression_id).unwrap();
-                        *operation = ExpressionOperation::FunctionCall(Rc::new(FunctionBinding {
-                            function: Rc::clone(&optimized_head),
+                        *operation = ExpressionOperation::FunctionCall(Arc::new(FunctionBinding {
+                            function: Arc::clone(optimized_head),
                             // TODO If we're not fully monomorphized, this may not be empty.
                             requirements_fulfillment: RequirementsFulfillment::empty(),
                         }));
@@ -112,8 +195,8 @@ pub fn inline_calls(
                         match inline_hint {
                             InlineHint::ReplaceCall(target_function, idxs) => {
                                 let operation = expression_forest.values.get_mut(&expression_id).unwrap();
-                                *operation = ExpressionOperation::FunctionCall(Rc::new(FunctionBinding {
-                                    function: Rc::clone(&target_function),
+                                *operation = ExpressionOperation::FunctionCall(Arc::new(FunctionBinding {
+                                    function: Arc::clone(target_function),
                                     // TODO If we're not monomorphized, this may not be empty.
                                     requirements_fulfillment: RequirementsFulfillment::empty(),
                                 }));
@@ -124,9 +207,63 @@ pub fn inline_calls(
                                 expression_forest.inline(expression_id, *idx);
                                 continue 'inline
                             },
-                            InlineHint::NoOp => {
-                                todo!("Should do this in another loop and remove if it's in a block, otherwise throw");
+                            InlineHint::SubstituteExpression(expression) => {
+                                let arguments = expression_forest.children[&expression_id].clone();
+                                // The expression's root takes the call's place; the rest is copied with new IDs.
+                                let ids: HashMap<_, _> = expression.values.keys()
+                                    .map(|id| (*id, if id == &expression.root { expression_id } else { Uuid::new_v4() }))
+                                    .collect();
+                                let new_id = |id: &ExpressionID| match expression.parameters.iter().position(|parameter| parameter == id) {
+                                    Some(idx) => arguments[idx],
+                                    None => ids[id],
+                                };
+
+                                for (id, operation) in expression.values.iter() {
+                                    if expression.parameters.contains(id) {
+                                        continue  // Replaced by the argument.
+                                    }
+
+                                    let copy = ids[id];
+                                    let children = expression.children[id].iter().map(new_id).collect_vec();
+                                    for child in children.iter() {
+                                        expression_forest.parents.insert(*child, copy);
+                                    }
+                                    expression_forest.values.insert(copy, operation.clone());
+                                    expression_forest.children.insert(copy, children);
+
+                                    if copy != expression_id {
+                                        implementation.type_forest.bind(copy, &expression.types[id]).unwrap();
+                                        next.push(copy);
+                                    }
+                                }
                                 continue 'inline
+                            }
+                            InlineHint::NoOp => {
+                                // The arguments may have side effects, so they are kept as statements
+                                //  unless they just read a local or literal.
+                                let (statements, dropped): (Vec<_>, Vec<_>) = std::mem::take(expression_forest.children.get_mut(&expression_id).unwrap())
+                                    .into_iter()
+                                    .partition(|argument| !matches!(expression_forest.values[argument], ExpressionOperation::GetLocal(_) | ExpressionOperation::StringLiteral(_)));
+                                expression_forest.truncate_down(dropped);
+
+                                // Inside a block, the call is replaced by the statements. Anywhere else,
+                                //  it becomes a (void) block of them.
+                                match expression_forest.parents.get(&expression_id).cloned() {
+                                    Some(parent) if matches!(expression_forest.values[&parent], ExpressionOperation::Block) => {
+                                        for statement in statements.iter() {
+                                            expression_forest.parents.insert(*statement, parent);
+                                        }
+                                        let siblings = expression_forest.children.get_mut(&parent).unwrap();
+                                        let idx = siblings.iter().position(|child| child == &expression_id).unwrap();
+                                        siblings.splice(idx..=idx, statements);
+                                        expression_forest.truncate_down(vec![expression_id]);
+                                    }
+                                    _ => {
+                                        *expression_forest.values.get_mut(&expression_id).unwrap() = ExpressionOperation::Block;
+                                        *expression_forest.children.get_mut(&expression_id).unwrap() = statements;
+                                    }
+                                }
+                                continue 'expression
                             },
                         }
                     }
@@ -1,5 +1,5 @@
 use std::collections::HashSet;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 
@@ -8,31 +8,30 @@ use crate::program::expression_tree::ExpressionOperation;
 use crate::program::functions::{FunctionHead, FunctionInterface};
 use crate::program::global::FunctionImplementation;
 
-pub fn swizzle_retaining_parameters(function: &FunctionImplementation, removed: &HashSet<Rc<ObjectReference>>) -> Vec<usize> {
+pub fn swizzle_retaining_parameters(function: &FunctionImplementation, removed: &HashSet<Arc<ObjectReference>>) -> Vec<usize> {
     function.parameter_locals.iter().enumerate()
-        .filter_map(|(idx, local)| (!removed.contains(local)).then(|| idx))
+        .filter_map(|(idx, local)| (!removed.contains(local)).then_some(idx))
         .collect_vec()
 }
 
-pub fn find_unused_locals(function: &FunctionImplementation) -> HashSet<Rc<ObjectReference>> {
+pub fn find_unused_locals(function: &FunctionImplementation) -> HashSet<Arc<ObjectReference>> {
     let mut unused = HashSet::from_iter(function.locals_names.keys().cloned());
 
     for operation in function.expression_tree.values.values() {
-        match operation {
-            ExpressionOperation::GetLocal(local) => _ = unused.remove(local),
-            _ => {},
+        if let ExpressionOperation::GetLocal(local) = operation {
+            unused.remove(local);
         }
     }
 
-    return unused
+    unused
 }
 
-pub fn remove_locals(implementation: &mut FunctionImplementation, removed_locals: &HashSet<Rc<ObjectReference>>) -> Option<Vec<usize>> {
+pub fn remove_locals(implementation: &mut FunctionImplementation, removed_locals: &HashSet<Arc<ObjectReference>>) -> Option<Vec<usize>> {
     let changes_interface = removed_locals.iter().any(|l| implementation.parameter_locals.contains(l));
 
-    let mut expression_forest = &mut implementation.expression_tree;
+    let expression_forest = &mut implementation.expression_tree;
     // TODO Also truncate removed from type forest
-    let mut type_forest = &mut implementation.type_forest;
+    let _type_forest = &mut implementation.type_forest;
 
     for expression_id in expression_forest.values.keys().cloned().collect_vec() {
         let Some(operation) = expression_forest.values.get(&expression_id) else {
@@ -40,29 +39,27 @@ pub fn remove_locals(implementation: &mut FunctionImplementation, removed_locals
         };
 
         match operation {
-            ExpressionOperation::GetLocal(local) => {
-                if removed_locals.contains(local) {
+            ExpressionOperation::GetLocal(local)
+                if removed_locals.contains(local) => {
                     expression_forest.truncate_up_and_down(vec![expression_id], |op| op == &ExpressionOperation::Block);
                 }
-            }
-            ExpressionOperation::SetLocal(local) => {
-                if removed_locals.contains(local) {
+            ExpressionOperation::SetLocal(local)
+                if removed_locals.contains(local) => {
                     expression_forest.truncate_up_and_down(vec![expression_id], |op| op == &ExpressionOperation::Block);
                 }
-            }
             _ => {},
         }
     }
 
     implementation.locals_names = implementation.locals_names.drain()
-        .filter(|(key, value)| !removed_locals.contains(key))
+        .filter(|(key, _value)| !removed_locals.contains(key))
         .collect();
 
     if changes_interface {
         let swizzle = swizzle_retaining_parameters(implementation, removed_locals);
 
         // TODO We may be able to remove some generics and requirements.
-        let new_head = FunctionHead::new(Rc::new(FunctionInterface {
+        let new_head = FunctionHead::new(Arc::new(FunctionInterface {
             parameters: swizzle.iter().map(|idx| implementation.head.interface.parameters[*idx].clone()).collect_vec(),
             return_type: implementation.head.interface.return_type.clone(),
             requirements: implementation.head.interface.requirements.clone(),
@@ -72,9 +69,9 @@ pub fn remove_locals(implementation: &mut FunctionImplementation, removed_locals
         implementation.head = new_head;
         implementation.parameter_locals = swizzle.iter().map(|idx| implementation.parameter_locals[*idx].clone()).collect_vec();
 
-        return Some(swizzle)
+        Some(swizzle)
     }
     else {
-        return None
+        None
     }
 }
@@ -1,19 +1,20 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use linked_hash_set::LinkedHashSet;
 
 use crate::program::allocation::ObjectReference;
 use crate::program::calls::FunctionBinding;
-use crate::program::expression_tree::ExpressionOperation;
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
 use crate::program::functions::{FunctionHead, FunctionInterface, FunctionType, Parameter};
 use crate::program::generics::TypeForest;
-use crate::program::global::FunctionImplementation;
+use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor, Reflection};
 use crate::program::traits::{RequirementsAssumption, RequirementsFulfillment, Trait, TraitConformanceWithTail};
-use crate::program::types::TypeProto;
+use crate::program::types::{TypeProto, TypeUnit};
+use crate::source::Source;
 
-pub fn monomorphize_implementation(implementation: &mut FunctionImplementation, function_binding: &FunctionBinding) -> LinkedHashSet<Rc<FunctionBinding>> {
+pub fn monomorphize_implementation(implementation: &mut FunctionImplementation, function_binding: &FunctionBinding) -> LinkedHashSet<Arc<FunctionBinding>> {
     let mut encountered_calls = LinkedHashSet::new();
 
     // Map types.
@@ -26,29 +27,29 @@ pub fn monomorphize_implementation(implementation: &mut FunctionImplementation,
     // TODO For fully internal variables, it would be enough to set the type to the Any's corresponding Generic,
     //  because those have been bound in the type forest. For variables featured in the interface, however, the
     //  type must be properly resolved. So we might as well map all variables to resolved types.
-    let locals_map: HashMap<Rc<ObjectReference>, Rc<ObjectReference>> = implementation.locals_names.keys()
+    let locals_map: HashMap<Arc<ObjectReference>, Arc<ObjectReference>> = implementation.locals_names.keys()
         .map(|v| {
-            (Rc::clone(v), map_variable(v, &implementation.type_forest, &generic_replacement_map))
+            (Arc::clone(v), map_variable(v, &implementation.type_forest, generic_replacement_map))
         })
         .collect();
 
     // Find function calls in the expression forest
     for expression_id in implementation.expression_tree.deep_children(implementation.expression_tree.root) {
-        let mut operation = implementation.expression_tree.values.get_mut(&expression_id).unwrap();
+        let operation = implementation.expression_tree.values.get_mut(&expression_id).unwrap();
 
         match operation {
             ExpressionOperation::FunctionCall(call) => {
-                let resolved_call = resolve_call(call, &function_binding.requirements_fulfillment, &generic_replacement_map, &implementation.type_forest);
-                encountered_calls.insert_if_absent(Rc::clone(&resolved_call));
+                let resolved_call = resolve_call(call, &function_binding.requirements_fulfillment, generic_replacement_map, &implementation.type_forest);
+                encountered_calls.insert_if_absent(Arc::clone(&resolved_call));
                 *operation = ExpressionOperation::FunctionCall(resolved_call)
             }
             ExpressionOperation::PairwiseOperations { calls } => {
                 *operation = ExpressionOperation::PairwiseOperations {
                     calls: calls.iter()
                         .map(|call| {
-                            let resolved_call = resolve_call(call, &function_binding.requirements_fulfillment, &generic_replacement_map, &implementation.type_forest);
+                            let resolved_call = resolve_call(call, &function_binding.requirements_fulfillment, generic_replacement_map, &implementation.type_forest);
 
-                            encountered_calls.insert_if_absent(Rc::clone(&resolved_call));
+                            encountered_calls.insert_if_absent(Arc::clone(&resolved_call));
 
                             resolved_call
                         }).collect_vec()
@@ -56,13 +57,15 @@ pub fn monomorphize_implementation(implementation: &mut FunctionImplementation,
             }
             ExpressionOperation::GetLocal(v) => {
                 // If we cannot find a replacement, it's a static variable. Unless we have a bug.
-                *operation = ExpressionOperation::GetLocal(Rc::clone(locals_map.get(v).unwrap_or(v)))
+                *operation = ExpressionOperation::GetLocal(Arc::clone(locals_map.get(v).unwrap_or(v)))
             }
             ExpressionOperation::SetLocal(v) => {
-                *operation = ExpressionOperation::SetLocal(Rc::clone(locals_map.get(v).unwrap_or(v)))
+                *operation = ExpressionOperation::SetLocal(Arc::clone(locals_map.get(v).unwrap_or(v)))
             }
             ExpressionOperation::ArrayLiteral => {},
             ExpressionOperation::StringLiteral(_) => {},
+            ExpressionOperation::TypeCheck(_) => {},
+            ExpressionOperation::TypeCast(_) => {},
             ExpressionOperation::Block => {},
             ExpressionOperation::Return => {}
             ExpressionOperation::IfThenElse => {}
@@ -71,23 +74,74 @@ pub fn monomorphize_implementation(implementation: &mut FunctionImplementation,
 
     // Update parameter variables
     for param_variable in implementation.parameter_locals.iter_mut() {
-        *param_variable = Rc::clone(&locals_map[param_variable])
+        *param_variable = Arc::clone(&locals_map[param_variable])
     }
     implementation.locals_names = implementation.locals_names.drain().map(|(key, value)| {
-        (Rc::clone(&locals_map[&key]), value)
+        (Arc::clone(&locals_map[&key]), value)
     }).collect();
 
     // Requirements
     // TODO This is correct only if all requirements have been fulfilled.
     //  If monomorphize was requested on a partially generic function, we continue to
     //  have some requirements.
-    implementation.requirements_assumption = Box::new(RequirementsAssumption { conformance: Default::default() });
+    *implementation.requirements_assumption = RequirementsAssumption { conformance: Default::default() };
     implementation.head = monomorphize_head(function_binding);
 
     encountered_calls
 }
 
-pub fn resolve_call(call: &Rc<FunctionBinding>, context: &RequirementsFulfillment, generic_replacement_map: &HashMap<Rc<Trait>, Rc<TypeProto>>, type_forest: &TypeForest) -> Rc<FunctionBinding> {
+/// Replaces calls to reflection functions with literals of what they describe.
+/// The resolver made sure the described types are known once the function is monomorphic.
+pub fn substitute_reflection(implementation: &mut FunctionImplementation, source: &Source) {
+    let calls = implementation.expression_tree.values.iter()
+        .filter_map(|(expression_id, operation)| {
+            let ExpressionOperation::FunctionCall(binding) = operation else {
+                return None
+            };
+            match source.fn_logic.get(&binding.function) {
+                Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::Reflection(reflection))) => Some((*expression_id, *reflection)),
+                _ => None,
+            }
+        })
+        .collect_vec();
+
+    for (expression_id, reflection) in calls {
+        let tree = &mut implementation.expression_tree;
+        let types = &mut implementation.type_forest;
+
+        // The argument is only used for its type, so it is never evaluated.
+        let arguments = std::mem::take(tree.children.get_mut(&expression_id).unwrap());
+        let subject = types.resolve_binding_alias(&arguments[0]).unwrap();
+        tree.truncate_down(arguments);
+
+        match reflection {
+            Reflection::TypeName => {
+                tree.values.insert(expression_id, ExpressionOperation::StringLiteral(format!("{:?}", subject)));
+            }
+            Reflection::FieldsOf => {
+                let TypeUnit::Struct(trait_) = &subject.arguments[0].unit else {
+                    panic!("fields_of was called on a type that is not a struct: {:?}", subject);
+                };
+                let struct_ = &source.struct_by_trait[trait_];
+                let string_type = Arc::clone(&types.resolve_binding_alias(&expression_id).unwrap().arguments[0]);
+
+                let elements = struct_.fields.iter().map(|field| {
+                    let element = ExpressionID::new_v4();
+                    types.bind(element, &string_type).unwrap();
+                    tree.values.insert(element, ExpressionOperation::StringLiteral(struct_.field_names[field].clone()));
+                    tree.children.insert(element, vec![]);
+                    tree.parents.insert(element, expression_id);
+                    element
+                }).collect_vec();
+
+                tree.values.insert(expression_id, ExpressionOperation::ArrayLiteral);
+                tree.children.insert(expression_id, elements);
+            }
+        }
+    }
+}
+
+pub fn resolve_call(call: &Arc<FunctionBinding>, context: &RequirementsFulfillment, generic_replacement_map: &HashMap<Arc<Trait>, Arc<TypeProto>>, type_forest: &TypeForest) -> Arc<FunctionBinding> {
     // A function can have multiple requirements. They must be fully fulfilled after monomorphization.
     // Each requirement has two routes it can be fulfilled from:
     // 1) The caller has already fulfilled the requirement, and it is passed here in the function replacement map as its tail.
@@ -101,11 +155,11 @@ pub fn resolve_call(call: &Rc<FunctionBinding>, context: &RequirementsFulfillmen
         type_forest
     );
 
-    let function: Rc<FunctionHead>;
+    let function: Arc<FunctionHead>;
     if let FunctionType::Polymorphic { assumed_requirement, abstract_function } = &call.function.function_type {
         let TraitConformanceWithTail {conformance, tail} = context.conformance[assumed_requirement].as_ref();
 
-        function = Rc::clone(&conformance.function_mapping[abstract_function]);
+        function = Arc::clone(&conformance.function_mapping[abstract_function]);
 
         if !tail.is_empty() {
             // TODO I think this is correct?
@@ -116,16 +170,16 @@ pub fn resolve_call(call: &Rc<FunctionBinding>, context: &RequirementsFulfillmen
         }
     }
     else {
-        function = Rc::clone(&call.function)
+        function = Arc::clone(&call.function)
     }
 
-    return Rc::new(FunctionBinding {
+    Arc::new(FunctionBinding {
         function,
-        requirements_fulfillment: Rc::new(requirements_fulfillment),
+        requirements_fulfillment: Arc::new(requirements_fulfillment),
     })
 }
 
-fn map_requirements_fulfillment(rc: &Rc<RequirementsFulfillment>, context: &RequirementsFulfillment, generic_replacement_map: &HashMap<Rc<Trait>, Rc<TypeProto>>, type_forest: &TypeForest) -> RequirementsFulfillment {
+fn map_requirements_fulfillment(rc: &Arc<RequirementsFulfillment>, context: &RequirementsFulfillment, generic_replacement_map: &HashMap<Arc<Trait>, Arc<TypeProto>>, type_forest: &TypeForest) -> RequirementsFulfillment {
     // A requirements fulfillment (for a function call) consists of many conformances to requirements.
     // Every conformance either:
     // 1) Uses some global conformance declaration. In this case, it's already correct - except for
@@ -141,21 +195,21 @@ fn map_requirements_fulfillment(rc: &Rc<RequirementsFulfillment>, context: &Requ
     RequirementsFulfillment {
         conformance: rc.conformance.iter()
             .map(|(requirement, conformance)| {
-                return (
-                    Rc::clone(requirement),
+                (
+                    Arc::clone(requirement),
                     if let Some(replacement) = context.conformance.get(&conformance.conformance.binding) {
                         // Conformance was abstract / has been mapped by the caller.
-                        Rc::clone(replacement)
+                        Arc::clone(replacement)
                     } else {
                         if conformance.tail.is_empty() {
                             // Conformance is static / good as-is.
-                            Rc::clone(conformance)
+                            Arc::clone(conformance)
                         } else {
                             // Conformance is static.
                             // We still need to map its tail because it may use requirements assumptions.
-                            Rc::new(TraitConformanceWithTail {
-                                conformance: Rc::clone(&conformance.conformance),
-                                tail: Rc::new(
+                            Arc::new(TraitConformanceWithTail {
+                                conformance: Arc::clone(&conformance.conformance),
+                                tail: Arc::new(
                                     map_requirements_fulfillment(&conformance.tail, context, generic_replacement_map, type_forest)
                                 ),
                             })
@@ -165,38 +219,39 @@ fn map_requirements_fulfillment(rc: &Rc<RequirementsFulfillment>, context: &Requ
             })
             .collect(),
         generic_mapping: rc.generic_mapping.iter().map(|(trait_, type_)| {
-            (Rc::clone(trait_), type_forest.resolve_type(type_).unwrap().replacing_structs(generic_replacement_map))
+            (Arc::clone(trait_), type_forest.resolve_type(type_).unwrap().replacing_structs(generic_replacement_map))
         }).collect(),
     }
 }
 
-pub fn monomorphize_head(binding: &FunctionBinding) -> Rc<FunctionHead> {
+pub fn monomorphize_head(binding: &FunctionBinding) -> Arc<FunctionHead> {
     FunctionHead::new(
-        Rc::new(map_interface_types(&binding.function.interface, &binding.requirements_fulfillment.generic_mapping)),
+        Arc::new(map_interface_types(&binding.function.interface, &binding.requirements_fulfillment.generic_mapping)),
         binding.function.function_type.clone(),
     )
 }
 
-pub fn map_variable(variable: &ObjectReference, type_forest: &TypeForest, type_replacement_map: &HashMap<Rc<Trait>, Rc<TypeProto>>) -> Rc<ObjectReference> {
-    Rc::new(ObjectReference {
-        id: variable.id.clone(),
+pub fn map_variable(variable: &ObjectReference, type_forest: &TypeForest, type_replacement_map: &HashMap<Arc<Trait>, Arc<TypeProto>>) -> Arc<ObjectReference> {
+    Arc::new(ObjectReference {
+        id: variable.id,
         type_: type_forest.resolve_type(&variable.type_).unwrap().replacing_structs(type_replacement_map),
-        mutability: variable.mutability.clone(),
+        mutability: variable.mutability,
     })
 }
 
-pub fn map_interface_types(interface: &FunctionInterface, mapping: &HashMap<Rc<Trait>, Rc<TypeProto>>) -> FunctionInterface {
+pub fn map_interface_types(interface: &FunctionInterface, mapping: &HashMap<Arc<Trait>, Arc<TypeProto>>) -> FunctionInterface {
     FunctionInterface {
         parameters: interface.parameters.iter().map(|x| Parameter {
             external_key: x.external_key.clone(),
             internal_name: x.internal_name.clone(),
             type_: x.type_.replacing_structs(mapping),
+            mutability: x.mutability,
         }).collect(),
         return_type: interface.return_type.replacing_structs(mapping),
         requirements: interface.requirements.iter().map(|x| x.mapping_types(&|type_| type_.replacing_structs(mapping))).collect(),
         // TODO Not sure if this is correct - if the mapping introduces MORE generics again, the new
         //  value is wrong. Luckily, this is not a use-case of ours for now - it will only be relevant
         //  when generic transpilation is allowed.
-        generics: interface.generics.iter().filter(|(k, v)| !mapping.contains_key(*v)).map(|(a, b)| (a.clone(), b.clone())).collect(),
+        generics: interface.generics.iter().filter(|(_k, v)| !mapping.contains_key(*v)).map(|(a, b)| (a.clone(), b.clone())).collect(),
     }
 }
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use display_with_options::with_options;
+use itertools::Itertools;
+
+use crate::program::functions::FunctionHead;
+use crate::program::global::FunctionLogic;
+use crate::refactor::inline::InlineHint;
+use crate::refactor::Refactor;
+use crate::util::json;
+
+pub enum InliningDecision {
+    /// Calls to the function were replaced according to the hint.
+    Inlined(InlineHint),
+    /// Explicit functions are part of the output's interface, so they may not be inlined.
+    KeptExplicit,
+    /// The function is not trivial enough to be inlined.
+    Kept,
+}
+
+pub struct InliningEntry {
+    pub head: Arc<FunctionHead>,
+    pub name: String,
+    pub decision: InliningDecision,
+    /// Names of the callers that were rewritten as a result of inlining.
+    pub rewritten_callers: Vec<String>,
+}
+
+/// What the refactor decided to inline, for every implemented function it knows about.
+pub struct InliningReport {
+    pub entries: Vec<InliningEntry>,
+}
+
+impl InliningReport {
+    pub fn new(refactor: &Refactor) -> InliningReport {
+        let implemented = refactor.fn_logic.iter()
+            .filter(|(_, logic)| matches!(logic, FunctionLogic::Implementation(_)))
+            .map(|(head, _)| head);
+
+        let entries = implemented.chain(refactor.fn_inline_hints.keys())
+            .unique()
+            .map(|head| {
+                let decision = match refactor.fn_inline_hints.get(head) {
+                    Some(hint) => InliningDecision::Inlined(hint.clone()),
+                    None if refactor.explicit_functions.contains(head) => InliningDecision::KeptExplicit,
+                    None => InliningDecision::Kept,
+                };
+                let rewritten_callers = refactor.fn_inlined_callers.get(head).into_iter().flatten()
+                    .map(|caller| function_name(refactor, caller))
+                    .sorted()
+                    .collect_vec();
+
+                InliningEntry { head: Arc::clone(head), name: function_name(refactor, head), decision, rewritten_callers }
+            })
+            .sorted_by(|a, b| (&a.name, a.head.function_id).cmp(&(&b.name, b.head.function_id)))
+            .collect_vec();
+
+        InliningReport { entries }
+    }
+
+    pub fn to_json(&self, refactor: &Refactor) -> String {
+        let mut output = String::new();
+
+        output.push_str("{\"functions\": [");
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if idx > 0 { output.push_str(", ") }
+
+            output.push_str("{\"function\": ");
+            json::write_string(&mut output, &entry.name);
+            write!(output, ", \"id\": \"{}\", \"decision\": ", entry.head.function_id).unwrap();
+            match &entry.decision {
+                InliningDecision::Inlined(hint) => {
+                    output.push_str("\"inlined\", \"hint\": ");
+                    write_json_hint(&mut output, hint, refactor);
+                }
+                InliningDecision::KeptExplicit => output.push_str("\"kept_explicit\""),
+                InliningDecision::Kept => output.push_str("\"kept\""),
+            }
+            output.push_str(", \"rewritten_callers\": [");
+            for (idx, caller) in entry.rewritten_callers.iter().enumerate() {
+                if idx > 0 { output.push_str(", ") }
+                json::write_string(&mut output, caller);
+            }
+            output.push_str("]}");
+        }
+        output.push_str("]}");
+
+        output
+    }
+}
+
+fn write_json_hint(output: &mut String, hint: &InlineHint, refactor: &Refactor) {
+    match hint {
+        InlineHint::ReplaceCall(target, swizzle) => {
+            output.push_str("{\"replace_call\": ");
+            json::write_string(output, &function_name(refactor, target));
+            write!(output, ", \"arguments\": [{}]}}", swizzle.iter().join(", ")).unwrap();
+        }
+        InlineHint::YieldParameter(idx) => write!(output, "{{\"yield_parameter\": {}}}", idx).unwrap(),
+        InlineHint::SubstituteExpression(_) => output.push_str("\"substitute_expression\""),
+        InlineHint::NoOp => output.push_str("\"no_op\""),
+    }
+}
+
+fn function_name(refactor: &Refactor, head: &Arc<FunctionHead>) -> String {
+    match refactor.fn_representations.get(head).or_else(|| refactor.runtime.source.fn_representations.get(head)) {
+        Some(representation) => format!("{:?}", with_options(head.interface.as_ref(), representation)),
+        None => format!("{:?}", head),
+    }
+}
@@ -1,9 +1,15 @@
+use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::RandomState;
+use std::sync::Arc;
 
 use linked_hash_set::LinkedHashSet;
 
-use crate::program::global::FunctionLogic;
-use crate::refactor::{locals, Refactor};
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
+use crate::program::functions::FunctionHead;
+use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor, PrimitiveOperation};
+use crate::refactor::{constants, locals, Refactor};
+use crate::static_analysis::purity;
+use crate::static_analysis::purity::Impurity;
 use crate::transpiler::Config;
 
 pub struct Simplify<'a, 'b> {
@@ -11,6 +17,11 @@ pub struct Simplify<'a, 'b> {
     pub inline: bool,
     pub trim_locals: bool,
     pub monomorphize: bool,
+    pub constant_fold: bool,
+    /// Impure functions, including those the refactor created.
+    pub impure_functions: HashMap<Arc<FunctionHead>, Impurity>,
+    /// Implementations that may fail, because they call something that may fail.
+    pub fallible_functions: HashSet<Arc<FunctionHead>>,
 }
 
 impl<'a, 'b> Simplify<'a, 'b> {
@@ -19,11 +30,15 @@ impl<'a, 'b> Simplify<'a, 'b> {
             todo!();  // Lots of reasons non-monomorphization doesn't work right now.
         }
 
+        let impure_functions = refactor.runtime.source.impure_functions.clone();
         Simplify {
             refactor,
             inline: config.should_inline,
             trim_locals: config.should_trim_locals,
             monomorphize: config.should_monomorphize,
+            constant_fold: config.should_constant_fold,
+            impure_functions,
+            fallible_functions: HashSet::new(),
         }
     }
 
@@ -44,14 +59,39 @@ impl<'a, 'b> Simplify<'a, 'b> {
         // Make sure refactor has everything that's needed so we can simplify it.
         self.refactor.gather_needed_functions();
 
+        // Monomorphization created new functions, whose purity we don't know yet.
+        //  Simplification only removes calls, so functions can't become impure while we're at it.
+        purity::infer(
+            self.refactor.fn_logic.values().filter_map(|logic| logic.as_implementation().ok()),
+            &self.refactor.fn_logic,
+            &mut self.impure_functions,
+        );
+        self.infer_fallible_functions();
+
         // Now, let's simplify!
         let mut next: LinkedHashSet<_, RandomState> = LinkedHashSet::from_iter(self.refactor.fn_logic.keys().cloned());
-        while let Some(current) = next.pop_front() {
-            let is_explicit = self.refactor.explicit_functions.contains(&current);
+        // Functions whose single expression may be substituted into their callers.
+        //  This is postponed until nothing else is left to do, so trivial inlining takes precedence.
+        let mut substitute_next: LinkedHashSet<_, RandomState> = LinkedHashSet::new();
+        loop {
+            let Some(current) = next.pop_front() else {
+                let Some(current) = substitute_next.pop_front() else {
+                    break
+                };
+
+                if self.refactor.fn_logic.contains_key(&current) {
+                    if let Some(affected) = self.refactor.try_inline(&current, true) {
+                        next.extend(affected);
+                    }
+                }
+                continue
+            };
+            // Vtables refer to their functions by head, so they keep it like explicit functions do.
+            let is_explicit = self.refactor.explicit_functions.contains(&current) || self.refactor.vtable_functions.contains(&current);
 
             if !is_explicit && self.inline {
                 // Try to inline the function if it's trivial.
-                if let Ok(affected) = self.refactor.try_inline(&current) {
+                if let Some(affected) = self.refactor.try_inline(&current, false) {
                     // Try inlining those that changed again.
                     // TODO This could be more efficient: It only makes sense to change functions once.
                     //  The inlining call can be delayed until we're sure we can either be inlined
@@ -61,6 +101,37 @@ impl<'a, 'b> Simplify<'a, 'b> {
                     // The function was inlined; there's no need to do anything else.
                     continue
                 }
+
+                // Constant folding also inlines functions that consist of a single expression.
+                if self.constant_fold {
+                    substitute_next.insert(current.clone());
+                }
+            }
+
+            // Try to move constant values to where they are used.
+            if self.constant_fold {
+                if let FunctionLogic::Implementation(implementation) = &self.refactor.fn_logic[&current] {
+                    let propagate = constants::find_propagatable_locals(implementation, |head, arguments| self.can_move(implementation, head, arguments));
+
+                    if !propagate.is_empty() {
+                        next.extend(self.refactor.swizzle_implementation(&current, |imp| {
+                            constants::propagate_locals(imp, &propagate);
+                            None
+                        }));
+                    }
+                };
+
+                // Identical constant expressions can be computed just once.
+                if let FunctionLogic::Implementation(implementation) = &self.refactor.fn_logic[&current] {
+                    let shared = constants::find_shared_expressions(implementation, |head, arguments| self.can_move(implementation, head, arguments));
+
+                    if !shared.is_empty() {
+                        next.extend(self.refactor.swizzle_implementation(&current, |imp| {
+                            constants::share_expressions(imp, &shared);
+                            None
+                        }));
+                    }
+                };
             }
 
             // Try to remove unused parameters for the function.
@@ -86,4 +157,57 @@ impl<'a, 'b> Simplify<'a, 'b> {
             }
         }
     }
+
+    /// Whether a call can be moved, shared or evaluated early.
+    /// The call must be pure, and mustn't fail, or it might run where a condition used to guard it.
+    fn can_move(&self, implementation: &FunctionImplementation, head: &Arc<FunctionHead>, arguments: &[ExpressionID]) -> bool {
+        if self.can_fail(implementation, head, arguments) {
+            return false
+        }
+
+        let logic = self.refactor.fn_logic.get(head).or_else(|| self.refactor.runtime.source.fn_logic.get(head));
+        match logic {
+            Some(FunctionLogic::Descriptor(descriptor)) => descriptor.is_pure(),
+            Some(FunctionLogic::Implementation(_)) => !self.impure_functions.contains_key(head),
+            None => false,
+        }
+    }
+
+    /// Whether the call may fail.
+    ///  Literals are parsed by calls that can fail, but they were range-checked during resolution.
+    fn can_fail(&self, implementation: &FunctionImplementation, head: &Arc<FunctionHead>, arguments: &[ExpressionID]) -> bool {
+        let logic = self.refactor.fn_logic.get(head).or_else(|| self.refactor.runtime.source.fn_logic.get(head));
+        match logic {
+            Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::PrimitiveOperation { operation, .. })) if operation.can_fail() => {
+                !(matches!(operation, PrimitiveOperation::ParseIntString | PrimitiveOperation::ParseRealString | PrimitiveOperation::ParseCharString)
+                    && matches!(arguments, [argument] if matches!(implementation.expression_tree.values[argument], ExpressionOperation::StringLiteral(_))))
+            }
+            Some(FunctionLogic::Implementation(_)) => self.fallible_functions.contains(head),
+            _ => false,
+        }
+    }
+
+    /// Finds the implementations that may fail, like purity::infer does for impure ones.
+    /// Calls to fallible implementations (e.g. a / b) may not be inlined yet, so they mustn't be moved either.
+    fn infer_fallible_functions(&mut self) {
+        loop {
+            let fallible = self.refactor.fn_logic.iter()
+                .filter_map(|(head, logic)| Some((head, logic.as_implementation().ok()?)))
+                .filter(|(head, implementation)| !self.fallible_functions.contains(*head) && {
+                    let tree = &implementation.expression_tree;
+                    tree.values.iter().any(|(expression, operation)| match operation {
+                        ExpressionOperation::FunctionCall(binding) => self.can_fail(implementation, &binding.function, &tree.children[expression]),
+                        _ => false,
+                    })
+                })
+                .map(|(head, _)| Arc::clone(head))
+                .collect::<Vec<_>>();
+
+            // Fallibility only spreads, so we're done once a pass makes no progress.
+            if fallible.is_empty() {
+                return
+            }
+            self.fallible_functions.extend(fallible);
+        }
+    }
 }
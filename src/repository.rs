@@ -1,11 +1,40 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+
 use crate::error::{RResult, RuntimeError};
+use crate::program::module::{module_name, ModuleName};
+use crate::util::strings::closest_match;
+
+/// Library sources compiled into the binary, so programs run without the monoteny directory.
+/// Keys are module paths relative to the monoteny directory. The table is generated by build.rs.
+pub const BUNDLED_SOURCES: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/bundled_sources.rs"));
+
+/// The directory the standard libraries (core and common) are read from, if present.
+pub const STD_DIRECTORY: &str = "monoteny";
 
-use crate::program::module::ModuleName;
+/// The name of project files, looked up from the compiled file's directory upwards.
+pub const PROJECT_FILE_NAME: &str = "monoteny.toml";
 
+#[derive(Clone)]
+pub enum Loader {
+    /// Modules are files in the directory, named by their path.
+    Path(PathBuf),
+    /// Modules are sources keyed by their path, e.g. "common/math".
+    Memory(HashMap<String, String>),
+    /// Modules are files in the directory if they exist, and sources keyed by their path otherwise.
+    Layered(PathBuf, HashMap<String, String>),
+}
+
+pub enum ModuleSource {
+    File(PathBuf),
+    Text(String),
+}
+
+#[derive(Clone)]
 pub struct Repository {
-    pub entries: HashMap<String, PathBuf>,
+    pub entries: HashMap<String, Loader>,
 }
 
 impl Repository {
@@ -16,18 +45,227 @@ impl Repository {
     }
 
     pub fn add(&mut self, name: &str, path: PathBuf) {
-        self.entries.insert(name.to_string(), path);
+        self.entries.insert(name.to_string(), Loader::Path(path));
+    }
+
+    pub fn add_sources(&mut self, name: &str, sources: HashMap<String, String>) {
+        self.entries.insert(name.to_string(), Loader::Memory(sources));
     }
 
-    pub fn resolve_module_path(&self, name: &ModuleName) -> RResult<PathBuf> {
+    /// Adds the library from BUNDLED_SOURCES.
+    pub fn add_bundled(&mut self, name: &str) {
+        self.add_sources(name, bundled_sources(name));
+    }
+
+    /// Adds a standard library, like core or common.
+    /// Files in STD_DIRECTORY override the embedded sources, so the libraries can be edited without rebuilding.
+    /// Without embedded sources, the library is read from STD_DIRECTORY only.
+    #[cfg(feature = "native")]
+    pub fn add_std(&mut self, name: &str, embedded: bool) {
+        match embedded {
+            true => self.entries.insert(name.to_string(), Loader::Layered(PathBuf::from(STD_DIRECTORY), bundled_sources(name))),
+            false => self.entries.insert(name.to_string(), Loader::Path(PathBuf::from(STD_DIRECTORY))),
+        };
+    }
+
+    /// Adds a standard library, like core or common, from BUNDLED_SOURCES.
+    #[cfg(not(feature = "native"))]
+    pub fn add_std(&mut self, name: &str, _embedded: bool) {
+        self.add_bundled(name);
+    }
+
+    pub fn resolve_module(&self, name: &ModuleName) -> RResult<ModuleSource> {
         let Some(first_part) = name.first() else {
             return Err(RuntimeError::error("Module name is empty...").to_array());
         };
 
-        let Some(base_path) = self.entries.get(first_part) else {
-            return Err(RuntimeError::error(format!("Module not in repository: {}", first_part).as_str()).to_array());
+        let Some(loader) = self.entries.get(first_part) else {
+            let error = RuntimeError::error(format!("module `{}` not found; no repository is named `{}`.", name.join("."), first_part).as_str());
+            return Err(with_suggestion(error, name, 0, self.entries.keys().map(String::as_str)).to_array());
         };
 
-        Ok(base_path.join(PathBuf::from(format!("{}.monoteny", name.join("/").as_str()))))
+        let path = name.join("/");
+        match loader {
+            Loader::Path(base_path) => {
+                let file_path = module_file_path(base_path, name);
+                #[cfg(feature = "native")]
+                if !file_path.is_file() {
+                    let error = RuntimeError::error(format!("module `{}` not found; searched: {}", name.join("."), file_path.display()).as_str());
+                    let siblings = sibling_files(file_path.parent().unwrap());
+                    return Err(with_suggestion(error, name, name.len() - 1, siblings.iter().map(String::as_str)).to_array());
+                }
+                Ok(ModuleSource::File(file_path))
+            }
+            Loader::Memory(sources) => resolve_in_memory(sources, name, &path),
+            Loader::Layered(base_path, sources) => {
+                let file_path = module_file_path(base_path, name);
+                match file_path.is_file() {
+                    true => Ok(ModuleSource::File(file_path)),
+                    false => resolve_in_memory(sources, name, &path),
+                }
+            }
+        }
+    }
+}
+
+/// The module's file in the directory. Pushed by component, so the platform's separator is used.
+fn module_file_path(base_path: &Path, name: &ModuleName) -> PathBuf {
+    let mut file_path = base_path.to_path_buf();
+    for directory in &name[..name.len() - 1] {
+        file_path.push(directory);
     }
+    file_path.push(format!("{}.monoteny", name[name.len() - 1]));
+    file_path
+}
+
+fn resolve_in_memory(sources: &HashMap<String, String>, name: &ModuleName, path: &str) -> RResult<ModuleSource> {
+    let Some(source) = sources.get(path) else {
+        let error = RuntimeError::error(format!("module `{}` not found; searched: {} (bundled)", name.join("."), path).as_str());
+        // Sources in the same directory, by their last path component.
+        let parent = path.rsplit_once('/').map(|(parent, _)| parent);
+        let siblings = sources.keys()
+            .filter_map(|key| match key.rsplit_once('/') {
+                Some((key_parent, file)) if Some(key_parent) == parent => Some(file),
+                None if parent.is_none() => Some(key.as_str()),
+                _ => None,
+            });
+        return Err(with_suggestion(error, name, name.len() - 1, siblings).to_array());
+    };
+    Ok(ModuleSource::Text(source.clone()))
+}
+
+/// The library's sources from BUNDLED_SOURCES.
+fn bundled_sources(name: &str) -> HashMap<String, String> {
+    let prefix = format!("{}/", name);
+    BUNDLED_SOURCES.iter()
+        .filter(|(path, _)| *path == name || path.starts_with(&prefix))
+        .map(|(path, source)| (path.to_string(), source.to_string()))
+        .collect()
+}
+
+/// Adds a note suggesting the module name with the part at index replaced by the closest candidate,
+///  if any is close enough to be a likely typo.
+fn with_suggestion<'a>(error: RuntimeError, name: &ModuleName, index: usize, candidates: impl Iterator<Item=&'a str>) -> RuntimeError {
+    let Some(suggestion) = closest_match(&name[index], candidates) else {
+        return error
+    };
+    let mut suggested_name = name.clone();
+    suggested_name[index] = suggestion.to_string();
+    error.with_note(RuntimeError::note(format!("Did you mean `{}`?", suggested_name.join(".")).as_str()))
+}
+
+/// Names of the modules in the directory, i.e. its .monoteny files without extension.
+#[cfg(feature = "native")]
+fn sibling_files(directory: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return vec![]
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "monoteny"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .sorted()
+        .collect_vec()
+}
+
+/// Settings shared by all files of a project, read from its project file.
+/// Project files are a small subset of TOML:
+///
+/// ```toml
+/// [prelude]
+/// imports = ["common", "core.debug"]
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Project {
+    /// Modules imported into every module before its own statements.
+    pub prelude: Vec<ModuleName>,
+}
+
+impl Project {
+    /// Reads the nearest project file in the path's directory or any of its ancestors.
+    #[cfg(feature = "native")]
+    pub fn find(path: &Path) -> RResult<Option<Project>> {
+        let path = std::fs::canonicalize(path)
+            .map_err(|e| RuntimeError::error(format!("Error loading {:?}: {}", path, e).as_str()).to_array())?;
+
+        let Some(project_path) = path.ancestors().skip(1).map(|dir| dir.join(PROJECT_FILE_NAME)).find(|path| path.is_file()) else {
+            return Ok(None)
+        };
+
+        let content = std::fs::read_to_string(&project_path)
+            .map_err(|e| RuntimeError::error(format!("Error loading {:?}: {}", project_path, e).as_str()).to_array())?;
+        Project::parse(&content)
+            .map(Some)
+            .map_err(|errs| errs.into_iter().map(|e| e.in_file(project_path.clone())).collect_vec())
+    }
+
+    pub fn parse(content: &str) -> RResult<Project> {
+        let mut project = Project::default();
+        let mut section = String::new();
+
+        let mut lines = content.lines().enumerate();
+        while let Some((line_idx, line)) = lines.next() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                continue
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(RuntimeError::error(format!("Expected `key = value` on line {}.", line_idx + 1).as_str()).to_array())
+            };
+
+            // Arrays may span multiple lines.
+            let mut value = value.trim().to_string();
+            while value.starts_with('[') && !value.ends_with(']') {
+                let Some((_, next_line)) = lines.next() else {
+                    return Err(RuntimeError::error(format!("Unterminated array on line {}.", line_idx + 1).as_str()).to_array())
+                };
+                value.push_str(strip_comment(next_line).trim());
+            }
+
+            match (section.as_str(), key.trim()) {
+                ("prelude", "imports") => {
+                    project.prelude = parse_string_array(&value)
+                        .ok_or_else(|| RuntimeError::error(format!("prelude.imports must be an array of module names, like [\"common\"] (line {}).", line_idx + 1).as_str()).to_array())?
+                        .iter().map(|name| module_name(name))
+                        .collect_vec();
+                }
+                ("prelude", key) => {
+                    return Err(RuntimeError::error(format!("Unknown key `{}` in [prelude] on line {}.", key, line_idx + 1).as_str()).to_array())
+                }
+                // Other sections are left for other tools.
+                _ => {}
+            }
+        }
+
+        Ok(project)
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_string_array(value: &str) -> Option<Vec<String>> {
+    let items = value.strip_prefix('[')?.strip_suffix(']')?;
+    items.split(',')
+        .map(str::trim)
+        // Trailing commas are allowed.
+        .filter(|item| !item.is_empty())
+        .map(|item| Some(item.strip_prefix('"')?.strip_suffix('"')?.to_string()))
+        .collect()
 }
@@ -3,6 +3,7 @@ pub use crate::resolver::global::resolve_file;
 pub mod scopes;
 pub mod imperative;
 pub mod traits;
+pub mod enums;
 pub mod conformance;
 pub mod global;
 pub mod ambiguous;
@@ -15,6 +16,14 @@ pub mod referencible;
 pub mod structs;
 pub mod decorations;
 pub mod precedence_order;
+pub mod settings;
 pub mod function;
+pub mod query;
+pub mod incremental;
+pub mod derive;
+pub mod deprecation;
+pub mod boxes;
+pub mod anonymous_structs;
 mod imperative_builder;
+mod tests;
 
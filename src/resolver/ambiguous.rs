@@ -5,6 +5,8 @@ pub use abstract_call::AmbiguousAbstractCall;
 pub use function_call::{AmbiguousFunctionCall, AmbiguousFunctionCandidate};
 
 use crate::error::RResult;
+use crate::program::generics::GenericAlias;
+use crate::program::primitives;
 use crate::resolver::imperative::ImperativeResolver;
 
 pub mod function_call;
@@ -18,5 +20,14 @@ pub enum AmbiguityResult<V> {
 pub trait ResolverAmbiguity: Display {
     fn attempt_to_resolve(&mut self, expressions: &mut ImperativeResolver) -> RResult<AmbiguityResult<()>>;
 
+    /// The generics whose bindings decide the outcome of an attempt.
+    /// Until one of them changes, attempting again yields the same result.
+    fn watched_aliases(&self) -> Vec<GenericAlias>;
+
     fn get_position(&self) -> Range<usize>;
+
+    /// The primitive to bind if nothing else decides the ambiguity, like Int64 for int literals.
+    fn number_default(&self, _resolver: &ImperativeResolver) -> Option<(GenericAlias, primitives::Type)> {
+        None
+    }
 }
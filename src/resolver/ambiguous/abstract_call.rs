@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::error::{ErrInRange, RResult};
 use crate::resolver::ambiguous::{AmbiguityResult, ResolverAmbiguity};
@@ -9,7 +9,10 @@ use crate::resolver::imperative::ImperativeResolver;
 use crate::program::calls::FunctionBinding;
 use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
 use crate::program::functions::FunctionHead;
+use crate::program::generics::GenericAlias;
+use crate::program::primitives;
 use crate::program::traits::{RequirementsFulfillment, Trait, TraitGraph};
+use crate::util::position::Positioned;
 
 pub struct AmbiguousAbstractCall {
     pub expression_id: ExpressionID,
@@ -18,8 +21,8 @@ pub struct AmbiguousAbstractCall {
 
     pub range: Range<usize>,
 
-    pub trait_: Rc<Trait>,
-    pub abstract_function: Rc<FunctionHead>,
+    pub trait_: Arc<Trait>,
+    pub abstract_function: Arc<FunctionHead>,
 }
 
 impl Display for AmbiguousAbstractCall {
@@ -43,24 +46,42 @@ impl ResolverAmbiguity for AmbiguousAbstractCall {
                 let used_function = &trait_conformance.conformance.function_mapping[&self.abstract_function];
 
                 resolver.builder.expression_tree.values.insert(
-                    self.expression_id.clone(),
-                    ExpressionOperation::FunctionCall(Rc::new(FunctionBinding {
-                        function: Rc::clone(used_function),
-                        requirements_fulfillment: Rc::new(RequirementsFulfillment {
+                    self.expression_id,
+                    ExpressionOperation::FunctionCall(Arc::new(FunctionBinding {
+                        function: Arc::clone(used_function),
+                        requirements_fulfillment: Arc::new(RequirementsFulfillment {
                             conformance: HashMap::from([(requirement, trait_conformance)]),
-                            generic_mapping: HashMap::from([(Rc::clone(&self.trait_.generics["Self"]), type_.clone())])
+                            generic_mapping: HashMap::from([(Arc::clone(&self.trait_.generics["Self"]), type_.clone())])
                         }),
                     }))
                 );
-                resolver.builder.types.bind(self.expression_id.clone(), type_.as_ref())
+                resolver.builder.types.bind(self.expression_id, type_.as_ref())
                     .err_in_range(&self.range)?;
+                resolver.literal_calls.push(Positioned { position: self.range.clone(), value: self.expression_id });
 
                 AmbiguityResult::Ok(())
             }
         })
     }
 
+    fn watched_aliases(&self) -> Vec<GenericAlias> {
+        vec![self.expression_id]
+    }
+
     fn get_position(&self) -> Range<usize> {
         self.range.clone()
     }
+
+    fn number_default(&self, resolver: &ImperativeResolver) -> Option<(GenericAlias, primitives::Type)> {
+        let traits = resolver.builder.runtime.traits.as_ref().unwrap();
+        if self.trait_ == traits.ConstructableByRealLiteral {
+            Some((self.expression_id, primitives::Type::Float(64)))
+        }
+        else if self.trait_ == traits.ConstructableByIntLiteral {
+            Some((self.expression_id, primitives::Type::Int(64)))
+        }
+        else {
+            None
+        }
+    }
 }
@@ -1,30 +1,34 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 use display_with_options::with_options;
 
 use itertools::{Itertools, zip_eq};
 
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 use crate::resolver::ambiguous::{AmbiguityResult, ResolverAmbiguity};
+use crate::resolver::deprecation;
 use crate::resolver::imperative::ImperativeResolver;
 use crate::program::calls::FunctionBinding;
 use crate::program::debug::MockFunctionInterface;
 use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
 use crate::program::function_object::FunctionRepresentation;
 use crate::program::functions::{FunctionHead, ParameterKey};
-use crate::program::generics::TypeForest;
+use crate::program::generics::{GenericAlias, TypeForest};
 use crate::program::traits::{RequirementsFulfillment, Trait, TraitBinding, TraitGraph};
 use crate::program::types::TypeProto;
 
 pub struct AmbiguousFunctionCandidate {
-    pub function: Rc<FunctionHead>,
-    pub generic_map: HashMap<Rc<Trait>, Rc<TypeProto>>,
+    pub function: Arc<FunctionHead>,
+    pub generic_map: HashMap<Arc<Trait>, Arc<TypeProto>>,
     // All these are seeded already
-    pub param_types: Vec<Rc<TypeProto>>,
-    pub return_type: Rc<TypeProto>,
-    pub requirements: Vec<Rc<TraitBinding>>,
+    pub param_types: Vec<Arc<TypeProto>>,
+    pub return_type: Arc<TypeProto>,
+    pub requirements: Vec<Arc<TraitBinding>>,
+    /// Where the function's where clause was declared, if it was declared in this file.
+    pub where_clause: Option<Range<usize>>,
 }
 
 pub struct AmbiguousFunctionCall {
@@ -40,23 +44,23 @@ pub struct AmbiguousFunctionCall {
 }
 
 impl AmbiguousFunctionCall {
-    fn attempt_with_candidate(&mut self, types: &mut TypeForest, candidate: &AmbiguousFunctionCandidate) -> RResult<AmbiguityResult<Rc<RequirementsFulfillment>>> {
+    fn attempt_with_candidate(&mut self, types: &mut TypeForest, candidate: &AmbiguousFunctionCandidate) -> RResult<AmbiguityResult<Arc<RequirementsFulfillment>>> {
         let param_types = &candidate.param_types;
 
         for (arg, param) in zip_eq(
             self.arguments.iter(),
             param_types.iter().map(|x| x.as_ref())
         ) {
-            types.bind(arg.clone(), param)?;
+            types.bind(*arg, param)?;
         }
-        types.bind(self.expression_id.clone(), &candidate.return_type)?;
+        types.bind(self.expression_id, &candidate.return_type)?;
 
         // Currently, our resolution is just pointing to generics. But that's good enough!
         let mut conformance = HashMap::new();
         // TODO We should only use deep requirements once we actually use this candidate.
         //  The deep ones are guaranteed to exist if the original requirements can be satisfied.
         for requirement in self.traits.gather_deep_requirements(candidate.requirements.iter().cloned()) {
-            match self.traits.satisfy_requirement(&requirement.mapping_types(&|type_| type_.replacing_structs(&candidate.generic_map)), &types)? {
+            match self.traits.satisfy_requirement(&requirement.mapping_types(&|type_| type_.replacing_structs(&candidate.generic_map)), types)? {
                 AmbiguityResult::Ok(trait_conformance) => {
                     conformance.insert(requirement, trait_conformance);
                 }
@@ -64,7 +68,57 @@ impl AmbiguousFunctionCall {
             }
         }
 
-        Ok(AmbiguityResult::Ok(Rc::new(RequirementsFulfillment { generic_mapping: candidate.generic_map.clone(), conformance })))
+        Ok(AmbiguityResult::Ok(Arc::new(RequirementsFulfillment { generic_mapping: candidate.generic_map.clone(), conformance })))
+    }
+
+    fn resolve_to(&self, resolver: &mut ImperativeResolver, candidate: &AmbiguousFunctionCandidate, resolution: Arc<RequirementsFulfillment>) -> AmbiguityResult<()> {
+        if !resolver.settings.allow_deprecated {
+            if let Some(warning) = deprecation::function_warning(&resolver.builder.runtime.source, &candidate.function) {
+                resolver.warnings.push(warning.in_range(self.range.clone()));
+            }
+        }
+        resolver.builder.expression_tree.values.insert(self.expression_id, ExpressionOperation::FunctionCall(Arc::new(FunctionBinding {
+            function: Arc::clone(&candidate.function),
+            requirements_fulfillment: resolution
+        })));
+        AmbiguityResult::Ok(())
+    }
+
+    /// The error once all candidates failed.
+    fn resolution_error(&self, resolver: &ImperativeResolver) -> Vec<RuntimeError> {
+        // TODO We should probably output the locations of candidates.
+        match &self.failed_candidates[..] {
+            [] => panic!(),
+            [(candidate, err)] => {
+                // TODO How so?
+                let mut error = RuntimeError::error(format!("function {:?} could not be resolved.", &with_options(candidate.function.as_ref(), &self.representation)).as_str())
+                    .with_code(ErrorCode::UnresolvedFunction)
+                    .with_note(
+                        RuntimeError::info("Candidate failed type / requirements test.")
+                            .with_notes(err.iter().cloned())
+                    );
+                if let Some(where_clause) = &candidate.where_clause {
+                    if err.iter().any(|e| e.code == Some(ErrorCode::MissingConformance)) {
+                        error = error.with_note(RuntimeError::note("Required by this where clause.").in_range(where_clause.clone()));
+                    }
+                }
+                error.to_array()
+            }
+            cs => {
+                let signature = MockFunctionInterface {
+                    representation: self.representation.clone(),
+                    argument_keys: self.arguments.iter().map(|_a| ParameterKey::Positional).collect_vec(),
+                    arguments: self.arguments.clone(),
+                    types: &resolver.builder.types,
+                };
+                RuntimeError::error(format!("function {} could not be resolved. ", signature).as_str())
+                    .with_code(ErrorCode::UnresolvedFunction)
+                    .with_note(
+                        RuntimeError::info(format!("{} candidates failed type / requirements test.", cs.len()).as_str())
+                    )
+                    .to_array()
+            }
+        }
     }
 }
 
@@ -76,6 +130,23 @@ impl Display for AmbiguousFunctionCall {
 
 impl ResolverAmbiguity for AmbiguousFunctionCall {
     fn attempt_to_resolve(&mut self, resolver: &mut ImperativeResolver) -> RResult<AmbiguityResult<()>> {
+        // A single candidate's bindings are needed whatever the outcome, so it's attempted on the types directly.
+        //  Copying the types for every call would make resolving a body quadratic in its size.
+        if self.candidates.len() == 1 {
+            let candidate = self.candidates.pop().unwrap();
+            return match self.attempt_with_candidate(&mut resolver.builder.types, &candidate) {
+                Ok(AmbiguityResult::Ok(resolution)) => Ok(self.resolve_to(resolver, &candidate, resolution)),
+                Ok(AmbiguityResult::Ambiguous) => {
+                    self.candidates.push(candidate);
+                    Ok(AmbiguityResult::Ambiguous)
+                }
+                Err(err) => {
+                    self.failed_candidates.push((candidate, err));
+                    Err(self.resolution_error(resolver))
+                }
+            }
+        }
+
         let mut is_ambiguous = false;
         for candidate in self.candidates.drain(..).collect_vec() {
             let mut types_copy = resolver.builder.types.clone();
@@ -103,13 +174,8 @@ impl ResolverAmbiguity for AmbiguousFunctionCall {
             // TODO We can just assign resolver.types to the candidate's result; it was literally just copied.
             match self.attempt_with_candidate(&mut resolver.builder.types, &candidate)? {
                 AmbiguityResult::Ok(resolution) => {
-                    resolver.builder.expression_tree.values.insert(self.expression_id, ExpressionOperation::FunctionCall(Rc::new(FunctionBinding {
-                        function: Rc::clone(&candidate.function),
-                        requirements_fulfillment: resolution
-                    })));
-
                     // We're done!
-                    return Ok(AmbiguityResult::Ok(()))
+                    return Ok(self.resolve_to(resolver, &candidate, resolution))
                 }
                 AmbiguityResult::Ambiguous => {
                     return Ok(AmbiguityResult::Ambiguous)
@@ -117,37 +183,11 @@ impl ResolverAmbiguity for AmbiguousFunctionCall {
             }
         }
 
-        // TODO We should probably output the locations of candidates.
+        Err(self.resolution_error(resolver))
+    }
 
-        match &self.failed_candidates[..] {
-            [] => panic!(),
-            [(candidate, err)] => {
-                // TODO How so?
-                Err(
-                    RuntimeError::error(format!("function {:?} could not be resolved.", &with_options(candidate.function.as_ref(), &self.representation)).as_str())
-                        .with_note(
-                            RuntimeError::info("Candidate failed type / requirements test.")
-                                .with_notes(err.iter().cloned())
-                        )
-                        .to_array()
-                )
-            }
-            cs => {
-                let signature = MockFunctionInterface {
-                    representation: self.representation.clone(),
-                    argument_keys: self.arguments.iter().map(|a| ParameterKey::Positional).collect_vec(),
-                    arguments: self.arguments.clone(),
-                    types: &resolver.builder.types,
-                };
-                Err(
-                    RuntimeError::error(format!("function {} could not be resolved. ", signature).as_str())
-                        .with_note(
-                            RuntimeError::info(format!("{} candidates failed type / requirements test.", cs.len()).as_str())
-                        )
-                        .to_array()
-                )
-            }
-        }
+    fn watched_aliases(&self) -> Vec<GenericAlias> {
+        self.arguments.iter().chain([&self.expression_id]).cloned().collect()
     }
 
     fn get_position(&self) -> Range<usize> {
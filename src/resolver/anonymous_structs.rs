@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::ast;
+use crate::interpreter::runtime::Runtime;
+use crate::program::allocation::{Mutability, ObjectReference};
+use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
+use crate::program::functions::{FunctionHead, FunctionInterface, Parameter, ParameterKey};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor};
+use crate::program::traits::{FieldHint, Trait};
+use crate::program::types::{TypeProto, TypeUnit};
+use crate::resolver::fields;
+use crate::source::{Source, StructInfo};
+
+/// The fields of an anonymous struct type like (q 'Int64, r 'Int64), if the expression is one.
+pub fn fields(syntax: &ast::Expression) -> Option<Vec<(&String, &ast::Expression)>> {
+    let [term] = &syntax[..] else {
+        return None
+    };
+    let ast::Term::Struct(struct_) = &term.value else {
+        return None
+    };
+    if struct_.arguments.is_empty() {
+        return None
+    }
+
+    struct_.arguments.iter().map(|argument| {
+        let ast::StructArgument { key: ParameterKey::Positional, value, type_declaration: Some(type_) } = &argument.value else {
+            return None
+        };
+        let [name] = &value[..] else {
+            return None
+        };
+        let ast::Term::Identifier(name) = &name.value else {
+            return None
+        };
+        Some((name, type_))
+    }).collect()
+}
+
+/// The field names of an anonymous struct literal like (q: 1, r: 2), if the expression starts with one.
+/// Structs following other terms may be the arguments of a call, so they aren't counted.
+fn literal_names(syntax: &ast::Expression) -> Option<Vec<String>> {
+    let ast::Term::Struct(struct_) = &syntax.first()?.value else {
+        return None
+    };
+    if struct_.arguments.is_empty() {
+        return None
+    }
+
+    struct_.arguments.iter().map(|argument| match &argument.value.key {
+        ParameterKey::Name(name) => Some(name.clone()),
+        ParameterKey::Positional => None,
+    }).collect()
+}
+
+/// Declares the anonymous struct of each anonymous struct type and literal in the block.
+/// Function bodies are resolved without changing the runtime, so they can't declare the structs they use themselves.
+pub fn declare_in_block(runtime: &mut Runtime, block: &ast::Block) {
+    for statement in block.statements.iter() {
+        declare_in_statement(runtime, &statement.value.value);
+    }
+}
+
+fn declare_in_statement(runtime: &mut Runtime, statement: &ast::Statement) {
+    match statement {
+        ast::Statement::VariableDeclaration { type_declaration, assignment, .. } => {
+            type_declaration.iter().for_each(|type_| declare_in_type(runtime, type_));
+            assignment.iter().for_each(|assignment| declare_in_expression(runtime, assignment));
+        }
+        ast::Statement::Destructuring { assignment, .. } => declare_in_expression(runtime, assignment),
+        ast::Statement::VariableUpdate { target, new_value, .. } => {
+            declare_in_expression(runtime, target);
+            declare_in_expression(runtime, new_value);
+        }
+        ast::Statement::Expression(expression) => declare_in_expression(runtime, expression),
+        ast::Statement::Return(expression) => expression.iter().for_each(|expression| declare_in_expression(runtime, expression)),
+        ast::Statement::FunctionDeclaration(function) => {
+            declare_in_expression(runtime, &function.interface.expression);
+            function.interface.return_type.iter().for_each(|type_| declare_in_type(runtime, type_));
+            function.body.iter().for_each(|body| declare_in_expression(runtime, body));
+        }
+        ast::Statement::Trait(trait_) => declare_in_block(runtime, &trait_.block),
+        ast::Statement::Conformance(conformance) => declare_in_block(runtime, &conformance.block),
+        ast::Statement::Enum(_) => {}
+    }
+}
+
+fn declare_in_type(runtime: &mut Runtime, syntax: &ast::Expression) {
+    if let Some(fields) = fields(syntax) {
+        declare(runtime, fields.into_iter().map(|(name, _)| name.clone()).collect_vec());
+    }
+    // The types of the fields are declared with their struct's arguments.
+    declare_in_expression(runtime, syntax);
+}
+
+fn declare_in_expression(runtime: &mut Runtime, syntax: &ast::Expression) {
+    if let Some(names) = literal_names(syntax) {
+        declare(runtime, names);
+    }
+
+    for term in syntax.iter() {
+        match &term.value {
+            ast::Term::Struct(struct_) => declare_in_struct(runtime, struct_),
+            ast::Term::Array(array) => {
+                for argument in array.arguments.iter() {
+                    argument.value.key.iter().for_each(|key| declare_in_expression(runtime, key));
+                    declare_in_expression(runtime, &argument.value.value);
+                    argument.value.type_declaration.iter().for_each(|type_| declare_in_type(runtime, type_));
+                }
+            }
+            ast::Term::StringLiteral(parts) => {
+                for part in parts.iter() {
+                    if let ast::StringPart::Object(struct_) = &part.value {
+                        declare_in_struct(runtime, struct_);
+                    }
+                }
+            }
+            ast::Term::Block(block) => declare_in_block(runtime, block),
+            ast::Term::IfThenElse(if_then_else) => {
+                declare_in_expression(runtime, &if_then_else.condition);
+                declare_in_expression(runtime, &if_then_else.consequent);
+                if_then_else.alternative.iter().for_each(|alternative| declare_in_expression(runtime, alternative));
+            }
+            ast::Term::Match(match_) => {
+                declare_in_expression(runtime, &match_.subject);
+                match_.arms.iter().for_each(|arm| declare_in_expression(runtime, &arm.value.value));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn declare_in_struct(runtime: &mut Runtime, struct_: &ast::Struct) {
+    for argument in struct_.arguments.iter() {
+        declare_in_expression(runtime, &argument.value.value);
+        argument.value.type_declaration.iter().for_each(|type_| declare_in_type(runtime, type_));
+    }
+}
+
+/// Declares the anonymous struct with the field names, unless it exists already.
+/// Its fields are generic, so structs with the same names share the struct regardless of their types.
+pub fn declare(runtime: &mut Runtime, names: Vec<String>) {
+    if runtime.source.anonymous_structs.contains_key(&names) || !names.iter().all_unique() {
+        return
+    }
+
+    let mut trait_ = Trait::new_flat(&format!("({})", names.join(", ")));
+    let field_types = names.iter().map(|name| {
+        let generic = Arc::new(Trait::new_flat(name));
+        trait_.generics.insert(name.clone(), Arc::clone(&generic));
+        TypeProto::unit_struct(&generic)
+    }).collect_vec();
+    // There are no abstract fields to access, so the hints only describe the struct.
+    trait_.field_hints = names.iter().zip(field_types.iter()).map(|(name, type_)| FieldHint {
+        name: name.clone(),
+        type_: type_.clone(),
+        setter: None,
+        getter: None,
+    }).collect();
+    let generics = trait_.generics.clone();
+    let trait_ = Arc::new(trait_);
+
+    let struct_type = Arc::new(TypeProto {
+        unit: TypeUnit::Struct(Arc::clone(&trait_)),
+        arguments: field_types.clone(),
+    });
+    let meta_type = TypeProto::one_arg(&runtime.Metatype, TypeProto::unit_struct(&trait_));
+
+    let mut parameters = vec![
+        Parameter {
+            external_key: ParameterKey::Positional,
+            internal_name: "type".to_string(),
+            type_: meta_type.clone(),
+            mutability: Mutability::Immutable,
+        }
+    ];
+    let mut fields = vec![];
+    let mut field_names = HashMap::new();
+    let mut field_getters = HashMap::new();
+    for (name, type_) in names.iter().zip(field_types.iter()) {
+        let field = ObjectReference::new_immutable(type_.clone());
+        // Like tuples, anonymous structs can't be changed after they are made.
+        let getter = fields::make(name, &struct_type, type_, &generics, true, false).getter.unwrap();
+
+        parameters.push(Parameter {
+            external_key: ParameterKey::Name(name.clone()),
+            internal_name: name.clone(),
+            type_: type_.clone(),
+            mutability: Mutability::Immutable,
+        });
+        field_names.insert(Arc::clone(&field), name.clone());
+        field_getters.insert(Arc::clone(&field), getter);
+        fields.push(field);
+    }
+
+    let struct_ = Arc::new(StructInfo {
+        trait_: Arc::clone(&trait_),
+        constructor: FunctionHead::new_static(Arc::new(FunctionInterface {
+            parameters,
+            return_type: struct_type,
+            requirements: Default::default(),
+            generics,
+        })),
+        fields,
+        field_names,
+        field_getters,
+        field_setters: Default::default(),
+    });
+
+    let source = &mut runtime.source;
+    let getter = FunctionHead::new_static(FunctionInterface::new_provider(&meta_type, vec![]));
+    add_function(source, &getter, FunctionRepresentation::new(&trait_.name, FunctionTargetType::Global, FunctionCallExplicity::Implicit), FunctionLogicDescriptor::TraitProvider(Arc::clone(&trait_)));
+    source.trait_references.insert(Arc::clone(&getter), Arc::clone(&trait_));
+    add_function(source, &struct_.constructor, FunctionRepresentation::new("call_as_function", FunctionTargetType::Member, FunctionCallExplicity::Explicit), FunctionLogicDescriptor::Constructor(Arc::clone(&struct_)));
+    for (field, head) in struct_.field_getters.iter() {
+        let representation = FunctionRepresentation::new(&struct_.field_names[field], FunctionTargetType::Member, FunctionCallExplicity::Implicit);
+        add_function(source, head, representation, FunctionLogicDescriptor::GetMemberField(Arc::clone(&struct_), Arc::clone(field)));
+    }
+
+    source.struct_by_trait.insert(trait_, struct_);
+    source.anonymous_structs.insert(names, getter);
+}
+
+fn add_function(source: &mut Source, head: &Arc<FunctionHead>, representation: FunctionRepresentation, descriptor: FunctionLogicDescriptor) {
+    source.fn_heads.insert(head.function_id, Arc::clone(head));
+    source.fn_representations.insert(Arc::clone(head), representation);
+    source.fn_logic.insert(Arc::clone(head), FunctionLogic::Descriptor(descriptor));
+}
+
+/// The struct and its metatype getter of the anonymous struct with the field names, if it was declared.
+pub fn get<'a>(source: &'a Source, names: &[String]) -> Option<(&'a Arc<StructInfo>, &'a Arc<FunctionHead>)> {
+    let getter = source.anonymous_structs.get(names)?;
+    Some((&source.struct_by_trait[&source.trait_references[getter]], getter))
+}
+
+/// The getters of the anonymous structs' fields with the name.
+/// Anonymous structs don't belong to a module, so their fields aren't in any scope, but can be read anywhere.
+pub fn getters(source: &Source, name: &str) -> Vec<Arc<FunctionHead>> {
+    source.anonymous_structs.keys()
+        .filter(|names| names.iter().any(|field_name| field_name == name))
+        .sorted()
+        .flat_map(|names| {
+            let (struct_, _) = get(source, names).unwrap();
+            struct_.fields.iter()
+                .filter(|field| struct_.field_names[*field] == name)
+                .map(|field| Arc::clone(&struct_.field_getters[field]))
+                .collect_vec()
+        })
+        .collect()
+}
@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::error::RResult;
+use crate::interpreter::runtime::Runtime;
+use crate::program::allocation::{Mutability, ObjectReference};
+use crate::program::calls::FunctionBinding;
+use crate::program::expression_tree::{ExpressionOperation, ExpressionTree};
+use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
+use crate::program::functions::{FunctionHead, FunctionInterface, Parameter, ParameterKey};
+use crate::program::generics::TypeForest;
+use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor};
+use crate::program::module::Module;
+use crate::program::traits::{RequirementsAssumption, Trait, TraitBinding, TraitConformance, TraitConformanceRule, TraitGraph};
+use crate::program::types::TypeProto;
+use crate::refactor::monomorphize::map_interface_types;
+use crate::resolver::imperative_builder::ImperativeBuilder;
+use crate::resolver::{referencible, scopes};
+
+/// Whether values of the trait can be boxed as Dyn<Trait>.
+/// Boxes only know their value's implementation of the trait's own functions, so each must take the value as
+///  its first parameter and mention Self nowhere else. Generic functions would need a vtable per instantiation.
+pub fn can_box(trait_: &Trait) -> bool {
+    let Some(self_) = trait_.generics.get("Self") else {
+        return false
+    };
+
+    trait_.generics.len() == 1
+        && trait_.requirements.is_empty()
+        && !trait_.abstract_functions.is_empty()
+        && trait_.abstract_functions.keys().all(|function| {
+            let interface = &function.interface;
+            let Some((receiver, parameters)) = interface.parameters.split_first() else {
+                return false
+            };
+
+            interface.generics.is_empty()
+                && interface.requirements.is_empty()
+                && receiver.type_ == TypeProto::unit_struct(self_)
+                && receiver.mutability == Mutability::Immutable
+                && !parameters.iter().any(|parameter| parameter.type_.contains_struct(self_))
+                && !interface.return_type.contains_struct(self_)
+        })
+}
+
+/// Makes Dyn<Trait> conform to the trait, if its values can be boxed.
+/// The functions of the conformance dispatch to the boxed value's implementation, through the vtable created by box.
+pub fn add_dyn_conformance(runtime: &mut Runtime, module: &mut Module, mut scope: Option<&mut scopes::Scope>, trait_: &Arc<Trait>) -> RResult<()> {
+    if !can_box(trait_) {
+        return Ok(())
+    }
+
+    let dyn_type = TypeProto::one_arg(&runtime.traits.as_ref().unwrap().Dyn, TypeProto::unit_struct(trait_));
+    let self_ = &trait_.generics["Self"];
+
+    // The type of the boxed value, generic in box and the functions of its vtable.
+    let value_trait = Arc::new(Trait::new_flat("Self"));
+    let value_type = TypeProto::unit_struct(&value_trait);
+    let requirement = trait_.create_generic_binding(vec![("Self", value_type.clone())]);
+    let granted = TraitGraph::new().assume_granted([Arc::clone(&requirement)].into_iter());
+    let [conformance] = &granted[..] else {
+        panic!("Boxable traits have no requirements.")
+    };
+
+    let mut vtable = vec![];
+    let mut function_mapping = HashMap::new();
+    for (index, abstract_function) in trait_.abstract_functions.keys().sorted_by_key(|function| function.function_id).enumerate() {
+        let representation = trait_.abstract_functions[abstract_function].clone();
+
+        let dispatch = FunctionHead::new_static(Arc::new(map_interface_types(
+            &abstract_function.interface,
+            &HashMap::from([(Arc::clone(self_), dyn_type.clone())]),
+        )));
+        // Like the functions of declared conformances, they can be called on Dyn values directly.
+        referencible::add_function(runtime, module, scope.as_deref_mut(), Arc::clone(&dispatch), representation.clone())?;
+        runtime.source.fn_logic.insert(Arc::clone(&dispatch), FunctionLogic::Descriptor(FunctionLogicDescriptor::DynDispatch(index)));
+        function_mapping.insert(Arc::clone(abstract_function), dispatch);
+
+        let implementation = make_vtable_function(runtime, abstract_function, conformance, &requirement, &value_trait)?;
+        let head = Arc::clone(&implementation.head);
+        runtime.source.fn_heads.insert(head.function_id, Arc::clone(&head));
+        runtime.source.fn_representations.insert(Arc::clone(&head), FunctionRepresentation::new(&representation.name, FunctionTargetType::Global, FunctionCallExplicity::Explicit));
+        runtime.source.fn_logic.insert(Arc::clone(&head), FunctionLogic::Implementation(implementation));
+        vtable.push(head);
+    }
+
+    let box_ = FunctionHead::new_static(Arc::new(FunctionInterface {
+        parameters: vec![Parameter {
+            external_key: ParameterKey::Positional,
+            internal_name: "value".to_string(),
+            type_: value_type,
+            mutability: Mutability::Immutable,
+        }],
+        return_type: dyn_type.clone(),
+        requirements: HashSet::from([requirement]),
+        generics: HashMap::from([("Self".to_string(), value_trait)]),
+    }));
+    runtime.source.fn_heads.insert(box_.function_id, Arc::clone(&box_));
+    runtime.source.fn_representations.insert(Arc::clone(&box_), FunctionRepresentation::new("box", FunctionTargetType::Global, FunctionCallExplicity::Explicit));
+    runtime.source.fn_logic.insert(Arc::clone(&box_), FunctionLogic::Descriptor(FunctionLogicDescriptor::DynBox(vtable)));
+    runtime.source.dyn_boxes.insert(Arc::clone(trait_), box_);
+
+    let rule = TraitConformanceRule::direct(TraitConformance::new(
+        trait_.create_generic_binding(vec![("Self", dyn_type)]),
+        function_mapping,
+    ));
+    module.trait_conformance.add_conformance_rule(Arc::clone(&rule));
+    if let Some(scope) = scope {
+        scope.trait_conformance.add_conformance_rule(rule);
+    }
+
+    Ok(())
+}
+
+/// A function of the vtable: it calls the boxed value's implementation of the abstract function.
+/// It shares the box's generic and requirement, so a box call's fulfillment also fulfills it.
+fn make_vtable_function(runtime: &Runtime, abstract_function: &Arc<FunctionHead>, conformance: &Arc<TraitConformance>, requirement: &Arc<TraitBinding>, value_trait: &Arc<Trait>) -> RResult<Box<FunctionImplementation>> {
+    let mut interface = map_interface_types(
+        &abstract_function.interface,
+        &requirement.generic_to_type,
+    );
+    interface.requirements.insert(Arc::clone(requirement));
+    interface.generics.insert("Self".to_string(), Arc::clone(value_trait));
+    let head = FunctionHead::new_static(Arc::new(interface));
+
+    let mut builder = ImperativeBuilder {
+        runtime,
+        types: Box::new(TypeForest::new()),
+        expression_tree: Box::new(ExpressionTree::new(Uuid::new_v4())),
+        locals_names: Default::default(),
+    };
+
+    let mut parameter_locals = vec![];
+    let mut arguments = vec![];
+    for parameter in head.interface.parameters.iter() {
+        let local = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: parameter.type_.clone(), mutability: parameter.mutability });
+        builder.locals_names.insert(Arc::clone(&local), parameter.internal_name.clone());
+        arguments.push(builder.make_full_expression(vec![], &parameter.type_, ExpressionOperation::GetLocal(Arc::clone(&local)))?);
+        parameter_locals.push(local);
+    }
+
+    let function = &conformance.function_mapping[abstract_function];
+    builder.expression_tree.root = builder.make_full_expression(
+        arguments,
+        &head.interface.return_type,
+        ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(function))),
+    )?;
+
+    Ok(Box::new(FunctionImplementation {
+        head,
+        requirements_assumption: Box::new(RequirementsAssumption { conformance: HashMap::from([(Arc::clone(requirement), Arc::clone(conformance))]) }),
+        expression_tree: builder.expression_tree,
+        type_forest: builder.types,
+        parameter_locals,
+        locals_names: builder.locals_names,
+        statement_ranges: Default::default(),
+        expression_ranges: Default::default(),
+        ambiguity_attempts: 0,
+        warnings: vec![],
+    }))
+}
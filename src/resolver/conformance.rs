@@ -1,24 +1,28 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
-use std::rc::Rc;
+use std::ops::Range;
+use std::sync::Arc;
 
 use display_with_options::with_options;
 use itertools::Itertools;
 
 use crate::ast;
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 use crate::interpreter::runtime::Runtime;
-use crate::program::function_object::FunctionRepresentation;
-use crate::program::functions::FunctionHead;
+use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
+use crate::program::functions::{FunctionHead, FunctionInterface};
 use crate::program::traits::{Trait, TraitBinding, TraitConformance};
 use crate::refactor::monomorphize::map_interface_types;
 use crate::resolver::interface::resolve_function_interface;
 use crate::resolver::scopes;
 
 pub struct UnresolvedFunctionImplementation<'a> {
-    pub function: Rc<FunctionHead>,
+    pub function: Arc<FunctionHead>,
     pub representation: FunctionRepresentation,
     pub body: &'a Option<ast::Expression>,
+    pub return_type: &'a Option<ast::Expression>,
+    pub range: Range<usize>,
 }
 
 pub struct ConformanceResolver<'a, 'b> {
@@ -27,18 +31,25 @@ pub struct ConformanceResolver<'a, 'b> {
 }
 
 impl <'a, 'b> ConformanceResolver<'a, 'b> {
-    pub fn resolve_statement(&mut self, statement: &'a ast::Statement, requirements: &HashSet<Rc<TraitBinding>>, generics: &HashMap<String, Rc<Trait>>, scope: &scopes::Scope) -> RResult<()> {
+    pub fn resolve_statement(&mut self, statement: &'a ast::Statement, range: &Range<usize>, requirements: &HashSet<Arc<TraitBinding>>, generics: &HashMap<String, Arc<Trait>>, scope: &scopes::Scope) -> RResult<()> {
         match statement {
             ast::Statement::FunctionDeclaration(syntax) => {
                 // TODO For simplicity's sake, we should match the generics IDs of all conformances
                 //  to the ID of the parent abstract function. That way, we can avoid another
                 //  generic to generic mapping later.
-                let (function, representation) = resolve_function_interface(&syntax.interface, &scope, None, &self.runtime, requirements, generics)?;
+                let (function, representation) = resolve_function_interface(&syntax.interface, scope, None, self.runtime, requirements, generics)?;
+                if syntax.body.is_none() {
+                    return Err(
+                        RuntimeError::error(format!("Function {:?} must have a body to implement the conformance.", with_options(function.interface.as_ref(), &representation)).as_str()).to_array()
+                    );
+                }
 
                 self.functions.push(UnresolvedFunctionImplementation {
                     function,
                     representation,
                     body: &syntax.body,
+                    return_type: &syntax.interface.return_type,
+                    range: range.clone(),
                 });
             }
             _ => {
@@ -47,7 +58,7 @@ impl <'a, 'b> ConformanceResolver<'a, 'b> {
                     exp.no_errors()?;
                 }
                 return Err(
-                    RuntimeError::error("Statement not valid in a conformance context.").to_array()
+                    RuntimeError::error("Statement not allowed inside a conformance declaration; conformances may only declare functions.").to_array()
                 );
             }
         }
@@ -55,7 +66,7 @@ impl <'a, 'b> ConformanceResolver<'a, 'b> {
         Ok(())
     }
 
-    pub fn finalize_conformance(&self, binding: Rc<TraitBinding>, conformance_requirements: &HashSet<Rc<TraitBinding>>, conformance_generics: &HashMap<String, Rc<Trait>>) -> RResult<Rc<TraitConformance>> {
+    pub fn finalize_conformance(&self, binding: Arc<TraitBinding>, conformance_requirements: &HashSet<Arc<TraitBinding>>, conformance_generics: &HashMap<String, Arc<Trait>>) -> RResult<Arc<TraitConformance>> {
         let mut function_bindings = HashMap::new();
         let mut unmatched_implementations = self.functions.iter().collect_vec();
 
@@ -65,13 +76,21 @@ impl <'a, 'b> ConformanceResolver<'a, 'b> {
             expected_interface.generics.extend(conformance_generics.clone());
 
             let matching_implementations = unmatched_implementations.iter().enumerate()
-                .filter(|(i, imp)| &imp.representation == abstract_representation && imp.function.interface.as_ref() == &expected_interface)
-                .map(|(i, interface)| i)
+                .filter(|(_i, imp)| &imp.representation == abstract_representation && imp.function.interface.is_callable_as(&expected_interface))
+                .map(|(i, _interface)| i)
                 .collect_vec();
 
-            if matching_implementations.len() == 0 {
+            if matching_implementations.is_empty() {
+                if let Some(near_miss) = unmatched_implementations.iter().find(|imp| is_misdeclared(imp, abstract_representation, &expected_interface)) {
+                    return Err(
+                        RuntimeError::error(format!("`{}` must be declared as {} to satisfy {}.", abstract_representation.name, describe_declaration(abstract_representation), binding.trait_.name).as_str())
+                            .in_range(near_miss.range.clone())
+                            .to_array()
+                    );
+                }
+
                 return Err(
-                    RuntimeError::error(format!("Function {:?} missing for conformance.", with_options(&expected_interface, abstract_representation)).as_str()).to_array()
+                    RuntimeError::error(format!("Function {:?} missing for conformance.", with_options(&expected_interface, abstract_representation)).as_str()).with_code(ErrorCode::MissingConformanceFunction).to_array()
                 );
             }
             else if matching_implementations.len() > 1 {
@@ -81,19 +100,41 @@ impl <'a, 'b> ConformanceResolver<'a, 'b> {
             }
             else {
                 function_bindings.insert(
-                    Rc::clone(abstract_function),
-                    Rc::clone(&unmatched_implementations.remove(matching_implementations[0]).function)
+                    Arc::clone(abstract_function),
+                    Arc::clone(&unmatched_implementations.remove(matching_implementations[0]).function)
                 );
             }
         }
 
-        if unmatched_implementations.len() > 0 {
+        if !unmatched_implementations.is_empty() {
             return Err(
                 RuntimeError::error(format!("Unrecognized functions for declaration {:?}: {:?}.", binding, unmatched_implementations).as_str()).to_array()
             );
         }
 
-        Ok(TraitConformance::new(Rc::clone(&binding), function_bindings.clone()))
+        Ok(TraitConformance::new(Arc::clone(&binding), function_bindings.clone()))
+    }
+}
+
+/// Whether the implementation was meant for the abstract function, but declared in the wrong form
+///  (e.g. a global function instead of a member function).
+fn is_misdeclared(implementation: &UnresolvedFunctionImplementation, representation: &FunctionRepresentation, interface: &FunctionInterface) -> bool {
+    let actual = &implementation.function.interface;
+
+    implementation.representation.name == representation.name
+        && &implementation.representation != representation
+        && actual.return_type == interface.return_type
+        && actual.parameters.len() == interface.parameters.len()
+        && actual.parameters.iter().zip(interface.parameters.iter())
+            .all(|(lhs, rhs)| lhs.external_key == rhs.external_key && lhs.type_ == rhs.type_)
+}
+
+fn describe_declaration(representation: &FunctionRepresentation) -> &'static str {
+    match (representation.target_type, representation.call_explicity) {
+        (FunctionTargetType::Member, FunctionCallExplicity::Explicit) => "a member function",
+        (FunctionTargetType::Member, FunctionCallExplicity::Implicit) => "a member function without parentheses",
+        (FunctionTargetType::Global, FunctionCallExplicity::Explicit) => "a global function",
+        (FunctionTargetType::Global, FunctionCallExplicity::Implicit) => "a global function without parentheses",
     }
 }
 
@@ -1,17 +1,54 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use uuid::Uuid;
 
 use crate::ast;
-use crate::error::{RResult, RuntimeError, TryCollectMany};
+use crate::error::{ErrInRange, RResult, RuntimeError, TryCollectMany};
 use crate::parser::expressions;
 use crate::parser::grammar::{Pattern, PatternPart};
 use crate::program::functions::{FunctionHead, ParameterKey};
-use crate::resolver::scopes;
+use crate::resolver::{interpreter_mock, scopes};
 use crate::util::position::Positioned;
 
-pub fn try_parse_pattern(decoration: &ast::Expression, function: Rc<FunctionHead>, scope: &scopes::Scope) -> RResult<Rc<Pattern<Rc<FunctionHead>>>> {
+/// Whether the decoration is `pure`, which asserts that the function has no side effects.
+pub fn is_pure_decoration(decoration: &ast::Expression) -> bool {
+    matches!(&decoration[..], [term] if matches!(&term.value, ast::Term::Identifier(name) if name == "pure"))
+}
+
+/// The message of a deprecation decoration, like `deprecated("Use greeting instead.")`.
+/// Returns None if the decoration isn't one.
+pub fn try_parse_deprecation(decoration: &ast::Expression) -> Option<RResult<String>> {
+    let [name, arguments] = &decoration[..] else { return None };
+    let (ast::Term::Identifier(name), ast::Term::Struct(arguments)) = (&name.value, &arguments.value) else { return None };
+    if name != "deprecated" {
+        return None
+    }
+
+    let message = match &arguments.arguments[..] {
+        [argument] if argument.value.key == ParameterKey::Positional && argument.value.type_declaration.is_none() => {
+            match &argument.value.value[..] {
+                [term] => match &term.value {
+                    ast::Term::StringLiteral(parts) => parts.iter().map(|part| match &part.value {
+                        ast::StringPart::Literal(literal) => Some(literal.as_str()),
+                        ast::StringPart::Object(_) => None,
+                    }).collect::<Option<String>>(),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    Some(message.ok_or_else(|| RuntimeError::error("deprecated expects a single string literal, e.g. deprecated(\"Use greeting instead.\").").to_array()))
+}
+
+/// Parses a pattern decoration for a function. Two forms are accepted:
+/// - `pattern(lhs + rhs, AdditionPrecedence)`, where identifiers that aren't parameter names are keywords.
+/// - `pattern(infix "<+>", precedence: AdditionPrecedence)`, or `prefix` for unary functions.
+///   `postfix` is rejected, because the grammar doesn't support right unary operators yet.
+pub fn try_parse_pattern(decoration: &ast::Expression, function: Arc<FunctionHead>, scope: &scopes::Scope) -> RResult<Arc<Pattern<Arc<FunctionHead>>>> {
     let parameters = function.interface.parameters.iter().map(|p| p.internal_name.clone()).collect_vec();
 
     let parsed = expressions::parse(decoration, &scope.grammar)?;
@@ -32,35 +69,87 @@ pub fn try_parse_pattern(decoration: &ast::Expression, function: Rc<FunctionHead
         return Err(RuntimeError::error("pattern decoration needs two arguments.").to_array())
     };
 
-    if a.value.key != ParameterKey::Positional || a.value.type_declaration.is_some() ||
-        b.value.key != ParameterKey::Positional || b.value.type_declaration.is_some() {
+    if a.value.key != ParameterKey::Positional || a.value.type_declaration.is_some() || b.value.type_declaration.is_some() {
         return Err(RuntimeError::error("pattern decoration arguments are faulty.").to_array())
     }
 
+    // The precedence may be passed positionally, or as precedence: Group.
+    if !matches!(&b.value.key, ParameterKey::Positional) && b.value.key != ParameterKey::Name("precedence".to_string()) {
+        return Err(RuntimeError::error("Second argument to pattern needs to be the precedence.").to_array())
+    }
+
     let precedence_group = match &b.value.value.iter().map(|p| p.as_ref()).collect_vec()[..] {
-        [Positioned { position, value: ast::Term::Identifier(precedence) }] =>
-            scope.resolve_precedence_group(&precedence)?,
+        [Positioned { position: _, value: ast::Term::Identifier(precedence) }] =>
+            scope.resolve_precedence_group(precedence)?,
         _ => return Err(RuntimeError::error("Second argument to pattern needs to be a precedence name.").to_array())
     };
 
-    let parts: Vec<Box<PatternPart>> = a.value.value.iter()
-        .map(|pterm| {
-            match &pterm.value {
-                ast::Term::Identifier(i) => {
-                    Ok(Box::new(parameters.iter()
-                        .position(|p| p == i)
-                        .map(|p| PatternPart::Parameter(p))
-                        .unwrap_or(PatternPart::Keyword(i.clone()))))
-                },
-                _ => Err(RuntimeError::error("Bad pattern.").to_array()),
+    let parts: Vec<Box<PatternPart>> = match &a.value.value.iter().map(|p| p.as_ref()).collect_vec()[..] {
+        [
+            Positioned { position, value: ast::Term::Identifier(form) },
+            Positioned { value: ast::Term::StringLiteral(_), .. },
+        ] if form == "postfix" => {
+            return Err(RuntimeError::error("postfix patterns are not supported yet; use a prefix or infix pattern.").to_array()).err_in_range(position);
+        }
+        // e.g. pattern(infix "<+>", precedence: AdditionPrecedence)
+        [
+            Positioned { position: _, value: ast::Term::Identifier(form) },
+            Positioned { position, value: ast::Term::StringLiteral(keyword) },
+        ] if matches!(form.as_str(), "prefix" | "infix") => {
+            let keyword = interpreter_mock::plain_string_literal("pattern", keyword)?;
+            if !is_valid_keyword(keyword) {
+                return Err(RuntimeError::error(format!("Pattern keyword '{}' is not a valid identifier or operator.", keyword).as_str()).to_array()).err_in_range(position);
+            }
+            let keyword = Box::new(PatternPart::Keyword(keyword.to_string()));
+
+            let arity = match form.as_str() {
+                "infix" => 2,
+                _ => 1,
+            };
+            if parameters.len() != arity {
+                return Err(RuntimeError::error(format!("{} pattern needs a function with {} parameter(s), but it has {}.", form, arity, parameters.len()).as_str()).to_array());
+            }
+
+            match form.as_str() {
+                "prefix" => vec![keyword, Box::new(PatternPart::Parameter(0))],
+                _ => vec![Box::new(PatternPart::Parameter(0)), keyword, Box::new(PatternPart::Parameter(1))],
             }
-        })
-        .try_collect_many()?;
+        }
+        // e.g. pattern(lhs + rhs, AdditionPrecedence)
+        terms => terms.iter()
+            .map(|pterm| {
+                match &pterm.value {
+                    ast::Term::Identifier(i) => {
+                        Ok(Box::new(parameters.iter()
+                            .position(|p| p == i)
+                            .map(PatternPart::Parameter)
+                            .unwrap_or(PatternPart::Keyword(i.clone()))))
+                    },
+                    _ => Err(RuntimeError::error("Bad pattern.").to_array()),
+                }
+            })
+            .try_collect_many()?,
+    };
 
-    Ok(Rc::new(Pattern {
+    Ok(Arc::new(Pattern {
         id: Uuid::new_v4(),
         precedence_group,
         parts,
-        function: function,
+        function,
     }))
 }
+
+/// Whether the keyword would be read back as a single identifier or operator token.
+fn is_valid_keyword(keyword: &str) -> bool {
+    let mut chars = keyword.chars();
+    match chars.next() {
+        Some('a'..='z' | 'A'..='Z' | '_') => chars.all(|ch| ch.is_alphanumeric() || ch == '_'),
+        Some(_) => {
+            keyword.chars().all(|ch| "!+\\-*/&%=>|.^?<".contains(ch))
+                && !matches!(keyword, "=" | "." | "!" | "->")
+                // The lexer reads a second '-' as the start of a comment.
+                && keyword.chars().nth(1) != Some('-')
+        },
+        None => false,
+    }
+}
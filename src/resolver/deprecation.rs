@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use crate::error::codes::ErrorCode;
+use crate::error::RuntimeError;
+use crate::program::functions::FunctionHead;
+use crate::program::traits::Trait;
+use crate::program::types::{TypeProto, TypeUnit};
+use crate::source::Source;
+
+/// The warning for calling the function, if it is declared deprecated.
+pub fn function_warning(source: &Source, function: &Arc<FunctionHead>) -> Option<RuntimeError> {
+    let message = source.fn_deprecations.get(function)?;
+    Some(
+        RuntimeError::warning(format!("Function {} is deprecated: {}", source.qualified_name(function), message).as_str())
+            .with_code(ErrorCode::Deprecated)
+    )
+}
+
+/// The warning for using the trait, if it is declared deprecated.
+pub fn trait_warning(source: &Source, trait_: &Arc<Trait>) -> Option<RuntimeError> {
+    let message = source.trait_deprecations.get(trait_)?;
+    Some(
+        RuntimeError::warning(format!("Trait {} is deprecated: {}", trait_.name, message).as_str())
+            .with_code(ErrorCode::Deprecated)
+    )
+}
+
+/// Warnings for the deprecated traits in the types, including their arguments. Each trait warns once.
+pub fn type_warnings<'a>(source: &Source, types: impl Iterator<Item=&'a Arc<TypeProto>>) -> Vec<RuntimeError> {
+    let mut traits: Vec<&Arc<Trait>> = vec![];
+    let mut todo = types.map(Arc::as_ref).collect::<Vec<_>>();
+    while let Some(type_) = todo.pop() {
+        if let TypeUnit::Struct(trait_) = &type_.unit {
+            if !traits.contains(&trait_) {
+                traits.push(trait_);
+            }
+        }
+        todo.extend(type_.arguments.iter().map(Arc::as_ref));
+    }
+
+    traits.into_iter().filter_map(|trait_| trait_warning(source, trait_)).collect()
+}
@@ -0,0 +1,107 @@
+use std::ops::Range;
+
+use crate::ast;
+use crate::program::functions::ParameterKey;
+use crate::util::position::Positioned;
+
+/// Expands conformances that are derived from a struct's fields, like `declare Point is ToJson :: {};`.
+/// The derived functions are added to the declaration's block, so they are resolved like hand-written ones.
+/// Returns None if nothing in the file is derived.
+pub fn derive_conformances(syntax: &ast::Block) -> Option<ast::Block> {
+    let mut derived = false;
+    let statements = syntax.statements.iter().map(|statement| {
+        let ast::Statement::Conformance(conformance) = &statement.value.value else {
+            return statement.clone()
+        };
+        let Some(function) = derive_function(conformance, syntax, &statement.value.position) else {
+            return statement.clone()
+        };
+
+        derived = true;
+        let mut conformance = conformance.clone();
+        conformance.block.statements.push(Box::new(ast::Decorated::undecorated(Positioned {
+            position: statement.value.position.clone(),
+            value: ast::Statement::FunctionDeclaration(Box::new(function)),
+        })));
+        Box::new(statement.with_value(statement.value.with_value(ast::Statement::Conformance(conformance))))
+    }).collect();
+
+    derived.then_some(ast::Block { statements })
+}
+
+fn derive_function(conformance: &ast::TraitConformanceDeclaration, syntax: &ast::Block, range: &Range<usize>) -> Option<ast::Function> {
+    if !conformance.block.statements.is_empty() {
+        return None
+    }
+    let [declared] = &conformance.declared[..] else { return None };
+    let [declared_for] = &conformance.declared_for[..] else { return None };
+    let (ast::Term::Identifier(trait_name), ast::Term::Identifier(struct_name)) = (&declared.value, &declared_for.value) else {
+        return None
+    };
+
+    let fields = struct_fields(syntax, struct_name)?;
+    match trait_name.as_str() {
+        "ToJson" => Some(derive_to_json(&fields, range)),
+        _ => None,
+    }
+}
+
+/// The fields of a struct declared in the same file, if it is one.
+fn struct_fields<'a>(syntax: &'a ast::Block, name: &str) -> Option<Vec<&'a str>> {
+    let trait_ = syntax.statements.iter().find_map(|statement| match &statement.value.value {
+        ast::Statement::Trait(trait_) if trait_.name == name => Some(trait_),
+        _ => None,
+    })?;
+
+    trait_.block.statements.iter().map(|statement| match &statement.value.value {
+        ast::Statement::VariableDeclaration { identifier, .. } => Some(identifier.as_str()),
+        _ => None,
+    }).collect()
+}
+
+/// `def (self 'Self).to_json() -> String :: "{\"x\": \(self.x.to_json()), ...}";`
+fn derive_to_json(fields: &[&str], range: &Range<usize>) -> ast::Function {
+    let term = |term: ast::Term| Box::new(Positioned { position: range.clone(), value: term });
+    let identifier = |name: &str| term(ast::Term::Identifier(name.to_string()));
+    let positional = |value: ast::Expression, type_declaration: Option<ast::Expression>| Box::new(Positioned {
+        position: range.clone(),
+        value: ast::StructArgument { key: ParameterKey::Positional, value, type_declaration },
+    });
+
+    let mut parts = vec![];
+    let mut literal = "{".to_string();
+    for (idx, field) in fields.iter().enumerate() {
+        if idx > 0 {
+            literal.push_str(", ");
+        }
+        literal.push_str(&format!("\"{}\": ", field));
+        parts.push(Box::new(Positioned { position: range.clone(), value: ast::StringPart::Literal(literal) }));
+
+        let to_json = ast::Expression::from(vec![
+            identifier("self"), term(ast::Term::Dot), identifier(field),
+            term(ast::Term::Dot), identifier("to_json"), term(ast::Term::Struct(Box::new(ast::Struct::empty()))),
+        ]);
+        parts.push(Box::new(Positioned {
+            position: range.clone(),
+            value: ast::StringPart::Object(Box::new(ast::Struct { arguments: vec![positional(to_json, None)] })),
+        }));
+        literal = String::new();
+    }
+    literal.push('}');
+    parts.push(Box::new(Positioned { position: range.clone(), value: ast::StringPart::Literal(literal) }));
+
+    let self_parameter = ast::Struct {
+        arguments: vec![positional(ast::Expression::from(vec![identifier("self")]), Some(ast::Expression::from(vec![identifier("Self")])))],
+    };
+    ast::Function {
+        interface: ast::FunctionInterface {
+            expression: ast::Expression::from(vec![
+                term(ast::Term::Struct(Box::new(self_parameter))), term(ast::Term::Dot),
+                identifier("to_json"), term(ast::Term::Struct(Box::new(ast::Struct::empty()))),
+            ]),
+            return_type: Some(ast::Expression::from(vec![identifier("String")])),
+            where_clause: None,
+        },
+        body: Some(ast::Expression::from(vec![term(ast::Term::StringLiteral(parts))])),
+    }
+}
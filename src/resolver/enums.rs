@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::ast;
+use crate::error::{RResult, RuntimeError};
+use crate::program::allocation::Mutability;
+use crate::program::builtins::traits::make_eq_functions;
+use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
+use crate::program::functions::{FunctionHead, FunctionInterface, Parameter, ParameterKey};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, PrimitiveOperation};
+use crate::program::primitives;
+use crate::program::traits::{Trait, TraitConformanceRule};
+use crate::program::types::TypeProto;
+use crate::resolver::global::GlobalResolver;
+use crate::resolver::referencible;
+use crate::source::EnumInfo;
+use crate::util::position::Positioned;
+
+/// Variants are represented by their index, which must fit a UInt8.
+const MAX_VARIANTS: usize = u8::MAX as usize + 1;
+
+/// Declares the enum's trait, a getter on its metatype for each variant (e.g. Color.Red),
+///  and its conformance to Eq.
+pub fn resolve_enum(syntax: &ast::EnumDefinition, resolver: &mut GlobalResolver) -> RResult<()> {
+    if syntax.variants.is_empty() {
+        return Err(RuntimeError::error(format!("Enum {} must declare at least one variant.", syntax.name).as_str()).to_array())
+    }
+    if syntax.variants.len() > MAX_VARIANTS {
+        return Err(RuntimeError::error(format!("Enum {} declares {} variants, but at most {} are supported.", syntax.name, syntax.variants.len(), MAX_VARIANTS).as_str()).to_array())
+    }
+    for (idx, variant) in syntax.variants.iter().enumerate() {
+        if let Some(previous) = syntax.variants[..idx].iter().find(|previous| previous.value == variant.value) {
+            return Err(
+                RuntimeError::error(format!("Variant {} is declared more than once.", variant.value).as_str())
+                    .in_range(variant.position.clone())
+                    .with_note(RuntimeError::note("Previously declared here.").in_range(previous.position.clone()))
+                    .to_array()
+            )
+        }
+    }
+
+    let trait_ = Arc::new(Trait::new_with_self(&syntax.name));
+    let getter = referencible::add_trait(resolver.runtime, resolver.module, Some(&mut resolver.global_variables), &trait_)?;
+
+    let enum_type = TypeProto::unit_struct(&trait_);
+    let meta_type = TypeProto::one_arg(&resolver.runtime.Metatype, enum_type.clone());
+    let variants = syntax.variants.iter().map(|_| FunctionHead::new_static(Arc::new(FunctionInterface {
+        parameters: vec![Parameter {
+            external_key: ParameterKey::Positional,
+            internal_name: "type".to_string(),
+            type_: meta_type.clone(),
+            mutability: Mutability::Immutable,
+        }],
+        return_type: enum_type.clone(),
+        requirements: Default::default(),
+        generics: Default::default(),
+    }))).collect::<Vec<_>>();
+
+    let traits = resolver.runtime.traits.as_ref().unwrap().clone();
+    let bool_type = TypeProto::unit_struct(&resolver.runtime.primitives.as_ref().unwrap()[&primitives::Type::Bool]);
+    let eq_functions = make_eq_functions(&enum_type, &bool_type);
+    let enum_ = Arc::new(EnumInfo {
+        trait_: Arc::clone(&trait_),
+        variants: variants.clone(),
+        variant_names: syntax.variants.iter().map(|variant| variant.value.clone()).collect(),
+        getter,
+        equal_to: Arc::clone(&eq_functions.equal_to.target),
+    });
+    resolver.runtime.source.enum_by_trait.insert(Arc::clone(&trait_), Arc::clone(&enum_));
+
+    for (idx, (head, name)) in variants.into_iter().zip(enum_.variant_names.iter()).enumerate() {
+        resolver.runtime.source.fn_logic.insert(
+            Arc::clone(&head),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::EnumVariant(Arc::clone(&enum_), idx))
+        );
+        resolver.add_function_interface(head, FunctionRepresentation::new(name, FunctionTargetType::Member, FunctionCallExplicity::Implicit))?;
+    }
+
+    // Variants are equal if their tags are.
+    for (function, operation) in [
+        (&eq_functions.equal_to, PrimitiveOperation::EqualTo),
+        (&eq_functions.not_equal_to, PrimitiveOperation::NotEqualTo),
+    ] {
+        resolver.runtime.source.fn_logic.insert(
+            Arc::clone(&function.target),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::PrimitiveOperation { operation, type_: primitives::Type::UInt(8) })
+        );
+        resolver.add_function_interface(Arc::clone(&function.target), function.representation.clone())?;
+    }
+
+    let rule = TraitConformanceRule::manual(
+        traits.Eq.create_generic_binding(vec![("Self", enum_type)]),
+        vec![
+            (&traits.Eq_functions.equal_to.target, &eq_functions.equal_to.target),
+            (&traits.Eq_functions.not_equal_to.target, &eq_functions.not_equal_to.target),
+        ]
+    );
+    resolver.module.trait_conformance.add_conformance_rule(Arc::clone(&rule));
+    resolver.global_variables.trait_conformance.add_conformance_rule(rule);
+
+    Ok(())
+}
+
+/// For each arm of a match over the enum, the index of the variant it matches, or None for the default arm _.
+/// Every variant must be matched by exactly one arm, unless the default arm comes last to match the rest.
+pub fn match_arm_variants(enum_: &EnumInfo, arms: &[Positioned<ast::MatchArm>]) -> RResult<Vec<Option<usize>>> {
+    let mut variants = vec![];
+    for (idx, arm) in arms.iter().enumerate() {
+        let pattern = &arm.value.pattern;
+        if let Some(previous) = arms[..idx].iter().find(|previous| previous.value.pattern.value == "_") {
+            return Err(
+                RuntimeError::error("Arms after the default arm are never matched.")
+                    .in_range(pattern.position.clone())
+                    .with_note(RuntimeError::note("The default arm is here.").in_range(previous.value.pattern.position.clone()))
+                    .to_array()
+            )
+        }
+        if pattern.value == "_" {
+            variants.push(None);
+            continue
+        }
+
+        let Some(variant) = enum_.variant_names.iter().position(|name| name == &pattern.value) else {
+            return Err(
+                RuntimeError::error(format!("Enum {} has no variant {}.", enum_.trait_.name, pattern.value).as_str())
+                    .in_range(pattern.position.clone())
+                    .to_array()
+            )
+        };
+        if let Some(previous) = arms[..idx].iter().find(|previous| previous.value.pattern.value == pattern.value) {
+            return Err(
+                RuntimeError::error(format!("Variant {} is matched more than once.", pattern.value).as_str())
+                    .in_range(pattern.position.clone())
+                    .with_note(RuntimeError::note("Previously matched here.").in_range(previous.value.pattern.position.clone()))
+                    .to_array()
+            )
+        }
+        variants.push(Some(variant));
+    }
+
+    if !variants.contains(&None) {
+        let uncovered = enum_.variant_names.iter().enumerate()
+            .filter(|(idx, _)| !variants.contains(&Some(*idx)))
+            .map(|(_, name)| name.as_str())
+            .collect::<Vec<_>>();
+        if !uncovered.is_empty() {
+            return Err(
+                RuntimeError::error(format!("match over {} does not cover {}.", enum_.trait_.name, uncovered.join(", ")).as_str())
+                    .with_note(RuntimeError::note("Add an arm for each, or a default arm like `_ :: value`."))
+                    .to_array()
+            )
+        }
+    }
+
+    Ok(variants)
+}
@@ -1,46 +1,52 @@
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::program::allocation::Mutability;
 use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
 use crate::program::functions::{FunctionHead, FunctionInterface, Parameter, ParameterKey};
 use crate::program::traits::{FieldHint, Trait};
 use crate::program::types::TypeProto;
 
-pub fn make(name: &str, self_type: &Rc<TypeProto>, field_type: &Rc<TypeProto>, add_getter: bool, add_setter: bool) -> FieldHint {
+pub fn make(name: &str, self_type: &Arc<TypeProto>, field_type: &Arc<TypeProto>, generics: &HashMap<String, Arc<Trait>>, add_getter: bool, add_setter: bool) -> FieldHint {
     let getter = add_getter.then_some({
-        let head = FunctionHead::new_static(
-            Rc::new(FunctionInterface {
+        
+        FunctionHead::new_static(
+            Arc::new(FunctionInterface {
                 parameters: vec![
                     Parameter {
                         external_key: ParameterKey::Positional,
                         internal_name: "self".to_string(),
                         type_: self_type.clone(),
+                        mutability: Mutability::Immutable,
                     }],
                 return_type: field_type.clone(),
                 requirements: Default::default(),
-                generics: Default::default(),
+                generics: generics.clone(),
             }),
-        );
-        head
+        )
     });
 
     let setter = add_setter.then_some({
-        let head = FunctionHead::new_static(
-            Rc::new(FunctionInterface {
+        
+        FunctionHead::new_static(
+            Arc::new(FunctionInterface {
                 parameters: vec![Parameter {
                     external_key: ParameterKey::Positional,
                     internal_name: "self".to_string(),
                     type_: self_type.clone(),
+                    // Setting a field mutates the object.
+                    mutability: Mutability::Mutable,
                 }, Parameter {
                     external_key: ParameterKey::Positional,
                     internal_name: name.to_string(),
                     type_: field_type.clone(),
+                    mutability: Mutability::Immutable,
                 }],
                 return_type: TypeProto::void(),
                 requirements: Default::default(),
-                generics: Default::default(),
+                generics: generics.clone(),
             }),
-        );
-        head
+        )
     });
 
     FieldHint {
@@ -54,13 +60,13 @@ pub fn make(name: &str, self_type: &Rc<TypeProto>, field_type: &Rc<TypeProto>, a
 pub fn add_to_trait(trait_: &mut Trait, field: FieldHint) {
     if let Some(getter) = &field.getter {
         trait_.insert_function(
-            Rc::clone(getter),
+            Arc::clone(getter),
             FunctionRepresentation::new(&field.name, FunctionTargetType::Member, FunctionCallExplicity::Implicit)
         )
     }
     if let Some(setter) = &field.setter {
         trait_.insert_function(
-            Rc::clone(setter),
+            Arc::clone(setter),
             FunctionRepresentation::new(&field.name, FunctionTargetType::Member, FunctionCallExplicity::Implicit)
         )
     }
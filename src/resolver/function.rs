@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::ops::Range;
+use std::sync::Arc;
 
 use uuid::Uuid;
 
@@ -11,12 +12,13 @@ use crate::program::expression_tree::ExpressionTree;
 use crate::program::functions::FunctionHead;
 use crate::program::generics::TypeForest;
 use crate::program::global::FunctionImplementation;
+use crate::program::module::{ModuleName, ModuleSettings};
 use crate::program::traits::{RequirementsAssumption, TraitConformance, TraitConformanceRule};
 use crate::resolver::imperative::ImperativeResolver;
 use crate::resolver::imperative_builder::ImperativeBuilder;
 use crate::resolver::scopes;
 
-pub fn resolve_function_body(head: Rc<FunctionHead>, body: &ast::Expression, scope: &scopes::Scope, runtime: &mut Runtime) -> RResult<Box<FunctionImplementation>> {
+pub fn resolve_function_body(head: Arc<FunctionHead>, body: &ast::Expression, return_type_range: Option<Range<usize>>, scope: &scopes::Scope, module_name: &ModuleName, settings: &ModuleSettings, runtime: &mut Runtime) -> RResult<Box<FunctionImplementation>> {
     let mut scope = scope.subscope();
 
     let granted_requirements = scope.trait_conformance.assume_granted(
@@ -35,39 +37,61 @@ pub fn resolve_function_body(head: Rc<FunctionHead>, body: &ast::Expression, sco
     // Register parameters as variables.
     let mut parameter_variables = vec![];
     for parameter in head.interface.parameters.clone() {
-        let parameter_variable = ObjectReference::new_immutable(parameter.type_.clone());
-        _ = builder.register_local(&parameter.internal_name, Rc::clone(&parameter_variable), &mut scope)?;
+        let parameter_variable = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: parameter.type_.clone(), mutability: parameter.mutability });
+        builder.register_local(&parameter.internal_name, Arc::clone(&parameter_variable), &mut scope)?;
         parameter_variables.push(parameter_variable);
     }
 
     let mut resolver = ImperativeResolver {
-        return_type: Rc::clone(&head.interface.return_type),
+        return_type: Arc::clone(&head.interface.return_type),
+        return_type_range,
+        module_name: module_name.clone(),
+        settings: settings.clone(),
+        first_return: None,
+        valueless_blocks: vec![],
+        diverging_blocks: vec![],
+        statement_ranges: Default::default(),
+        expression_ranges: Default::default(),
         builder,
         ambiguities: vec![],
+        literal_calls: vec![],
+        ambiguity_attempts: 0,
+        warnings: vec![],
     };
 
     let head_expression = resolver.resolve_expression(body, &scope)?;
-    resolver.builder.types.bind(head_expression, &head.interface.return_type)?;
+    // The body's value is discarded by void functions.
+    if !head.interface.return_type.unit.is_void() {
+        resolver.builder.types.bind(head_expression, &head.interface.return_type)?;
+    }
     resolver.builder.expression_tree.root = head_expression;  // TODO This is kinda dumb; but we can't write into an existing head expression
     resolver.resolve_all_ambiguities()?;
+    resolver.check_literal_ranges()?;
+    resolver.check_mutable_arguments()?;
+    resolver.check_reflection_calls(&head.interface.generics)?;
+    resolver.check_block_values()?;
 
     Ok(Box::new(FunctionImplementation {
         head,
-        requirements_assumption: Box::new(RequirementsAssumption { conformance: HashMap::from_iter(granted_requirements.into_iter().map(|c| (Rc::clone(&c.binding), c))) }),
+        requirements_assumption: Box::new(RequirementsAssumption { conformance: HashMap::from_iter(granted_requirements.into_iter().map(|c| (Arc::clone(&c.binding), c))) }),
         expression_tree: resolver.builder.expression_tree,
         type_forest: resolver.builder.types,
         parameter_locals: parameter_variables,
         locals_names: resolver.builder.locals_names,
+        statement_ranges: resolver.statement_ranges,
+        expression_ranges: resolver.expression_ranges,
+        ambiguity_attempts: resolver.ambiguity_attempts,
+        warnings: resolver.warnings,
     }))
 }
 
-fn add_conformances_to_scope(scope: &mut scopes::Scope, granted_requirements: &Vec<Rc<TraitConformance>>) -> RResult<()> {
+fn add_conformances_to_scope(scope: &mut scopes::Scope, granted_requirements: &[Arc<TraitConformance>]) -> RResult<()> {
     // TODO Register generic types as variables so they can be referenced in the function
 
     // Let our scope know that our parameter types (all of type any!) conform to the requirements
     for conformance in granted_requirements.iter() {
         scope.trait_conformance.add_conformance_rule(TraitConformanceRule::direct(
-            Rc::clone(conformance),
+            Arc::clone(conformance),
         ));
     };
 
@@ -1,94 +1,236 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 
+use display_with_options::with_options;
 use itertools::Itertools;
+use log::debug;
+use uuid::Uuid;
 
 use crate::ast;
+use crate::error::codes::ErrorCode;
 use crate::error::{ErrInRange, RResult, RuntimeError};
 use crate::interpreter::runtime::Runtime;
 use crate::parser::expressions;
+use crate::parser::grammar::Pattern;
 use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
 use crate::program::functions::{FunctionHead, FunctionInterface};
 use crate::program::global::{FunctionLogic, FunctionLogicDescriptor};
 use crate::program::module::Module;
 use crate::program::traits::{Trait, TraitBinding, TraitConformanceRule};
 use crate::program::types::*;
-use crate::resolver::{imports, interpreter_mock, referencible, scopes};
+use crate::resolver::{anonymous_structs, boxes, deprecation, derive, imports, incremental, referencible, scopes};
 use crate::resolver::conformance::ConformanceResolver;
-use crate::resolver::decorations::try_parse_pattern;
+use crate::resolver::decorations::{is_pure_decoration, try_parse_deprecation, try_parse_pattern};
+use crate::resolver::enums::resolve_enum;
 use crate::resolver::function::resolve_function_body;
 use crate::resolver::imports::resolve_imports;
 use crate::resolver::interface::resolve_function_interface;
 use crate::resolver::precedence_order::resolve_precedence_order;
+use crate::resolver::settings::{resolve_allow, resolve_strict};
 use crate::resolver::traits::{TraitResolver, try_make_struct};
-use crate::resolver::type_factory::TypeFactory;
+use crate::resolver::type_factory::{shadowed_primitive, TypeFactory};
+use crate::source::DeclarationSite;
 use crate::static_analysis;
+use crate::static_analysis::purity;
 use crate::util::position::Positioned;
 
 pub struct GlobalResolver<'a> {
     pub runtime: &'a mut Runtime,
     pub global_variables: scopes::Scope<'a>,
-    pub function_bodies: HashMap<Rc<FunctionHead>, Positioned<&'a ast::Expression>>,
+    pub function_bodies: HashMap<Arc<FunctionHead>, Positioned<FunctionBody<'a>>>,
     pub module: &'a mut Module,
+    /// Where the patterns of this module were declared, for error reporting.
+    pub pattern_positions: HashMap<Uuid, Range<usize>>,
+    /// Functions decorated as pure, and where they were decorated.
+    pub pure_declarations: Vec<(Arc<FunctionHead>, Range<usize>)>,
+    /// Functions declared by the module's global statements, in order.
+    pub declared_functions: Vec<Arc<FunctionHead>>,
 }
 
-pub fn resolve_file(syntax: &ast::Block, scope: &scopes::Scope, runtime: &mut Runtime, module: &mut Module) -> RResult<()> {
+pub struct FunctionBody<'a> {
+    pub body: &'a ast::Expression,
+    pub return_type: Option<&'a ast::Expression>,
+}
+
+pub fn resolve_file(original_syntax: &ast::Block, scope: &scopes::Scope, runtime: &mut Runtime, module: &mut Module) -> RResult<()> {
+    let derived_syntax = derive::derive_conformances(original_syntax);
+    let syntax = derived_syntax.as_ref().unwrap_or(original_syntax);
     let mut global_resolver = GlobalResolver {
         runtime,
         module,
         global_variables: scope.subscope(),
         function_bodies: Default::default(),
+        pattern_positions: Default::default(),
+        pure_declarations: Default::default(),
+        declared_functions: Default::default(),
     };
 
-    // Resolve global types / interfaces
-    for statement in &syntax.statements {
+    // Resolve global types / interfaces.
+    // This happens in phases, so that declarations can use anything declared in the file, regardless of order.
+    let in_phase = |phase| syntax.statements.iter()
+        .map(AsRef::as_ref)
+        .filter(move |statement: &&ast::Decorated<Positioned<ast::Statement>>| declaration_phase(&statement.value.value) == phase);
+    for statement in in_phase(DeclarationPhase::Scope) {
+        global_resolver.resolve_global_statement(statement, &HashSet::new())
+            .err_in_range(&statement.value.position)?;
+    }
+    // Anonymous structs have no declaration of their own, so they are declared wherever they are mentioned.
+    anonymous_structs::declare_in_block(global_resolver.runtime, syntax);
+    global_resolver.resolve_types(in_phase(DeclarationPhase::Types).collect_vec())?;
+    for statement in in_phase(DeclarationPhase::Interfaces) {
         global_resolver.resolve_global_statement(statement, &HashSet::new())
             .err_in_range(&statement.value.position)?;
     }
 
+    global_resolver.module.grammar = global_resolver.global_variables.grammar.clone();
+    global_resolver.module.declarations = incremental::declarations(original_syntax, global_resolver.declared_functions);
     let global_variable_scope = global_resolver.global_variables;
     let runtime = global_resolver.runtime;
 
     // Resolve function bodies
+    let settings = global_resolver.module.settings.clone();
     let mut errors = vec![];
     for (head, pbody) in global_resolver.function_bodies {
-        match resolve_function_body(head, pbody.value, &global_variable_scope, runtime).and_then(|mut imp| {
-            static_analysis::check(&mut imp)?;
+        debug!("Resolving function {:?}", with_options(head.as_ref(), &runtime.source.fn_representations[&head]));
+        match resolve_function_body(head, pbody.value.body, pbody.value.return_type.and_then(|t| t.position()), &global_variable_scope, &global_resolver.module.name, &settings, runtime).and_then(|mut imp| {
+            global_resolver.module.warnings.extend(static_analysis::check(&mut imp, &settings, runtime)?);
             Ok(imp)
         }) {
             Ok(implementation) => {
-                runtime.source.fn_logic.insert(Rc::clone(&implementation.head), FunctionLogic::Implementation(implementation));
+                debug!("Resolved function {} ({} ambiguity attempts)", implementation.head.function_id, implementation.ambiguity_attempts);
+                global_resolver.module.implemented_functions.push(Arc::clone(&implementation.head));
+                runtime.source.fn_logic.insert(Arc::clone(&implementation.head), FunctionLogic::Implementation(implementation));
             }
             Err(e) => {
+                debug!("Failed to resolve function ({} errors)", e.len());
                 errors.extend(e.iter().map(|e| e.clone().in_range(pbody.position.clone())));
             }
         }
     }
 
+    let module = global_resolver.module;
+    purity::infer(
+        module.implemented_functions.iter().filter_map(|head| runtime.source.fn_logic[head].as_implementation().ok()),
+        &runtime.source.fn_logic,
+        &mut runtime.source.impure_functions,
+    );
+    for (head, range) in global_resolver.pure_declarations {
+        let chain = purity::impure_call_chain(&head, &runtime.source.impure_functions);
+        let Some((_, callees)) = chain.split_first() else {
+            continue
+        };
+
+        let name_of = |head: &Arc<FunctionHead>| format!("`{}`", runtime.source.fn_representations[head].name);
+        errors.push(
+            RuntimeError::error(format!("{} is declared pure, but calls {}.", name_of(&head), callees.iter().map(name_of).join(", which calls ")).as_str())
+                .in_range(range)
+        );
+    }
+
     match errors.is_empty() {
         true => Ok(()),
         false => Err(errors)
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DeclarationPhase {
+    /// Macros that change what's in scope, or how the file is resolved.
+    Scope,
+    /// Declarations of types, which interfaces may refer to.
+    Types,
+    /// Functions and conformances, whose interfaces refer to types.
+    Interfaces,
+}
+
+fn declaration_phase(statement: &ast::Statement) -> DeclarationPhase {
+    match statement {
+        ast::Statement::Expression(_) => DeclarationPhase::Scope,
+        ast::Statement::Trait(_) | ast::Statement::Enum(_) => DeclarationPhase::Types,
+        _ => DeclarationPhase::Interfaces,
+    }
+}
+
 impl <'a> GlobalResolver<'a> {
-    pub fn resolve_global_statement(&mut self, pstatement: &'a ast::Decorated<Positioned<ast::Statement>>, requirements: &HashSet<Rc<TraitBinding>>) -> RResult<()> {
+    /// Resolves the type declarations, retrying those that fail until no more can be resolved.
+    /// This way, types may refer to types declared after them.
+    /// If some still fail, the errors of the first one are returned.
+    pub fn resolve_types(&mut self, statements: Vec<&'a ast::Decorated<Positioned<ast::Statement>>>) -> RResult<()> {
+        let mut pending = statements;
+        while !pending.is_empty() {
+            let mut failed = vec![];
+            let mut first_errors = None;
+            for statement in pending.iter() {
+                if let Err(errors) = self.resolve_global_statement(statement, &HashSet::new()).err_in_range(&statement.value.position) {
+                    failed.push(*statement);
+                    first_errors.get_or_insert(errors);
+                }
+            }
+
+            if failed.len() == pending.len() {
+                return Err(first_errors.unwrap())
+            }
+            pending = failed;
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve_global_statement(&mut self, pstatement: &'a ast::Decorated<Positioned<ast::Statement>>, requirements: &HashSet<Arc<TraitBinding>>) -> RResult<()> {
         match &pstatement.value.value {
             ast::Statement::FunctionDeclaration(syntax) => {
                 let scope = &self.global_variables;
-                let (fun, representation) = resolve_function_interface(&syntax.interface, &scope, Some(&mut self.module), &self.runtime, requirements, &HashMap::new())?;
+                let (fun, representation) = resolve_function_interface(&syntax.interface, scope, Some(&mut self.module), self.runtime, requirements, &HashMap::new())?;
+                if let Some(where_clause) = &syntax.interface.where_clause {
+                    self.global_variables.where_clauses.insert(Arc::clone(&fun), where_clause.position.clone());
+                }
 
                 for decoration in pstatement.decorations_as_vec()? {
-                    let pattern = try_parse_pattern(decoration, Rc::clone(&fun), &self.global_variables)?;
-                    self.module.patterns.insert(Rc::clone(&pattern));
+                    let range = decoration.position().unwrap_or(pstatement.value.position.clone());
+                    if is_pure_decoration(decoration) {
+                        self.pure_declarations.push((Arc::clone(&fun), range));
+                        continue
+                    }
+                    if let Some(message) = try_parse_deprecation(decoration) {
+                        self.runtime.source.fn_deprecations.insert(Arc::clone(&fun), message.err_in_range(&range)?);
+                        continue
+                    }
+
+                    let pattern = try_parse_pattern(decoration, Arc::clone(&fun), &self.global_variables)
+                        .err_in_range(&range)?;
+                    self.check_pattern_conflicts(&pattern, &range)?;
+                    self.warn_shadowed_functions(&pattern, &range);
+
+                    self.pattern_positions.insert(pattern.id, range);
+                    self.module.patterns.insert(Arc::clone(&pattern));
                     self.global_variables.grammar.add_pattern(pattern)?;
                 }
-                self.schedule_function_body(&fun, syntax.body.as_ref(), pstatement.value.position.clone());
+                self.warn_deprecated_types(fun.interface.parameters.iter().map(|parameter| &parameter.type_).chain([&fun.interface.return_type]), &pstatement.value.position);
+                self.schedule_function_body(&fun, syntax.body.as_ref(), syntax.interface.return_type.as_ref(), pstatement.value.position.clone());
+                self.record_declaration(&fun, pstatement.value.position.clone());
+                self.declared_functions.push(Arc::clone(&fun));
                 self.add_function_interface(fun, representation)?;
             }
             ast::Statement::Trait(syntax) => {
-                pstatement.no_decorations()?;
+                let mut deprecation = None;
+                for decoration in pstatement.decorations_as_vec()? {
+                    let range = decoration.position().unwrap_or(pstatement.value.position.clone());
+                    let Some(message) = try_parse_deprecation(decoration) else {
+                        return Err(RuntimeError::error("Traits can only be decorated with deprecated.").in_range(range).to_array())
+                    };
+                    deprecation = Some(message.err_in_range(&range)?);
+                }
+
+                // TODO Once namespaced references exist, the builtin should stay reachable, e.g. as builtins.Float32.
+                if shadowed_primitive(self.runtime, &syntax.name).is_some() {
+                    self.module.warnings.push(
+                        RuntimeError::warning(format!("Trait {} shadows the builtin primitive of the same name.", syntax.name).as_str())
+                            .with_code(ErrorCode::ShadowedPrimitive)
+                            .in_range(pstatement.value.position.clone())
+                            .with_note(RuntimeError::note(format!("{} refers to this trait in this module, and in modules importing it.", syntax.name).as_str()))
+                    );
+                }
 
                 let mut trait_ = Trait::new_with_self(&syntax.name);
 
@@ -102,10 +244,10 @@ impl <'a> GlobalResolver<'a> {
 
                 let mut scope = self.global_variables.subscope();
                 scope.overload_function(&generic_self_self_getter, FunctionRepresentation::new("Self", FunctionTargetType::Global, FunctionCallExplicity::Implicit))?;
-                self.runtime.source.trait_references.insert(Rc::clone(&generic_self_self_getter), Rc::clone(&trait_.generics["Self"]));
+                self.runtime.source.trait_references.insert(Arc::clone(&generic_self_self_getter), Arc::clone(&trait_.generics["Self"]));
 
                 let mut resolver = TraitResolver {
-                    runtime: &self.runtime,
+                    runtime: self.runtime,
                     trait_: &mut trait_,
                     generic_self_type,
                 };
@@ -115,17 +257,37 @@ impl <'a> GlobalResolver<'a> {
                     resolver.resolve_statement(&statement.value.value, requirements, &HashMap::new(), &scope)
                         .err_in_range(&statement.value.position)?;
                 }
+                for requirement in syntax.where_clause.iter().flat_map(|where_clause| where_clause.value.iter()) {
+                    resolver.resolve_requirement(&requirement.value, &scope)
+                        .err_in_range(&requirement.position)?;
+                }
 
-                self.add_trait(&Rc::new(trait_))?;
+                let trait_ = Arc::new(trait_);
+                self.add_trait(&trait_)?;
+                if let Some(message) = deprecation {
+                    self.runtime.source.trait_deprecations.insert(Arc::clone(&trait_), message);
+                }
+
+                if let Some(where_clause) = &syntax.where_clause {
+                    // The requirements are checked when constructing the struct, so the constructor points to them.
+                    let Some(struct_) = self.runtime.source.struct_by_trait.get(&trait_) else {
+                        return Err(
+                            RuntimeError::error(format!("Only structs can declare a where clause, but {} has abstract functions.", trait_.name).as_str())
+                                .in_range(where_clause.position.clone())
+                                .to_array()
+                        );
+                    };
+                    self.global_variables.where_clauses.insert(Arc::clone(&struct_.constructor), where_clause.position.clone());
+                }
             }
             ast::Statement::Conformance(syntax) => {
                 pstatement.no_decorations()?;
 
-                let mut type_factory = TypeFactory::new(&self.global_variables, &mut self.runtime);
+                let mut type_factory = TypeFactory::new(&self.global_variables, self.runtime);
                 let self_type = type_factory.resolve_type(&syntax.declared_for, true)?;
                 let declared_type = type_factory.resolve_type(&syntax.declared, false)?;
                 let TypeUnit::Struct(declared) = &declared_type.unit else {
-                    panic!("Somehow, the resolved type wasn't a struct.")
+                    return Err(RuntimeError::error(format!("Conformance can only be declared to traits, not {:?}.", declared_type).as_str()).to_array());
                 };
                 if !declared_type.arguments.is_empty() {
                     return Err(RuntimeError::error("Conformance cannot be declared with bindings for now.").to_array());
@@ -138,13 +300,15 @@ impl <'a> GlobalResolver<'a> {
                 }
                 let generics = type_factory.generics;
                 let conformance_requirements = type_factory.requirements;
+                self.warn_deprecated_types([&self_type, &declared_type].into_iter(), &pstatement.value.position);
 
                 // FIXME This is not ideal; technically the trait_references thing should be a BOUND trait,
                 //  because the user may have bound some generics of self in the declaration.
                 //  For now it's fine - determining the self type will be the task of the interpreter in the future anyway.
+                // Any struct type may conform, including the builtin primitives (e.g. Int32).
                 let self_trait = match &self_type.unit {
-                    TypeUnit::Struct(trait_) => Rc::clone(trait_),
-                    _ => panic!()
+                    TypeUnit::Struct(trait_) => Arc::clone(trait_),
+                    _ => return Err(RuntimeError::error(format!("Conformance can only be declared for struct types, not {:?}.", self_type).as_str()).to_array()),
                 };
 
                 let self_meta_type = TypeProto::one_arg(&self.runtime.Metatype, self_type.clone());
@@ -155,35 +319,46 @@ impl <'a> GlobalResolver<'a> {
 
                 let mut scope = self.global_variables.subscope();
                 scope.overload_function(&self_getter, FunctionRepresentation::new("Self", FunctionTargetType::Global, FunctionCallExplicity::Implicit))?;
-                self.runtime.source.trait_references.insert(Rc::clone(&self_getter), self_trait);
+                self.runtime.source.trait_references.insert(Arc::clone(&self_getter), self_trait);
 
-                let mut resolver = ConformanceResolver { runtime: &self.runtime, functions: vec![], };
+                let mut resolver = ConformanceResolver { runtime: self.runtime, functions: vec![], };
                 for statement in syntax.block.statements.iter() {
                     statement.no_decorations()?;
 
-                    resolver.resolve_statement(&statement.value.value, &requirements.union(&conformance_requirements).cloned().collect(), &generics, &scope)
+                    resolver.resolve_statement(&statement.value.value, &statement.value.position, &requirements.union(&conformance_requirements).cloned().collect(), &generics, &scope)
                         .err_in_range(&statement.value.position)?;
                 }
 
                 // TODO To be order independent, we should finalize after sorting...
                 //  ... Or check inconsistencies only at the very end.
                 let conformance = resolver.finalize_conformance(self_binding, &conformance_requirements, &generics)?;
+                let functions = resolver.functions;
 
-                let rule = Rc::new(TraitConformanceRule {
+                let rule = Arc::new(TraitConformanceRule {
                     generics,
                     requirements: conformance_requirements,
                     conformance,
+                    module: Some(self.module.name.clone()),
                 });
                 self.module.trait_conformance.add_conformance_rule(rule.clone());
-                self.global_variables.trait_conformance.add_conformance_rule(rule);
+                // Our own declaration takes precedence over any duplicate, declared here or imported.
+                if let Some(shadowed) = self.global_variables.trait_conformance.add_conformance_rule(Arc::clone(&rule)) {
+                    self.report_duplicate_conformance(&rule, &shadowed, &pstatement.value.position)?;
+                }
 
-                for fun in resolver.functions {
-                    self.schedule_function_body(&fun.function, fun.body.as_ref(), pstatement.value.position.clone());
+                for fun in functions {
+                    self.schedule_function_body(&fun.function, fun.body.as_ref(), fun.return_type.as_ref(), pstatement.value.position.clone());
+                    self.record_declaration(&fun.function, fun.range.clone());
                     // TODO Instead of adding conformance functions statically, we should add the abstract function to the scope.
                     //  This will allow the compiler to determine "function exists but no declaration exists" in the future.
                     self.add_function_interface(fun.function, fun.representation.clone())?;
                 }
             }
+            ast::Statement::Enum(syntax) => {
+                pstatement.no_decorations()?;
+
+                resolve_enum(syntax, self)?;
+            }
             ast::Statement::Expression(e) => {
                 pstatement.no_decorations()?;
                 e.no_errors()?;
@@ -205,17 +380,29 @@ impl <'a> GlobalResolver<'a> {
                         self.global_variables.grammar.set_precedence_order(precedence_order);
                         return Ok(())
                     }
+                    "strict" => {
+                        return resolve_strict(call_struct, &self.global_variables, &mut self.module.settings)
+                    }
+                    "allow" => {
+                        return resolve_allow(call_struct, &self.global_variables, &mut self.module.settings)
+                    }
                     "use" => {
                         for import in resolve_imports(call_struct, &self.global_variables)? {
-                            self.import(&&import.relative_to(&self.module.name))?;
+                            if let Some(alias) = &import.alias {
+                                return Err(
+                                    RuntimeError::error(format!("use! cannot rename modules; only include! can re-export a module as `{}`.", alias).as_str()).to_array()
+                                )
+                            }
+                            self.import(&import.relative_to(&self.module.name), &import.position)?;
                         }
                         return Ok(())
                     }
                     "include" => {
                         for import in resolve_imports(call_struct, &self.global_variables)? {
-                            let import = import.relative_to(&self.module.name);
-                            self.import(&import)?;
-                            self.module.included_modules.push(import);
+                            // The including module sees the names unprefixed, even if they're re-exported under an alias.
+                            let name = import.relative_to(&self.module.name);
+                            self.import(&name, &import.position)?;
+                            self.module.included_modules.push((name, import.alias));
                         }
                         return Ok(())
                     }
@@ -234,34 +421,141 @@ impl <'a> GlobalResolver<'a> {
         Ok(())
     }
 
-    fn import(&mut self, import: &Vec<String>) -> RResult<()> {
-        let root_module = self.runtime.get_or_load_module(import)?;
+    fn import(&mut self, import: &Vec<String>, range: &Range<usize>) -> RResult<()> {
+        // Missing or unreadable modules, and declarations that can't be imported, are reported at the argument that names them.
+        // Errors from within the module already point to its file.
+        let at_import = |errs: Vec<RuntimeError>| errs.into_iter().map(|e| match e.path {
+            None => e.in_range(range.clone()),
+            Some(_) => e,
+        }).collect_vec();
+        let root_module = self.runtime.get_or_load_module(import).map_err(at_import)?;
+        self.module.imported_modules.push(import.clone());
         let root_module_name = root_module.name.clone();
-        imports::deep(&mut self.runtime, root_module_name, &mut self.global_variables)?;
+        // Rules that were known first are kept, so the import's duplicates are skipped.
+        for (skipped, kept) in imports::deep(self.runtime, root_module_name, &mut self.global_variables).map_err(at_import)? {
+            self.report_duplicate_conformance(&kept, &skipped, range)?;
+        }
+        Ok(())
+    }
+
+    /// Warns that a conformance is declared twice, or fails under strict!(deny_duplicate_conformances).
+    fn report_duplicate_conformance(&mut self, used: &Arc<TraitConformanceRule>, ignored: &Arc<TraitConformanceRule>, range: &Range<usize>) -> RResult<()> {
+        let binding = &used.conformance.binding;
+        let types = binding.generic_to_type.iter()
+            .sorted_by_key(|(generic, _)| &generic.name)
+            .map(|(_, type_)| format!("{:?}", type_))
+            .join(", ");
+        let title = format!("Conformance of {} to {} is declared more than once.", types, binding.trait_.name);
+        let notes = [
+            RuntimeError::note(format!("The declaration from {} is used.", self.declaring_module(used)).as_str()),
+            RuntimeError::note(format!("The declaration from {} is ignored.", self.declaring_module(ignored)).as_str()),
+        ];
+
+        if self.module.settings.deny_duplicate_conformances {
+            return Err(
+                RuntimeError::error(title.as_str())
+                    .with_code(ErrorCode::DuplicateConformance)
+                    .in_range(range.clone())
+                    .with_notes(notes.into_iter())
+                    .with_note(RuntimeError::note("Duplicate conformances are denied by strict!(deny_duplicate_conformances)."))
+                    .to_array()
+            )
+        }
+
+        self.module.warnings.push(
+            RuntimeError::warning(title.as_str())
+                .with_code(ErrorCode::DuplicateConformanceWarning)
+                .in_range(range.clone())
+                .with_notes(notes.into_iter())
+        );
         Ok(())
     }
 
-    fn add_trait(&mut self, trait_: &Rc<Trait>) -> RResult<()> {
-        referencible::add_trait(self.runtime, &mut self.module, Some(&mut self.global_variables), &trait_)?;
+    /// The name of the module that declares the rule.
+    fn declaring_module(&self, rule: &Arc<TraitConformanceRule>) -> String {
+        if let Some(module) = &rule.module {
+            return module.join(".")
+        }
+
+        // Builtin rules don't know their module, so it has to be searched.
+        let declares = |module: &Module| module.trait_conformance.conformance_rules.get(&rule.conformance.binding.trait_)
+            .is_some_and(|rules| rules.iter().any(|r| Arc::ptr_eq(r, rule)));
+
+        self.runtime.source.module_by_name.values()
+            .find(|module| declares(module))
+            .map_or(&self.module.name, |module| &module.name)
+            .join(".")
+    }
+
+    fn add_trait(&mut self, trait_: &Arc<Trait>) -> RResult<()> {
+        referencible::add_trait(self.runtime, self.module, Some(&mut self.global_variables), trait_)?;
+        boxes::add_dyn_conformance(self.runtime, self.module, Some(&mut self.global_variables), trait_)?;
         try_make_struct(trait_, self)?;
         Ok(())
     }
 
-    pub fn add_function_interface(&mut self, pointer: Rc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
-        referencible::add_function(self.runtime, &mut self.module, Some(&mut self.global_variables), pointer, representation)?;
+    pub fn add_function_interface(&mut self, pointer: Arc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
+        referencible::add_function(self.runtime, self.module, Some(&mut self.global_variables), pointer, representation)?;
 
         Ok(())
     }
 
-    pub fn schedule_function_body(&mut self, head: &Rc<FunctionHead>, body: Option<&'a ast::Expression>, range: Range<usize>) {
+    /// Warns for each deprecated trait that the declaration uses, unless the module allows it.
+    fn warn_deprecated_types<'b>(&mut self, types: impl Iterator<Item=&'b Arc<TypeProto>>, range: &Range<usize>) {
+        if self.module.settings.allow_deprecated {
+            return
+        }
+        let warnings = deprecation::type_warnings(&self.runtime.source, types);
+        self.module.warnings.extend(warnings.into_iter().map(|warning| warning.in_range(range.clone())));
+    }
+
+        fn check_pattern_conflicts(&self, pattern: &Pattern<Arc<FunctionHead>>, range: &Range<usize>) -> RResult<()> {
+        let Some(conflict) = self.global_variables.grammar.find_conflicting_pattern(pattern) else {
+            return Ok(())
+        };
+
+        let previous_declaration = match self.pattern_positions.get(&conflict.id) {
+            Some(previous_range) => RuntimeError::note("Previously declared here.").in_range(previous_range.clone()),
+            None => RuntimeError::note(format!(
+                "Previously declared (imported) for {:?}.",
+                with_options(conflict.function.as_ref(), &self.runtime.source.fn_representations[&conflict.function])
+            ).as_str()),
+        };
+
+        Err(
+            RuntimeError::error(format!("Pattern keyword '{}' is already declared in {}, not {}.", pattern.keywords().join(" "), conflict.precedence_group.name, pattern.precedence_group.name).as_str())
+                .in_range(range.clone())
+                .with_note(previous_declaration)
+                .to_array()
+        )
+    }
+
+    /// Functions named like the pattern's keywords can no longer be called by name, since the name is parsed as an operator.
+    pub fn warn_shadowed_functions(&mut self, pattern: &Pattern<Arc<FunctionHead>>, range: &Range<usize>) {
+        for keyword in pattern.keywords() {
+            if let Ok(scopes::Reference::FunctionOverload(_)) = self.global_variables.resolve(FunctionTargetType::Global, keyword) {
+                self.module.warnings.push(
+                    RuntimeError::warning(format!("Calls to '{}' will now be parsed as an operator.", keyword).as_str())
+                        .in_range(range.clone())
+                        .with_note(RuntimeError::note(format!("A function named '{}' is in scope.", keyword).as_str()))
+                );
+            }
+        }
+    }
+
+    pub fn record_declaration(&mut self, head: &Arc<FunctionHead>, range: Range<usize>) {
+        self.runtime.source.fn_declarations.insert(Arc::clone(head), DeclarationSite { module: self.module.name.clone(), range });
+    }
+
+    pub fn schedule_function_body(&mut self, head: &Arc<FunctionHead>, body: Option<&'a ast::Expression>, return_type: Option<&'a ast::Expression>, range: Range<usize>) {
         if let Some(body) = body {
-            self.function_bodies.insert(Rc::clone(head), Positioned {
-                value: body,
+            self.function_bodies.insert(Arc::clone(head), Positioned {
+                value: FunctionBody { body, return_type },
                 position: range
             });
         }
         else {
-            self.runtime.source.fn_logic.insert(Rc::clone(head), FunctionLogic::Descriptor(FunctionLogicDescriptor::Stub));
+            self.runtime.source.fn_logic.insert(Arc::clone(head), FunctionLogic::Descriptor(FunctionLogicDescriptor::Stub));
         }
     }
 }
@@ -1,45 +1,79 @@
 use std::collections::HashMap;
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 
+use display_with_options::with_options;
 use itertools::{Either, Itertools};
 use itertools::Either::{Left, Right};
+use log::trace;
 use uuid::Uuid;
 
 use crate::ast;
+use crate::error::codes::ErrorCode;
 use crate::error::{ErrInRange, RResult, RuntimeError, TryCollectMany};
-use crate::interpreter::runtime::Runtime;
 use crate::parser::expressions;
-use crate::program::allocation::ObjectReference;
+use crate::program::allocation::{Mutability, ObjectReference};
 use crate::program::calls::FunctionBinding;
 use crate::program::debug::MockFunctionInterface;
-use crate::program::expression_tree::{ExpressionID, ExpressionOperation, ExpressionTree};
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation};
 use crate::program::function_object::{FunctionCallExplicity, FunctionOverload, FunctionRepresentation, FunctionTargetType};
 use crate::program::functions::{FunctionHead, ParameterKey};
-use crate::program::generics::{GenericAlias, TypeForest};
+use crate::program::generics::GenericAlias;
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, PrimitiveOperation, Reflection};
+use crate::program::module::{ModuleName, ModuleSettings};
 use crate::program::primitives;
 use crate::program::traits::{Trait, TraitGraph};
 use crate::program::types::*;
 use crate::resolver::ambiguous::{AmbiguityResult, AmbiguousAbstractCall, AmbiguousFunctionCall, AmbiguousFunctionCandidate, ResolverAmbiguity};
 use crate::resolver::imperative_builder::ImperativeBuilder;
-use crate::resolver::scopes;
+use crate::resolver::{anonymous_structs, deprecation, enums, scopes};
 use crate::resolver::structs::Struct;
-use crate::resolver::type_factory::TypeFactory;
+use crate::resolver::type_factory::{shadowed_primitive_notes, TypeFactory};
 use crate::util::position::Positioned;
+use crate::util::strings;
 
 pub struct ImperativeResolver<'a> {
     pub builder: ImperativeBuilder<'a>,
-    pub return_type: Rc<TypeProto>,
+    pub return_type: Arc<TypeProto>,
+    /// Where the return type was declared, if it was declared explicitly.
+    pub return_type_range: Option<Range<usize>>,
+    /// The module declaring the function.
+    pub module_name: ModuleName,
+    /// The settings of the module declaring the function.
+    pub settings: ModuleSettings,
+    /// The first return statement offering a value, for error reporting.
+    pub first_return: Option<Positioned<ExpressionID>>,
+    /// Blocks not ending in an expression, positioned where a value would be expected.
+    ///  They may only be used where no value is needed.
+    pub valueless_blocks: Vec<Positioned<ExpressionID>>,
+    /// Blocks ending in a return statement. They never produce a value, so they fit any type.
+    pub diverging_blocks: Vec<ExpressionID>,
+    pub statement_ranges: HashMap<ExpressionID, Range<usize>>,
+    pub expression_ranges: HashMap<ExpressionID, Range<usize>>,
     pub ambiguities: Vec<Box<dyn ResolverAmbiguity>>,
+    /// Calls resolved from abstract calls, like literal constructors. Literals among them are
+    ///  range-checked once all ambiguities are resolved.
+    pub literal_calls: Vec<Positioned<ExpressionID>>,
+    /// How often ambiguities were attempted to be resolved, as a measure of resolution effort.
+    pub ambiguity_attempts: usize,
+    /// Problems found while resolving that don't prevent the function from resolving.
+    pub warnings: Vec<RuntimeError>,
 }
 
 impl <'a> ImperativeResolver<'a> {
     pub fn resolve_all_ambiguities(&mut self) -> RResult<()> {
-        let mut has_changed = true;
+        // Bindings may have changed since the ambiguities were registered.
+        let mut retry_all = true;
         while !self.ambiguities.is_empty() {
-            if !has_changed {
+            let changed_identities = self.builder.types.take_changed_identities();
+            if !retry_all && changed_identities.is_empty() {
+                if self.bind_number_default()? {
+                    continue
+                }
+
                 return Err(
                     RuntimeError::error(format!("Function is ambiguous ({} times)", self.ambiguities.len()).as_str())
+                        .with_code(ErrorCode::AmbiguousFunction)
                         .with_notes(
                             self.ambiguities.iter()
                                 .map(|x| RuntimeError::warning(x.to_string().as_str()).in_range(x.get_position()))
@@ -48,21 +82,72 @@ impl <'a> ImperativeResolver<'a> {
                 );
             }
 
-            has_changed = false;
+            trace!("Resolving {} ambiguities", self.ambiguities.len());
 
+            // Only ambiguities waiting on changed bindings can have a different outcome.
             let callbacks: Vec<Box<dyn ResolverAmbiguity>> = self.ambiguities.drain(..).collect();
             for mut ambiguity in callbacks {
+                let is_changed = |alias: &GenericAlias| self.builder.types.identity_of(alias).is_some_and(|identity| changed_identities.contains(&identity));
+                if !retry_all && !ambiguity.watched_aliases().iter().any(is_changed) {
+                    self.ambiguities.push(ambiguity);
+                    continue
+                }
+
+                self.ambiguity_attempts += 1;
                 match ambiguity.attempt_to_resolve(self)? {
-                    AmbiguityResult::Ok(_) => has_changed = true,
+                    AmbiguityResult::Ok(_) => {},
                     AmbiguityResult::Ambiguous => self.ambiguities.push(ambiguity),
                 }
             }
+            retry_all = false;
+        }
+
+        Ok(())
+    }
+
+    /// Binds a number literal that nothing else decides to its default type: Float64 for reals, and Int64 for ints.
+    /// Reals are bound first, so that e.g. `1 + 0.5` is a Float64.
+    /// Returns whether a literal was bound; under strict!(no_implicit_number_defaults), that's an error instead.
+    fn bind_number_default(&mut self) -> RResult<bool> {
+        let Some((range, alias, primitive)) = self.ambiguities.iter()
+            .filter_map(|ambiguity| ambiguity.number_default(self).map(|(alias, primitive)| (ambiguity.get_position(), alias, primitive)))
+            .min_by_key(|(_, _, primitive)| !matches!(primitive, primitives::Type::Float(_))) else {
+            return Ok(false)
+        };
+
+        if self.settings.no_implicit_number_defaults {
+            return Err(
+                RuntimeError::error("The literal's type is ambiguous.")
+                    .in_range(range)
+                    .with_note(RuntimeError::note(format!("strict!(no_implicit_number_defaults) denies defaulting it to {}.", primitive.identifier_string()).as_str()))
+                    .to_array()
+            )
+        }
+
+        let type_ = TypeProto::unit_struct(&self.builder.runtime.primitives.as_ref().unwrap()[&primitive]);
+        self.builder.types.bind(alias, &type_).err_in_range(&range)?;
+        Ok(true)
+    }
+
+    /// Attempts the ambiguities waiting on the alias right away, rather than in the next round.
+    pub fn resolve_ambiguities_watching(&mut self, alias: GenericAlias) -> RResult<()> {
+        let (watching, others): (Vec<_>, Vec<_>) = self.ambiguities.drain(..)
+            .partition(|ambiguity| ambiguity.watched_aliases().contains(&alias));
+        self.ambiguities = others;
+
+        for mut ambiguity in watching {
+            self.ambiguity_attempts += 1;
+            match ambiguity.attempt_to_resolve(self)? {
+                AmbiguityResult::Ok(_) => {},
+                AmbiguityResult::Ambiguous => self.ambiguities.push(ambiguity),
+            }
         }
 
         Ok(())
     }
 
     pub fn register_ambiguity(&mut self, mut ambiguity: Box<dyn ResolverAmbiguity>) -> RResult<()> {
+        self.ambiguity_attempts += 1;
         match ambiguity.attempt_to_resolve(self)? {
             AmbiguityResult::Ok(_) => {},
             AmbiguityResult::Ambiguous => self.ambiguities.push(ambiguity),
@@ -71,7 +156,7 @@ impl <'a> ImperativeResolver<'a> {
         Ok(())
     }
 
-    pub fn resolve_abstract_function_call(&mut self, arguments: Vec<ExpressionID>, interface: Rc<Trait>, abstract_function: Rc<FunctionHead>, traits: TraitGraph, range: Range<usize>) -> RResult<ExpressionID> {
+    pub fn resolve_abstract_function_call(&mut self, arguments: Vec<ExpressionID>, interface: Arc<Trait>, abstract_function: Arc<FunctionHead>, traits: TraitGraph, range: Range<usize>) -> RResult<ExpressionID> {
         let expression_id = self.builder.make_expression(arguments.clone());
 
         self.register_ambiguity(Box::new(AmbiguousAbstractCall {
@@ -83,20 +168,183 @@ impl <'a> ImperativeResolver<'a> {
             traits,
         }))?;
 
-        return Ok(expression_id);
+        Ok(expression_id)
     }
 
-    pub fn resolve_block(&mut self, body: &ast::Block, scope: &scopes::Scope) -> RResult<ExpressionID> {
+    /// The value of a block is its last statement, if it is an expression.
+    /// A return statement, in any block, returns from the enclosing function.
+    pub fn resolve_block(&mut self, body: &ast::Block, scope: &scopes::Scope, range: &Range<usize>) -> RResult<ExpressionID> {
         let mut scope = scope.subscope();
-        let statements: Vec<ExpressionID> = body.statements.iter().map(|pstatement| {
-            self.resolve_statement(&mut scope, pstatement)
-                .err_in_range(&pstatement.value.position)
+        let statements: Vec<ExpressionID> = body.statements.iter().map(|pstatement| -> RResult<ExpressionID> {
+            let statement = self.resolve_statement(&mut scope, pstatement)
+                .err_in_range(&pstatement.value.position)?;
+            self.statement_ranges.insert(statement, pstatement.value.position.clone());
+            Ok(statement)
         }).try_collect()?;
         // try_collect means we stop after the first error.
         // This makes sense because an error may mean ambiguities or lacks of variable declarations.
         // Anything after the first error could just be a followup error.
 
-        Ok(self.builder.make_operation_expression(statements, ExpressionOperation::Block))
+        let last_statement = statements.last().cloned();
+        let block = self.builder.make_operation_expression(statements, ExpressionOperation::Block);
+
+        match body.statements.last().map(|pstatement| &pstatement.value) {
+            Some(Positioned { value: ast::Statement::Expression(_), .. }) => {
+                self.builder.types.bind(block, &TypeProto::unit(TypeUnit::Generic(last_statement.unwrap())))?;
+            }
+            Some(Positioned { value: ast::Statement::Return(_), .. }) => {
+                self.diverging_blocks.push(block);
+            }
+            Some(pstatement) => self.valueless_blocks.push(pstatement.with_value(block)),
+            None => self.valueless_blocks.push(Positioned { position: range.clone(), value: block }),
+        }
+
+        Ok(block)
+    }
+
+    /// Literals are parsed when run, so they would overflow only then. Primitives can check them right away.
+    pub fn check_literal_ranges(&self) -> RResult<()> {
+        for call in self.literal_calls.iter() {
+            let Some(ExpressionOperation::FunctionCall(binding)) = self.builder.expression_tree.values.get(&call.value) else {
+                continue
+            };
+            let Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::PrimitiveOperation {
+                operation: PrimitiveOperation::ParseIntString | PrimitiveOperation::ParseRealString,
+                type_,
+            })) = self.builder.runtime.source.fn_logic.get(&binding.function) else {
+                continue
+            };
+            let Some(ExpressionOperation::StringLiteral(literal)) = self.builder.expression_tree.children[&call.value].first()
+                .and_then(|argument| self.builder.expression_tree.values.get(argument)) else {
+                continue
+            };
+
+            if !type_.can_represent_literal(literal) {
+                return Err(
+                    RuntimeError::error(format!("Literal {} is out of range for {}.", literal, type_.identifier_string()).as_str())
+                        .with_code(ErrorCode::LiteralOutOfRange)
+                        .in_range(call.position.clone())
+                        .with_note(RuntimeError::note(format!("{} values range from {}.", type_.identifier_string(), type_.range_description()).as_str()))
+                        .to_array()
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Functions may change their mut parameters, so only mutable values may be passed to them.
+    /// Fields read from an object are as mutable as the local the object was read from, e.g. for `grow(zoo.animal)`.
+    /// Other values, like the results of function calls, are new to the caller, so they may be changed.
+    pub fn check_mutable_arguments(&self) -> RResult<()> {
+        let tree = &self.builder.expression_tree;
+        for expression in tree.deep_children(tree.root) {
+            let Some(ExpressionOperation::FunctionCall(binding)) = tree.values.get(&expression) else {
+                continue
+            };
+
+            for (argument, parameter) in tree.children[&expression].iter().zip(binding.function.interface.parameters.iter()) {
+                if parameter.mutability != Mutability::Mutable {
+                    continue
+                }
+                let Some(local) = self.root_local(argument) else {
+                    continue
+                };
+                if local.mutability == Mutability::Mutable {
+                    continue
+                }
+
+                let mut error = RuntimeError::error(format!("Cannot pass an immutable value to mut parameter '{}'.", parameter.internal_name).as_str())
+                    .with_code(ErrorCode::ImmutableToMutParameter);
+                if let Some(range) = self.expression_ranges.get(argument) {
+                    error = error.in_range(range.clone());
+                }
+                return Err(error.to_array())
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The local the value was read from, following reads of fields to the object they were read from.
+    fn root_local(&self, expression: &ExpressionID) -> Option<&Arc<ObjectReference>> {
+        let tree = &self.builder.expression_tree;
+        match tree.values.get(expression)? {
+            ExpressionOperation::GetLocal(local) => Some(local),
+            ExpressionOperation::FunctionCall(binding) => match self.builder.runtime.source.fn_logic.get(&binding.function) {
+                Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::GetMemberField(_, _))) => self.root_local(&tree.children[expression][0]),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Reflection is substituted during monomorphization, so the types it describes must be known by then.
+    /// Types that are generic to the function are known, since each monomorphization binds them.
+    pub fn check_reflection_calls(&self, generics: &HashMap<String, Arc<Trait>>) -> RResult<()> {
+        for (expression, operation) in self.builder.expression_tree.values.iter() {
+            let ExpressionOperation::FunctionCall(binding) = operation else {
+                continue
+            };
+            let Some(FunctionLogic::Descriptor(FunctionLogicDescriptor::Reflection(reflection))) = self.builder.runtime.source.fn_logic.get(&binding.function) else {
+                continue
+            };
+
+            let subject = self.builder.types.resolve_binding_alias(&self.builder.expression_tree.children[expression][0])?;
+            let subject = match reflection {
+                Reflection::TypeName => subject,
+                // The argument is the metatype.
+                Reflection::FieldsOf => Arc::clone(&subject.arguments[0]),
+            };
+            let name = &self.builder.runtime.source.fn_representations[&binding.function].name;
+
+            let error = if TypeProto::contains_generics([&subject].into_iter()) {
+                RuntimeError::error(format!("The type passed to {} must be known at compile time, but could not be inferred.", name).as_str())
+                    .with_note(RuntimeError::note("Declare the type of the value, for example with `let x 'Int32 = ...`."))
+            }
+            else {
+                match (&subject.unit, reflection) {
+                    (TypeUnit::Struct(trait_), Reflection::FieldsOf) if !self.builder.runtime.source.struct_by_trait.contains_key(trait_) && !generics.values().contains(trait_) => {
+                        RuntimeError::error(format!("{} needs a struct, but {} has no fields.", name, trait_.name).as_str())
+                    }
+                    _ => continue,
+                }
+            };
+
+            return Err(match self.expression_ranges.get(expression) {
+                Some(range) => error.in_range(range.clone()),
+                None => error,
+            }.to_array())
+        }
+
+        Ok(())
+    }
+
+    /// Blocks that don't produce a value are void, unless a value was required of them.
+    pub fn check_block_values(&mut self) -> RResult<()> {
+        for block in self.valueless_blocks.iter() {
+            match self.builder.types.get_unit(&block.value) {
+                None | Some(TypeUnit::Void) => self.builder.types.bind(block.value, &TypeProto::void())?,
+                Some(_) if block.value == self.builder.expression_tree.root && self.builder.expression_tree.children[&block.value].is_empty() => return Err(
+                    RuntimeError::error("Function declares a return type but has an empty body.")
+                        .in_range(block.position.clone())
+                        .to_array()
+                ),
+                Some(_) => return Err(
+                    RuntimeError::error("Block used as a value must end in an expression.")
+                        .in_range(block.position.clone())
+                        .to_array()
+                ),
+            }
+        }
+
+        for block in self.diverging_blocks.iter() {
+            if self.builder.types.get_unit(block).is_none() {
+                self.builder.types.bind(*block, &TypeProto::void())?;
+            }
+        }
+
+        Ok(())
     }
 
     fn resolve_statement(&mut self, scope: &mut scopes::Scope, pstatement: &ast::Decorated<Positioned<ast::Statement>>) -> RResult<ExpressionID> {
@@ -106,26 +354,44 @@ impl <'a> ImperativeResolver<'a> {
             } => {
                 pstatement.no_decorations()?;
 
-                let Some(assignment) = assignment else {
-                    return Err(
-                        RuntimeError::error(format!("Value {} must be assigned on declaration.", identifier).as_str()).to_array()
-                    )
+                let assignment = match assignment {
+                    Some(assignment) => Some(self.resolve_declaration_value(identifier, assignment, type_declaration, &pstatement.value.position, scope)?),
+                    None => None,
+                };
+                let type_ = match (assignment, type_declaration) {
+                    (Some(assignment), _) => TypeProto::unit(TypeUnit::Generic(assignment)),
+                    // The value is assigned later, by upd. Static analysis checks it's assigned before it's read.
+                    (None, Some(type_declaration)) => self.resolve_type_hint(type_declaration, scope)?,
+                    (None, None) => return Err(
+                        RuntimeError::error(format!("Value {} must be assigned on declaration, or declare its type.", identifier).as_str()).to_array()
+                    ),
                 };
-                let assignment: ExpressionID = self.resolve_expression(&assignment, &scope)?;
 
-                if let Some(type_declaration) = type_declaration {
-                    self.hint_type(assignment, type_declaration, &scope)?;
-                }
+                self.check_shadowing(identifier, scope)?;
+
+                let object_ref = Arc::new(ObjectReference { id: Uuid::new_v4(), type_, mutability: *mutability });
+                self.builder.register_local(identifier, Arc::clone(&object_ref), scope)?;
 
-                let object_ref = Rc::new(ObjectReference { id: Uuid::new_v4(), type_: TypeProto::unit(TypeUnit::Generic(assignment)), mutability: mutability.clone() });
-                self.builder.register_local(identifier, Rc::clone(&object_ref), scope)?;
+                match assignment {
+                    Some(assignment) => self.builder.make_full_expression(vec![assignment], &TypeProto::void(), ExpressionOperation::SetLocal(object_ref))?,
+                    // An empty statement, so the declaration still has a place in its block.
+                    None => self.builder.make_full_expression(vec![], &TypeProto::void(), ExpressionOperation::Block)?,
+                }
+            },
+            ast::Statement::Destructuring { mutability, targets, assignment } => {
+                pstatement.no_decorations()?;
 
-                self.builder.make_full_expression(vec![assignment], &TypeProto::void(), ExpressionOperation::SetLocal(object_ref))?
+                self.resolve_destructuring(mutability, targets, assignment, scope)?
             },
-            ast::Statement::VariableUpdate { target, new_value } => {
+            ast::Statement::VariableUpdate { target, operator, new_value } => {
                 pstatement.no_decorations()?;
 
-                let new_value: ExpressionID = self.resolve_expression(new_value, &scope)?;
+                let new_value: ExpressionID = match operator {
+                    // upd x += y means upd x = x + (y), with + resolved by the grammar like anywhere else.
+                    // Note that the target's terms are resolved twice, once to read and once to write.
+                    Some(operator) => self.resolve_expression(&compound_assignment_value(target, operator, new_value), scope)?,
+                    None => self.resolve_expression(new_value, scope)?,
+                };
 
                 let lhs = expressions::parse(target, &scope.grammar)?;
                 match &lhs.value {
@@ -135,12 +401,12 @@ impl <'a> ImperativeResolver<'a> {
                             .as_local(true)?;
                         self.builder.types.bind(new_value, &object_ref.type_)?;
 
-                        self.builder.make_full_expression(vec![new_value], &TypeProto::void(), ExpressionOperation::SetLocal(Rc::clone(&object_ref)))?
+                        self.builder.make_full_expression(vec![new_value], &TypeProto::void(), ExpressionOperation::SetLocal(Arc::clone(object_ref)))?
                     }
                     expressions::Value::MemberAccess(target, member) => {
                         let target = self.resolve_expression_token(target, scope)?;
                         let overload = scope
-                            .resolve(FunctionTargetType::Member, &member)?
+                            .resolve(FunctionTargetType::Member, member)?
                             .as_function_overload()?;
                         self.resolve_function_call(
                             overload.functions.iter(),
@@ -164,8 +430,13 @@ impl <'a> ImperativeResolver<'a> {
                         )
                     }
 
-                    let result: ExpressionID = self.resolve_expression(expression, &scope)?;
-                    self.builder.types.bind(result, &self.return_type)?;
+                    let result: ExpressionID = self.resolve_expression(expression, scope)?;
+                    if let Err(errors) = self.builder.types.bind(result, &self.return_type) {
+                        return Err(errors.into_iter().map(|e| self.add_return_notes(e)).collect());
+                    }
+                    if self.first_return.is_none() {
+                        self.first_return = Some(pstatement.value.with_value(result));
+                    }
 
                     self.builder.make_full_expression(vec![result], &TypeProto::void(), ExpressionOperation::Return)?
                 } else {
@@ -181,7 +452,7 @@ impl <'a> ImperativeResolver<'a> {
             ast::Statement::Expression(expression) => {
                 pstatement.no_decorations()?;
 
-                self.resolve_expression(&expression, &scope)?
+                self.resolve_expression(expression, scope)?
             }
             statement => {
                 return Err(
@@ -192,6 +463,102 @@ impl <'a> ImperativeResolver<'a> {
         Ok(expression_id)
     }
 
+    fn check_shadowing(&self, identifier: &str, scope: &scopes::Scope) -> RResult<()> {
+        if self.settings.deny_shadowing {
+            if let Ok(scopes::Reference::Local(_)) = scope.resolve(FunctionTargetType::Global, identifier) {
+                return Err(
+                    RuntimeError::error(format!("Variable {} shadows another variable of the same name.", identifier).as_str())
+                        .with_note(RuntimeError::note("Shadowing is denied by strict!(deny_shadowing)."))
+                        .to_array()
+                )
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `let (q, r) = value;` means `let q_r = value; let q = q_r.q; let r = q_r.r;`, with q_r hidden.
+    fn resolve_destructuring(&mut self, mutability: &Mutability, targets: &[Positioned<ast::DestructuringTarget>], assignment: &ast::Expression, scope: &mut scopes::Scope) -> RResult<ExpressionID> {
+        if let Some(duplicate) = targets.iter().map(|target| &target.value.identifier).duplicates().next() {
+            return Err(RuntimeError::error(format!("Variable {} is declared twice.", duplicate).as_str()).to_array())
+        }
+        let value = self.resolve_expression(assignment, scope)?;
+
+        let object_ref = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: TypeProto::unit(TypeUnit::Generic(value)), mutability: Mutability::Immutable });
+        self.builder.locals_names.insert(Arc::clone(&object_ref), targets.iter().map(|target| &target.value.identifier).join("_"));
+        let mut statements = vec![
+            self.builder.make_full_expression(vec![value], &TypeProto::void(), ExpressionOperation::SetLocal(Arc::clone(&object_ref)))?
+        ];
+
+        for target in targets.iter() {
+            let ast::DestructuringTarget { field, identifier } = &target.value;
+            let object = self.builder.make_full_expression(vec![], &object_ref.type_, ExpressionOperation::GetLocal(Arc::clone(&object_ref)))?;
+            let Left(field_value) = self.resolve_member(scope, &target.position, &field, object)? else {
+                return Err(RuntimeError::error(format!("{} is not a field, so it cannot be destructured.", field).as_str()).in_range(target.position.clone()).to_array())
+            };
+            self.check_shadowing(identifier, scope).err_in_range(&target.position)?;
+
+            let field_ref = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: TypeProto::unit(TypeUnit::Generic(field_value)), mutability: *mutability });
+            self.builder.register_local(identifier, Arc::clone(&field_ref), scope)?;
+            statements.push(self.builder.make_full_expression(vec![field_value], &TypeProto::void(), ExpressionOperation::SetLocal(field_ref))?);
+        }
+
+        // The block is flattened into the surrounding one when transpiled, like any nested block.
+        self.builder.make_full_expression(statements, &TypeProto::void(), ExpressionOperation::Block)
+    }
+
+    /// Resolves the value a variable is declared with, checking it against the declared type if there is one.
+    fn resolve_declaration_value(&mut self, identifier: &str, assignment: &ast::Expression, type_declaration: &Option<Box<ast::Expression>>, range: &Range<usize>, scope: &scopes::Scope) -> RResult<ExpressionID> {
+        let assignment_range = assignment.position().unwrap_or(range.clone());
+        let assignment: ExpressionID = self.resolve_expression(assignment, scope)?;
+
+        if let Some(type_declaration) = type_declaration {
+            let declared_type = self.resolve_type_hint(type_declaration, scope)?;
+            let inferred_type = self.builder.types.get_unit(&assignment).is_some()
+                .then(|| self.builder.types.prototype_binding_alias(&assignment));
+
+            // Literals and calls resolve against the declared type, so their failures are
+            //  provoked here and reported as a conflict with the declaration.
+            self.builder.types.bind(assignment, &declared_type)
+                .and_then(|_| self.resolve_ambiguities_watching(assignment))
+                .map_err(|errors| {
+                    let title = match &inferred_type {
+                        Some(inferred_type) => format!("Cannot initialize `{}` of type {:?} with a value of type {:?}.", identifier, declared_type, inferred_type),
+                        None => format!("Cannot initialize `{}` of type {:?} with this value.", identifier, declared_type),
+                    };
+                    RuntimeError::error(title.as_str())
+                        .in_range(range.clone())
+                        .with_note(
+                            RuntimeError::note(format!("`{}` is declared as {:?} here.", identifier, declared_type).as_str())
+                                .in_range(type_declaration.position().unwrap_or(range.clone()))
+                        )
+                        .with_notes(errors.into_iter().map(|error| error.in_range(assignment_range.clone())))
+                        .with_notes(shadowed_primitive_notes(self.builder.runtime, &declared_type).into_iter())
+                        .to_array()
+                })?;
+        }
+
+        Ok(assignment)
+    }
+
+    fn add_return_notes(&self, mut error: RuntimeError) -> RuntimeError {
+        if let Some(first_return) = &self.first_return {
+            let first_type = self.builder.types.resolve_binding_alias(&first_return.value)
+                .unwrap_or_else(|_| Arc::clone(&self.return_type));
+            error = error.with_note(
+                RuntimeError::note(format!("Return type was first constrained to {:?} here.", first_type).as_str())
+                    .in_range(first_return.position.clone())
+            );
+        }
+        if let Some(return_type_range) = &self.return_type_range {
+            error = error.with_note(
+                RuntimeError::note(format!("Return type declared as {:?} here.", self.return_type).as_str())
+                    .in_range(return_type_range.clone())
+            );
+        }
+        error
+    }
+
     pub fn resolve_expression_with_type(&mut self, syntax: &ast::Expression, type_declaration: &Option<ast::Expression>, scope: &scopes::Scope) -> RResult<ExpressionID> {
         let value = self.resolve_expression(syntax, scope)?;
         if let Some(type_declaration) = type_declaration {
@@ -207,27 +574,65 @@ impl <'a> ImperativeResolver<'a> {
             .err_in_range(&token.position)
     }
 
-    pub fn resolve_expression_token(&mut self, ptoken: &Positioned<expressions::Value<Rc<FunctionHead>>>, scope: &scopes::Scope) -> RResult<ExpressionID> {
-        let range = &ptoken.position;
+    pub fn resolve_expression_token(&mut self, ptoken: &Positioned<expressions::Value<Arc<FunctionHead>>>, scope: &scopes::Scope) -> RResult<ExpressionID> {
+        let expression_id = match &ptoken.value {
+            expressions::Value::Operation(_, _) => self.resolve_operations(ptoken, scope)?,
+            _ => self.resolve_token(ptoken, scope)?,
+        };
+        // Tokens that resolve to an inner token's expression, like parentheses, keep the inner range.
+        self.expression_ranges.entry(expression_id).or_insert_with(|| ptoken.position.clone());
+        Ok(expression_id)
+    }
 
-        match &ptoken.value {
-            expressions::Value::Operation(function_head, args) => {
-                let args: Vec<_> = args.into_iter().map(|arg|
-                    self.resolve_expression_token(&arg, scope)
-                        .err_in_range(&arg.position)
-                ).try_collect_many()?;
+    /// Resolves nested operations, like a long chain of additions.
+    /// Their nesting is as deep as the chain is long, so they are walked with a stack instead of recursing.
+    fn resolve_operations(&mut self, ptoken: &Positioned<expressions::Value<Arc<FunctionHead>>>, scope: &scopes::Scope) -> RResult<ExpressionID> {
+        // Operations whose arguments are being resolved, with the results so far.
+        let mut stack: Vec<(&Positioned<expressions::Value<Arc<FunctionHead>>>, Vec<RResult<ExpressionID>>)> = vec![(ptoken, vec![])];
+
+        loop {
+            let (operation, results) = stack.last_mut().unwrap();
+            let expressions::Value::Operation(overloads, args) = &operation.value else { unreachable!() };
+
+            if let Some(arg) = args.get(results.len()) {
+                if let expressions::Value::Operation(_, _) = &arg.value {
+                    stack.push((arg, vec![]));
+                    continue
+                }
+
+                let result = self.resolve_expression_token(arg, scope).err_in_range(&arg.position);
+                results.push(result);
+                continue
+            }
 
+            let (operation, results) = stack.pop().unwrap();
+            let result = results.into_iter().try_collect_many().and_then(|args: Vec<_>| {
                 self.resolve_function_call(
-                    [function_head].into_iter(),
-                    self.builder.runtime.source.fn_representations[function_head].clone(),
+                    overloads.iter(),
+                    self.builder.runtime.source.fn_representations[&overloads[0]].clone(),
                     vec![ParameterKey::Positional; args.len()],
                     args,
                     scope,
-                    range.clone()
+                    operation.position.clone()
                 )
+            });
+            let Some((_, parent_results)) = stack.last_mut() else {
+                return result
+            };
+            if let Ok(expression_id) = &result {
+                self.expression_ranges.entry(*expression_id).or_insert_with(|| operation.position.clone());
             }
-            expressions::Value::MacroIdentifier(identifier) => {
-                return Err(RuntimeError::error("Macro not supported here.").to_array())
+            parent_results.push(result.err_in_range(&operation.position));
+        }
+    }
+
+    fn resolve_token(&mut self, ptoken: &Positioned<expressions::Value<Arc<FunctionHead>>>, scope: &scopes::Scope) -> RResult<ExpressionID> {
+        let range = &ptoken.position;
+
+        match &ptoken.value {
+            expressions::Value::Operation(_, _) => self.resolve_operations(ptoken, scope),
+            expressions::Value::MacroIdentifier(_identifier) => {
+                Err(RuntimeError::error("Macro not supported here.").to_array())
             }
             expressions::Value::Identifier(identifier) => {
                 match self.resolve_global(scope, range, identifier)? {
@@ -235,30 +640,25 @@ impl <'a> ImperativeResolver<'a> {
                     Right(fun) => self.builder.add_function_reference(&fun),
                 }
             }
-            expressions::Value::RealLiteral(s) => {
-                let string_expression_id = self.builder.add_string_primitive(s)?;
-
-                self.resolve_abstract_function_call(
-                    vec![string_expression_id],
-                    Rc::clone(&self.builder.runtime.traits.as_ref().unwrap().ConstructableByRealLiteral),
-                    Rc::clone(&self.builder.runtime.traits.as_ref().unwrap().parse_real_literal_function.target),
-                    scope.trait_conformance.clone(),
-                    range.clone(),
-                )
-            }
-            expressions::Value::IntLiteral(s) => {
+            expressions::Value::RealLiteral(s) => self.resolve_number_literal(s, true, scope, range),
+            expressions::Value::IntLiteral(s) => self.resolve_number_literal(s, false, scope, range),
+            expressions::Value::NegativeLiteral(literal) => match &literal.value {
+                expressions::Value::RealLiteral(s) => self.resolve_number_literal(&format!("-{}", s), true, scope, range),
+                expressions::Value::IntLiteral(s) => self.resolve_number_literal(&format!("-{}", s), false, scope, range),
+                _ => panic!("Only number literals can be negative literals."),
+            },
+            expressions::Value::CharLiteral(s) => {
                 let string_expression_id = self.builder.add_string_primitive(s)?;
+                let parse_function = Arc::clone(&self.builder.runtime.traits.as_ref().unwrap().parse_char_literal_function.target);
 
-                self.resolve_abstract_function_call(
+                self.builder.make_full_expression(
                     vec![string_expression_id],
-                    Rc::clone(&self.builder.runtime.traits.as_ref().unwrap().ConstructableByIntLiteral),
-                    Rc::clone(&self.builder.runtime.traits.as_ref().unwrap().parse_int_literal_function.target),
-                    scope.trait_conformance.clone(),
-                    range.clone(),
+                    &parse_function.interface.return_type.clone(),
+                    ExpressionOperation::FunctionCall(FunctionBinding::pure(parse_function))
                 )
             }
             expressions::Value::StringLiteral(parts) => {
-                self.resolve_string_literal(scope, &range, parts)
+                self.resolve_string_literal(scope, range, parts)
             }
             expressions::Value::StructLiteral(struct_) => {
                 let struct_ = self.resolve_struct(scope, struct_)?;
@@ -267,31 +667,73 @@ impl <'a> ImperativeResolver<'a> {
                     return Ok(struct_.values[0])
                 }
 
-                return Err(RuntimeError::error("Anonymous struct literals are not yet supported.").to_array())
+                self.resolve_anonymous_struct(struct_, scope, range)
             }
             expressions::Value::ArrayLiteral(array) => {
-                let values = array.arguments.iter().map(|x| {
+                let values: Vec<_> = array.arguments.iter().map(|x| {
                     self.resolve_expression_with_type(&x.value.value, &x.value.type_declaration, scope)
                         .err_in_range(&x.position)
                 }).try_collect_many()?;
 
-                let supertype = self.builder.types.merge_all(&values)?.clone();
-                return Err(RuntimeError::error("Array literals are not yet supported.").to_array());
+                let _supertype = self.builder.types.merge_all(&values)?;
+                Err(RuntimeError::error("Array literals are not yet supported.").to_array())
             }
             expressions::Value::Block(block) => {
-                self.resolve_block(block, scope)
+                self.resolve_block(block, scope, range)
             }
             expressions::Value::MemberAccess(target, member) => {
-                let target = self.resolve_expression_token(&target, scope)
+                if let Some(reference) = self.resolve_namespaced(scope, range, target, member) {
+                    return match reference? {
+                        Left(expr) => Ok(expr),
+                        Right(fun) => self.builder.add_function_reference(&fun),
+                    }
+                }
+
+                let target = self.resolve_expression_token(target, scope)
                     .err_in_range(&target.position)?;
 
                 match self.resolve_member(scope, range, member, target)? {
                     Left(expr) => Ok(expr),
-                    Right(overload) => {
-                        return Err(RuntimeError::error("Member function references are not yet supported.").to_array());
+                    Right(_overload) => {
+                        // Member function references aren't supported, so the member can only be a field of an anonymous struct.
+                        let anonymous_getters = anonymous_structs::getters(&self.builder.runtime.source, member);
+                        if anonymous_getters.is_empty() {
+                            return Err(RuntimeError::error("Member function references are not yet supported.").to_array());
+                        }
+                        self.resolve_function_call(
+                            anonymous_getters.iter(),
+                            FunctionRepresentation::new(member, FunctionTargetType::Member, FunctionCallExplicity::Implicit),
+                            vec![ParameterKey::Positional],
+                            vec![target],
+                            scope,
+                            range.clone()
+                        )
                     }
                 }
             }
+            expressions::Value::TypeCheck(target, type_name) => {
+                let target = self.resolve_expression_token(target, scope)
+                    .err_in_range(&target.position)?;
+                let trait_ = self.resolve_type_test_target(scope, type_name, range).err_in_range(range)?;
+
+                self.builder.make_full_expression(
+                    vec![target],
+                    &TypeProto::unit_struct(&self.builder.runtime.primitives.as_ref().unwrap()[&primitives::Type::Bool]),
+                    ExpressionOperation::TypeCheck(trait_)
+                )
+            }
+            expressions::Value::TypeCast(target, type_name) => {
+                let target = self.resolve_expression_token(target, scope)
+                    .err_in_range(&target.position)?;
+                let trait_ = self.resolve_type_test_target(scope, type_name, range).err_in_range(range)?;
+
+                self.builder.make_full_expression(
+                    vec![target],
+                    &TypeProto::unit_struct(&trait_),
+                    ExpressionOperation::TypeCast(trait_)
+                )
+            }
+            expressions::Value::DynBox(trait_name, struct_) => self.resolve_dyn_box(trait_name, struct_, scope, range),
             expressions::Value::FunctionCall(call_target, struct_) => {
                 let struct_ = self.resolve_struct(scope, struct_)?;
 
@@ -314,10 +756,26 @@ impl <'a> ImperativeResolver<'a> {
                             }
                         }
                     }
+                    expressions::Value::MemberAccess(member_target, member) if let Some(reference) = self.resolve_namespaced(scope, range, member_target, member) => {
+                        // Found a function re-exported under a namespace, e.g. math.triple(3).
+                        match reference? {
+                            Left(expr) => expr,
+                            Right(overload) => {
+                                return self.resolve_function_call(
+                                    overload.functions.iter(),
+                                    overload.representation.clone(),
+                                    struct_.keys,
+                                    struct_.values,
+                                    scope,
+                                    range.clone(),
+                                )
+                            }
+                        }
+                    }
                     expressions::Value::MemberAccess(member_target, member) => {
                         // Found a member access. We may just be calling a member function!
 
-                        let target_expression = self.resolve_expression_token(&member_target, scope)
+                        let target_expression = self.resolve_expression_token(member_target, scope)
                             .err_in_range(&member_target.position)?;
 
                         match self.resolve_member(scope, &call_target.position, member, target_expression)? {
@@ -336,7 +794,7 @@ impl <'a> ImperativeResolver<'a> {
                         }
                     }
                     _ => {
-                        self.resolve_expression_token(&call_target, scope)
+                        self.resolve_expression_token(call_target, scope)
                             .err_in_range(&call_target.position)?
                     }
                 };
@@ -356,58 +814,155 @@ impl <'a> ImperativeResolver<'a> {
                     range.clone(),
                 )
             }
-            expressions::Value::Subscript(target, array) => {
-                let values: Vec<_> = array.arguments.iter().map(|x| {
+            expressions::Value::Subscript(_target, array) => {
+                let _values: Vec<_> = array.arguments.iter().map(|x| {
                     self.resolve_expression_with_type(&x.value.value, &x.value.type_declaration, scope)
                         .err_in_range(&x.position)
                 }).try_collect_many()?;
 
-                return Err(RuntimeError::error("Object subscript is not yet supported.").to_array())
+                Err(RuntimeError::error("Object subscript is not yet supported.").to_array())
             }
             expressions::Value::IfThenElse(if_then_else) => {
-                let condition: ExpressionID = self.resolve_expression(&if_then_else.condition, &scope)?;
-                self.builder.types.bind(condition, &TypeProto::unit(TypeUnit::Struct(Rc::clone(&self.builder.runtime.primitives.as_ref().unwrap()[&primitives::Type::Bool]))))?;
-                let consequent: ExpressionID = self.resolve_expression(&if_then_else.consequent, &scope)?;
+                let condition: ExpressionID = self.resolve_expression(&if_then_else.condition, scope)?;
+                if self.builder.types.bind(condition, &TypeProto::unit(TypeUnit::Struct(Arc::clone(&self.builder.runtime.primitives.as_ref().unwrap()[&primitives::Type::Bool])))).is_err() {
+                    // Conditions are never converted implicitly.
+                    let found = self.builder.types.prototype_binding_alias(&condition);
+                    return Err(
+                        RuntimeError::error(format!("if condition must be Bool, found {:?}.", found).as_str())
+                            .with_code(ErrorCode::NonBoolCondition)
+                            .in_range(if_then_else.condition.position().unwrap_or(range.clone()))
+                            .with_note(RuntimeError::note("Use an explicit comparison to get a Bool, e.g. `!= 0`."))
+                            .to_array()
+                    )
+                }
+                let consequent: ExpressionID = self.resolve_expression(&if_then_else.consequent, scope)?;
 
                 let mut arguments = vec![condition, consequent];
 
                 if let Some(alternative) = &if_then_else.alternative {
-                    let alternative: ExpressionID = self.resolve_expression(alternative, &scope)?;
+                    let alternative: ExpressionID = self.resolve_expression(alternative, scope)?;
                     self.builder.types.bind(alternative, &TypeProto::unit(TypeUnit::Generic(consequent)))?;
                     arguments.push(alternative);
                 }
 
                 self.builder.make_full_expression(arguments, &TypeProto::unit(TypeUnit::Generic(consequent)), ExpressionOperation::IfThenElse)
             }
+            expressions::Value::Match(match_) => self.resolve_match(match_, scope, range),
         }
     }
 
-    fn resolve_member(&mut self, scope: &scopes::Scope, range: &Range<usize>, member: &&String, target: ExpressionID) -> RResult<Either<ExpressionID, Rc<FunctionOverload>>> {
-        let overload = scope.resolve(FunctionTargetType::Member, member)
-            .err_in_range(range)?
-            .as_function_overload().err_in_range(range)?;
+    /// Resolves the match as a chain of if / else, comparing the subject to each arm's variant in turn.
+    /// Unless the subject is a local already, it is stored in one first, so it is evaluated only once.
+    fn resolve_match(&mut self, match_: &ast::Match, scope: &scopes::Scope, range: &Range<usize>) -> RResult<ExpressionID> {
+        let subject = self.resolve_expression(&match_.subject, scope)?;
+        let enum_ = match self.builder.types.get_unit(&subject) {
+            Some(TypeUnit::Struct(trait_)) => self.builder.runtime.source.enum_by_trait.get(trait_).cloned(),
+            _ => None,
+        };
+        let Some(enum_) = enum_ else {
+            let message = match self.builder.types.get_unit(&subject) {
+                Some(_) => format!("match requires an enum value, found {:?}.", self.builder.types.prototype_binding_alias(&subject)),
+                None => "match requires an enum value, but the subject's type is not known yet.".to_string(),
+            };
+            return Err(
+                RuntimeError::error(message.as_str())
+                    .in_range(match_.subject.position().unwrap_or(range.clone()))
+                    .to_array()
+            )
+        };
+        let variants = enums::match_arm_variants(&enum_, &match_.arms).err_in_range(range)?;
 
-        Ok(match overload.representation.call_explicity {
-            FunctionCallExplicity::Explicit => {
-                Right(overload)
-            }
-            FunctionCallExplicity::Implicit => {
-                Left(self.resolve_function_call(
-                    overload.functions.iter(),
-                    overload.representation.clone(),
-                    vec![ParameterKey::Positional],
-                    vec![target],
-                    scope,
-                    range.clone()
-                )?)
+        let (local, mut first_read, mut statements) = match &self.builder.expression_tree.values[&subject] {
+            ExpressionOperation::GetLocal(local) => (Arc::clone(local), Some(subject), vec![]),
+            _ => {
+                let local = Arc::new(ObjectReference { id: Uuid::new_v4(), type_: TypeProto::unit(TypeUnit::Generic(subject)), mutability: Mutability::Immutable });
+                self.builder.locals_names.insert(Arc::clone(&local), "subject".to_string());
+                let statement = self.builder.make_full_expression(vec![subject], &TypeProto::void(), ExpressionOperation::SetLocal(Arc::clone(&local)))?;
+                (local, None, vec![statement])
             }
-        })
+        };
+
+        let values: Vec<ExpressionID> = match_.arms.iter()
+            .map(|arm| self.resolve_expression(&arm.value.value, scope))
+            .try_collect()?;
+
+        // The last arm needs no comparison, since the arms before it matched every other variant.
+        let bool_type = TypeProto::unit(TypeUnit::Struct(Arc::clone(&self.builder.runtime.primitives.as_ref().unwrap()[&primitives::Type::Bool])));
+        let mut conditions = vec![];
+        for variant in variants[..variants.len() - 1].iter() {
+            // Only the last arm can be the default arm.
+            let variant = &enum_.variants[variant.unwrap()];
+            let read = match first_read.take() {
+                Some(read) => read,
+                None => self.builder.make_full_expression(vec![], &local.type_, ExpressionOperation::GetLocal(Arc::clone(&local)))?,
+            };
+            let meta_type = self.builder.make_full_expression(vec![], &enum_.getter.interface.return_type, ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(&enum_.getter))))?;
+            let value = self.builder.make_full_expression(vec![meta_type], &variant.interface.return_type, ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(variant))))?;
+            conditions.push(self.builder.make_full_expression(vec![read, value], &bool_type, ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(&enum_.equal_to))))?);
+        }
+        // With a single arm, the subject is only read for its side effects.
+        statements.extend(first_read);
+
+        let mut value = *values.last().unwrap();
+        for ((condition, consequent), arm) in conditions.into_iter().zip(values).zip(match_.arms.iter()).rev() {
+            self.builder.types.bind(value, &TypeProto::unit(TypeUnit::Generic(consequent))).err_in_range(&arm.position)?;
+            value = self.builder.make_full_expression(vec![condition, consequent, value], &TypeProto::unit(TypeUnit::Generic(consequent)), ExpressionOperation::IfThenElse)?;
+        }
+
+        if statements.is_empty() {
+            return Ok(value)
+        }
+        statements.push(value);
+        self.builder.make_full_expression(statements, &TypeProto::unit(TypeUnit::Generic(value)), ExpressionOperation::Block)
+    }
+
+    fn resolve_member(&mut self, scope: &scopes::Scope, range: &Range<usize>, member: &&String, target: ExpressionID) -> RResult<Either<ExpressionID, Arc<FunctionOverload>>> {
+        let anonymous_getters = anonymous_structs::getters(&self.builder.runtime.source, member);
+        let overload = match scope.resolve(FunctionTargetType::Member, member) {
+            Ok(reference) => Some(reference.as_function_overload().err_in_range(range)?),
+            // The member may still be a field of an anonymous struct.
+            Err(_) if !anonymous_getters.is_empty() => None,
+            Err(errors) => return Err(errors).err_in_range(range),
+        };
+
+        let functions = match overload {
+            Some(overload) if overload.representation.call_explicity == FunctionCallExplicity::Explicit => return Ok(Right(overload)),
+            Some(overload) => overload.functions.iter().chain(anonymous_getters.iter()).map(Arc::clone).collect_vec(),
+            None => anonymous_getters,
+        };
+
+        Ok(Left(self.resolve_function_call(
+            functions.iter(),
+            FunctionRepresentation::new(member, FunctionTargetType::Member, FunctionCallExplicity::Implicit),
+            vec![ParameterKey::Positional],
+            vec![target],
+            scope,
+            range.clone()
+        )?))
     }
 
-    fn resolve_global(&mut self, scope: &scopes::Scope, range: &Range<usize>, identifier: &String) -> RResult<Either<ExpressionID, Rc<FunctionOverload>>> {
-        Ok(match scope.resolve(FunctionTargetType::Global, identifier)? {
+    fn resolve_global(&mut self, scope: &scopes::Scope, range: &Range<usize>, identifier: &str) -> RResult<Either<ExpressionID, Arc<FunctionOverload>>> {
+        let reference = scope.resolve(FunctionTargetType::Global, identifier)?;
+        self.resolve_reference(scope, range, reference)
+    }
+
+    /// Resolves `namespace.name` if the target names a namespace (e.g. `math` or `outer.math`),
+    ///  and no local or global shadows its first part.
+    fn resolve_namespaced(&mut self, scope: &scopes::Scope, range: &Range<usize>, target: &Positioned<expressions::Value<Arc<FunctionHead>>>, name: &str) -> Option<RResult<Either<ExpressionID, Arc<FunctionOverload>>>> {
+        let path = namespace_path(&target.value)?;
+        let root = path.split('.').next().unwrap();
+        if scope.resolve(FunctionTargetType::Global, root).is_ok() {
+            return None
+        }
+
+        Some(scope.resolve_in_namespace(&path, name)?
+            .and_then(|reference| self.resolve_reference(scope, range, reference)))
+    }
+
+    fn resolve_reference(&mut self, scope: &scopes::Scope, range: &Range<usize>, reference: &scopes::Reference) -> RResult<Either<ExpressionID, Arc<FunctionOverload>>> {
+        Ok(match reference {
             scopes::Reference::Local(local) => {
-                let ObjectReference { id, type_, mutability } = local.as_ref();
+                let ObjectReference { id: _, type_, mutability: _ } = local.as_ref();
 
                 Left(self.builder.make_full_expression(
                     vec![],
@@ -418,7 +973,7 @@ impl <'a> ImperativeResolver<'a> {
             scopes::Reference::FunctionOverload(overload) => {
                 match overload.representation.call_explicity {
                     FunctionCallExplicity::Explicit => {
-                        Right(Rc::clone(overload))
+                        Right(Arc::clone(overload))
                     }
                     FunctionCallExplicity::Implicit => {
                         Left(self.resolve_function_call(
@@ -442,14 +997,101 @@ impl <'a> ImperativeResolver<'a> {
             },
             ast::StringPart::Object(o) => {
                 let struct_ = self.resolve_struct(scope, o)?;
+                let values = struct_.values.clone();
                 // Call format(<args>)
                 self.resolve_simple_function_call("format", struct_.keys, struct_.values, scope, part.position.clone())
+                    .map_err(|errors| self.explain_unformattable(&values, scope, errors))
+                    // Rather than the whole string literal, point at the value, or the interpolation if it has several.
+                    .err_in_range(match &o.arguments[..] {
+                        [argument] => &argument.position,
+                        _ => &part.position,
+                    })
+            }
+        }
+    }
+
+    /// Resolves the type of an is / as expression.
+    pub fn resolve_type_test_target(&mut self, scope: &scopes::Scope, type_name: &str, range: &Range<usize>) -> RResult<Arc<Trait>> {
+        let trait_ = TypeFactory::new(scope, self.builder.runtime).resolve_trait(type_name)?;
+        if !self.settings.allow_deprecated {
+            if let Some(warning) = deprecation::trait_warning(&self.builder.runtime.source, &trait_) {
+                self.warnings.push(warning.in_range(range.clone()));
+            }
+        }
+
+        if trait_.generics.keys().any(|name| name != "Self") {
+            return Err(RuntimeError::error(format!("Cannot test for generic type {}.", trait_.name).as_str()).to_array())
+        }
+
+        Ok(trait_)
+    }
+
+    /// If a single interpolated value cannot be formatted because it isn't ToString, say so instead of
+    /// listing every format candidate.
+    fn explain_unformattable(&self, values: &[ExpressionID], scope: &scopes::Scope, errors: Vec<RuntimeError>) -> Vec<RuntimeError> {
+        let [value] = values[..] else {
+            return errors
+        };
+        let Ok(type_) = self.builder.types.resolve_binding_alias(&value) else {
+            return errors
+        };
+
+        let requirement = self.builder.runtime.traits.as_ref().unwrap().ToString.create_generic_binding(vec![("Self", type_.clone())]);
+        match scope.trait_conformance.clone().satisfy_requirement(&requirement, &self.builder.types) {
+            Err(_) => RuntimeError::error(format!("Cannot format type {:?} in string interpolation.", type_).as_str())
+                .with_note(RuntimeError::note("Interpolated values need to be ToString. Declare a conformance, or call a function that returns a String."))
+                .to_array(),
+            Ok(_) => errors,
+        }
+    }
+
+    /// Number literals are parsed from their string by whichever type they turn out to be.
+    fn resolve_number_literal(&mut self, literal: &str, is_real: bool, scope: &scopes::Scope, range: &Range<usize>) -> RResult<ExpressionID> {
+        let string_expression_id = self.builder.add_string_primitive(literal)?;
+        let traits = self.builder.runtime.traits.as_ref().unwrap();
+        let (trait_, parse_function) = match is_real {
+            true => (&traits.ConstructableByRealLiteral, &traits.parse_real_literal_function.target),
+            false => (&traits.ConstructableByIntLiteral, &traits.parse_int_literal_function.target),
+        };
+
+        self.resolve_abstract_function_call(
+            vec![string_expression_id],
+            Arc::clone(trait_),
+            Arc::clone(parse_function),
+            scope.trait_conformance.clone(),
+            range.clone(),
+        )
+    }
+
+    fn resolve_dyn_box(&mut self, trait_name: &str, struct_: &ast::Struct, scope: &scopes::Scope, range: &Range<usize>) -> RResult<ExpressionID> {
+        let trait_ = TypeFactory::new(scope, self.builder.runtime).resolve_trait(trait_name).err_in_range(range)?;
+        if !self.settings.allow_deprecated {
+            if let Some(warning) = deprecation::trait_warning(&self.builder.runtime.source, &trait_) {
+                self.warnings.push(warning.in_range(range.clone()));
             }
         }
+        let Some(box_) = self.builder.runtime.source.dyn_boxes.get(&trait_).cloned() else {
+            return Err(
+                RuntimeError::error(format!("{} cannot be boxed.", trait_.name).as_str())
+                    .in_range(range.clone())
+                    .with_note(RuntimeError::note("Boxable traits have functions, each of which takes Self as its first parameter and nowhere else."))
+                    .to_array()
+            )
+        };
+
+        let struct_ = self.resolve_struct(scope, struct_)?;
+        self.resolve_function_call(
+            [box_].iter(),
+            FunctionRepresentation::new("box", FunctionTargetType::Global, FunctionCallExplicity::Explicit),
+            struct_.keys,
+            struct_.values,
+            scope,
+            range.clone(),
+        )
     }
 
-    pub fn resolve_string_literal(&mut self, scope: &scopes::Scope, range: &Range<usize>, parts: &Vec<Box<Positioned<ast::StringPart>>>) -> Result<ExpressionID, Vec<RuntimeError>> {
-        Ok(match &parts[..] {
+    pub fn resolve_string_literal(&mut self, scope: &scopes::Scope, range: &Range<usize>, parts: &[Box<Positioned<ast::StringPart>>]) -> Result<ExpressionID, Vec<RuntimeError>> {
+        Ok(match parts {
             // Simple case: Just one part means we can use it directly.
             [] => self.resolve_string_part(
                 &Positioned {
@@ -494,47 +1136,79 @@ impl <'a> ImperativeResolver<'a> {
         })
     }
 
+    /// Constructs the anonymous struct named by the literal's keys, like (q: 1, r: 2).
+    fn resolve_anonymous_struct(&mut self, struct_: Struct, scope: &scopes::Scope, range: &Range<usize>) -> RResult<ExpressionID> {
+        let Some(names) = struct_.keys.iter().map(|key| match key {
+            ParameterKey::Name(name) => Some(name.clone()),
+            ParameterKey::Positional => None,
+        }).collect::<Option<Vec<_>>>() else {
+            return Err(RuntimeError::error("Anonymous struct literals must name their fields, like (q: 1, r: 2).").in_range(range.clone()).to_array())
+        };
+        let Some((struct_info, getter)) = anonymous_structs::get(&self.builder.runtime.source, &names) else {
+            return Err(
+                RuntimeError::error(format!("Anonymous struct ({}) was not declared.", names.join(", ")).as_str())
+                    .in_range(range.clone())
+                    .with_note(RuntimeError::note("Anonymous structs are declared by types like (q 'Int64, r 'Int64), and by literals that start an expression."))
+                    .to_array()
+            )
+        };
+
+        let meta_type = self.builder.make_full_expression(
+            vec![],
+            &getter.interface.return_type,
+            ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(getter)))
+        )?;
+        self.resolve_function_call(
+            [&struct_info.constructor].into_iter(),
+            FunctionRepresentation::new("call_as_function", FunctionTargetType::Member, FunctionCallExplicity::Explicit),
+            [ParameterKey::Positional].into_iter().chain(struct_.keys).collect(),
+            [meta_type].into_iter().chain(struct_.values).collect(),
+            scope,
+            range.clone(),
+        )
+    }
+
+    /// Calls a function that string interpolation is built from, like format or add.
+    /// Locals of the same name are skipped, so shadowing them doesn't affect interpolation.
     pub fn resolve_simple_function_call(&mut self, name: &str, keys: Vec<ParameterKey>, args: Vec<ExpressionID>, scope: &scopes::Scope, range: Range<usize>) -> RResult<ExpressionID> {
-        let variable = scope.resolve(FunctionTargetType::Global, name)?;
+        let Some(overload) = scope.resolve_explicit_function(name) else {
+            return Err(
+                RuntimeError::error(format!("String interpolation requires a '{}' function to be in scope.", name).as_str())
+                    .in_range(range)
+                    .to_array()
+            )
+        };
 
-        match variable {
-            scopes::Reference::FunctionOverload(overload) => {
-                match (overload.representation.target_type, overload.representation.call_explicity) {
-                    (FunctionTargetType::Global, FunctionCallExplicity::Explicit) => {
-                        let expression_id = self.resolve_function_call(overload.functions.iter(), overload.representation.clone(), keys, args, scope, range)?;
-                        // Make sure the return type is actually String.
-                        self.builder.types.bind(expression_id, &TypeProto::unit_struct(&self.builder.runtime.traits.as_ref().unwrap().String))?;
-                        Ok(expression_id)
-                    }
-                    // this could happen if somebody uses def format ... without parentheses.
-                    _ => panic!("'{}' must not be shadowed in this context.", name)
-                }
-            }
-            // todo lolz, this is kinda dumb?
-            _ => panic!("'{}' must not be shadowed in this context.", name)
-        }
+        let expression_id = self.resolve_function_call(overload.functions.iter(), overload.representation.clone(), keys, args, scope, range)?;
+        // Make sure the return type is actually String.
+        self.builder.types.bind(expression_id, &TypeProto::unit_struct(&self.builder.runtime.traits.as_ref().unwrap().String))?;
+        Ok(expression_id)
     }
 
-    pub fn resolve_conjunctive_pairs(&mut self, arguments: Vec<Positioned<ExpressionID>>, operations: Vec<Rc<FunctionHead>>) -> RResult<Positioned<ExpressionID>> {
+    pub fn resolve_conjunctive_pairs(&mut self, _arguments: Vec<Positioned<ExpressionID>>, _operations: Vec<Arc<FunctionHead>>) -> RResult<Positioned<ExpressionID>> {
         todo!()
     }
 
-    pub fn resolve_function_call<'b>(&mut self, functions: impl Iterator<Item=&'b Rc<FunctionHead>>, representation: FunctionRepresentation, argument_keys: Vec<ParameterKey>, argument_expressions: Vec<ExpressionID>, scope: &scopes::Scope, range: Range<usize>) -> RResult<ExpressionID> {
+    pub fn resolve_function_call<'b>(&mut self, functions: impl Iterator<Item=&'b Arc<FunctionHead>>, representation: FunctionRepresentation, argument_keys: Vec<ParameterKey>, argument_expressions: Vec<ExpressionID>, scope: &scopes::Scope, range: Range<usize>) -> RResult<ExpressionID> {
         // TODO Check if any arguments are void before anything else
         let argument_keys: Vec<&ParameterKey> = argument_keys.iter().collect();
 
         let mut candidates_with_failed_signature = vec![];
         let mut candidates: Vec<Box<AmbiguousFunctionCandidate>> = vec![];
 
-        for fun in functions.map(Rc::clone) {
-            let param_keys = fun.interface.parameters.iter().map(|x| &x.external_key).collect::<Vec<&ParameterKey>>();
-            if param_keys != argument_keys {
+        // Positional parameters may also be passed by their internal name, but only if no
+        //  candidate takes the keys as given.
+        let functions = functions.map(Arc::clone).collect_vec();
+        let accepts_exactly = functions.iter().any(|fun| accepts_keys(fun, &argument_keys, true));
+
+        for fun in functions {
+            if !accepts_keys(&fun, &argument_keys, accepts_exactly) {
                 candidates_with_failed_signature.push(fun);
                 continue;
             }
 
             let generic_map = fun.interface.generics.values()
-                .map(|trait_| (Rc::clone(trait_), TypeProto::unit(TypeUnit::Generic(Uuid::new_v4()))))
+                .map(|trait_| (Arc::clone(trait_), TypeProto::unit(TypeUnit::Generic(Uuid::new_v4()))))
                 .collect();
 
             candidates.push(Box::new(AmbiguousFunctionCandidate {
@@ -543,12 +1217,13 @@ impl <'a> ImperativeResolver<'a> {
                     .collect(),
                 return_type: fun.interface.return_type.replacing_structs(&generic_map),
                 requirements: fun.interface.requirements.iter().cloned().collect_vec(),
+                where_clause: scope.where_clause(&fun),
                 function: fun,
                 generic_map,
             }));
         }
 
-        if candidates.len() >= 1 {
+        if !candidates.is_empty() {
             let expression_id = self.builder.make_expression(argument_expressions.clone());
 
             self.register_ambiguity(Box::new(AmbiguousFunctionCall {
@@ -564,10 +1239,9 @@ impl <'a> ImperativeResolver<'a> {
             return Ok(expression_id);
         }
 
-        // TODO We should probably output the locations of candidates.
 
         let signature = MockFunctionInterface {
-            representation,
+            representation: representation.clone(),
             argument_keys: argument_keys.clone().into_iter().cloned().collect_vec(),
             arguments: argument_expressions.clone(),
             types: &self.builder.types,
@@ -578,41 +1252,155 @@ impl <'a> ImperativeResolver<'a> {
 
         match &candidates_with_failed_signature[..] {
             [candidate] => {
-                error = error.with_note(
-                    RuntimeError::info(format!("Candidate has mismatching signature: {:?}", candidate).as_str())
-                );
+                error = error.with_note(self.candidate_note(candidate, format!(
+                    "Candidate {:?} {}",
+                    with_options(candidate.interface.as_ref(), &representation),
+                    describe_signature_mismatch(candidate, &argument_keys),
+                )));
             }
             [] => {}
             candidates => {
-                // TODO Output all?
                 error = error.with_note(
                     RuntimeError::info(format!("{} candidates have mismatching signatures.", candidates.len()).as_str())
                 );
+                for candidate in candidates {
+                    error = error.with_note(self.candidate_note(candidate, format!(
+                        "Candidate {:?}.",
+                        with_options(candidate.interface.as_ref(), &representation),
+                    )));
+                }
             }
         }
 
-        return Err(error.to_array());
+        Err(error.to_array())
+    }
+
+    /// A note about a candidate function, pointing to its declaration.
+    /// Notes can only point into the erroring module, so declarations elsewhere are named instead.
+    fn candidate_note(&self, candidate: &FunctionHead, title: String) -> RuntimeError {
+        match self.builder.runtime.source.fn_declarations.get(candidate) {
+            Some(site) if site.module == self.module_name => RuntimeError::info(&title).in_range(site.range.clone()),
+            Some(site) => RuntimeError::info(format!("{} It is declared in {}.", title, site.module.join(".")).as_str()),
+            None => RuntimeError::info(&title),
+        }
     }
 
+    /// Ascribes a type to the value. This only constrains inference; no expression is added for it.
     pub fn hint_type(&mut self, value: GenericAlias, type_declaration: &ast::Expression, scope: &scopes::Scope) -> RResult<()> {
-        let mut type_factory = TypeFactory::new(&scope, &self.builder.runtime);
+        let declared_type = self.resolve_type_hint(type_declaration, scope)?;
+
+        // Like with declarations, calls inside the value resolve against the ascribed type,
+        //  so conflicts are reported here rather than wherever the value ends up being used.
+        self.builder.types.bind(value, declared_type.as_ref())
+            .and_then(|_| self.resolve_ambiguities_watching(value))
+            .map_err(|errors| {
+                RuntimeError::error(format!("Value cannot be ascribed {:?}.", declared_type).as_str())
+                    .in_range(type_declaration.position().unwrap_or(0..0))
+                    .with_notes(errors.into_iter())
+                    .with_notes(shadowed_primitive_notes(self.builder.runtime, &declared_type).into_iter())
+                    .to_array()
+            })
+    }
+
+    pub fn resolve_type_hint(&mut self, type_declaration: &ast::Expression, scope: &scopes::Scope) -> RResult<Arc<TypeProto>> {
+        let mut type_factory = TypeFactory::new(scope, self.builder.runtime);
 
-        let type_declaration = type_factory.resolve_type(&type_declaration,true)?;
+        let type_ = type_factory.resolve_type(type_declaration,true)?;
+        if !self.settings.allow_deprecated {
+            let range = type_declaration.position().unwrap_or(0..0);
+            self.warnings.extend(deprecation::type_warnings(&self.builder.runtime.source, [&type_].into_iter()).into_iter().map(|warning| warning.in_range(range.clone())));
+        }
 
-        for requirement in type_factory.requirements {
+        if !type_factory.requirements.is_empty() {
             todo!("Implicit imperative requirements are not implemented yet")
         }
 
-        for (name, generic) in type_factory.generics.into_iter() {
-            panic!("Anonymous type hints are not supported yet") // need a mut scope
-            // scope.insert_singleton(
-            //     scopes::Environment::Global,
-            //     Reference::make_immutable_type(TypeProto::unit(generic.clone())),
-            //     &name
-            // );
+        if !type_factory.generics.is_empty() {
+            // TODO The generic would have to be declared in the (mutable) scope for the rest of the block.
+            panic!("Anonymous type hints are not supported yet")
         }
 
-        self.builder.types.bind(value, type_declaration.as_ref())?;
-        Ok(())
+        Ok(type_)
+    }
+}
+
+/// Whether the arguments' keys fit the function's parameters, unless `exactly` also by internal name.
+fn accepts_keys(function: &FunctionHead, argument_keys: &[&ParameterKey], exactly: bool) -> bool {
+    let parameters = &function.interface.parameters;
+    parameters.len() == argument_keys.len() && parameters.iter().zip(argument_keys).all(|(parameter, key)| {
+        match (&parameter.external_key, key) {
+            (expected, given) if expected == *given => true,
+            (ParameterKey::Positional, ParameterKey::Name(name)) => !exactly && &parameter.internal_name == name,
+            _ => false,
+        }
+    })
+}
+
+/// Explains why the arguments' keys don't fit the function's parameters.
+/// Arity is checked first, then keys in order. Types are checked later, by the ambiguity.
+fn describe_signature_mismatch(function: &FunctionHead, argument_keys: &Vec<&ParameterKey>) -> String {
+    let parameter_keys = function.interface.parameters.iter().map(|x| &x.external_key).collect_vec();
+    if parameter_keys.len() != argument_keys.len() {
+        return format!("takes {} argument(s), but {} were given.", parameter_keys.len(), argument_keys.len())
+    }
+
+    let Some((idx, (expected, given))) = parameter_keys.iter().zip(argument_keys.iter())
+        .find_position(|(expected, given)| expected != given) else {
+        return "has a mismatching signature.".to_string()
+    };
+
+    let ParameterKey::Name(name) = given else {
+        return format!("expects argument {} to be passed as '{}'.", idx + 1, expected.to_string().trim_end())
+    };
+    if parameter_keys.contains(given) {
+        return format!("expects {} as argument {}, not '{}'.", describe_key(expected), idx + 1, given.to_string().trim_end())
+    }
+
+    let parameter_names = function.interface.parameters.iter().map(|parameter| match &parameter.external_key {
+        ParameterKey::Name(name) => name.as_str(),
+        ParameterKey::Positional => parameter.internal_name.as_str(),
+    });
+    match strings::closest_match(name, parameter_names) {
+        Some(suggestion) => format!("has no parameter '{}'. Did you mean '{}'?", name, suggestion),
+        None => format!("has no parameter '{}'.", name),
+    }
+}
+
+/// The dotted name of a namespace, like `outer.math`, if the value could name one.
+fn namespace_path<Function>(value: &expressions::Value<Function>) -> Option<String> {
+    match value {
+        expressions::Value::Identifier(name) => Some(name.to_string()),
+        expressions::Value::MemberAccess(target, member) => Some(format!("{}.{}", namespace_path(&target.value)?, member)),
+        _ => None,
+    }
+}
+
+fn describe_key(key: &ParameterKey) -> String {
+    match key {
+        ParameterKey::Positional => "a positional argument".to_string(),
+        ParameterKey::Name(name) => format!("'{}:'", name),
     }
 }
+
+/// The terms of `target <operator> (new_value)`.
+fn compound_assignment_value(target: &ast::Expression, operator: &Positioned<String>, new_value: &ast::Expression) -> ast::Expression {
+    let new_value = Positioned {
+        position: new_value.position().unwrap_or(operator.position.clone()),
+        value: new_value,
+    };
+
+    let mut terms = target.to_vec();
+    terms.push(Box::new(operator.with_value(ast::Term::Identifier(operator.value.clone()))));
+    terms.push(Box::new(new_value.with_value(
+        ast::Term::Struct(Box::new(ast::Struct {
+            arguments: vec![Box::new(new_value.with_value(
+                ast::StructArgument {
+                    key: ParameterKey::Positional,
+                    value: new_value.value.clone(),
+                    type_declaration: None,
+                }
+            ))],
+        }))
+    )));
+    ast::Expression::from(terms)
+}
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
+use linked_hash_map::LinkedHashMap;
 
 use crate::error::{RResult, RuntimeError};
 use crate::interpreter::runtime::Runtime;
@@ -18,7 +18,7 @@ pub struct ImperativeBuilder<'a> {
     pub runtime: &'a Runtime,
     pub types: Box<TypeForest>,
     pub expression_tree: Box<ExpressionTree>,
-    pub locals_names: HashMap<Rc<ObjectReference>, String>,
+    pub locals_names: LinkedHashMap<Arc<ObjectReference>, String>,
 }
 
 impl<'a> ImperativeBuilder<'a> {
@@ -36,21 +36,23 @@ impl<'a> ImperativeBuilder<'a> {
 
     pub fn make_operation_expression(&mut self, arguments: Vec<ExpressionID>, operation: ExpressionOperation) -> ExpressionID {
         let id = self.make_expression(arguments);
-        self.expression_tree.values.insert(id.clone(), operation);
+        self.expression_tree.values.insert(id, operation);
         id
     }
 
     pub fn make_full_expression(&mut self, arguments: Vec<ExpressionID>, return_type: &TypeProto, operation: ExpressionOperation) -> RResult<ExpressionID> {
         let id = self.make_expression(arguments);
 
-        self.expression_tree.values.insert(id.clone(), operation);
+        self.expression_tree.values.insert(id, operation);
 
-        self.types.bind(id, &return_type)
+        self.types.bind(id, return_type)
             .map(|_| id)
     }
 
-    pub fn register_local(&mut self, identifier: &str, reference: Rc<ObjectReference>, scope: &mut scopes::Scope) -> RResult<()> {
-        self.locals_names.insert(Rc::clone(&reference), identifier.to_string());
+    /// Makes the identifier refer to the new local from here on.
+    /// Expressions resolved earlier, including in enclosing or nested blocks, keep their reference to the previous local.
+    pub fn register_local(&mut self, identifier: &str, reference: Arc<ObjectReference>, scope: &mut scopes::Scope) -> RResult<()> {
+        self.locals_names.insert(Arc::clone(&reference), identifier.to_string());
         scope.override_reference(FunctionTargetType::Global, scopes::Reference::Local(reference), identifier)
     }
 
@@ -62,7 +64,7 @@ impl<'a> ImperativeBuilder<'a> {
         )
     }
 
-    pub fn add_function_reference(&mut self, overload: &Rc<FunctionOverload>) -> RResult<ExpressionID> {
+    pub fn add_function_reference(&mut self, overload: &Arc<FunctionOverload>) -> RResult<ExpressionID> {
         match overload.functions.iter().exactly_one() {
             Ok(function) => {
                 let getter = &self.runtime.source.fn_getters[function];
@@ -70,12 +72,12 @@ impl<'a> ImperativeBuilder<'a> {
                     vec![],
                     &getter.interface.return_type,
                     // Call the getter of the function 'object' instead of the function itself.
-                    ExpressionOperation::FunctionCall(FunctionBinding::pure(Rc::clone(getter)))
+                    ExpressionOperation::FunctionCall(FunctionBinding::pure(Arc::clone(getter)))
                 )?;
 
                 Ok(expression_id)
             }
-            _ => return Err(
+            _ => Err(
                 RuntimeError::error("References to overloaded functions are not yet supported (need syntax to distinguish which to choose).").to_array()
             )?,
         }
@@ -1,3 +1,6 @@
+use std::ops::Range;
+use std::sync::Arc;
+
 use itertools::Itertools;
 
 use crate::ast;
@@ -6,17 +9,20 @@ use crate::interpreter::runtime::Runtime;
 use crate::parser::expressions;
 use crate::program::functions::ParameterKey;
 use crate::program::module::ModuleName;
+use crate::program::traits::TraitConformanceRule;
 use crate::resolver::{interpreter_mock, scopes};
-use crate::util::iter::omega;
-use crate::util::position::Positioned;
 
 pub struct Import {
     pub is_relative: bool,
     pub elements: Vec<String>,
+    /// The namespace to re-export the module under, from `module!("...") as name`.
+    pub alias: Option<String>,
+    /// The argument that names the module, to report problems loading it.
+    pub position: Range<usize>,
 }
 
 impl Import {
-    pub fn relative_to(&self, path: &Vec<String>) -> Vec<String> {
+    pub fn relative_to(&self, path: &[String]) -> Vec<String> {
         match self.is_relative {
             true => path.iter().chain(&self.elements).cloned().collect_vec(),
             false => self.elements.clone(),
@@ -46,6 +52,11 @@ pub fn resolve_module(body: &ast::Expression, scope: &scopes::Scope) -> RResult<
 
     let parsed = expressions::parse(body, &scope.grammar)?;
 
+    let (parsed, alias) = match &parsed.value {
+        expressions::Value::TypeCast(target, alias) => (target, Some(alias.to_string())),
+        _ => (&parsed, None),
+    };
+
     let expressions::Value::FunctionCall(target, call_struct) = &parsed.value else {
         return Err(error)
     };
@@ -74,7 +85,7 @@ pub fn resolve_module(body: &ast::Expression, scope: &scopes::Scope) -> RResult<
         literal = chars.as_str();
     }
 
-    let mut elements = literal.split(".").collect_vec();
+    let elements = literal.split(".").collect_vec();
 
     if !elements.iter().all(|p| p.chars().all(|c| c.is_alphanumeric())) {
         return Err(error);
@@ -83,15 +94,51 @@ pub fn resolve_module(body: &ast::Expression, scope: &scopes::Scope) -> RResult<
     Ok(Import {
         is_relative,
         elements: elements.iter().map(|e| e.to_string()).collect_vec(),
+        alias,
+        position: parsed.position.clone(),
     })
 }
 
-pub fn deep(runtime: &Runtime, module_name: ModuleName, scope: &mut scopes::Scope) -> RResult<()> {
-    let all_modules = omega([&module_name].into_iter(), |m| runtime.source.module_by_name[*m].included_modules.iter());
+/// Imports the module and everything it includes.
+/// Modules re-exported under an alias are imported into the alias' namespace.
+/// Returns the conformance rules that were skipped as duplicates, like `Scope::import`.
+pub fn deep(runtime: &Runtime, module_name: ModuleName, scope: &mut scopes::Scope) -> RResult<Vec<(Arc<TraitConformanceRule>, Arc<TraitConformanceRule>)>> {
+    let mut all_modules = vec![];
+    gather_included_modules(runtime, &module_name, None, &mut vec![], &mut all_modules);
+
+    let mut duplicates = vec![];
+    for (module, namespace, chain) in all_modules {
+        if chain.len() > 1 {
+            scope.trait_conformance.include_chains.entry(module.clone())
+                .or_insert_with(|| chain.into_iter().cloned().collect_vec());
+        }
+        duplicates.extend(scope.import_into_namespace(&runtime.source.module_by_name[module], namespace.as_deref(), runtime)?);
+    }
+
+    Ok(duplicates)
+}
+
+/// Collects the module and all modules it includes (transitively), each only once,
+///  along with the namespace it is re-exported under, and the chain of includes that led to it,
+///  starting with the imported module.
+/// Aliases nest, so a module re-exported as `inner` by a module re-exported as `outer` is in `outer.inner`.
+/// Included modules come before their includer, so that their precedence order is known before
+///  the patterns of the including modules are added.
+fn gather_included_modules<'a>(runtime: &'a Runtime, module_name: &'a ModuleName, namespace: Option<String>, chain: &mut Vec<&'a ModuleName>, modules: &mut Vec<(&'a ModuleName, Option<String>, Vec<&'a ModuleName>)>) {
+    if modules.iter().any(|(module, _, _)| *module == module_name) {
+        return
+    }
 
-    for module in all_modules {
-        scope.import(&runtime.source.module_by_name[module], runtime)?;
+    chain.push(module_name);
+    for (included, alias) in runtime.source.module_by_name[module_name].included_modules.iter() {
+        let namespace = match (&namespace, alias) {
+            (Some(namespace), Some(alias)) => Some(format!("{}.{}", namespace, alias)),
+            (_, Some(alias)) => Some(alias.clone()),
+            (namespace, None) => namespace.clone(),
+        };
+        gather_included_modules(runtime, included, namespace, chain, modules);
     }
 
-    Ok(())
+    modules.push((module_name, namespace, chain.clone()));
+    chain.pop();
 }
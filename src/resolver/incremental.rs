@@ -0,0 +1,160 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::ast;
+use crate::error::RResult;
+use crate::interpreter::runtime::Runtime;
+use crate::program::functions::FunctionHead;
+use crate::program::global::FunctionLogic;
+use crate::program::module::{Declaration, Module};
+use crate::resolver::decorations::is_pure_decoration;
+use crate::resolver::function::resolve_function_body;
+use crate::resolver::{anonymous_structs, imports};
+use crate::source::DeclarationSite;
+use crate::static_analysis;
+use crate::static_analysis::purity;
+use crate::util::position::Positioned;
+
+/// Hashes the module's global statements, pairing function declarations with the functions they declared.
+pub fn declarations(syntax: &ast::Block, declared_functions: Vec<Arc<FunctionHead>>) -> Vec<Declaration> {
+    let mut declared_functions = declared_functions.into_iter();
+
+    syntax.statements.iter().map(|statement| {
+        let (interface_hash, body_hash) = hash_statement(statement);
+        let head = match &statement.value.value {
+            ast::Statement::FunctionDeclaration(_) => declared_functions.next(),
+            _ => None,
+        };
+
+        Declaration {
+            interface_hash,
+            body: head.zip(body_hash),
+        }
+    }).collect_vec()
+}
+
+/// Hashes the statement's syntax, ignoring positions.
+/// For function declarations, the body is hashed separately.
+fn hash_statement(statement: &ast::Decorated<Positioned<ast::Statement>>) -> (u64, Option<u64>) {
+    match &statement.value.value {
+        ast::Statement::FunctionDeclaration(function) => (
+            hash((&statement.decorations, &function.interface, function.body.is_some())),
+            function.body.as_ref().map(hash),
+        ),
+        _ => (hash(statement), None),
+    }
+}
+
+fn hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resolves the bodies that changed from the module's declarations to the syntax, if nothing else changed.
+/// Returns the functions whose bodies were resolved again, or None if the module needs to be resolved from scratch.
+/// The implementations of unchanged functions are kept, including their source ranges.
+/// Only where functions are declared is updated for them.
+pub fn update_bodies(syntax: &ast::Block, runtime: &mut Runtime, module: &mut Module) -> RResult<Option<Vec<Arc<FunctionHead>>>> {
+    if syntax.statements.len() != module.declarations.len() {
+        return Ok(None)
+    }
+    // Pure declarations are checked against the whole module's call graph.
+    if syntax.statements.iter().any(|statement| statement.decorations_as_vec().is_ok_and(|decorations| decorations.into_iter().any(is_pure_decoration))) {
+        return Ok(None)
+    }
+
+    let mut changed = vec![];
+    for (statement, declaration) in syntax.statements.iter().zip_eq(module.declarations.iter()) {
+        let (interface_hash, body_hash) = hash_statement(statement);
+        if interface_hash != declaration.interface_hash {
+            return Ok(None)
+        }
+        if let (Some((head, previous_hash)), Some(body_hash)) = (&declaration.body, body_hash) {
+            if *previous_hash != body_hash {
+                changed.push((statement, Arc::clone(head)));
+            }
+        }
+    }
+
+    // Changed bodies may construct anonymous structs that weren't mentioned before.
+    anonymous_structs::declare_in_block(runtime, syntax);
+
+    // The module's global scope, as it was when its bodies were first resolved.
+    let base_scope = runtime.base_scope()?;
+    let mut scope = base_scope.subscope();
+    // Own declarations are known first, so they take precedence over duplicate imported conformances.
+    scope.import(module, runtime)?;
+    for name in module.imported_modules.iter() {
+        imports::deep(runtime, name.clone(), &mut scope)?;
+    }
+    scope.grammar = module.grammar.clone();
+
+    let mut errors = vec![];
+    let mut implementations = vec![];
+    for (statement, head) in changed.iter() {
+        let ast::Statement::FunctionDeclaration(function) = &statement.value.value else {
+            panic!("Only function declarations have bodies.")
+        };
+
+        let range = statement.value.position.clone();
+        match resolve_function_body(Arc::clone(head), function.body.as_ref().unwrap(), function.interface.return_type.as_ref().and_then(|t| t.position()), &scope, &module.name, &module.settings, runtime).and_then(|mut imp| {
+            module.warnings.extend(static_analysis::check(&mut imp, &module.settings, runtime)?);
+            Ok(imp)
+        }) {
+            Ok(implementation) => implementations.push(implementation),
+            Err(e) => errors.extend(e.into_iter().map(|e| e.in_range(range.clone()))),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors)
+    }
+
+    let heads = changed.iter().map(|(_, head)| Arc::clone(head)).collect_vec();
+    for implementation in implementations {
+        runtime.source.fn_logic.insert(Arc::clone(&implementation.head), FunctionLogic::Implementation(implementation));
+    }
+    for (declaration, statement) in module.declarations.iter_mut().zip_eq(syntax.statements.iter()) {
+        if let (Some((head, hash)), (_, Some(body_hash))) = (&mut declaration.body, hash_statement(statement)) {
+            *hash = body_hash;
+            // Edits may have moved the declaration, even if it didn't change.
+            runtime.source.fn_declarations.insert(Arc::clone(head), DeclarationSite { module: module.name.clone(), range: statement.value.position.clone() });
+        }
+    }
+
+    // Callers may have become pure or impure with their callee.
+    let stale = with_callers(&heads, runtime);
+    runtime.source.impure_functions.retain(|head, _| !stale.contains(head));
+    purity::infer(
+        stale.iter().filter_map(|head| runtime.source.fn_logic[head].as_implementation().ok()),
+        &runtime.source.fn_logic,
+        &mut runtime.source.impure_functions,
+    );
+
+    Ok(Some(heads))
+}
+
+/// The functions, and all implementations that call them directly or indirectly.
+pub fn with_callers(heads: &[Arc<FunctionHead>], runtime: &Runtime) -> HashSet<Arc<FunctionHead>> {
+    let callers = runtime.source.fn_logic.values()
+        .filter_map(|logic| logic.as_implementation().ok())
+        .flat_map(|implementation| purity::callees(implementation).into_iter().map(|callee| (callee, Arc::clone(&implementation.head))))
+        .into_group_map();
+
+    let mut found: HashSet<_> = heads.iter().cloned().collect();
+    let mut next = heads.to_vec();
+    while let Some(head) = next.pop() {
+        for caller in callers.get(&head).into_iter().flatten() {
+            if found.insert(Arc::clone(caller)) {
+                next.push(Arc::clone(caller));
+            }
+        }
+    }
+
+    found
+}
@@ -1,13 +1,14 @@
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use try_map::FallibleMapExt;
 
 use crate::ast;
-use crate::error::{RResult, RuntimeError, TryCollectMany};
+use crate::error::{ErrInRange, RResult, RuntimeError, TryCollectMany};
 use crate::interpreter::runtime::Runtime;
-use crate::parser::expressions;
+use crate::parser::{expressions, lexer};
+use crate::program::allocation::Mutability;
 use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentation, FunctionTargetType};
 use crate::program::functions::{FunctionHead, FunctionInterface, Parameter};
 use crate::program::module::{Module, module_name};
@@ -17,9 +18,10 @@ use crate::resolver::scopes;
 use crate::resolver::type_factory::TypeFactory;
 use crate::util::position::Positioned;
 
-pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &scopes::Scope, module: Option<&mut Module>, runtime: &Runtime, requirements: &HashSet<Rc<TraitBinding>>, generics: &HashMap<String, Rc<Trait>>) -> RResult<(Rc<FunctionHead>, FunctionRepresentation)> {
-    let mut type_factory = TypeFactory::new(scope, runtime);
+pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &scopes::Scope, module: Option<&mut Module>, runtime: &Runtime, requirements: &HashSet<Arc<TraitBinding>>, generics: &HashMap<String, Arc<Trait>>) -> RResult<(Arc<FunctionHead>, FunctionRepresentation)> {
+    let type_factory = TypeFactory::new(scope, runtime);
 
+    check_declared_names(&interface.expression, scope)?;
     let parsed = expressions::parse(&interface.expression, &scope.grammar)?;
 
     match &parsed.value {
@@ -37,7 +39,7 @@ pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &sc
                 name: identifier.to_string(),
                 target_type: FunctionTargetType::Global,
                 call_explicity: FunctionCallExplicity::Implicit,
-            }, [].into_iter(), &interface.return_type, type_factory, requirements, generics)
+            }, [].into_iter(), interface, type_factory, requirements, generics)
         }
         expressions::Value::MemberAccess(target, member) => {
             // Member constant like
@@ -46,7 +48,7 @@ pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &sc
                 name: member.to_string(),
                 target_type: FunctionTargetType::Member,
                 call_explicity: FunctionCallExplicity::Implicit,
-            }, Some(target).into_iter(), &interface.return_type, type_factory, requirements, generics)
+            }, Some(target).into_iter(), interface, type_factory, requirements, generics)
         }
         expressions::Value::FunctionCall(target, call_struct) => {
             match &target.value {
@@ -56,7 +58,7 @@ pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &sc
                         name: identifier.to_string(),
                         target_type: FunctionTargetType::Global,
                         call_explicity: FunctionCallExplicity::Explicit,
-                    }, call_struct.arguments.iter().map(|a| &a.value), &interface.return_type, type_factory, requirements, generics)
+                    }, call_struct.arguments.iter().map(|a| &a.value), interface, type_factory, requirements, generics)
                 }
                 expressions::Value::MemberAccess(target, member) => {
                     // Member function like
@@ -65,27 +67,55 @@ pub fn resolve_function_interface(interface: &ast::FunctionInterface, scope: &sc
                         name: member.to_string(),
                         target_type: FunctionTargetType::Member,
                         call_explicity: FunctionCallExplicity::Explicit,
-                    }, Some(target).into_iter().chain(call_struct.arguments.iter().map(|a| &a.value)), &interface.return_type, type_factory, requirements, generics)
+                    }, Some(target).into_iter().chain(call_struct.arguments.iter().map(|a| &a.value)), interface, type_factory, requirements, generics)
                 }
-                _ => return Err(RuntimeError::error("Invalid function definition.").to_array()),
+                _ => Err(RuntimeError::error("Invalid function definition.").to_array()),
             }
         }
-        _ => return Err(RuntimeError::error("Invalid function definition.").to_array()),
+        _ => Err(RuntimeError::error("Invalid function definition.").to_array()),
     }
 }
 
-fn resolve_macro_function_interface(module: Option<&mut Module>, runtime: &Runtime, m: &String) -> RResult<(Rc<FunctionHead>, FunctionRepresentation)> {
+/// Keywords would be parsed as operators or statements wherever the function is used, so they can't be declared.
+/// Names after a dot are member names, which are never keywords.
+fn check_declared_names(terms: &[Box<Positioned<ast::Term>>], scope: &scopes::Scope) -> RResult<()> {
+    for (i, term) in terms.iter().enumerate() {
+        let ast::Term::Identifier(name) = &term.value else {
+            continue
+        };
+        if i > 0 && matches!(terms[i - 1].value, ast::Term::Dot) {
+            continue
+        }
+
+        let kind = if lexer::is_reserved_keyword(name) {
+            "a reserved keyword"
+        } else if scope.grammar.keywords.contains(name) {
+            "an active operator keyword"
+        } else {
+            continue
+        };
+        return Err(
+            RuntimeError::error(format!("'{}' is {} and cannot be used as a function name.", name, kind).as_str())
+                .in_range(term.position.clone())
+                .to_array()
+        );
+    }
+
+    Ok(())
+}
+
+fn resolve_macro_function_interface(module: Option<&mut Module>, runtime: &Runtime, m: &String) -> RResult<(Arc<FunctionHead>, FunctionRepresentation)> {
     match m.as_str() {
         "main" => {
             let proto_function = runtime.source.module_by_name[&module_name("core.run")].explicit_functions(&runtime.source).into_iter()
                 .filter(|function| runtime.source.fn_representations[*function].name == "main")
                 .exactly_one().unwrap();
 
-            let fun = FunctionHead::new_static(Rc::clone(&proto_function.interface));
+            let fun = FunctionHead::new_static(Arc::clone(&proto_function.interface));
             let representation = FunctionRepresentation::new("main", FunctionTargetType::Global, FunctionCallExplicity::Explicit);
 
             if let Some(module) = module {
-                module.main_functions.push(Rc::clone(&fun));
+                module.main_functions.push(Arc::clone(&fun));
             }
             Ok((fun, representation))
         },
@@ -94,11 +124,11 @@ fn resolve_macro_function_interface(module: Option<&mut Module>, runtime: &Runti
                 .filter(|function| runtime.source.fn_representations[*function].name == "transpile")
                 .exactly_one().unwrap();
 
-            let fun = FunctionHead::new_static(Rc::clone(&proto_function.interface));
+            let fun = FunctionHead::new_static(Arc::clone(&proto_function.interface));
             let representation = FunctionRepresentation::new("transpile", FunctionTargetType::Global, FunctionCallExplicity::Explicit);
 
             if let Some(module) = module {
-                module.transpile_functions.push(Rc::clone(&fun));
+                module.transpile_functions.push(Arc::clone(&fun));
             }
             Ok((fun, representation))
         },
@@ -108,9 +138,15 @@ fn resolve_macro_function_interface(module: Option<&mut Module>, runtime: &Runti
     }
 }
 
-pub fn _resolve_function_interface<'a>(representation: FunctionRepresentation, parameters: impl Iterator<Item=&'a ast::StructArgument>, return_type: &Option<ast::Expression>, mut type_factory: TypeFactory, requirements: &HashSet<Rc<TraitBinding>>, generics: &HashMap<String, Rc<Trait>>) -> RResult<(Rc<FunctionHead>, FunctionRepresentation)> {
-    let return_type = return_type.as_ref()
-        .try_map(|x| type_factory.resolve_type(&x, true))?
+pub fn _resolve_function_interface<'a>(representation: FunctionRepresentation, parameters: impl Iterator<Item=&'a ast::StructArgument>, interface: &ast::FunctionInterface, mut type_factory: TypeFactory, requirements: &HashSet<Arc<TraitBinding>>, generics: &HashMap<String, Arc<Trait>>) -> RResult<(Arc<FunctionHead>, FunctionRepresentation)> {
+    // Generics may be introduced by the where clause, so it's resolved first.
+    for requirement in interface.where_clause.iter().flat_map(|where_clause| where_clause.value.iter()) {
+        type_factory.resolve_requirement(&requirement.value)
+            .err_in_range(&requirement.position)?;
+    }
+
+    let return_type = interface.return_type.as_ref()
+        .try_map(|x| type_factory.resolve_type(x, true))?
         .unwrap_or(TypeProto::void());
 
     let parameters = parameters
@@ -122,12 +158,12 @@ pub fn _resolve_function_interface<'a>(representation: FunctionRepresentation, p
 
     let requirements = requirements.iter()
         .chain(&type_factory.requirements)
-        .map(Rc::clone)
+        .map(Arc::clone)
         .collect();
 
     Ok((
         FunctionHead::new_static(
-            Rc::new(FunctionInterface {
+            Arc::new(FunctionInterface {
                 parameters,
                 return_type,
                 requirements,
@@ -145,22 +181,23 @@ pub fn resolve_function_parameter(parameter: &ast::StructArgument, type_factory:
         );
     };
 
-    let [
-        Positioned { position, value: ast::Term::Identifier(internal_name) }
-    ] = parameter.value.iter().map(|a| a.as_ref()).collect_vec()[..] else {
-        return Err(
+    let (mutability, internal_name) = match parameter.value.iter().map(|a| &a.as_ref().value).collect_vec()[..] {
+        [ast::Term::Identifier(internal_name)] => (Mutability::Immutable, internal_name),
+        [ast::Term::Identifier(keyword), ast::Term::Identifier(internal_name)] if keyword == "mut" => (Mutability::Mutable, internal_name),
+        _ => return Err(
             RuntimeError::error("Cannot have non-identifier internal name.").to_array()
-        )
+        ),
     };
 
     Ok(Parameter {
         external_key: parameter.key.clone(),
         internal_name: internal_name.clone(),
         type_: type_factory.resolve_type(type_declaration, true)?,
+        mutability,
     })
 }
 
-pub fn get_as_target_parameter<'a>(term: &'a expressions::Value<Rc<FunctionHead>>) -> RResult<&'a ast::StructArgument> {
+pub fn get_as_target_parameter<'a>(term: &'a expressions::Value<Arc<FunctionHead>>) -> RResult<&'a ast::StructArgument> {
     let expressions::Value::StructLiteral(struct_) = term else {
         return Err(RuntimeError::error("Target of member function must be one-element struct.").to_array())
     };
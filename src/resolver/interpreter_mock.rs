@@ -20,8 +20,8 @@ pub fn plain_parameter<'a>(cause: &str, struct_: &'a ast::Struct) -> RResult<&'a
     Ok(&body.value.value)
 }
 
-pub fn plain_string_literal<'a>(cause: &str, literal: &'a Vec<Box<Positioned<ast::StringPart>>>) -> RResult<&'a str> {
-    let [part] = &literal[..] else {
+pub fn plain_string_literal<'a>(cause: &str, literal: &'a [Box<Positioned<ast::StringPart>>]) -> RResult<&'a str> {
+    let [part] = literal else {
         return Err(RuntimeError::error(format!("{} needs a plain string literal.", cause).as_str()).to_array());
     };
 
@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use strum::IntoEnumIterator;
@@ -11,8 +11,8 @@ use crate::parser::grammar::{OperatorAssociativity, PrecedenceGroup};
 use crate::program::functions::ParameterKey;
 use crate::resolver::scopes;
 
-pub fn resolve_precedence_order(call_struct: &ast::Struct, scope: &scopes::Scope) -> RResult<Vec<Rc<PrecedenceGroup>>> {
-    let order: Vec<Rc<PrecedenceGroup>> = call_struct.arguments.iter().map(|arg| {
+pub fn resolve_precedence_order(call_struct: &ast::Struct, scope: &scopes::Scope) -> RResult<Vec<Arc<PrecedenceGroup>>> {
+    let order: Vec<Arc<PrecedenceGroup>> = call_struct.arguments.iter().map(|arg| {
         let ParameterKey::Name(name) = &arg.value.key else {
             return Err(RuntimeError::error("Not a named argument.").in_range(arg.position.clone()).to_array())
         };
@@ -22,7 +22,7 @@ pub fn resolve_precedence_order(call_struct: &ast::Struct, scope: &scopes::Scope
 
         let associativity = resolve_associativity(&arg.value.value, scope)?;
 
-        Ok(Rc::new(PrecedenceGroup {
+        Ok(Arc::new(PrecedenceGroup {
             trait_id: Uuid::new_v4(),
             name: name.to_string(),
             associativity,
@@ -48,7 +48,7 @@ pub fn resolve_associativity(body: &ast::Expression, scope: &scopes::Scope) -> R
         return Err(error);
     };
 
-    let Ok(associativity) = OperatorAssociativity::iter().filter(|a| &a.to_string() == identifier.as_str()).exactly_one() else {
+    let Ok(associativity) = OperatorAssociativity::iter().filter(|a| a.to_string() == identifier.as_str()).exactly_one() else {
         return Err(error)
     };
 
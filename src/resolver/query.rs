@@ -0,0 +1,53 @@
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use crate::interpreter::runtime::Runtime;
+use crate::program::expression_tree::ExpressionID;
+use crate::program::global::{FunctionImplementation, FunctionLogic};
+use crate::program::module::Module;
+
+/// A function's expressions, sorted by where they start in the source.
+pub struct ExpressionIndex {
+    intervals: Vec<(Range<usize>, ExpressionID)>,
+}
+
+impl ExpressionIndex {
+    pub fn new(implementation: &FunctionImplementation) -> ExpressionIndex {
+        ExpressionIndex {
+            intervals: implementation.expression_ranges.iter()
+                // Expressions may have been removed after resolution, e.g. as unreachable code.
+                .filter(|(expression, _)| implementation.expression_tree.values.contains_key(expression))
+                .map(|(expression, range)| (range.clone(), *expression))
+                .sorted_by_key(|(range, _)| range.start)
+                .collect_vec(),
+        }
+    }
+
+    /// The innermost expression whose source contains the offset.
+    pub fn innermost_at(&self, offset: usize) -> Option<&(Range<usize>, ExpressionID)> {
+        let starting_before = self.intervals.partition_point(|(range, _)| range.start <= offset);
+        self.intervals[..starting_before].iter()
+            .filter(|(range, _)| offset < range.end)
+            .min_by_key(|(range, _)| range.len())
+    }
+}
+
+/// The type of the innermost expression at the byte offset in the module's source,
+///  formatted like in error messages.
+pub fn type_at(runtime: &Runtime, module: &Module, offset: usize) -> Option<String> {
+    let (implementation, expression) = module.implemented_functions.iter()
+        .filter_map(|head| match runtime.source.fn_logic.get(head)? {
+            FunctionLogic::Implementation(implementation) => Some(implementation),
+            FunctionLogic::Descriptor(_) => None,
+        })
+        .filter_map(|implementation| {
+            let (range, expression) = ExpressionIndex::new(implementation).innermost_at(offset)?.clone();
+            Some((range.len(), implementation, expression))
+        })
+        .min_by_key(|(length, _, _)| *length)
+        .map(|(_, implementation, expression)| (implementation, expression))?;
+
+    let type_ = implementation.type_forest.resolve_binding_alias(&expression).ok()?;
+    Some(format!("{:?}", type_))
+}
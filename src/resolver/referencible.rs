@@ -1,4 +1,4 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::error::RResult;
 use crate::interpreter::runtime::Runtime;
@@ -10,24 +10,25 @@ use crate::program::module::Module;
 use crate::program::traits::{Trait, TraitConformanceRule};
 use crate::program::types::TypeProto;
 
-pub fn add_trait(runtime: &mut Runtime, module: &mut Module, scope: Option<&mut scopes::Scope>, trait_: &Rc<Trait>) -> RResult<()> {
+/// Adds a getter for the trait's metatype, and returns it.
+pub fn add_trait(runtime: &mut Runtime, module: &mut Module, scope: Option<&mut scopes::Scope>, trait_: &Arc<Trait>) -> RResult<Arc<FunctionHead>> {
     let meta_type = TypeProto::one_arg(&runtime.Metatype, TypeProto::unit_struct(trait_));
     let getter = FunctionHead::new_static(FunctionInterface::new_provider(&meta_type, vec![]));
 
-    runtime.source.fn_heads.insert(getter.function_id, Rc::clone(&getter));
+    runtime.source.fn_heads.insert(getter.function_id, Arc::clone(&getter));
     runtime.source.trait_references.insert(
-        Rc::clone(&getter),
-        Rc::clone(trait_),
+        Arc::clone(&getter),
+        Arc::clone(trait_),
     );
     runtime.source.fn_logic.insert(
-        Rc::clone(&getter),
-        FunctionLogic::Descriptor(FunctionLogicDescriptor::TraitProvider(Rc::clone(trait_))),
+        Arc::clone(&getter),
+        FunctionLogic::Descriptor(FunctionLogicDescriptor::TraitProvider(Arc::clone(trait_))),
     );
 
     let representation = FunctionRepresentation::new(&trait_.name, FunctionTargetType::Global, FunctionCallExplicity::Implicit);
 
     runtime.source.fn_representations.insert(
-        Rc::clone(&getter),
+        Arc::clone(&getter),
         representation.clone(),
     );
 
@@ -35,36 +36,36 @@ pub fn add_trait(runtime: &mut Runtime, module: &mut Module, scope: Option<&mut
         scope.overload_function(&getter, representation)?;
     }
 
-    module.exposed_functions.insert(getter);
+    module.exposed_functions.insert(Arc::clone(&getter));
 
-    Ok(())
+    Ok(getter)
 }
 
-pub fn add_function(runtime: &mut Runtime, module: &mut Module, scope: Option<&mut scopes::Scope>, function: Rc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
+pub fn add_function(runtime: &mut Runtime, module: &mut Module, scope: Option<&mut scopes::Scope>, function: Arc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
     // TODO Once functions are actually objects, we can call add_trait from here.
-    let function_trait = Rc::new(Trait::new_with_self(&representation.name));
+    let function_trait = Arc::new(Trait::new_with_self(&representation.name));
     let conformance_to_function = TraitConformanceRule::manual(runtime.traits.as_ref().unwrap().Function.create_generic_binding(vec![
         ("Self", TypeProto::unit_struct(&function_trait))
     ]), vec![]);
-    module.trait_conformance.add_conformance_rule(Rc::clone(&conformance_to_function));
+    module.trait_conformance.add_conformance_rule(Arc::clone(&conformance_to_function));
 
-    runtime.source.function_traits.insert(Rc::clone(&function_trait), Rc::clone(&function));
+    runtime.source.function_traits.insert(Arc::clone(&function_trait), Arc::clone(&function));
 
     let getter = FunctionHead::new_static(
         FunctionInterface::new_provider(&TypeProto::unit_struct(&function_trait), vec![]),
     );
-    runtime.source.fn_heads.insert(function.function_id, Rc::clone(&function));
-    runtime.source.fn_heads.insert(getter.function_id, Rc::clone(&getter));
+    runtime.source.fn_heads.insert(function.function_id, Arc::clone(&function));
+    runtime.source.fn_heads.insert(getter.function_id, Arc::clone(&getter));
     runtime.source.fn_logic.insert(
-        Rc::clone(&getter),
-        FunctionLogic::Descriptor(FunctionLogicDescriptor::FunctionProvider(Rc::clone(&function))),
+        Arc::clone(&getter),
+        FunctionLogic::Descriptor(FunctionLogicDescriptor::FunctionProvider(Arc::clone(&function))),
     );
-    runtime.source.fn_getters.insert(Rc::clone(&function), Rc::clone(&getter));
+    runtime.source.fn_getters.insert(Arc::clone(&function), Arc::clone(&getter));
 
-    runtime.source.fn_representations.insert(Rc::clone(&function), representation.clone());
+    runtime.source.fn_representations.insert(Arc::clone(&function), representation.clone());
 
     runtime.source.fn_representations.insert(
-        Rc::clone(&getter),
+        Arc::clone(&getter),
         FunctionRepresentation::new(representation.name.as_str(), representation.target_type, FunctionCallExplicity::Implicit)
     );
 
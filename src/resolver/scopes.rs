@@ -1,15 +1,19 @@
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use std::rc::Rc;
+use std::ops::Range;
+use std::sync::Arc;
 
+use itertools::Itertools;
+
+use crate::error::codes::ErrorCode;
 use crate::error::{RResult, RuntimeError};
 use crate::interpreter::runtime::Runtime;
 use crate::parser::grammar::{Grammar, PrecedenceGroup};
 use crate::program::allocation::ObjectReference;
-use crate::program::function_object::{FunctionOverload, FunctionRepresentation, FunctionTargetType};
+use crate::program::function_object::{FunctionCallExplicity, FunctionOverload, FunctionRepresentation, FunctionTargetType};
 use crate::program::functions::FunctionHead;
 use crate::program::module::Module;
-use crate::program::traits::TraitGraph;
+use crate::program::traits::{TraitConformanceRule, TraitGraph};
 
 // Note: While a single pool cannot own overloaded variables, multiple same-level pools (-> from imports) can.
 // When we have imports, this should be ignored until referenced, to avoid unnecessary import complications.
@@ -20,10 +24,21 @@ pub struct Scope<'a> {
     pub parent: Option<&'a Scope<'a>>,
 
     pub trait_conformance: TraitGraph,
-    pub grammar: Grammar<Rc<FunctionHead>>,
+    pub grammar: Grammar<Arc<FunctionHead>>,
 
     pub global: RefPool,
     pub member: RefPool,
+    /// Global references of modules re-exported under an alias, by the alias (e.g. `math` or `outer.math`).
+    pub namespaces: HashMap<String, RefPool>,
+
+    /// Where the where clauses of the file's functions were declared, for error reporting.
+    pub where_clauses: HashMap<Arc<FunctionHead>, Range<usize>>,
+}
+
+impl<'a> Default for Scope<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl <'a> Scope<'a> {
@@ -36,6 +51,9 @@ impl <'a> Scope<'a> {
 
             global: HashMap::new(),
             member: HashMap::new(),
+            namespaces: HashMap::new(),
+
+            where_clauses: HashMap::new(),
         }
     }
 
@@ -51,6 +69,9 @@ impl <'a> Scope<'a> {
 
             global: HashMap::new(),
             member: HashMap::new(),
+            namespaces: HashMap::new(),
+
+            where_clauses: HashMap::new(),
         }
     }
 
@@ -68,39 +89,68 @@ impl <'a> Scope<'a> {
         }
     }
 
+    pub fn where_clause(&self, function: &Arc<FunctionHead>) -> Option<Range<usize>> {
+        match self.where_clauses.get(function) {
+            Some(range) => Some(range.clone()),
+            None => self.parent.and_then(|parent| parent.where_clause(function)),
+        }
+    }
+
     pub fn not_a_keyword(&self, keyword: &str) -> RResult<()> {
-        return if self.grammar.keywords.contains(keyword) {
+        if self.grammar.keywords.contains(keyword) {
             Err(RuntimeError::error("Cannot shadow keyword.").to_array())
         } else {
             Ok(())
         }
     }
 
-    pub fn import(&mut self, module: &Module, runtime: &Runtime) -> RResult<()> {
+    /// Imports everything the module declares.
+    /// Returns the module's conformance rules that were skipped because a duplicate was known already, each with that duplicate.
+    pub fn import(&mut self, module: &Module, runtime: &Runtime) -> RResult<Vec<(Arc<TraitConformanceRule>, Arc<TraitConformanceRule>)>> {
+        self.import_into_namespace(module, None, runtime)
+    }
+
+    /// Like import, but with a namespace, global functions are only reachable through it (e.g. `math.triple(3)`).
+    /// Member functions are called on their values, so they are imported unprefixed either way.
+    pub fn import_into_namespace(&mut self, module: &Module, namespace: Option<&str>, runtime: &Runtime) -> RResult<Vec<(Arc<TraitConformanceRule>, Arc<TraitConformanceRule>)>> {
         // This wipes any existing patterns, but I think that's what we want.
+        //  Modules re-exporting the same precedence order (through include!) don't count as a change though.
         if let Some(precedence) = &module.precedence_order {
-            self.grammar.set_precedence_order(precedence.clone());
+            if !self.grammar.groups_and_keywords.keys().eq(precedence.iter()) {
+                self.grammar.set_precedence_order(precedence.clone());
+            }
         }
 
         for pattern in module.patterns.iter() {
-            self.grammar.add_pattern(Rc::clone(pattern))?;
+            self.grammar.add_pattern(Arc::clone(pattern)).map_err(|errors| errors.into_iter().map(|error| {
+                error.with_note(RuntimeError::note(format!("The pattern is declared in {}.", module.name.join(".")).as_str()))
+            }).collect_vec())?;
         }
 
         for function in module.exposed_functions.iter() {
             let representation = &runtime.source.fn_representations[function];
-            self.overload_function(function, representation.clone())?;
+            match namespace {
+                Some(namespace) if representation.target_type == FunctionTargetType::Global => {
+                    let pool = self.namespaces.entry(namespace.to_string()).or_default();
+                    let overload = match pool.remove(&representation.name) {
+                        Some(Reference::FunctionOverload(overload)) => overload.adding_function(function)?,
+                        _ => FunctionOverload::from(function, representation.clone()),
+                    };
+                    pool.insert(representation.name.clone(), Reference::FunctionOverload(overload));
+                }
+                _ => self.overload_function(function, representation.clone())?,
+            }
         }
 
-        self.trait_conformance.add_graph(&module.trait_conformance);
-
-        Ok(())
+        Ok(self.trait_conformance.add_graph(&module.trait_conformance))
     }
 
-    pub fn overload_function(&mut self, fun: &Rc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
+
+    pub fn overload_function(&mut self, fun: &Arc<FunctionHead>, representation: FunctionRepresentation) -> RResult<()> {
         let name = &representation.name;
         self.not_a_keyword(name)?;
 
-        let mut refs = self.references_mut(representation.target_type);
+        let refs = self.references_mut(representation.target_type);
 
         // Remove the current FunctionOverload reference and replace with a reference containing also our new overload.
         // This may seem weird at first but it kinda makes sense - if someone queries the scope, gets a reference,
@@ -120,11 +170,11 @@ impl <'a> Scope<'a> {
             if let Some(Some(Reference::FunctionOverload(overload))) = self.parent.map(|x| x.resolve(representation.target_type, name).ok()) {
                 let overload = Reference::FunctionOverload(overload.adding_function(fun)?);
 
-                let mut refs = self.references_mut(representation.target_type);
+                let refs = self.references_mut(representation.target_type);
                 refs.insert(representation.name.clone(), overload);
             }
 
-            let mut refs = self.references_mut(representation.target_type);
+            let refs = self.references_mut(representation.target_type);
 
             let overload = Reference::FunctionOverload(FunctionOverload::from(fun, representation.clone()));
 
@@ -136,9 +186,9 @@ impl <'a> Scope<'a> {
 
     pub fn insert_singleton(&mut self, target_type: FunctionTargetType, reference: Reference, name: &str) -> RResult<()> {
         self.not_a_keyword(name)?;
-        let mut refs = self.references_mut(target_type);
+        let refs = self.references_mut(target_type);
 
-        if let Some(other) = refs.insert(name.to_string(), reference) {
+        if let Some(_other) = refs.insert(name.to_string(), reference) {
             return Err(RuntimeError::error(format!("Multiple references with this name: {}", name).as_str()).to_array());
         }
         Ok(())
@@ -146,7 +196,7 @@ impl <'a> Scope<'a> {
 
     pub fn override_reference(&mut self, target_type: FunctionTargetType, reference: Reference, name: &str) -> RResult<()> {
         self.not_a_keyword(name)?;
-        let mut refs = self.references_mut(target_type);
+        let refs = self.references_mut(target_type);
 
         refs.insert(name.to_string(), reference);
         Ok(())
@@ -175,19 +225,55 @@ impl <'a> Scope<'a> {
                     FunctionTargetType::Member => "."
                 };
 
-                return Err(RuntimeError::error(format!("Cannot find '{}{}' in this scope", env_part, name).as_str()).to_array())
+                return Err(RuntimeError::error(format!("Cannot find '{}{}' in this scope", env_part, name).as_str()).with_code(ErrorCode::UnknownReference).to_array())
+            }
+        }
+    }
+
+    /// Finds a global reference re-exported under the namespace, if the namespace exists.
+    pub fn resolve_in_namespace(&'a self, namespace: &str, name: &str) -> Option<RResult<&'a Reference>> {
+        let mut scope = Some(self);
+        let mut found_namespace = false;
+        while let Some(current) = scope {
+            if let Some(pool) = current.namespaces.get(namespace) {
+                found_namespace = true;
+                if let Some(reference) = pool.get(name) {
+                    return Some(Ok(reference))
+                }
             }
+            scope = current.parent;
         }
+
+        found_namespace.then(|| Err(
+            RuntimeError::error(format!("Cannot find '{}' in namespace '{}'", name, namespace).as_str()).with_code(ErrorCode::UnknownReference).to_array()
+        ))
+    }
+
+    /// Finds the nearest overload of explicitly called global functions, skipping other references.
+    /// This is for code that calls functions implicitly, which should keep working if a local shadows the name.
+    pub fn resolve_explicit_function(&'a self, name: &str) -> Option<&'a FunctionOverload> {
+        let mut scope = Some(self);
+        while let Some(current) = scope {
+            if let Some(Reference::FunctionOverload(overload)) = current.global.get(name) {
+                if overload.representation.target_type == FunctionTargetType::Global
+                    && overload.representation.call_explicity == FunctionCallExplicity::Explicit {
+                    return Some(overload)
+                }
+            }
+            scope = current.parent;
+        }
+
+        None
     }
 
-    pub fn resolve_precedence_group(&self, name: &str) -> RResult<Rc<PrecedenceGroup>> {
+    pub fn resolve_precedence_group(&self, name: &str) -> RResult<Arc<PrecedenceGroup>> {
         for group in self.grammar.groups_and_keywords.keys() {
-            if &group.name == name {
-                return Ok(Rc::clone(group))
+            if group.name == name {
+                return Ok(Arc::clone(group))
             }
         }
 
-        return Err(
+        Err(
             RuntimeError::error(format!("Precedence group could not be resolved: {}", name).as_str()).to_array()
         )
     }
@@ -196,26 +282,26 @@ impl <'a> Scope<'a> {
 #[derive(Clone, PartialEq, Eq)]
 pub enum Reference {
     // TODO WE can probably get rid of locals if we replace them by getters and setters.
-    Local(Rc<ObjectReference>),
+    Local(Arc<ObjectReference>),
     // This COULD be an object, but only if it 'inherits' the callable interfaces
     //  from ALL included overloads. Overall, this is probably too confusing and thus not worth
     //  the effort. Rather, as in other languages, we should expect the user to resolve the overload
     //  - either immediately, or by context (e.g. `(should_add ? add : sub)(1, 2)`).
-    FunctionOverload(Rc<FunctionOverload>),
+    FunctionOverload(Arc<FunctionOverload>),
 }
 
 impl Reference {
-    pub fn as_local(&self, require_mutable: bool) -> RResult<&Rc<ObjectReference>> {
+    pub fn as_local(&self, _require_mutable: bool) -> RResult<&Arc<ObjectReference>> {
         let Reference::Local(obj_ref) = self else {
             return Err(RuntimeError::error("Reference is not a local.").to_array());
         };
 
-        Ok(&obj_ref)
+        Ok(obj_ref)
     }
 
-    pub fn as_function_overload(&self) -> RResult<Rc<FunctionOverload>> {
+    pub fn as_function_overload(&self) -> RResult<Arc<FunctionOverload>> {
         match self {
-            Reference::FunctionOverload(overload) => Ok(Rc::clone(overload)),
+            Reference::FunctionOverload(overload) => Ok(Arc::clone(overload)),
             _ => Err(RuntimeError::error("Reference is not a function.").to_array())
         }
     }
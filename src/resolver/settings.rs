@@ -0,0 +1,41 @@
+use crate::ast;
+use crate::error::{RResult, RuntimeError, TryCollectMany};
+use crate::parser::expressions;
+use crate::program::functions::ParameterKey;
+use crate::program::module::ModuleSettings;
+use crate::resolver::scopes;
+
+/// Enables each flag listed in the strict! call.
+pub fn resolve_strict(call_struct: &ast::Struct, scope: &scopes::Scope, settings: &mut ModuleSettings) -> RResult<()> {
+    resolve_flags("strict!", call_struct, scope, &ModuleSettings::FLAGS, |flag| settings.enable(flag))
+}
+
+/// Silences each warning listed in the allow! call.
+pub fn resolve_allow(call_struct: &ast::Struct, scope: &scopes::Scope, settings: &mut ModuleSettings) -> RResult<()> {
+    resolve_flags("allow!", call_struct, scope, &ModuleSettings::ALLOWED_WARNINGS, |warning| settings.allow(warning))
+}
+
+fn resolve_flags(macro_name: &str, call_struct: &ast::Struct, scope: &scopes::Scope, supported: &[&str], mut apply: impl FnMut(&str) -> bool) -> RResult<()> {
+    let _: Vec<()> = call_struct.arguments.iter().map(|arg| {
+        if arg.value.key != ParameterKey::Positional || arg.value.type_declaration.is_some() {
+            return Err(RuntimeError::error(format!("{} expects a list of flags.", macro_name).as_str()).in_range(arg.position.clone()).to_array())
+        }
+
+        let parsed = expressions::parse(&arg.value.value, &scope.grammar)?;
+        let expressions::Value::Identifier(flag) = &parsed.value else {
+            return Err(RuntimeError::error(format!("{} expects a list of flags.", macro_name).as_str()).in_range(arg.position.clone()).to_array())
+        };
+
+        match apply(flag) {
+            true => Ok(()),
+            false => Err(
+                RuntimeError::error(format!("Unknown {} flag: {}", macro_name, flag).as_str())
+                    .in_range(arg.position.clone())
+                    .with_note(RuntimeError::note(format!("Supported flags are: {}", supported.join(", ")).as_str()))
+                    .to_array()
+            ),
+        }
+    }).try_collect_many()?;
+
+    Ok(())
+}
@@ -1,5 +1,3 @@
-use std::fmt::Display;
-use std::hash::Hash;
 
 use crate::program::expression_tree::ExpressionID;
 use crate::program::functions::ParameterKey;
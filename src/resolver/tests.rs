@@ -0,0 +1,577 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use annotate_snippets::Level;
+    use itertools::Itertools;
+    use uuid::Uuid;
+
+    use crate::error::codes::{ErrorCode, LintCode};
+    use crate::error::{RResult, RuntimeError};
+    use crate::{analysis, interpreter, transpiler};
+    use crate::interpreter::runtime::Runtime;
+    use crate::program::generics::TypeForest;
+    use crate::program::global::{FunctionImplementation, FunctionLogic};
+    use crate::program::module::{module_name, ModuleSettings};
+    use crate::program::types::{TypeProto, TypeUnit};
+    use crate::resolver::query::type_at;
+    use crate::static_analysis::lint::{CustomLint, Lint, LintContext};
+    use crate::transpiler::LanguageContext;
+
+    #[test]
+    fn type_at_position() -> RResult<()> {
+        let path = "test-code/query/types.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
+
+        let type_of = |text: &str| type_at(&runtime, &module, source.find(text).unwrap());
+        // The argument of a call nested in string interpolation.
+        assert_eq!(type_of("3.5"), Some("Float32".to_string()));
+        assert_eq!(type_of("square(3.5)"), Some("Float32".to_string()));
+        assert_eq!(type_of("count)"), Some("Int32".to_string()));
+        assert_eq!(type_of("write_line"), Some("Void".to_string()));
+        // Outside any function body.
+        assert_eq!(type_of("use!"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dump_tree() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/query/tree.monoteny"), module_name("main"))?;
+
+        let head = module.implemented_functions.iter()
+            .find(|head| runtime.source.fn_representations[*head].name == "describe")
+            .unwrap();
+        let FunctionLogic::Implementation(implementation) = &runtime.source.fn_logic[head] else {
+            panic!("describe has no implementation.")
+        };
+        assert_eq!(implementation.dump(&runtime.source), "\
+Block 'String
+  SetLocal is_large 'Void
+    FunctionCall common.math._is_greater 'Bool
+      GetLocal count 'Int32
+      FunctionCall parse_int_literal 'Int32
+        StringLiteral \"10\" 'String
+  IfThenElse 'String
+    GetLocal is_large 'Bool
+    StringLiteral \"large\" 'String
+    FunctionCall core.strings.format 'String
+      GetLocal count 'Int32
+");
+
+        Ok(())
+    }
+
+    #[test]
+    fn strict_settings() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("strict", PathBuf::from("test-code"));
+
+        // The importer is strict, but its import is resolved with its own, default settings.
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/strict/strict.monoteny"), module_name("main"))?;
+        assert!(module.settings.no_implicit_number_defaults && module.settings.deny_shadowing && module.settings.deny_unused_results);
+        let lax = runtime.get_or_load_module(&module_name("strict.lax"))?;
+        assert_eq!(lax.settings, ModuleSettings::default());
+        assert_eq!(runtime.module_paths[&module_name("strict.lax")], PathBuf::from("test-code/strict/lax.monoteny"));
+
+        let errors = runtime.load_file_as_module(&PathBuf::from("test-code/strict/violations.monoteny"), module_name("violations")).err().unwrap();
+        let titles = errors.iter().map(|e| e.title.as_str()).sorted().collect_vec();
+        assert_eq!(titles, vec!["The literal's type is ambiguous.", "Unused result.", "Variable value shadows another variable of the same name."]);
+
+        let errors = runtime.load_file_as_module(&PathBuf::from("test-code/strict/unknown_flag.monoteny"), module_name("unknown_flag")).err().unwrap();
+        assert_eq!(errors[0].title, "Unknown strict! flag: deny_everything");
+        assert_eq!(errors[0].notes[0].title, "Supported flags are: no_implicit_number_defaults, deny_shadowing, deny_unused_results, deny_duplicate_conformances");
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_literal_ambiguities() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+
+        // Every literal and every call is ambiguous until the outermost sum is typed.
+        let literals = 200;
+        let sum = (0..literals).map(|i| i.to_string()).join(" + ");
+        let source = format!("use!(module!(\"common\"));\n\ndef main! :: {{\n    let a 'Int32 = {};\n}};\n", sum);
+        let module = runtime.load_text_as_module(&source, module_name("main"))?;
+
+        let FunctionLogic::Implementation(implementation) = &runtime.source.fn_logic[&module.main_functions[0]] else {
+            panic!()
+        };
+        // Retrying every ambiguity each round would take quadratically many attempts.
+        assert!(implementation.ambiguity_attempts <= 10 * literals);
+
+        Ok(())
+    }
+
+    /// Resolving a sum takes time linear in its length, for literals and for variables.
+    #[test]
+    fn linear_sum_resolution() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+
+        let mut resolve_sum = |terms: usize| -> RResult<Duration> {
+            let sum = (0..terms).map(|i| if i % 2 == 0 { "one".to_string() } else { (i % 10).to_string() }).join(" + ");
+            let source = format!("use!(module!(\"common\"));\n\ndef main! :: {{\n    let one 'Int32 = 1;\n    let a 'Int32 = {};\n}};\n", sum);
+            // The fastest run is the one least slowed down by tests running in parallel.
+            let mut fastest = Duration::MAX;
+            for _ in 0..3 {
+                let start = Instant::now();
+                runtime.load_text_as_module(&source, module_name("main"))?;
+                fastest = fastest.min(start.elapsed());
+            }
+            Ok(fastest)
+        };
+
+        let short = resolve_sum(250)?;
+        let long = resolve_sum(1000)?;
+        // Linear growth takes about 4 times as long, quadratic growth about 16 times.
+        assert!(long < short * 8, "250 terms took {:?}, but 1000 terms took {:?}.", short, long);
+
+        Ok(())
+    }
+
+    #[test]
+    fn deprecation() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("deprecation", PathBuf::from("test-code"));
+
+        // The title and source text of each warning.
+        let mut warnings = |file: &str| -> RResult<Vec<(String, String)>> {
+            let path = format!("test-code/deprecation/{}.monoteny", file);
+            let source = std::fs::read_to_string(&path).unwrap();
+            let module = runtime.load_file_as_module(&PathBuf::from(&path), module_name(file))?;
+            Ok(module.warnings.iter().map(|warning| {
+                assert_eq!(warning.code, Some(ErrorCode::Deprecated));
+                (warning.title.clone(), source[warning.range.clone().unwrap()].to_string())
+            }).collect_vec())
+        };
+
+        let old_greeting = "Function deprecation.library.old_greeting is deprecated: Use greeting instead.".to_string();
+        assert_eq!(warnings("calls")?, vec![
+            (old_greeting.clone(), "old_greeting()".to_string()),
+            (old_greeting.clone(), "old_greeting()".to_string()),
+        ]);
+        assert_eq!(warnings("included")?, vec![(old_greeting.clone(), "old_greeting()".to_string())]);
+        assert_eq!(warnings("allowed")?, vec![]);
+
+        let traits = warnings("traits")?;
+        let titles = traits.iter().map(|(title, _)| title.as_str()).collect_vec();
+        assert_eq!(titles, vec![
+            "Trait Extent is deprecated: Use Size instead.",
+            "Trait Describable is deprecated: Conform to ToString instead.",
+            "Trait Extent is deprecated: Use Size instead.",
+        ]);
+        assert!(traits[1].1.starts_with("declare Float32 is Describable"));
+        assert_eq!(traits[2].1, "Extent");
+
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_conformances() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("traits", PathBuf::from("test-code"));
+
+        // Returns the transpiled python and the notes of the duplicate conformance warning.
+        let mut transpile = |file: &str| -> RResult<(String, Vec<String>)> {
+            let module = runtime.load_file_as_module(&PathBuf::from(format!("test-code/traits/duplicates/{}.monoteny", file)), module_name("main"))?;
+            let [warning] = &module.warnings[..] else {
+                panic!("Expected one warning, got {:?}", module.warnings)
+            };
+            assert_eq!(warning.title, "Conformance of Int32 to Scaled is declared more than once.");
+
+            let context = transpiler::python::Context::new(&runtime);
+            let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+            // Without constant folding, so the conformance's function isn't substituted.
+            let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::with_optimization_level(1), "main")?;
+            Ok((file_map["main.py"].to_string(), warning.notes.iter().map(|note| note.title.clone()).collect_vec()))
+        };
+
+        // The first import wins.
+        let (py_file, notes) = transpile("imports")?;
+        assert!(py_file.contains("self * int32(2)") && !py_file.contains("self * int32(3)"));
+        assert_eq!(notes, vec!["The declaration from traits.duplicates.first is used.", "The declaration from traits.duplicates.second is ignored."]);
+
+        // The module's own declaration wins over imports.
+        let (py_file, notes) = transpile("own")?;
+        assert!(py_file.contains("self * int32(10)") && !py_file.contains("self * int32(2)"));
+        assert_eq!(notes, vec!["The declaration from main is used.", "The declaration from traits.duplicates.first is ignored."]);
+
+        // Within a module, the later declaration wins.
+        let (py_file, notes) = transpile("same_module")?;
+        assert!(py_file.contains("self * int32(5)") && !py_file.contains("self * int32(4)"));
+        assert_eq!(notes, vec!["The declaration from main is used.", "The declaration from main is ignored."]);
+
+        let errors = runtime.load_file_as_module(&PathBuf::from("test-code/traits/duplicates/strict.monoteny"), module_name("main")).err().unwrap();
+        assert_eq!(errors[0].title, "Conformance of Int32 to Scaled is declared more than once.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn included_conformance_conflict() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("traits", PathBuf::from("test-code"));
+
+        let errors = runtime.load_file_as_module(&PathBuf::from("test-code/traits/conflicts/included.monoteny"), module_name("main")).err().unwrap();
+        let conflict = &errors[0].notes[0].notes[0];
+        assert_eq!(conflict.code, Some(ErrorCode::ConflictingConformance));
+
+        // Only the rules matching Int32 are listed, each with where it came from.
+        let [rules] = &conflict.notes[..] else {
+            panic!("Expected one note, got {:?}", conflict.notes)
+        };
+        assert_eq!(rules.title, "2 matching rule(s).");
+        let rules = rules.notes.iter()
+            .map(|rule| (rule.title.as_str(), rule.notes.iter().map(|note| note.title.as_str()).collect_vec()))
+            .sorted()
+            .collect_vec();
+        assert_eq!(rules, vec![
+            ("$Number is Named, declared in traits.conflicts.numbers", vec!["Included through traits.conflicts.outer -> traits.conflicts.mid -> traits.conflicts.numbers."]),
+            ("Int32 is Named, declared in traits.conflicts.ints", vec![]),
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_modules() -> RResult<()> {
+        let directory = std::env::temp_dir().join("monoteny_missing_modules");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("shapes")).unwrap();
+        std::fs::write(directory.join("shapes/square.monoteny"), "").unwrap();
+        // Not valid UTF-8, so the file exists but can't be read as source.
+        std::fs::write(directory.join("shapes/binary.monoteny"), [0xff, 0xfe]).unwrap();
+
+        let load = |source: &str| {
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("shapes", directory.clone());
+            let path = directory.join("main.monoteny");
+            std::fs::write(&path, source).unwrap();
+            Ok::<_, Vec<RuntimeError>>(runtime.load_file_as_module(&path, module_name("main")).err().unwrap().remove(0))
+        };
+
+        let source = "use!(module!(\"shapes.squares\"));";
+        let error = load(source)?;
+        let searched = directory.join("shapes/squares.monoteny");
+        assert_eq!(error.title, format!("module `shapes.squares` not found; searched: {}", searched.display()));
+        assert_eq!(&source[error.range.unwrap()], "module!(\"shapes.squares\")");
+        assert_eq!(error.notes[0].title, "Did you mean `shapes.square`?");
+
+        let error = load("include!(module!(\"shape.square\"));")?;
+        assert_eq!(error.title, "module `shape.square` not found; no repository is named `shape`.");
+        assert_eq!(error.notes[0].title, "Did you mean `shapes.square`?");
+
+        let error = load("use!(module!(\"shapes.circle\"));")?;
+        assert!(error.notes.is_empty());
+
+        let error = load("use!(module!(\"shapes.binary\"));")?;
+        assert!(error.title.starts_with("Error loading") && error.title.ends_with("stream did not contain valid UTF-8"));
+        assert_eq!(error.range, Some(5..29));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn enum_errors() -> RResult<()> {
+        let load = |source: &str| {
+            let mut runtime = Runtime::new_cached()?;
+            Ok::<_, Vec<RuntimeError>>(runtime.load_text_as_module(source, module_name("main")).err().unwrap().remove(0))
+        };
+
+        let source = "enum Color { Red, Green, Red };";
+        let error = load(source)?;
+        assert_eq!(error.title, "Variant Red is declared more than once.");
+        assert_eq!(error.range, Some(25..28));
+        assert_eq!(error.notes[0].range, Some(13..16));
+
+        let error = load("enum Color {};")?;
+        assert_eq!(error.title, "Enum Color must declare at least one variant.");
+
+        let source = "enum Color { Red, Green, Blue }; def f(color 'Color) -> Int32 :: match color :: { Green :: 1 };";
+        let error = load(source)?;
+        assert_eq!(error.title, "match over Color does not cover Red, Blue.");
+        assert_eq!(error.range, Some(65..94));
+
+        // The default arm covers the rest.
+        let mut runtime = Runtime::new_cached()?;
+        runtime.load_text_as_module("enum Color { Red, Green }; def f(color 'Color) -> Int32 :: match color :: { Green :: 1, _ :: 2 };", module_name("main"))?;
+
+        let source = "enum Color { Red }; def f(color 'Color) -> Int32 :: match color :: { Red :: 1, Red :: 2 };";
+        let error = load(source)?;
+        assert_eq!(error.title, "Variant Red is matched more than once.");
+        assert_eq!(error.range, Some(79..82));
+        assert_eq!(error.notes[0].range, Some(69..72));
+
+        let error = load("enum Color { Red }; def f(color 'Color) -> Int32 :: match color :: { Blue :: 1 };")?;
+        assert_eq!(error.title, "Enum Color has no variant Blue.");
+
+        let error = load("enum Color { Red }; def f(color 'Color) -> Int32 :: match color :: { _ :: 1, Red :: 2 };")?;
+        assert_eq!(error.title, "Arms after the default arm are never matched.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn type_argument_errors() -> RResult<()> {
+        let load = |source: &str| {
+            let mut runtime = Runtime::new_cached()?;
+            Ok::<_, Vec<RuntimeError>>(runtime.load_text_as_module(source, module_name("main")).err().unwrap().remove(0))
+        };
+
+        let source = "def f(list 'List<Int32>>) :: {};";
+        let error = load(source)?;
+        assert_eq!(error.title, "Expected the end of the type.");
+        assert_eq!(error.range, Some(23..24));
+
+        let source = "def f(value 'Int32<Int32>) :: {};";
+        let error = load(source)?;
+        assert_eq!(error.title, "Int32 expects 0 type arguments, but got 1.");
+        assert_eq!(error.range, Some(13..18));
+
+        let error = load("def f(list 'List<Int32) :: {};")?;
+        assert_eq!(error.title, "Expected > after the type argument.");
+
+        let error = load("trait Pair { let first '#A; let second '#B; }; def f(pair 'Pair<Int32>) :: {};")?;
+        assert_eq!(error.title, "Pair expects 2 type arguments, but got 1.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn analyze() -> RResult<()> {
+        let paths = [PathBuf::from("test-code/query/types.monoteny"), PathBuf::from("test-code/differential/enums.monoteny")];
+        let program = analysis::analyze(&paths)?;
+        let entries = program.modules().filter(|module| module.is_entry()).collect_vec();
+        assert_eq!(entries.iter().map(|module| module.name()).collect_vec(), vec!["types", "enums"]);
+        assert_eq!(entries[0].path(), Some(paths[0].as_path()));
+
+        let functions = entries[0].functions().collect_vec();
+        let square = functions.iter().find(|function| function.name() == "square").unwrap();
+        assert_eq!(square.parameters().map(|parameter| format!("{}: {}", parameter.name, parameter.type_)).collect_vec(), vec!["value: Float32"]);
+        assert_eq!(square.return_type().to_string(), "Float32");
+        assert!(square.is_pure());
+        let (module, range) = square.declaration().unwrap();
+        assert_eq!(module.name(), "types");
+        let source = std::fs::read_to_string(&paths[0]).unwrap();
+        assert!(source[range].starts_with("def square("));
+
+        let main = functions.iter().find(|function| function.name() == "main").unwrap();
+        assert!(!main.is_pure());
+        let call = main.calls().into_iter().find(|call| call.callee() == square.id()).unwrap();
+        assert_eq!(call.result_type().unwrap().to_string(), "Float32");
+        assert_eq!(&source[call.range().unwrap()], "square(3.5)");
+        assert_eq!(main.type_at(source.find("count)").unwrap()).unwrap().to_string(), "Int32");
+
+        let conformances = entries[1].conformances().map(|conformance| conformance.to_string()).collect_vec();
+        // Functions conform to Function implicitly.
+        assert_eq!(conformances[0], "Eq<Self: Color>");
+        assert!(conformances.contains(&"Function<Self: describe>".to_string()));
+
+        Ok(())
+    }
+
+    /// Reports each function with the name, at the level.
+    struct NamedFunction(&'static str, Level);
+
+    impl Lint for NamedFunction {
+        fn name(&self) -> &'static str {
+            "named_function"
+        }
+
+        fn run(&self, function: &FunctionImplementation, context: &LintContext) -> Vec<RuntimeError> {
+            match context.runtime.source.fn_representations[&function.head].name == self.0 {
+                true => RuntimeError::new(self.1, format!("Function is named {}.", self.0).as_str()).to_array(),
+                false => vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn custom_lints() -> RResult<()> {
+        let paths = [PathBuf::from("test-code/query/types.monoteny")];
+        const NAMED_FUNCTION: LintCode = LintCode { prefix: "acme", name: "named_function" };
+        let lint = |level| CustomLint { code: &NAMED_FUNCTION, lint: Arc::new(NamedFunction("square", level)) };
+
+        let program = analysis::analyze_with_lints(&paths, vec![lint(Level::Warning)])?;
+        let entry = program.modules().find(|module| module.is_entry()).unwrap();
+        let [warning] = entry.warnings() else {
+            panic!("Expected one warning, got {:?}", entry.warnings())
+        };
+        assert_eq!(warning.title, "Function is named square.");
+        assert_eq!(warning.code, Some(ErrorCode::Lint(&LintCode { prefix: "acme", name: "named_function" })));
+        assert_eq!(warning.path.as_deref(), Some(paths[0].as_path()));
+
+        let errors = analysis::analyze_with_lints(&paths, vec![lint(Level::Error)]).err().unwrap();
+        assert_eq!(errors[0].title, "Function is named square.");
+        assert_eq!(errors[0].code.unwrap().to_string(), "acme::named_function");
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolation_positions() -> RResult<()> {
+        let path = "test-code/grammar/interpolation_positions.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        let errors = runtime.load_file_as_module(&PathBuf::from(path), module_name("main")).err().unwrap();
+
+        // The title, line, column and source text of each error, in order of their position.
+        let errors = errors.iter()
+            .map(|error| {
+                let range = error.range.clone().unwrap();
+                let before = &source[..range.start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.rsplit('\n').next().unwrap().chars().count() + 1;
+                (error.title.clone(), line, column, source[range].to_string())
+            })
+            .sorted_by_key(|(_, line, column, _)| (*line, *column))
+            .collect_vec();
+        assert_eq!(errors, vec![
+            ("Cannot find 'missing' in this scope".to_string(), 10, 35, "missing".to_string()),
+            ("Cannot format type Point in string interpolation.".to_string(), 13, 19, "point".to_string()),
+            (errors[2].0.clone(), 15, 59, "(point, 2)".to_string()),
+        ]);
+        assert!(errors[2].0.starts_with("function format(_ 'Point, "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_precedence_group() -> RResult<()> {
+        let path = "test-code/grammar/missing_precedence_group.monoteny";
+        let source = std::fs::read_to_string(path).unwrap();
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("grammar", PathBuf::from("test-code"));
+        let errors = runtime.load_file_as_module(&PathBuf::from(path), module_name("main")).err().unwrap();
+
+        assert_eq!(errors[0].title, "Pattern '<>' belongs to precedence group AdditionPrecedence, which is not in the precedence order.");
+        assert_eq!(errors[0].code, Some(ErrorCode::MissingPrecedenceGroup));
+        assert_eq!(&source[errors[0].range.clone().unwrap()], "module!(\"grammar.precedence.operators\")");
+        assert_eq!(errors[0].notes[0].title, "The pattern is declared in grammar.precedence.operators.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn statements_in_declarations() -> RResult<()> {
+        let trait_error = "Statement not allowed inside a trait declaration; traits may only declare functions and fields.";
+        let conformance_error = "Statement not allowed inside a conformance declaration; conformances may only declare functions.";
+        let cases = [
+            ("trait Animal { write_line(\"Hello\"); };", "write_line(\"Hello\");", trait_error),
+            ("trait Animal { return; };", "return;", trait_error),
+            ("trait Animal { trait Dog {}; };", "trait Dog {};", trait_error),
+            ("trait Animal { declare Int32 is Animal :: {}; };", "declare Int32 is Animal :: {};", trait_error),
+            ("trait Animal {}; declare Int32 is Animal :: { let x 'Int32 = 1; };", "let x 'Int32 = 1;", conformance_error),
+            ("trait Animal {}; declare Int32 is Animal :: { write_line(\"Hello\"); };", "write_line(\"Hello\");", conformance_error),
+            ("trait Animal {}; declare Int32 is Animal :: { trait Dog {}; };", "trait Dog {};", conformance_error),
+            ("trait Animal { def (self 'Self).talk() -> String; }; declare Int32 is Animal :: { def (self 'Self).talk() -> String; };", "def (self 'Self).talk() -> String;", "Function (self 'Int32).talk() -> String must have a body to implement the conformance."),
+        ];
+
+        for (code, statement, title) in cases {
+            let source = format!("use!(module!(\"common\"));\n\n{}\n", code);
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+            let errors = runtime.load_text_as_module(&source, module_name("main")).err().unwrap();
+
+            assert_eq!(errors[0].title, title, "in {}", code);
+            let range = errors[0].range.clone().unwrap();
+            assert_eq!(source[range].trim_end_matches(';'), statement.trim_end_matches(';'), "in {}", code);
+        }
+
+        Ok(())
+    }
+
+    /// Resolves a body with many member calls, to measure how resolution scales with the size of a body.
+    #[test]
+    fn many_member_calls() -> RResult<()> {
+        let calls = "    point.x;\n".repeat(2_000);
+        let source = format!("use!(module!(\"common\"));\n\ntrait Point {{\n    let x 'Int32;\n}};\n\ndef touch(point 'Point) :: {{\n{}}};\n", calls);
+
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.get_or_load_module(&module_name("common"))?;
+        runtime.load_text_as_module(&source, module_name("main"))?;
+
+        Ok(())
+    }
+
+    /// Calls that stay ambiguous fail, even if attempting them binds types that are already bound.
+    /// Without strict!(no_implicit_number_defaults), the literals default to Int64 instead.
+    #[test]
+    fn unresolvable_single_candidate() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+
+        let source = "use!(module!(\"common\"));\n\ndef main! :: {\n    var count = 1;\n    let doubled = count * 2;\n};\n";
+        runtime.load_text_as_module(source, module_name("main"))?;
+
+        let source = format!("strict!(no_implicit_number_defaults);\n{}", source);
+        let errors = runtime.load_text_as_module(&source, module_name("strict_main")).err().unwrap();
+        assert_eq!(errors[0].title, "The literal's type is ambiguous.");
+        assert_eq!(&source[errors[0].range.clone().unwrap()], "1");
+        assert_eq!(errors[0].notes[0].title, "strict!(no_implicit_number_defaults) denies defaulting it to Int64.");
+
+        Ok(())
+    }
+
+    /// Deeply nested types are walked without recursion, and rejected cleanly by inference.
+    #[test]
+    fn deeply_nested_type() -> RResult<()> {
+        let runtime = Runtime::new_cached()?;
+        let traits = runtime.traits.as_ref().unwrap();
+        let string = TypeProto::unit_struct(&traits.String);
+        let generic = Uuid::new_v4();
+
+        let mut type_ = TypeProto::unit(TypeUnit::Generic(generic));
+        for _ in 0..10_000 {
+            type_ = TypeProto::one_arg(&traits.List, type_);
+        }
+        assert_eq!(type_.depth(), 10_001);
+        assert!(!type_.contains_struct(&traits.String));
+
+        let replaced = type_.replacing_generics(&HashMap::from([(generic, string.clone())]));
+        assert!(replaced.contains_struct(&traits.String));
+        let replaced = replaced.replacing_structs(&HashMap::from([(Arc::clone(&traits.String), TypeProto::unit(TypeUnit::Generic(generic)))]));
+        assert_eq!(TypeProto::collect_generics([&replaced].into_iter()), HashSet::from([generic]));
+
+        let mut types = TypeForest::new();
+        let errors = types.bind(Uuid::new_v4(), &type_).unwrap_err();
+        assert_eq!(errors[0].title, "Type too deeply nested; at most 1000 levels are supported.");
+
+        // Each binding is shallow, but together they nest too deeply.
+        // They are bound from the innermost out, so no binding needs to be updated later.
+        let aliases = (0..1_100).map(|_| Uuid::new_v4()).collect_vec();
+        for (alias, argument) in aliases.iter().tuple_windows().collect_vec().into_iter().rev() {
+            types.bind(*alias, &TypeProto::one_arg(&traits.List, TypeProto::unit(TypeUnit::Generic(*argument))))?;
+        }
+        assert_eq!(types.resolve_binding_alias(&aliases[0]).unwrap_err()[0].title, "Type too deeply nested; at most 1000 levels are supported.");
+
+        // Normal depths are unaffected.
+        let alias = Uuid::new_v4();
+        types.bind(alias, &TypeProto::one_arg(&traits.List, string.clone()))?;
+        assert_eq!(types.resolve_binding_alias(&alias)?, TypeProto::one_arg(&traits.List, string));
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use display_with_options::with_options;
 use itertools::Itertools;
@@ -12,7 +12,7 @@ use crate::program::function_object::{FunctionCallExplicity, FunctionRepresentat
 use crate::program::functions::{FunctionHead, FunctionInterface, Parameter, ParameterKey};
 use crate::program::global::{FunctionLogic, FunctionLogicDescriptor};
 use crate::program::traits::{Trait, TraitBinding, TraitConformance, TraitConformanceRule};
-use crate::program::types::TypeProto;
+use crate::program::types::{TypeProto, TypeUnit};
 use crate::resolver::{fields, scopes};
 use crate::resolver::global::GlobalResolver;
 use crate::resolver::interface::resolve_function_interface;
@@ -22,15 +22,15 @@ use crate::source::StructInfo;
 pub struct TraitResolver<'a> {
     pub runtime: &'a Runtime,
     pub trait_: &'a mut Trait,
-    pub generic_self_type: Rc<TypeProto>,
+    pub generic_self_type: Arc<TypeProto>,
 }
 
 impl <'a> TraitResolver<'a> {
-    pub fn resolve_statement(&mut self, statement: &'a ast::Statement, requirements: &HashSet<Rc<TraitBinding>>, generics: &HashMap<String, Rc<Trait>>, scope: &scopes::Scope) -> RResult<()> {
+    pub fn resolve_statement(&mut self, statement: &'a ast::Statement, requirements: &HashSet<Arc<TraitBinding>>, generics: &HashMap<String, Arc<Trait>>, scope: &scopes::Scope) -> RResult<()> {
         match statement {
             ast::Statement::FunctionDeclaration(syntax) => {
-                let (fun, representation) = resolve_function_interface(&syntax.interface, &scope, None, &self.runtime, requirements, generics)?;
-                if !syntax.body.is_none() {
+                let (fun, representation) = resolve_function_interface(&syntax.interface, scope, None, self.runtime, requirements, generics)?;
+                if syntax.body.is_some() {
                     return Err(
                         RuntimeError::error(format!("Abstract function {:?} cannot have a body.", with_options(fun.as_ref(), &representation)).as_str()).to_array()
                     );
@@ -39,7 +39,7 @@ impl <'a> TraitResolver<'a> {
                 self.trait_.insert_function(fun, representation);
             }
             ast::Statement::VariableDeclaration { mutability, identifier, type_declaration, assignment } => {
-                if let Some(_) = assignment {
+                if assignment.is_some() {
                     return Err(
                         RuntimeError::error("Trait variables cannot have defaults until default monads are supported.").to_array()
                     );
@@ -56,10 +56,19 @@ impl <'a> TraitResolver<'a> {
                     );
                 };
 
-                let mut type_factory = TypeFactory::new(scope, &self.runtime);
+                // Generics (e.g. '#A) are shared by all fields, and become generics of the trait.
+                let mut type_factory = TypeFactory::new(scope, self.runtime);
+                type_factory.generics = self.trait_.generics.clone();
 
                 let variable_type = type_factory.resolve_type(type_declaration, true)?;
 
+                if !type_factory.requirements.is_empty() {
+                    return Err(
+                        RuntimeError::error("Trait variables cannot have requirements.").to_array()
+                    );
+                }
+                self.trait_.generics.extend(type_factory.generics);
+
                 if TypeProto::contains_generics([&variable_type].into_iter()) {
                     return Err(
                         RuntimeError::error(format!("Variables cannot be generic: {}", identifier).as_str()).to_array()
@@ -70,10 +79,11 @@ impl <'a> TraitResolver<'a> {
                     identifier,
                     &self.generic_self_type,
                     &variable_type,
+                    &Default::default(),
                     true,
                     mutability == &Mutability::Mutable,
                 );
-                fields::add_to_trait(&mut self.trait_, field);
+                fields::add_to_trait(self.trait_, field);
             }
             _ => {
                 if let ast::Statement::Expression(exp) = statement {
@@ -81,16 +91,33 @@ impl <'a> TraitResolver<'a> {
                     exp.no_errors()?;
                 }
                 return Err(
-                    RuntimeError::error("Statement not valid in a trait context.").to_array()
+                    RuntimeError::error("Statement not allowed inside a trait declaration; traits may only declare functions and fields.").to_array()
                 );
             }
         }
 
         Ok(())
     }
+
+    /// Resolves requirements like `#T is Ord`, for the generics of the fields declared before.
+    pub fn resolve_requirement(&mut self, requirement: &ast::Requirement, scope: &scopes::Scope) -> RResult<()> {
+        let mut type_factory = TypeFactory::new(scope, self.runtime);
+        type_factory.generics = self.trait_.generics.clone();
+
+        type_factory.resolve_requirement(requirement)?;
+
+        if let Some(name) = type_factory.generics.keys().find(|name| !self.trait_.generics.contains_key(*name)) {
+            return Err(
+                RuntimeError::error(format!("{} is not a generic of any field.", name).as_str()).to_array()
+            );
+        }
+        self.trait_.struct_requirements.extend(type_factory.requirements);
+
+        Ok(())
+    }
 }
 
-pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RResult<Option<Rc<StructInfo>>> {
+pub fn try_make_struct(trait_: &Arc<Trait>, resolver: &mut GlobalResolver) -> RResult<Option<Arc<StructInfo>>> {
     let mut unaccounted_for_abstract_functions: HashSet<_> = trait_.abstract_functions.keys().collect();
     trait_.field_hints.iter().for_each(|hint| {
         [&hint.getter, &hint.setter].into_iter().flatten().map(|g| unaccounted_for_abstract_functions.remove(g)).collect_vec();
@@ -106,13 +133,30 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
 
     // Can be instantiated as a struct!
 
-    let struct_type = TypeProto::unit_struct(trait_);
+    // Generic fields make the struct generic, with its arguments in order of appearance.
+    let mut generics = HashMap::new();
+    let mut generic_arguments = vec![];
+    for abstract_field in trait_.field_hints.iter() {
+        for (name, generic) in trait_.generics.iter() {
+            if name != "Self" && !generics.contains_key(name) && abstract_field.type_.contains_struct(generic) {
+                generics.insert(name.clone(), Arc::clone(generic));
+                generic_arguments.push(TypeProto::unit_struct(generic));
+            }
+        }
+    }
+
+    let struct_type = Arc::new(TypeProto {
+        unit: TypeUnit::Struct(Arc::clone(trait_)),
+        arguments: generic_arguments,
+    });
     let mut function_mapping = HashMap::new();
     let mut parameters = vec![
         Parameter {
             external_key: ParameterKey::Positional,
             internal_name: "type".to_string(),
-            type_: TypeProto::one_arg(&resolver.runtime.Metatype, struct_type.clone()),
+            // Like List.new(), the metatype doesn't carry the generic arguments.
+            type_: TypeProto::one_arg(&resolver.runtime.Metatype, TypeProto::unit_struct(trait_)),
+            mutability: Mutability::Immutable,
         }
     ];
     let mut fields = vec![];
@@ -123,28 +167,29 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
             &abstract_field.name,
             &struct_type,
             &abstract_field.type_,
+            &generics,
             abstract_field.getter.is_some(),
             abstract_field.setter.is_some(),
         );
 
-        // TODO Once generic types are supported, the variable type should be mapped to actual types
         if let Some(abstract_getter) = &abstract_field.getter {
             let struct_getter = struct_field.getter.clone().unwrap();
-            function_mapping.insert(Rc::clone(abstract_getter), Rc::clone(&struct_getter));
-            field_getters.insert(Rc::clone(&variable_as_object), struct_getter);
+            function_mapping.insert(Arc::clone(abstract_getter), Arc::clone(&struct_getter));
+            field_getters.insert(Arc::clone(&variable_as_object), struct_getter);
         }
         if let Some(abstract_setter) = &abstract_field.setter {
             let struct_setter = struct_field.setter.clone().unwrap();
-            function_mapping.insert(Rc::clone(abstract_setter), Rc::clone(&struct_setter));
-            field_setters.insert(Rc::clone(&variable_as_object), struct_setter);
+            function_mapping.insert(Arc::clone(abstract_setter), Arc::clone(&struct_setter));
+            field_setters.insert(Arc::clone(&variable_as_object), struct_setter);
         }
 
         parameters.push(Parameter {
             external_key: ParameterKey::Name(abstract_field.name.clone()),
             internal_name: abstract_field.name.clone(),
             type_: abstract_field.type_.clone(),
+            mutability: Mutability::Immutable,
         });
-        field_names.insert(Rc::clone(&variable_as_object), abstract_field.name.clone());
+        field_names.insert(Arc::clone(&variable_as_object), abstract_field.name.clone());
         fields.push(variable_as_object);
     }
 
@@ -156,14 +201,14 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
     resolver.module.trait_conformance.add_conformance_rule(conformance_rule.clone());
     resolver.global_variables.trait_conformance.add_conformance_rule(conformance_rule);
 
-    let struct_ = Rc::new(StructInfo {
-        trait_: Rc::clone(trait_),
+    let struct_ = Arc::new(StructInfo {
+        trait_: Arc::clone(trait_),
         constructor: FunctionHead::new_static(
-            Rc::new(FunctionInterface {
+            Arc::new(FunctionInterface {
                 parameters,
                 return_type: struct_type,
-                requirements: Default::default(),
-                generics: Default::default(),
+                requirements: trait_.struct_requirements.clone(),
+                generics,
             }),
         ),
         fields,
@@ -172,12 +217,13 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
         field_setters,
     });
 
+    resolver.runtime.source.struct_by_trait.insert(Arc::clone(trait_), Arc::clone(&struct_));
     resolver.runtime.source.fn_logic.insert(
-        Rc::clone(&struct_.constructor),
-        FunctionLogic::Descriptor(FunctionLogicDescriptor::Constructor(Rc::clone(&struct_)))
+        Arc::clone(&struct_.constructor),
+        FunctionLogic::Descriptor(FunctionLogicDescriptor::Constructor(Arc::clone(&struct_)))
     );
     resolver.add_function_interface(
-        Rc::clone(&struct_.constructor),
+        Arc::clone(&struct_.constructor),
         FunctionRepresentation::new("call_as_function", FunctionTargetType::Member, FunctionCallExplicity::Explicit),
     )?;
 
@@ -185,11 +231,11 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
         let name = &struct_.field_names[ref_];
 
         resolver.runtime.source.fn_logic.insert(
-            Rc::clone(head),
-            FunctionLogic::Descriptor(FunctionLogicDescriptor::GetMemberField(Rc::clone(&struct_), Rc::clone(ref_)))
+            Arc::clone(head),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::GetMemberField(Arc::clone(&struct_), Arc::clone(ref_)))
         );
         resolver.add_function_interface(
-            Rc::clone(head),
+            Arc::clone(head),
             FunctionRepresentation::new(name, FunctionTargetType::Member, FunctionCallExplicity::Implicit),
         )?;
     }
@@ -198,11 +244,11 @@ pub fn try_make_struct(trait_: &Rc<Trait>, resolver: &mut GlobalResolver) -> RRe
         let name = &struct_.field_names[ref_];
 
         resolver.runtime.source.fn_logic.insert(
-            Rc::clone(&head),
-            FunctionLogic::Descriptor(FunctionLogicDescriptor::SetMemberField(Rc::clone(&struct_), Rc::clone(ref_)))
+            Arc::clone(head),
+            FunctionLogic::Descriptor(FunctionLogicDescriptor::SetMemberField(Arc::clone(&struct_), Arc::clone(ref_)))
         );
         resolver.add_function_interface(
-            Rc::clone(head),
+            Arc::clone(head),
             FunctionRepresentation::new(name, FunctionTargetType::Member, FunctionCallExplicity::Implicit),
         )?;
     }
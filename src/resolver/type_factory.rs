@@ -1,23 +1,27 @@
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::sync::Arc;
+use std::vec::IntoIter;
 
 use itertools::Itertools;
 
 use crate::ast;
-use crate::error::{ErrInRange, RResult, RuntimeError};
+use crate::error::{ErrInRange, RResult, RuntimeError, TryCollectMany};
 use crate::interpreter::runtime::Runtime;
 use crate::parser::expressions;
 use crate::program::function_object::FunctionTargetType;
+use crate::program::functions::FunctionHead;
 use crate::program::traits::{Trait, TraitBinding};
 use crate::program::types::{TypeProto, TypeUnit};
-use crate::resolver::scopes;
+use crate::resolver::{anonymous_structs, scopes};
 
 pub struct TypeFactory<'a> {
     pub runtime: &'a Runtime,
     pub scope: &'a scopes::Scope<'a>,
 
-    pub generics: HashMap<String, Rc<Trait>>,
-    pub requirements: HashSet<Rc<TraitBinding>>,
+    pub generics: HashMap<String, Arc<Trait>>,
+    pub requirements: HashSet<Arc<TraitBinding>>,
 }
 
 // TODO Essentially this is a form of mini interpreter.
@@ -32,42 +36,105 @@ impl <'a> TypeFactory<'a> {
         }
     }
 
-    pub fn resolve_trait(&mut self, name: &str) -> RResult<Rc<Trait>> {
-        let reference = self.scope.resolve(FunctionTargetType::Global, &name)?;
-        let overload = reference.as_function_overload()?;
+    pub fn resolve_trait(&mut self, name: &str) -> RResult<Arc<Trait>> {
+        let function = self.resolve_trait_getter(name)?;
+        let trait_ = self.runtime.source.trait_references.get(&function)
+            .ok_or_else(|| RuntimeError::error(format!("Interpreted types aren't supported yet; please use an explicit type for now.\n{}", name).as_str()).to_array())?;
 
+        Ok(Arc::clone(trait_))
+    }
+
+    fn resolve_namespaced_trait(&mut self, namespace: &str, name: &str) -> RResult<Arc<Trait>> {
+        let reference = self.scope.resolve_in_namespace(namespace, name)
+            .unwrap_or_else(|| Err(RuntimeError::error(format!("Cannot find namespace '{}' in this scope", namespace).as_str()).to_array()))?;
+        let overload = reference.as_function_overload()?;
         let function = overload.functions.iter().exactly_one()
             .map_err(|_| RuntimeError::error("Function overload cannot be resolved to a type.").to_array())?;
-        let trait_ = self.runtime.source.trait_references.get(function)
-            .ok_or_else(|| RuntimeError::error(format!("Interpreted types aren't supported yet; please use an explicit type for now.\n{}", name).as_str()).to_array())?;
 
-        return Ok(Rc::clone(trait_))
+        self.runtime.source.trait_references.get(function)
+            .map(Arc::clone)
+            .ok_or_else(|| RuntimeError::error(format!("{}.{} is not a type.", namespace, name).as_str()).to_array())
+    }
+
+    fn resolve_trait_getter(&mut self, name: &str) -> RResult<Arc<FunctionHead>> {
+        let reference = self.scope.resolve(FunctionTargetType::Global, name)?;
+        let overload = reference.as_function_overload()?;
+
+        overload.functions.iter().exactly_one()
+            .map(Arc::clone)
+            .map_err(|_| RuntimeError::error("Function overload cannot be resolved to a type.").to_array())
     }
 
-    fn register_generic(&mut self, name: &str) -> Rc<Trait> {
-        let trait_ = Rc::new(Trait::new_flat(name));
-        self.generics.insert(name.to_string(), Rc::clone(&trait_));
+    fn register_generic(&mut self, name: &str) -> Arc<Trait> {
+        let trait_ = Arc::new(Trait::new_flat(name));
+        self.generics.insert(name.to_string(), Arc::clone(&trait_));
         trait_
     }
 
-    fn register_requirement(&mut self, requirement: Rc<TraitBinding>) {
+    fn register_requirement(&mut self, requirement: Arc<TraitBinding>) {
         self.requirements.insert(requirement);
     }
 
-    pub fn resolve_type(&mut self, syntax: &ast::Expression, allow_anonymous_generics: bool) -> RResult<Rc<TypeProto>> {
+    /// Registers a requirement like `#T is Ord`, creating the generic if it doesn't exist yet.
+    pub fn resolve_requirement(&mut self, syntax: &ast::Requirement) -> RResult<()> {
+        let subject = self.resolve_type(&syntax.subject, true)?;
+        if !self.generics.values().any(|generic| subject.unit == TypeUnit::Struct(Arc::clone(generic))) {
+            return Err(RuntimeError::error(format!("Requirements can only be declared for generics, not {:?}.", subject).as_str()).to_array())
+        }
+
+        syntax.trait_.no_errors()?;
+        let parsed = expressions::parse(&syntax.trait_, &self.scope.grammar)?;
+        let expressions::Value::Identifier(name) = &parsed.value else {
+            return Err(RuntimeError::error("Expected the name of a trait.").in_range(parsed.position).to_array())
+        };
+        let trait_ = self.resolve_trait(name).err_in_range(&parsed.position)?;
+        let Some(self_generic) = trait_.generics.get("Self") else {
+            return Err(RuntimeError::error(format!("{} cannot be required, because it has no Self.", trait_.name).as_str()).in_range(parsed.position).to_array())
+        };
+
+        self.register_requirement(Arc::new(TraitBinding {
+            generic_to_type: HashMap::from([(Arc::clone(self_generic), subject)]),
+            trait_,
+        }));
+        Ok(())
+    }
+
+    pub fn resolve_type(&mut self, syntax: &ast::Expression, allow_anonymous_generics: bool) -> RResult<Arc<TypeProto>> {
         syntax.no_errors()?;
 
+        if let Some(fields) = anonymous_structs::fields(syntax) {
+            return self.resolve_anonymous_struct(&fields, allow_anonymous_generics)
+        }
+
+        if let Some(tokens) = type_argument_tokens(syntax) {
+            let mut tokens = tokens.into_iter().peekable();
+            let type_ = self.resolve_type_with_arguments(&mut tokens, allow_anonymous_generics)?;
+            if let Some((_, position)) = tokens.next() {
+                return Err(RuntimeError::error("Expected the end of the type.").in_range(position).to_array())
+            }
+            return Ok(type_)
+        }
+
         let parsed = expressions::parse(syntax, &self.scope.grammar)?;
 
+        // A trait re-exported under a namespace, like 'math.Vector.
+        if let expressions::Value::MemberAccess(target, name) = &parsed.value {
+            if let expressions::Value::Identifier(namespace) = &target.value {
+                return self.resolve_namespaced_trait(namespace, name)
+                    .map(|trait_| TypeProto::unit_struct(&trait_))
+                    .err_in_range(&parsed.position)
+            }
+        }
+
         let expressions::Value::Identifier(identifier) = &parsed.value else {
             return Err(RuntimeError::error("Interpreted types aren't supported yet; please use an explicit type for now.").in_range(parsed.position).to_array())
         };
 
-        self.resolve_type_by_name(allow_anonymous_generics, &identifier)
+        self.resolve_type_by_name(allow_anonymous_generics, identifier)
             .err_in_range(&parsed.position)
     }
 
-    fn resolve_type_by_name(&mut self, allow_anonymous_generics: bool, type_name: &str) -> RResult<Rc<TypeProto>> {
+    fn resolve_type_by_name(&mut self, allow_anonymous_generics: bool, type_name: &str) -> RResult<Arc<TypeProto>> {
         let arguments = vec![];
 
         if let Some(type_) = self.generics.get(type_name) {
@@ -77,28 +144,195 @@ impl <'a> TypeFactory<'a> {
         if !allow_anonymous_generics || !(type_name.starts_with("#") || type_name.starts_with("$")) {
             // No special generic; let's try just resolving it normally.
             let trait_ = self.resolve_trait(type_name)?;
+            // Self in a conformance declared for a type with arguments (e.g. List<#T>) stands for the whole type.
+            let getter = self.resolve_trait_getter(type_name)?;
+            if let [type_] = &getter.interface.return_type.arguments[..] {
+                if !type_.arguments.is_empty() {
+                    return Ok(type_.clone())
+                }
+            }
+            if allow_anonymous_generics {
+                if let Some(type_) = self.resolve_generic_struct(&trait_) {
+                    return Ok(type_)
+                }
+            }
             // Found a trait! Until we actually interpret the expression, this is guaranteed to be unbound.
             return Ok(TypeProto::unit_struct(&trait_));
         }
 
-        let type_ = Rc::new(TypeProto {
+        let type_ = Arc::new(TypeProto {
             unit: TypeUnit::Struct(self.register_generic(type_name).clone()),
             arguments
         });
 
-        if type_name.starts_with("$") {
-            let type_name = match type_name.find("#") {
-                None => { String::from(&type_name[1..]) }
-                Some(hash_start_index) => { String::from(&type_name[1..hash_start_index]) }
+        if let Some(type_name) = type_name.strip_prefix('$') {
+            let type_name = match type_name.find('#') {
+                None => type_name,
+                Some(hash_start_index) => &type_name[..hash_start_index],
             };
 
-            let requirement_trait = self.resolve_trait(&type_name)?;
-            self.register_requirement(Rc::new(TraitBinding {
-                generic_to_type: HashMap::from([(Rc::clone(&requirement_trait.generics["Self"]), type_.clone())]),
+            let requirement_trait = self.resolve_trait(type_name)?;
+            self.register_requirement(Arc::new(TraitBinding {
+                generic_to_type: HashMap::from([(Arc::clone(&requirement_trait.generics["Self"]), type_.clone())]),
                 trait_: requirement_trait,
             }));
         }
 
         Ok(type_)
     }
+
+    /// Resolves an anonymous struct type like (q 'Int64, r 'Int64), whose struct is shared by all with the same field names.
+    fn resolve_anonymous_struct(&mut self, fields: &[(&String, &ast::Expression)], allow_anonymous_generics: bool) -> RResult<Arc<TypeProto>> {
+        let names = fields.iter().map(|(name, _)| name.to_string()).collect_vec();
+        if !names.iter().all_unique() {
+            return Err(RuntimeError::error(format!("Fields of anonymous structs must have different names, but ({}) repeats one.", names.join(", ")).as_str()).to_array())
+        }
+        let Some((struct_, _)) = anonymous_structs::get(&self.runtime.source, &names) else {
+            return Err(RuntimeError::error(format!("Anonymous struct ({}) was not declared.", names.join(", ")).as_str()).to_array())
+        };
+
+        Ok(Arc::new(TypeProto {
+            unit: TypeUnit::Struct(Arc::clone(&struct_.trait_)),
+            arguments: fields.iter()
+                .map(|(_, type_)| self.resolve_type(type_, allow_anonymous_generics))
+                .try_collect_many()?,
+        }))
+    }
+
+    /// Resolves a type like `List<Int32>`, whose arguments may have arguments themselves.
+    fn resolve_type_with_arguments(&mut self, tokens: &mut Peekable<IntoIter<(TypeToken, Range<usize>)>>, allow_anonymous_generics: bool) -> RResult<Arc<TypeProto>> {
+        let (name, position) = match tokens.next() {
+            Some((TypeToken::Name(name), position)) => (name, position),
+            Some((_, position)) => return Err(RuntimeError::error("Expected a type.").in_range(position).to_array()),
+            None => return Err(RuntimeError::error("Expected a type.").to_array()),
+        };
+        if tokens.next_if(|(token, _)| *token == TypeToken::Open).is_none() {
+            return self.resolve_type_by_name(allow_anonymous_generics, &name).err_in_range(&position)
+        }
+
+        let trait_ = self.resolve_trait(&name).err_in_range(&position)?;
+        let mut arguments = vec![self.resolve_type_with_arguments(tokens, allow_anonymous_generics)?];
+        while tokens.next_if(|(token, _)| *token == TypeToken::Comma).is_some() {
+            arguments.push(self.resolve_type_with_arguments(tokens, allow_anonymous_generics)?);
+        }
+        match tokens.next() {
+            Some((TypeToken::Close, _)) => {},
+            Some((_, position)) => return Err(RuntimeError::error("Expected > after the type argument.").in_range(position).to_array()),
+            None => return Err(RuntimeError::error("Expected > after the type argument.").in_range(position).to_array()),
+        }
+
+        let expected_count = self.argument_count(&trait_);
+        if expected_count != arguments.len() {
+            return Err(RuntimeError::error(format!("{} expects {} type arguments, but got {}.", trait_.name, expected_count, arguments.len()).as_str()).in_range(position).to_array())
+        }
+
+        // The struct's where clause must hold for the arguments.
+        if let Some(struct_) = self.runtime.source.struct_by_trait.get(&trait_) {
+            let generic_map: HashMap<_, _> = struct_.constructor.interface.return_type.arguments.iter().zip(arguments.iter())
+                .map(|(generic, argument)| {
+                    let TypeUnit::Struct(generic) = &generic.unit else { panic!() };
+                    (Arc::clone(generic), Arc::clone(argument))
+                })
+                .collect();
+            for requirement in trait_.struct_requirements.iter() {
+                self.register_requirement(requirement.mapping_types(&|type_| type_.replacing_structs(&generic_map)));
+            }
+        }
+
+        Ok(Arc::new(TypeProto { unit: TypeUnit::Struct(trait_), arguments }))
+    }
+
+    /// The number of type arguments the trait's type takes.
+    fn argument_count(&self, trait_: &Arc<Trait>) -> usize {
+        let traits = self.runtime.traits.as_ref().unwrap();
+        if trait_ == &traits.List || trait_ == &traits.Dyn {
+            return 1
+        }
+
+        self.runtime.source.struct_by_trait.get(trait_)
+            .map_or(0, |struct_| struct_.constructor.interface.return_type.arguments.len())
+    }
+
+    /// Generic structs named without arguments stand for any arguments, so e.g. 'Bounds stands for Bounds<#T> with a new generic #T.
+    /// The requirements of the struct's where clause hold for any constructed struct, so they are implied.
+    fn resolve_generic_struct(&mut self, trait_: &Arc<Trait>) -> Option<Arc<TypeProto>> {
+        let struct_ = self.runtime.source.struct_by_trait.get(trait_)?;
+        let struct_type = &struct_.constructor.interface.return_type;
+        if struct_type.arguments.is_empty() {
+            return None
+        }
+
+        let mut generic_map = HashMap::new();
+        for argument in struct_type.arguments.iter() {
+            let TypeUnit::Struct(generic) = &argument.unit else { panic!() };
+            // Each mention is its own type, so it gets its own generic.
+            let name = match self.generics.contains_key(&generic.name) {
+                false => generic.name.clone(),
+                true => format!("{}{}", generic.name, self.generics.len()),
+            };
+            generic_map.insert(Arc::clone(generic), TypeProto::unit_struct(&self.register_generic(&name)));
+        }
+
+        for requirement in trait_.struct_requirements.iter() {
+            self.register_requirement(requirement.mapping_types(&|type_| type_.replacing_structs(&generic_map)));
+        }
+        Some(struct_type.replacing_structs(&generic_map))
+    }
+}
+
+#[derive(PartialEq)]
+enum TypeToken {
+    Name(String),
+    Open,
+    Close,
+    Comma,
+}
+
+/// Splits a type with arguments into its names, angle brackets and commas.
+/// Nested arguments are closed by one operator, like `>>`, so brackets are split by char.
+/// Types without arguments, and expressions that aren't made of names alone, return None.
+fn type_argument_tokens(syntax: &ast::Expression) -> Option<Vec<(TypeToken, Range<usize>)>> {
+    let mut tokens = vec![];
+    for term in syntax.iter() {
+        let ast::Term::Identifier(identifier) = &term.value else {
+            return None
+        };
+
+        if identifier == "," {
+            tokens.push((TypeToken::Comma, term.position.clone()));
+            continue
+        }
+        if !identifier.chars().all(|ch| ch == '<' || ch == '>') {
+            tokens.push((TypeToken::Name(identifier.clone()), term.position.clone()));
+            continue
+        }
+
+        for (idx, ch) in identifier.chars().enumerate() {
+            let token = if ch == '<' { TypeToken::Open } else { TypeToken::Close };
+            tokens.push((token, term.position.start + idx..term.position.start + idx + 1));
+        }
+    }
+
+    tokens.iter().any(|(token, _)| *token == TypeToken::Open).then_some(tokens)
+}
+
+/// The builtin primitive trait with the name, if a trait of that name would shadow it.
+pub fn shadowed_primitive<'a>(runtime: &'a Runtime, name: &str) -> Option<&'a Arc<Trait>> {
+    runtime.primitives.as_ref()?.values().find(|primitive| primitive.name == name)
+}
+
+/// Notes for each trait in the type that is named like a builtin primitive, but isn't it.
+/// Errors mentioning such types are confusing otherwise, since the names look the same.
+pub fn shadowed_primitive_notes(runtime: &Runtime, type_: &TypeProto) -> Vec<RuntimeError> {
+    let mut notes = vec![];
+    let mut todo = vec![type_];
+    while let Some(type_) = todo.pop() {
+        if let TypeUnit::Struct(trait_) = &type_.unit {
+            if shadowed_primitive(runtime, &trait_.name).is_some_and(|primitive| primitive != trait_) {
+                notes.push(RuntimeError::note(format!("{} is a trait declared in source, not the builtin primitive {}.", trait_.name, trait_.name).as_str()));
+            }
+        }
+        todo.extend(type_.arguments.iter().map(Arc::as_ref));
+    }
+    notes
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::ops::Range;
+use std::sync::Arc;
 
 use uuid::Uuid;
 
@@ -9,40 +10,83 @@ use crate::program::functions::FunctionHead;
 use crate::program::global::FunctionLogic;
 use crate::program::module::{Module, ModuleName};
 use crate::program::traits::Trait;
+use crate::static_analysis::purity::Impurity;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct StructInfo {
-    pub trait_: Rc<Trait>,
+    pub trait_: Arc<Trait>,
 
-    pub constructor: Rc<FunctionHead>,
-    pub fields: Vec<Rc<ObjectReference>>,
+    pub constructor: Arc<FunctionHead>,
+    pub fields: Vec<Arc<ObjectReference>>,
 
-    pub field_names: HashMap<Rc<ObjectReference>, String>,
-    pub field_getters: HashMap<Rc<ObjectReference>, Rc<FunctionHead>>,
-    pub field_setters: HashMap<Rc<ObjectReference>, Rc<FunctionHead>>,
+    pub field_names: HashMap<Arc<ObjectReference>, String>,
+    pub field_getters: HashMap<Arc<ObjectReference>, Arc<FunctionHead>>,
+    pub field_setters: HashMap<Arc<ObjectReference>, Arc<FunctionHead>>,
 }
 
+/// A trait whose values are one of its variants, which carry no data.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumInfo {
+    pub trait_: Arc<Trait>,
+    /// In order of declaration, which is also the order of their tags.
+    pub variants: Vec<Arc<FunctionHead>>,
+    pub variant_names: Vec<String>,
+    /// The getter of the enum's metatype, which variants are read from.
+    pub getter: Arc<FunctionHead>,
+    /// The enum's == function.
+    pub equal_to: Arc<FunctionHead>,
+}
+
+/// Where a function was declared, for navigating to it from errors and tools.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DeclarationSite {
+    pub module: ModuleName,
+    /// The declaration's byte range in the module's source.
+    pub range: Range<usize>,
+}
+
+#[derive(Clone)]
 pub struct Source {
     pub module_by_name: HashMap<ModuleName, Box<Module>>,
 
     // Cache of aggregated module_by_name fields for quick reference.
 
     /// For every getter, which trait it provides.
-    pub trait_references: HashMap<Rc<FunctionHead>, Rc<Trait>>,
+    pub trait_references: HashMap<Arc<FunctionHead>, Arc<Trait>>,
     /// For referencible functions, the trait for it as an object.
-    pub function_traits: HashMap<Rc<Trait>, Rc<FunctionHead>>,
+    pub function_traits: HashMap<Arc<Trait>, Arc<FunctionHead>>,
     /// For instantiatable traits, their struct info
-    pub struct_by_trait: HashMap<Rc<Trait>, Rc<StructInfo>>,
+    pub struct_by_trait: HashMap<Arc<Trait>, Arc<StructInfo>>,
+    /// For enum traits, their enum info.
+    pub enum_by_trait: HashMap<Arc<Trait>, Arc<EnumInfo>>,
+    /// For traits whose values can be boxed as Dyn, the generic function boxing them.
+    pub dyn_boxes: HashMap<Arc<Trait>, Arc<FunctionHead>>,
+    /// For anonymous struct types like (q 'Int64, r 'Int64), the getter of the struct's trait, by its field names.
+    pub anonymous_structs: HashMap<Vec<String>, Arc<FunctionHead>>,
 
     /// For each function_id, its head.
-    pub fn_heads: HashMap<Uuid, Rc<FunctionHead>>,
+    pub fn_heads: HashMap<Uuid, Arc<FunctionHead>>,
     /// For referencible functions, a way to load it. The getter itself does not get a getter.
-    pub fn_getters: HashMap<Rc<FunctionHead>, Rc<FunctionHead>>,
+    pub fn_getters: HashMap<Arc<FunctionHead>, Arc<FunctionHead>>,
     
     /// For all functions, the 'default' representation for syntax.
-    pub fn_representations: HashMap<Rc<FunctionHead>, FunctionRepresentation>,
+    pub fn_representations: HashMap<Arc<FunctionHead>, FunctionRepresentation>,
     /// For all functions, their logic.
-    pub fn_logic: HashMap<Rc<FunctionHead>, FunctionLogic>,
+    pub fn_logic: HashMap<Arc<FunctionHead>, FunctionLogic>,
+    /// For functions declared in source, where they were declared. Builtins have none.
+    pub fn_declarations: HashMap<Arc<FunctionHead>, DeclarationSite>,
+    /// For functions that have side effects, why they do. All others are pure.
+    pub impure_functions: HashMap<Arc<FunctionHead>, Impurity>,
+    /// For functions declared deprecated, the message of their deprecated decoration.
+    pub fn_deprecations: HashMap<Arc<FunctionHead>, String>,
+    /// For traits declared deprecated, the message of their deprecated decoration.
+    pub trait_deprecations: HashMap<Arc<Trait>, String>,
+}
+
+impl Default for Source {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Source {
@@ -52,10 +96,26 @@ impl Source {
             trait_references: Default::default(),
             function_traits: Default::default(),
             struct_by_trait: Default::default(),
+            enum_by_trait: Default::default(),
+            dyn_boxes: Default::default(),
+            anonymous_structs: Default::default(),
             fn_heads: Default::default(),
             fn_getters: Default::default(),
             fn_representations: Default::default(),
             fn_logic: Default::default(),
+            fn_declarations: Default::default(),
+            impure_functions: Default::default(),
+            fn_deprecations: Default::default(),
+            trait_deprecations: Default::default(),
+        }
+    }
+
+    /// The function's name, qualified by the module declaring it if known (e.g. common.math._add).
+    pub fn qualified_name(&self, function: &Arc<FunctionHead>) -> String {
+        let name = &self.fn_representations[function].name;
+        match self.fn_declarations.get(function) {
+            Some(declaration) => format!("{}.{}", declaration.module.join("."), name),
+            None => name.clone(),
         }
     }
 }
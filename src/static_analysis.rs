@@ -1,6 +1,277 @@
-use crate::error::RResult;
+pub mod purity;
+pub mod lint;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use annotate_snippets::Level;
+
+use crate::error::codes::ErrorCode;
+use crate::error::{RResult, RuntimeError};
+use crate::interpreter::runtime::Runtime;
+use crate::program::allocation::{Mutability, ObjectReference};
+use crate::program::expression_tree::{ExpressionID, ExpressionOperation, ExpressionTree};
 use crate::program::global::FunctionImplementation;
+use crate::program::module::ModuleSettings;
+use crate::static_analysis::lint::{Lint, LintContext};
+
+/// The lints run on every function, in order, before the runtime's custom lints.
+pub const BUILTIN_LINTS: [&dyn Lint; 3] = [&AssignmentLint, &StaticTypeTestLint, &UnusedResultLint];
+
+/// Analyzes the function, removing code that can never run.
+/// Returns the warnings of resolving it, warnings for anything suspicious, and errors for anything the settings deny.
+/// Lints run in order; the first lint reporting errors fails the function, without running the others.
+pub fn check(function: &mut FunctionImplementation, settings: &ModuleSettings, runtime: &Runtime) -> RResult<Vec<RuntimeError>> {
+    let mut warnings = std::mem::take(&mut function.warnings);
+    warnings.extend(remove_unreachable(function));
+
+    let context = LintContext { runtime, settings };
+    let custom_lints = runtime.lints.iter().map(|custom| {
+        (custom.lint.as_ref(), Some(ErrorCode::Lint(custom.code)))
+    });
+    for (lint, code) in BUILTIN_LINTS.into_iter().map(|lint| (lint, None)).chain(custom_lints) {
+        let diagnostics = lint.run(function, &context).into_iter()
+            .map(|diagnostic| match code {
+                Some(code) => diagnostic.with_code(code),
+                None => diagnostic,
+            });
+        let (errors, lint_warnings): (Vec<_>, Vec<_>) = diagnostics.partition(|diagnostic| diagnostic.level == Level::Error);
+        if !errors.is_empty() {
+            return Err(errors)
+        }
+        warnings.extend(lint_warnings);
+    }
+
+    Ok(warnings)
+}
+
+/// Removes statements following one that always returns, warning about the first of them.
+fn remove_unreachable(function: &mut FunctionImplementation) -> Vec<RuntimeError> {
+    let mut warnings = vec![];
+    let tree = &mut function.expression_tree;
+    for block in tree.deep_children(tree.root) {
+        // The block may have been removed as part of unreachable code.
+        let Some(ExpressionOperation::Block) = tree.values.get(&block) else {
+            continue
+        };
+
+        let statements = &tree.children[&block];
+        let Some(diverging_idx) = statements.iter().position(|statement| diverges(tree, statement)) else {
+            continue
+        };
+        let unreachable = statements[diverging_idx + 1..].to_vec();
+        let Some(first_unreachable) = unreachable.first() else {
+            continue
+        };
+
+        warnings.push(
+            RuntimeError::warning("Unreachable statement.")
+                .with_code(ErrorCode::UnreachableStatement)
+                .in_range(function.statement_ranges[first_unreachable].clone())
+                .with_note(
+                    RuntimeError::note("Any code following this statement is unreachable.")
+                        .in_range(function.statement_ranges[&statements[diverging_idx]].clone())
+                )
+        );
+
+        tree.children.get_mut(&block).unwrap().truncate(diverging_idx + 1);
+        tree.truncate_down(unreachable);
+    }
+
+    warnings
+}
+
+/// Fails for statements whose value is discarded, if the module denies it with strict!(deny_unused_results).
+/// The last statement of a block is its value, so it doesn't count.
+pub struct UnusedResultLint;
+
+impl Lint for UnusedResultLint {
+    fn name(&self) -> &'static str {
+        "unused_results"
+    }
+
+    fn run(&self, function: &FunctionImplementation, context: &LintContext) -> Vec<RuntimeError> {
+        match context.settings.deny_unused_results {
+            true => unused_results(function),
+            false => vec![],
+        }
+    }
+}
+
+fn unused_results(function: &FunctionImplementation) -> Vec<RuntimeError> {
+    let tree = &function.expression_tree;
+    tree.deep_children(tree.root).into_iter()
+        .filter(|block| tree.values[block] == ExpressionOperation::Block)
+        .flat_map(|block| {
+            let statements = &tree.children[&block];
+            statements[..statements.len().saturating_sub(1)].to_vec()
+        })
+        .filter(|statement| {
+            function.type_forest.resolve_binding_alias(statement).is_ok_and(|type_| !type_.unit.is_void())
+        })
+        .map(|statement| {
+            let error = RuntimeError::error("Unused result.")
+                .with_note(RuntimeError::note("Discarding values is denied by strict!(deny_unused_results)."));
+            match function.statement_ranges.get(&statement) {
+                Some(range) => error.in_range(range.clone()),
+                None => error,
+            }
+        })
+        .collect()
+}
+
+/// Which locals have been assigned at some point of the function.
+#[derive(Clone)]
+struct Assignments {
+    /// Assigned on every path leading here.
+    definite: HashSet<Arc<ObjectReference>>,
+    /// Assigned on at least one path leading here.
+    possible: HashSet<Arc<ObjectReference>>,
+}
+
+/// Fails for locals that are read before they are assigned on every path, which only locals declared
+///  without a value can be, and for let locals that may be assigned more than once.
+pub struct AssignmentLint;
+
+impl Lint for AssignmentLint {
+    fn name(&self) -> &'static str {
+        "assignments"
+    }
+
+    fn run(&self, function: &FunctionImplementation, _context: &LintContext) -> Vec<RuntimeError> {
+        let parameters: HashSet<_> = function.parameter_locals.iter().cloned().collect();
+        let mut assignments = Assignments { definite: parameters.clone(), possible: parameters };
+        let mut errors = vec![];
+        track_assignments(function, &function.expression_tree.root, &mut assignments, &mut errors);
+        errors
+    }
+}
+
+/// Arguments are tracked in order. They can be nested as deeply as a chain of operations is long,
+///  so they are walked with a stack; only conditionals and assignments recurse.
+fn track_assignments(function: &FunctionImplementation, expression: &ExpressionID, assignments: &mut Assignments, errors: &mut Vec<RuntimeError>) {
+    let tree = &function.expression_tree;
+    let mut pending = vec![*expression];
+    while let Some(expression) = pending.pop() {
+        let expression = &expression;
+        let arguments = &tree.children[expression];
+        match &tree.values[expression] {
+            ExpressionOperation::IfThenElse => {
+                track_assignments(function, &arguments[0], assignments, errors);
+
+                let mut consequent = assignments.clone();
+                track_assignments(function, &arguments[1], &mut consequent, errors);
+                let mut alternative = assignments.clone();
+                if let Some(argument) = arguments.get(2) {
+                    track_assignments(function, argument, &mut alternative, errors);
+                }
+
+                // Branches that return don't lead to the code after the if.
+                *assignments = match (diverges(tree, &arguments[1]), arguments.get(2).is_some_and(|argument| diverges(tree, argument))) {
+                    (true, _) => alternative,
+                    (false, true) => consequent,
+                    (false, false) => Assignments {
+                        definite: consequent.definite.intersection(&alternative.definite).cloned().collect(),
+                        possible: consequent.possible.union(&alternative.possible).cloned().collect(),
+                    },
+                };
+            }
+            ExpressionOperation::SetLocal(local) => {
+                for argument in arguments {
+                    track_assignments(function, argument, assignments, errors);
+                }
+
+                if local.mutability == Mutability::Immutable && assignments.possible.contains(local) {
+                    let error = RuntimeError::error(format!("`{}` may be assigned more than once, but is declared with let.", function.locals_names[local]).as_str())
+                        .with_note(RuntimeError::note("Declare it with var to allow changing it."));
+                    errors.push(match function.statement_ranges.get(expression) {
+                        Some(range) => error.in_range(range.clone()),
+                        None => error,
+                    });
+                }
+                assignments.definite.insert(Arc::clone(local));
+                assignments.possible.insert(Arc::clone(local));
+            }
+            ExpressionOperation::GetLocal(local) => {
+                if assignments.definite.contains(local) {
+                    continue
+                }
+
+                let name = &function.locals_names[local];
+                let error = match assignments.possible.contains(local) {
+                    true => RuntimeError::error(format!("`{}` is read before it is assigned on every path.", name).as_str())
+                        .with_note(RuntimeError::note("Assign it in every branch, e.g. in both the if and the else.")),
+                    false => RuntimeError::error(format!("`{}` is read before it is assigned.", name).as_str()),
+                }.with_code(ErrorCode::UnassignedLocal);
+                errors.push(match function.expression_ranges.get(expression) {
+                    Some(range) => error.in_range(range.clone()),
+                    None => error,
+                });
+                // Report each local only once; later reads would fail for the same reason.
+                assignments.definite.insert(Arc::clone(local));
+            }
+            _ => pending.extend(arguments.iter().rev().cloned()),
+        }
+    }
+}
+
+/// Warns about type checks and casts whose value isn't generic, because their result is already known.
+pub struct StaticTypeTestLint;
+
+impl Lint for StaticTypeTestLint {
+    fn name(&self) -> &'static str {
+        "static_type_tests"
+    }
+
+    fn run(&self, function: &FunctionImplementation, _context: &LintContext) -> Vec<RuntimeError> {
+        static_type_tests(function)
+    }
+}
+
+fn static_type_tests(function: &FunctionImplementation) -> Vec<RuntimeError> {
+    let tree = &function.expression_tree;
+    tree.deep_children(tree.root).into_iter()
+        .filter_map(|expression| {
+            let (ExpressionOperation::TypeCheck(trait_) | ExpressionOperation::TypeCast(trait_)) = &tree.values[&expression] else {
+                return None
+            };
+            let type_ = function.type_forest.resolve_binding_alias(&tree.children[&expression][0]).ok()?;
+            if function.head.interface.generics.values().any(|generic| type_.contains_struct(generic)) {
+                return None
+            }
+
+            let warning = match (&tree.values[&expression], type_.is_instance_of(trait_)) {
+                (ExpressionOperation::TypeCheck(_), is_instance) => RuntimeError::warning(format!("Type check is always {}.", is_instance).as_str()),
+                (_, true) => RuntimeError::warning("Cast always succeeds."),
+                (_, false) => RuntimeError::warning("Cast always fails."),
+            };
+            let warning = warning.with_code(ErrorCode::StaticTypeTest).with_note(RuntimeError::note(format!("The value is known to be {:?}.", type_).as_str()));
+            Some(match function.expression_ranges.get(&expression) {
+                Some(range) => warning.in_range(range.clone()),
+                None => warning,
+            })
+        })
+        .collect()
+}
+
+/// Whether evaluating the expression always returns from the function.
+/// Arguments can be nested as deeply as a chain of operations is long, so they are walked with a stack.
+fn diverges(tree: &ExpressionTree, expression: &ExpressionID) -> bool {
+    let mut pending = vec![*expression];
+    while let Some(expression) = pending.pop() {
+        let arguments = &tree.children[&expression];
+        match &tree.values[&expression] {
+            ExpressionOperation::Return => return true,
+            // Only the condition is always evaluated, so the branches must both return.
+            ExpressionOperation::IfThenElse => {
+                if diverges(tree, &arguments[0])
+                    || (arguments.len() == 3 && diverges(tree, &arguments[1]) && diverges(tree, &arguments[2])) {
+                    return true
+                }
+            }
+            _ => pending.extend(arguments.iter().cloned()),
+        }
+    }
 
-pub fn check(function: &mut FunctionImplementation) -> RResult<()> {
-    Ok(())
+    false
 }
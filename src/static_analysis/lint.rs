@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use crate::error::codes::LintCode;
+use crate::error::RuntimeError;
+use crate::interpreter::runtime::Runtime;
+use crate::program::global::FunctionImplementation;
+use crate::program::module::ModuleSettings;
+
+/// A check over resolved function bodies, run by static_analysis::check after each body is resolved.
+/// Diagnostics at error level fail the function's module; any other diagnostics are reported as its warnings.
+///
+/// Tools can supply their own lints by adding them to the runtime, or by passing them to analyze_with_lints:
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use monoteny::error::codes::LintCode;
+/// use monoteny::error::RuntimeError;
+/// use monoteny::interpreter::runtime::Runtime;
+/// use monoteny::program::global::FunctionImplementation;
+/// use monoteny::program::module::module_name;
+/// use monoteny::static_analysis::lint::{CustomLint, Lint, LintContext};
+///
+/// struct LongFunction;
+///
+/// const LONG_FUNCTION: LintCode = LintCode { prefix: "acme", name: "long_function" };
+///
+/// impl Lint for LongFunction {
+///     fn name(&self) -> &'static str { "long_function" }
+///
+///     fn run(&self, function: &FunctionImplementation, _context: &LintContext) -> Vec<RuntimeError> {
+///         match function.expression_tree.values.len() > 50 {
+///             true => RuntimeError::warning("Function is longer than 50 expressions.").to_array(),
+///             false => vec![],
+///         }
+///     }
+/// }
+///
+/// let mut runtime = Runtime::new().unwrap();
+/// runtime.repository.add_bundled("common");
+/// runtime.lints.push(CustomLint { code: &LONG_FUNCTION, lint: Arc::new(LongFunction) });
+///
+/// let sum = vec!["1"; 60].join(" + ");
+/// let source = format!("use!(module!(\"common\"));\ndef long() -> Int32 :: {};\ndef short() -> Int32 :: 1;", sum);
+/// let module = runtime.load_text_as_module(&source, module_name("main")).unwrap();
+///
+/// assert_eq!(module.warnings.len(), 1);
+/// assert_eq!(module.warnings[0].code.unwrap().to_string(), "acme::long_function");
+/// ```
+pub trait Lint: Send + Sync {
+    /// Identifies the lint, like "long_function".
+    fn name(&self) -> &'static str;
+
+    fn run(&self, function: &FunctionImplementation, context: &LintContext) -> Vec<RuntimeError>;
+}
+
+/// What lints may inspect besides the function itself.
+pub struct LintContext<'a> {
+    pub runtime: &'a Runtime,
+    /// The settings of the module declaring the function.
+    pub settings: &'a ModuleSettings,
+}
+
+/// A lint supplied by the caller, run after the built-in lints.
+/// Its diagnostics get its code, like `acme::long_function`, so they can't be confused with built-in codes.
+#[derive(Clone)]
+pub struct CustomLint {
+    pub code: &'static LintCode,
+    pub lint: Arc<dyn Lint>,
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::program::expression_tree::ExpressionOperation;
+use crate::program::functions::FunctionHead;
+use crate::program::global::{FunctionImplementation, FunctionLogic};
+
+/// Why a function is not side effect free.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Impurity {
+    /// The function is a builtin with side effects, or one whose results have identity.
+    Intrinsic,
+    /// The function calls another impure function.
+    Calls(Arc<FunctionHead>),
+}
+
+/// Infers which of the implementations are impure, adding them to `impure`.
+/// A function is impure if it calls an impure function. Calls to functions without logic, such as
+///  trait requirements, are assumed to be pure.
+/// Recursive functions are pure unless something in their cycle is impure.
+pub fn infer<'a>(implementations: impl Iterator<Item=&'a FunctionImplementation>, fn_logic: &HashMap<Arc<FunctionHead>, FunctionLogic>, impure: &mut HashMap<Arc<FunctionHead>, Impurity>) {
+    let mut pending = implementations
+        .map(|implementation| (Arc::clone(&implementation.head), callees(implementation)))
+        .collect_vec();
+
+    // Impurity only spreads, so we're done once a pass makes no progress.
+    loop {
+        let count_before = pending.len();
+        pending.retain(|(head, callees)| {
+            let Some(callee) = callees.iter().find(|callee| is_impure(callee, fn_logic, impure)) else {
+                return true
+            };
+            impure.insert(Arc::clone(head), Impurity::Calls(Arc::clone(callee)));
+            false
+        });
+
+        if pending.len() == count_before {
+            return
+        }
+    }
+}
+
+/// The chain of calls leading from the function to an impure intrinsic, starting with the function itself.
+/// Empty if the function is pure.
+pub fn impure_call_chain(head: &Arc<FunctionHead>, impure: &HashMap<Arc<FunctionHead>, Impurity>) -> Vec<Arc<FunctionHead>> {
+    let mut chain = vec![];
+    let mut current = head;
+    while let Some(impurity) = impure.get(current) {
+        // Shouldn't happen since chains are built from already impure functions, but let's not hang.
+        if chain.contains(current) {
+            break
+        }
+        chain.push(Arc::clone(current));
+        match impurity {
+            Impurity::Intrinsic => break,
+            Impurity::Calls(callee) => current = callee,
+        }
+    }
+    chain
+}
+
+fn is_impure(head: &Arc<FunctionHead>, fn_logic: &HashMap<Arc<FunctionHead>, FunctionLogic>, impure: &mut HashMap<Arc<FunctionHead>, Impurity>) -> bool {
+    if impure.contains_key(head) {
+        return true
+    }
+
+    match fn_logic.get(head) {
+        Some(FunctionLogic::Descriptor(descriptor)) if !descriptor.is_pure() => {
+            impure.insert(Arc::clone(head), Impurity::Intrinsic);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The functions the implementation calls directly, in the order of the expression tree.
+pub fn callees(implementation: &FunctionImplementation) -> Vec<Arc<FunctionHead>> {
+    let tree = &implementation.expression_tree;
+    // The order decides which call is blamed for impurity, so it shouldn't depend on hashing.
+    tree.deep_children(tree.root).iter()
+        .filter_map(|expression_id| tree.values.get(expression_id))
+        .flat_map(|operation| match operation {
+            ExpressionOperation::FunctionCall(binding) => vec![Arc::clone(&binding.function)],
+            ExpressionOperation::PairwiseOperations { calls } => calls.iter().map(|binding| Arc::clone(&binding.function)).collect_vec(),
+            _ => vec![],
+        })
+        .unique()
+        .collect_vec()
+}
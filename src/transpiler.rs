@@ -1,14 +1,16 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
-use itertools::Itertools;
 
 use crate::error::{RResult, TryCollectMany};
 use crate::interpreter::runtime::Runtime;
 use crate::program::function_object::FunctionRepresentation;
 use crate::program::functions::FunctionHead;
 use crate::program::global::{FunctionImplementation, FunctionLogic, FunctionLogicDescriptor};
+use crate::program::module::ModuleName;
+use crate::program::traits::Trait;
 use crate::refactor::Refactor;
+use crate::refactor::report::InliningReport;
 use crate::refactor::simplify::Simplify;
 
 pub mod python;
@@ -21,15 +23,44 @@ pub struct Config {
     pub should_monomorphize: bool,
     pub should_inline: bool,
     pub should_trim_locals: bool,
+    pub should_eliminate_dead_functions: bool,
+    /// Adds a json file explaining which functions were inlined.
+    pub should_explain_inlining: bool,
+    /// Raises the interpreter's errors for list indices out of bounds, instead of python's.
+    pub should_match_interpreter_errors: bool,
+    pub layout: Layout,
+}
+
+/// How the transpiled code is split into files.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layout {
+    /// All code in a single file.
+    OneFile,
+    /// One file per module, in a package mirroring the module tree.
+    Package,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config::with_optimization_level(2)
+    }
 }
 
 impl Config {
-    pub fn default() -> Config {
+
+    /// 0: No refactoring beyond what's required for transpilation.
+    /// 1: Inline trivial functions and trim unused locals.
+    /// 2: Additionally propagate constants and eliminate functions that are no longer called.
+    pub fn with_optimization_level(level: u8) -> Config {
         Config {
-            should_constant_fold: true,
-            should_monomorphize: true,
-            should_inline: true,
-            should_trim_locals: true,
+            should_constant_fold: level >= 2,
+            should_monomorphize: true, // TODO Cannot do without it for now
+            should_inline: level >= 1,
+            should_trim_locals: level >= 1,
+            should_eliminate_dead_functions: level >= 2,
+            should_explain_inlining: false,
+            should_match_interpreter_errors: false,
+            layout: Layout::OneFile,
         }
     }
 }
@@ -40,17 +71,30 @@ pub enum TranspiledArtifact {
 
 pub struct Transpiler {
     // In the future, this should all be accessible by monoteny code itself - including the context.
-    pub main_function: Option<Rc<FunctionHead>>,
+    pub main_function: Option<Arc<FunctionHead>>,
     pub exported_artifacts: Vec<TranspiledArtifact>,
+    /// Modules the transpiled module includes, with the namespace they are included under, if any.
+    pub included_modules: Vec<(ModuleName, Option<String>)>,
 }
 
 pub struct TranspilePackage<'a> {
     // In the future, this should all be accessible by monoteny code itself - including the context.
-    pub main_function: Option<Rc<FunctionHead>>,
+    pub main_function: Option<Arc<FunctionHead>>,
     pub explicit_functions: Vec<&'a FunctionImplementation>,
     pub implicit_functions: Vec<&'a FunctionImplementation>,
-    pub used_native_functions: HashMap<Rc<FunctionHead>, FunctionLogicDescriptor>,
-    pub fn_representations: HashMap<Rc<FunctionHead>, FunctionRepresentation>
+    pub used_native_functions: HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>,
+    pub fn_representations: HashMap<Arc<FunctionHead>, FunctionRepresentation>,
+    /// The module each function was declared in. Functions of the transpiled module itself are missing.
+    pub fn_modules: HashMap<Arc<FunctionHead>, ModuleName>,
+    /// The source function each invented function was made from, e.g. by monomorphization.
+    pub fn_origins: HashMap<Arc<FunctionHead>, Arc<FunctionHead>>,
+    /// The module each trait was declared in. Traits of the transpiled module itself are missing.
+    pub trait_modules: HashMap<Arc<Trait>, ModuleName>,
+    /// For the transpiled module (None) and each other module, the modules it includes.
+    pub module_includes: HashMap<Option<ModuleName>, Vec<(ModuleName, Option<String>)>>,
+    /// The traits of anonymous structs, which have no name of their own.
+    pub anonymous_structs: HashSet<Arc<Trait>>,
+    pub config: &'a Config,
 }
 
 pub trait LanguageContext {
@@ -64,14 +108,14 @@ pub trait LanguageContext {
     ) -> RResult<HashMap<String, String>>;
 }
 
-pub fn transpile(transpiler: Box<Transpiler>, runtime: &mut Runtime, context: &dyn LanguageContext, config: &Config, base_filename: &str) -> RResult<HashMap<String, String>>{
+pub fn transpile(transpiler: Transpiler, runtime: &mut Runtime, context: &dyn LanguageContext, config: &Config, base_filename: &str) -> RResult<HashMap<String, String>>{
     let mut refactor = Refactor::new(runtime);
     context.register_builtins(&mut refactor);
 
     for artifact in transpiler.exported_artifacts {
         match artifact {
             TranspiledArtifact::Function(implementation) => {
-                let head = Rc::clone(&implementation.head);
+                let head = Arc::clone(&implementation.head);
                 let representation = refactor.runtime.source.fn_representations[&head].clone();
 
                 refactor.add(implementation, representation);
@@ -85,10 +129,40 @@ pub fn transpile(transpiler: Box<Transpiler>, runtime: &mut Runtime, context: &d
     // --- Reclaim from Refactor and make the ast
     context.refactor_code(&mut refactor);
 
+    let inlining_report = config.should_explain_inlining.then(|| InliningReport::new(&refactor).to_json(&refactor));
+
     // TODO The call_graph doesn't know about calls made outside the refactor. If there was no monomorphization, some functions may not even be caught by this.
-    let deep_calls = refactor.gather_needed_functions();
+    let deep_calls = match config.should_eliminate_dead_functions {
+        true => refactor.gather_needed_functions(),
+        // Keep every function the refactor knows about, even if nobody calls it anymore.
+        false => refactor.gather_all_functions(),
+    };
+    let source = &refactor.runtime.source;
+    let module_by_function: HashMap<_, _> = source.module_by_name.values()
+        .flat_map(|module| module.implemented_functions.iter().map(|head| (head, &module.name)))
+        .collect();
+    let fn_modules = deep_calls.iter().chain(refactor.explicit_functions.iter())
+        .filter_map(|head| Some((Arc::clone(head), module_by_function.get(refactor.original_function(head))?.to_vec())))
+        .collect();
+    let fn_origins = deep_calls.iter()
+        .filter(|head| refactor.invented_functions.contains(*head))
+        .map(|head| (Arc::clone(head), Arc::clone(refactor.original_function(head))))
+        .collect();
+    let trait_modules = source.module_by_name.values()
+        .flat_map(|module| module.exposed_functions.iter()
+            .filter_map(|head| Some((Arc::clone(source.trait_references.get(head)?), module.name.clone())))
+        )
+        .collect();
+    let module_includes = source.module_by_name.values()
+        .map(|module| (Some(module.name.clone()), module.included_modules.clone()))
+        .chain([(None, transpiler.included_modules)])
+        .collect();
+    let anonymous_structs = source.anonymous_structs.values()
+        .map(|getter| Arc::clone(&source.trait_references[getter]))
+        .collect();
+
     let fn_representations = refactor.fn_representations;
-    let mut fn_logic = refactor.fn_logic;
+    let fn_logic = refactor.fn_logic;
 
     let exported_functions = refactor.explicit_functions.iter()
         .map(|head| fn_logic.get(head).unwrap().as_implementation())
@@ -97,6 +171,11 @@ pub fn transpile(transpiler: Box<Transpiler>, runtime: &mut Runtime, context: &d
     let mut native_functions = HashMap::new();
 
     for head in deep_calls {
+        if refactor.explicit_functions.contains(&head) {
+            // Explicit functions may be called by others, but are defined only once.
+            continue
+        }
+
         // Either Refactor has it (because it invented it) or it's unchanged from source.
         match fn_logic.get(&head).unwrap() {
             FunctionLogic::Implementation(i) => {
@@ -108,11 +187,23 @@ pub fn transpile(transpiler: Box<Transpiler>, runtime: &mut Runtime, context: &d
         }
     }
 
-    context.make_files(base_filename, TranspilePackage {
+    let mut files = context.make_files(base_filename, TranspilePackage {
         main_function: transpiler.main_function,
         explicit_functions: exported_functions,
         implicit_functions,
         used_native_functions: native_functions,
         fn_representations,
-    })
+        fn_modules,
+        fn_origins,
+        trait_modules,
+        module_includes,
+        anonymous_structs,
+        config,
+    })?;
+
+    if let Some(inlining_report) = inlining_report {
+        files.insert(format!("{}.inlining.json", base_filename), inlining_report);
+    }
+
+    Ok(files)
 }
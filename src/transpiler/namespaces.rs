@@ -10,6 +10,12 @@ pub struct Level {
     sublevels: Vec<Level>
 }
 
+impl Default for Level {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Level {
     pub fn new() -> Level {
         Level {
@@ -25,7 +31,7 @@ impl Level {
         else {
             self.claims.insert(
                 name.to_string(),
-                LinkedHashSet::from_iter([uuid.clone()])
+                LinkedHashSet::from_iter([uuid])
             );
         }
 
@@ -45,7 +51,7 @@ impl Level {
             format!("{}{}", prefix, idx)
         }
 
-        for (name, claims) in self.claims.iter().sorted_by_key(|(name, claims)| name.len()) {
+        for (name, claims) in self.claims.iter().sorted_by_key(|(name, _)| (name.len(), name.as_str())) {
             if let Ok(claim) = claims.iter().exactly_one() {
                 // Can use plain name
                 let mut name = name.clone();
@@ -62,13 +68,11 @@ impl Level {
                     prefix = format!("{}_", prefix);
                 }
 
-                let mut idx = 0;
-                for claim in claims.iter() {
+                for (idx, claim) in claims.iter().enumerate() {
                     let postfixed_name = make_name(&prefix, idx);
                     reserved.insert(postfixed_name.clone());
 
-                    mapping.insert(claim.clone(), postfixed_name);
-                    idx += 1;
+                    mapping.insert(*claim, postfixed_name);
                 }
             }
         }
@@ -1,18 +1,24 @@
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::sync::Arc;
 use display_with_options::{IndentOptions, with_options};
 
 use itertools::Itertools;
 use linked_hash_map::LinkedHashMap;
+use uuid::Uuid;
 
 use crate::error::RResult;
 use crate::interpreter::runtime::Runtime;
-use crate::program::global::FunctionLogicDescriptor;
+use crate::program::global::{FunctionLogicDescriptor, ListOperation, PrimitiveOperation};
+use crate::program::module::ModuleName;
+use crate::program::primitives;
+use crate::program::types::TypeProto;
 use crate::refactor::Refactor;
+use crate::source::StructInfo;
 use crate::transpiler;
-use crate::transpiler::{namespaces, structs, TranspilePackage};
+use crate::transpiler::{Layout, namespaces, structs, TranspilePackage};
 use crate::transpiler::python::ast::Statement;
-use crate::transpiler::python::class::{ClassContext, transpile_class};
+use crate::transpiler::python::class::{ClassContext, transpile_class, transpile_enum};
+use crate::transpiler::python::keywords::{KEYWORD_IDS, PSEUDO_KEYWORD_IDS};
 use crate::transpiler::python::imperative::{FunctionContext, transpile_function};
 use crate::transpiler::python::representations::{FunctionForm, Representations};
 
@@ -23,6 +29,7 @@ pub mod ast;
 pub mod imperative;
 pub mod representations;
 pub mod keywords;
+pub mod package;
 mod strings;
 
 pub struct Context {
@@ -42,48 +49,61 @@ impl transpiler::LanguageContext for Context {
         context
     }
 
-    fn register_builtins(&self, refactor: &mut Refactor) {
+    fn register_builtins(&self, _refactor: &mut Refactor) {
         // TODO If there's any optimizations we know (e.g. sin()), place it here.
     }
 
-    fn refactor_code(&self, refactor: &mut Refactor) {
+    fn refactor_code(&self, _refactor: &mut Refactor) {
         // TODO We need to at least break up inner blocks of all functions.
     }
 
     fn make_files(&self, base_filename: &str, package: TranspilePackage) -> RResult<HashMap<String, String>> {
-        let ast = self.create_ast(package)?;
-
-        let string = format!("{}", with_options(ast.as_ref(), &IndentOptions {
-            full_indentation: String::new(),
-            next_level: "    ",
-        }));
-
-        Ok(HashMap::from([
-            (format!("{}.py", base_filename), string)
-        ]))
+        let layout = package.config.layout;
+        let statements = self.create_ast(package)?;
+
+        Ok(match layout {
+            Layout::OneFile => HashMap::from([
+                (format!("{}.py", base_filename), write_module(&statements.into_module()))
+            ]),
+            Layout::Package => package::make_package(base_filename, statements),
+        })
     }
 }
 
 impl Context {
-    pub fn create_ast(&self, transpile: TranspilePackage) -> RResult<Box<ast::Module>> {
+    pub fn create_ast(&self, mut transpile: TranspilePackage) -> RResult<TopLevelStatements> {
         let mut representations = self.representations.clone();
         let builtin_structs: HashSet<_> = representations.type_ids.keys().cloned().collect();
 
+        // Instantiations replaced by python builtins aren't transpiled themselves.
+        transpile.implicit_functions.retain(|implementation| {
+            let head = &implementation.head;
+            let Some(form) = transpile.fn_origins.get(head).and_then(|origin| representations.primitive_instantiation_forms.get(origin)) else {
+                return true
+            };
+            if !head.interface.parameters.iter().all(|parameter| representations.primitive_types.contains(&parameter.type_)) {
+                return true
+            }
+
+            representations.function_forms.insert(Arc::clone(head), form.clone());
+            false
+        });
+
         let mut global_namespace = self.builtin_global_namespace.clone();
         // TODO We COULD have one namespace per object.
         //  But then we'll also need to register names on an object per object basis,
         //  and currently we have no way of identifying object namespaces easily.
         //  Maybe it will naturally arise later.
         let mut member_namespace = self.builtin_global_namespace.clone();
-        let mut exports_namespace = global_namespace.add_sublevel();
+        let exports_namespace = global_namespace.add_sublevel();
 
         // ================= Names ==================
 
         // Names for exported functions
         for implementation in transpile.explicit_functions.iter() {
             representations::find_for_function(
-                &mut representations.function_forms,
-                &mut exports_namespace,
+                &mut representations,
+                exports_namespace,
                 implementation,
                 &transpile.fn_representations[&implementation.head]
             )
@@ -96,43 +116,80 @@ impl Context {
         // structs::find_in_interfaces(explicit_functions.iter().map(|i| &i.head), &mut structs);
         structs::find_in_implementations(&transpile.explicit_functions, &transpile.used_native_functions, &mut structs);
         let exported_structs = structs.keys().cloned().collect_vec();
-        for struct_ in structs.values() {
-            exports_namespace.insert_name(struct_.trait_.id, struct_.trait_.name.as_str());
+        let mut all_structs = structs.clone();
+        structs::find_in_implementations(&transpile.implicit_functions, &transpile.used_native_functions, &mut all_structs);
+        representations.tuple_types = representations::find_tuple_types(
+            all_structs.iter().filter(|(_, struct_)| transpile.anonymous_structs.contains(&struct_.trait_)).map(|(type_, _)| type_),
+            transpile.explicit_functions.iter().chain(transpile.implicit_functions.iter()),
+            &transpile.used_native_functions
+        );
+        representations.class_types = all_structs.keys().filter(|type_| !representations.tuple_types.contains(*type_)).cloned().collect();
+        let struct_name = |type_: &TypeProto, struct_: &StructInfo| match transpile.anonymous_structs.contains(&struct_.trait_) {
+            true => representations::anonymous_struct_name(type_, struct_, transpile.explicit_functions.iter().chain(transpile.implicit_functions.iter()), &transpile.fn_representations),
+            false => structs::type_name(type_),
+        };
+        // Each specialization of a generic struct gets its own class.
+        let mut struct_ids = HashMap::new();
+        for (type_, struct_) in structs.iter().filter(|(type_, _)| !representations.tuple_types.contains(*type_)) {
+            let id = *struct_ids.entry(type_.clone()).or_insert_with(|| make_struct_id(type_, struct_));
+            exports_namespace.insert_name(id, struct_name(type_, struct_).as_str());
         }
 
-        let mut internals_namespace = exports_namespace.add_sublevel();
+        // Enums are named by their trait, so that their metatype refers to the class.
+        let mut enums = LinkedHashMap::new();
+        structs::find_enums_in_implementations(&transpile.explicit_functions, &transpile.used_native_functions, &mut enums);
+        structs::find_enums_in_implementations(&transpile.implicit_functions, &transpile.used_native_functions, &mut enums);
+        for (trait_, enum_) in enums.iter() {
+            exports_namespace.insert_name(trait_.id, trait_.name.as_str());
+            representations.type_ids.insert(TypeProto::unit_struct(trait_), trait_.id);
+
+            let namespace = member_namespace.add_sublevel();
+            for (variant, name) in enum_.variants.iter().zip(enum_.variant_names.iter()) {
+                namespace.insert_name(variant.function_id, name);
+                representations.function_forms.insert(Arc::clone(variant), FunctionForm::GetMemberField(variant.function_id));
+            }
+        }
+
+        let internals_namespace = exports_namespace.add_sublevel();
 
         // We only really know from encountered calls which structs are left after monomorphization.
         // So let's just search the encountered calls.
         for implementation in transpile.explicit_functions.iter().chain(transpile.implicit_functions.iter()) {
             let function_namespace = internals_namespace.add_sublevel();
             // Map internal variable names
-            for (ref_, name) in implementation.locals_names.iter() {
+            let destructured_tuples = imperative::destructured_tuples(implementation, &representations, &transpile.used_native_functions);
+            for (ref_, name) in implementation.locals_names.iter().filter(|(ref_, _)| !destructured_tuples.contains(*ref_)) {
                 function_namespace.insert_name(ref_.id, name);
             }
+            for block in imperative::block_arguments(&implementation.expression_tree) {
+                function_namespace.insert_name(block, "block_value");
+            }
         }
 
         // Internal struct names
         structs::find_in_implementations(&transpile.implicit_functions, &transpile.used_native_functions, &mut structs);
-        let internal_structs = structs.keys().filter(|s| !exported_structs.contains(s)).collect_vec();
+        let internal_structs = structs.keys().filter(|s| !exported_structs.contains(s) && !representations.tuple_types.contains(*s)).collect_vec();
         for type_ in internal_structs.iter() {
             let struct_ = &structs[*type_];
-            internals_namespace.insert_name(struct_.trait_.id, struct_.trait_.name.as_str());
+            let id = *struct_ids.entry((*type_).clone()).or_insert_with(|| make_struct_id(type_, struct_));
+            internals_namespace.insert_name(id, struct_name(type_, struct_).as_str());
         }
 
         // Other struct pertaining functions
         for (type_, struct_) in structs.iter() {
             let namespace = member_namespace.add_sublevel();
             for (field, getter) in struct_.field_getters.iter() {
-                let ptr = &transpile.fn_representations[getter];
-                namespace.insert_name(field.id, ptr.name.as_str());
-                representations.function_forms.insert(Rc::clone(getter), FunctionForm::GetMemberField(field.id));
+                // The getter may be unused, in which case it has no representation.
+                namespace.insert_name(field.id, struct_.field_names[field].as_str());
+                representations.function_forms.insert(Arc::clone(getter), FunctionForm::GetMemberField(field.id));
             }
             for (field, getter) in struct_.field_setters.iter() {
-                representations.function_forms.insert(Rc::clone(getter), FunctionForm::SetMemberField(field.id));
+                representations.function_forms.insert(Arc::clone(getter), FunctionForm::SetMemberField(field.id));
+            }
+            representations.function_forms.insert(Arc::clone(&struct_.constructor), FunctionForm::CallAsFunction);
+            if let Some(id) = struct_ids.get(type_) {
+                representations.type_ids.insert(type_.clone(), *id);
             }
-            representations.function_forms.insert(Rc::clone(&struct_.constructor), FunctionForm::CallAsFunction);
-            representations.type_ids.insert(type_.clone(), struct_.trait_.id);
         }
 
         // Internal / generated functions
@@ -140,23 +197,85 @@ impl Context {
             let representation = &transpile.fn_representations[&implementation.head];
 
             representations::find_for_function(
-                &mut representations.function_forms,
-                &mut internals_namespace,
+                &mut representations,
+                internals_namespace,
                 implementation, representation
             )
         }
 
+        let mut helpers = vec![];
         for (native_function, descriptor) in transpile.used_native_functions.iter() {
             match descriptor {
-                FunctionLogicDescriptor::Stub => {}
+                // Some stubs are supplied by helpers.
+                FunctionLogicDescriptor::Stub => match representations.function_forms.get(native_function) {
+                    Some(FunctionForm::FunctionCall(id)) if *id == PSEUDO_KEYWORD_IDS["_push_line"] => helpers.push(builtins::PUSH_LINE),
+                    Some(FunctionForm::FunctionCall(id)) if *id == PSEUDO_KEYWORD_IDS["_console_contents"] => helpers.push(builtins::CONSOLE_CONTENTS),
+                    _ => {}
+                },
                 FunctionLogicDescriptor::TraitProvider(trait_) => {
-                    representations.function_forms.insert(Rc::clone(&native_function), FunctionForm::Constant(trait_.id));
+                    // Builtin traits may already be represented by a python type.
+                    representations.function_forms.entry(Arc::clone(native_function))
+                        .or_insert(FunctionForm::Constant(trait_.id));
                 }
                 FunctionLogicDescriptor::FunctionProvider(_) => {}
+                FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::EqualTo, .. } => {
+                    // Enums compare their variants outside the builtins module.
+                    representations.function_forms.entry(Arc::clone(native_function))
+                        .or_insert(FunctionForm::Binary(KEYWORD_IDS["=="]));
+                }
+                FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::NotEqualTo, .. } => {
+                    representations.function_forms.entry(Arc::clone(native_function))
+                        .or_insert(FunctionForm::Binary(KEYWORD_IDS["!="]));
+                }
+                FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Exp, type_ } if type_.is_int() => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_int_pow"]));
+                    helpers.push(builtins::CHECKED_INT_POW);
+                }
+                FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ToString, type_: primitives::Type::Bool } => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_bool_to_string"]));
+                    helpers.push(builtins::BOOL_TO_STRING);
+                }
+                FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::CharFromCode, .. } => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_chr"]));
+                    helpers.push(builtins::CHECKED_CHR);
+                }
                 FunctionLogicDescriptor::PrimitiveOperation { .. } => {}
+                FunctionLogicDescriptor::EnumVariant(_, _) => {}
                 FunctionLogicDescriptor::Constructor(_) => {}
                 FunctionLogicDescriptor::GetMemberField(_, _) => {}
                 FunctionLogicDescriptor::SetMemberField(_, _) => {}
+                // Native indexing wraps negative indices around, unlike the interpreter.
+                FunctionLogicDescriptor::ListOperation(ListOperation::Get) => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_list_get"]));
+                    helpers.push(match transpile.config.should_match_interpreter_errors {
+                        true => builtins::CHECKED_LIST_GET,
+                        false => builtins::NON_NEGATIVE_LIST_GET,
+                    });
+                }
+                FunctionLogicDescriptor::ListOperation(ListOperation::Set) => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_list_set"]));
+                    helpers.push(match transpile.config.should_match_interpreter_errors {
+                        true => builtins::CHECKED_LIST_SET,
+                        false => builtins::NON_NEGATIVE_LIST_SET,
+                    });
+                }
+                FunctionLogicDescriptor::ListOperation(_) => {}
+                FunctionLogicDescriptor::JsonOperation(_) => helpers.extend([builtins::JSON_VALUE, builtins::PARSE_JSON]),
+                // Calls are substituted during monomorphization, so they are never transpiled.
+                FunctionLogicDescriptor::Reflection(_) => {}
+                FunctionLogicDescriptor::DynBox(_) => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_dyn_box"]));
+                    helpers.push(builtins::DYN_BOX);
+                }
+                FunctionLogicDescriptor::DynDispatch(idx) => {
+                    representations.function_forms.insert(Arc::clone(native_function), FunctionForm::CallVtable(*idx));
+                }
+            }
+        }
+
+        for implementation in transpile.explicit_functions.iter().chain(transpile.implicit_functions.iter()) {
+            if imperative::has_failing_cast(implementation) {
+                helpers.push(builtins::CAST_ERROR);
             }
         }
 
@@ -166,16 +285,18 @@ impl Context {
         let mut names = global_namespace.map_names();
         names.extend(member_namespace.map_names());
 
-        let mut module = Box::new(ast::Module {
-            exported_statements: vec![],
-            internal_statements: vec![],
-            exported_names: HashSet::new(),
-            main_function: transpile.main_function.map(|head| names[&head.function_id].clone())
-        });
+        let mut statements = TopLevelStatements {
+            statements: vec![],
+            main_function: transpile.main_function.map(|head| names[&head.function_id].clone()),
+            // used_native_functions is a HashMap, so the order would vary between runs otherwise.
+            // Functions of different types may share a helper.
+            helpers: helpers.into_iter().sorted().dedup().collect(),
+            module_includes: transpile.module_includes,
+        };
 
-        let mut unestablished_structs = structs.keys().map(Rc::clone).collect();
+        let mut unestablished_structs = structs.keys().map(Arc::clone).collect();
         for (type_, struct_) in structs.iter() {
-            if builtin_structs.contains(type_) {
+            if builtin_structs.contains(type_) || representations.tuple_types.contains(type_) {
                 continue
             }
 
@@ -185,17 +306,36 @@ impl Context {
                 unestablished_structs: &unestablished_structs,
             };
 
-            let statement = Box::new(Statement::Class(transpile_class(type_, &context)));
+            let statement = Box::new(Statement::Class(transpile_class(type_, struct_, &context)));
             let id = &representations.type_ids[type_];
 
             // TODO Only classes used in the interface of exported functions should be exported.
             //  Everything else is an internal class.
-            module.exported_statements.push(statement);
-            module.exported_names.insert(names[id].clone());
+            statements.statements.push(TopLevelStatement {
+                statement,
+                name: names[id].clone(),
+                module: transpile.trait_modules.get(&struct_.trait_).cloned(),
+                is_exported: true,
+            });
 
             unestablished_structs.remove(type_);
         }
 
+        for (trait_, enum_) in enums.iter() {
+            let context = ClassContext {
+                names: &names,
+                representations: &representations,
+                unestablished_structs: &unestablished_structs,
+            };
+
+            statements.statements.push(TopLevelStatement {
+                statement: Box::new(Statement::Class(transpile_enum(enum_, &context))),
+                name: names[&trait_.id].clone(),
+                module: transpile.trait_modules.get(trait_).cloned(),
+                is_exported: true,
+            });
+        }
+
         for (implementations, is_exported) in [
             (&transpile.explicit_functions, true),
             (&transpile.implicit_functions, false),
@@ -209,18 +349,74 @@ impl Context {
                     logic: &transpile.used_native_functions,
                 };
 
-                let transpiled = transpile_function(implementation, &context);
+                statements.statements.push(TopLevelStatement {
+                    statement: transpile_function(implementation, &context),
+                    name: names[&implementation.head.function_id].clone(),
+                    module: transpile.fn_modules.get(&implementation.head).cloned(),
+                    is_exported,
+                });
+            }
+        }
 
-                if is_exported {
-                    module.exported_names.insert(names[&implementation.head.function_id].clone());
-                    module.exported_statements.push(transpiled);
-                }
-                else {
-                    module.internal_statements.push(transpiled);
-                }
+        Ok(statements)
+    }
+}
+
+/// A statement at the top level of the transpiled code.
+pub struct TopLevelStatement {
+    pub statement: Box<Statement>,
+    /// The name the statement declares.
+    pub name: String,
+    /// The module the statement was declared in, or None for the transpiled module.
+    pub module: Option<ModuleName>,
+    pub is_exported: bool,
+}
+
+/// Everything that was transpiled, before it is arranged into files.
+pub struct TopLevelStatements {
+    pub statements: Vec<TopLevelStatement>,
+    pub main_function: Option<String>,
+    pub helpers: Vec<&'static str>,
+    /// For the transpiled module (None) and each other module, the modules it includes.
+    pub module_includes: HashMap<Option<ModuleName>, Vec<(ModuleName, Option<String>)>>,
+}
+
+impl TopLevelStatements {
+    /// All statements in a single module.
+    pub fn into_module(self) -> Box<ast::Module> {
+        let mut module = Box::new(ast::Module {
+            exported_statements: vec![],
+            internal_statements: vec![],
+            exported_names: HashSet::new(),
+            main_function: self.main_function,
+            imports: vec![],
+            helpers: self.helpers,
+        });
+
+        for statement in self.statements {
+            if statement.is_exported {
+                module.exported_names.insert(statement.name);
+                module.exported_statements.push(statement.statement);
+            }
+            else {
+                module.internal_statements.push(statement.statement);
             }
         }
 
-        Ok(module)
+        module
+    }
+}
+
+pub fn write_module(module: &ast::Module) -> String {
+    format!("{}", with_options(module, &IndentOptions {
+        full_indentation: String::new(),
+        next_level: "    ",
+    }))
+}
+
+fn make_struct_id(type_: &TypeProto, struct_: &StructInfo) -> Uuid {
+    match type_.arguments.is_empty() {
+        true => struct_.trait_.id,
+        false => Uuid::new_v4(),
     }
 }
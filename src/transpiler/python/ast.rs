@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::fmt::Write;
@@ -17,6 +18,10 @@ pub struct Module {
 
     pub exported_names: HashSet<String>,
     pub main_function: Option<String>,
+    /// Imports of names from other files of the package, written after the standard imports.
+    pub imports: Vec<Import>,
+    /// Python source of helper functions the statements use, written after the imports.
+    pub helpers: Vec<&'static str>,
 }
 
 impl<'a> DisplayWithOptions<IndentOptions<'a>> for Module {
@@ -25,12 +30,26 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Module {
 
         writeln!(f, "import numpy as np")?;
         writeln!(f, "import math")?;
+        writeln!(f, "import copy")?;
+        if self.exported_statements.iter().chain(self.internal_statements.iter()).any(|statement| matches!(statement.as_ref(), Statement::Function(function) if function.is_cached)) {
+            writeln!(f, "import functools")?;
+        }
         writeln!(f, "import operator as op")?;
         writeln!(f, "from dataclasses import dataclass")?;
+        if self.exported_statements.iter().chain(self.internal_statements.iter()).any(|statement| matches!(statement.as_ref(), Statement::Class(class) if class.is_enum)) {
+            writeln!(f, "from enum import Enum")?;
+        }
         writeln!(f, "from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64")?;
         writeln!(f, "from typing import Any, Callable")?;
+        for import in self.imports.iter() {
+            writeln!(f, "{}", import)?;
+        }
         write!(f, "\n\n")?;
 
+        for helper in self.helpers.iter() {
+            write!(f, "{}\n\n", helper)?;
+        }
+
         for statement in self.exported_statements.iter() {
             write!(f, "{}\n\n", with_options(statement.as_ref(), &options.restart()))?;
         }
@@ -58,14 +77,42 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Module {
     }
 }
 
+/// from module import name, other as alias
+pub struct Import {
+    pub module: String,
+    /// Each imported name, and the alias it's imported as, if any.
+    pub names: Vec<(String, Option<String>)>,
+}
+
+impl Display for Import {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "from {} import ", self.module)?;
+        for (idx, (name, alias)) in self.names.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", name)?;
+            if let Some(alias) = alias {
+                write!(f, " as {}", alias)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct Class {
     pub name: String,
     pub block: Block,
+    /// Enums subclass Enum instead of being dataclasses.
+    pub is_enum: bool,
 }
 
 impl<'a> DisplayWithOptions<IndentOptions<'a>> for Class {
     fn fmt(&self, f: &mut Formatter, options: &IndentOptions) -> std::fmt::Result {
-        write!(f, "{}@dataclass\nclass {}:\n", options, self.name)?;
+        match self.is_enum {
+            true => writeln!(f, "{}class {}(Enum):", options, self.name)?,
+            false => write!(f, "{}@dataclass\nclass {}:\n", options, self.name)?,
+        }
 
         let options = options.deeper();
         let mut f = IndentingFormatter::new(f, &options.full_indentation);
@@ -97,6 +144,8 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Block {
 
 pub struct Function {
     pub name: String,
+    /// Cached functions are evaluated once, on their first call.
+    pub is_cached: bool,
 
     pub parameters: Vec<Box<Parameter>>,
     pub return_type: Option<Box<Expression>>,
@@ -105,6 +154,9 @@ pub struct Function {
 
 impl<'a> DisplayWithOptions<IndentOptions<'a>> for Function {
     fn fmt(&self, f: &mut Formatter, options: &IndentOptions) -> std::fmt::Result {
+        if self.is_cached {
+            writeln!(f, "{}@functools.cache", options)?;
+        }
         write!(f, "{}def {}(", options, self.name)?;
         for (idx, parameter) in self.parameters.iter().enumerate() {
             write!(f, "{}", parameter)?;
@@ -119,7 +171,7 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Function {
             write!(f, " -> {}", return_type)?;
         }
 
-        write!(f, ":\n")?;
+        writeln!(f, ":")?;
 
         let options = options.deeper();
         let mut f = IndentingFormatter::new(f, &options.full_indentation);
@@ -131,7 +183,7 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Function {
         if !self.parameters.is_empty() {
             write!(f, "\n\n{}Args:", options)?;
 
-            for (idx, parameter) in self.parameters.iter().enumerate() {
+            for parameter in self.parameters.iter() {
                 write!(f, "\n{}{}: <TODO>", indent_once, parameter.name)?;
             }
         }
@@ -151,6 +203,8 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Function {
 
 pub enum Statement {
     VariableAssignment { target: Box<Expression>, value: Option<Box<Expression>>, type_annotation: Option<Box<Expression>> },
+    /// Like x += y, for operator +.
+    AugmentedAssignment { target: Box<Expression>, operator: String, value: Box<Expression> },
     Expression(Box<Expression>),
     Return(Option<Box<Expression>>),
     Class(Box<Class>),
@@ -158,6 +212,62 @@ pub enum Statement {
     IfThenElse(Vec<(Box<Expression>, Box<Block>)>, Option<Box<Block>>),
 }
 
+impl Statement {
+    /// All names the statement refers to, including the ones it declares.
+    pub fn referenced_names(&self) -> HashSet<&str> {
+        let mut names = HashSet::new();
+        // Expressions can be nested deeply, so we keep our own stack instead of recursing.
+        let mut statements = vec![self];
+        let mut expressions = vec![];
+        while let Some(statement) = statements.pop() {
+            match statement {
+                Statement::VariableAssignment { target, value, type_annotation } => {
+                    expressions.push(target.as_ref());
+                    expressions.extend(value.iter().chain(type_annotation.iter()).map(Box::as_ref));
+                }
+                Statement::AugmentedAssignment { target, value, .. } => expressions.extend([target.as_ref(), value.as_ref()]),
+                Statement::Expression(expression) => expressions.push(expression),
+                Statement::Return(value) => expressions.extend(value.iter().map(Box::as_ref)),
+                Statement::Class(class) => {
+                    names.insert(class.name.as_str());
+                    statements.extend(class.block.statements.iter().map(Box::as_ref));
+                }
+                Statement::Function(function) => {
+                    names.insert(function.name.as_str());
+                    expressions.extend(function.parameters.iter().map(|parameter| parameter.type_.as_ref()));
+                    expressions.extend(function.return_type.iter().map(Box::as_ref));
+                    statements.extend(function.block.statements.iter().map(Box::as_ref));
+                }
+                Statement::IfThenElse(ifs, else_) => {
+                    for (condition, body) in ifs.iter() {
+                        expressions.push(condition);
+                        statements.extend(body.statements.iter().map(Box::as_ref));
+                    }
+                    statements.extend(else_.iter().flat_map(|body| body.statements.iter().map(Box::as_ref)));
+                }
+            }
+        }
+
+        while let Some(expression) = expressions.pop() {
+            match expression {
+                Expression::MemberAccess(target, _) => expressions.push(target),
+                Expression::Subscript(target, index) => expressions.extend([target.as_ref(), index.as_ref()]),
+                Expression::UnaryOperation(_, argument) => expressions.push(argument),
+                Expression::BinaryOperation(lhs, _, rhs) => expressions.extend([lhs.as_ref(), rhs.as_ref()]),
+                Expression::FunctionCall(target, arguments) => {
+                    expressions.push(target);
+                    expressions.extend(arguments.iter().map(|(_, argument)| argument.as_ref()));
+                }
+                Expression::ListLiteral(elements) | Expression::Tuple(elements) => expressions.extend(elements.iter().map(Box::as_ref)),
+                Expression::NamedReference(name) => _ = names.insert(name.as_str()),
+                Expression::StringLiteral(_) | Expression::ValueLiteral(_) => {},
+            }
+        }
+
+        names
+    }
+}
+
 impl<'a> DisplayWithOptions<IndentOptions<'a>> for Statement {
     fn fmt(&self, f: &mut Formatter, options: &IndentOptions) -> std::fmt::Result {
         match self {
@@ -175,6 +285,9 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Statement {
 
                 Ok(())
             }
+            Statement::AugmentedAssignment { target, operator, value } => {
+                writeln!(f, "{} {}= {}", target, operator, value)
+            }
             Statement::Expression(e) => writeln!(f, "{}", e),
             Statement::Return(Some(expression)) => {
                 writeln!(f, "return {}", expression)
@@ -187,10 +300,10 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Statement {
             Statement::IfThenElse(ifs, else_) => {
                 for (idx, (condition, body)) in ifs.iter().enumerate() {
                     if idx == 0 {
-                        write!(f, "if {}:\n", condition)?;
+                        writeln!(f, "if {}:", condition)?;
                     }
                     else {
-                        write!(f, "elif {}:\n", condition)?;
+                        writeln!(f, "elif {}:", condition)?;
                     }
 
                     let options = options.deeper();
@@ -200,7 +313,7 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Statement {
                     write!(f, "{}", with_options(body.as_ref(), &options))?;
                 }
                 if let Some(else_) = else_ {
-                    write!(f, "else:\n")?;
+                    writeln!(f, "else:")?;
 
                     let options = options.deeper();
                     let mut f = IndentingFormatter::new(f, &options.full_indentation);
@@ -217,12 +330,16 @@ impl<'a> DisplayWithOptions<IndentOptions<'a>> for Statement {
 
 pub enum Expression {
     MemberAccess(Box<Expression>, String),
+    Subscript(Box<Expression>, Box<Expression>),
     UnaryOperation(String, Box<Expression>),
     BinaryOperation(Box<Expression>, String, Box<Expression>),
     FunctionCall(Box<Expression>, Vec<(ParameterKey, Box<Expression>)>),
     NamedReference(String),
     StringLiteral(String),
     ValueLiteral(String),
+    ListLiteral(Vec<Box<Expression>>),
+    /// Written without parentheses where python allows it, like `return q, r`.
+    Tuple(Vec<Box<Expression>>),
 }
 
 impl Expression {
@@ -234,57 +351,123 @@ impl Expression {
             Expression::NamedReference(_) => true,
             Expression::StringLiteral(_) => true,
             Expression::ValueLiteral(_) => true,
+            Expression::ListLiteral(_) => true,
+            Expression::Tuple(_) => false,
             Expression::MemberAccess(_, _) => true,
+            Expression::Subscript(_, _) => true,
         }
     }
 }
 
+/// Binary operators for which python evaluates (a op b) op c like a op b op c.
+/// Comparisons are missing on purpose: python chains them, so a < b < c means (a < b) and (b < c).
+const LEFT_ASSOCIATIVE_OPERATORS: [&str; 11] = ["+", "-", "*", "/", "//", "%", "&", "|", "^", "<<", ">>"];
+
+/// A part of an expression's source, while it is being written.
+enum Piece<'a> {
+    Expression(&'a Expression),
+    Text(Cow<'a, str>),
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
-        match self {
-            Expression::UnaryOperation(op, ex) => {
-                if matches!(op.as_str(), "+" | "-") {
-                    write!(f, "{}", op)?;
-                }
-                else {
-                    write!(f, "{} ", op)?;
+        // Expressions can be nested deeply, so we keep our own stack instead of recursing.
+        let mut stack = vec![Piece::Expression(self)];
+        while let Some(piece) = stack.pop() {
+            let expression = match piece {
+                Piece::Expression(expression) => expression,
+                Piece::Text(text) => {
+                    write!(f, "{}", text)?;
+                    continue
                 }
-                write_maybe_parenthesized(f, ex, !ex.is_simple())
-            }
-            Expression::BinaryOperation(lhs, op, rhs) => {
-                write_maybe_parenthesized(f, lhs, !lhs.is_simple())?;
-                write!(f, " {} ", op)?;
-                write_maybe_parenthesized(f, rhs, !rhs.is_simple())
-            }
-            Expression::FunctionCall(name, params) => {
-                write!(f, "{}(", name)?;
+            };
 
-                for (i, (key, argument)) in params.iter().enumerate() {
-                    if let ParameterKey::Name(name) = key {
-                        write!(f, "{}=", name)?;
+            let mut pieces = vec![];
+            match expression {
+                Expression::UnaryOperation(op, ex) => {
+                    if matches!(op.as_str(), "+" | "-") {
+                        pieces.push(Piece::Text(op.into()));
+                    }
+                    else {
+                        pieces.push(Piece::Text(format!("{} ", op).into()));
+                    }
+                    push_maybe_parenthesized(&mut pieces, ex, !ex.is_simple());
+                }
+                Expression::BinaryOperation(lhs, op, rhs) => {
+                    // Chains like a + b + c group from the left, so their left side needs no parentheses.
+                    //  Python only allows 200 nested parentheses, so long chains depend on this.
+                    let is_chained = matches!(lhs.as_ref(), Expression::BinaryOperation(_, lhs_op, _) if lhs_op == op && LEFT_ASSOCIATIVE_OPERATORS.contains(&op.as_str()));
+                    push_maybe_parenthesized(&mut pieces, lhs, !lhs.is_simple() && !is_chained);
+                    pieces.push(Piece::Text(format!(" {} ", op).into()));
+                    push_maybe_parenthesized(&mut pieces, rhs, !rhs.is_simple());
+                }
+                Expression::FunctionCall(name, params) => {
+                    pieces.push(Piece::Expression(name));
+                    pieces.push(Piece::Text("(".into()));
+
+                    for (i, (key, argument)) in params.iter().enumerate() {
+                        if let ParameterKey::Name(name) = key {
+                            pieces.push(Piece::Text(format!("{}=", name).into()));
+                        }
+                        push_maybe_parenthesized(&mut pieces, argument, matches!(argument.as_ref(), Expression::Tuple(_)));
+
+                        if i < params.len() - 1 {
+                            pieces.push(Piece::Text(", ".into()));
+                        }
                     }
-                    write!(f, "{}", argument)?;
 
-                    if i < params.len() - 1 {
-                        write!(f, ", ")?;
+                    pieces.push(Piece::Text(")".into()));
+                }
+                Expression::NamedReference(v) => {
+                    pieces.push(Piece::Text(v.into()));
+                }
+                Expression::StringLiteral(v) => {
+                    pieces.push(Piece::Text(format!("\"{}\"", escape_string(v)).into()));
+                }
+                Expression::ValueLiteral(v) => {
+                    pieces.push(Piece::Text(v.into()));
+                }
+                Expression::ListLiteral(elements) => {
+                    pieces.push(Piece::Text("[".into()));
+                    for (i, element) in elements.iter().enumerate() {
+                        push_maybe_parenthesized(&mut pieces, element, matches!(element.as_ref(), Expression::Tuple(_)));
+
+                        if i < elements.len() - 1 {
+                            pieces.push(Piece::Text(", ".into()));
+                        }
                     }
+                    pieces.push(Piece::Text("]".into()));
                 }
+                Expression::Tuple(elements) => {
+                    for (i, element) in elements.iter().enumerate() {
+                        push_maybe_parenthesized(&mut pieces, element, matches!(element.as_ref(), Expression::Tuple(_)));
 
-                write!(f, ")")
-            }
-            Expression::NamedReference(v) => {
-                write!(f, "{}", v)
-            }
-            Expression::StringLiteral(v) => {
-                write!(f, "\"{}\"", escape_string(v))
-            }
-            Expression::ValueLiteral(v) => {
-                write!(f, "{}", v)
-            }
-            Expression::MemberAccess(e, m) => {
-                write!(f, "{}.{}", e, m)
+                        if i < elements.len() - 1 {
+                            pieces.push(Piece::Text(", ".into()));
+                        }
+                    }
+                    // (q,) is a tuple, but (q) isn't.
+                    if elements.len() == 1 {
+                        pieces.push(Piece::Text(",".into()));
+                    }
+                }
+                Expression::MemberAccess(e, m) => {
+                    push_maybe_parenthesized(&mut pieces, e, matches!(e.as_ref(), Expression::Tuple(_)));
+                    pieces.push(Piece::Text(format!(".{}", m).into()));
+                }
+                Expression::Subscript(e, i) => {
+                    push_maybe_parenthesized(&mut pieces, e, matches!(e.as_ref(), Expression::Tuple(_)));
+                    pieces.push(Piece::Text("[".into()));
+                    pieces.push(Piece::Expression(i));
+                    pieces.push(Piece::Text("]".into()));
+                }
             }
+
+            // The stack is popped from the back, so the pieces go on in reverse.
+            stack.extend(pieces.into_iter().rev());
         }
+
+        Ok(())
     }
 }
 
@@ -299,11 +482,13 @@ impl Display for Parameter {
     }
 }
 
-pub fn write_maybe_parenthesized<D: Display>(f: &mut Formatter, d: D, parenthesize: bool) -> std::fmt::Result {
+fn push_maybe_parenthesized<'a>(pieces: &mut Vec<Piece<'a>>, expression: &'a Expression, parenthesize: bool) {
     if parenthesize {
-        write!(f, "({})", d)
+        pieces.push(Piece::Text("(".into()));
+        pieces.push(Piece::Expression(expression));
+        pieces.push(Piece::Text(")".into()));
     }
     else {
-        write!(f, "{}", d)
+        pieces.push(Piece::Expression(expression));
     }
 }
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::interpreter::runtime::Runtime;
-use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, PrimitiveOperation};
+use crate::program::global::{FunctionLogic, FunctionLogicDescriptor, JsonOperation, ListOperation, PrimitiveOperation};
 use crate::program::module::module_name;
 use crate::program::primitives;
 use crate::program::types::TypeProto;
@@ -10,6 +10,114 @@ use crate::transpiler::python::{Context, keywords};
 use crate::transpiler::python::keywords::{KEYWORD_IDS, PSEUDO_KEYWORD_IDS};
 use crate::transpiler::python::representations::FunctionForm;
 
+/// Native indexing wraps negative indices around, so these reject them first, like the interpreter.
+/// Other indices out of bounds fail in native indexing already.
+pub const NON_NEGATIVE_LIST_GET: &str = "def _list_get(sequence, index):
+    if index < 0:
+        raise IndexError(\"list index out of range\")
+    return sequence[index]";
+pub const NON_NEGATIVE_LIST_SET: &str = "def _list_set(sequence, index, value):
+    if index < 0:
+        raise IndexError(\"list assignment index out of range\")
+    sequence[index] = value";
+
+/// Like the interpreter, these reject indices out of bounds, including negative ones, with its message.
+/// Used instead of NON_NEGATIVE_LIST_GET and NON_NEGATIVE_LIST_SET with Config::should_match_interpreter_errors.
+pub const CHECKED_LIST_GET: &str = "def _list_get(sequence, index):
+    if not 0 <= index < len(sequence):
+        raise IndexError(f\"index {index} out of bounds for length {len(sequence)}\")
+    return sequence[index]";
+pub const CHECKED_LIST_SET: &str = "def _list_set(sequence, index, value):
+    if not 0 <= index < len(sequence):
+        raise IndexError(f\"index {index} out of bounds for length {len(sequence)}\")
+    sequence[index] = value";
+
+/// Like the interpreter, this rejects exponents that don't fit into a u32, instead of producing a float.
+pub const CHECKED_INT_POW: &str = "def _int_pow(base, exponent):
+    if exponent < 0:
+        raise ValueError(\"integer pow with negative exponent\")
+    if exponent > 4294967295:
+        raise ValueError(f\"integer pow with exponent {exponent} above 4294967295\")
+    return base ** exponent";
+
+/// Like the interpreter, this writes bools in lowercase, while python capitalizes them.
+pub const BOOL_TO_STRING: &str = "def _bool_to_string(value):
+    return \"true\" if value else \"false\"";
+
+/// Like the interpreter, this rejects surrogates, which python's chr accepts, and fails with its message.
+pub const CHECKED_CHR: &str = "def _chr(code):
+    if 0xD800 <= code <= 0xDFFF or code > 0x10FFFF:
+        raise ValueError(f\"Not a valid character code: {code}.\")
+    return chr(code)";
+
+/// Boxes a value as its vtable's functions, bound to the value.
+pub const DYN_BOX: &str = "def _dyn_box(value, *vtable):
+    import functools
+    return [functools.partial(function, value) for function in vtable]";
+
+/// Fails a cast with the interpreter's message. Casts are expressions, so they can't raise themselves.
+pub const CAST_ERROR: &str = "def _cast_error(message):
+    raise TypeError(message)";
+
+/// StringConsole has no fields, so its lines are attached to the object when it's first written to.
+pub const PUSH_LINE: &str = "def _push_line(console, value):
+    console.__dict__.setdefault(\"lines\", []).append(value)";
+pub const CONSOLE_CONTENTS: &str = "def _console_contents(console):
+    return \"\".join(line + \"\\n\" for line in console.__dict__.get(\"lines\", []))";
+
+/// Wraps the values json.loads returns, with the accessors of Json.
+/// Like in the interpreter, lookups that find nothing return null instead of failing.
+pub const JSON_VALUE: &str = "class _Json:
+    def __init__(self, value):
+        self.value = value
+
+    def get(self, key):
+        return _Json(self.value.get(key) if isinstance(self.value, dict) else None)
+
+    def at(self, index):
+        if isinstance(self.value, list) and 0 <= index < len(self.value):
+            return _Json(self.value[index])
+        return _Json(None)
+
+    def __len__(self):
+        return len(self.value) if isinstance(self.value, (list, dict)) else 0
+
+    def kind(self):
+        if self.value is None:
+            return \"null\"
+        if isinstance(self.value, bool):
+            return \"bool\"
+        if isinstance(self.value, (int, float)):
+            return \"number\"
+        if isinstance(self.value, str):
+            return \"string\"
+        return \"array\" if isinstance(self.value, list) else \"object\"
+
+    def _expect(self, kind):
+        if self.kind() != kind:
+            raise TypeError(f\"Expected a JSON {kind}, but got {self.kind()}.\")
+        return self.value
+
+    def as_string(self):
+        return self._expect(\"string\")
+
+    def as_float(self):
+        return float64(self._expect(\"number\"))
+
+    def as_bool(self):
+        return self._expect(\"bool\")";
+/// Errors name the position like the interpreter does. NaN and Infinity are rejected, like in the interpreter.
+pub const PARSE_JSON: &str = "def _parse_json(string):
+    import json
+
+    def reject_constant(constant):
+        raise ValueError(f\"Invalid JSON: {constant} is not allowed.\")
+
+    try:
+        return _Json(json.loads(string, parse_constant=reject_constant))
+    except json.JSONDecodeError as error:
+        raise ValueError(f\"Invalid JSON at line {error.lineno}, char {error.colno}: {error.msg}.\") from None";
+
 pub fn register_global(runtime: &Runtime, context: &mut Context) {
     let representations = &mut context.representations;
     let global = &mut context.builtin_global_namespace;
@@ -30,6 +138,7 @@ pub fn register_global(runtime: &Runtime, context: &mut Context) {
         (primitives::Type::UInt(64), "uint64"),
         (primitives::Type::Float(32), "float32"),
         (primitives::Type::Float(64), "float64"),
+        (primitives::Type::Char, "str"),
     ]);
 
     // The operators can normally be referenced as operators (which the transpiler does do).
@@ -39,93 +148,131 @@ pub fn register_global(runtime: &Runtime, context: &mut Context) {
             continue;
         };
 
-        let (higher_order_ref_name, representation) = match descriptor {
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::EqualTo, type_ } => {
+        let (_higher_order_ref_name, representation) = match descriptor {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::EqualTo, type_: _ } => {
                 ("op.eq", FunctionForm::Binary(KEYWORD_IDS["=="]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::NotEqualTo, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::NotEqualTo, type_: _ } => {
                 ("op.ne", FunctionForm::Binary(KEYWORD_IDS["!="]))
             }
 
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::GreaterThan, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::GreaterThan, type_: _ } => {
                 ("op.gt", FunctionForm::Binary(KEYWORD_IDS[">"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::LesserThan, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::LesserThan, type_: _ } => {
                 ("op.lt", FunctionForm::Binary(KEYWORD_IDS["<"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::GreaterThanOrEqual, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::GreaterThanOrEqual, type_: _ } => {
                 ("op.ge", FunctionForm::Binary(KEYWORD_IDS[">="]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::LesserThanOrEqual, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::LesserThanOrEqual, type_: _ } => {
                 ("op.le", FunctionForm::Binary(KEYWORD_IDS["<="]))
             }
 
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::And, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::And, type_: _ } => {
                 ("op.and_", FunctionForm::Binary(KEYWORD_IDS["and"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Or, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Or, type_: _ } => {
                 ("op.or_", FunctionForm::Binary(KEYWORD_IDS["or"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Not, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Not, type_: _ } => {
                 ("op.not_", FunctionForm::Unary(KEYWORD_IDS["not"]))
             }
 
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Negative, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Negative, type_: _ } => {
                 ("op.neg", FunctionForm::Unary(KEYWORD_IDS["-"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Add, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Add, type_: _ } => {
                 ("op.add", FunctionForm::Binary(KEYWORD_IDS["+"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Subtract, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Subtract, type_: _ } => {
                 ("op.sub", FunctionForm::Binary(KEYWORD_IDS["-"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Multiply, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Multiply, type_: _ } => {
                 ("op.mul", FunctionForm::Binary(KEYWORD_IDS["*"]))
             }
             FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Divide, type_ } => {
                 match type_.is_int() {
-                    true => ("op.truediv", FunctionForm::Binary(KEYWORD_IDS["//"])),
-                    false => ("op.div", FunctionForm::Binary(KEYWORD_IDS["//"])),
+                    true => ("op.floordiv", FunctionForm::Binary(KEYWORD_IDS["//"])),
+                    false => ("op.truediv", FunctionForm::Binary(KEYWORD_IDS["/"])),
                 }
             }
 
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Modulo, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Modulo, type_: _ } => {
                 ("op.mod", FunctionForm::Binary(KEYWORD_IDS["%"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Exp, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Exp, type_: _ } => {
                 ("op.pow", FunctionForm::Binary(KEYWORD_IDS["**"]))
             }
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Log, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::Log, type_: _ } => {
                 ("math.log", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["math.log"]))
             }
 
-            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ToString, type_ } => {
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ToString, type_: _ } => {
                 ("str", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["str"]))
             }
 
             FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ParseIntString, type_ }
-            | FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ParseRealString, type_ } => {
+            | FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ParseRealString, type_ }
+            | FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::ParseCharString, type_ } => {
                 if let Some(builtin_name) = primitive_map.get(type_) {
-                    (builtin_name.clone(), FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS[builtin_name]))
+                    (*builtin_name, FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS[builtin_name]))
                 }
                 else {
                     continue
                 }
             }
 
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::CharFromCode, .. } => {
+                ("chr", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["chr"]))
+            }
+            FunctionLogicDescriptor::PrimitiveOperation { operation: PrimitiveOperation::CharToCode, .. } => {
+                ("ord", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["ord"]))
+            }
+
+            FunctionLogicDescriptor::ListOperation(operation) => match operation {
+                ListOperation::New => ("list", FunctionForm::CallAsFunction),
+                ListOperation::Push => ("list.append", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["append"])),
+                ListOperation::Pop => ("list.pop", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["pop"])),
+                ListOperation::Length => ("len", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["len"])),
+                ListOperation::Get => ("op.getitem", FunctionForm::GetItem),
+                ListOperation::Set => ("op.setitem", FunctionForm::SetItem),
+                ListOperation::FromChars => ("list", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["list"])),
+            },
+            FunctionLogicDescriptor::JsonOperation(operation) => match operation {
+                JsonOperation::Parse => ("_parse_json", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["_parse_json"])),
+                JsonOperation::Get => ("_Json.get", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["get"])),
+                JsonOperation::At => ("_Json.at", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["at"])),
+                JsonOperation::Length => ("len", FunctionForm::FunctionCall(PSEUDO_KEYWORD_IDS["len"])),
+                JsonOperation::Kind => ("_Json.kind", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["kind"])),
+                JsonOperation::AsString => ("_Json.as_string", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["as_string"])),
+                JsonOperation::AsFloat => ("_Json.as_float", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["as_float"])),
+                JsonOperation::AsBool => ("_Json.as_bool", FunctionForm::MemberCall(PSEUDO_KEYWORD_IDS["as_bool"])),
+            },
+            FunctionLogicDescriptor::TraitProvider(trait_) if trait_ == &runtime.traits.as_ref().unwrap().List => {
+                ("list", FunctionForm::Constant(PSEUDO_KEYWORD_IDS["list"]))
+            }
+
             FunctionLogicDescriptor::Constructor(_) => continue,
             FunctionLogicDescriptor::GetMemberField(_, _) => continue,
             FunctionLogicDescriptor::SetMemberField(_, _) => continue,
+            FunctionLogicDescriptor::EnumVariant(_, _) => continue,
             FunctionLogicDescriptor::Stub => continue,
             FunctionLogicDescriptor::TraitProvider(_) => continue,
             FunctionLogicDescriptor::FunctionProvider(_) => continue,
+            FunctionLogicDescriptor::Reflection(_) => continue,
+            FunctionLogicDescriptor::DynBox(_) => continue,
+            FunctionLogicDescriptor::DynDispatch(_) => continue,
         };
 
-        representations.function_forms.insert(Rc::clone(function), representation);
+        representations.function_forms.insert(Arc::clone(function), representation);
     }
 
     for (struct_, id) in [
         (&runtime.traits.as_ref().unwrap().String, PSEUDO_KEYWORD_IDS["str"]),
+        (&runtime.traits.as_ref().unwrap().List, PSEUDO_KEYWORD_IDS["list"]),
+        (&runtime.traits.as_ref().unwrap().Json, PSEUDO_KEYWORD_IDS["_Json"]),
+        (&runtime.traits.as_ref().unwrap().Dyn, PSEUDO_KEYWORD_IDS["list"]),
     ].into_iter() {
         representations.type_ids.insert(TypeProto::unit_struct(struct_), id);
     }
@@ -133,6 +280,7 @@ pub fn register_global(runtime: &Runtime, context: &mut Context) {
     for (primitive, name) in primitive_map.iter() {
         let struct_ = &runtime.primitives.as_ref().unwrap()[primitive];
         representations.type_ids.insert(TypeProto::unit_struct(struct_), PSEUDO_KEYWORD_IDS[name]);
+        representations.primitive_types.insert(TypeProto::unit_struct(struct_));
     }
 
     // TODO Some of these sneakily convert the type - especially float to int and vice versa.
@@ -164,7 +312,7 @@ pub fn register_global(runtime: &Runtime, context: &mut Context) {
             };
 
             // By the time we need other representations hopefully we can use object namespaces
-            representations.function_forms.insert(Rc::clone(function), FunctionForm::FunctionCall(id));
+            representations.function_forms.insert(Arc::clone(function), FunctionForm::FunctionCall(id));
         }
     }
 
@@ -177,29 +325,60 @@ pub fn register_global(runtime: &Runtime, context: &mut Context) {
             _ => continue,
         };
 
-        representations.function_forms.insert(Rc::clone(function), FunctionForm::FunctionCall(id));
+        representations.function_forms.insert(Arc::clone(function), FunctionForm::FunctionCall(id));
+    }
+
+    for function in runtime.source.module_by_name[&module_name("core.console")].explicit_functions(&runtime.source) {
+        let representation = &runtime.source.fn_representations[function];
+
+        let id = match representation.name.as_str() {
+            "_push_line" => PSEUDO_KEYWORD_IDS["_push_line"],
+            "contents" => PSEUDO_KEYWORD_IDS["_console_contents"],
+            _ => continue,
+        };
+
+        representations.function_forms.insert(Arc::clone(function), FunctionForm::FunctionCall(id));
     }
 
     for function in runtime.source.module_by_name[&module_name("core.strings")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        let (higher_order_name, id) = match representation.name.as_str() {
+        let (_higher_order_name, id) = match representation.name.as_str() {
             "add" => ("op.add", FunctionForm::Binary(KEYWORD_IDS["+"])),
+            "_is_equal_string" => ("op.eq", FunctionForm::Binary(KEYWORD_IDS["=="])),
+            "_is_not_equal_string" => ("op.ne", FunctionForm::Binary(KEYWORD_IDS["!="])),
+            "_is_greater_string" => ("op.gt", FunctionForm::Binary(KEYWORD_IDS[">"])),
+            "_is_greater_or_equal_string" => ("op.ge", FunctionForm::Binary(KEYWORD_IDS[">="])),
+            "_is_lesser_string" => ("op.lt", FunctionForm::Binary(KEYWORD_IDS["<"])),
+            "_is_lesser_or_equal_string" => ("op.le", FunctionForm::Binary(KEYWORD_IDS["<="])),
+            _ => continue,
+        };
+
+        representations.function_forms.insert(Arc::clone(function), id);
+    }
+
+    // Python's builtins order primitives like their implementations in core.order, but other types may order differently.
+    for function in runtime.source.module_by_name[&module_name("core.order")].explicit_functions(&runtime.source) {
+        let representation = &runtime.source.fn_representations[function];
+
+        let id = match representation.name.as_str() {
+            "min" => PSEUDO_KEYWORD_IDS["min"],
+            "max" => PSEUDO_KEYWORD_IDS["max"],
             _ => continue,
         };
 
-        representations.function_forms.insert(Rc::clone(function), id);
+        representations.primitive_instantiation_forms.insert(Arc::clone(function), FunctionForm::FunctionCall(id));
     }
 
     for function in runtime.source.module_by_name[&module_name("core.bool")].explicit_functions(&runtime.source) {
         let representation = &runtime.source.fn_representations[function];
 
-        let (higher_order_name, id) = match representation.name.as_str() {
+        let (_higher_order_name, id) = match representation.name.as_str() {
             "true" => ("True", FunctionForm::Constant(KEYWORD_IDS["True"])),
             "false" => ("False", FunctionForm::Constant(KEYWORD_IDS["False"])),
             _ => continue,
         };
 
-        representations.function_forms.insert(Rc::clone(function), id);
+        representations.function_forms.insert(Arc::clone(function), id);
     }
 }
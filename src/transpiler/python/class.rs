@@ -1,9 +1,10 @@
 use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use uuid::Uuid;
 
 use crate::program::types::{TypeProto, TypeUnit};
+use crate::source::{EnumInfo, StructInfo};
 use crate::transpiler::python::ast;
 use crate::transpiler::python::ast::Block;
 use crate::transpiler::python::representations::Representations;
@@ -11,20 +12,29 @@ use crate::transpiler::python::representations::Representations;
 pub struct ClassContext<'a> {
     pub names: &'a HashMap<Uuid, String>,
     pub representations: &'a Representations,
-    pub unestablished_structs: &'a HashSet<Rc<TypeProto>>,
+    pub unestablished_structs: &'a HashSet<Arc<TypeProto>>,
 }
 
-pub fn transpile_class(type_def: &TypeProto, context: &ClassContext) -> Box<ast::Class> {
+pub fn transpile_class(type_def: &TypeProto, struct_: &StructInfo, context: &ClassContext) -> Box<ast::Class> {
     // TODO If the type has no variables, we can fold it away from the program entirely
     let struct_id = context.representations.type_ids[type_def];
     let mut statements = vec![];
 
-    // TODO Need to account for bindings
+    // Generic fields are annotated with the types of this specialization.
+    let generic_map: HashMap<_, _> = struct_.constructor.interface.return_type.arguments.iter()
+        .zip(type_def.arguments.iter())
+        .filter_map(|(generic, type_)| match &generic.unit {
+            TypeUnit::Struct(generic) => Some((Arc::clone(generic), Arc::clone(type_))),
+            _ => None,
+        })
+        .collect();
+
     match &type_def.unit {
-        TypeUnit::Struct(struct_) => {
-            for hint in &struct_.field_hints {
-                let is_established = !context.unestablished_structs.contains(&hint.type_);
-                let type_string = context.names[&context.representations.type_ids[&hint.type_]].clone();
+        TypeUnit::Struct(trait_) => {
+            for hint in &trait_.field_hints {
+                let field_type = hint.type_.replacing_structs(&generic_map);
+                let is_established = !context.unestablished_structs.contains(&field_type);
+                let type_string = context.names[&context.representations.type_ids[&field_type]].clone();
 
                 statements.push(Box::new(ast::Statement::VariableAssignment {
                     target: Box::new(ast::Expression::NamedReference(hint.name.clone())),
@@ -42,5 +52,23 @@ pub fn transpile_class(type_def: &TypeProto, context: &ClassContext) -> Box<ast:
     Box::new(ast::Class {
         name: context.names[&struct_id].clone(),
         block: Block { statements },
+        is_enum: false,
+    })
+}
+
+pub fn transpile_enum(enum_: &EnumInfo, context: &ClassContext) -> Box<ast::Class> {
+    // Variants are assigned their index, like in the interpreter.
+    let statements = enum_.variants.iter().enumerate().map(|(idx, variant)| {
+        Box::new(ast::Statement::VariableAssignment {
+            target: Box::new(ast::Expression::NamedReference(context.names[&variant.function_id].clone())),
+            value: Some(Box::new(ast::Expression::ValueLiteral(idx.to_string()))),
+            type_annotation: None,
+        })
+    }).collect();
+
+    Box::new(ast::Class {
+        name: context.names[&enum_.trait_.id].clone(),
+        block: Block { statements },
+        is_enum: true,
     })
 }
@@ -1,23 +1,26 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use itertools::{Either, Itertools, zip_eq};
 use itertools::Either::{Left, Right};
 use regex;
 use uuid::Uuid;
 
+use crate::program::allocation::{Mutability, ObjectReference};
 use crate::program::expression_tree::*;
 use crate::program::functions::{FunctionHead, ParameterKey};
 use crate::program::generics::TypeForest;
 use crate::program::global::{FunctionImplementation, FunctionLogicDescriptor, PrimitiveOperation};
+use crate::program::types::TypeProto;
 use crate::transpiler::python::{ast, types};
+use crate::transpiler::python::keywords::PSEUDO_KEYWORD_IDS;
 use crate::transpiler::python::representations::{FunctionForm, Representations};
 
 pub struct FunctionContext<'a> {
     pub names: &'a HashMap<Uuid, String>,
 
     pub representations: &'a Representations,
-    pub logic: &'a HashMap<Rc<FunctionHead>, FunctionLogicDescriptor>,
+    pub logic: &'a HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>,
 
     pub expressions: &'a ExpressionTree,
     pub types: &'a TypeForest,
@@ -34,17 +37,21 @@ pub fn transpile_function(implementation: &FunctionImplementation, context: &Fun
         }
         FunctionForm::FunctionCall(id) => Box::new(ast::Statement::Function(transpile_plain_function(implementation, context.names[id].clone(), context))),
         FunctionForm::CallAsFunction => panic!(),
-        FunctionForm::GetMemberField(id) => panic!(),
+        FunctionForm::GetMemberField(_id) => panic!(),
         FunctionForm::SetMemberField(_) => panic!(),
-        FunctionForm::MemberCall(id) => panic!(),
-        FunctionForm::Unary(id) => panic!("Internal Error: Custom static unary functions are not supported in python"),
-        FunctionForm::Binary(id) => panic!("Internal Error: Custom static binary functions are not supported in python"),
+        FunctionForm::MemberCall(_id) => panic!(),
+        FunctionForm::GetItem => panic!(),
+        FunctionForm::SetItem => panic!(),
+        FunctionForm::Unary(_id) => panic!("Internal Error: Custom static unary functions are not supported in python"),
+        FunctionForm::Binary(_id) => panic!("Internal Error: Custom static binary functions are not supported in python"),
+        FunctionForm::CallVtable(_) => panic!(),
     }
 }
 
 pub fn transpile_plain_function(implementation: &FunctionImplementation, name: String, context: &FunctionContext) -> Box<ast::Function> {
     let mut syntax = Box::new(ast::Function {
         name,
+        is_cached: context.representations.cached_functions.contains(&implementation.head),
         parameters: implementation.parameter_locals.iter().map(|parameter| {
             Box::new(ast::Parameter {
                 name: context.names[&parameter.id].clone(),
@@ -85,82 +92,376 @@ pub fn transpile_plain_function(implementation: &FunctionImplementation, name: S
     //     };
     // }
 
-    syntax.block = transpile_as_block(implementation, context, &implementation.expression_tree.root, true);
+    // The body's value is returned, unless the function is void.
+    let auto_return = !implementation.head.interface.return_type.unit.is_void();
+    syntax.block = transpile_as_block(implementation, context, &implementation.expression_tree.root, auto_return);
 
     syntax
 }
 
-fn transpile_block(implementation: &FunctionImplementation, context: &FunctionContext, statements: &Vec<ExpressionID>) -> Box<ast::Block> {
-    let mut statements_ = vec![];
-
-    for statement in statements.iter() {
-        let operation = &implementation.expression_tree.values[&statement];
-        statements_.push(match operation {
-            ExpressionOperation::Block => todo!(),
-            ExpressionOperation::SetLocal(variable) => {
-                Box::new(ast::Statement::VariableAssignment {
-                    target: Box::new(ast::Expression::NamedReference(context.names[&variable.id].clone())),
-                    value: Some(transpile_expression(implementation.expression_tree.children[&statement][0], context)),
-                    // TODO We can omit the type annotation if we assign the variable a second time
-                    type_annotation: Some(types::transpile(&implementation.type_forest.resolve_type(&variable.type_).unwrap(), context)),
-                })
+/// Transpiles the statement into the python statements.
+/// Nested blocks are flattened, since their locals are named uniquely anyway.
+/// If auto_return is set, the statement's value is returned (for blocks, the value of their last statement).
+fn transpile_statement(implementation: &FunctionImplementation, context: &FunctionContext, statement: &ExpressionID, auto_return: bool, statements: &mut Vec<Box<ast::Statement>>) {
+    let operation = &implementation.expression_tree.values[statement];
+    match operation {
+        ExpressionOperation::Block => {
+            let children = &implementation.expression_tree.children[statement];
+            let mut idx = 0;
+            while idx < children.len() {
+                if let Some(fields) = find_tuple_destructuring(implementation, context.representations, context.logic, &children[idx..]) {
+                    let Some(value) = transpile_value(implementation, context, &implementation.expression_tree.children[&children[idx]][0], statements) else {
+                        return  // The value returned before it was assigned.
+                    };
+                    statements.push(Box::new(ast::Statement::VariableAssignment {
+                        target: tuple_destructuring_targets(implementation, context, &children[idx], &fields),
+                        value: Some(value),
+                        type_annotation: None,
+                    }));
+                    idx += fields.len() + 1;
+                    continue
+                }
+
+                transpile_statement(implementation, context, &children[idx], auto_return && idx == children.len() - 1, statements);
+                idx += 1;
             }
-            ExpressionOperation::Return => {
-                let value = implementation.expression_tree.children[&statement].iter().exactly_one().ok();
-                Box::new(ast::Statement::Return(value.map(|value| transpile_expression(*value, context))))
+        }
+        ExpressionOperation::SetLocal(variable) => {
+            let value_id = implementation.expression_tree.children[statement][0];
+            let Some(mut value) = transpile_value(implementation, context, &value_id, statements) else {
+                return  // The value returned before it was assigned.
+            };
+
+            // Python passes objects by reference. If a struct passed to a non-mut parameter is assigned
+            //  to a mutable variable, it would be modified from the outside. So we copy it first, like the VM.
+            if let ExpressionOperation::GetLocal(local) = &implementation.expression_tree.values[&value_id] {
+                let is_class = context.representations.class_types.contains(&implementation.type_forest.resolve_type(&variable.type_).unwrap());
+                if variable.mutability == Mutability::Mutable && local.mutability == Mutability::Immutable && implementation.parameter_locals.contains(local) && is_class {
+                    value = Box::new(ast::Expression::FunctionCall(
+                        Box::new(ast::Expression::NamedReference(context.names[&PSEUDO_KEYWORD_IDS["copy.copy"]].clone())),
+                        vec![(ParameterKey::Positional, value)]
+                    ));
+                }
             }
-            ExpressionOperation::FunctionCall(call) => {
-                match transpile_function_call(context, &call.function, &context.representations.function_forms[&call.function], *statement) {
-                    Left(e) => Box::new(ast::Statement::Expression(e)),
-                    Right(s) => s,
+
+            let name = &context.names[&variable.id];
+            statements.push(Box::new(match *value {
+                // x = x + y is written as x += y.
+                ast::Expression::BinaryOperation(lhs, operator, rhs) if is_augmentable(&operator) && matches!(lhs.as_ref(), ast::Expression::NamedReference(lhs_name) if lhs_name == name) => {
+                    ast::Statement::AugmentedAssignment {
+                        target: lhs,
+                        operator,
+                        value: rhs,
+                    }
+                }
+                value => ast::Statement::VariableAssignment {
+                    target: Box::new(ast::Expression::NamedReference(name.clone())),
+                    value: Some(Box::new(value)),
+                    // TODO We can omit the type annotation if we assign the variable a second time
+                    type_annotation: Some(types::transpile(&implementation.type_forest.resolve_type(&variable.type_).unwrap(), context)),
+                },
+            }))
+        }
+        ExpressionOperation::Return => {
+            let value = match implementation.expression_tree.children[statement].iter().exactly_one() {
+                Ok(value_id) => match transpile_value(implementation, context, value_id, statements) {
+                    Some(value) => Some(value),
+                    None => return,  // The value returned by itself.
+                },
+                Err(_) => None,
+            };
+            statements.push(Box::new(ast::Statement::Return(value)))
+        }
+        ExpressionOperation::IfThenElse => {
+            // Build up elifs from nested if else { if } expressions
+            let mut current_if = Some((
+                &implementation.expression_tree.values[statement],
+                statement
+            ));
+            let mut if_thens = vec![];
+
+            while let Some((ExpressionOperation::IfThenElse, expression)) = current_if {
+                let children = &implementation.expression_tree.children[expression];
+                // Statements can't be put before an elif, so conditions with block arguments are nested in the else instead.
+                if !if_thens.is_empty() && !block_arguments_of(&implementation.expression_tree, &children[0]).is_empty() {
+                    break
                 }
+                if !hoist_block_arguments(implementation, context, &children[0], statements) {
+                    return
+                }
+                let condition = transpile_expression(children[0], context);
+                let consequent = transpile_as_block(implementation, context, &children[1], auto_return);
+
+                if_thens.push((condition, consequent));
+                current_if = children.get(2).map(|a| (&implementation.expression_tree.values[a], a));
+            };
+
+            let alternative = current_if.map(|(_, a)| transpile_as_block(implementation, context, a, auto_return));
+
+            statements.push(Box::new(ast::Statement::IfThenElse(if_thens, alternative)))
+        }
+        _ => {
+            if !hoist_block_arguments(implementation, context, statement, statements) {
+                return  // A block argument returned before the statement ran.
             }
-            ExpressionOperation::IfThenElse => {
-                // Build up elifs from nested if else { if } expressions
-                let mut current_if = Some((
-                    &implementation.expression_tree.values[statement],
-                    statement
-                ));
-                let mut if_thens = vec![];
-
-                while let Some((ExpressionOperation::IfThenElse, expression)) = current_if {
-                    let children = &implementation.expression_tree.children[expression];
-                    let condition = transpile_expression(children[0], context);
-                    let consequent = transpile_as_block(implementation, context, &children[1], false);
-
-                    if_thens.push((condition, consequent));
-                    current_if = children.get(2).map(|a| (&implementation.expression_tree.values[a], a));
-                };
 
-                let alternative = current_if.map(|(_, a)| transpile_as_block(implementation, context, a, false));
+            let expression = match operation {
+                ExpressionOperation::FunctionCall(call) => {
+                    match with_arguments(*statement, context, |statement, context, transpiled| transpile_function_call(context, &call.function, &context.representations.function_forms[&call.function], statement, transpiled)) {
+                        Left(e) => e,
+                        Right(s) => {
+                            statements.push(s);
+                            return
+                        }
+                    }
+                }
+                _ => transpile_expression(*statement, context),
+            };
 
-                Box::new(ast::Statement::IfThenElse(if_thens, alternative))
+            let is_void = implementation.type_forest.resolve_binding_alias(statement).unwrap().unit.is_void();
+            statements.push(Box::new(match auto_return && !is_void {
+                true => ast::Statement::Return(Some(expression)),
+                false => ast::Statement::Expression(expression),
+            }))
+        }
+    }
+}
+
+/// If the first statement assigns a local that only the following statements read, each assigning another field of it
+///  to a local of its own (like `let (q, r) = divmod(a, b);` does), the index of each field and the statement assigning it.
+pub fn find_destructuring(tree: &ExpressionTree, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>, statements: &[ExpressionID]) -> Option<Vec<(usize, ExpressionID)>> {
+    let (first, following) = statements.split_first()?;
+    let ExpressionOperation::SetLocal(object) = &tree.values[first] else {
+        return None
+    };
+
+    let fields = following.iter().map_while(|statement| {
+        let ExpressionOperation::SetLocal(_) = &tree.values[statement] else {
+            return None
+        };
+        let value = &tree.children[statement][0];
+        let ExpressionOperation::FunctionCall(call) = &tree.values[value] else {
+            return None
+        };
+        let Some(FunctionLogicDescriptor::GetMemberField(struct_, field)) = logic.get(&call.function) else {
+            return None
+        };
+        match &tree.values[&tree.children[value][0]] {
+            ExpressionOperation::GetLocal(local) if local == object => Some((struct_.fields.iter().position(|f| f == field).unwrap(), *statement)),
+            _ => None,
+        }
+    }).collect_vec();
+
+    let reads = tree.values.values().filter(|operation| matches!(operation, ExpressionOperation::GetLocal(local) if local == object)).count();
+    (!fields.is_empty() && reads == fields.len() && fields.iter().map(|(idx, _)| idx).all_unique()).then_some(fields)
+}
+
+/// Like find_destructuring, if the destructured local is a tuple.
+fn find_tuple_destructuring(implementation: &FunctionImplementation, representations: &Representations, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>, statements: &[ExpressionID]) -> Option<Vec<(usize, ExpressionID)>> {
+    let fields = find_destructuring(&implementation.expression_tree, logic, statements)?;
+    let ExpressionOperation::SetLocal(object) = &implementation.expression_tree.values[&statements[0]] else {
+        unreachable!()
+    };
+    representations.tuple_types.contains(&implementation.type_forest.resolve_type(&object.type_).unwrap())
+        .then_some(fields)
+}
+
+/// The locals that tuples are destructured from. They are assigned all at once, so they never need a name.
+pub fn destructured_tuples(implementation: &FunctionImplementation, representations: &Representations, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>) -> HashSet<Arc<ObjectReference>> {
+    let tree = &implementation.expression_tree;
+    tree.values.iter()
+        .filter(|(_, operation)| **operation == ExpressionOperation::Block)
+        .flat_map(|(block, _)| {
+            let statements = &tree.children[block];
+            (0..statements.len()).filter_map(|idx| {
+                find_tuple_destructuring(implementation, representations, logic, &statements[idx..])?;
+                match &tree.values[&statements[idx]] {
+                    ExpressionOperation::SetLocal(object) => Some(Arc::clone(object)),
+                    _ => unreachable!(),
+                }
+            })
+        })
+        .collect()
+}
+
+/// The targets to assign all fields at once, like `q, r = divmod(a, b)`. Fields that aren't read are assigned to _.
+fn tuple_destructuring_targets(implementation: &FunctionImplementation, context: &FunctionContext, statement: &ExpressionID, fields: &[(usize, ExpressionID)]) -> Box<ast::Expression> {
+    let tree = &implementation.expression_tree;
+    let field_count = implementation.type_forest.resolve_binding_alias(&tree.children[statement][0]).unwrap().arguments.len();
+
+    Box::new(ast::Expression::Tuple((0..field_count).map(|idx| {
+        let name = match fields.iter().find(|(field, _)| *field == idx) {
+            Some((_, assignment)) => {
+                let ExpressionOperation::SetLocal(local) = &tree.values[assignment] else {
+                    unreachable!()
+                };
+                context.names[&local.id].clone()
             }
-            _ => Box::new(ast::Statement::Expression(transpile_expression(*statement, context))),
-        });
+            None => context.names[&PSEUDO_KEYWORD_IDS["_"]].clone(),
+        };
+        Box::new(ast::Expression::NamedReference(name))
+    }).collect()))
+}
+
+/// Whether python has an augmented assignment (like +=) for the binary operator.
+fn is_augmentable(operator: &str) -> bool {
+    matches!(operator, "+" | "-" | "*" | "/" | "//" | "%" | "**")
+}
+
+/// Transpiles an expression whose value is needed.
+/// Blocks can't be python expressions, so their statements are put before, and their value is returned.
+/// Returns None if the value never materializes, because the function returns first.
+fn transpile_value(implementation: &FunctionImplementation, context: &FunctionContext, expression: &ExpressionID, statements: &mut Vec<Box<ast::Statement>>) -> Option<Box<ast::Expression>> {
+    let ExpressionOperation::Block = &implementation.expression_tree.values[expression] else {
+        if !hoist_block_arguments(implementation, context, expression, statements) {
+            return None
+        }
+        return Some(transpile_expression(*expression, context))
+    };
+
+    let children = &implementation.expression_tree.children[expression];
+    let (last, leading) = children.split_last().unwrap();
+    for child in leading {
+        transpile_statement(implementation, context, child, false, statements);
     }
 
-    Box::new(ast::Block { statements: statements_ })
+    match &implementation.expression_tree.values[last] {
+        ExpressionOperation::Return => {
+            transpile_statement(implementation, context, last, false, statements);
+            None
+        }
+        _ => transpile_value(implementation, context, last, statements),
+    }
+}
+
+/// Blocks that are arguments of other expressions, like `f({ let x = g(); x + 1; })`.
+/// Each is evaluated into a local of its own before the statement using it, since python can't evaluate it in place.
+/// They are listed in order of appearance, so their names are numbered in order too.
+pub fn block_arguments(tree: &ExpressionTree) -> Vec<ExpressionID> {
+    let mut blocks = vec![];
+    let mut stack = vec![tree.root];
+    while let Some(current) = stack.pop() {
+        if tree.values[&current] == ExpressionOperation::Block && tree.parents.get(&current).is_some_and(|parent| !is_statement_context(tree, parent, &current)) {
+            blocks.push(current);
+        }
+        stack.extend(tree.children[&current].iter().rev());
+    }
+    blocks
+}
+
+/// Whether any cast in the function fails, so it needs a helper to raise.
+pub fn has_failing_cast(implementation: &FunctionImplementation) -> bool {
+    let tree = &implementation.expression_tree;
+    tree.values.iter().any(|(expression, operation)| match operation {
+        ExpressionOperation::TypeCast(trait_) => implementation.type_forest.resolve_binding_alias(&tree.children[expression][0])
+            .is_ok_and(|subject_type| !subject_type.is_instance_of(trait_)),
+        _ => false,
+    })
+}
+
+/// Whether the child's value is used as a statement, or assigned or returned as a whole.
+fn is_statement_context(tree: &ExpressionTree, parent: &ExpressionID, child: &ExpressionID) -> bool {
+    match &tree.values[parent] {
+        ExpressionOperation::Block | ExpressionOperation::SetLocal(_) | ExpressionOperation::Return => true,
+        // The condition is the only value that is used as an argument.
+        ExpressionOperation::IfThenElse => tree.children[parent][0] != *child,
+        _ => false,
+    }
+}
+
+/// The block arguments of the expression and its arguments, in order of evaluation.
+/// Blocks nested in the blocks aren't included; they're evaluated with their block.
+fn block_arguments_of(tree: &ExpressionTree, expression: &ExpressionID) -> Vec<ExpressionID> {
+    let mut blocks = vec![];
+    let mut stack = vec![*expression];
+    while let Some(current) = stack.pop() {
+        if current != *expression && tree.values[&current] == ExpressionOperation::Block {
+            blocks.push(current);
+            continue
+        }
+        stack.extend(tree.children[&current].iter().rev());
+    }
+    blocks
+}
+
+/// Evaluates the block arguments of the expression into their locals.
+/// Returns false if one of them returns from the function, so the expression is never evaluated.
+fn hoist_block_arguments(implementation: &FunctionImplementation, context: &FunctionContext, expression: &ExpressionID, statements: &mut Vec<Box<ast::Statement>>) -> bool {
+    for block in block_arguments_of(&implementation.expression_tree, expression) {
+        let Some(value) = transpile_value(implementation, context, &block, statements) else {
+            return false
+        };
+        statements.push(Box::new(ast::Statement::VariableAssignment {
+            target: Box::new(ast::Expression::NamedReference(context.names[&block].clone())),
+            value: Some(value),
+            type_annotation: Some(types::transpile(&implementation.type_forest.resolve_binding_alias(&block).unwrap(), context)),
+        }));
+    }
+    true
 }
 
 fn transpile_as_block(implementation: &FunctionImplementation, context: &FunctionContext, expression: &ExpressionID, auto_return: bool) -> Box<ast::Block> {
-    match &implementation.expression_tree.values[expression] {
-        ExpressionOperation::Block => {
-            transpile_block(&implementation, context, &implementation.expression_tree.children[expression])
+    let mut statements = vec![];
+    transpile_statement(implementation, context, expression, auto_return, &mut statements);
+    Box::new(ast::Block { statements })
+}
+
+
+/// Supplies the transpiled arguments of an expression.
+/// Expressions can be nested deeply, so they are transpiled bottom up rather than by recursing:
+///  Each is first probed for the arguments it uses, which are then transpiled before it.
+enum Arguments<'a> {
+    Probe(Vec<ExpressionID>),
+    Transpiled(&'a mut HashMap<ExpressionID, Box<ast::Expression>>),
+}
+
+impl Arguments<'_> {
+    fn take(&mut self, expression_id: ExpressionID) -> Box<ast::Expression> {
+        match self {
+            Arguments::Probe(used) => {
+                used.push(expression_id);
+                // Probed expressions are discarded, so any argument will do.
+                Box::new(ast::Expression::ValueLiteral(String::new()))
+            }
+            Arguments::Transpiled(transpiled) => transpiled.remove(&expression_id).unwrap(),
         }
-        _ => {
-            let expression = transpile_expression(*expression, context);
+    }
+}
 
-            Box::new(ast::Block { statements: vec![Box::new(match !auto_return && implementation.head.interface.return_type.unit.is_void() {
-                true => ast::Statement::Expression(expression),
-                false => ast::Statement::Return(Some(expression)),
-            })] })
+/// Transpiles the expression with transpile, after transpiling the arguments it uses.
+fn with_arguments<T>(expression_id: ExpressionID, context: &FunctionContext, transpile: impl Fn(ExpressionID, &FunctionContext, &mut Arguments) -> T) -> T {
+    let mut transpiled = HashMap::new();
+    // (expression, whether its arguments are transpiled)
+    let mut stack = probe_arguments(expression_id, context, &transpile).into_iter().rev().map(|argument| (argument, false)).collect_vec();
+    while let Some((argument, is_ready)) = stack.pop() {
+        if is_ready {
+            let expression = transpile_operation(argument, context, &mut Arguments::Transpiled(&mut transpiled));
+            transpiled.insert(argument, expression);
+            continue
         }
+
+        stack.push((argument, true));
+        stack.extend(probe_arguments(argument, context, transpile_operation).into_iter().rev().map(|argument| (argument, false)));
     }
+
+    transpile(expression_id, context, &mut Arguments::Transpiled(&mut transpiled))
 }
 
+/// The arguments transpile uses for the expression, in order.
+fn probe_arguments<T>(expression_id: ExpressionID, context: &FunctionContext, transpile: impl Fn(ExpressionID, &FunctionContext, &mut Arguments) -> T) -> Vec<ExpressionID> {
+    let mut arguments = Arguments::Probe(vec![]);
+    transpile(expression_id, context, &mut arguments);
+    let Arguments::Probe(used) = arguments else {
+        unreachable!()
+    };
+    used
+}
 
 pub fn transpile_expression(expression_id: ExpressionID, context: &FunctionContext) -> Box<ast::Expression> {
+    with_arguments(expression_id, context, transpile_operation)
+}
+
+fn transpile_operation(expression_id: ExpressionID, context: &FunctionContext, transpiled: &mut Arguments) -> Box<ast::Expression> {
     match &context.expressions.values.get(&expression_id).unwrap() {
         ExpressionOperation::StringLiteral(string) => {
             Box::new(ast::Expression::StringLiteral(string.clone()))
@@ -170,24 +471,57 @@ pub fn transpile_expression(expression_id: ExpressionID, context: &FunctionConte
         }
         ExpressionOperation::FunctionCall(call) => {
             let form = &context.representations.function_forms.get(&call.function).unwrap_or_else(|| panic!("Unable to get function form for {:?}", call.function));
-            match transpile_function_call(context, &call.function, form, expression_id) {
+            match transpile_function_call(context, &call.function, form, expression_id, transpiled) {
                 Left(e) => e,
-                Right(s) => panic!("Statement not supported in expression context.")
+                Right(_s) => panic!("Statement not supported in expression context.")
             }
         },
+        ExpressionOperation::TypeCheck(trait_) => {
+            let [subject] = context.expressions.children[&expression_id][..] else {
+                panic!("Type check got the wrong number of arguments.")
+            };
+
+            match context.representations.type_ids.get(&TypeProto::unit_struct(trait_)) {
+                // Generated classes can be tested for at runtime.
+                Some(id) if !PSEUDO_KEYWORD_IDS.values().contains(id) => {
+                    Box::new(ast::Expression::FunctionCall(
+                        Box::new(ast::Expression::NamedReference(context.names[&PSEUDO_KEYWORD_IDS["isinstance"]].clone())),
+                        vec![
+                            (ParameterKey::Positional, transpiled.take(subject)),
+                            (ParameterKey::Positional, Box::new(ast::Expression::NamedReference(context.names[id].clone()))),
+                        ]
+                    ))
+                }
+                // Builtin values (like numpy scalars) don't reliably match their python type,
+                //  but every type is exact after monomorphization.
+                _ => {
+                    let is_instance = context.types.resolve_binding_alias(&subject).unwrap().is_instance_of(trait_);
+                    Box::new(ast::Expression::ValueLiteral(if is_instance { "True" } else { "False" }.to_string()))
+                }
+            }
+        }
+        ExpressionOperation::TypeCast(trait_) => {
+            let [subject] = context.expressions.children[&expression_id][..] else {
+                panic!("Type cast got the wrong number of arguments.")
+            };
+
+            let subject_type = context.types.resolve_binding_alias(&subject).unwrap();
+            if subject_type.is_instance_of(trait_) {
+                transpiled.take(subject)
+            }
+            else {
+                Box::new(ast::Expression::FunctionCall(
+                    Box::new(ast::Expression::NamedReference(context.names[&PSEUDO_KEYWORD_IDS["_cast_error"]].clone())),
+                    vec![(ParameterKey::Positional, Box::new(ast::Expression::StringLiteral(format!("Cannot cast {:?} to {}.", subject_type, trait_.name))))]
+                ))
+            }
+        }
         ExpressionOperation::ArrayLiteral => {
-            todo!()
-            // write!(stream, "[")?;
-            // for (idx, expression) in expressions.iter().enumerate() {
-            //     transpile_expression(stream, expression, context)?;
-            //
-            //     if idx < expressions.len() -1 {
-            //         write!(stream, ", ")?;
-            //     }
-            // }
-            // write!(stream, "]")?;
+            Box::new(ast::Expression::ListLiteral(
+                context.expressions.children[&expression_id].iter().map(|element| transpiled.take(*element)).collect()
+            ))
         },
-        ExpressionOperation::PairwiseOperations { calls } => {
+        ExpressionOperation::PairwiseOperations { calls: _ } => {
             todo!()
             // // TODO Unfortunately, python's a > b > c syntax does not support non-bool results.
             // //  For true boolean results, we could actually use it for readability.
@@ -205,17 +539,18 @@ pub fn transpile_expression(expression_id: ExpressionID, context: &FunctionConte
             //     }
             // }
         }
-        ExpressionOperation::Block => todo!(),
+        // Block arguments were evaluated into their locals before the statement.
+        ExpressionOperation::Block => Box::new(ast::Expression::NamedReference(context.names[&expression_id].clone())),
         ExpressionOperation::SetLocal(_) => panic!("Variable assignment not allowed as expression."),
         ExpressionOperation::Return => panic!("Return not allowed as expression."),
         ExpressionOperation::IfThenElse => panic!("If-Then-Else not allowed as expression."),
     }
 }
 
-fn transpile_function_call(context: &FunctionContext, function: &Rc<FunctionHead>, form: &FunctionForm, expression_id: ExpressionID) -> Either<Box<ast::Expression>, Box<ast::Statement>> {
+fn transpile_function_call(context: &FunctionContext, function: &Arc<FunctionHead>, form: &FunctionForm, expression_id: ExpressionID, transpiled: &mut Arguments) -> Either<Box<ast::Expression>, Box<ast::Statement>> {
     let arguments = context.expressions.children.get(&expression_id).unwrap();
 
-    if let Some(s) = try_transpile_optimization(function, &expression_id, arguments, context) {
+    if let Some(s) = try_transpile_optimization(function, &expression_id, arguments, context, transpiled) {
         return Left(s)
     }
 
@@ -228,75 +563,115 @@ fn transpile_function_call(context: &FunctionContext, function: &Rc<FunctionHead
             assert!(arguments.is_empty());
             return Left(Box::new(ast::Expression::NamedReference(context.names[id].clone())))
         },
-        FunctionForm::Unary(id) => return Left(transpile_unary_operator(&context.names[&id], &arguments, context)),
-        FunctionForm::Binary(id) => return Left(transpile_binary_operator(&context.names[&id], &arguments, context)),
+        FunctionForm::Unary(id) => return Left(transpile_unary_operator(&context.names[id], &arguments, transpiled)),
+        FunctionForm::Binary(id) => return Left(transpile_binary_operator(&context.names[id], &arguments, transpiled)),
         FunctionForm::FunctionCall(id) => Box::new(ast::Expression::NamedReference(context.names[id].clone())),
         FunctionForm::CallAsFunction => {
             parameters.remove(0);
-            transpile_expression(arguments.remove(0), context)
+            let callee = arguments.remove(0);
+            if context.representations.tuple_types.contains(&context.types.resolve_binding_alias(&expression_id).unwrap()) {
+                return Left(Box::new(ast::Expression::Tuple(arguments.iter().map(|argument| transpiled.take(*argument)).collect())))
+            }
+            match context.logic.get(function) {
+                // Generic structs have a class per specialization, which the metatype doesn't know about.
+                Some(FunctionLogicDescriptor::Constructor(_)) => types::transpile(&context.types.resolve_binding_alias(&expression_id).unwrap(), context),
+                _ => transpiled.take(callee),
+            }
         },
         FunctionForm::GetMemberField(id) => {
             assert_eq!(arguments.len(), 1);
-            let object = transpile_expression(arguments[0], context);
+            let object = transpiled.take(arguments[0]);
+            if let Some(FunctionLogicDescriptor::GetMemberField(struct_, field)) = context.logic.get(function) {
+                if context.representations.tuple_types.contains(&context.types.resolve_binding_alias(&arguments[0]).unwrap()) {
+                    let idx = struct_.fields.iter().position(|f| f == field).unwrap();
+                    return Left(Box::new(ast::Expression::Subscript(object, Box::new(ast::Expression::ValueLiteral(idx.to_string())))))
+                }
+            }
             return Left(Box::new(ast::Expression::MemberAccess(object, context.names[id].clone())))
         },
         FunctionForm::SetMemberField(id) => {
             assert_eq!(arguments.len(), 2);
             return Right(Box::new(ast::Statement::VariableAssignment {
-                target: Box::new(ast::Expression::MemberAccess(transpile_expression(arguments[0], context), context.names[id].clone())),
-                value: Some(transpile_expression(arguments[1], context)),
+                target: Box::new(ast::Expression::MemberAccess(transpiled.take(arguments[0]), context.names[id].clone())),
+                value: Some(transpiled.take(arguments[1])),
+                type_annotation: None,
+            }))
+        }
+        FunctionForm::GetItem => {
+            assert_eq!(arguments.len(), 2);
+            let object = transpiled.take(arguments[0]);
+            return Left(Box::new(ast::Expression::Subscript(object, transpiled.take(arguments[1]))))
+        },
+        FunctionForm::SetItem => {
+            assert_eq!(arguments.len(), 3);
+            return Right(Box::new(ast::Statement::VariableAssignment {
+                target: Box::new(ast::Expression::Subscript(transpiled.take(arguments[0]), transpiled.take(arguments[1]))),
+                value: Some(transpiled.take(arguments[2])),
                 type_annotation: None,
             }))
         }
         FunctionForm::MemberCall(id) => {
             parameters.remove(0);
-            let object = transpile_expression(arguments.remove(0), context);
+            let object = transpiled.take(arguments.remove(0));
             Box::new(ast::Expression::MemberAccess(object, context.names[id].clone()))
         },
+        FunctionForm::CallVtable(idx) => {
+            parameters.remove(0);
+            let object = transpiled.take(arguments.remove(0));
+            Box::new(ast::Expression::Subscript(object, Box::new(ast::Expression::ValueLiteral(idx.to_string()))))
+        },
     };
 
     for (parameter, argument) in zip_eq(parameters.iter(), arguments.iter()) {
-        py_arguments.push((parameter.external_key.clone(), transpile_expression(argument.clone(), context)));
+        py_arguments.push((parameter.external_key.clone(), transpiled.take(*argument)));
     }
 
-    return Left(Box::new(ast::Expression::FunctionCall(target, py_arguments)))
+    Left(Box::new(ast::Expression::FunctionCall(target, py_arguments)))
 }
 
-pub fn try_transpile_optimization(function: &Rc<FunctionHead>, expression_id: &ExpressionID, arguments: &Vec<ExpressionID>, context: &FunctionContext) -> Option<Box<ast::Expression>> {
-    let Some(descriptor) = context.logic.get(function) else {
-        return None;
-    };
+fn try_transpile_optimization(function: &Arc<FunctionHead>, expression_id: &ExpressionID, arguments: &[ExpressionID], context: &FunctionContext, transpiled: &mut Arguments) -> Option<Box<ast::Expression>> {
+    let descriptor = context.logic.get(function)?;
 
     // TODO Monoteny should instead offer its own parser function, and we simply optimize calls that have python-parseable literals.
     Some(match descriptor {
-        FunctionLogicDescriptor::PrimitiveOperation { type_, operation } => {
+        FunctionLogicDescriptor::PrimitiveOperation { type_: _, operation } => {
             match operation {
-                PrimitiveOperation::ParseIntString => transpile_parse_function("^[0-9]+$", arguments, expression_id, context),
-                PrimitiveOperation::ParseRealString => transpile_parse_function("^[0-9]+\\.[0-9]*$", arguments, expression_id, context),
+                PrimitiveOperation::ParseIntString => transpile_parse_function("^-?[0-9]+$", arguments, expression_id, context, transpiled),
+                PrimitiveOperation::ParseRealString => transpile_parse_function("^-?[0-9]+\\.[0-9]*$", arguments, expression_id, context, transpiled),
+                // Chars are represented as 1-length strings, so the literal can be used as is.
+                PrimitiveOperation::ParseCharString => transpiled.take(arguments[0]),
                 _ => return None,
             }
         }
+        // The vtable's functions are passed along with the value.
+        FunctionLogicDescriptor::DynBox(vtable) => Box::new(ast::Expression::FunctionCall(
+            Box::new(ast::Expression::NamedReference(context.names[&PSEUDO_KEYWORD_IDS["_dyn_box"]].clone())),
+            [transpiled.take(arguments[0])].into_iter()
+                .chain(vtable.iter().map(|function| Box::new(ast::Expression::NamedReference(context.names[&function.function_id].clone()))))
+                .map(|argument| (ParameterKey::Positional, argument))
+                .collect()
+        )),
         _ => return None,
     })
 }
 
-pub fn transpile_unary_operator(operator: &str, arguments: &Vec<ExpressionID>, context: &FunctionContext) -> Box<ast::Expression> {
+fn transpile_unary_operator(operator: &str, arguments: &[ExpressionID], transpiled: &mut Arguments) -> Box<ast::Expression> {
     let [expression] = arguments[..] else {
         panic!("Unary operator got {} arguments: {}", arguments.len(), operator);
     };
 
-    Box::new(ast::Expression::UnaryOperation(operator.to_string(), transpile_expression(expression, context)))
+    Box::new(ast::Expression::UnaryOperation(operator.to_string(), transpiled.take(expression)))
 }
 
-pub fn transpile_binary_operator(operator: &str, arguments: &Vec<ExpressionID>, context: &FunctionContext) -> Box<ast::Expression> {
+fn transpile_binary_operator(operator: &str, arguments: &[ExpressionID], transpiled: &mut Arguments) -> Box<ast::Expression> {
     let [lhs, rhs] = arguments[..] else {
         panic!("Binary operator got {} arguments: {}", arguments.len(), operator);
     };
 
-    Box::new(ast::Expression::BinaryOperation(transpile_expression(lhs, context), operator.to_string(), transpile_expression(rhs, context)))
+    Box::new(ast::Expression::BinaryOperation(transpiled.take(lhs), operator.to_string(), transpiled.take(rhs)))
 }
 
-pub fn transpile_parse_function(supported_regex: &str, arguments: &Vec<ExpressionID>, expression_id: &ExpressionID, context: &FunctionContext) -> Box<ast::Expression> {
+fn transpile_parse_function(supported_regex: &str, arguments: &[ExpressionID], expression_id: &ExpressionID, context: &FunctionContext, transpiled: &mut Arguments) -> Box<ast::Expression> {
     let [argument_expression_id] = arguments[..] else {
         panic!("Parse function got {} arguments", arguments.len());
     };
@@ -308,10 +683,10 @@ pub fn transpile_parse_function(supported_regex: &str, arguments: &Vec<Expressio
                 Box::new(ast::Expression::ValueLiteral(literal.clone()))
             }
             else {
-                transpile_expression(argument_expression_id, context)
+                transpiled.take(argument_expression_id)
             }
         }
-        _ => transpile_expression(argument_expression_id, context),
+        _ => transpiled.take(argument_expression_id),
     };
 
     Box::new(ast::Expression::FunctionCall(
@@ -100,6 +100,9 @@ lazy_static! {
         "op.pow",
         "op.log",
 
+        "copy",
+        "copy.copy",
+
         "math",
         "math.factorial",
         "math.log",
@@ -119,9 +122,39 @@ lazy_static! {
         "math.floor",
         "round",
         "abs",
+        "min",
+        "max",
+        "chr",
+        "ord",
+        "isinstance",
 
         "exit",
         "print",
+
+        "list",
+        "tuple",
+        "len",
+        "append",
+        "_list_get",
+        "_list_set",
+        "_int_pow",
+        "_bool_to_string",
+        "_chr",
+        "_cast_error",
+        "_dyn_box",
+        "_push_line",
+        "_console_contents",
+        "_Json",
+        "_parse_json",
+        "get",
+        "at",
+        "kind",
+        "as_string",
+        "as_float",
+        "as_bool",
+        "pop",
+        // Assigned values that are discarded, like unused fields when destructuring tuples.
+        "_",
     ].into_iter().map(|s| (s, Uuid::new_v4())));
 }
 
@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::program::module::ModuleName;
+use crate::transpiler::python::{ast, TopLevelStatements, write_module};
+
+/// Arranges the statements into a package, with one file per module they were declared in.
+/// Module a.b is written to {base}/a/b/__init__.py, so that modules can have submodules.
+/// The transpiled module's statements make up the package itself.
+pub fn make_package(base_filename: &str, statements: TopLevelStatements) -> HashMap<String, String> {
+    let TopLevelStatements { statements, main_function, helpers, module_includes } = statements;
+
+    let names = statements.iter().map(|statement| statement.name.clone()).collect_vec();
+    let statement_by_name: HashMap<_, _> = names.iter().enumerate()
+        .map(|(idx, name)| (name.as_str(), idx))
+        .collect();
+    // For each statement, the other statements it refers to.
+    let references = statements.iter().enumerate().map(|(idx, statement)| {
+        statement.statement.referenced_names().into_iter()
+            .filter_map(|name| statement_by_name.get(name).copied())
+            .filter(|other| *other != idx)
+            .sorted()
+            .collect_vec()
+    }).collect_vec();
+
+    // Python cannot import files circularly. Statements that would need to are moved to the
+    //  package itself, which no other file imports.
+    let mut placement = statements.iter().map(|statement| statement.module.clone()).collect_vec();
+    loop {
+        let mut dependencies: HashMap<Option<ModuleName>, HashSet<Option<ModuleName>>> = HashMap::new();
+        for (module, includes) in module_includes.iter() {
+            dependencies.entry(module.clone()).or_default().extend(includes.iter().map(|(included, _)| Some(included.clone())));
+        }
+        for (idx, others) in references.iter().enumerate() {
+            dependencies.entry(placement[idx].clone()).or_default().extend(others.iter().map(|other| placement[*other].clone()));
+        }
+
+        let moved = (0..statements.len())
+            .filter(|idx| placement[*idx].is_some())
+            .filter(|idx| references[*idx].iter().any(|other| {
+                placement[*other] != placement[*idx] && (placement[*other].is_none() || reaches(&dependencies, &placement[*other], &placement[*idx]))
+            }))
+            .collect_vec();
+        if moved.is_empty() {
+            break
+        }
+        for idx in moved {
+            placement[idx] = None;
+        }
+    }
+
+    let mut defined: HashMap<Option<ModuleName>, Vec<usize>> = HashMap::new();
+    for (idx, module) in placement.iter().enumerate() {
+        defined.entry(module.clone()).or_default().push(idx);
+    }
+    let mut exports = HashMap::new();
+    let modules = defined.keys().cloned()
+        .chain(module_includes.keys().cloned())
+        .chain([None])
+        .unique()
+        .filter(|module| module.is_none() || !gather_exports(module, &names, &defined, &module_includes, &mut exports).is_empty())
+        .sorted()
+        .collect_vec();
+
+    let mut statements = statements.into_iter().map(Some).collect_vec();
+
+    let mut files = HashMap::new();
+    for module in modules.iter() {
+        let depth = module.as_ref().map_or(0, |name| name.len());
+        let relative_path = |name: &[String]| format!("{}{}", ".".repeat(depth + 1), name.join("."));
+
+        let mut file = Box::new(ast::Module {
+            exported_statements: vec![],
+            internal_statements: vec![],
+            exported_names: HashSet::new(),
+            main_function: None,
+            imports: vec![],
+            helpers: vec![],
+        });
+        let mut imports: BTreeMap<String, BTreeSet<(String, Option<String>)>> = BTreeMap::new();
+
+        for idx in defined.get(module).into_iter().flatten() {
+            let statement = statements[*idx].take().unwrap();
+            // Only the explicitly transpiled functions are part of the package's interface.
+            if statement.is_exported || module.is_some() {
+                file.exported_names.insert(statement.name);
+            }
+            match statement.is_exported {
+                true => file.exported_statements.push(statement.statement),
+                false => file.internal_statements.push(statement.statement),
+            }
+
+            for other in references[*idx].iter() {
+                if let Some(other_module) = placement[*other].as_ref().filter(|other_module| Some(*other_module) != module.as_ref()) {
+                    imports.entry(relative_path(other_module)).or_default().insert((names[*other].clone(), None));
+                }
+            }
+        }
+        if defined.contains_key(module) {
+            file.helpers = helpers.clone();
+        }
+
+        // Included modules are re-exported.
+        for (included, namespace) in module_includes.get(module).into_iter().flatten() {
+            let included_exports = gather_exports(&Some(included.clone()), &names, &defined, &module_includes, &mut exports);
+            if included_exports.is_empty() {
+                continue
+            }
+
+            match namespace {
+                None => {
+                    for name in included_exports {
+                        imports.entry(relative_path(included)).or_default().insert((name.clone(), None));
+                        file.exported_names.insert(name);
+                    }
+                }
+                Some(namespace) => {
+                    let (last, parent) = included.split_last().unwrap();
+                    imports.entry(relative_path(parent)).or_default().insert((last.clone(), Some(namespace.clone())));
+                    file.exported_names.insert(namespace.clone());
+                }
+            }
+        }
+
+        file.imports = imports.into_iter()
+            .map(|(module, names)| ast::Import { module, names: names.into_iter().collect() })
+            .collect();
+
+        files.insert(file_path(base_filename, module.as_deref().unwrap_or(&[])), write_module(&file));
+    }
+
+    // Directories of modules without code need to be packages too.
+    for module in modules.iter().flatten() {
+        for depth in 1..module.len() {
+            files.entry(file_path(base_filename, &module[..depth])).or_insert_with(String::new);
+        }
+    }
+
+    if let Some(main_function) = main_function {
+        files.insert(format!("{}/__main__.py", base_filename), format!("from . import {}\n\n\n{}()\n", main_function, main_function));
+    }
+
+    files
+}
+
+fn file_path(base_filename: &str, module: &[String]) -> String {
+    [base_filename].into_iter().chain(module.iter().map(String::as_str)).chain(["__init__.py"]).join("/")
+}
+
+/// The names a module's file exports: Its own, and those of the modules it includes.
+fn gather_exports(
+    module: &Option<ModuleName>,
+    names: &Vec<String>,
+    defined: &HashMap<Option<ModuleName>, Vec<usize>>,
+    module_includes: &HashMap<Option<ModuleName>, Vec<(ModuleName, Option<String>)>>,
+    cache: &mut HashMap<Option<ModuleName>, Vec<String>>,
+) -> Vec<String> {
+    if let Some(exports) = cache.get(module) {
+        return exports.clone()
+    }
+    // Guards against include cycles.
+    cache.insert(module.clone(), vec![]);
+
+    let mut exports = defined.get(module).into_iter().flatten().map(|idx| names[*idx].clone()).collect_vec();
+    for (included, namespace) in module_includes.get(module).into_iter().flatten() {
+        let included_exports = gather_exports(&Some(included.clone()), names, defined, module_includes, cache);
+        match namespace {
+            _ if included_exports.is_empty() => {},
+            None => exports.extend(included_exports),
+            Some(namespace) => exports.push(namespace.clone()),
+        }
+    }
+
+    let exports = exports.into_iter().unique().sorted().collect_vec();
+    cache.insert(module.clone(), exports.clone());
+    exports
+}
+
+/// Whether from depends on to, directly or transitively.
+fn reaches(dependencies: &HashMap<Option<ModuleName>, HashSet<Option<ModuleName>>>, from: &Option<ModuleName>, to: &Option<ModuleName>) -> bool {
+    let mut visited = HashSet::from([from]);
+    let mut next = vec![from];
+    while let Some(current) = next.pop() {
+        if current == to {
+            return true
+        }
+        for dependency in dependencies.get(current).into_iter().flatten() {
+            if visited.insert(dependency) {
+                next.push(dependency);
+            }
+        }
+    }
+    false
+}
@@ -1,26 +1,51 @@
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
+use itertools::Itertools;
 use uuid::Uuid;
 
 use crate::program::expression_tree::ExpressionOperation;
 use crate::program::function_object::FunctionRepresentation;
 use crate::program::functions::FunctionHead;
-use crate::program::global::FunctionImplementation;
+use crate::program::global::{FunctionImplementation, FunctionLogicDescriptor};
 use crate::program::types::TypeProto;
+use crate::source::StructInfo;
 use crate::transpiler::namespaces;
+use crate::transpiler::python::imperative;
 
 #[derive(Clone)]
 pub struct Representations {
-    pub function_forms: HashMap<Rc<FunctionHead>, FunctionForm>,
-    pub type_ids: HashMap<Rc<TypeProto>, Uuid>,
+    pub function_forms: HashMap<Arc<FunctionHead>, FunctionForm>,
+    /// Forms of generic functions, for instantiations whose parameters are all primitives.
+    /// Other instantiations are transpiled like any other function.
+    pub primitive_instantiation_forms: HashMap<Arc<FunctionHead>, FunctionForm>,
+    pub type_ids: HashMap<Arc<TypeProto>, Uuid>,
+    /// Types python represents natively, like int32.
+    pub primitive_types: HashSet<Arc<TypeProto>>,
+    /// Anonymous struct types that are represented by tuples instead of classes.
+    pub tuple_types: HashSet<Arc<TypeProto>>,
+    /// Struct types that are represented by classes, whose objects python passes by reference.
+    pub class_types: HashSet<Arc<TypeProto>>,
+    /// Global constants that need a function to be computed. It is cached, so they're evaluated once, like in the VM.
+    pub cached_functions: HashSet<Arc<FunctionHead>>,
+}
+
+impl Default for Representations {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Representations {
     pub fn new() -> Representations {
         Representations {
             function_forms: Default::default(),
+            primitive_instantiation_forms: Default::default(),
             type_ids: Default::default(),
+            primitive_types: Default::default(),
+            tuple_types: Default::default(),
+            class_types: Default::default(),
+            cached_functions: Default::default(),
         }
     }
 }
@@ -35,21 +60,75 @@ pub enum FunctionForm {
     SetMemberField(Uuid),
     GetMemberField(Uuid),
     MemberCall(Uuid),
+    GetItem,
+    SetItem,
     Unary(Uuid),
     Binary(Uuid),
+    /// Calls the function at the index of the Dyn's vtable, which is bound to the boxed value.
+    CallVtable(usize),
 }
 
-pub fn find_for_function(forms: &mut HashMap<Rc<FunctionHead>, FunctionForm>, global_namespace: &mut namespaces::Level, implementation: &FunctionImplementation, representation: &FunctionRepresentation) {
+pub fn find_for_function(representations: &mut Representations, global_namespace: &mut namespaces::Level, implementation: &FunctionImplementation, representation: &FunctionRepresentation) {
     if implementation.parameter_locals.is_empty() {
         // TODO We could make a helper function and still use a constant even if we use blocks.
         let has_blocks = implementation.expression_tree.values.values().any(|op| matches!(op, ExpressionOperation::Block));
         if !has_blocks {
             global_namespace.insert_name(implementation.head.function_id, representation.name.as_str());
-            forms.insert(Rc::clone(&implementation.head), FunctionForm::Constant(implementation.head.function_id));
+            representations.function_forms.insert(Arc::clone(&implementation.head), FunctionForm::Constant(implementation.head.function_id));
             return
         }
     }
 
+    if representation.is_global_constant(&implementation.head) {
+        representations.cached_functions.insert(Arc::clone(&implementation.head));
+    }
     global_namespace.insert_name(implementation.head.function_id, representation.name.as_str());
-    forms.insert(Rc::clone(&implementation.head), FunctionForm::FunctionCall(implementation.head.function_id));
+    representations.function_forms.insert(Arc::clone(&implementation.head), FunctionForm::FunctionCall(implementation.head.function_id));
+}
+
+/// The anonymous struct types whose fields are only read by destructuring, like `let (q, r) = divmod(a, b);`.
+/// Python can return and destructure tuples just as well, while a class only helps to read fields by name.
+pub fn find_tuple_types<'a>(types: impl Iterator<Item=&'a Arc<TypeProto>>, implementations: impl Iterator<Item=&'a &'a FunctionImplementation>, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>) -> HashSet<Arc<TypeProto>> {
+    let mut tuple_types: HashSet<_> = types.cloned().collect();
+
+    for implementation in implementations {
+        let tree = &implementation.expression_tree;
+        let destructured_reads: HashSet<_> = tree.values.iter()
+            .filter(|(_, operation)| **operation == ExpressionOperation::Block)
+            .flat_map(|(block, _)| {
+                let statements = &tree.children[block];
+                (0..statements.len()).flat_map(|idx| imperative::find_destructuring(tree, logic, &statements[idx..]).unwrap_or_default())
+            })
+            .map(|(_, statement)| tree.children[&statement][0])
+            .collect();
+
+        for (expression, operation) in tree.values.iter() {
+            let ExpressionOperation::FunctionCall(call) = operation else {
+                continue
+            };
+            if matches!(logic.get(&call.function), Some(FunctionLogicDescriptor::GetMemberField(_, _))) && !destructured_reads.contains(expression) {
+                tuple_types.remove(&implementation.type_forest.resolve_binding_alias(&tree.children[expression][0]).unwrap());
+            }
+        }
+    }
+
+    tuple_types
+}
+
+/// A class name for the anonymous struct type, after the first function returning it, like DivideResult.
+/// If no function returns it, it is named after its fields instead, like QuotientRemainder.
+pub fn anonymous_struct_name<'a>(type_: &TypeProto, struct_: &StructInfo, mut implementations: impl Iterator<Item=&'a &'a FunctionImplementation>, fn_representations: &HashMap<Arc<FunctionHead>, FunctionRepresentation>) -> String {
+    match implementations.find(|implementation| implementation.head.interface.return_type.as_ref() == type_) {
+        Some(implementation) => format!("{}Result", upper_camel_case(&fn_representations[&implementation.head].name)),
+        None => struct_.fields.iter().map(|field| upper_camel_case(&struct_.field_names[field])).join(""),
+    }
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+        })
+        .join("")
 }
@@ -1,13 +1,21 @@
 use crate::program::types::{TypeProto, TypeUnit};
 use crate::transpiler::python::{ast, FunctionContext};
+use crate::transpiler::python::keywords::PSEUDO_KEYWORD_IDS;
 
 pub fn transpile(type_def: &TypeProto, context: &FunctionContext) -> Box<ast::Expression> {
     match &type_def.unit {
+        TypeUnit::Struct(_) if context.representations.tuple_types.contains(type_def) => Box::new(ast::Expression::Subscript(
+            Box::new(ast::Expression::NamedReference(context.names[&PSEUDO_KEYWORD_IDS["tuple"]].clone())),
+            Box::new(ast::Expression::Tuple(type_def.arguments.iter().map(|argument| transpile(argument, context)).collect())),
+        )),
         TypeUnit::Struct(s) => {
-            let representation = &context.representations.type_ids.get(type_def).unwrap_or_else(|| panic!("Unable to find representation for type {:?}", s));
+            // Generic builtins (like List) are represented independently of their arguments.
+            let representation = &context.representations.type_ids.get(type_def)
+                .or_else(|| context.representations.type_ids.get(&TypeProto::unit_struct(s)))
+                .unwrap_or_else(|| panic!("Unable to find representation for type {:?}", s));
             Box::new(ast::Expression::NamedReference(context.names[representation].clone()))
         },
-        TypeUnit::Generic(id) => panic!("Failed to transpile {:?}, generics shouldn't exist anymore at this point.", type_def),
+        TypeUnit::Generic(_id) => panic!("Failed to transpile {:?}, generics shouldn't exist anymore at this point.", type_def),
         TypeUnit::Void => todo!(),
     }
 }
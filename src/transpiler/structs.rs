@@ -1,23 +1,25 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
+use itertools::Itertools;
 use linked_hash_map::{Entry, LinkedHashMap};
 
 use crate::program::expression_tree::ExpressionOperation;
 use crate::program::functions::FunctionHead;
 use crate::program::global::{FunctionImplementation, FunctionLogicDescriptor};
-use crate::program::types::TypeProto;
-use crate::source::StructInfo;
+use crate::program::types::{TypeProto, TypeUnit};
+use crate::program::traits::Trait;
+use crate::source::{EnumInfo, StructInfo};
 
-pub fn find_in_interfaces(heads: impl Iterator<Item=Rc<FunctionHead>>, map: &mut LinkedHashMap<Rc<TypeProto>, Rc<StructInfo>>) {
+pub fn find_in_interfaces(heads: impl Iterator<Item=Arc<FunctionHead>>, _map: &mut LinkedHashMap<Arc<TypeProto>, Arc<StructInfo>>) {
     for head in heads {
-        for type_ in head.interface.parameters.iter().map(|p| &p.type_).chain([&head.interface.return_type].into_iter()) {
+        for _type_ in head.interface.parameters.iter().map(|p| &p.type_).chain([&head.interface.return_type]) {
             todo!("From the type we SHOULD be able to deduce the struct info, but we can't for now.")
         }
     }
 }
 
-pub fn find_in_implementations(implementations: &Vec<&FunctionImplementation>, logic: &HashMap<Rc<FunctionHead>, FunctionLogicDescriptor>, map: &mut LinkedHashMap<Rc<TypeProto>, Rc<StructInfo>>) {
+pub fn find_in_implementations(implementations: &Vec<&FunctionImplementation>, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>, map: &mut LinkedHashMap<Arc<TypeProto>, Arc<StructInfo>>) {
     for implementation in implementations {
         for expression_id in implementation.expression_tree.deep_children(implementation.expression_tree.root) {
             let operation = &implementation.expression_tree.values[&expression_id];
@@ -27,12 +29,39 @@ pub fn find_in_implementations(implementations: &Vec<&FunctionImplementation>, l
                     continue;
                 };
 
-                let type_ = &binding.function.interface.return_type;  // Fulfillment for Self
-                if let Entry::Vacant(entry) = map.entry(type_.clone()) {
+                // The call's type is the struct's, specialized if it is generic.
+                let type_ = implementation.type_forest.resolve_binding_alias(&expression_id).unwrap();
+                if let Entry::Vacant(entry) = map.entry(type_) {
                     // If it's already present and we insert, we shuffle it to the end, which is unnecessary
-                    entry.insert(Rc::clone(struct_info));
+                    entry.insert(Arc::clone(struct_info));
                 }
             }
         }
     }
 }
+
+pub fn find_enums_in_implementations(implementations: &Vec<&FunctionImplementation>, logic: &HashMap<Arc<FunctionHead>, FunctionLogicDescriptor>, map: &mut LinkedHashMap<Arc<Trait>, Arc<EnumInfo>>) {
+    for implementation in implementations {
+        for expression_id in implementation.expression_tree.deep_children(implementation.expression_tree.root) {
+            let ExpressionOperation::FunctionCall(binding) = &implementation.expression_tree.values[&expression_id] else {
+                continue;
+            };
+            let Some(FunctionLogicDescriptor::EnumVariant(enum_, _)) = logic.get(&binding.function) else {
+                continue;
+            };
+
+            if let Entry::Vacant(entry) = map.entry(Arc::clone(&enum_.trait_)) {
+                entry.insert(Arc::clone(enum_));
+            }
+        }
+    }
+}
+
+/// A name for the struct type that distinguishes specializations of generic structs, e.g. Pair_Int32_String.
+pub fn type_name(type_: &TypeProto) -> String {
+    match &type_.unit {
+        TypeUnit::Struct(trait_) if type_.arguments.is_empty() => trait_.name.clone(),
+        TypeUnit::Struct(trait_) => format!("{}_{}", trait_.name, type_.arguments.iter().map(|argument| type_name(argument)).join("_")),
+        _ => panic!("Type {:?} cannot be named.", type_),
+    }
+}
@@ -1,25 +1,35 @@
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-    use std::rc::Rc;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+    use std::sync::Arc;
     use itertools::Itertools;
 
-    use crate::{interpreter, parser, transpiler};
-    use crate::error::{RResult, RuntimeError};
+    use crate::{interpreter, transpiler};
+    use crate::error::RResult;
+    use crate::error::codes::ErrorCode;
     use crate::interpreter::run::gather_functions_logic;
     use crate::interpreter::runtime::Runtime;
     use crate::program::module::module_name;
-    use crate::transpiler::{LanguageContext, Transpiler};
+    use crate::refactor::Refactor;
+    use crate::refactor::simplify::Simplify;
+    use crate::transpiler::{LanguageContext, TranspiledArtifact, Transpiler};
 
     fn test_transpiles(path: &str) -> RResult<String> {
-        let mut runtime = Runtime::new()?;
+        test_transpiles_with_config(path, &transpiler::Config::default())
+    }
+
+    fn test_transpiles_with_config(path: &str, config: &transpiler::Config) -> RResult<String> {
+        let mut runtime = Runtime::new_cached()?;
         runtime.repository.add("common", PathBuf::from("monoteny"));
 
         let module = runtime.load_file_as_module(&PathBuf::from(path), module_name("main"))?;
         let context = transpiler::python::Context::new(&runtime);
 
-        let transpiler = interpreter::run::transpile(&module, &mut runtime)?;
-        let file_map = transpiler::transpile(transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, config, "main")?;
 
         let python_string = file_map["main.py"].to_string();
         assert!(python_string.contains("def main():"));
@@ -29,19 +39,20 @@ mod tests {
 
     #[test]
     fn uninterpreted_hello_world() -> RResult<()> {
-        let mut runtime = Runtime::new()?;
+        let mut runtime = Runtime::new_cached()?;
         runtime.repository.add("common", PathBuf::from("monoteny"));
 
         let module = runtime.load_file_as_module(&PathBuf::from("test-code/hello_world.monoteny"), module_name("main"))?;
         let main_function = module.main_functions.iter().at_most_one().unwrap().unwrap();
 
         let transpiler = Box::new(Transpiler {
-            main_function: Some(Rc::clone(main_function)),
-            exported_artifacts: gather_functions_logic(&runtime, &vec![main_function.function_id]),
+            main_function: Some(Arc::clone(main_function)),
+            exported_artifacts: gather_functions_logic(&runtime, &[main_function.function_id]),
+            included_modules: module.included_modules.clone(),
         });
 
         let context = transpiler::python::Context::new(&runtime);
-        let file_map = transpiler::transpile(transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
 
         let python_string = file_map["main.py"].to_string();
         assert!(python_string.contains("def main():"));
@@ -49,12 +60,310 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn include_chain() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("grammar", PathBuf::from("test-code"));
+
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/grammar/include_chain.monoteny"), module_name("main"))?;
+        let context = transpiler::python::Context::new(&runtime);
+
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
+
+        let python_string = file_map["main.py"].to_string();
+        // Multiplication-like precedence of mid's operator is applied before base's addition-like operator.
+        assert!(python_string.contains("_add_twice(int32(1), _multiply_twice(int32(2), int32(3)))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn operator_overloads() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("grammar", PathBuf::from("test-code"));
+
+        // Both modules' + patterns, and common.math's, are resolved like overloads of one function.
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/grammar/operator_overloads.monoteny"), module_name("main"))?;
+        let mut out: Vec<u8> = vec![];
+        interpreter::run::main(&module, &mut runtime, &mut out)?;
+        assert_eq!(std::str::from_utf8(&out).unwrap(), "(4, 6)\n35 cents\n3\n");
+
+        let context = transpiler::python::Context::new(&runtime);
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")?;
+        let python_string = file_map["main.py"].to_string();
+        assert!(python_string.contains("_add_vectors(Vector(x=int32(1), y=int32(2)), Vector(x=int32(3), y=int32(4)))"));
+        assert!(python_string.contains("int32(1) + int32(2)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn include_alias() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("grammar", PathBuf::from("test-code"));
+
+        // The including module records the alias, and uses the included names unprefixed.
+        let mid = runtime.get_or_load_module(&module_name("grammar.alias.mid"))?;
+        assert_eq!(mid.included_modules, vec![(module_name("grammar.alias.inner"), Some("math".to_string()))]);
+
+        // Importers see them in the namespace only.
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/grammar/include_alias.monoteny"), module_name("main"))?;
+        let mut out: Vec<u8> = vec![];
+        interpreter::run::main(&module, &mut runtime, &mut out)?;
+        assert_eq!(std::str::from_utf8(&out).unwrap(), "9\n");
+
+        let errors = runtime.load_text_as_module("use!(module!(\"grammar.alias.outer\")); def f() -> Int32 :: triple(3 'Int32);", module_name("unprefixed")).err().unwrap();
+        assert_eq!(errors[0].title, "Cannot find 'triple' in this scope");
+
+        // Aliases nest, and the namespace's own names are in it too.
+        let module = runtime.load_text_as_module("use!(module!(\"grammar.alias.nested\")); def f() -> Int32 :: outer.math.triple(outer.nine());", module_name("nested"))?;
+        assert!(module.warnings.is_empty());
+
+        // Traits are in the namespace too, including for types.
+        runtime.load_text_as_module("use!(module!(\"grammar.alias.outer\")); def f(vector 'math.Vector) -> math.Vector :: math.Vector(x: vector.x);", module_name("types"))?;
+
+        let errors = runtime.load_file_as_module(&PathBuf::from("test-code/grammar/use_alias.monoteny"), module_name("main")).err().unwrap();
+        assert_eq!(errors[0].title, "use! cannot rename modules; only include! can re-export a module as `c`.");
+
+        Ok(())
+    }
+
+    #[test]
+    fn keyword_patterns() -> RResult<()> {
+        // Without constant folding, so the patterns' functions aren't substituted.
+        let py_file = test_transpiles_with_config("test-code/grammar/keyword_patterns.monoteny", &transpiler::Config::with_optimization_level(1))?;
+        assert!(py_file.contains("_add_squares(_double(int32(1)), int32(2))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_conflict() {
+        let errors = test_transpiles("test-code/grammar/pattern_conflict.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Pattern keyword '<+>' is already declared in AdditionPrecedence, not MultiplicationPrecedence.");
+        assert!(errors[0].notes[0].range.is_some());
+    }
+
+    #[test]
+    fn postfix_pattern() {
+        let path = "test-code/grammar/postfix_pattern.monoteny";
+        let errors = test_transpiles(path).unwrap_err();
+        assert_eq!(errors[0].title, "postfix patterns are not supported yet; use a prefix or infix pattern.");
+        assert_eq!(&fs::read_to_string(path).unwrap()[errors[0].range.clone().unwrap()], "postfix");
+    }
+
+    #[test]
+    fn misdeclared_conformance() {
+        let errors = test_transpiles("test-code/traits/misdeclared_conformance.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "`talk` must be declared as a member function to satisfy Animal.");
+        assert!(errors[0].range.is_some());
+    }
+
+    #[test]
+    fn signature_mismatch() {
+        let note_of = |path: &str| test_transpiles(path).unwrap_err()[0].notes[0].title.clone();
+        assert!(note_of("test-code/signatures/arity.monoteny").ends_with("takes 2 argument(s), but 3 were given."));
+        assert!(note_of("test-code/signatures/positional_key.monoteny").ends_with("expects argument 2 to be passed as 'height:'."));
+        assert!(note_of("test-code/signatures/keyword_typo.monoteny").ends_with("has no parameter 'heigth'. Did you mean 'height'?"));
+    }
+
+    #[test]
+    fn candidate_declarations() {
+        let errors = test_transpiles("test-code/signatures/overloads.monoteny").unwrap_err();
+        let notes = &errors[0].notes;
+        assert_eq!(notes[0].title, "2 candidates have mismatching signatures.");
+        assert_eq!(notes.len(), 3);
+        assert!(notes[1..].iter().all(|note| note.range.is_some()));
+        assert_ne!(notes[1].range, notes[2].range);
+
+        let errors = test_transpiles("test-code/signatures/imported_arity.monoteny").unwrap_err();
+        let note = &errors[0].notes[0];
+        assert!(note.title.ends_with("takes 1 argument(s), but 2 were given. It is declared in common.json."));
+        assert_eq!(note.range, None);
+    }
+
+    #[test]
+    fn builtin_signature() {
+        let errors = test_transpiles("test-code/signatures/builtin_arity.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "function and_f(_ 'Bool) could not be resolved.");
+        assert_eq!(errors[0].notes[0].title, "Candidate and_f(lhs 'Bool, rhs 'Bool) -> Bool takes 2 argument(s), but 1 were given.");
+    }
+
+    #[test]
+    fn add_non_function() {
+        let errors = test_transpiles("test-code/signatures/add_non_function.monoteny").unwrap_err();
+        assert_eq!(errors[0].code, Some(ErrorCode::UnresolvedFunction));
+        assert!(errors[0].title.contains(".add(function '$Function) could not be resolved."));
+        let requirement = &errors[0].notes[0].notes[0];
+        assert!(requirement.title.starts_with("No compatible declaration for trait conformance requirement: Function<"));
+        assert_eq!(requirement.code, Some(ErrorCode::MissingConformance));
+    }
+
+    #[test]
+    fn reserved_name() {
+        let errors = test_transpiles("test-code/signatures/reserved_name.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "'if' is a reserved keyword and cannot be used as a function name.");
+        assert!(errors[0].range.is_some());
+    }
+
+    #[test]
+    fn operator_name() {
+        let errors = test_transpiles("test-code/signatures/operator_name.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "'and' is an active operator keyword and cannot be used as a function name.");
+    }
+
     #[test]
     fn hello_world() -> RResult<()> {
         test_transpiles("test-code/hello_world.monoteny")?;
         Ok(())
     }
 
+    /// Transpiles a golden test's source. Its modules are in the transpile repository, i.e. test-code/transpile.
+    fn transpile_golden(path: &Path, layout: transpiler::Layout) -> RResult<HashMap<String, String>> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+        runtime.repository.add("transpile", PathBuf::from("test-code"));
+
+        let module = runtime.load_file_as_module(&path.to_path_buf(), module_name("main"))?;
+        let context = transpiler::python::Context::new(&runtime);
+
+        let config = transpiler::Config { layout, ..Default::default() };
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        transpiler::transpile(*transpiler, &mut runtime, &context, &config, "main")
+    }
+
+    fn files_in(directory: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return vec![]
+        };
+        entries.map(|entry| entry.unwrap().path())
+            .flat_map(|path| if path.is_dir() { files_in(&path) } else { vec![path] })
+            .collect()
+    }
+
+    /// Compares the output for each file in test-code/transpile to its .py golden file, and checks
+    ///  that python can compile it, if python3 is available.
+    /// Files with a `-- golden: package` line are also transpiled as a package, into a .package directory.
+    /// Run with MONOTENY_BLESS=1 to write the current output to the golden files instead.
+    #[test]
+    fn golden_files() -> RResult<()> {
+        let bless = std::env::var_os("MONOTENY_BLESS").is_some();
+        let has_python = Command::new("python3").arg("--version").output()
+            .is_ok_and(|output| output.status.success());
+
+        let sources = fs::read_dir("test-code/transpile").unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "monoteny"))
+            .sorted()
+            .collect_vec();
+        assert!(!sources.is_empty());
+
+        let mut mismatches = vec![];
+        for source in sources.iter() {
+            let mut outputs = vec![
+                (source.with_extension("py"), transpile_golden(source, transpiler::Layout::OneFile)?.remove("main.py").unwrap()),
+            ];
+
+            if fs::read_to_string(source).unwrap().lines().any(|line| line == "-- golden: package") {
+                let package_path = source.with_extension("package");
+                let files = transpile_golden(source, transpiler::Layout::Package)?;
+
+                if bless {
+                    _ = fs::remove_dir_all(&package_path);
+                }
+                else if files_in(&package_path).len() != files.len() {
+                    mismatches.push(package_path.clone());
+                }
+                outputs.extend(files.into_iter().sorted().map(|(path, content)| (package_path.join(path), content)));
+            }
+
+            for (golden_path, py_file) in outputs {
+                if bless {
+                    fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+                    fs::write(&golden_path, &py_file).unwrap();
+                }
+                else if fs::read_to_string(&golden_path).ok().as_ref() != Some(&py_file) {
+                    mismatches.push(golden_path.clone());
+                }
+
+                if has_python {
+                    let compile_path = std::env::temp_dir().join(format!("monoteny_golden_{}", golden_path.to_str().unwrap().replace('/', "_")));
+                    fs::write(&compile_path, &py_file).unwrap();
+                    let output = Command::new("python3").args(["-m", "py_compile"]).arg(&compile_path).output().unwrap();
+                    assert!(output.status.success(), "{} doesn't compile: {}", golden_path.display(), String::from_utf8_lossy(&output.stderr));
+                }
+            }
+        }
+
+        assert!(mismatches.is_empty(), "Transpiled output differs from golden files (run with MONOTENY_BLESS=1 to update): {:?}", mismatches);
+
+        Ok(())
+    }
+
+    /// Runs each program in the interpreter and as transpiled python, and compares their output.
+    /// Programs failing in the interpreter must fail in python with the same message.
+    /// Programs with known divergences declare them with a `-- differential: skip <reason>` line.
+    #[test]
+    #[ignore = "requires python3 with numpy; run with --ignored"]
+    fn differential() -> RResult<()> {
+        let has_numpy = Command::new("python3").args(["-c", "import numpy"]).output()
+            .is_ok_and(|output| output.status.success());
+        assert!(has_numpy, "The differential tests require python3 with numpy.");
+
+        let sources = fs::read_dir("test-code/differential").unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "monoteny"))
+            .sorted()
+            .collect_vec();
+        assert!(!sources.is_empty());
+
+        let mut divergences = vec![];
+        for source in sources.iter() {
+            let code = fs::read_to_string(source).unwrap();
+            if code.lines().any(|line| line.starts_with("-- differential: skip")) {
+                continue
+            }
+
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+            let module = runtime.load_file_as_module(source, module_name("main"))?;
+            let mut interpreted: Vec<u8> = vec![];
+            let interpreter_error = interpreter::run::main(&module, &mut runtime, &mut interpreted).err()
+                .map(|errors| errors[0].title.clone());
+            let interpreted = String::from_utf8(interpreted).unwrap();
+
+            let config = transpiler::Config { should_match_interpreter_errors: true, ..Default::default() };
+            let py_file = test_transpiles_with_config(source.to_str().unwrap(), &config)?;
+            let py_path = std::env::temp_dir().join(format!("monoteny_differential_{}.py", source.file_stem().unwrap().to_str().unwrap()));
+            fs::write(&py_path, &py_file).unwrap();
+            let output = Command::new("python3").arg(&py_path).output().unwrap();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match &interpreter_error {
+                None => assert!(output.status.success(), "{} fails in python: {}", source.display(), stderr),
+                Some(error) => assert!(
+                    !output.status.success() && stderr.trim_end().lines().last().is_some_and(|line| line.ends_with(error)),
+                    "{} should fail in python with '{}', but got: {}", source.display(), error, stderr
+                ),
+            }
+            let transpiled = String::from_utf8(output.stdout).unwrap();
+
+            for (line, (lhs, rhs)) in interpreted.lines().zip_longest(transpiled.lines()).map(|lines| lines.or("", "")).enumerate() {
+                if lhs != rhs {
+                    divergences.push(format!("{}:{}: interpreter printed {:?}, python printed {:?}", source.display(), line + 1, lhs, rhs));
+                }
+            }
+        }
+
+        assert!(divergences.is_empty(), "Interpreter and python output differ:\n{}", divergences.join("\n"));
+
+        Ok(())
+    }
+
     /// This tests generics, algebra and printing.
     #[test]
     fn custom_grammar() -> RResult<()> {
@@ -94,6 +403,151 @@ mod tests {
     #[test]
     fn trait_conformance() -> RResult<()> {
         let py_file = test_transpiles("test-code/traits/conformance.monoteny")?;
+        // Both conformances' talk is a constant, and converse is monomorphized for each order of animals.
+        assert!(py_file.contains("talk_0: str = \"Meow\""));
+        assert!(py_file.contains("talk_1: str = \"Bark\""));
+        assert!(py_file.contains("print(\"Conversation: \\n    \" + (talk_0 + (\" \\n    \" + talk_1)))"));
+        assert!(py_file.contains("print(\"Conversation: \\n    \" + (talk_1 + (\" \\n    \" + talk_0)))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn primitive_conformance() -> RResult<()> {
+        // Without constant folding, so the conformance's functions aren't substituted.
+        let py_file = test_transpiles_with_config("test-code/traits/primitive_conformance.monoteny", &transpiler::Config::with_optimization_level(1))?;
+        assert!(py_file.contains("self * int32(2)"));
+        assert!(py_file.contains("(self: str) -> str:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn generic_clamp() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/clamp.monoteny")?;
+        assert!(py_file.contains("(value: uint8, lower: uint8, upper: uint8) -> uint8:"));
+        assert!(py_file.contains("(value: int64, lower: int64, upper: int64) -> int64:"));
+        assert!(py_file.contains("(value: float32, lower: float32, upper: float32) -> float32:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_console() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/console.monoteny")?;
+        // Writing to the StringConsole goes through helpers, and writing to the StdOut is a plain print.
+        assert!(py_file.contains("def _push_line(console, value):"));
+        assert!(py_file.contains("_push_line(out, \"Hello, \" + (name + \"!\"))"));
+        assert!(py_file.contains("print(\"Hello, \" + (name + \"!\"))"));
+        assert!(py_file.contains("_console_contents(captured)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn enums() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/enums.monoteny")?;
+        assert!(py_file.contains("from enum import Enum"));
+        assert!(py_file.contains("class Color(Enum):\n    Red = 0\n    Green = 1\n    Blue = 2\n"));
+        assert!(py_file.contains("if color == Color.Red:"));
+        // Matches are chains of comparisons, the last arm needing none.
+        assert!(py_file.contains("    if color == Color.Red:\n        return \"red\"\n    elif color == Color.Green:\n        return \"green\"\n    else:\n        return \"blue\"\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn anonymous_structs() -> RResult<()> {
+        let py_file = test_transpiles_with_config("test-code/differential/anonymous_structs.monoteny", &transpiler::Config::with_optimization_level(0))?;
+        // Functions returning the same type share its class, which is named after the first of them.
+        assert!(py_file.contains("@dataclass\nclass DivideResult:\n    quotient: int64\n    remainder: int64\n"));
+        assert!(py_file.contains("def split_digit(value: int64) -> DivideResult:"));
+        assert!(py_file.contains("class DivideRealResult:"));
+        assert!(py_file.contains("result.quotient"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn destructuring() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/destructuring.monoteny")?;
+        // divmod's result is only destructured, so it is returned as a tuple.
+        assert!(py_file.contains("def divmod(a: int64, b: int64) -> tuple[int64, int64]:"));
+        assert!(py_file.contains("return a // b, a % b"));
+        assert!(py_file.contains("q, r = divmod(int64(7), int64(2))"));
+        assert!(py_file.contains("_, rest = divmod(int64(17), int64(5))"));
+        // Named structs stay classes.
+        assert!(py_file.contains("x: int64 = x_y.x"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dyn_box() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/dyn_box.monoteny")?;
+        assert!(py_file.contains("def _dyn_box(value, *vtable):"));
+        assert!(py_file.contains("_list_get(list_, idx)[0]()"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_equality() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/string_equality.monoteny")?;
+        assert!(py_file.contains("return (needle == first) or (needle == second)"));
+        assert!(py_file.contains("if lhs != rhs:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn min_max() -> RResult<()> {
+        let py_file = test_transpiles_with_config("test-code/differential/min_max.monoteny", &transpiler::Config::with_optimization_level(0))?;
+        // Primitive instantiations call python's builtins; strings use the transpiled generic.
+        assert!(py_file.contains("min(int32(3), int32(5))"));
+        assert!(py_file.contains("max(int64(3), int64(5))"));
+        assert!(py_file.contains("return min(max(value, lower), upper)"));
+        assert!(py_file.contains("def min_(lhs: str, rhs: str) -> str:"));
+        assert!(!py_file.contains("lhs: int32"));
+
+        // Clamp is inlined, leaving only the builtins.
+        let py_file = test_transpiles("test-code/differential/min_max.monoteny")?;
+        assert!(py_file.contains("min(max(int32(12), int32(0)), int32(10))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_to_string() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/list_to_string.monoteny")?;
+        // Each element type gets its own copy of the conformance, which writes the elements its own way.
+        assert!(py_file.contains("return str(_list_get(list_, start))"));
+        assert!(py_file.contains("return _list_get(list_, start)"));
+        assert_eq!(py_file.matches("def _list_to_string_range_").count(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn json() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/json.monoteny")?;
+        // Escaping is transpiled from common.json, so python writes the same JSON as the interpreter.
+        assert!(py_file.contains("def _json_escape_char(char: str) -> str:"));
+        assert!(py_file.contains("return \"{\\\"x\\\": \" + (str(self.x) + (\", \\\"y\\\": \" + (str(self.y) + \"}\")))"));
+        assert!(!py_file.contains("json.dumps"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn json_parse() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/json_parse.monoteny")?;
+        // json.loads is wrapped, so the accessors behave like the interpreter's.
+        assert!(py_file.contains("class _Json:"));
+        assert!(py_file.contains("return _Json(json.loads(string, parse_constant=reject_constant))"));
+        assert!(py_file.contains("json: _Json = _parse_json(to_json_0(label))"));
+        assert!(py_file.contains("json.get(\"position\").get(\"x\").as_float() == float64(3.0)"));
+        assert!(py_file.contains("\"array\" if isinstance(self.value, list) else \"object\""));
 
         Ok(())
     }
@@ -101,6 +555,26 @@ mod tests {
     #[test]
     fn trait_fields() -> RResult<()> {
         let py_file = test_transpiles("test-code/traits/fields.monoteny")?;
+        assert!(py_file.contains("animal: Animal = Animal(species=\"Cat\", name=\"Noir\", height_cm=float32(180))"));
+        assert!(py_file.contains("animal.height_cm = float32(25)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn generic_struct() -> RResult<()> {
+        let py_file = test_transpiles("test-code/traits/generic_struct.monoteny")?;
+        assert!(py_file.contains("class Pair_Int32_String:\n    first: int32\n    second: str"));
+        assert!(py_file.contains("Pair_String_Int32(first=\"Two\", second=int32(2))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn struct_where_clause() -> RResult<()> {
+        let py_file = test_transpiles("test-code/traits/struct_where_clause.monoteny")?;
+        assert!(py_file.contains("def is_empty_0(self: Bounds_Int32) -> bool:"));
+        assert!(py_file.contains("def is_empty_1(self: Bounds_Float32) -> bool:"));
 
         Ok(())
     }
@@ -108,6 +582,62 @@ mod tests {
     #[test]
     fn string_interpolation() -> RResult<()> {
         let py_file = test_transpiles("test-code/grammar/string_interpolation.monoteny")?;
+        assert!(py_file.contains("print(\"Left: \" + (\"String\" + (\", Right: \" + str(int32(2)))))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowed_interpolation() -> RResult<()> {
+        let py_file = test_transpiles("test-code/grammar/shadowed_interpolation.monoteny")?;
+        assert!(py_file.contains("str(int32(3))"));
+
+        Ok(())
+    }
+
+    /// Ascriptions only guide inference; they leave nothing in the output.
+    #[test]
+    fn ascription() -> RResult<()> {
+        let py_file = test_transpiles("test-code/types/ascription.monoteny")?;
+        assert!(py_file.contains("print(str((int64(1) + int64(2)) * int64(3)))"));
+        assert!(!py_file.contains("Int64"));
+        Ok(())
+    }
+
+    #[test]
+    fn type_test() -> RResult<()> {
+        let py_file = test_transpiles("test-code/types/type_test.monoteny")?;
+        // Builtin types are known after monomorphization, but generated classes are tested for.
+        assert!(py_file.contains("if True:"));
+        assert!(py_file.contains("if False:"));
+        assert!(py_file.contains("return isinstance(value, Point)"));
+        assert!(py_file.contains("return value * int32(2)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_cast() -> RResult<()> {
+        let py_file = test_transpiles("test-code/types/failed_cast.monoteny")?;
+        assert!(py_file.contains("raise TypeError(message)"));
+        assert!(py_file.contains("_cast_error(\"Cannot cast Int32 to Bool.\")"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolate_primitives() -> RResult<()> {
+        let py_file = test_transpiles("test-code/grammar/interpolate_primitives.monoteny")?;
+        // Python would capitalize the bool.
+        assert!(py_file.contains("\"Flag: \" + (_bool_to_string(flag) + "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interpolate_conformance() -> RResult<()> {
+        let py_file = test_transpiles("test-code/grammar/interpolate_conformance.monoteny")?;
+        assert!(py_file.contains("\"Point: \" + to_string(point)"));
 
         Ok(())
     }
@@ -115,6 +645,8 @@ mod tests {
     #[test]
     fn if_then_else() -> RResult<()> {
         let py_file = test_transpiles("test-code/control_flow/if_then_else.monoteny")?;
+        // The nested if in the else branch becomes an elif.
+        assert!(py_file.contains("    if True:\n        print(\"true\")\n    elif False:\n        print(\"false\")\n    else:\n        print(\"maybe\")\n"));
 
         Ok(())
     }
@@ -122,6 +654,412 @@ mod tests {
     #[test]
     fn and_or() -> RResult<()> {
         let py_file = test_transpiles("test-code/control_flow/and_or.monoteny")?;
+        assert!(py_file.contains("if False and True:"));
+        assert!(py_file.contains("if True or False:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn list() -> RResult<()> {
+        let py_file = test_transpiles("test-code/collections/list.monoteny")?;
+        assert!(py_file.contains("list()"));
+        assert!(py_file.contains(".append("));
+        assert!(py_file.contains(".pop()"));
+        assert!(py_file.contains("len("));
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_sum() -> RResult<()> {
+        let py_file = test_transpiles("test-code/differential/list_sum.monoteny")?;
+        assert!(py_file.contains("def fill(list_: list, start: int64, end: int64):"));
+        assert!(py_file.contains("nested: list = list()"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_bounds() -> RResult<()> {
+        // Negative indices are rejected, since native indexing would wrap them around.
+        let py_file = test_transpiles("test-code/collections/list_negative_index.monoteny")?;
+        assert!(py_file.contains("def _list_set(sequence, index, value):\n    if index < 0:\n"));
+        assert!(py_file.contains("_list_set(list_, int64(-1), int64(2))"));
+        assert!(!py_file.contains("list_[int64(-1)]"));
+
+        let config = transpiler::Config { should_match_interpreter_errors: true, ..Default::default() };
+        let py_file = test_transpiles_with_config("test-code/collections/list_negative_index.monoteny", &config)?;
+        assert!(py_file.contains("def _list_set(sequence, index, value):"));
+        assert!(py_file.contains("_list_set(list_, int64(-1), int64(2))"));
+        assert!(!py_file.contains("def _list_get"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn int_pow() -> RResult<()> {
+        // Python would produce a float for negative exponents, so integer powers use a helper.
+        let py_file = test_transpiles("test-code/primitives/int_pow.monoteny")?;
+        assert_eq!(py_file.matches("def _int_pow(base, exponent):").count(), 1);
+        assert!(py_file.contains("_int_pow(int8(2), int8(7))"));
+        assert!(py_file.contains("float64(2.0) ** float64(-1.0)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn char() -> RResult<()> {
+        let py_file = test_transpiles("test-code/primitives/char.monoteny")?;
+        assert!(py_file.contains("letter: str = \"é\""));
+        assert!(py_file.contains("ord(letter)"));
+        // Python's chr accepts surrogates, so a helper rejects them like the interpreter.
+        assert!(py_file.contains("def _chr(code):\n    if 0xD800 <= code <= 0xDFFF or code > 0x10FFFF:\n"));
+        assert!(py_file.contains("_chr(code)"));
+        assert!(py_file.contains("list(\"héllo\")"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn not() -> RResult<()> {
+        let py_file = test_transpiles("test-code/primitives/not.monoteny")?;
+        assert!(py_file.contains("not (not a)"));
+        assert!(py_file.contains("(not a) == b"));
+        assert!(py_file.contains("not (a and b)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn negative_literals() -> RResult<()> {
+        let py_file = test_transpiles("test-code/literals/in_range.monoteny")?;
+        assert!(py_file.contains("int8(-128)"));
+        assert!(py_file.contains("int64(-9223372036854775808)"));
+        assert!(py_file.contains("float64(-1.5)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_literal() -> RResult<()> {
+        // Without constant folding, so the parse functions aren't substituted.
+        let py_file = test_transpiles_with_config("test-code/literals/decimal.monoteny", &transpiler::Config::with_optimization_level(1))?;
+        // The user's parse functions receive the literal text; only primitives are parsed in advance.
+        assert!(py_file.contains("price: Decimal = parse_real_literal(\"3.5\")"));
+        assert!(py_file.contains("count: Decimal = parse_int_literal(\"2\")"));
+        assert!(py_file.contains("def parse_real_literal(literal: str) -> Decimal:"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn optimization_levels() -> RResult<()> {
+        let path = "test-code/refactor/optimize.monoteny";
+
+        let py_file = test_transpiles_with_config(path, &transpiler::Config::with_optimization_level(0))?;
+        assert!(py_file.contains("def print_greeting("));
+        assert!(py_file.contains("greeting: str = \"Hello World!\""));
+
+        // The wrapper function is inlined.
+        let py_file = test_transpiles_with_config(path, &transpiler::Config::with_optimization_level(1))?;
+        assert!(!py_file.contains("def print_greeting("));
+        assert!(py_file.contains("greeting: str = \"Hello World!\""));
+
+        // The constant is propagated.
+        let py_file = test_transpiles_with_config(path, &transpiler::Config::with_optimization_level(2))?;
+        assert!(!py_file.contains("def print_greeting("));
+        assert!(py_file.contains("print(\"Hello World!\")"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn one_liners() -> RResult<()> {
+        let path = "test-code/refactor/one_liners.monoteny";
+
+        // The one-liners aren't trivial calls, so they are kept.
+        let py_file = test_transpiles_with_config(path, &transpiler::Config::with_optimization_level(1))?;
+        assert!(py_file.contains("def double(x: int64) -> int64:"));
+        assert!(py_file.contains("return double(quadruple(x))"));
+
+        // With constant folding, the chain of one-liners collapses into main.
+        let py_file = test_transpiles_with_config(path, &transpiler::Config::with_optimization_level(2))?;
+        assert!(!py_file.contains("def double("));
+        assert!(!py_file.contains("def quadruple("));
+        assert!(!py_file.contains("def octuple("));
+        assert!(py_file.contains("print(str(int64(5) * int64(2) * int64(2) * int64(2)))"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mut_parameter() -> RResult<()> {
+        let py_file = test_transpiles("test-code/mutability/mut_parameter.monoteny")?;
+        // The non-mut parameter is copied before it's modified.
+        assert!(py_file.contains("copy.copy(animal)"));
+
+        Ok(())
+    }
+
+    /// Asserts the program fails because of the immutable argument, which is found in the source as part of the call.
+    fn assert_immutable_argument(path: &str, parameter: &str, call: &str, argument: &str) {
+        let errors = test_transpiles(path).unwrap_err();
+        assert_eq!(errors[0].title, format!("Cannot pass an immutable value to mut parameter '{}'.", parameter));
+        assert_eq!(errors[0].code, Some(ErrorCode::ImmutableToMutParameter));
+
+        let start = fs::read_to_string(path).unwrap().find(call).unwrap() + call.find(argument).unwrap();
+        assert_eq!(errors[0].range, Some(start..start + argument.len()));
+    }
+
+    #[test]
+    fn immutable_argument() {
+        assert_immutable_argument("test-code/mutability/immutable_argument.monoteny", "animal", "grow(animal)", "animal");
+        // Fields are as mutable as the object they are read from.
+        assert_immutable_argument("test-code/mutability/immutable_field_argument.monoteny", "animal", "grow(zoo.animal)", "zoo.animal");
+    }
+
+    #[test]
+    fn compound_assignment() -> RResult<()> {
+        let py_file = test_transpiles("test-code/mutability/compound_assignment.monoteny")?;
+        assert!(py_file.contains("count += int32(5)\n    count -= int32(3)\n    count *= int32(4)\n    count //= int32(6)\n"));
+        assert!(py_file.contains("counter.count = counter.count + int32(2)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn deferred_assignment() -> RResult<()> {
+        let py_file = test_transpiles("test-code/mutability/deferred_assignment.monoteny")?;
+        // Nothing is declared before the branches assign it.
+        assert!(py_file.contains("    if value < int64(0):\n        name: str = \"negative\"\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reflection() -> RResult<()> {
+        let py_file = test_transpiles("test-code/reflection/debug_print.monoteny")?;
+        // Reflection is substituted by literals; nothing is left to call.
+        assert!(py_file.contains("print(\"Point\" + (\": \" + to_string(Point("));
+        assert!(py_file.contains("fields: list = [\"x\", \"y\"]"));
+        assert!(!py_file.contains("type_name"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutate_parameter() {
+        // Setting a field passes the object to the setter's mut self.
+        assert_immutable_argument("test-code/mutability/mutate_parameter.monoteny", "self", "upd animal", "animal");
+    }
+
+    #[test]
+    fn return_mismatch() {
+        let errors = test_transpiles("test-code/control_flow/return_mismatch.monoteny").unwrap_err();
+        let notes = errors.iter().flat_map(|e| e.notes.iter().map(|n| n.title.clone())).collect_vec();
+        assert!(notes.contains(&"Return type was first constrained to Int64 here.".to_string()));
+        assert!(notes.contains(&"Return type declared as Int64 here.".to_string()));
+    }
+
+    #[test]
+    fn blocks() -> RResult<()> {
+        let py_file = test_transpiles("test-code/control_flow/blocks.monoteny")?;
+        assert!(py_file.contains("if True:\n        return\n"));
+        assert!(py_file.contains("twice: int32 = value * int32(2)\n    return twice"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_arguments() -> RResult<()> {
+        // Without inlining, so the blocks stay arguments.
+        let py_file = test_transpiles_with_config("test-code/control_flow/block_arguments.monoteny", &transpiler::Config::with_optimization_level(0))?;
+        assert!(py_file.contains("doubled: int32 = add(int32(2), int32(2))\n    block_value_0: int32 = _multiply(doubled, int32(2))\n    result: int32 = increment(block_value_0)\n"));
+        assert!(py_file.contains("(add(block_value_1, increment(block_value_2)))"));
+        // The block in the elif's condition can't be evaluated before the if.
+        assert!(py_file.contains("    else:\n        scaled: int32 = _multiply(result, int32(20))\n        block_value_3: int32 = scaled\n        if is_large(block_value_3):\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shadowing() -> RResult<()> {
+        let py_file = test_transpiles_with_config("test-code/control_flow/shadowing.monoteny", &transpiler::Config::with_optimization_level(0))?;
+        // Each let gets its own name, so earlier reads keep referring to the earlier variable.
+        assert!(py_file.contains("    doubled: int32 = _multiply(count_0, int32(2))\n"));
+        assert!(py_file.contains("    count_2: int32 = _add(count_2, int32(1))\n"));
+        assert!(py_file.contains("    write_line_0(\"outer \" + format_1(count_1))\n"));
+        assert!(py_file.contains("    snapshot: int32 = total_0\n    total_1: int32 = _add(total_0, int32(10))\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable() -> RResult<()> {
+        let py_file = test_transpiles("test-code/control_flow/unreachable.monoteny")?;
+        assert!(py_file.contains("    return int32(42)\n\n"));
+        assert!(!py_file.contains("Unreachable"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_without_value() {
+        let errors = test_transpiles("test-code/control_flow/block_without_value.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Block used as a value must end in an expression.");
+    }
+
+    #[test]
+    fn empty_blocks() -> RResult<()> {
+        let py_file = test_transpiles("test-code/control_flow/empty_blocks.monoteny")?;
+        assert!(py_file.contains("    if True:\n        pass\n"));
+        assert!(py_file.contains("    \"\"\"\n    pass\n"));
+        // Calls to empty functions are removed, but their arguments are still evaluated.
+        assert!(py_file.contains("    effect()\n    if True:\n        effect()\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_body() {
+        let errors = test_transpiles("test-code/control_flow/empty_body.monoteny").unwrap_err();
+        assert_eq!(errors[0].title, "Function declares a return type but has an empty body.");
+    }
+
+    #[test]
+    fn shared_expressions() -> RResult<()> {
+        fn count_main_expressions(config: &transpiler::Config) -> RResult<usize> {
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+
+            let module = runtime.load_file_as_module(&PathBuf::from("test-code/refactor/shared_expressions.monoteny"), module_name("main"))?;
+            let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+            let TranspiledArtifact::Function(implementation) = transpiler.exported_artifacts.into_iter().exactly_one().ok().unwrap();
+            let head = Arc::clone(&implementation.head);
+
+            let mut refactor = Refactor::new(&mut runtime);
+            let representation = refactor.runtime.source.fn_representations[&head].clone();
+            refactor.add(implementation, representation);
+            Simplify::new(&mut refactor, config).run();
+
+            Ok(refactor.fn_logic[&head].as_implementation()?.expression_tree.values.len())
+        }
+
+        let unshared_count = count_main_expressions(&transpiler::Config::with_optimization_level(1))?;
+        let shared_count = count_main_expressions(&transpiler::Config::with_optimization_level(2))?;
+        // str((2 + 3) * 4) is shared as a whole, (2 + 3) with the last line.
+        assert_eq!(unshared_count - shared_count, 8);
+
+        let py_file = test_transpiles("test-code/refactor/shared_expressions.monoteny")?;
+        assert_eq!(py_file.match_indices("int64(2) + int64(3)").count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn purity() -> RResult<()> {
+        let py_file = test_transpiles("test-code/refactor/purity.monoteny")?;
+        // Pure calls are shared, impure ones are not.
+        assert_eq!(py_file.match_indices(" = cube(").count(), 1);
+        assert_eq!(py_file.match_indices("loud_cube(shared_1)").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn lazy_constants() -> RResult<()> {
+        let py_file = test_transpiles("test-code/globals/lazy_constants.monoteny")?;
+        // Like in the VM, the constant is evaluated on first use only.
+        assert!(py_file.contains("import functools\n"));
+        assert!(py_file.contains("@functools.cache\ndef greeting() -> str:\n"));
+        assert_eq!(py_file.match_indices("print(greeting())").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn guarded_division() -> RResult<()> {
+        let py_file = test_transpiles("test-code/refactor/guarded_division.monoteny")?;
+        // Divisions may fail, so they're not shared. Other expressions are shared inside the guard.
+        assert_eq!(py_file.match_indices("int64(100) // b").count(), 2);
+        let guard = py_file.find("if b != int64(0):").unwrap();
+        assert!(py_file.find("shared: str = ").unwrap() > guard);
+
+        Ok(())
+    }
+
+    #[test]
+    fn guarded_recursion() -> RResult<()> {
+        let py_file = test_transpiles("test-code/refactor/guarded_recursion.monoteny")?;
+        // Hoisting the recursive call out of the guard would recurse forever.
+        assert!(!py_file.contains("shared"));
+        assert_eq!(py_file.match_indices("count(n - int64(1))").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stable_output() -> RResult<()> {
+        // Hash maps are seeded per instance, so nondeterministic ordering shows up within one process too.
+        for path in ["test-code/refactor/shared_expressions.monoteny", "test-code/traits/fields.monoteny"] {
+            let py_file = test_transpiles(path)?;
+            for _ in 0..4 {
+                assert_eq!(test_transpiles(path)?, py_file);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn explain_inlining() -> RResult<()> {
+        let mut runtime = Runtime::new_cached()?;
+        runtime.repository.add("common", PathBuf::from("monoteny"));
+
+        let module = runtime.load_file_as_module(&PathBuf::from("test-code/hello_world.monoteny"), module_name("main"))?;
+        let context = transpiler::python::Context::new(&runtime);
+
+        let config = transpiler::Config { should_explain_inlining: true, ..Default::default() };
+        let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::stdout())?;
+        let file_map = transpiler::transpile(*transpiler, &mut runtime, &context, &config, "main")?;
+
+        let report = &file_map["main.inlining.json"];
+        assert!(report.contains("\"function\": \"main()\", "));
+        assert!(report.contains("\"decision\": \"kept_explicit\", \"rewritten_callers\": []"));
+        assert!(report.contains("\"decision\": \"inlined\", \"hint\": {\"replace_call\": \"_write_line(value 'String)\", \"arguments\": [0]}, \"rewritten_callers\": [\"main()\"]"));
+
+        Ok(())
+    }
+
+    /// Explains the inlining of every fixture that transpiles.
+    /// Fixtures that fail to transpile are covered by their own tests.
+    #[test]
+    fn explain_inlining_fixtures() -> RResult<()> {
+        let sources = files_in(Path::new("test-code")).into_iter()
+            .filter(|path| path.extension().is_some_and(|extension| extension == "monoteny"))
+            .filter(|path| fs::read_to_string(path).unwrap().contains("def transpile!"))
+            .sorted()
+            .collect_vec();
+        assert!(sources.contains(&PathBuf::from("test-code/traits/conformance.monoteny")));
+
+        let config = transpiler::Config { should_explain_inlining: true, ..Default::default() };
+        for source in sources {
+            let mut runtime = Runtime::new_cached()?;
+            runtime.repository.add("common", PathBuf::from("monoteny"));
+            runtime.repository.add("transpile", PathBuf::from("test-code"));
+
+            let Ok(module) = runtime.load_file_as_module(&source, module_name("main")) else {
+                continue
+            };
+            let context = transpiler::python::Context::new(&runtime);
+            let Ok(transpiler) = interpreter::run::transpile(&module, &mut runtime, &mut std::io::sink()) else {
+                continue
+            };
+            if let Ok(file_map) = transpiler::transpile(*transpiler, &mut runtime, &context, &config, "main") {
+                assert!(file_map.contains_key("main.inlining.json"), "{} has no inlining report", source.display());
+            }
+        }
 
         Ok(())
     }
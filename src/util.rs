@@ -5,5 +5,9 @@ pub mod position;
 pub mod iter;
 pub mod vec;
 pub mod strings;
+pub mod json;
+#[cfg(feature = "native")]
 pub mod file_writer;
+#[cfg(feature = "native")]
+pub mod glob;
 pub mod graphs;
@@ -9,8 +9,11 @@ pub fn write_file_safe(base_path: &Path, sub_path: &str, content: &str) -> PathB
         panic!("Tried to write a file in unexpected directory: {}", file_path.as_os_str().to_string_lossy());
     }
 
+    if let Some(directory) = file_path.parent() {
+        std::fs::create_dir_all(directory).expect("Unable to create directory");
+    }
     let mut f = File::create(file_path.clone()).expect("Unable to create file");
-    let f: &mut (dyn Write) = &mut f;
+    let f: &mut dyn Write  = &mut f;
     write!(f, "{}", content).expect("Error writing file");
 
     file_path
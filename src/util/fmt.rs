@@ -8,7 +8,7 @@ pub fn fmta<F: Fn(&mut Formatter) -> std::fmt::Result>(fun: F) -> String {
 
     impl<F: Fn(&mut Formatter) -> std::fmt::Result> Display for Mock<F> {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            (&self.fun)(f)
+            (self.fun)(f)
         }
     }
 
@@ -0,0 +1,59 @@
+use std::path::{Component, Path, PathBuf};
+
+use itertools::Itertools;
+use regex::Regex;
+
+/// Expands a path pattern to the files it matches, sorted.
+/// `*` and `?` match within one path component, `**` matches any number of directories.
+/// Paths without wildcards are returned as they are, even if they don't exist, so reading them can fail later.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let is_wildcard = |component: &Component| component.as_os_str().to_string_lossy().contains(['*', '?']);
+    if !pattern.components().any(|component| is_wildcard(&component)) {
+        return vec![pattern.to_path_buf()]
+    }
+
+    let base: PathBuf = pattern.components().take_while(|component| !is_wildcard(component)).collect();
+    let regex = Regex::new(&format!("^{}$", to_regex(&pattern.to_string_lossy()))).unwrap();
+
+    let mut files = vec![];
+    collect_files(if base.as_os_str().is_empty() { Path::new(".") } else { &base }, &mut files);
+    files.into_iter()
+        .map(|path| path.strip_prefix(".").map(Path::to_path_buf).unwrap_or(path))
+        .filter(|path| regex.is_match(&path.to_string_lossy()))
+        .sorted()
+        .collect_vec()
+}
+
+fn to_regex(pattern: &str) -> String {
+    let mut regex = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // Like in shells, **/ also matches no directory at all.
+                match chars.next_if_eq(&'/') {
+                    Some(_) => regex.push_str("(.*/)?"),
+                    None => regex.push_str(".*"),
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            _ => regex.push_str(&regex::escape(&char.to_string())),
+        }
+    }
+    regex
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match path.is_dir() {
+            true => collect_files(&path, files),
+            false => files.push(path),
+        }
+    }
+}
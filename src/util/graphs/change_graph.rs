@@ -10,6 +10,12 @@ pub struct ChangeGraph<I: Eq + Hash + Clone> {
     pub dependents: HashMap<I, HashSet<I>>,
 }
 
+impl<I: Eq + Hash + Clone> Default for ChangeGraph<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<I: Eq + Hash + Clone> ChangeGraph<I> {
     pub fn new() -> ChangeGraph<I> {
         ChangeGraph {
@@ -61,7 +61,7 @@ impl<Key: Hash + Eq + Clone, Value> NodeTree<Key, Value> {
         }
     }
 
-    pub fn swizzle_arguments(&mut self, expression_id: Key, swizzle: &Vec<usize>) {
+    pub fn swizzle_arguments(&mut self, expression_id: Key, swizzle: &[usize]) {
         let args = self.children.get_mut(&expression_id).unwrap();
         let removed = vec::swizzle(args, swizzle);
         self.truncate_down(removed);
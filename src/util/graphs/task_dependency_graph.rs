@@ -10,6 +10,12 @@ pub struct TaskDependencyGraph<I: Eq + Hash + Clone> {
     pub dependents: HashMap<I, HashSet<I>>,
 }
 
+impl<I: Eq + Hash + Clone> Default for TaskDependencyGraph<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<I: Eq + Hash + Clone> TaskDependencyGraph<I> {
     pub fn new() -> TaskDependencyGraph<I> {
         TaskDependencyGraph {
@@ -0,0 +1,18 @@
+use std::fmt::Write;
+
+/// Writes the string as a quoted json string literal.
+pub fn write_string(output: &mut String, string: &str) {
+    output.push('"');
+    for char in string.chars() {
+        match char {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            char if (char as u32) < 0x20 => write!(output, "\\u{:04x}", char as u32).unwrap(),
+            char => output.push(char),
+        }
+    }
+    output.push('"');
+}
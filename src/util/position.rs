@@ -1,4 +1,5 @@
 use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -23,6 +24,13 @@ pub fn positioned<V>(v: V, start: usize, end: usize) -> Positioned<V> {
     }
 }
 
+/// Only the value is hashed, so that code hashes the same wherever it is.
+impl<V: Hash> Hash for Positioned<V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state)
+    }
+}
+
 impl<V: Display> Display for Positioned<V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.value)
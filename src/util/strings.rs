@@ -10,3 +10,38 @@ pub fn map_chars(string: &str, fun: impl Fn(char) -> Option<&'static str>) -> St
     }
     output
 }
+
+/// The number of character insertions, deletions, substitutions and adjacent swaps needed to turn a into b.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // distances[i][j] is the distance between the first i chars of a and the first j chars of b.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() { row[0] = i; }
+    for (j, distance) in distances[0].iter_mut().enumerate() { *distance = j; }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution_cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            distances[i][j] = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + substitution_cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distances[i][j] = distances[i][j].min(distances[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+/// The candidate closest to the string, if it's close enough to be a likely typo.
+pub fn closest_match<'a>(string: &str, candidates: impl Iterator<Item=&'a str>) -> Option<&'a str> {
+    let max_distance = (string.chars().count() / 3).max(1);
+    candidates
+        .map(|candidate| (edit_distance(string, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
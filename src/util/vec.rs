@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-pub fn swizzle<A>(vec: &mut Vec<A>, swizzle: &Vec<usize>) -> Vec<A> {
+pub fn swizzle<A>(vec: &mut Vec<A>, swizzle: &[usize]) -> Vec<A> {
     if swizzle.is_empty() {
         return vec.drain(..).collect_vec()
     }
@@ -11,7 +11,7 @@ pub fn swizzle<A>(vec: &mut Vec<A>, swizzle: &Vec<usize>) -> Vec<A> {
     let mut tmp_array = vec.drain(..).enumerate()
         .map(|(idx, obj)| (swizzle.iter().position(|p| p == &idx).unwrap_or(usize::MAX), obj))
         .collect_vec();
-    tmp_array.sort_by_key(|(idx, obj)| *idx);
+    tmp_array.sort_by_key(|(idx, _obj)| *idx);
     let mut removed = vec![];
     for (idx, obj) in tmp_array {
         if idx != usize::MAX {
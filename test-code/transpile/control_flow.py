@@ -0,0 +1,37 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    a: bool = True
+    b: bool = False
+    if a and (not b):
+        print("a")
+    else:
+        print("b")
+    if a or b:
+        pass
+    value: int32 = int32(21) * int32(2)
+    print(str(value))
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+__all__ = [
+    "main",
+]
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,69 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    print(str(square_0(int32(3))) + (" " + str(hypotenuse_squared(float64(3), float64(4)))))
+
+
+def hypotenuse_squared(a: float64, b: float64) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        a: <TODO>
+        b: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return square_1(a) + square_1(b)
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+def square_0(value: int32) -> int32:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value
+
+
+def square_1(value: float64) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value
+
+
+__all__ = [
+    "hypotenuse_squared",
+    "main",
+]
+
+
+if __name__ == "__main__":
+    main()
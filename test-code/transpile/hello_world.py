@@ -0,0 +1,28 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    print("Hello World!")
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+__all__ = [
+    "main",
+]
+
+
+if __name__ == "__main__":
+    main()
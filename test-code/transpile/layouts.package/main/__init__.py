@@ -0,0 +1,27 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+from .transpile.layouts.shapes import Square, surface, volume
+from .transpile.layouts.units import cubed_0
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    square: Square = Square(side=float64(2))
+    print(str(surface(square)) + (" " + (str(volume(square)) + (" " + str(cubed_0(int32(3)))))))
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+__all__ = [
+    "main",
+]
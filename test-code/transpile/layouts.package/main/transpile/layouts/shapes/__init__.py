@@ -0,0 +1,54 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+from ....transpile.layouts.units import cubed_0, cubed_1
+
+
+@dataclass
+class Square:
+    side: float64
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+def surface(square: Square) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        square: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return square.side * square.side * float64(6)
+
+
+def volume(square: Square) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        square: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    side: float64 = square.side
+    return cubed_1(side)
+
+
+__all__ = [
+    "Square",
+    "cubed_0",
+    "cubed_1",
+    "surface",
+    "volume",
+]
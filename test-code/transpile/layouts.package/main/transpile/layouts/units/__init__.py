@@ -0,0 +1,44 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+def cubed_0(value: int32) -> int32:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value * value
+
+
+def cubed_1(value: float64) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value * value
+
+
+__all__ = [
+    "cubed_0",
+    "cubed_1",
+]
@@ -0,0 +1,88 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+@dataclass
+class Square:
+    side: float64
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    square: Square = Square(side=float64(2))
+    print(str(surface(square)) + (" " + (str(volume(square)) + (" " + str(cubed_0(int32(3)))))))
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+def surface(square: Square) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        square: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return square.side * square.side * float64(6)
+
+
+def volume(square: Square) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        square: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    side: float64 = square.side
+    return cubed_1(side)
+
+
+def cubed_0(value: int32) -> int32:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value * value
+
+
+def cubed_1(value: float64) -> float64:
+    """
+    <DOCSTRING TODO>
+
+    Args:
+        value: <TODO>
+
+    Returns:
+        <TODO>
+    """
+    return value * value * value
+
+
+__all__ = [
+    "Square",
+    "main",
+]
+
+
+if __name__ == "__main__":
+    main()
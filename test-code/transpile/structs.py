@@ -0,0 +1,37 @@
+import numpy as np
+import math
+import copy
+import operator as op
+from dataclasses import dataclass
+from numpy import int8, int16, int32, int64, uint8, uint16, uint32, uint64, float32, float64
+from typing import Any, Callable
+
+
+@dataclass
+class Counter:
+    name: str
+    count: int32
+
+
+def main():
+    """
+    <DOCSTRING TODO>
+    """
+    counter: Counter = Counter(name="Clicks", count=int32(0))
+    counter.count = counter.count + int32(1)
+    print(counter.name + (" = " + str(counter.count)))
+
+
+# ========================== ======== ============================
+# ========================== Internal ============================
+# ========================== ======== ============================
+
+
+__all__ = [
+    "Counter",
+    "main",
+]
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,169 @@
+use std::process::Command;
+
+/// Minimal json validator; returns the remaining input after one value.
+fn parse_value(input: &str) -> Result<&str, String> {
+    let input = input.trim_start();
+    match input.chars().next() {
+        Some('{') => {
+            let mut rest = input[1..].trim_start();
+            if let Some(rest) = rest.strip_prefix('}') {
+                return Ok(rest)
+            }
+            loop {
+                rest = parse_string(rest.trim_start())?;
+                rest = rest.trim_start().strip_prefix(':').ok_or("Expected ':'")?;
+                rest = parse_value(rest)?.trim_start();
+                if let Some(r) = rest.strip_prefix(',') { rest = r; continue }
+                return rest.strip_prefix('}').ok_or("Expected '}'".to_string())
+            }
+        }
+        Some('[') => {
+            let mut rest = input[1..].trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok(rest)
+            }
+            loop {
+                rest = parse_value(rest)?.trim_start();
+                if let Some(r) = rest.strip_prefix(',') { rest = r; continue }
+                return rest.strip_prefix(']').ok_or("Expected ']'".to_string())
+            }
+        }
+        Some('"') => parse_string(input),
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            Ok(input.trim_start_matches(|c: char| c == '-' || c == '.' || c == 'e' || c == 'E' || c == '+' || c.is_ascii_digit()))
+        }
+        _ => ["true", "false", "null"].iter()
+            .find_map(|literal| input.strip_prefix(literal))
+            .ok_or(format!("Unexpected input: {:?}", input.chars().take(20).collect::<String>())),
+    }
+}
+
+fn parse_string(input: &str) -> Result<&str, String> {
+    let mut chars = input.strip_prefix('"').ok_or("Expected '\"'")?.char_indices();
+    while let Some((idx, char)) = chars.next() {
+        match char {
+            '"' => return Ok(&input[idx + 2..]),
+            '\\' => { chars.next(); },
+            c if (c as u32) < 0x20 => return Err("Unescaped control character".to_string()),
+            _ => {},
+        }
+    }
+    Err("Unterminated string".to_string())
+}
+
+#[test]
+fn check_json_with_verbosity() {
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["-v", "-v", "check", "--json", "test-code/hello_world.monoteny", "test-code/mutability/immutable_argument.monoteny"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rest = parse_value(&stdout).unwrap();
+    assert!(rest.trim().is_empty(), "Trailing output: {:?}", rest);
+    assert!(stdout.contains("\"errors\": []"));
+    assert!(stdout.contains("Cannot pass an immutable value to mut parameter"));
+    assert!(stdout.contains("\"code\": \"E0104\""));
+
+    // The logs went somewhere else.
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Loading module"));
+}
+
+#[test]
+fn run_eval() {
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["run", "-e", "write_line(\"Hello\");", "-e", "write_line(\"World\");"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Hello\nWorld\n");
+
+    // Errors point into the code, not the implicit main! around it.
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["run", "-e", "write_line(\"Hello\");", "-e", "write_line(x);"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Cannot find 'x' in this scope"));
+    assert!(stdout.contains("<eval>:2:12"));
+}
+
+#[test]
+fn explain_grammar() {
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["explain-grammar", "test-code/hello_world.monoteny"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("3. MultiplicationPrecedence (Left)\n    %  common.math._modulo\n    *  common.math._multiply\n"));
+    assert!(stdout.contains("Keywords in multiple groups:\n    +  LeftUnaryPrecedence, AdditionPrecedence\n    -  LeftUnaryPrecedence, AdditionPrecedence\n"));
+}
+
+#[test]
+fn explain() {
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["explain", "E0101"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("E0101\n\nNo function matches the call's name and arguments."));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["explain", "E9999"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Unknown error code: E9999"));
+}
+
+#[test]
+fn check_many_files() {
+    // The pattern is expanded by monoteny, not the shell.
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["-v", "check", "test-code/batch/*.monoteny"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Checked 2 file(s): 0 failed"));
+
+    // Both files are loaded, but the module they share only once.
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Loading module main from \"test-code/batch/first.monoteny\""));
+    assert!(stderr.contains("Loading module main from \"test-code/batch/second.monoteny\""));
+    assert_eq!(stderr.matches("Loading module common.math from").count(), 1);
+}
+
+#[test]
+fn run_with_embedded_std() {
+    // Without a monoteny directory, core and common are read from the binary.
+    let directory = std::env::temp_dir().join("monoteny-embedded-std");
+    std::fs::create_dir_all(&directory).unwrap();
+    let code = "write_line(\"\\(format(\"embedded\")) \\(\"a\" == \"a\")\");";
+
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["run", "-e", code])
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "embedded true\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_monoteny"))
+        .args(["run", "--no-embedded-std", "-e", code])
+        .current_dir(&directory)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("module `core` not found"));
+}
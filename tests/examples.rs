@@ -0,0 +1,79 @@
+//! Runs every program in examples/ and compares its output to the sibling .expected file.
+//! Set MONOTENY_BLESS to write the current output to the .expected files instead.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+
+use monoteny::error::{print_errors, RResult};
+use monoteny::interpreter;
+use monoteny::interpreter::runtime::Runtime;
+use monoteny::program::module::{Module, module_name};
+use monoteny::transpiler;
+use monoteny::transpiler::LanguageContext;
+
+fn load(path: &Path) -> RResult<(Box<Runtime>, Box<Module>)> {
+    let mut runtime = Runtime::new()?;
+    runtime.repository.add("common", PathBuf::from("monoteny"));
+    let module = runtime.load_file_as_module(&path.to_path_buf(), module_name("main"))?;
+    Ok((runtime, module))
+}
+
+fn run(path: &Path) -> RResult<String> {
+    let (mut runtime, module) = load(path)?;
+    let mut out: Vec<u8> = vec![];
+    interpreter::run::main(&module, &mut runtime, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn transpile(path: &Path) -> RResult<HashMap<String, String>> {
+    let (mut runtime, module) = load(path)?;
+    let context = transpiler::python::Context::new(&runtime);
+    let transpiler = interpreter::run::transpile(&module, &mut runtime, &mut std::io::sink())?;
+    transpiler::transpile(*transpiler, &mut runtime, &context, &transpiler::Config::default(), "main")
+}
+
+#[test]
+fn examples() {
+    let bless = std::env::var_os("MONOTENY_BLESS").is_some();
+    let sources = fs::read_dir("examples").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "monoteny"))
+        .sorted()
+        .collect_vec();
+    assert!(!sources.is_empty());
+
+    let mut failures = vec![];
+    for source in sources.iter() {
+        let output = match run(source) {
+            Ok(output) => output,
+            Err(errors) => {
+                print_errors(&errors);
+                failures.push(format!("{}: failed to run", source.display()));
+                continue
+            }
+        };
+
+        let expected_path = source.with_extension("expected");
+        if bless {
+            fs::write(&expected_path, &output).unwrap();
+        }
+        else if fs::read_to_string(&expected_path).ok().as_ref() != Some(&output) {
+            failures.push(format!("{}: output differs from {}:\n{}", source.display(), expected_path.display(), output));
+        }
+
+        // The transpiled code can't be run without python, but it should at least be generated the same way every time.
+        match (transpile(source), transpile(source)) {
+            (Ok(first), Ok(second)) if first == second && first.contains_key("main.py") => {},
+            (Ok(_), Ok(_)) => failures.push(format!("{}: transpiled differently twice", source.display())),
+            (Err(errors), _) | (_, Err(errors)) => {
+                print_errors(&errors);
+                failures.push(format!("{}: failed to transpile", source.display()));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "Examples failed:\n{}", failures.join("\n"));
+}
@@ -0,0 +1,81 @@
+//! Drives the C API through direct linkage. Run with `cargo test --features ffi`.
+#![cfg(feature = "ffi")]
+
+use std::ffi::c_void;
+
+use monoteny::ffi::*;
+
+extern "C" fn collect_output(user_data: *mut c_void, text: *const u8, len: usize) {
+    let out = unsafe { &mut *(user_data as *mut String) };
+    out.push_str(std::str::from_utf8(unsafe { std::slice::from_raw_parts(text, len) }).unwrap());
+}
+
+unsafe fn string(string: MonotenyString) -> String {
+    std::str::from_utf8(std::slice::from_raw_parts(string.ptr, string.len)).unwrap().to_string()
+}
+
+#[test]
+fn eval() {
+    unsafe {
+        let runtime = monoteny_runtime_new();
+        assert!(!runtime.is_null());
+
+        let mut out = String::new();
+        monoteny_set_output_callback(runtime, Some(collect_output), &mut out as *mut String as *mut c_void);
+
+        let source = "let a 'Int32 = 3;\nwrite_line(\"Hello \\(a)\");";
+        let mut err = MonotenyString { ptr: std::ptr::null(), len: 1 };
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::None);
+        assert_eq!(err.len, 0);
+        assert_eq!(out, "Hello 3\n");
+
+        // Errors are rendered relative to the evaluated source.
+        let source = "write_line(undefined);";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::Error);
+        let error = string(err);
+        assert_eq!(string(result.string), error);
+        assert!(error.contains("undefined"));
+        assert!(error.contains("<eval>"));
+
+        // The runtime can be used again after errors.
+        let source = "write_line(\"Again\");";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::None);
+        assert_eq!(out, "Hello 3\nAgain\n");
+
+        // The value of the last statement is returned.
+        let source = "write_line(\"Sum\");\n1 + 2;";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::Int);
+        assert_eq!(result.int_value, 3);
+        assert_eq!(out, "Hello 3\nAgain\nSum\n");
+
+        let source = "let a 'Float32 = 1.5;\na * 2;";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::Float);
+        assert_eq!(result.float_value, 3.0);
+
+        let source = "\"Hello \\(1 + 1)\";";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::String);
+        assert_eq!(string(result.string), "Hello 2");
+
+        let source = "true;";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::None);
+
+        // Panics are reported as errors, and don't unwind into the caller.
+        let source = "let a '#T = 1;";
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), &mut err);
+        assert_eq!(result.tag, MonotenyTag::Error);
+        assert!(string(err).starts_with("Internal error: "));
+
+        let source = [0xff_u8];
+        let result = monoteny_eval(runtime, source.as_ptr(), source.len(), std::ptr::null_mut());
+        assert_eq!(result.tag, MonotenyTag::Error);
+
+        monoteny_runtime_free(runtime);
+    }
+}